@@ -19,11 +19,15 @@
 
 use std::fmt::{Display, Formatter};
 use std::io::{self, Read};
-use std::{env, process};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
 
 use clap::{Arg, Command};
+use hime_redist::errors::ParseErrorDataTrait;
+use hime_redist::symbols::{SemanticElementTrait, SID_DOLLAR};
 use hime_sdk::errors::{Error, Errors};
-use hime_sdk::{CompilationTask, Input, Mode, Modifier, ParsingMethod, Runtime};
+use hime_sdk::grammars::{Grammar, Rule, RuleRef, SymbolRef, GENERATED_AXIOM};
+use hime_sdk::{CompilationTask, Input, LoadedData, Mode, Modifier, ParsingMethod, Runtime};
 use miette::{EyreContext, MietteHandler};
 
 /// The name of this program
@@ -122,9 +126,28 @@ pub fn main() -> miette::Result<()> {
                     "lr1",
                     "lalr1",
                     "rnglr1",
-                    "rnglalr1"
+                    "rnglalr1",
+                    "ielr1"
                 ])
         )
+        .arg(
+            Arg::new("expected_conflicts")
+                .value_name("COUNT")
+                .long("expect-conflicts")
+                .help("The exact number of shift/reduce conflicts to accept, resolved in favor of the shift; the compilation fails for any other number.")
+                .takes_value(true)
+                .validator(str::parse::<usize>)
+                .required(false)
+        )
+        .arg(
+            Arg::new("max_states")
+                .value_name("COUNT")
+                .long("max-states")
+                .help("The maximum number of states in the LR graphs, the compilation of a grammar fails when it is exceeded (defaults to 100000).")
+                .takes_value(true)
+                .validator(str::parse::<usize>)
+                .required(false)
+        )
         .arg(
             Arg::new("debug")
                 .long("debug")
@@ -132,6 +155,22 @@ pub fn main() -> miette::Result<()> {
                 .takes_value(false)
                 .required(false)
         )
+        .arg(
+            Arg::new("debug_max_lookaheads")
+                .value_name("COUNT")
+                .long("debug-max-lookaheads")
+                .help("The maximum number of lookaheads printed for an item in the debug data (default to 8).")
+                .takes_value(true)
+                .validator(str::parse::<usize>)
+                .required(false)
+        )
+        .arg(
+            Arg::new("debug_verbose")
+                .long("debug-verbose")
+                .help("Print all the lookaheads of the items in the debug data.")
+                .takes_value(false)
+                .required(false)
+        )
         .arg(
             Arg::new("rust_no_std")
                 .long("--no-std")
@@ -153,6 +192,13 @@ pub fn main() -> miette::Result<()> {
                 .takes_value(false)
                 .required(false)
         )
+        .arg(
+            Arg::new("rust_no_actions")
+                .long("--no-actions")
+                .help("Rust-only, indicates whether to omit the semantic actions plumbing in the generated code, even when the grammar declares semantic actions (default to false)")
+                .takes_value(false)
+                .required(false)
+        )
         .arg(
             Arg::new("grammar_name")
                 .value_name("GRAMMAR")
@@ -171,13 +217,148 @@ pub fn main() -> miette::Result<()> {
         .arg(
             Arg::new("inputs")
                 .value_name("INPUTS")
-                .help("The file names of the input grammars")
+                .help("The file names of the input grammars (gzip-compressed when ending with .gz)")
                 .takes_value(true)
                 .required(true)
                 .multiple_values(true)
         )
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("coverage")
+                .about("Compiles the target grammar in-memory, parses a corpus of inputs and reports the grammar rules that are never reduced")
+                .arg(
+                    Arg::new("grammar_name")
+                        .value_name("GRAMMAR")
+                        .short('g')
+                        .long("grammar")
+                        .help("The name of the grammar to compile if there are multiple.")
+                        .takes_value(true)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Output the coverage report as JSON")
+                        .takes_value(false)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("grammar")
+                        .value_name("GRAMMAR_FILE")
+                        .help("The file name of the input grammar (gzip-compressed when ending with .gz)")
+                        .takes_value(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("inputs")
+                        .value_name("INPUTS")
+                        .help("The file names of the inputs to parse")
+                        .takes_value(true)
+                        .required(true)
+                        .multiple_values(true)
+                )
+        )
+        .subcommand(
+            Command::new("lex")
+                .about("Compiles the target grammar in-memory and prints the tokens matched by its lexer in an input, with the lexical errors")
+                .arg(
+                    Arg::new("grammar_name")
+                        .value_name("GRAMMAR")
+                        .short('g')
+                        .long("grammar")
+                        .help("The name of the grammar to compile if there are multiple.")
+                        .takes_value(true)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Output the tokens and errors as JSON")
+                        .takes_value(false)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("symbols")
+                        .value_name("SYMBOLS")
+                        .short('s')
+                        .long("symbols")
+                        .help("Only output the tokens for these terminals, separated by commas")
+                        .takes_value(true)
+                        .use_value_delimiter(true)
+                        .required(false)
+                )
+                .arg(
+                    Arg::new("grammar")
+                        .value_name("GRAMMAR_FILE")
+                        .help("The file name of the input grammar (gzip-compressed when ending with .gz)")
+                        .takes_value(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("input")
+                        .value_name("INPUT")
+                        .help("The file name of the input to tokenize")
+                        .takes_value(true)
+                        .required(true)
+                )
+        )
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("coverage") {
+        let task = CompilationTask {
+            inputs: matches
+                .value_of("grammar")
+                .map(get_input)
+                .into_iter()
+                .collect(),
+            grammar_name: matches
+                .value_of("grammar_name")
+                .map(std::string::ToString::to_string),
+            ..CompilationTask::default()
+        };
+        let corpus: Vec<&str> = matches
+            .values_of("inputs")
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        let result = execute_coverage(&task, &corpus, matches.is_present("json"));
+        if let Err(errors) = result {
+            println!("{}", HimeCcErrors(errors));
+            process::exit(1);
+        } else {
+            process::exit(0);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lex") {
+        let task = CompilationTask {
+            inputs: matches
+                .value_of("grammar")
+                .map(get_input)
+                .into_iter()
+                .collect(),
+            grammar_name: matches
+                .value_of("grammar_name")
+                .map(std::string::ToString::to_string),
+            ..CompilationTask::default()
+        };
+        let symbols: Option<Vec<&str>> = matches.values_of("symbols").map(Iterator::collect);
+        let result = execute_lex(
+            &task,
+            matches.value_of("input").unwrap(),
+            symbols.as_deref(),
+            matches.is_present("json"),
+        );
+        match result {
+            Ok(0) => process::exit(0),
+            Ok(_) => process::exit(1),
+            Err(errors) => {
+                println!("{}", HimeCcErrors(errors));
+                process::exit(1);
+            }
+        }
+    }
+
     let mut task = CompilationTask::default();
     match matches.value_of("output_mode") {
         Some("sources") => task.mode = Some(Mode::Sources),
@@ -211,11 +392,24 @@ pub fn main() -> miette::Result<()> {
         Some("lalr1") => task.method = Some(ParsingMethod::LALR1),
         Some("rnglr1") => task.method = Some(ParsingMethod::RNGLR1),
         Some("rnglalr1") => task.method = Some(ParsingMethod::RNGLALR1),
+        Some("ielr1") => task.method = Some(ParsingMethod::IELR1),
         _ => {}
     }
+    task.expected_conflicts = matches
+        .value_of("expected_conflicts")
+        .and_then(|count| count.parse().ok());
+    task.max_states = matches
+        .value_of("max_states")
+        .and_then(|count| count.parse().ok());
     if matches.is_present("debug") {
         task.print_debug_data = Some(true);
     }
+    task.debug_max_lookaheads = matches
+        .value_of("debug_max_lookaheads")
+        .and_then(|count| count.parse().ok());
+    if matches.is_present("debug_verbose") {
+        task.debug_verbose = Some(true);
+    }
     if matches.is_present("rust_no_std") {
         task.rust_use_std = Some(false);
     }
@@ -225,12 +419,15 @@ pub fn main() -> miette::Result<()> {
     if matches.is_present("rust_compress_automata") {
         task.rust_compress_automata = Some(true);
     }
+    if matches.is_present("rust_no_actions") {
+        task.rust_emit_actions = Some(false);
+    }
     task.grammar_name = matches
         .value_of("grammar_name")
         .map(std::string::ToString::to_string);
     if let Some(inputs) = matches.values_of("inputs") {
         for input in inputs {
-            task.inputs.push(Input::FileName(input.to_string()));
+            task.inputs.push(get_input(input));
         }
     }
     let result = if matches.is_present("test") {
@@ -246,9 +443,39 @@ pub fn main() -> miette::Result<()> {
     }
 }
 
+/// Gets the compilation input for a file name
+fn get_input(input: &str) -> Input<'static> {
+    if Path::new(input)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+    {
+        Input::GzipFile(PathBuf::from(input))
+    } else {
+        Input::FileName(input.to_string())
+    }
+}
+
+/// Gets the index of the target grammar in the loaded data
+fn get_target_grammar(task: &CompilationTask<'_>, data: &LoadedData<'_>) -> Result<usize, Error> {
+    if data.grammars.is_empty() || (data.grammars.len() > 1 && task.grammar_name.is_none()) {
+        return Err(Error::GrammarNotSpecified);
+    }
+    if data.grammars.len() == 1 {
+        return Ok(0);
+    }
+    let name = task.grammar_name.as_ref().unwrap();
+    data.grammars
+        .iter()
+        .position(|grammar| &grammar.name == name)
+        .ok_or_else(|| Error::GrammarNotFound(name.clone()))
+}
+
 /// Executes the normal operation of the compiler
 fn execute_normal<'a>(task: &CompilationTask<'a>) -> Result<(), Errors<'a>> {
-    task.execute()?;
+    let (data, warnings) = task.execute_with_warnings()?;
+    if !warnings.is_empty() {
+        println!("{}", HimeCcErrors(Errors::from(data, warnings)));
+    }
     Ok(())
 }
 
@@ -258,28 +485,13 @@ fn execute_normal<'a>(task: &CompilationTask<'a>) -> Result<(), Errors<'a>> {
 /// Output the result
 fn execute_test<'a>(task: &CompilationTask<'a>) -> Result<(), Errors<'a>> {
     let mut data = task.load()?;
-    if data.grammars.is_empty() || (data.grammars.len() > 1 && task.grammar_name.is_none()) {
-        return Err(Errors::from(data, vec![Error::GrammarNotSpecified]));
-    }
-    let (grammar_index, grammar) = if data.grammars.len() == 1 {
-        (0, &mut data.grammars[0])
-    } else {
-        let name = task.grammar_name.as_ref().unwrap();
-        match data
-            .grammars
-            .iter_mut()
-            .enumerate()
-            .find(|(_, grammar)| &grammar.name == name)
-        {
-            Some(couple) => couple,
-            None => {
-                return Err(Errors::from(
-                    data,
-                    vec![Error::GrammarNotFound(name.clone())],
-                ));
-            }
+    let grammar_index = match get_target_grammar(task, &data) {
+        Ok(index) => index,
+        Err(error) => {
+            return Err(Errors::from(data, vec![error]));
         }
     };
+    let grammar = &mut data.grammars[grammar_index];
     let parser = match task.generate_in_memory(grammar, grammar_index) {
         Ok(p) => p,
         Err(errs) => {
@@ -305,6 +517,236 @@ fn execute_test<'a>(task: &CompilationTask<'a>) -> Result<(), Errors<'a>> {
     Ok(())
 }
 
+/// Executes the compiler in coverage mode
+/// Compiles the target grammar in-memory
+/// Parses each input of the corpus while counting the reductions of each rule
+/// Output the rules that are never reduced
+fn execute_coverage<'a>(
+    task: &CompilationTask<'a>,
+    corpus: &[&str],
+    json: bool,
+) -> Result<(), Errors<'a>> {
+    let mut data = task.load()?;
+    let grammar_index = match get_target_grammar(task, &data) {
+        Ok(index) => index,
+        Err(error) => {
+            return Err(Errors::from(data, vec![error]));
+        }
+    };
+    let build = match data.grammars[grammar_index].build(task.method, grammar_index) {
+        Ok(build) => build,
+        Err(errs) => {
+            return Err(Errors::from(data, errs));
+        }
+    };
+    let grammar = &data.grammars[grammar_index];
+    let parser = match grammar.get_in_memory(&build) {
+        Ok(p) => p,
+        Err(errs) => {
+            return Err(Errors::from(data, errs));
+        }
+    };
+
+    let mut totals = vec![0; parser.productions.len()];
+    let mut failed = Vec::new();
+    for &file_name in corpus {
+        let input = match fs::read_to_string(file_name) {
+            Ok(input) => input,
+            Err(error) => {
+                return Err(Errors::from(data, vec![Error::Io(error)]));
+            }
+        };
+        let result = parser.parse_with_rule_counts(&input);
+        if !result.is_success() {
+            failed.push(file_name);
+        }
+        if let Some(counts) = result.rule_counts() {
+            for (total, count) in totals.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+    }
+    let counts = parser.get_rule_counts(&totals);
+
+    let rules: Vec<RuleRef> = grammar
+        .variables
+        .iter()
+        .filter(|variable| !variable.name.starts_with(GENERATED_AXIOM))
+        .flat_map(|variable| {
+            (0..variable.rules.len()).map(move |index| RuleRef::new(variable.id, index))
+        })
+        .collect();
+    let uncovered: Vec<RuleRef> = rules
+        .iter()
+        .copied()
+        .filter(|rule_ref| counts.get(rule_ref).copied().unwrap_or_default() == 0)
+        .collect();
+    let covered = rules.len() - uncovered.len();
+    #[allow(clippy::cast_precision_loss)]
+    let coverage = if rules.is_empty() {
+        100.0
+    } else {
+        covered as f64 * 100.0 / rules.len() as f64
+    };
+
+    if json {
+        let report = serde_json::json!({
+            "rules": rules.len(),
+            "covered": covered,
+            "coverage": coverage,
+            "uncovered": uncovered
+                .iter()
+                .map(|rule_ref| {
+                    let rule = rule_ref.get_rule_in(grammar);
+                    serde_json::json!({
+                        "rule": format_rule(grammar, rule),
+                        "input": data.inputs[rule.head_input_ref.input_index].name,
+                        "line": rule.head_input_ref.position.line,
+                        "column": rule.head_input_ref.position.column,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "failed": failed,
+        });
+        println!("{report}");
+    } else {
+        println!(
+            "Covered {covered} of {} rules ({coverage:.1}%)",
+            rules.len()
+        );
+        for rule_ref in &uncovered {
+            let rule = rule_ref.get_rule_in(grammar);
+            println!(
+                "{}:{}:{}  {}",
+                data.inputs[rule.head_input_ref.input_index].name,
+                rule.head_input_ref.position.line,
+                rule.head_input_ref.position.column,
+                format_rule(grammar, rule)
+            );
+        }
+        for file_name in &failed {
+            println!("Failed to parse {file_name}");
+        }
+    }
+    Ok(())
+}
+
+/// Executes the compiler in lexing mode
+/// Compiles the target grammar in-memory
+/// Runs only its lexer on the input
+/// Output the matched tokens and the lexical errors at their positions
+/// Returns the number of lexical errors
+fn execute_lex<'a>(
+    task: &CompilationTask<'a>,
+    file_name: &str,
+    symbols: Option<&[&str]>,
+    json: bool,
+) -> Result<usize, Errors<'a>> {
+    let mut data = task.load()?;
+    let grammar_index = match get_target_grammar(task, &data) {
+        Ok(index) => index,
+        Err(error) => {
+            return Err(Errors::from(data, vec![error]));
+        }
+    };
+    let input = match fs::read_to_string(file_name) {
+        Ok(input) => input,
+        Err(error) => {
+            return Err(Errors::from(data, vec![Error::Io(error)]));
+        }
+    };
+    let grammar = &mut data.grammars[grammar_index];
+    let parser = match task.generate_in_memory(grammar, grammar_index) {
+        Ok(p) => p,
+        Err(errs) => {
+            return Err(Errors::from(data, errs));
+        }
+    };
+
+    let result = parser.tokenize(&input);
+    let mut entries = Vec::new();
+    for error in &result.errors.errors {
+        let position = error.get_position();
+        entries.push((
+            position,
+            if json {
+                serde_json::json!({
+                    "error": error.to_string(),
+                    "line": position.line,
+                    "column": position.column,
+                })
+                .to_string()
+            } else {
+                format!("error  {}:{}  {error}", position.line, position.column)
+            },
+        ));
+    }
+    let tokens = result.get_tokens();
+    for token in tokens.iter() {
+        let symbol = token.get_symbol();
+        if symbol.id == SID_DOLLAR || symbols.is_some_and(|names| !names.contains(&symbol.name)) {
+            continue;
+        }
+        let position = token.get_position().unwrap();
+        let span = token.get_span().unwrap();
+        let context = parser
+            .get_context_name(token.context_id())
+            .unwrap_or_default();
+        entries.push((
+            position,
+            if json {
+                serde_json::json!({
+                    "symbol": symbol.name,
+                    "line": position.line,
+                    "column": position.column,
+                    "start": span.index,
+                    "end": span.index + span.length,
+                    "value": token.get_value().unwrap_or_default(),
+                    "context": context,
+                })
+                .to_string()
+            } else {
+                format!(
+                    "{}  {}:{}  {}..{}  {:?}  {context}",
+                    symbol.name,
+                    position.line,
+                    position.column,
+                    span.index,
+                    span.index + span.length,
+                    token.get_value().unwrap_or_default()
+                )
+            },
+        ));
+    }
+    // the errors come before the tokens at the same position
+    entries.sort_by_key(|(position, _)| (position.line, position.column));
+    if json {
+        let entries: Vec<&str> = entries.iter().map(|(_, entry)| entry.as_str()).collect();
+        println!("[{}]", entries.join(","));
+    } else {
+        for (_, entry) in &entries {
+            println!("{entry}");
+        }
+    }
+    Ok(result.errors.errors.len())
+}
+
+/// Formats a grammar rule as `head -> body`
+fn format_rule(grammar: &Grammar, rule: &Rule) -> String {
+    let mut result = format!(
+        "{} ->",
+        grammar.get_symbol_name(SymbolRef::Variable(rule.head))
+    );
+    if rule.body.elements.is_empty() {
+        result.push_str(" ε");
+    }
+    for element in &rule.body.elements {
+        result.push(' ');
+        result.push_str(grammar.get_symbol_value(element.symbol));
+    }
+    result
+}
+
 /// Encapsulate SDK errors to implement Display with specific error formatting
 struct HimeCcErrors<'t>(Errors<'t>);
 
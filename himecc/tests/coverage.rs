@@ -0,0 +1,82 @@
+/*******************************************************************************
+ * Copyright (c) 2020 Association Cénotélie (cenotelie.fr)
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3
+ * of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General
+ * Public License along with this program.
+ * If not, see <http://www.gnu.org/licenses/>.
+ ******************************************************************************/
+
+//! Tests for the coverage sub-command
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Writes the files for a test in a new temporary folder
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("himecc-{name}-{}", std::process::id()));
+    fs::create_dir_all(&folder).unwrap();
+    for (file_name, content) in files {
+        fs::write(folder.join(file_name), content).unwrap();
+    }
+    folder
+}
+
+/// Runs the coverage sub-command and gets the JSON report
+fn run_coverage(folder: &Path, inputs: &[&str]) -> serde_json::Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_himecc"))
+        .current_dir(folder)
+        .arg("coverage")
+        .arg("--json")
+        .arg("test.gram")
+        .args(inputs)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_coverage_report() {
+    for method in ["lalr1", "rnglalr1"] {
+        let grammar = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; B -> 'b'; C -> 'c'; }} rules {{ e -> A y z ; y -> B | ; z -> C | ; }} }}"
+        );
+        let folder = write_files(
+            method,
+            &[
+                ("test.gram", &grammar),
+                ("first.txt", "a"),
+                ("second.txt", "ab"),
+                ("failed.txt", "b"),
+            ],
+        );
+        let report = run_coverage(&folder, &["first.txt", "second.txt", "failed.txt"]);
+        assert_eq!(report["rules"], 5, "{method}");
+        // the ε-rules are reduced in the right-nullable part of e
+        assert_eq!(report["covered"], 4, "{method}");
+        let uncovered = report["uncovered"].as_array().unwrap();
+        assert_eq!(uncovered.len(), 1, "{method}");
+        assert_eq!(uncovered[0]["rule"], "z -> C", "{method}");
+        assert_eq!(uncovered[0]["input"], "test.gram", "{method}");
+        assert_eq!(
+            report["failed"],
+            serde_json::json!(["failed.txt"]),
+            "{method}"
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
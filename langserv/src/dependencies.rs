@@ -0,0 +1,81 @@
+/*******************************************************************************
+ * Copyright (c) 2020 Association Cénotélie (cenotelie.fr)
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3
+ * of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General
+ * Public License along with this program.
+ * If not, see <http://www.gnu.org/licenses/>.
+ ******************************************************************************/
+
+//! Module for the dependencies between the grammars of a workspace
+
+use hime_sdk::grammars::Grammar;
+use hime_sdk::InputReference;
+
+/// A dependency of a grammar onto another one
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    /// The index of the dependent grammar
+    pub grammar_index: usize,
+    /// The input reference for the name of the dependency in the dependent grammar
+    pub input_ref: InputReference,
+    /// The name of the grammar depended upon
+    pub name: String,
+    /// The index of the grammar depended upon, if it is defined
+    pub target: Option<usize>,
+}
+
+/// The graph of the dependencies between grammars
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// The dependencies of each grammar
+    pub grammars: Vec<Vec<Dependency>>,
+}
+
+impl DependencyGraph {
+    /// Initializes the graph from grammars
+    #[must_use]
+    pub fn from(grammars: &[Grammar]) -> DependencyGraph {
+        DependencyGraph {
+            grammars: grammars
+                .iter()
+                .enumerate()
+                .map(|(grammar_index, grammar)| {
+                    grammar
+                        .parents
+                        .iter()
+                        .map(|parent| Dependency {
+                            grammar_index,
+                            input_ref: parent.input_ref,
+                            name: parent.name.clone(),
+                            target: grammars.iter().position(|g| g.name == parent.name),
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Gets all the dependencies found in an input
+    pub fn in_input(&self, input_index: usize) -> impl Iterator<Item = &Dependency> {
+        self.grammars
+            .iter()
+            .flatten()
+            .filter(move |dependency| dependency.input_ref.input_index == input_index)
+    }
+
+    /// Gets the dependency at a location in an input
+    #[must_use]
+    pub fn find_at(&self, location: &InputReference) -> Option<&Dependency> {
+        self.in_input(location.input_index)
+            .find(|dependency| dependency.input_ref.overlaps_with(location))
+    }
+}
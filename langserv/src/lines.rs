@@ -0,0 +1,176 @@
+/*******************************************************************************
+ * Copyright (c) 2020 Association Cénotélie (cenotelie.fr)
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3
+ * of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General
+ * Public License along with this program.
+ * If not, see <http://www.gnu.org/licenses/>.
+ ******************************************************************************/
+
+//! Module for the index of the lines in the content of a document
+
+use tower_lsp::lsp_types::Position;
+
+/// A character that is encoded differently in UTF-8 and UTF-16
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    /// The byte offset of the character from the start of its line
+    start: usize,
+    /// The length of the character in UTF-8, i.e. in bytes
+    len_utf8: usize,
+    /// The length of the character in UTF-16 code units
+    len_utf16: usize,
+}
+
+impl WideChar {
+    /// Gets the byte offset of the end of the character from the start of its line
+    fn end(self) -> usize {
+        self.start + self.len_utf8
+    }
+}
+
+/// An index of the lines in a text for converting between LSP positions and byte offsets
+/// As per the LSP, the characters in a line are counted in UTF-16 code units
+/// and the lines are terminated by `\n`, `\r\n` or `\r`.
+/// The conversions are logarithmic in the number of lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The byte offset of the start of each line
+    starts: Vec<usize>,
+    /// The byte offset of the end of each line, before its terminator
+    ends: Vec<usize>,
+    /// The wide characters on each line
+    wide_chars: Vec<Vec<WideChar>>,
+}
+
+impl LineIndex {
+    /// Builds the index of the lines in a text
+    #[must_use]
+    pub fn new(text: &str) -> LineIndex {
+        let mut starts = vec![0];
+        let mut ends = Vec::new();
+        let mut wide_chars = vec![Vec::new()];
+        let mut chars = text.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            let next = match c {
+                '\r' if chars.peek().map(|&(_, c)| c) == Some('\n') => {
+                    chars.next();
+                    offset + 2
+                }
+                '\r' | '\n' => offset + 1,
+                _ => {
+                    if c.len_utf8() > 1 {
+                        let start = offset - starts[starts.len() - 1];
+                        wide_chars[starts.len() - 1].push(WideChar {
+                            start,
+                            len_utf8: c.len_utf8(),
+                            len_utf16: c.len_utf16(),
+                        });
+                    }
+                    continue;
+                }
+            };
+            ends.push(offset);
+            starts.push(next);
+            wide_chars.push(Vec::new());
+        }
+        ends.push(text.len());
+        LineIndex {
+            starts,
+            ends,
+            wide_chars,
+        }
+    }
+
+    /// Gets the number of lines
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Gets the byte offset for a position
+    /// A position after the end of its line is clamped to the end of the line,
+    /// a position after the last line is clamped to the end of the text.
+    #[must_use]
+    pub fn offset_of(&self, position: Position) -> usize {
+        let line = position.line as usize;
+        if line >= self.starts.len() {
+            return self.ends[self.ends.len() - 1];
+        }
+        let mut column = position.character as usize;
+        for wide_char in &self.wide_chars[line] {
+            if wide_char.start >= column {
+                break;
+            }
+            column += wide_char.len_utf8 - wide_char.len_utf16;
+        }
+        (self.starts[line] + column).min(self.ends[line])
+    }
+
+    /// Gets the position for a byte offset
+    /// An offset within a line terminator is at the end of the line,
+    /// an offset after the end of the text is at the end of the text.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn position_of(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = offset.min(self.ends[line]) - self.starts[line];
+        let mut character = column;
+        for wide_char in &self.wide_chars[line] {
+            if wide_char.end() > column {
+                break;
+            }
+            character -= wide_char.len_utf8 - wide_char.len_utf16;
+        }
+        Position::new(line as u32, character as u32)
+    }
+}
+
+#[test]
+fn test_line_index_ascii() {
+    let index = LineIndex::new("ab\ncd\r\nef\rgh");
+    assert_eq!(index.line_count(), 4);
+    let expected = [
+        (0, Position::new(0, 0)),
+        (2, Position::new(0, 2)),
+        (3, Position::new(1, 0)),
+        (5, Position::new(1, 2)),
+        (7, Position::new(2, 0)),
+        (10, Position::new(3, 0)),
+        (12, Position::new(3, 2)),
+    ];
+    for (offset, position) in expected {
+        assert_eq!(index.position_of(offset), position);
+        assert_eq!(index.offset_of(position), offset);
+    }
+    // offsets in line terminators and after the end are clamped
+    assert_eq!(index.position_of(6), Position::new(1, 2));
+    assert_eq!(index.position_of(100), Position::new(3, 2));
+    // positions after the end of a line or the text are clamped
+    assert_eq!(index.offset_of(Position::new(1, 10)), 5);
+    assert_eq!(index.offset_of(Position::new(10, 0)), 12);
+}
+
+#[test]
+fn test_line_index_wide_chars() {
+    // 'é' is 2 bytes and 1 code unit, '𝄞' is 4 bytes and 2 code units
+    let text = "aé𝄞b\n𝄞";
+    let index = LineIndex::new(text);
+    for (offset, _) in text.char_indices().chain([(text.len(), ' ')]) {
+        let position = index.position_of(offset);
+        assert_eq!(index.offset_of(position), offset);
+    }
+    assert_eq!(index.position_of(3), Position::new(0, 2));
+    assert_eq!(index.position_of(7), Position::new(0, 4));
+    assert_eq!(index.position_of(8), Position::new(0, 5));
+    assert_eq!(index.position_of(13), Position::new(1, 2));
+    assert_eq!(index.offset_of(Position::new(0, 4)), 7);
+}
@@ -17,23 +17,33 @@
 
 //! Generator of lexers and parsers for the Hime runtime.
 
+pub mod dependencies;
+pub mod lines;
 pub mod symbols;
 pub mod workspace;
 
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::{Arg, ArgAction, Command};
 use futures::future::join_all;
-use tokio::sync::RwLock;
+use hime_sdk::CompilationProgress;
+use tokio::sync::{mpsc, RwLock};
 use tower_lsp::jsonrpc::{Error, Result};
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
 use tower_lsp::lsp_types::{
-    CodeLens, CodeLensOptions, CodeLensParams, DidChangeTextDocumentParams,
-    DidChangeWatchedFilesParams, ExecuteCommandOptions, ExecuteCommandParams, GotoDefinitionParams,
-    GotoDefinitionResponse, Hover, HoverParams, HoverProviderCapability, InitializeParams,
-    InitializeResult, Location, OneOf, ReferenceParams, ServerCapabilities, ServerInfo,
-    SymbolInformation, TextDocumentSyncCapability, TextDocumentSyncKind, WorkDoneProgressOptions,
-    WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities, WorkspaceSymbolParams,
+    CodeActionParams, CodeActionProviderCapability, CodeActionResponse, CodeLens, CodeLensOptions,
+    CodeLensParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DocumentLink,
+    DocumentLinkOptions, DocumentLinkParams, ExecuteCommandOptions, ExecuteCommandParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, HoverProviderCapability,
+    InitializeParams, InitializeResult, InlayHint, InlayHintParams, Location, NumberOrString,
+    OneOf, ProgressParams, ProgressParamsValue, ProgressToken, ReferenceParams, ServerCapabilities,
+    ServerInfo, SymbolInformation, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+    WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+    WorkDoneProgressOptions, WorkDoneProgressReport, WorkspaceFoldersServerCapabilities,
+    WorkspaceServerCapabilities, WorkspaceSymbolParams,
 };
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use workspace::Workspace;
@@ -57,28 +67,110 @@ struct Backend {
 }
 
 impl Backend {
-    /// Create a new backend
-    fn new(client: Client) -> Backend {
+    /// Create a new backend, caching the LR graphs of the grammars in a directory, if any
+    fn new(client: Client, graph_cache: Option<PathBuf>) -> Backend {
         Backend {
             client: Arc::new(client),
-            workspace: Arc::new(RwLock::new(Workspace::default())),
+            workspace: Arc::new(RwLock::new(Workspace {
+                graph_cache,
+                ..Workspace::default()
+            })),
         }
     }
 
     /// Execute the background work
     async fn worker(workspace: Arc<RwLock<Workspace>>, client: Arc<Client>) {
         let mut workspace = workspace.write().await;
-        workspace.lint();
+        let token = NumberOrString::String(String::from("hime/lint"));
+        let has_progress = client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .is_ok();
+        let diagnostics = if has_progress {
+            let begin = WorkDoneProgressBegin {
+                title: String::from("Linting grammars"),
+                ..WorkDoneProgressBegin::default()
+            };
+            Backend::send_progress(&client, &token, WorkDoneProgress::Begin(begin)).await;
+            let (sender, mut receiver) = mpsc::unbounded_channel();
+            let forwarder = {
+                let client = client.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    while let Some(progress) = receiver.recv().await {
+                        let report = WorkDoneProgressReport {
+                            message: Some(Backend::describe_progress(progress)),
+                            ..WorkDoneProgressReport::default()
+                        };
+                        Backend::send_progress(&client, &token, WorkDoneProgress::Report(report))
+                            .await;
+                    }
+                })
+            };
+            let diagnostics = workspace.lint_with_progress(Some(Box::new(move |progress| {
+                // the forwarder only stops once the sender is dropped
+                let _ = sender.send(progress);
+            })));
+            let _ = forwarder.await;
+            let end = WorkDoneProgressEnd::default();
+            Backend::send_progress(&client, &token, WorkDoneProgress::End(end)).await;
+            diagnostics
+        } else {
+            workspace.lint()
+        };
         join_all(workspace.documents.iter().map(|doc| {
-            client.publish_diagnostics(doc.url.clone(), doc.diagnostics.clone(), doc.version)
+            let diags = diagnostics.get(&doc.url).cloned().unwrap_or_default();
+            client.publish_diagnostics(doc.url.clone(), diags, doc.version)
         }))
         .await;
+        workspace.apply_diagnostics(diagnostics);
+    }
+
+    /// Sends a `$/progress` notification to the client
+    async fn send_progress(client: &Client, token: &ProgressToken, progress: WorkDoneProgress) {
+        client
+            .send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
+
+    /// Gets the message describing the progress of a compilation
+    fn describe_progress(progress: CompilationProgress) -> String {
+        match progress {
+            CompilationProgress::LoadingGrammar(current, total) => {
+                format!("Loading grammar {current}/{total}")
+            }
+            CompilationProgress::BuildingLRGraph => String::from("Building LR graph"),
+            CompilationProgress::BuildingReductions => String::from("Building reductions"),
+            CompilationProgress::Generating => String::from("Generating parser"),
+        }
     }
 
     /// Execute the background work
     fn execute(&self) {
         tokio::spawn(Backend::worker(self.workspace.clone(), self.client.clone()));
     }
+
+    /// Handles the custom request for previewing the parse of a sample input against a grammar.
+    /// The parameters are the `uri` of the grammar's document and the `sample` input.
+    async fn preview(&self, params: serde_json::Value) -> Result<serde_json::Value> {
+        let uri = params
+            .get("uri")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|uri| Url::parse(uri).ok());
+        let sample = params.get("sample").and_then(serde_json::Value::as_str);
+        let (Some(uri), Some(sample)) = (uri, sample) else {
+            return Err(Error::invalid_params(
+                "Expected `uri` and `sample` parameters",
+            ));
+        };
+        let workspace = self.workspace.read().await;
+        Ok(workspace.try_parse(&uri, sample).to_value())
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -116,6 +208,12 @@ impl LanguageServer for Backend {
                 code_lens_provider: Some(CodeLensOptions {
                     resolve_provider: None,
                 }),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 ..ServerCapabilities::default()
             },
             server_info: Some(ServerInfo {
@@ -199,6 +297,21 @@ impl LanguageServer for Backend {
         Ok(workspace.get_code_lens(params.text_document.uri.as_str()))
     }
 
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let workspace = self.workspace.read().await;
+        Ok(workspace.get_inlay_hints(params.text_document.uri.as_str(), params.range))
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let workspace = self.workspace.read().await;
+        Ok(workspace.get_document_links(params.text_document.uri.as_str()))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let workspace = self.workspace.read().await;
+        Ok(workspace.get_code_actions(&params.text_document.uri, &params.context.diagnostics))
+    }
+
     async fn execute_command(
         &self,
         params: ExecuteCommandParams,
@@ -266,6 +379,13 @@ async fn main() {
                 .help("The TCP port to listen to, if using a TCP stream")
                 .required(false),
         )
+        .arg(
+            Arg::new("cache")
+                .value_name("DIRECTORY")
+                .long("cache")
+                .help("The directory where the LR graphs of the grammars are cached across lints")
+                .required(false),
+        )
         .subcommand(Command::new("version").about("Display the version string"))
         .get_matches();
 
@@ -274,6 +394,7 @@ async fn main() {
             println!("{CRATE_NAME} {CRATE_VERSION} tag={GIT_TAG} hash={GIT_HASH}");
         }
         _ => {
+            let graph_cache = matches.get_one::<String>("cache").map(PathBuf::from);
             if matches.get_flag("tcp") {
                 let address = matches
                     .get_one::<String>("address")
@@ -290,12 +411,18 @@ async fn main() {
                     .unwrap();
                 let (stream, _) = listener.accept().await.unwrap();
                 let (read, write) = tokio::io::split(stream);
-                let (service, socket) = LspService::new(Backend::new);
+                let (service, socket) =
+                    LspService::build(|client| Backend::new(client, graph_cache))
+                        .custom_method("hime/preview", Backend::preview)
+                        .finish();
                 Server::new(read, write, socket).serve(service).await;
             } else {
                 let stdin = tokio::io::stdin();
                 let stdout = tokio::io::stdout();
-                let (service, socket) = LspService::new(Backend::new);
+                let (service, socket) =
+                    LspService::build(|client| Backend::new(client, graph_cache))
+                        .custom_method("hime/preview", Backend::preview)
+                        .finish();
                 Server::new(stdin, stdout, socket).serve(service).await;
             }
         }
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use hime_sdk::grammars::{Grammar, SymbolRef};
 use hime_sdk::InputReference;
+use tower_lsp::lsp_types::{SymbolInformation, Url};
 
 /*******************************************************************************
  * Copyright (c) 2021 Association Cénotélie (cenotelie.fr)
@@ -145,3 +146,90 @@ impl SymbolRegistry {
         }
     }
 }
+
+/// The maximum number of results when looking up the index of symbols
+pub const MAX_LOOKUP_RESULTS: usize = 200;
+
+/// An index of the symbols defined in the documents of a workspace, for lookups by name
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    /// The symbols defined in each document
+    pub documents: HashMap<Url, Vec<SymbolInformation>>,
+}
+
+impl SymbolIndex {
+    /// Updates the index with the symbols of the last loaded grammars
+    /// When the grammars are not `complete`, i.e. some documents failed to load,
+    /// the documents without symbols keep the last-known ones.
+    pub fn update(&mut self, mut documents: HashMap<Url, Vec<SymbolInformation>>, complete: bool) {
+        if !complete {
+            for (url, symbols) in self.documents.drain() {
+                documents.entry(url).or_insert(symbols);
+            }
+        }
+        self.documents = documents;
+    }
+
+    /// Lookups the symbols matching a query, ordered by match quality then name
+    /// The query may be qualified by the name of a grammar, as in `Grammar.symbol`.
+    #[must_use]
+    pub fn lookup(&self, query: &str) -> Vec<SymbolInformation> {
+        let (container, query) = match query.split_once('.') {
+            Some((container, query)) => (Some(container), query),
+            None => (None, query),
+        };
+        let mut matches = self
+            .documents
+            .values()
+            .flatten()
+            .filter(|symbol| {
+                container.is_none_or(|container| {
+                    symbol
+                        .container_name
+                        .as_deref()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(container))
+                })
+            })
+            .filter_map(|symbol| fuzzy_match(&symbol.name, query).map(|score| (score, symbol)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(score1, symbol1), (score2, symbol2)| {
+            score1
+                .cmp(score2)
+                .then_with(|| symbol1.name.cmp(&symbol2.name))
+                .then_with(|| symbol1.container_name.cmp(&symbol2.container_name))
+                .then_with(|| symbol1.location.uri.cmp(&symbol2.location.uri))
+        });
+        matches
+            .into_iter()
+            .take(MAX_LOOKUP_RESULTS)
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+}
+
+/// Matches a name against a query, as a case-insensitive subsequence
+/// Returns the score of the match, the lower the better, or `None` when the name does not match.
+/// Exact matches come first, then prefixes, substrings and finally subsequences by how spread out they are.
+#[must_use]
+pub fn fuzzy_match(name: &str, query: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let query = query.to_lowercase();
+    if name == query {
+        return Some(0);
+    }
+    if name.starts_with(&query) {
+        return Some(1);
+    }
+    if name.contains(&query) {
+        return Some(2);
+    }
+    let mut chars = name.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for c in query.chars() {
+        let (index, _) = chars.find(|&(_, n)| n == c)?;
+        first.get_or_insert(index);
+        last = index;
+    }
+    Some(3 + last - first.unwrap_or(last))
+}
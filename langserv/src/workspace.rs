@@ -17,25 +17,44 @@
 
 //! Module for the definition of a server-side workspace
 
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{self, BufReader, ErrorKind, Read};
-use std::path::{Path, PathBuf};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
 
-use hime_redist::text::TextPosition;
+use hime_redist::errors::{ParseError, ParseErrorDataTrait};
+use hime_redist::text::{Text, TextPosition};
 use hime_sdk::errors::Error;
 use hime_sdk::grammars::{
-    Grammar, RuleBodyElement, Symbol, SymbolRef, OPTION_AXIOM, OPTION_SEPARATOR,
+    Annotation, BuildData, Grammar, RuleBodyElement, Symbol, SymbolRef, OPTION_AXIOM,
+    OPTION_ENTRIES, OPTION_SEPARATOR,
 };
-use hime_sdk::{CompilationTask, Input, InputReference, LoadedData, LoadedInput};
-use serde_json::Value;
+use hime_sdk::lr::{ConflictKind, Graph, Item};
+use hime_sdk::{
+    CompilationProgress, CompilationTask, Input, InputInclude, InputReference, LoadedData,
+    LoadedInput,
+};
+use serde_json::{json, Value};
 use tower_lsp::jsonrpc::Error as JsonRpcError;
 use tower_lsp::lsp_types::{
-    CodeLens, Command, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity,
-    DidChangeTextDocumentParams, FileChangeType, FileEvent, GotoDefinitionResponse, Hover,
-    HoverContents, Location, MarkedString, Position, Range, SymbolInformation, SymbolKind, Url,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeLens, Command, CreateFile, Diagnostic,
+    DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DocumentChangeOperation, DocumentChanges, DocumentLink, FileChangeType, FileEvent,
+    GotoDefinitionResponse, Hover, HoverContents, InlayHint, InlayHintLabel, Location,
+    MarkedString, NumberOrString, OneOf, OptionalVersionedTextDocumentIdentifier, Position, Range,
+    ResourceOp, SymbolInformation, SymbolKind, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
 };
 
-use crate::symbols::{SymbolRegistry, SymbolRegistryElement};
+use crate::dependencies::DependencyGraph;
+use crate::lines::LineIndex;
+use crate::symbols::{SymbolIndex, SymbolRegistry, SymbolRegistryElement};
+
+/// The magic number at the start of the files caching the LR graph of a grammar
+const GRAPH_CACHE_MAGIC: &[u8; 4] = b"HGB1";
+
+/// The code of the diagnostics for references to undefined grammars
+const CODE_GRAMMAR_NOT_DEFINED: &str = "grammar-not-defined";
 
 /// Represents a document in a workspace
 #[derive(Debug, Clone)]
@@ -43,26 +62,225 @@ pub struct Document {
     /// The document's URL
     pub url: Url,
     /// The content of the document in this version
-    pub content: Option<String>,
+    content: Option<String>,
     /// The current version
     pub version: Option<i32>,
     /// The diagnostics for the document
     pub diagnostics: Vec<Diagnostic>,
+    /// The warning produced when decoding the document's content from disk, if any
+    pub decoding_warning: Option<String>,
+    /// The directory of the document, against which its includes are resolved, if it is a file
+    pub directory: Option<PathBuf>,
+    /// The index of the lines in the content, built on first use
+    line_index: OnceLock<LineIndex>,
 }
 
 impl Document {
     /// Creates a new document
     #[must_use]
     pub fn new(url: Url, content: String) -> Document {
+        let directory = get_directory(&url);
+        Document {
+            url,
+            content: Some(content),
+            version: None,
+            diagnostics: Vec::new(),
+            decoding_warning: None,
+            directory,
+            line_index: OnceLock::new(),
+        }
+    }
+
+    /// Creates a new document from the raw bytes read from a file
+    ///
+    /// A byte order mark, if any, is used to detect UTF-8, UTF-16LE and UTF-16BE encodings.
+    /// Otherwise the content is decoded as UTF-8.
+    /// Content that cannot be decoded is decoded lossily and a warning is recorded.
+    #[must_use]
+    pub fn from_bytes(url: Url, bytes: &[u8]) -> Document {
+        let (content, decoding_warning) = decode_content(bytes);
+        let directory = get_directory(&url);
         Document {
             url,
             content: Some(content),
             version: None,
             diagnostics: Vec::new(),
+            decoding_warning,
+            directory,
+            line_index: OnceLock::new(),
+        }
+    }
+
+    /// Replaces the content of the document
+    /// The index of the lines is invalidated and the decoding warning, if any, no longer applies.
+    pub fn set_content(&mut self, content: String) {
+        self.content = Some(content);
+        self.decoding_warning = None;
+        self.line_index = OnceLock::new();
+    }
+
+    /// Gets the content of the document in this version, if any
+    #[must_use]
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    /// Gets the index of the lines in the content of the document, if any
+    #[must_use]
+    pub fn line_index(&self) -> Option<&LineIndex> {
+        let content = self.content.as_ref()?;
+        Some(self.line_index.get_or_init(|| LineIndex::new(content)))
+    }
+
+    /// Gets the diagnostic for the decoding warning, if any
+    fn get_decoding_diagnostic(&self) -> Option<Diagnostic> {
+        self.decoding_warning.as_ref().map(|warning| Diagnostic {
+            range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: None,
+            code_description: None,
+            source: Some(super::CRATE_NAME.to_string()),
+            message: warning.clone(),
+            related_information: None,
+            tags: None,
+            data: None,
+        })
+    }
+}
+
+/// Gets the directory of the file behind a URL, if any
+fn get_directory(url: &Url) -> Option<PathBuf> {
+    url.to_file_path().ok()?.parent().map(Path::to_path_buf)
+}
+
+/// Resolves the path of an include relative to the directory of the including document
+/// The path is canonicalized when the file exists,
+/// otherwise the `.` and `..` components are resolved lexically.
+fn resolve_include_path(directory: &Path, include: &str) -> PathBuf {
+    let path = directory.join(include);
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+/// Decodes the raw content of a file
+///
+/// Returns the decoded content and a warning when the content could only be decoded lossily
+fn decode_content(bytes: &[u8]) -> (String, Option<String>) {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return decode_utf8(rest);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16(rest, u16::from_le_bytes, "UTF-16LE");
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return decode_utf16(rest, u16::from_be_bytes, "UTF-16BE");
+    }
+    decode_utf8(bytes)
+}
+
+/// Decodes UTF-8 content, falling back to a lossy decoding
+fn decode_utf8(bytes: &[u8]) -> (String, Option<String>) {
+    match std::str::from_utf8(bytes) {
+        Ok(content) => (content.to_string(), None),
+        Err(error) => (
+            String::from_utf8_lossy(bytes).into_owned(),
+            Some(format!(
+                "Document is not valid UTF-8 (invalid byte sequence at offset {}), invalid sequences have been replaced",
+                error.valid_up_to()
+            )),
+        ),
+    }
+}
+
+/// Decodes UTF-16 content, falling back to a lossy decoding
+fn decode_utf16(
+    bytes: &[u8],
+    decode_unit: fn([u8; 2]) -> u16,
+    encoding: &str,
+) -> (String, Option<String>) {
+    let chunks = bytes.chunks_exact(2);
+    let odd_length = !chunks.remainder().is_empty();
+    let units = chunks
+        .map(|chunk| decode_unit([chunk[0], chunk[1]]))
+        .collect::<Vec<_>>();
+    match String::from_utf16(&units) {
+        Ok(content) if !odd_length => (content, None),
+        _ => {
+            let mut content = String::from_utf16_lossy(&units);
+            if odd_length {
+                content.push(char::REPLACEMENT_CHARACTER);
+            }
+            (
+                content,
+                Some(format!(
+                    "Document is not valid {encoding}, invalid sequences have been replaced"
+                )),
+            )
         }
     }
 }
 
+/// Builds a grammar, reusing its LR graph cached in a directory, if any
+/// On a cache miss, the graph of a successful build is written to the cache.
+fn build_grammar(
+    task: &CompilationTask,
+    grammar: &mut Grammar,
+    index: usize,
+    cache_dir: Option<&Path>,
+) -> Result<BuildData, Vec<Error>> {
+    let Some(cache_dir) = cache_dir else {
+        return task.build_grammar(grammar, index);
+    };
+    let fingerprint = grammar.fingerprint(task.method);
+    let path = cache_dir.join(format!("{}.hgb", grammar.name));
+    let cached = read_cached_graph(&path, fingerprint).ok().flatten();
+    let is_hit = cached.is_some();
+    let build = task.build_grammar_from_graph(grammar, index, cached)?;
+    if !is_hit {
+        // the cache is an optimization, failing to write it does not fail the build
+        let _ = write_cached_graph(cache_dir, &path, fingerprint, &build.graph);
+    }
+    Ok(build)
+}
+
+/// Reads the LR graph cached in a file, if it was built for a grammar with the given fingerprint
+fn read_cached_graph(path: &Path, fingerprint: u64) -> io::Result<Option<Graph>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0; 12];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != GRAPH_CACHE_MAGIC || header[4..] != fingerprint.to_le_bytes() {
+        return Ok(None);
+    }
+    Graph::read_from(&mut reader).map(Some)
+}
+
+/// Writes the LR graph of a grammar with the given fingerprint to a cache file
+fn write_cached_graph(
+    cache_dir: &Path,
+    path: &Path,
+    fingerprint: u64,
+    graph: &Graph,
+) -> io::Result<()> {
+    std::fs::create_dir_all(cache_dir)?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(GRAPH_CACHE_MAGIC)?;
+    writer.write_all(&fingerprint.to_le_bytes())?;
+    graph.write_to(&mut writer)?;
+    writer.flush()
+}
+
 /// The data associated to the workspace
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Clone, Default)]
@@ -73,30 +291,11 @@ pub struct WorkspaceData {
     pub grammars: Vec<Grammar>,
     /// The registry of symbols
     pub symbols: SymbolRegistry,
+    /// The dependencies between the grammars
+    pub dependencies: DependencyGraph,
 }
 
 impl WorkspaceData {
-    /// Translate an input reference to a LSP range
-    #[must_use]
-    pub fn get_range(&self, input_reference: InputReference) -> Range {
-        WorkspaceData::to_range(&self.inputs, input_reference)
-    }
-
-    /// Translate an input reference to a LSP range
-    #[allow(clippy::cast_possible_truncation)]
-    fn to_range(inputs: &[LoadedInput], input_reference: InputReference) -> Range {
-        let end = inputs[input_reference.input_index]
-            .content
-            .get_position_for(input_reference.position, input_reference.length);
-        Range::new(
-            Position::new(
-                (input_reference.position.line - 1) as u32,
-                (input_reference.position.column - 1) as u32,
-            ),
-            Position::new((end.line - 1) as u32, (end.column - 1) as u32),
-        )
-    }
-
     /// Gets the symbol at a location in an input
     fn find_symbol_at(&self, location: InputReference) -> Option<&SymbolRegistryElement> {
         for symbols in &self.symbols.grammars {
@@ -110,6 +309,56 @@ impl WorkspaceData {
     }
 }
 
+/// An error found when previewing the parse of a sample input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePreviewError {
+    /// The range of the error in the sample input
+    pub range: Range,
+    /// The error's message
+    pub message: String,
+    /// The names of the terminals that were expected, for an unexpected token
+    pub expected: Vec<String>,
+}
+
+/// The result of parsing a sample input against a grammar of the workspace
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsePreview {
+    /// No grammar could be compiled for the document, with the reason why
+    Unavailable(String),
+    /// The sample input was parsed, with the serialized parse tree
+    Tree(Value),
+    /// The sample input could not be parsed
+    Errors(Vec<ParsePreviewError>),
+}
+
+impl ParsePreview {
+    /// Serializes this preview to a JSON value
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        match self {
+            ParsePreview::Unavailable(reason) => json!({
+                "kind": "unavailable",
+                "reason": reason,
+            }),
+            ParsePreview::Tree(root) => json!({
+                "kind": "tree",
+                "root": root,
+            }),
+            ParsePreview::Errors(errors) => json!({
+                "kind": "errors",
+                "errors": errors
+                    .iter()
+                    .map(|error| json!({
+                        "range": error.range,
+                        "message": error.message,
+                        "expected": error.expected,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+        }
+    }
+}
+
 /// Represents the current workspace for a server
 #[derive(Debug, Clone, Default)]
 pub struct Workspace {
@@ -119,6 +368,12 @@ pub struct Workspace {
     pub documents: Vec<Document>,
     /// The currently loaded data, if any
     pub data: Option<WorkspaceData>,
+    /// The index of the symbols in the documents, as of their last successful load
+    pub symbol_index: SymbolIndex,
+    /// The directory where the LR graphs of the grammars are cached across lints, if any
+    /// The graph of each grammar is cached in a `.hgb` file named after the grammar,
+    /// it is reloaded as long as the grammar is unchanged and rebuilt otherwise.
+    pub graph_cache: Option<PathBuf>,
 }
 
 impl Workspace {
@@ -188,10 +443,10 @@ impl Workspace {
     /// Resolves a document
     fn resolve_document(&mut self, uri: Url, path: &Path) -> io::Result<()> {
         let mut reader = BufReader::new(File::open(path)?);
-        let mut content = String::new();
-        reader.read_to_string(&mut content)?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
         if self.documents.iter().all(|doc| doc.url != uri) {
-            self.documents.push(Document::new(uri, content));
+            self.documents.push(Document::from_bytes(uri, &bytes));
         }
         Ok(())
     }
@@ -226,137 +481,275 @@ impl Workspace {
             .iter_mut()
             .find(|doc| doc.url == event.text_document.uri)
         {
+            document.version = Some(event.text_document.version);
             for change in event.content_changes {
                 if change.range.is_none() && change.range_length.is_none() {
-                    document.content = Some(change.text);
+                    document.set_content(change.text);
                 }
             }
         }
     }
 
     /// Runs the diagnostics
-    pub fn lint(&mut self) {
+    /// The diagnostics of each document are returned without being applied to the documents.
+    pub fn lint(&mut self) -> HashMap<Url, Vec<Diagnostic>> {
+        self.lint_with_progress(None)
+    }
+
+    /// Runs the diagnostics, notifying the progress callback, if any, of the compilation's progress
+    /// The diagnostics of each document are returned without being applied to the documents.
+    pub fn lint_with_progress(
+        &mut self,
+        progress: Option<Box<dyn Fn(CompilationProgress) + '_>>,
+    ) -> HashMap<Url, Vec<Diagnostic>> {
         self.data = None;
-        let mut task = CompilationTask::default();
-        for doc in &mut self.documents {
-            doc.diagnostics.clear();
-            if let Some(content) = doc.content.as_ref() {
-                task.inputs.push(Input::Raw(content));
+        let cache_dir = self.graph_cache.clone();
+        let mut diagnostics: Vec<Vec<Diagnostic>> = self
+            .documents
+            .iter()
+            .map(|doc| doc.get_decoding_diagnostic().into_iter().collect())
+            .collect();
+        let loaded = {
+            let mut task = CompilationTask {
+                progress: progress
+                    .as_ref()
+                    .map(|progress| Box::new(progress) as Box<dyn Fn(CompilationProgress)>),
+                ..CompilationTask::default()
+            };
+            for doc in &self.documents {
+                if let Some(content) = doc.content() {
+                    task.inputs.push(Input::Raw(content));
+                }
             }
-        }
-        match task.load() {
-            Ok(data) => {
-                let mut data = data.into_static();
-                let mut errors = Vec::new();
-                for (index, grammar) in data.grammars.iter_mut().enumerate() {
-                    if let Err(mut errs) = task.generate_in_memory(grammar, index) {
-                        errors.append(&mut errs);
+            let loaded = match task.load() {
+                Ok(data) => {
+                    let mut data = data.into_static();
+                    let mut errors = data.find_duplicate_grammars();
+                    for (index, grammar) in data.grammars.iter_mut().enumerate() {
+                        match build_grammar(&task, grammar, index, cache_dir.as_deref()) {
+                            Ok(build) => {
+                                task.report_progress(CompilationProgress::Generating);
+                                if let Err(mut errs) = grammar.get_in_memory(&build) {
+                                    errors.append(&mut errs);
+                                }
+                                errors.append(&mut grammar.find_unused_contexts(index, &build));
+                                errors.append(&mut grammar.find_shadowed_terminals(index, &build));
+                            }
+                            Err(mut errs) => errors.append(&mut errs),
+                        }
+                        errors.append(&mut grammar.find_nullable_unit_rules(index));
+                        errors.append(&mut grammar.find_axiom_references(index));
+                        errors.append(&mut grammar.find_redefined_symbols());
                     }
+                    Ok((data, errors))
                 }
+                Err(errors) => Err(errors.into_static()),
+            };
+            loaded
+        };
+        let includes = match &loaded {
+            Ok((data, _)) => self.resolve_includes(&data.inputs),
+            Err(errors) => self.resolve_includes(&errors.context.inputs),
+        };
+        if self.load_included_documents(&includes) {
+            // files included from outside of the workspace were added, lint them as well
+            return self.lint_with_progress(progress);
+        }
+        let include_errors = self.find_missing_includes(includes);
+        match loaded {
+            Ok((data, mut errors)) => {
+                errors.extend(include_errors);
                 for error in &errors {
-                    if let Some((index, diag)) = to_diagnostic(&mut self.documents, &data, error) {
-                        self.documents[index].diagnostics.push(diag);
+                    if let Some((index, diag)) = to_diagnostic(&self.documents, &data, error) {
+                        diagnostics[index].push(with_grammar_name(&data, index, error, diag));
                     }
                 }
+                let index = self.collect_symbols(&data.inputs, &data.grammars);
+                self.symbol_index.update(index, true);
                 let symbols = SymbolRegistry::from(&data.grammars);
+                let dependencies = DependencyGraph::from(&data.grammars);
                 self.data = Some(WorkspaceData {
                     inputs: data.inputs,
                     grammars: data.grammars,
                     symbols,
+                    dependencies,
                 });
             }
-            Err(errors) => {
-                let errors = errors.into_static();
+            Err(mut errors) => {
+                errors.errors.extend(include_errors);
                 for error in &errors.errors {
                     if let Some((index, diag)) =
-                        to_diagnostic(&mut self.documents, &errors.context, error)
+                        to_diagnostic(&self.documents, &errors.context, error)
                     {
-                        self.documents[index].diagnostics.push(diag);
+                        diagnostics[index].push(with_grammar_name(
+                            &errors.context,
+                            index,
+                            error,
+                            diag,
+                        ));
                     }
                 }
+                let index = self.collect_symbols(&errors.context.inputs, &errors.context.grammars);
+                self.symbol_index.update(index, false);
+            }
+        }
+        self.documents
+            .iter()
+            .map(|doc| doc.url.clone())
+            .zip(diagnostics)
+            .collect()
+    }
+
+    /// Resolves the includes of the loaded inputs to the paths of the included files
+    /// The path is unknown when the including document is not a file.
+    fn resolve_includes(&self, inputs: &[LoadedInput]) -> Vec<(InputInclude, Option<PathBuf>)> {
+        inputs
+            .iter()
+            .zip(self.documents.iter())
+            .flat_map(|(input, document)| {
+                input.includes.iter().map(|include| {
+                    let path = document
+                        .directory
+                        .as_ref()
+                        .map(|directory| resolve_include_path(directory, &include.path));
+                    (include.clone(), path)
+                })
+            })
+            .collect()
+    }
+
+    /// Gets whether a file is a document of this workspace
+    fn has_document_for(&self, path: &Path) -> bool {
+        Url::from_file_path(path).is_ok_and(|url| self.documents.iter().any(|doc| doc.url == url))
+    }
+
+    /// Loads the included files that are not yet documents of this workspace
+    /// Returns whether documents were added.
+    fn load_included_documents(&mut self, includes: &[(InputInclude, Option<PathBuf>)]) -> bool {
+        let mut added = false;
+        for path in includes.iter().filter_map(|(_, path)| path.as_ref()) {
+            if path.is_file() && !self.has_document_for(path) {
+                added |= self.resolve_document_path(path).is_ok() && self.has_document_for(path);
             }
         }
+        added
     }
 
-    /// Lookups information for symbols matching the query
+    /// Gets the errors for the included files that are not documents of this workspace
+    fn find_missing_includes(&self, includes: Vec<(InputInclude, Option<PathBuf>)>) -> Vec<Error> {
+        includes
+            .into_iter()
+            .filter(|(_, path)| {
+                !path
+                    .as_ref()
+                    .is_some_and(|path| self.has_document_for(path))
+            })
+            .map(|(include, _)| {
+                Error::Parsing(
+                    include.input_ref,
+                    format!("Cannot find included file `{}`", include.path),
+                )
+            })
+            .collect()
+    }
+
+    /// Applies diagnostics, as returned by `lint`, to the documents
+    /// The diagnostics of all documents are replaced, those without diagnostics in the map are cleared.
+    pub fn apply_diagnostics(&mut self, mut diagnostics: HashMap<Url, Vec<Diagnostic>>) {
+        for doc in &mut self.documents {
+            doc.diagnostics = diagnostics.remove(&doc.url).unwrap_or_default();
+        }
+    }
+
+    /// Lookups information for symbols matching the query, e.g. for the `workspace/symbol` request
+    /// The symbols are matched by a case-insensitive subsequence of their name,
+    /// the exact matches come first, then the prefixes, the substrings and the other subsequences.
     #[must_use]
     pub fn lookup_symbols(&self, query: &str) -> Vec<SymbolInformation> {
-        let mut result = Vec::new();
-        if let Some(data) = self.data.as_ref() {
-            let parts = query.split('.').collect::<Vec<_>>();
-            if parts.len() == 2 {
-                // lookup in a specific grammar
-                if let Some(grammar) = data.grammars.iter().find(|g| g.name == parts[0]) {
-                    self.lookup_symbols_in(grammar, &mut result, parts[1]);
-                }
-            } else if parts.len() == 1 {
-                // lookup in all grammars
-                for grammar in &data.grammars {
-                    self.lookup_symbols_in(grammar, &mut result, parts[0]);
-                }
+        self.symbol_index.lookup(query)
+    }
+
+    /// Collects the symbols defined in grammars for the index, by document
+    fn collect_symbols(
+        &self,
+        inputs: &[LoadedInput],
+        grammars: &[Grammar],
+    ) -> HashMap<Url, Vec<SymbolInformation>> {
+        let mut result: HashMap<Url, Vec<SymbolInformation>> = HashMap::new();
+        for grammar in grammars {
+            let mut buffer = Vec::new();
+            self.collect_symbols_in(inputs, grammar, &mut buffer);
+            for symbol in buffer {
+                result
+                    .entry(symbol.location.uri.clone())
+                    .or_default()
+                    .push(symbol);
             }
         }
         result
     }
 
-    /// Lookup symbols matching the specified name in the grammar
-    fn lookup_symbols_in(
+    /// Collects the symbols defined in a grammar
+    fn collect_symbols_in(
         &self,
+        inputs: &[LoadedInput],
         grammar: &Grammar,
         buffer: &mut Vec<SymbolInformation>,
-        query: &str,
     ) {
-        if grammar.name.contains(query) {
+        let container = Some(&grammar.name);
+        buffer.push(self.new_symbol(
+            inputs,
+            grammar.name.to_string(),
+            SymbolKind::CLASS,
+            None,
+            grammar.input_ref,
+        ));
+        for terminal in grammar.terminals.iter().filter(|t| !t.is_anonymous) {
             buffer.push(self.new_symbol(
-                grammar.name.to_string(),
-                SymbolKind::CLASS,
-                grammar.input_ref,
+                inputs,
+                terminal.name.to_string(),
+                SymbolKind::FIELD,
+                container,
+                terminal.input_ref,
             ));
         }
-        for terminal in &grammar.terminals {
-            if !terminal.is_anonymous && terminal.name.contains(query) {
-                buffer.push(self.new_symbol(
-                    format!("{}.{}", grammar.name, terminal.name),
-                    SymbolKind::FIELD,
-                    terminal.input_ref,
-                ));
-            }
-        }
         for variable in &grammar.variables {
-            if variable.generated_for.is_none() && variable.name.contains(query) {
+            if variable.generated_for.is_none() {
                 if let Some(rule) = variable.rules.first() {
                     buffer.push(self.new_symbol(
-                        format!("{}.{}", grammar.name, variable.name),
+                        inputs,
+                        variable.name.to_string(),
                         SymbolKind::PROPERTY,
+                        container,
                         rule.head_input_ref,
                     ));
                 }
             }
         }
         for symbol in &grammar.virtuals {
-            if symbol.name.contains(query) {
-                if let Some(element) =
-                    Self::lookup_symbol_in_rules(grammar, SymbolRef::Virtual(symbol.id))
-                {
-                    buffer.push(self.new_symbol(
-                        format!("{}.{}", grammar.name, symbol.name),
-                        SymbolKind::CONSTANT,
-                        element.input_ref.unwrap(),
-                    ));
-                }
+            if let Some(element) =
+                Self::lookup_symbol_in_rules(grammar, SymbolRef::Virtual(symbol.id))
+            {
+                buffer.push(self.new_symbol(
+                    inputs,
+                    symbol.name.to_string(),
+                    SymbolKind::CONSTANT,
+                    container,
+                    element.input_ref.unwrap(),
+                ));
             }
         }
         for symbol in &grammar.actions {
-            if symbol.name.contains(query) {
-                if let Some(element) =
-                    Self::lookup_symbol_in_rules(grammar, SymbolRef::Action(symbol.id))
-                {
-                    buffer.push(self.new_symbol(
-                        format!("{}.{}", grammar.name, symbol.name),
-                        SymbolKind::METHOD,
-                        element.input_ref.unwrap(),
-                    ));
-                }
+            if let Some(element) =
+                Self::lookup_symbol_in_rules(grammar, SymbolRef::Action(symbol.id))
+            {
+                buffer.push(self.new_symbol(
+                    inputs,
+                    symbol.name.to_string(),
+                    SymbolKind::METHOD,
+                    container,
+                    element.input_ref.unwrap(),
+                ));
             }
         }
     }
@@ -369,21 +762,14 @@ impl Workspace {
         line: u32,
         character: u32,
     ) -> Option<GotoDefinitionResponse> {
-        let doc_index = self
-            .documents
-            .iter()
-            .enumerate()
-            .find(|(_, doc)| doc.url.as_str() == doc_uri)?
-            .0;
-        let input_ref = InputReference {
-            input_index: doc_index,
-            position: TextPosition {
-                line: line as usize + 1,
-                column: character as usize + 1,
-            },
-            length: 0,
-        };
+        let input_ref = self.get_input_ref_at(doc_uri, line, character)?;
         let data = self.data.as_ref()?;
+        if let Some(dependency) = data.dependencies.find_at(&input_ref) {
+            let target = &data.grammars[dependency.target?];
+            return Some(GotoDefinitionResponse::Scalar(
+                self.get_location(target.input_ref),
+            ));
+        }
         let symbol = data.find_symbol_at(input_ref)?;
         if symbol.definitions.is_empty() {
             None
@@ -410,20 +796,7 @@ impl Workspace {
         line: u32,
         character: u32,
     ) -> Option<Vec<Location>> {
-        let doc_index = self
-            .documents
-            .iter()
-            .enumerate()
-            .find(|(_, doc)| doc.url.as_str() == doc_uri)?
-            .0;
-        let input_ref = InputReference {
-            input_index: doc_index,
-            position: TextPosition {
-                line: line as usize + 1,
-                column: character as usize + 1,
-            },
-            length: 0,
-        };
+        let input_ref = self.get_input_ref_at(doc_uri, line, character)?;
         let data = self.data.as_ref()?;
         let symbol = data.find_symbol_at(input_ref)?;
         let mut references = Vec::new();
@@ -448,20 +821,7 @@ impl Workspace {
         line: u32,
         character: u32,
     ) -> Option<Hover> {
-        let doc_index = self
-            .documents
-            .iter()
-            .enumerate()
-            .find(|(_, doc)| doc.url.as_str() == doc_uri)?
-            .0;
-        let input_ref = InputReference {
-            input_index: doc_index,
-            position: TextPosition {
-                line: line as usize + 1,
-                column: character as usize + 1,
-            },
-            length: 0,
-        };
+        let input_ref = self.get_input_ref_at(doc_uri, line, character)?;
         let data = self.data.as_ref()?;
         let symbol = data.find_symbol_at(input_ref)?;
         let content = match symbol.symbol_ref {
@@ -469,14 +829,18 @@ impl Workspace {
             SymbolRef::Epsilon => String::from("<epsilon>"),
             SymbolRef::Dollar => String::from("<dollar>"),
             SymbolRef::NullTerminal => String::from("<null>"),
-            SymbolRef::Terminal(sid) => data.grammars[symbol.grammar_index]
-                .get_terminal(sid)
-                .unwrap()
-                .get_description(),
-            SymbolRef::Variable(sid) => data.grammars[symbol.grammar_index]
-                .get_variable(sid)
-                .unwrap()
-                .get_description(),
+            SymbolRef::Terminal(sid) => {
+                let terminal = data.grammars[symbol.grammar_index]
+                    .get_terminal(sid)
+                    .unwrap();
+                with_annotations(terminal.get_description(), &terminal.annotations)
+            }
+            SymbolRef::Variable(sid) => {
+                let variable = data.grammars[symbol.grammar_index]
+                    .get_variable(sid)
+                    .unwrap();
+                with_annotations(variable.get_description(), &variable.annotations)
+            }
             SymbolRef::Virtual(sid) => data.grammars[symbol.grammar_index]
                 .get_virtual(sid)
                 .unwrap()
@@ -525,6 +889,146 @@ impl Workspace {
         }
     }
 
+    /// Gets the links in a document from the references to other grammars
+    /// to the documents that define them
+    #[must_use]
+    pub fn get_document_links(&self, doc_uri: &str) -> Option<Vec<DocumentLink>> {
+        let doc_index = self
+            .documents
+            .iter()
+            .position(|doc| doc.url.as_str() == doc_uri)?;
+        let data = self.data.as_ref()?;
+        let result: Vec<DocumentLink> = data
+            .dependencies
+            .in_input(doc_index)
+            .filter_map(|dependency| {
+                let target = &data.grammars[dependency.target?];
+                Some(DocumentLink {
+                    range: self.get_range(dependency.input_ref),
+                    target: Some(self.documents[target.input_ref.input_index].url.clone()),
+                    tooltip: Some(format!("Go to grammar `{}`", target.name)),
+                    data: None,
+                })
+            })
+            .collect();
+        if result.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Gets the code actions fixing the diagnostics of a document
+    /// For a reference to an undefined grammar,
+    /// this offers to create the missing grammar in a new document next to this one.
+    #[must_use]
+    pub fn get_code_actions(
+        &self,
+        doc_uri: &Url,
+        diagnostics: &[Diagnostic],
+    ) -> Option<Vec<CodeActionOrCommand>> {
+        let result: Vec<CodeActionOrCommand> = diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                diagnostic.code
+                    == Some(NumberOrString::String(String::from(
+                        CODE_GRAMMAR_NOT_DEFINED,
+                    )))
+            })
+            .filter_map(|diagnostic| {
+                let name = diagnostic.data.as_ref()?.get("grammar")?.as_str()?;
+                let uri = doc_uri.join(&format!("{name}.gram")).ok()?;
+                if self.documents.iter().any(|doc| doc.url == uri) {
+                    return None;
+                }
+                Some(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Create grammar `{name}` in {name}.gram"),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(new_grammar_edit(uri, name)),
+                    ..CodeAction::default()
+                }))
+            })
+            .collect();
+        if result.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Gets the inlay hints for a document within a range,
+    /// showing the FIRSTS set of the variables referenced in rule bodies.
+    /// Hints are not produced for variables that can only start with a single terminal.
+    #[must_use]
+    pub fn get_inlay_hints(&self, doc_uri: &str, range: Range) -> Option<Vec<InlayHint>> {
+        let doc_index = self
+            .documents
+            .iter()
+            .position(|doc| doc.url.as_str() == doc_uri)?;
+        let data = self.data.as_ref()?;
+        let mut positions = HashSet::new();
+        let mut result = Vec::new();
+        for grammar in &data.grammars {
+            for variable in &grammar.variables {
+                for rule in &variable.rules {
+                    // only look at the rules defined by this grammar, not the inherited ones
+                    if !variable.definitions.iter().any(|definition| {
+                        definition.grammar == grammar.name
+                            && definition.input_ref == rule.head_input_ref
+                    }) {
+                        continue;
+                    }
+                    for element in &rule.body.elements {
+                        let (SymbolRef::Variable(id), Some(input_ref)) =
+                            (element.symbol, element.input_ref)
+                        else {
+                            continue;
+                        };
+                        if input_ref.input_index != doc_index
+                            || grammar
+                                .get_variable(id)
+                                .is_some_and(|target| target.generated_for.is_some())
+                        {
+                            continue;
+                        }
+                        let position = self.get_range(input_ref).end;
+                        if position < range.start
+                            || position > range.end
+                            || !positions.insert((position.line, position.character))
+                        {
+                            continue;
+                        }
+                        let firsts = grammar.first_set(element.symbol);
+                        if firsts.len() <= 1 {
+                            continue;
+                        }
+                        let names: Vec<String> = firsts
+                            .iter()
+                            .map(|terminal| terminal.name_in(grammar))
+                            .collect();
+                        result.push(InlayHint {
+                            position,
+                            label: InlayHintLabel::String(format!("{{{}}}", names.join(", "))),
+                            kind: None,
+                            text_edits: None,
+                            tooltip: None,
+                            padding_left: Some(true),
+                            padding_right: None,
+                            data: None,
+                        });
+                    }
+                }
+            }
+        }
+        if result.is_empty() {
+            None
+        } else {
+            result.sort_by_key(|hint| hint.position);
+            Some(result)
+        }
+    }
+
     /// Tests an input against a grammar
     ///
     /// # Errors
@@ -563,6 +1067,66 @@ impl Workspace {
         }
     }
 
+    /// Parses a sample input against the last grammar defined in a document,
+    /// compiling it in memory, so that the result can be previewed in the editor
+    #[must_use]
+    pub fn try_parse(&self, uri: &Url, sample: &str) -> ParsePreview {
+        let Some(doc_index) = self.documents.iter().position(|doc| &doc.url == uri) else {
+            return ParsePreview::Unavailable(format!("Document {uri} is not in the workspace"));
+        };
+        let Some(data) = self.data.as_ref() else {
+            return ParsePreview::Unavailable(String::from("The workspace has not been loaded"));
+        };
+        let Some((grammar_index, grammar)) = data
+            .grammars
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, grammar)| grammar.input_ref.input_index == doc_index)
+        else {
+            return ParsePreview::Unavailable(format!("Document {uri} does not define a grammar"));
+        };
+        let name = grammar.name.clone();
+        let mut grammar = grammar.clone();
+        let task = CompilationTask::default();
+        let Ok(parser) = task.generate_in_memory(&mut grammar, grammar_index) else {
+            return ParsePreview::Unavailable(format!("Grammar `{name}` cannot be compiled"));
+        };
+        let result = parser.parse(sample);
+        if result.is_success() {
+            return ParsePreview::Tree(
+                serde_json::to_value(result.get_ast().get_root()).unwrap_or_default(),
+            );
+        }
+        let lines = LineIndex::new(sample);
+        ParsePreview::Errors(
+            result
+                .errors
+                .errors
+                .iter()
+                .map(|error| {
+                    let position = error.get_position();
+                    let end = result.text.get_position_for(position, error.get_length());
+                    ParsePreviewError {
+                        range: Range::new(
+                            to_lsp_position(&lines, &result.text, position),
+                            to_lsp_position(&lines, &result.text, end),
+                        ),
+                        message: error.to_string(),
+                        expected: match error {
+                            ParseError::UnexpectedToken(error) => error
+                                .get_expected()
+                                .iter()
+                                .map(|symbol| symbol.name.to_string())
+                                .collect(),
+                            _ => Vec::new(),
+                        },
+                    }
+                })
+                .collect(),
+        )
+    }
+
     /// Finds a symbol in a rule
     fn lookup_symbol_in_rules(grammar: &Grammar, symbol_ref: SymbolRef) -> Option<RuleBodyElement> {
         for variable in &grammar.variables {
@@ -581,8 +1145,10 @@ impl Workspace {
     #[allow(deprecated)]
     fn new_symbol(
         &self,
+        inputs: &[LoadedInput],
         name: String,
         kind: SymbolKind,
+        container: Option<&String>,
         input_ref: InputReference,
     ) -> SymbolInformation {
         SymbolInformation {
@@ -590,28 +1156,163 @@ impl Workspace {
             kind,
             tags: None,
             deprecated: None,
-            location: self.get_location(input_ref),
-            container_name: None,
+            location: Location {
+                range: to_range(&self.documents, inputs, input_ref),
+                uri: self.documents[input_ref.input_index].url.clone(),
+            },
+            container_name: container.cloned(),
         }
     }
 
-    /// Transforms an input reference into a location
-    fn get_location(&self, input_ref: InputReference) -> Location {
+    /// Gets the input reference for a LSP position in a document
+    /// The characters of the position are counted in UTF-16 code units, as per the LSP.
+    fn get_input_ref_at(&self, doc_uri: &str, line: u32, character: u32) -> Option<InputReference> {
+        let input_index = self
+            .documents
+            .iter()
+            .position(|doc| doc.url.as_str() == doc_uri)?;
+        let offset = self.documents[input_index]
+            .line_index()?
+            .offset_of(Position::new(line, character));
+        let input = self.data.as_ref()?.inputs.get(input_index)?;
+        Some(InputReference {
+            input_index,
+            position: input.content.get_position_at(offset),
+            length: 0,
+        })
+    }
+
+    /// Translates an input reference to a LSP range
+    fn get_range(&self, input_ref: InputReference) -> Range {
         // we expect to have loaded data when calling this method,
         // otherwise we would not have an input reference in argument
         let data = self.data.as_ref().unwrap();
+        to_range(&self.documents, &data.inputs, input_ref)
+    }
+
+    /// Transforms an input reference into a location
+    fn get_location(&self, input_ref: InputReference) -> Location {
         let document = &self.documents[input_ref.input_index];
         Location {
-            range: data.get_range(input_ref),
+            range: self.get_range(input_ref),
             uri: document.url.clone(),
         }
     }
 }
 
+/// Gets the template for a new grammar
+fn new_grammar_template(name: &str) -> String {
+    format!(
+        r#"grammar {name}
+{{
+  options
+  {{
+    Axiom = "root";
+  }}
+  terminals
+  {{
+    NAME -> [a-zA-Z_] [a-zA-Z0-9_]* ;
+  }}
+  rules
+  {{
+    root -> NAME ;
+  }}
+}}
+"#
+    )
+}
+
+/// Gets the edit creating a new document for a grammar
+fn new_grammar_edit(uri: Url, name: &str) -> WorkspaceEdit {
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: uri.clone(),
+                options: None,
+                annotation_id: None,
+            })),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: vec![OneOf::Left(TextEdit {
+                    range: Range::default(),
+                    new_text: new_grammar_template(name),
+                })],
+            }),
+        ])),
+        ..WorkspaceEdit::default()
+    }
+}
+
+/// Appends the user-defined annotations of a symbol to its description, one per line
+fn with_annotations(mut description: String, annotations: &[Annotation]) -> String {
+    for annotation in annotations {
+        description.push_str(&format!("\n@{}({:?})", annotation.key, annotation.value));
+    }
+    description
+}
+
+/// Translates an input reference to a LSP range
+/// The positions are converted with the index of the lines in the input's document,
+/// so that the characters are counted in UTF-16 code units, as per the LSP.
+fn to_range(
+    documents: &[Document],
+    inputs: &[LoadedInput],
+    input_reference: InputReference,
+) -> Range {
+    let text = &inputs[input_reference.input_index].content;
+    let end = text.get_position_for(input_reference.position, input_reference.length);
+    let to_range = |lines: &LineIndex| {
+        Range::new(
+            to_lsp_position(lines, text, input_reference.position),
+            to_lsp_position(lines, text, end),
+        )
+    };
+    match documents
+        .get(input_reference.input_index)
+        .and_then(Document::line_index)
+    {
+        Some(lines) => to_range(lines),
+        None => to_range(&LineIndex::new(text.get_value(0, text.len()))),
+    }
+}
+
+/// Converts a position in a text to a LSP position, with the index of the lines in the text
+/// The characters are counted in UTF-16 code units, as per the LSP.
+fn to_lsp_position(lines: &LineIndex, text: &Text, position: TextPosition) -> Position {
+    lines.position_of(text.get_index_at(position))
+}
+
+/// Adds the name of the grammar an error is about to its diagnostic in an input
+/// The name is only added when it is ambiguous, i.e. when the input defines several grammars
+/// or when the grammar is defined in another input.
+fn with_grammar_name(
+    data: &LoadedData,
+    input_index: usize,
+    error: &Error,
+    mut diagnostic: Diagnostic,
+) -> Diagnostic {
+    let Some(grammar) = error
+        .get_grammar_index()
+        .and_then(|index| data.grammars.get(index))
+    else {
+        return diagnostic;
+    };
+    let in_input = data
+        .grammars
+        .iter()
+        .filter(|g| g.input_ref.input_index == input_index)
+        .count();
+    if in_input > 1 || grammar.input_ref.input_index != input_index {
+        diagnostic.message = format!("[{}] {}", grammar.name, diagnostic.message);
+        diagnostic.source = Some(format!("{} ({})", super::CRATE_NAME, grammar.name));
+    }
+    diagnostic
+}
+
 /// Converts an error to a diagnostic
 #[allow(clippy::too_many_lines)]
 fn to_diagnostic(
-    documents: &mut [Document],
+    documents: &[Document],
     data: &LoadedData,
     error: &Error,
 ) -> Option<(usize, Diagnostic)> {
@@ -619,7 +1320,7 @@ fn to_diagnostic(
         Error::Parsing(input_reference, msg) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -641,7 +1342,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -658,7 +1359,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -678,7 +1379,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -690,15 +1391,35 @@ fn to_diagnostic(
                 },
             ))
         }
-        Error::SeparatorNotDefined(grammar_index) => {
+        Error::EntryNotDefined(grammar_index, name) => {
             let option = data.grammars[*grammar_index]
-                .get_option(OPTION_SEPARATOR)
+                .get_option(OPTION_ENTRIES)
                 .unwrap();
             let input_reference = option.value_input_ref;
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!("Grammar entry point `{name}` is not defined"),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::SeparatorNotDefined(grammar_index) => {
+            let option = data.grammars[*grammar_index]
+                .get_option(OPTION_SEPARATOR)
+                .unwrap();
+            let input_reference = option.value_input_ref;
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -719,7 +1440,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -743,7 +1464,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -761,7 +1482,7 @@ fn to_diagnostic(
         Error::TemplateRuleNotFound(input_reference, name) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -775,7 +1496,7 @@ fn to_diagnostic(
         Error::TemplateRuleWrongNumberOfArgs(input_reference, expected, provided) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -789,7 +1510,7 @@ fn to_diagnostic(
         Error::SymbolNotFound(input_reference, name) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -800,10 +1521,26 @@ fn to_diagnostic(
                 data: None,
             },
         )),
+        Error::RecursiveTerminalReference(input_reference, name) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!(
+                    "Terminal `{name}` refers to itself, terminals cannot be recursive"
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )),
         Error::InvalidCharacterSpan(input_reference) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -814,10 +1551,56 @@ fn to_diagnostic(
                 data: None,
             },
         )),
+        Error::InvalidLookahead(input_reference) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: "Invalid lookahead, only single characters can be excluded".to_string(),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::AnnotationWithoutSymbol(input_reference, key) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!(
+                    "Annotation `@{key}` is not followed by the declaration of a terminal or a variable"
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::InvalidExternalScanner(input_reference, name) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!(
+                    "Invalid external scanner `{name}`, expected the path to a Rust function"
+                ),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )),
         Error::UnknownUnicodeBlock(input_reference, name) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -831,7 +1614,7 @@ fn to_diagnostic(
         Error::UnknownUnicodeCategory(input_reference, name) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -845,7 +1628,7 @@ fn to_diagnostic(
         Error::UnsupportedNonPlane0InCharacterClass(input_reference, c) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -861,7 +1644,7 @@ fn to_diagnostic(
         Error::InvalidCodePoint(input_reference, c) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -875,7 +1658,7 @@ fn to_diagnostic(
         Error::OverridingPreviousTerminal(input_reference, name, _previous) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
@@ -886,24 +1669,236 @@ fn to_diagnostic(
                 data: None,
             },
         )),
-        Error::GrammarNotDefined(input_reference, name) => Some((
+        Error::TerminalInSeveralGroups(input_reference, name, _previous) => Some((
             input_reference.input_index,
             Diagnostic {
-                range: WorkspaceData::to_range(&data.inputs, *input_reference),
+                range: to_range(documents, &data.inputs, *input_reference),
                 severity: Some(DiagnosticSeverity::ERROR),
                 code: None,
                 code_description: None,
                 source: Some(super::CRATE_NAME.to_string()),
+                message: format!("Terminal `{name}` is already in another priority group"),
+                related_information: None,
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::GrammarNotDefined(input_reference, name) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(String::from(CODE_GRAMMAR_NOT_DEFINED))),
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
                 message: format!("Grammar `{name}` is not defined"),
                 related_information: None,
                 tags: None,
+                data: Some(json!({ "grammar": name })),
+            },
+        )),
+        Error::DuplicateGrammar(input_reference, name, other) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!("Grammar `{name}` is declared multiple times"),
+                related_information: Some(vec![DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[other.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, *other),
+                    },
+                    message: format!("Other declaration of `{name}`"),
+                }]),
+                tags: None,
                 data: None,
             },
         )),
+        Error::RedefinedTerminal(input_reference, name, previous) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!(
+                    "Terminal `{name}` redefines an inherited terminal with a different pattern"
+                ),
+                related_information: Some(vec![DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[previous.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, *previous),
+                    },
+                    message: format!("Inherited definition of `{name}`"),
+                }]),
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::RedefinedVariable(input_reference, name, previous) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!(
+                    "Variable `{name}` redefines an inherited variable with different alternatives"
+                ),
+                related_information: Some(vec![DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[previous.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, *previous),
+                    },
+                    message: format!("Inherited definition of `{name}`"),
+                }]),
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::ConflictingInheritedSymbol(input_reference, name, definition, previous) => Some((
+            input_reference.input_index,
+            Diagnostic {
+                range: to_range(documents, &data.inputs, *input_reference),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: None,
+                code_description: None,
+                source: Some(super::CRATE_NAME.to_string()),
+                message: format!("Symbol `{name}` is defined differently by inherited grammars"),
+                related_information: Some(vec![
+                    DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: documents[definition.input_index].url.clone(),
+                            range: to_range(documents, &data.inputs, *definition),
+                        },
+                        message: format!("Definition of `{name}` in this parent"),
+                    },
+                    DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: documents[previous.input_index].url.clone(),
+                            range: to_range(documents, &data.inputs, *previous),
+                        },
+                        message: format!("Definition of `{name}` in a previous parent"),
+                    },
+                ]),
+                tags: None,
+                data: None,
+            },
+        )),
+        Error::StateLimitExceeded(grammar_index, error) => {
+            let grammar = &data.grammars[*grammar_index];
+            let heads = error
+                .heads
+                .iter()
+                .take(5)
+                .map(|(variable, _count)| {
+                    format!(
+                        "`{}`",
+                        grammar.get_symbol_name(SymbolRef::Variable(*variable))
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            Some((
+                grammar.input_ref.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, grammar.input_ref),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "LR graph has more than {} states with method {:?}, the most recent states are mostly for {heads}",
+                        error.limit, error.method
+                    ),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
         Error::LrConflict(grammar_index, conflict) => {
             let grammar = &data.grammars[*grammar_index];
-            let _terminal = grammar.get_symbol_value(conflict.lookahead.terminal.into());
-            None
+            let terminal = conflict.lookahead.terminal.name_in(grammar);
+            let mut related = Vec::new();
+            for item in &conflict.shift_items {
+                let input_ref = get_item_input_reference(grammar, item);
+                related.push(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[input_ref.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, input_ref),
+                    },
+                    message: format!("Could consume `{terminal}` at this point"),
+                });
+            }
+            for item in &conflict.reduce_items {
+                let input_ref = get_item_input_reference(grammar, item);
+                related.push(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[input_ref.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, input_ref),
+                    },
+                    message: format!(
+                        "Could match the rule ending here when looking ahead to `{terminal}`"
+                    ),
+                });
+            }
+            // report on the first symbol involved in the conflict
+            let input_reference = conflict
+                .shift_items
+                .first()
+                .or_else(|| conflict.reduce_items.first())
+                .map_or(grammar.input_ref, |item| {
+                    get_item_input_reference(grammar, item)
+                });
+            let entry = conflict.entry.map_or(String::new(), |entry| {
+                format!(
+                    " when parsing from entry point `{}`",
+                    grammar.get_symbol_name(SymbolRef::Variable(entry))
+                )
+            });
+            let contexts = conflict.get_context_names(grammar);
+            let contexts = if contexts.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " in context{} `{}`",
+                    if contexts.len() > 1 { "s" } else { "" },
+                    contexts.join("`, `")
+                )
+            };
+            let example = conflict.phrases.first().map_or(String::new(), |phrase| {
+                format!(
+                    "\nExample of input that is ambiguous: {}",
+                    phrase.display(grammar)
+                )
+            });
+            let message = format!(
+                "{} conflict, cannot decide what to do facing `{terminal}`{contexts}{entry}{example}",
+                match conflict.kind {
+                    ConflictKind::ShiftReduce => "Shift/Reduce",
+                    ConflictKind::ReduceReduce => "Reduce/Reduce",
+                }
+            );
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message,
+                    related_information: Some(related),
+                    tags: None,
+                    data: None,
+                },
+            ))
         }
         Error::TerminalOutsideContext(grammar_index, error) => {
             let grammar = &data.grammars[*grammar_index];
@@ -912,7 +1907,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -932,7 +1927,7 @@ fn to_diagnostic(
                                 DiagnosticRelatedInformation {
                                     location: Location {
                                         uri: documents[input_ref.input_index].url.clone(),
-                                        range: WorkspaceData::to_range(&data.inputs, input_ref),
+                                        range: to_range(documents, &data.inputs, input_ref),
                                     },
                                     message: String::from("Used outside required context"),
                                 }
@@ -952,7 +1947,7 @@ fn to_diagnostic(
             Some((
                 input_reference.input_index,
                 Diagnostic {
-                    range: WorkspaceData::to_range(&data.inputs, input_reference),
+                    range: to_range(documents, &data.inputs, input_reference),
                     severity: Some(DiagnosticSeverity::ERROR),
                     code: None,
                     code_description: None,
@@ -967,10 +1962,196 @@ fn to_diagnostic(
                 },
             ))
         }
+        Error::LookaheadNotSupported(grammar_index, terminal_ref) => {
+            let terminal = data.grammars[*grammar_index]
+                .get_terminal(terminal_ref.sid())
+                .unwrap();
+            let input_reference = terminal.input_ref;
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Terminal `{}` uses a lookahead, which is only supported for Rust",
+                        &terminal.name
+                    ),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::ExternalTerminalNotSupported(grammar_index, terminal_ref) => {
+            let terminal = data.grammars[*grammar_index]
+                .get_terminal(terminal_ref.sid())
+                .unwrap();
+            let input_reference = terminal.input_ref;
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Terminal `{}` is matched by an external scanner, which is only supported for Rust",
+                        &terminal.name
+                    ),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::NullableUnitRule(grammar_index, rule_ref, conflicting_ref) => {
+            let grammar = &data.grammars[*grammar_index];
+            let rule = rule_ref.get_rule_in(grammar);
+            let element = rule.body.choices[0].elements[0];
+            let input_reference = element.input_ref.unwrap_or(rule.head_input_ref);
+            let conflicting = conflicting_ref.get_rule_in(grammar);
+            let conflicting_input = conflicting.body.choices[0]
+                .elements
+                .first()
+                .and_then(|element| element.input_ref)
+                .unwrap_or(conflicting.head_input_ref);
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Rule for `{}` may match the empty string through nullable variable `{}`",
+                        grammar.get_symbol_name(SymbolRef::Variable(rule.head)),
+                        grammar.get_symbol_name(element.symbol)
+                    ),
+                    related_information: Some(vec![DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: documents[conflicting_input.input_index].url.clone(),
+                            range: to_range(documents, &data.inputs, conflicting_input),
+                        },
+                        message: String::from("Conflicting alternative"),
+                    }]),
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::AxiomReferenced(grammar_index, rule_ref, position) => {
+            let grammar = &data.grammars[*grammar_index];
+            let rule = rule_ref.get_rule_in(grammar);
+            let input_reference = rule.body.elements[*position]
+                .input_ref
+                .unwrap_or(rule.head_input_ref);
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Axiom `{}` derives itself through a rule for `{}`",
+                        grammar.get_axiom().unwrap_or_default(),
+                        grammar.get_symbol_name(SymbolRef::Variable(rule.head))
+                    ),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::UnusedContext(grammar_index, context) => {
+            let grammar = &data.grammars[*grammar_index];
+            let input_reference = grammar
+                .terminals
+                .iter()
+                .find(|terminal| terminal.context == *context)
+                .map_or(grammar.input_ref, |terminal| terminal.input_ref);
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Lexical context `{}` is never opened, its terminals can never be matched",
+                        &grammar.contexts[*context]
+                    ),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
+        Error::TerminalShadowed(grammar_index, error) => {
+            let grammar = &data.grammars[*grammar_index];
+            let terminal = grammar.get_terminal(error.terminal.sid()).unwrap();
+            let input_reference = terminal.input_ref;
+            let mut related = vec![DiagnosticRelatedInformation {
+                location: Location {
+                    uri: documents[input_reference.input_index].url.clone(),
+                    range: to_range(documents, &data.inputs, input_reference),
+                },
+                message: format!("Shadowed terminal `{}`", &terminal.name),
+            }];
+            for overrider in &error.overriders {
+                let overrider = grammar.get_terminal(overrider.sid()).unwrap();
+                related.push(DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: documents[overrider.input_ref.input_index].url.clone(),
+                        range: to_range(documents, &data.inputs, overrider.input_ref),
+                    },
+                    message: format!(
+                        "`{}` has more priority and matches instead",
+                        &overrider.name
+                    ),
+                });
+            }
+            Some((
+                input_reference.input_index,
+                Diagnostic {
+                    range: to_range(documents, &data.inputs, input_reference),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: None,
+                    code_description: None,
+                    source: Some(super::CRATE_NAME.to_string()),
+                    message: format!(
+                        "Terminal `{}` can never be matched, it is always shadowed by terminals with more priority",
+                        &terminal.name
+                    ),
+                    related_information: Some(related),
+                    tags: None,
+                    data: None,
+                },
+            ))
+        }
         _ => None,
     }
 }
 
+/// Gets the input reference for the symbol at the position of an item in its rule,
+/// or for the last symbol of the rule when the item is a reduction
+fn get_item_input_reference(grammar: &Grammar, item: &Item) -> InputReference {
+    let rule = item.rule.get_rule_in(grammar);
+    let elements = &rule.body.choices[0].elements;
+    let position = item.position.min(elements.len().saturating_sub(1));
+    elements
+        .get(position)
+        .and_then(|element| element.input_ref)
+        .unwrap_or(rule.head_input_ref)
+}
+
 #[test]
 fn test_scan_workspace_in() -> io::Result<()> {
     let mut workspace = Workspace::default();
@@ -998,3 +2179,596 @@ fn test_scan_workspace() -> io::Result<()> {
     assert!(!workspace.documents.is_empty());
     Ok(())
 }
+
+#[test]
+fn test_lint_conflict_points_to_rule_symbol() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> x | y ; x -> A ; y -> A ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    let diagnostic = &diagnostics[&workspace.documents[0].url][0];
+    assert!(diagnostic.message.starts_with("Reduce/Reduce conflict"));
+    // the diagnostic points to the `A` symbol in the body of `x`
+    assert_eq!(diagnostic.range.start.character, 90);
+    assert_eq!(diagnostic.range.end.character, 91);
+    assert_eq!(diagnostic.related_information.as_ref().unwrap().len(), 2);
+}
+
+#[test]
+fn test_inlay_hints_show_firsts() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> x y ; x -> A | B | ; y -> A ; } }",
+        ),
+    ));
+    workspace.lint();
+    let range = Range::new(Position::new(0, 0), Position::new(1, 0));
+    let hints = workspace
+        .get_inlay_hints("file:///test.gram", range)
+        .unwrap();
+    // no hint for `y` that only starts with `A`
+    assert_eq!(hints.len(), 1);
+    assert_eq!(hints[0].position, Position::new(0, 88));
+    assert!(matches!(&hints[0].label, InlayHintLabel::String(label) if label == "{ε, A, B}"));
+    // no hint outside of the range
+    let range = Range::new(Position::new(0, 0), Position::new(0, 80));
+    assert!(workspace
+        .get_inlay_hints("file:///test.gram", range)
+        .is_none());
+}
+
+#[test]
+fn test_try_parse_preview() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A B ; } }",
+        ),
+    ));
+    workspace.lint();
+    let ParsePreview::Tree(root) = workspace.try_parse(&url, "ab") else {
+        panic!("expected a parse tree");
+    };
+    assert_eq!(root["symbol"]["name"], "e");
+    let ParsePreview::Errors(errors) = workspace.try_parse(&url, "aa") else {
+        panic!("expected parse errors");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].range.start, Position::new(0, 1));
+    assert_eq!(errors[0].range.end, Position::new(0, 2));
+    assert_eq!(errors[0].expected, ["B"]);
+    // the ranges count the characters in UTF-16 code units
+    let ParsePreview::Errors(errors) = workspace.try_parse(&url, "𝄞aa") else {
+        panic!("expected parse errors");
+    };
+    assert_eq!(
+        errors.iter().map(|error| error.range).collect::<Vec<_>>(),
+        [
+            Range::new(Position::new(0, 0), Position::new(0, 2)),
+            Range::new(Position::new(0, 3), Position::new(0, 4))
+        ]
+    );
+    let unknown = Url::parse("file:///unknown.gram").unwrap();
+    assert!(matches!(
+        workspace.try_parse(&unknown, "ab"),
+        ParsePreview::Unavailable(_)
+    ));
+}
+
+#[test]
+fn test_lint_duplicate_grammars() {
+    let content =
+        "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }";
+    let mut workspace = Workspace::default();
+    for url in ["file:///first.gram", "file:///second.gram"] {
+        workspace.documents.push(Document::new(
+            Url::parse(url).unwrap(),
+            String::from(content),
+        ));
+    }
+    let diagnostics = workspace.lint();
+    for (index, other) in [(0, "file:///second.gram"), (1, "file:///first.gram")] {
+        let diagnostics = &diagnostics[&workspace.documents[index].url];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 8));
+        let related = diagnostics[0].related_information.as_ref().unwrap();
+        assert_eq!(related[0].location.uri.as_str(), other);
+    }
+}
+
+#[test]
+fn test_lint_then_apply_diagnostics() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from("grammar Test { options { Axiom = \"e\"; } rules { e -> A ; } }"),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(!diagnostics[&url].is_empty());
+    // linting does not modify the documents
+    assert!(workspace.documents[0].diagnostics.is_empty());
+    workspace.apply_diagnostics(diagnostics.clone());
+    assert_eq!(workspace.documents[0].diagnostics, diagnostics[&url]);
+    // fixing the document clears its diagnostics once applied
+    workspace.documents[0].set_content(String::from(
+        "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics[&url].is_empty());
+    assert!(!workspace.documents[0].diagnostics.is_empty());
+    workspace.apply_diagnostics(diagnostics);
+    assert!(workspace.documents[0].diagnostics.is_empty());
+}
+
+#[test]
+fn test_lint_with_cache() {
+    let cache_dir = hime_sdk::output::temporary_folder();
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A '+' e | A ; } }",
+        ),
+    ));
+    let expected = workspace.lint();
+    // the first lint builds the graph and caches it
+    workspace.graph_cache = Some(cache_dir.clone());
+    assert_eq!(workspace.lint(), expected);
+    let path = cache_dir.join("Test.hgb");
+    let cached = std::fs::read(&path).unwrap();
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    // the second lint reloads the graph without writing it again
+    assert_eq!(workspace.lint(), expected);
+    assert_eq!(
+        std::fs::metadata(&path).unwrap().modified().unwrap(),
+        modified
+    );
+    let data = workspace.data.as_ref().unwrap();
+    assert_eq!(data.grammars.len(), 1);
+    // a change in the grammar invalidates the cache
+    workspace.documents[0].set_content(String::from(
+        "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A '-' e | A ; } }",
+    ));
+    workspace.graph_cache = None;
+    let expected = workspace.lint();
+    workspace.graph_cache = Some(cache_dir.clone());
+    assert_eq!(workspace.lint(), expected);
+    assert_ne!(std::fs::read(&path).unwrap(), cached);
+    std::fs::remove_dir_all(cache_dir).unwrap();
+}
+
+#[test]
+fn test_decode_content() {
+    let text = "grammar Test { }";
+    // plain UTF-8, with and without BOM
+    assert_eq!(decode_content(text.as_bytes()), (text.to_string(), None));
+    let mut bytes = vec![0xEF, 0xBB, 0xBF];
+    bytes.extend_from_slice(text.as_bytes());
+    assert_eq!(decode_content(&bytes), (text.to_string(), None));
+    // UTF-16LE
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    assert_eq!(decode_content(&bytes), (text.to_string(), None));
+    // UTF-16BE
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    assert_eq!(decode_content(&bytes), (text.to_string(), None));
+    // invalid UTF-8 falls back to lossy decoding
+    let (content, warning) = decode_content(b"grammar \xFF { }");
+    assert_eq!(content, "grammar \u{FFFD} { }");
+    assert!(warning.is_some());
+}
+
+#[test]
+fn test_lint_reports_decoding_warning() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::from_bytes(
+        Url::parse("file:///test.gram").unwrap(),
+        b"grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } } // \xFF",
+    ));
+    let diagnostics = workspace.lint();
+    let diagnostics = &diagnostics[&workspace.documents[0].url];
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+}
+
+#[test]
+fn test_hover_inline_terminal() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e '+' A | A '+' ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics[&workspace.documents[0].url].is_empty());
+    // inline terminals are not public symbols
+    assert!(workspace.lookup_symbols("__T").is_empty());
+    for start in [79, 89] {
+        let hover = workspace
+            .get_symbol_description_at("file:///test.gram", 0, start + 1)
+            .unwrap();
+        assert!(
+            hover.contents
+                == HoverContents::Scalar(MarkedString::String(String::from("Inline terminal `+`")))
+        );
+        assert_eq!(hover.range.unwrap().start.character, start);
+        assert_eq!(hover.range.unwrap().end.character, start + 3);
+    }
+}
+
+#[test]
+fn test_hover_after_wide_chars() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { NUM -> 'a'; } rules { /* 𝄞 */ e -> NUM ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics[&workspace.documents[0].url].is_empty());
+    // '𝄞' is 1 character, but 2 UTF-16 code units
+    let hover = workspace
+        .get_symbol_description_at("file:///test.gram", 0, 90)
+        .unwrap();
+    assert_eq!(
+        hover.range.unwrap(),
+        Range::new(Position::new(0, 88), Position::new(0, 91))
+    );
+    assert!(workspace
+        .get_symbol_description_at("file:///test.gram", 0, 91)
+        .is_none());
+}
+
+#[test]
+fn test_hover_terminal_alias() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { T_PLUS -> '+'; %alias T_PLUS \"+\"; } rules { e -> T_PLUS ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics[&workspace.documents[0].url].is_empty());
+    let hover = workspace
+        .get_symbol_description_at("file:///test.gram", 0, 53)
+        .unwrap();
+    assert!(
+        hover.contents
+            == HoverContents::Scalar(MarkedString::String(String::from(
+                "Terminal `+` (alias of `T_PLUS`)"
+            )))
+    );
+}
+
+#[test]
+fn test_hover_symbol_annotations() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///test.gram").unwrap(),
+        String::from(
+            "grammar Test { options { Axiom = \"expr\"; } terminals { T -> 't'; } rules { @node_kind(\"Root\") expr -> T ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics[&workspace.documents[0].url].is_empty());
+    let hover = workspace
+        .get_symbol_description_at("file:///test.gram", 0, 96)
+        .unwrap();
+    assert!(
+        hover.contents
+            == HoverContents::Scalar(MarkedString::String(String::from(
+                "Variable `expr`\n@node_kind(\"Root\")"
+            )))
+    );
+}
+
+#[test]
+fn test_links_to_parent_grammars() {
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        Url::parse("file:///base.gram").unwrap(),
+        String::from(
+            "grammar Base { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        ),
+    ));
+    workspace.documents.push(Document::new(
+        Url::parse("file:///derived.gram").unwrap(),
+        String::from("grammar Derived : Base { options { } rules { f -> e A ; } }"),
+    ));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics.values().all(Vec::is_empty));
+    let links = workspace
+        .get_document_links("file:///derived.gram")
+        .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].range.start, Position::new(0, 18));
+    assert_eq!(
+        links[0].target.as_ref().unwrap().as_str(),
+        "file:///base.gram"
+    );
+    assert!(workspace.get_document_links("file:///base.gram").is_none());
+    let Some(GotoDefinitionResponse::Scalar(location)) =
+        workspace.get_definition_at("file:///derived.gram", 0, 20)
+    else {
+        panic!("expected the definition of the parent grammar");
+    };
+    assert_eq!(location.uri.as_str(), "file:///base.gram");
+    assert_eq!(location.range.start, Position::new(0, 8));
+}
+
+#[test]
+fn test_quick_fix_creates_missing_grammar() {
+    let url = Url::parse("file:///derived.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from("grammar Derived : Base { options { } rules { e -> root root ; } }"),
+    ));
+    let diagnostics = workspace.lint();
+    let actions = workspace
+        .get_code_actions(&url, &diagnostics[&url])
+        .unwrap();
+    assert_eq!(actions.len(), 1);
+    let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+        panic!("expected a code action");
+    };
+    assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+    let Some(DocumentChanges::Operations(operations)) = action
+        .edit
+        .as_ref()
+        .and_then(|edit| edit.document_changes.as_ref())
+    else {
+        panic!("expected the operations creating the document");
+    };
+    let DocumentChangeOperation::Op(ResourceOp::Create(create)) = &operations[0] else {
+        panic!("expected the creation of the document");
+    };
+    assert_eq!(create.uri.as_str(), "file:///Base.gram");
+    let DocumentChangeOperation::Edit(edit) = &operations[1] else {
+        panic!("expected the content of the document");
+    };
+    let OneOf::Left(content) = &edit.edits[0] else {
+        panic!("expected a text edit");
+    };
+    // the created grammar fixes the reference
+    workspace
+        .documents
+        .push(Document::new(create.uri.clone(), content.new_text.clone()));
+    let diagnostics = workspace.lint();
+    assert!(diagnostics.values().all(Vec::is_empty));
+}
+
+#[test]
+fn test_lint_parse_error_at_offending_token() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    for (line, expected) in [
+        // a stray character
+        ("  rules { e -> A ` A; }", (17, 18)),
+        // an unexpected token
+        ("  rules { e -> A = A; }", (17, 18)),
+        ("  rules { e -> A options A; }", (17, 24)),
+    ] {
+        let mut workspace = Workspace::default();
+        workspace.documents.push(Document::new(
+            url.clone(),
+            format!("grammar Test\n{{\n  options {{ Axiom = \"e\"; }}\n  terminals {{ A -> 'a'; }}\n{line}\n}}\n"),
+        ));
+        let diagnostics = workspace.lint();
+        assert_eq!(
+            diagnostics[&url][0].range,
+            Range::new(Position::new(4, expected.0), Position::new(4, expected.1)),
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn test_resolve_includes_relative_to_including_file() {
+    let root = std::env::temp_dir().join(format!("hime_test_includes_{}", std::process::id()));
+    std::fs::create_dir_all(root.join("a")).unwrap();
+    std::fs::create_dir_all(root.join("common")).unwrap();
+    std::fs::write(
+        root.join("common").join("base.gram"),
+        "grammar Base { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+    )
+    .unwrap();
+    let main = root.join("a").join("main.gram");
+    std::fs::write(
+        &main,
+        "%include \"../common/base.gram\";\ngrammar Main : Base { options { } rules { f -> e A ; } }",
+    )
+    .unwrap();
+    let mut workspace = Workspace::default();
+    workspace.resolve_document_path(&main).unwrap();
+    let diagnostics = workspace.lint();
+    std::fs::remove_dir_all(&root).unwrap();
+    // the included file is loaded from the directory of the including file
+    assert_eq!(workspace.documents.len(), 2);
+    assert!(diagnostics.values().all(Vec::is_empty));
+}
+
+#[test]
+fn test_lint_missing_include() {
+    let url = Url::parse("file:///a/main.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "%include \"missing.gram\";\ngrammar Main { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    let diagnostics = &diagnostics[&url];
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].range,
+        Range::new(Position::new(0, 9), Position::new(0, 23))
+    );
+    assert!(diagnostics[0].message.contains("missing.gram"));
+}
+
+#[test]
+fn test_lookup_workspace_symbols() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"expr\"; } terminals { NUMBER -> [0-9]+; } rules { expr -> expr_list NUMBER; expr_list -> NUMBER; } }",
+        ),
+    ));
+    assert!(workspace.lint()[&url].is_empty());
+    let names = |symbols: Vec<SymbolInformation>| {
+        symbols
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect::<Vec<_>>()
+    };
+    // exact match first, then prefixes
+    assert_eq!(
+        names(workspace.lookup_symbols("EXPR")),
+        ["expr", "expr_list"]
+    );
+    // prefixes before substrings
+    assert_eq!(
+        names(workspace.lookup_symbols("e")),
+        ["expr", "expr_list", "NUMBER", "Test"]
+    );
+    // subsequence
+    assert_eq!(names(workspace.lookup_symbols("nbr")), ["NUMBER"]);
+    assert_eq!(names(workspace.lookup_symbols("Test.elst")), ["expr_list"]);
+    assert!(workspace.lookup_symbols("Other.expr").is_empty());
+    let symbol = &workspace.lookup_symbols("NUMBER")[0];
+    assert_eq!(symbol.container_name.as_deref(), Some("Test"));
+    assert_eq!(symbol.location.range.start, Position::new(0, 55));
+
+    // the document fails to parse, its symbols are kept at their last-known positions
+    workspace.documents[0] = Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"expr\"; } terminals { NUMBER -> [0-9]+; } rules { expr -> expr_list NUMBER; expr_list -> NUMBER } }",
+        ),
+    );
+    assert!(!workspace.lint()[&url].is_empty());
+    let symbol = &workspace.lookup_symbols("NUMBER")[0];
+    assert_eq!(symbol.location.range.start, Position::new(0, 55));
+}
+
+#[test]
+fn test_lint_shadowed_terminal() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { IF -> 'if'; ID -> [a-z]+; } rules { e -> ID ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    let diagnostics = &diagnostics[&url];
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diagnostics[0].range.start, Position::new(0, 52));
+    let related = diagnostics[0].related_information.as_ref().unwrap();
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[0].location.range.start, Position::new(0, 52));
+    assert_eq!(related[1].location.range.start, Position::new(0, 64));
+
+    // the keyword has more priority when declared last
+    workspace.documents[0] = Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { ID -> [a-z]+; IF -> 'if'; } rules { e -> ID ; } }",
+        ),
+    );
+    assert!(workspace.lint()[&url].is_empty());
+}
+
+#[test]
+fn test_lint_axiom_derives_itself() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A | '(' e ')' | x ; x -> o e ; o -> '(' | ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    let warnings: Vec<_> = diagnostics[&url]
+        .iter()
+        .filter(|diagnostic| diagnostic.message.starts_with("Axiom"))
+        .collect();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].message,
+        "Axiom `e` derives itself through a rule for `x`"
+    );
+    assert_eq!(warnings[0].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(warnings[0].range.start, Position::new(0, 104));
+}
+
+#[test]
+fn test_document_line_index() {
+    use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, VersionedTextDocumentIdentifier};
+
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace
+        .documents
+        .push(Document::new(url.clone(), String::from("a\nb")));
+    assert_eq!(workspace.documents[0].line_index().unwrap().line_count(), 2);
+    // the index is rebuilt for the new content
+    workspace.on_file_changes(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(url, 2),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: String::from("a\nb\nc"),
+        }],
+    });
+    let document = &workspace.documents[0];
+    assert_eq!(document.version, Some(2));
+    let index = document.line_index().unwrap();
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.offset_of(Position::new(2, 0)), 4);
+}
+
+#[test]
+fn test_lint_diagnostics_grammar_names() {
+    let url = Url::parse("file:///test.gram").unwrap();
+    let mut workspace = Workspace::default();
+    workspace.documents.push(Document::new(
+        url.clone(),
+        String::from(
+            "grammar A { options { Axiom = \"e\"; } terminals { X -> 'x'; } rules { e -> X ; } }\n\
+             grammar B { options { Axiom = \"f\"; } terminals { X -> 'x'; } rules { f -> a | b ; a -> X ; b -> X ; } }",
+        ),
+    ));
+    let diagnostics = workspace.lint();
+    let diagnostics = &diagnostics[&url];
+    assert!(!diagnostics.is_empty());
+    for diagnostic in diagnostics {
+        assert!(
+            diagnostic.message.starts_with("[B] "),
+            "{}",
+            diagnostic.message
+        );
+        assert_eq!(diagnostic.source.as_deref(), Some("hime_langserv (B)"));
+    }
+}
@@ -17,14 +17,16 @@
 
 //! Module for Abstract-Syntax Trees
 
+use alloc::vec::Vec;
 use core::fmt::{Display, Error, Formatter};
 use core::iter::FusedIterator;
 
+#[cfg(feature = "serde")]
 use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
 
 use crate::symbols::{SemanticElementTrait, Symbol};
 use crate::text::{TextContext, TextPosition, TextSpan};
-use crate::tokens::{Token, TokenRepository};
+use crate::tokens::{Token, TokenRepository, Trivia};
 use crate::utils::biglist::BigList;
 
 /// Represents a type of symbol table
@@ -152,6 +154,12 @@ impl AstImpl {
         self.root.is_some()
     }
 
+    /// Removes all the nodes, keeping the allocated storage for reuse
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+    }
+
     /// Stores the root of this tree
     pub fn store_root(&mut self, node: AstCell) {
         self.root = Some(self.nodes.push(node));
@@ -241,6 +249,44 @@ impl<'s, 't, 'a> Ast<'s, 't, 'a> {
         }
     }
 
+    /// Gets an iterator over the nodes of this tree in pre-order,
+    /// i.e. a node comes before its children
+    #[must_use]
+    pub fn iter_preorder(&'a self) -> AstPreorderIterator<'s, 't, 'a> {
+        AstPreorderIterator {
+            tree: self,
+            stack: self
+                .data
+                .root
+                .map(|root| alloc::vec![(root, root + 1)])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Gets an iterator over the nodes of this tree in post-order,
+    /// i.e. a node comes after its children
+    #[must_use]
+    pub fn iter_postorder(&'a self) -> AstPostorderIterator<'s, 't, 'a> {
+        AstPostorderIterator {
+            tree: self,
+            stack: self
+                .data
+                .root
+                .map(|root| alloc::vec![(root, 0)])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Gets an iterator over the nodes of this tree for the symbol with the specified identifier, in pre-order
+    /// There is no index of the nodes per symbol, the nodes are filtered while walking the tree.
+    #[must_use]
+    pub fn iter_symbol(&'a self, sid: u32) -> AstSymbolIterator<'s, 't, 'a> {
+        AstSymbolIterator {
+            inner: self.iter_preorder(),
+            sid,
+        }
+    }
+
     /// Gets the AST node (if any) that has the specified token as label
     #[must_use]
     pub fn find_node_for(&'a self, token: &Token<'s, 't, 'a>) -> Option<AstNode<'s, 't, 'a>> {
@@ -267,12 +313,106 @@ impl<'s, 't, 'a> Ast<'s, 't, 'a> {
     /// a token label that contains the specified index in the input text
     #[must_use]
     pub fn find_node_at_position(&'a self, position: TextPosition) -> Option<AstNode<'s, 't, 'a>> {
-        let index = self.tokens.text.get_line_index(position.line) + position.column - 1;
+        let index = self.tokens.text.get_index_at(position);
         self.tokens
             .find_token_at(index)
             .and_then(|token| self.find_node_for(&token))
     }
 
+    /// Gets the innermost node (if any) whose span covers the specified position in the input text
+    ///
+    /// See `node_at_index` for the details.
+    #[must_use]
+    pub fn node_at(&'a self, position: TextPosition) -> Option<AstNode<'s, 't, 'a>> {
+        let text = self.tokens.text;
+        if position.line == 0 || position.column == 0 || position.line > text.get_line_count() {
+            return None;
+        }
+        self.node_at_index(text.get_index_at(position))
+    }
+
+    /// Gets the innermost node (if any) whose span covers the specified index in the input text
+    ///
+    /// The span of a node goes from the first to the last token in its sub-tree,
+    /// so that an index in the whitespace between two tokens is covered by the innermost node containing both.
+    /// Nodes without tokens, e.g. for virtual symbols and empty variables, are never returned.
+    /// The tokens around the index are found with a binary search, then the tree is walked down from its root.
+    #[must_use]
+    pub fn node_at_index(&'a self, index: usize) -> Option<AstNode<'s, 't, 'a>> {
+        let root = self.data.root?;
+        // the range of tokens that the node must cover
+        let (first, last) = match self.tokens.locate_token(index) {
+            Ok(token) => (token, token),
+            Err(next) if next == 0 || next >= self.tokens.get_tokens_count() => return None,
+            Err(next) => (next - 1, next),
+        };
+        let covers = |node: usize| {
+            self.get_token_bounds(node)
+                .is_some_and(|(start, end)| start <= first && last <= end)
+        };
+        if !covers(root) {
+            return None;
+        }
+        let mut current = root;
+        loop {
+            let cell = self.data.nodes[current];
+            if cell.label.table_type() == TableType::Token && cell.label.index() == first {
+                // this node is labelled by the token at the index
+                break;
+            }
+            match (cell.first..(cell.first + cell.count))
+                .map(|child| child as usize)
+                .find(|&child| covers(child))
+            {
+                Some(child) => current = child,
+                None => break,
+            }
+        }
+        Some(AstNode {
+            tree: self,
+            index: current,
+        })
+    }
+
+    /// Gets the top-most nodes whose span is fully contained in the specified span of the input text, in order
+    ///
+    /// The span of a node goes from the first to the last token in its sub-tree.
+    /// Nodes without tokens, e.g. for virtual symbols and empty variables, are never returned.
+    #[must_use]
+    pub fn nodes_in_range(&'a self, span: TextSpan) -> Vec<AstNode<'s, 't, 'a>> {
+        let mut result = Vec::new();
+        let Some(root) = self.data.root else {
+            return result;
+        };
+        // the range [first, end[ of tokens fully within the span
+        let first = match self.tokens.locate_token(span.index) {
+            Ok(token) if self.tokens.get_span_of(token).index < span.index => token + 1,
+            Ok(token) | Err(token) => token,
+        };
+        let (Ok(end) | Err(end)) = self.tokens.locate_token(span.index + span.length);
+        if first >= end {
+            return result;
+        }
+        let mut stack = alloc::vec![root];
+        while let Some(current) = stack.pop() {
+            let Some((start, last)) = self.get_token_bounds(current) else {
+                continue;
+            };
+            if first <= start && last < end {
+                result.push(AstNode {
+                    tree: self,
+                    index: current,
+                });
+            } else if start < end && last >= first {
+                let cell = self.data.nodes[current];
+                for i in (0..cell.count).rev() {
+                    stack.push((cell.first + i) as usize);
+                }
+            }
+        }
+        result
+    }
+
     /// Gets the parent of the specified node, if any
     #[must_use]
     pub fn find_parent_of(&'a self, node: usize) -> Option<AstNode<'s, 't, 'a>> {
@@ -328,6 +468,94 @@ impl<'s, 't, 'a> Ast<'s, 't, 'a> {
         self.get_total_position_and_span(node).map(|(_, span)| span)
     }
 
+    /// Gets the total number of nodes in this tree, for both terminals and variables
+    /// This is 0 when the tree has no root.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.get_metrics().0
+    }
+
+    /// Gets the maximum depth of this tree, i.e. the number of nodes on the longest path from the root to a leaf
+    /// This is 0 when the tree has no root.
+    #[must_use]
+    pub fn max_depth(&self) -> usize {
+        self.get_metrics().1
+    }
+
+    /// Gets whether the depth of this tree is logarithmic in its number of nodes
+    /// The depth must be at most twice the number of bits in the number of nodes.
+    /// Deep unbalanced trees, as produced by recursive rules for long lists, risk overflowing the stack of recursive walks.
+    #[must_use]
+    pub fn is_balanced(&self) -> bool {
+        let (count, depth) = self.get_metrics();
+        depth <= 2 * (usize::BITS - count.leading_zeros()) as usize
+    }
+
+    /// Gets the number of nodes and the maximum depth of this tree in a single walk
+    fn get_metrics(&self) -> (usize, usize) {
+        let Some(root) = self.data.root else {
+            return (0, 0);
+        };
+        let mut count = 0;
+        let mut depth = 0;
+        // the ranges of the nodes (current, end excluded) remaining at each depth
+        let mut stack = alloc::vec![(root, root + 1)];
+        while let Some(top) = stack.last_mut() {
+            if top.0 >= top.1 {
+                stack.pop();
+                continue;
+            }
+            let cell = self.data.nodes[top.0];
+            top.0 += 1;
+            count += 1;
+            depth = depth.max(stack.len());
+            if cell.count > 0 {
+                stack.push((cell.first as usize, (cell.first + cell.count) as usize));
+            }
+        }
+        (count, depth)
+    }
+
+    /// Gets the indices of the first and last tokens in the sub-tree of a node, if any
+    fn get_token_bounds(&self, node: usize) -> Option<(usize, usize)> {
+        Some((
+            self.get_token_bound(node, false)?,
+            self.get_token_bound(node, true)?,
+        ))
+    }
+
+    /// Gets the index of the first, or last, token in the sub-tree of a node, if any
+    /// The children of a node are in the order of the input, but a node's own token,
+    /// when promoted by a tree action, may come before or after them.
+    fn get_token_bound(&self, node: usize, last: bool) -> Option<usize> {
+        // the explored nodes with the number of their children already explored,
+        // with an explicit stack so that deep trees do not overflow the call stack
+        let mut stack = alloc::vec![(node, 0)];
+        // the bound found in the sub-tree of the last explored node
+        let mut inner = None;
+        while let Some((current, explored)) = stack.last_mut() {
+            let cell = self.data.nodes[*current];
+            if inner.is_none() && *explored < cell.count {
+                let child = if last {
+                    cell.first + cell.count - 1 - *explored
+                } else {
+                    cell.first + *explored
+                };
+                *explored += 1;
+                stack.push((child as usize, 0));
+                continue;
+            }
+            stack.pop();
+            let own = (cell.label.table_type() == TableType::Token).then(|| cell.label.index());
+            inner = match (own, inner) {
+                (Some(own), Some(inner)) if last => Some(own.max(inner)),
+                (Some(own), Some(inner)) => Some(own.min(inner)),
+                (own, inner) => own.or(inner),
+            };
+        }
+        inner
+    }
+
     /// Traverses the AST from the specified node
     fn traverse<F: FnMut(usize)>(&self, from: usize, mut action: F) {
         let mut stack = alloc::vec![from];
@@ -435,6 +663,26 @@ impl<'s, 't, 'a> AstNode<'s, 't, 'a> {
     pub fn get_total_position_and_span(&self) -> Option<(TextPosition, TextSpan)> {
         self.tree.get_total_position_and_span(self.index)
     }
+
+    /// Gets an iterator over the ancestors of this node, starting with its parent
+    /// Each step looks up the parent, which is linear in the size of the tree.
+    #[must_use]
+    pub fn ancestors(&self) -> AstAncestorsIterator<'s, 't, 'a> {
+        AstAncestorsIterator {
+            tree: self.tree,
+            current: self.index,
+        }
+    }
+
+    /// Gets an iterator over the descendants of this node in pre-order, excluding this node
+    #[must_use]
+    pub fn descendants(&self) -> AstPreorderIterator<'s, 't, 'a> {
+        let cell = self.tree.data.nodes[self.index];
+        AstPreorderIterator {
+            tree: self.tree,
+            stack: alloc::vec![(cell.first as usize, (cell.first + cell.count) as usize)],
+        }
+    }
 }
 
 impl<'s, 't, 'a> SemanticElementTrait<'s, 'a> for AstNode<'s, 't, 'a> {
@@ -488,6 +736,13 @@ impl<'s, 't, 'a> SemanticElementTrait<'s, 'a> for AstNode<'s, 't, 'a> {
             _ => None,
         }
     }
+    /// Gets the separator tokens that precede the first token in the sub-tree of this node
+    fn leading_trivia(&self) -> Vec<Trivia<'s, 'a>> {
+        self.tree
+            .get_token_bound(self.index, false)
+            .map(|token| self.tree.tokens.get_trivia_for(token))
+            .unwrap_or_default()
+    }
 }
 
 impl<'s, 't, 'a> PartialEq<AstNode<'s, 't, 'a>> for AstNode<'s, 't, 'a> {
@@ -538,6 +793,71 @@ impl<'s, 't, 'a> Display for AstNode<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for Ast<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.has_root() {
+            serializer.serialize_some(&AstTreeNode(self.get_root()))
+        } else {
+            serializer.serialize_none()
+        }
+    }
+}
+
+/// A node serialized as part of a whole tree
+/// The tokens are serialized as `{"symbol", "value", "line", "col"}`
+/// and the other nodes as `{"symbol", "children"}`.
+#[cfg(feature = "serde")]
+struct AstTreeNode<'s, 't, 'a>(AstNode<'s, 't, 'a>);
+
+#[cfg(feature = "serde")]
+impl Serialize for AstTreeNode<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let node = &self.0;
+        if node.get_token_index().is_some() {
+            let position = node
+                .get_position()
+                .unwrap_or(TextPosition { line: 0, column: 0 });
+            let mut state = serializer.serialize_struct("AstToken", 4)?;
+            state.serialize_field("symbol", node.get_symbol().name)?;
+            state.serialize_field("value", &node.get_value())?;
+            state.serialize_field("line", &position.line)?;
+            state.serialize_field("col", &position.column)?;
+            state.end()
+        } else {
+            let mut state = serializer.serialize_struct("AstVariable", 2)?;
+            state.serialize_field("symbol", node.get_symbol().name)?;
+            state.serialize_field("children", &AstTreeChildren(node.children()))?;
+            state.end()
+        }
+    }
+}
+
+/// The children of a node serialized as part of a whole tree
+#[cfg(feature = "serde")]
+struct AstTreeChildren<'s, 't, 'a>(AstFamily<'s, 't, 'a>);
+
+#[cfg(feature = "serde")]
+impl Serialize for AstTreeChildren<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for node in self.0.iter() {
+            seq.serialize_element(&AstTreeNode(node))?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for AstNode<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -661,6 +981,7 @@ impl<'s, 't, 'a> AstFamily<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for AstFamily<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -673,3 +994,324 @@ impl<'s, 't, 'a> Serialize for AstFamily<'s, 't, 'a> {
         seq.end()
     }
 }
+
+/// Represents an iterator over the nodes of a tree in pre-order
+/// The iterator only keeps the remaining siblings on the path to the current node.
+pub struct AstPreorderIterator<'s, 't, 'a> {
+    /// The original parse tree
+    tree: &'a Ast<'s, 't, 'a>,
+    /// The ranges of the nodes (current, end excluded) remaining at each depth
+    stack: Vec<(usize, usize)>,
+}
+
+/// Implementation of the `Iterator` trait for `AstPreorderIterator`
+impl<'s, 't, 'a> Iterator for AstPreorderIterator<'s, 't, 'a> {
+    type Item = AstNode<'s, 't, 'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let top = self.stack.last_mut()?;
+            if top.0 >= top.1 {
+                self.stack.pop();
+                continue;
+            }
+            let index = top.0;
+            top.0 += 1;
+            let cell = self.tree.data.nodes[index];
+            if cell.count > 0 {
+                self.stack
+                    .push((cell.first as usize, (cell.first + cell.count) as usize));
+            }
+            return Some(AstNode {
+                tree: self.tree,
+                index,
+            });
+        }
+    }
+}
+
+impl FusedIterator for AstPreorderIterator<'_, '_, '_> {}
+
+/// Represents an iterator over the nodes of a tree in post-order
+/// The iterator only keeps the path to the current node.
+pub struct AstPostorderIterator<'s, 't, 'a> {
+    /// The original parse tree
+    tree: &'a Ast<'s, 't, 'a>,
+    /// The nodes on the path to the current one, with the number of their children already visited
+    stack: Vec<(usize, u32)>,
+}
+
+/// Implementation of the `Iterator` trait for `AstPostorderIterator`
+impl<'s, 't, 'a> Iterator for AstPostorderIterator<'s, 't, 'a> {
+    type Item = AstNode<'s, 't, 'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let top = self.stack.last_mut()?;
+            let cell = self.tree.data.nodes[top.0];
+            if top.1 < cell.count {
+                let child = (cell.first + top.1) as usize;
+                top.1 += 1;
+                self.stack.push((child, 0));
+                continue;
+            }
+            let index = top.0;
+            self.stack.pop();
+            return Some(AstNode {
+                tree: self.tree,
+                index,
+            });
+        }
+    }
+}
+
+impl FusedIterator for AstPostorderIterator<'_, '_, '_> {}
+
+/// Represents an iterator over the nodes of a tree for a specific symbol
+pub struct AstSymbolIterator<'s, 't, 'a> {
+    /// The iterator over all the nodes
+    inner: AstPreorderIterator<'s, 't, 'a>,
+    /// The identifier of the symbol to look for
+    sid: u32,
+}
+
+/// Implementation of the `Iterator` trait for `AstSymbolIterator`
+impl<'s, 't, 'a> Iterator for AstSymbolIterator<'s, 't, 'a> {
+    type Item = AstNode<'s, 't, 'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let sid = self.sid;
+        self.inner.find(|node| node.get_symbol().id == sid)
+    }
+}
+
+impl FusedIterator for AstSymbolIterator<'_, '_, '_> {}
+
+/// Represents an iterator over the ancestors of a node
+pub struct AstAncestorsIterator<'s, 't, 'a> {
+    /// The original parse tree
+    tree: &'a Ast<'s, 't, 'a>,
+    /// The index of the last returned node
+    current: usize,
+}
+
+/// Implementation of the `Iterator` trait for `AstAncestorsIterator`
+impl<'s, 't, 'a> Iterator for AstAncestorsIterator<'s, 't, 'a> {
+    type Item = AstNode<'s, 't, 'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let parent = self.tree.find_parent_of(self.current)?;
+        self.current = parent.index;
+        Some(parent)
+    }
+}
+
+#[cfg(test)]
+fn new_test_cell(variable: usize, count: u32, first: u32) -> AstCell {
+    AstCell::new(
+        TableElemRef::new(TableType::Variable, variable),
+        count,
+        first,
+    )
+}
+
+#[test]
+fn test_ast_iterators_order() {
+    // e -> a b ; a -> x x ; b -> x
+    let variables = [
+        Symbol { id: 1, name: "e" },
+        Symbol { id: 2, name: "a" },
+        Symbol { id: 3, name: "b" },
+        Symbol { id: 4, name: "x" },
+    ];
+    let mut data = AstImpl::default();
+    let leaves = [new_test_cell(3, 0, 0); 3];
+    assert_eq!(data.store(&leaves, 0, 3), 0);
+    let inner = [new_test_cell(1, 2, 0), new_test_cell(2, 1, 2)];
+    assert_eq!(data.store(&inner, 0, 2), 3);
+    data.store_root(new_test_cell(0, 2, 3));
+    let text = crate::text::Text::from_str("");
+    let tokens = crate::tokens::TokenRepositoryImpl::default();
+    let ast = Ast::new(
+        TokenRepository::new(&[], &text, &tokens),
+        &variables,
+        &[],
+        &data,
+    );
+
+    let ids =
+        |iter: &mut dyn Iterator<Item = AstNode>| iter.map(|node| node.id()).collect::<Vec<_>>();
+    assert_eq!(ids(&mut ast.iter_preorder()), [5, 3, 0, 1, 4, 2]);
+    assert_eq!(ids(&mut ast.iter_postorder()), [0, 1, 3, 2, 4, 5]);
+    assert_eq!(ids(&mut ast.iter_symbol(4)), [0, 1, 2]);
+    assert!(ids(&mut ast.iter_symbol(5)).is_empty());
+    assert_eq!(ids(&mut ast.get_node(1).ancestors()), [3, 5]);
+    assert_eq!(ids(&mut ast.get_node(3).descendants()), [0, 1]);
+    assert!(ids(&mut ast.get_node(2).descendants()).is_empty());
+    assert_eq!(ast.node_count(), 6);
+    assert_eq!(ast.max_depth(), 3);
+    assert!(ast.is_balanced());
+}
+
+#[test]
+fn test_ast_metrics() {
+    // e -> x e | x, for a list of 100 elements
+    let variables = [Symbol { id: 1, name: "e" }, Symbol { id: 2, name: "x" }];
+    let mut data = AstImpl::default();
+    let text = crate::text::Text::from_str("");
+    let tokens = crate::tokens::TokenRepositoryImpl::default();
+    {
+        let ast = Ast::new(
+            TokenRepository::new(&[], &text, &tokens),
+            &variables,
+            &[],
+            &data,
+        );
+        assert_eq!(ast.node_count(), 0);
+        assert_eq!(ast.max_depth(), 0);
+        assert!(ast.is_balanced());
+    }
+    let first = data.store(&[new_test_cell(1, 0, 0)], 0, 1);
+    let mut last = new_test_cell(0, 1, first as u32);
+    for _ in 1..100 {
+        let first = data.store(&[new_test_cell(1, 0, 0), last], 0, 2);
+        last = new_test_cell(0, 2, first as u32);
+    }
+    data.store_root(last);
+    let ast = Ast::new(
+        TokenRepository::new(&[], &text, &tokens),
+        &variables,
+        &[],
+        &data,
+    );
+    assert_eq!(ast.node_count(), 200);
+    assert_eq!(ast.max_depth(), 101);
+    assert!(!ast.is_balanced());
+}
+
+#[test]
+fn test_ast_token_bounds_deep() {
+    // e -> e | x x, nested 1M times
+    let variables = [Symbol { id: 1, name: "e" }];
+    let mut data = AstImpl::default();
+    let tokens = [
+        AstCell::new(TableElemRef::new(TableType::Token, 0), 0, 0),
+        AstCell::new(TableElemRef::new(TableType::Token, 1), 0, 0),
+    ];
+    let first = data.store(&tokens, 0, 2);
+    let mut last = new_test_cell(0, 2, first as u32);
+    for _ in 0..1_000_000 {
+        let first = data.store(&[last], 0, 1);
+        last = new_test_cell(0, 1, first as u32);
+    }
+    data.store_root(last);
+    let text = crate::text::Text::from_str("");
+    let repository = crate::tokens::TokenRepositoryImpl::default();
+    let ast = Ast::new(
+        TokenRepository::new(&[], &text, &repository),
+        &variables,
+        &[],
+        &data,
+    );
+    let root = data.root.unwrap();
+    assert_eq!(ast.get_token_bounds(root), Some((0, 1)));
+}
+
+#[test]
+fn test_ast_iterators_large() {
+    // a root with 1000 children, each with 999 leaves, for 1M nodes in total
+    let variables = [Symbol { id: 1, name: "e" }];
+    let mut data = AstImpl::default();
+    let mut inner = Vec::new();
+    for _ in 0..1000 {
+        let first = data.store(&[new_test_cell(0, 0, 0); 999], 0, 999);
+        inner.push(new_test_cell(0, 999, first as u32));
+    }
+    let first = data.store(&inner, 0, inner.len());
+    data.store_root(new_test_cell(0, 1000, first as u32));
+    let text = crate::text::Text::from_str("");
+    let tokens = crate::tokens::TokenRepositoryImpl::default();
+    let ast = Ast::new(
+        TokenRepository::new(&[], &text, &tokens),
+        &variables,
+        &[],
+        &data,
+    );
+    let root = data.root.unwrap();
+    let mut expected = Vec::new();
+    ast.traverse(root, |node| expected.push(node));
+    assert_eq!(expected.len(), 1_000_001);
+
+    // the walks only keep the path to the current node, not the visited nodes
+    let mut preorder = ast.iter_preorder();
+    let mut visited = Vec::new();
+    let mut max_stack = 0;
+    while let Some(node) = preorder.next() {
+        max_stack = max_stack.max(preorder.stack.len());
+        visited.push(node.index);
+    }
+    assert_eq!(visited, expected);
+    assert!(max_stack <= ast.max_depth(), "{max_stack}");
+    let mut postorder = ast.iter_postorder();
+    let mut visited = Vec::new();
+    let mut max_stack = 0;
+    while let Some(node) = postorder.next() {
+        max_stack = max_stack.max(postorder.stack.len() + 1);
+        visited.push(node.index);
+    }
+    assert_eq!(visited.len(), expected.len());
+    assert_eq!(visited.last(), Some(&root));
+    assert!(max_stack <= ast.max_depth(), "{max_stack}");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_ast_serialize() {
+    // e -> x y ; y -> z
+    let terminals = [
+        Symbol { id: 0, name: "ε" },
+        Symbol { id: 1, name: "$" },
+        Symbol { id: 3, name: "X" },
+    ];
+    let variables = [Symbol { id: 4, name: "e" }, Symbol { id: 5, name: "y" }];
+    let text = crate::text::Text::from_str("ab\n c");
+    let mut tokens = crate::tokens::TokenRepositoryImpl::default();
+    {
+        let mut repository = TokenRepository::new_mut(&terminals, &text, &mut tokens);
+        repository.add(2, 0, 2);
+        repository.add(2, 4, 1);
+    }
+    let mut data = AstImpl::default();
+    let token = |index| AstCell::new_empty(TableElemRef::new(TableType::Token, index));
+    let first = data.store(&[token(1)], 0, 1);
+    let first = data.store(&[token(0), new_test_cell(1, 1, first as u32)], 0, 2);
+    data.store_root(new_test_cell(0, 2, first as u32));
+    let ast = Ast::new(
+        TokenRepository::new(&terminals, &text, &tokens),
+        &variables,
+        &[],
+        &data,
+    );
+    let expected = serde_json::json!({
+        "symbol": "e",
+        "children": [
+            { "symbol": "X", "value": "ab", "line": 1, "col": 1 },
+            {
+                "symbol": "y",
+                "children": [
+                    { "symbol": "X", "value": "c", "line": 2, "col": 2 }
+                ]
+            }
+        ]
+    });
+    let json = serde_json::to_string(&ast).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value, expected);
+    assert_eq!(serde_json::to_value(&ast).unwrap(), expected);
+
+    // an empty tree
+    let data = AstImpl::default();
+    let ast = Ast::new(
+        TokenRepository::new(&terminals, &text, &tokens),
+        &variables,
+        &[],
+        &data,
+    );
+    assert_eq!(serde_json::to_string(&ast).unwrap(), "null");
+}
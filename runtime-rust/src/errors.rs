@@ -17,14 +17,16 @@
 
 //! Module for the definition of lexical and syntactic errors
 
-use alloc::string::String;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::fmt::{Display, Formatter};
 
+#[cfg(feature = "serde")]
 use serde::Serialize;
 
 use crate::symbols::Symbol;
-use crate::text::{TextPosition, Utf16C};
+use crate::text::{Text, TextPosition, Utf16C};
 
 /// Common trait for data about an error
 pub trait ParseErrorDataTrait: Display {
@@ -35,10 +37,55 @@ pub trait ParseErrorDataTrait: Display {
     /// Gets the error's length in the input (in number of characters)
     #[must_use]
     fn get_length(&self) -> usize;
+
+    /// Gets the text of the input around the error, on the same line,
+    /// with up to `window` characters before and after the error
+    #[must_use]
+    fn get_excerpt<'t>(&self, text: &'t Text, window: usize) -> &'t str {
+        let position = self.get_position();
+        if position.line > text.get_line_count() {
+            return "";
+        }
+        let line = text.get_line_content(position.line).trim_end_matches([
+            '\r', '\n', '\u{000B}', '\u{000C}', '\u{0085}', '\u{2028}', '\u{2029}',
+        ]);
+        let offset_of = |column: usize| {
+            line.char_indices()
+                .nth(column)
+                .map_or(line.len(), |(offset, _)| offset)
+        };
+        // the number of characters before the error on the line
+        let column = line
+            .get(..(text.get_index_at(position) - text.get_line_index(position.line)))
+            .map_or(line.chars().count(), |before| before.chars().count());
+        let start = column.saturating_sub(window);
+        let end = column + self.get_length() + window;
+        &line[offset_of(start)..offset_of(end)]
+    }
+
+    /// Renders this error with a frame showing the line of the input where it occurs,
+    /// the error being underlined with carets
+    #[must_use]
+    fn render(&self, text: &Text) -> String {
+        let position = self.get_position();
+        let frame = if position.line <= text.get_line_count() {
+            let context = text.get_context_for(position, self.get_length());
+            let number = position.line.to_string();
+            let margin = " ".repeat(number.len());
+            format!(
+                "{margin} |\n{number} | {}\n{margin} | {}\n",
+                context.content, context.pointer
+            )
+        } else {
+            String::new()
+        };
+        format!("{self}\n --> {position}\n{frame}")
+    }
 }
 
 /// Represents the unexpected of the input text while more characters were expected
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ParseErrorEndOfInput {
     /// The error's position in the input text
     position: TextPosition,
@@ -62,6 +109,8 @@ impl Display for ParseErrorEndOfInput {
     }
 }
 
+impl core::error::Error for ParseErrorEndOfInput {}
+
 impl ParseErrorEndOfInput {
     /// Creates a new error
     #[must_use]
@@ -71,7 +120,8 @@ impl ParseErrorEndOfInput {
 }
 
 /// Represents an unexpected character error in the input stream of a lexer
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ParseErrorUnexpectedChar {
     /// The error's position in the input text
     position: TextPosition,
@@ -101,6 +151,8 @@ impl Display for ParseErrorUnexpectedChar {
     }
 }
 
+impl core::error::Error for ParseErrorUnexpectedChar {}
+
 impl ParseErrorUnexpectedChar {
     /// Creates a new error
     #[must_use]
@@ -113,13 +165,14 @@ impl ParseErrorUnexpectedChar {
 }
 
 /// Represents an incorrect encoding sequence error in the input of a lexer
-/// This kind of error cannot really be produced by the Rust runtime
-#[derive(Debug, Clone, Serialize)]
+/// This kind of error is only produced for inputs decoded from UTF-16, the invalid sequence being replaced by U+FFFD
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ParseErrorIncorrectEncodingSequence {
     /// The error's position in the input text
     position: TextPosition,
     /// The precise error type
-    #[serde(rename = "missingHigh")]
+    #[cfg_attr(feature = "serde", serde(rename = "missingHigh"))]
     missing_high: bool,
     /// The incorrect sequence
     sequence: Utf16C,
@@ -150,6 +203,8 @@ impl Display for ParseErrorIncorrectEncodingSequence {
     }
 }
 
+impl core::error::Error for ParseErrorIncorrectEncodingSequence {}
+
 impl ParseErrorIncorrectEncodingSequence {
     /// Initializes this error
     #[must_use]
@@ -167,7 +222,8 @@ impl ParseErrorIncorrectEncodingSequence {
 }
 
 /// Represents an unexpected token error in a parser
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ParseErrorUnexpectedToken<'s> {
     /// The error's position in the input text
     position: TextPosition,
@@ -182,6 +238,10 @@ pub struct ParseErrorUnexpectedToken<'s> {
     state_ids: Vec<u32>,
     /// The expected terminals
     expected: Vec<Symbol<'s>>,
+    /// The stacks of states of the parser, from the bottom to the top
+    stacks: Vec<Vec<u32>>,
+    /// The display names of the terminals, for those that have one
+    aliases: Vec<(u32, &'s str)>,
 }
 
 impl<'s> ParseErrorDataTrait for ParseErrorUnexpectedToken<'s> {
@@ -217,13 +277,15 @@ impl<'s> Display for ParseErrorUnexpectedToken<'s> {
                 if i != 0 {
                     write!(f, ", ")?;
                 }
-                write!(f, "{}", x.name)?;
+                write!(f, "{}", self.get_display_name(*x))?;
             }
         }
         Ok(())
     }
 }
 
+impl core::error::Error for ParseErrorUnexpectedToken<'_> {}
+
 impl<'s> ParseErrorUnexpectedToken<'s> {
     /// Initializes this error
     #[must_use]
@@ -243,13 +305,281 @@ impl<'s> ParseErrorUnexpectedToken<'s> {
             #[cfg(feature = "debug")]
             state_ids,
             expected,
+            stacks: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Sets the stacks of states of the parser when the error occurred, from the bottom to the top
+    #[must_use]
+    pub fn with_stacks(mut self, stacks: Vec<Vec<u32>>) -> ParseErrorUnexpectedToken<'s> {
+        self.stacks = stacks;
+        self
+    }
+
+    /// Sets the display names of the terminals, by symbol identifier
+    /// Only the aliases of the terminals involved in this error are kept.
+    #[must_use]
+    pub fn with_aliases(mut self, aliases: &[(u32, &'s str)]) -> ParseErrorUnexpectedToken<'s> {
+        self.aliases = aliases
+            .iter()
+            .filter(|(id, _)| {
+                *id == self.terminal.id || self.expected.iter().any(|symbol| symbol.id == *id)
+            })
+            .copied()
+            .collect();
+        self
+    }
+
+    /// Gets the name to display for a terminal symbol, i.e. its alias if it has one
+    #[must_use]
+    pub fn get_display_name(&self, terminal: Symbol<'s>) -> &'s str {
+        self.aliases
+            .iter()
+            .find(|(id, _)| *id == terminal.id)
+            .map_or(terminal.name, |(_, alias)| *alias)
+    }
+
+    /// Gets the terminal symbol for the unexpected token
+    #[must_use]
+    pub fn get_terminal(&self) -> Symbol<'s> {
+        self.terminal
+    }
+
+    /// Gets the terminals that were expected instead
+    #[must_use]
+    pub fn get_expected(&self) -> &[Symbol<'s>] {
+        &self.expected
+    }
+
+    /// Gets the stacks of states of the parser when the error occurred, from the bottom to the top
+    /// A LR(k) parser has a single stack, a RNGLR parser has one for each of its current states.
+    /// The stacks are empty unless the parser was set to capture them.
+    #[must_use]
+    pub fn get_stacks(&self) -> &[Vec<u32>] {
+        &self.stacks
+    }
+}
+
+/// Represents the error of a lexer producing more tokens than allowed
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseErrorMaxTokensExceeded {
+    /// The error's position in the input text
+    position: TextPosition,
+    /// The maximum number of tokens that was exceeded
+    count: usize,
+}
+
+impl ParseErrorDataTrait for ParseErrorMaxTokensExceeded {
+    /// Gets the error's position in the input
+    fn get_position(&self) -> TextPosition {
+        self.position
+    }
+
+    /// Gets the error's length in the input (in number of characters)
+    fn get_length(&self) -> usize {
+        0
+    }
+}
+
+impl Display for ParseErrorMaxTokensExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Maximum number of tokens exceeded ({})", self.count)
+    }
+}
+
+impl core::error::Error for ParseErrorMaxTokensExceeded {}
+
+impl ParseErrorMaxTokensExceeded {
+    /// Creates a new error
+    #[must_use]
+    pub fn new(position: TextPosition, count: usize) -> ParseErrorMaxTokensExceeded {
+        ParseErrorMaxTokensExceeded { position, count }
+    }
+
+    /// Gets the maximum number of tokens that was exceeded
+    #[must_use]
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Represents the error of a RNGLR parser having more live stacks at an input position than allowed
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseErrorForkBudgetExceeded {
+    /// The error's position in the input text
+    position: TextPosition,
+    /// The maximum number of live stacks that was exceeded
+    budget: usize,
+}
+
+impl ParseErrorDataTrait for ParseErrorForkBudgetExceeded {
+    /// Gets the error's position in the input
+    fn get_position(&self) -> TextPosition {
+        self.position
+    }
+
+    /// Gets the error's length in the input (in number of characters)
+    fn get_length(&self) -> usize {
+        0
+    }
+}
+
+impl Display for ParseErrorForkBudgetExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Maximum number of live parser stacks exceeded ({})",
+            self.budget
+        )
+    }
+}
+
+impl core::error::Error for ParseErrorForkBudgetExceeded {}
+
+impl ParseErrorForkBudgetExceeded {
+    /// Creates a new error
+    #[must_use]
+    pub fn new(position: TextPosition, budget: usize) -> ParseErrorForkBudgetExceeded {
+        ParseErrorForkBudgetExceeded { position, budget }
+    }
+
+    /// Gets the maximum number of live stacks that was exceeded
+    #[must_use]
+    pub fn get_budget(&self) -> usize {
+        self.budget
+    }
+}
+
+/// Represents an error raised by a semantic action
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseErrorSemantic {
+    /// The error's position in the input text
+    position: TextPosition,
+    /// The error's length in the input (in number of characters)
+    length: usize,
+    /// The message of the semantic action
+    message: String,
+}
+
+impl ParseErrorDataTrait for ParseErrorSemantic {
+    /// Gets the error's position in the input
+    fn get_position(&self) -> TextPosition {
+        self.position
+    }
+
+    /// Gets the error's length in the input (in number of characters)
+    fn get_length(&self) -> usize {
+        self.length
+    }
+}
+
+impl Display for ParseErrorSemantic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl core::error::Error for ParseErrorSemantic {}
+
+impl ParseErrorSemantic {
+    /// Creates a new error
+    #[must_use]
+    pub fn new(position: TextPosition, length: usize, message: String) -> ParseErrorSemantic {
+        ParseErrorSemantic {
+            position,
+            length,
+            message,
         }
     }
+
+    /// Gets the message of the semantic action
+    #[must_use]
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Represents a token of a recoverable terminal that is not terminated in the input
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseErrorUnterminatedToken<'s> {
+    /// The error's position in the input text
+    position: TextPosition,
+    /// The error's length in the input (in number of characters)
+    length: usize,
+    /// The terminal symbol for the unterminated token
+    terminal: Symbol<'s>,
+    /// The display name of the terminal, if it has one
+    alias: Option<&'s str>,
+}
+
+impl ParseErrorDataTrait for ParseErrorUnterminatedToken<'_> {
+    /// Gets the error's position in the input
+    fn get_position(&self) -> TextPosition {
+        self.position
+    }
+
+    /// Gets the error's length in the input (in number of characters)
+    fn get_length(&self) -> usize {
+        self.length
+    }
+}
+
+impl Display for ParseErrorUnterminatedToken<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Unterminated {}", self.get_display_name())
+    }
+}
+
+impl core::error::Error for ParseErrorUnterminatedToken<'_> {}
+
+impl<'s> ParseErrorUnterminatedToken<'s> {
+    /// Creates a new error
+    #[must_use]
+    pub fn new(
+        position: TextPosition,
+        length: usize,
+        terminal: Symbol<'s>,
+    ) -> ParseErrorUnterminatedToken<'s> {
+        ParseErrorUnterminatedToken {
+            position,
+            length,
+            terminal,
+            alias: None,
+        }
+    }
+
+    /// Sets the display names of the terminals, by symbol identifier
+    #[must_use]
+    pub fn with_aliases(mut self, aliases: &[(u32, &'s str)]) -> ParseErrorUnterminatedToken<'s> {
+        self.alias = aliases
+            .iter()
+            .find(|(id, _)| *id == self.terminal.id)
+            .map(|(_, alias)| *alias);
+        self
+    }
+
+    /// Gets the name to display for the terminal, i.e. its alias if it has one
+    #[must_use]
+    pub fn get_display_name(&self) -> &'s str {
+        self.alias.unwrap_or(self.terminal.name)
+    }
+
+    /// Gets the terminal symbol for the unterminated token
+    #[must_use]
+    pub fn get_terminal(&self) -> Symbol<'s> {
+        self.terminal
+    }
 }
 
 /// Represents a lexical or syntactic error
-#[derive(Debug, Clone, Serialize)]
-#[serde(tag = "type")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
 pub enum ParseError<'s> {
     /// Lexical error occurring when the end of input has been encountered while more characters were expected
     UnexpectedEndOfInput(ParseErrorEndOfInput),
@@ -261,6 +591,14 @@ pub enum ParseError<'s> {
     IncorrectUTF16NoLowSurrogate(ParseErrorIncorrectEncodingSequence),
     /// Lexical error occurring when the high surrogate encoding point is missing in a UTF-16 encoding sequence with an expected high and low surrogate pair
     IncorrectUTF16NoHighSurrogate(ParseErrorIncorrectEncodingSequence),
+    /// Lexical error occurring when the lexer produces more tokens than allowed
+    MaxTokensExceeded(ParseErrorMaxTokensExceeded),
+    /// Syntactic error occurring when a RNGLR parser has more live stacks at an input position than allowed
+    ForkBudgetExceeded(ParseErrorForkBudgetExceeded),
+    /// Semantic error raised by a semantic action
+    Semantic(ParseErrorSemantic),
+    /// Lexical error occurring when a token of a recoverable terminal is not terminated in the input
+    UnterminatedToken(ParseErrorUnterminatedToken<'s>),
 }
 
 impl<'s> ParseErrorDataTrait for ParseError<'s> {
@@ -272,6 +610,10 @@ impl<'s> ParseErrorDataTrait for ParseError<'s> {
             ParseError::UnexpectedToken(x) => x.get_position(),
             ParseError::IncorrectUTF16NoLowSurrogate(x)
             | ParseError::IncorrectUTF16NoHighSurrogate(x) => x.get_position(),
+            ParseError::MaxTokensExceeded(x) => x.get_position(),
+            ParseError::ForkBudgetExceeded(x) => x.get_position(),
+            ParseError::Semantic(x) => x.get_position(),
+            ParseError::UnterminatedToken(x) => x.get_position(),
         }
     }
 
@@ -283,6 +625,10 @@ impl<'s> ParseErrorDataTrait for ParseError<'s> {
             ParseError::UnexpectedToken(x) => x.get_length(),
             ParseError::IncorrectUTF16NoLowSurrogate(x)
             | ParseError::IncorrectUTF16NoHighSurrogate(x) => x.get_length(),
+            ParseError::MaxTokensExceeded(x) => x.get_length(),
+            ParseError::ForkBudgetExceeded(x) => x.get_length(),
+            ParseError::Semantic(x) => x.get_length(),
+            ParseError::UnterminatedToken(x) => x.get_length(),
         }
     }
 }
@@ -296,12 +642,40 @@ impl<'s> Display for ParseError<'s> {
             ParseError::UnexpectedToken(x) => x.fmt(f),
             ParseError::IncorrectUTF16NoLowSurrogate(x)
             | ParseError::IncorrectUTF16NoHighSurrogate(x) => x.fmt(f),
+            ParseError::MaxTokensExceeded(x) => x.fmt(f),
+            ParseError::ForkBudgetExceeded(x) => x.fmt(f),
+            ParseError::Semantic(x) => x.fmt(f),
+            ParseError::UnterminatedToken(x) => x.fmt(f),
         }
     }
 }
 
-#[cfg(feature = "std")]
-impl<'s> std::error::Error for ParseError<'s> {}
+impl core::error::Error for ParseError<'_> {}
+
+impl ParseError<'_> {
+    /// Gets whether this is a lexical error, including the errors in the encoding of the input
+    #[must_use]
+    pub fn is_lexical(&self) -> bool {
+        matches!(
+            self,
+            ParseError::UnexpectedEndOfInput(_)
+                | ParseError::UnexpectedChar(_)
+                | ParseError::IncorrectUTF16NoLowSurrogate(_)
+                | ParseError::IncorrectUTF16NoHighSurrogate(_)
+                | ParseError::MaxTokensExceeded(_)
+                | ParseError::UnterminatedToken(_)
+        )
+    }
+
+    /// Gets whether this is a syntactic error
+    #[must_use]
+    pub fn is_syntactic(&self) -> bool {
+        matches!(
+            self,
+            ParseError::UnexpectedToken(_) | ParseError::ForkBudgetExceeded(_)
+        )
+    }
+}
 
 /// Represents an entity that can handle lexical and syntactic errors
 #[derive(Debug, Default, Clone)]
@@ -310,7 +684,57 @@ pub struct ParseErrors<'s> {
     pub errors: Vec<ParseError<'s>>,
 }
 
+/// Implementation of `Display` for `ParseErrors`, with one error per line
+impl<'s> Display for ParseErrors<'s> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "@{} {error}", error.get_position())?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ParseErrors<'_> {}
+
 impl<'s> ParseErrors<'s> {
+    /// Gets whether there are no errors
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Renders all the errors with frames showing the lines of the input where they occur
+    #[must_use]
+    pub fn render(&self, text: &Text) -> String {
+        self.errors
+            .iter()
+            .map(|error| error.render(text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Initializes the errors with the invalid sequences replaced when decoding a text
+    #[must_use]
+    pub fn from_decoding_errors(text: &Text) -> ParseErrors<'s> {
+        let mut errors = ParseErrors::default();
+        for error in text.get_decoding_errors() {
+            let data = ParseErrorIncorrectEncodingSequence::new(
+                text.get_position_at(error.index),
+                error.missing_high,
+                error.sequence,
+            );
+            if error.missing_high {
+                errors.push_error_no_high_utf16_surrogate(data);
+            } else {
+                errors.push_error_no_low_utf16_surrogate(data);
+            }
+        }
+        errors
+    }
+
     /// Handles the end-of-input error
     pub fn push_error_eoi(&mut self, error: ParseErrorEndOfInput) {
         self.errors.push(ParseError::UnexpectedEndOfInput(error));
@@ -343,4 +767,24 @@ impl<'s> ParseErrors<'s> {
         self.errors
             .push(ParseError::IncorrectUTF16NoHighSurrogate(error));
     }
+
+    /// Handles the maximum number of tokens exceeded error
+    pub fn push_error_max_tokens_exceeded(&mut self, error: ParseErrorMaxTokensExceeded) {
+        self.errors.push(ParseError::MaxTokensExceeded(error));
+    }
+
+    /// Handles the maximum number of live stacks exceeded error
+    pub fn push_error_fork_budget_exceeded(&mut self, error: ParseErrorForkBudgetExceeded) {
+        self.errors.push(ParseError::ForkBudgetExceeded(error));
+    }
+
+    /// Handles the error raised by a semantic action
+    pub fn push_error_semantic(&mut self, error: ParseErrorSemantic) {
+        self.errors.push(ParseError::Semantic(error));
+    }
+
+    /// Handles the unterminated token error
+    pub fn push_error_unterminated_token(&mut self, error: ParseErrorUnterminatedToken<'s>) {
+        self.errors.push(ParseError::UnterminatedToken(error));
+    }
 }
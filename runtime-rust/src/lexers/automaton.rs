@@ -19,18 +19,61 @@
 
 use alloc::vec::Vec;
 
-use crate::text::{Text, Utf16C};
+use crate::text::{is_line_ending_char, Text, Utf16C};
 use crate::utils::bin::{read_table_u16, read_table_u32, read_u32};
 
 /// Identifier of an invalid state in an automaton
 pub const DEAD_STATE: u32 = 0xFFFF;
 
+/// Flag on the index of a matched terminal when it is only matched at the end of the input
+pub const TERMINAL_AT_END: u16 = 0x4000;
+/// Flag on the index of a matched terminal when the match includes one trailing lookahead character
+pub const TERMINAL_BEFORE_LOOKAHEAD: u16 = 0x8000;
+/// Mask for the index of a matched terminal, without the flags
+const TERMINAL_INDEX_MASK: u16 = 0x3FFF;
+
 /// Represents the information of a terminal matched at the state of a lexer's automaton
+#[derive(Copy, Clone)]
 pub struct MatchedTerminal {
     /// The context
     pub context: u16,
     /// The terminal's index
     pub index: u16,
+    /// Whether the terminal is only matched at the end of the input
+    pub at_end: bool,
+    /// Whether the match includes one trailing lookahead character that is not part of the terminal
+    pub before_lookahead: bool,
+}
+
+impl MatchedTerminal {
+    /// Gets whether the terminal can be matched, depending on whether the input ends after the match
+    #[must_use]
+    pub fn is_possible(self, at_end: bool) -> bool {
+        at_end || !self.at_end
+    }
+}
+
+/// The point at which an unterminated token of a recoverable terminal stops
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecoveryPoint {
+    /// The token stops at the end of a line, or of the input
+    EndOfLine,
+    /// The token stops at the end of the input
+    EndOfInput,
+}
+
+/// Represents the recovery of the unterminated tokens of a terminal
+/// that are stopped at a state of a lexer's automaton
+#[derive(Debug, Copy, Clone)]
+pub struct Recovery {
+    /// The state of the automaton
+    pub state: u32,
+    /// The context of the terminal
+    pub context: u16,
+    /// The terminal's index
+    pub index: u16,
+    /// The point at which unterminated tokens stop
+    pub point: RecoveryPoint,
 }
 
 /// Represents a transition in the automaton of a lexer
@@ -87,12 +130,22 @@ impl<'a> AutomatonState<'a> {
     /// Gets the i-th matched terminal in this state
     #[must_use]
     pub fn get_terminal(&self, index: usize) -> MatchedTerminal {
+        let value = self.table[self.offset + index * 2 + 4];
         MatchedTerminal {
             context: self.table[self.offset + index * 2 + 3],
-            index: self.table[self.offset + index * 2 + 4],
+            index: value & TERMINAL_INDEX_MASK,
+            at_end: value & TERMINAL_AT_END != 0,
+            before_lookahead: value & TERMINAL_BEFORE_LOOKAHEAD != 0,
         }
     }
 
+    /// Gets the first matched terminal that is possible, depending on whether the input ends after the match
+    #[must_use]
+    pub fn get_first_terminal(&self, at_end: bool) -> Option<MatchedTerminal> {
+        self.get_terminals()
+            .find(|terminal| terminal.is_possible(at_end))
+    }
+
     /// Gets an iterator over all the matched terminals
     pub fn get_terminals(&self) -> impl Iterator<Item = MatchedTerminal> + '_ {
         let count = self.get_terminals_count();
@@ -174,6 +227,8 @@ pub struct Automaton {
     states: Vec<u16>,
     /// The number of states in the automaton
     states_count: usize,
+    /// The recoveries of unterminated tokens, ordered by state
+    recoveries: Vec<Recovery>,
 }
 
 impl Automaton {
@@ -188,9 +243,36 @@ impl Automaton {
             table,
             states,
             states_count,
+            recoveries: Vec::new(),
         }
     }
 
+    /// Sets the recoveries of unterminated tokens for this automaton
+    /// For the same state, the recoveries are expected in decreasing priority order.
+    #[must_use]
+    pub fn with_recoveries(mut self, recoveries: &[Recovery]) -> Automaton {
+        self.recoveries = recoveries.to_vec();
+        self.recoveries.sort_by_key(|recovery| recovery.state);
+        self
+    }
+
+    /// Gets whether this automaton recovers unterminated tokens
+    #[must_use]
+    pub fn has_recoveries(&self) -> bool {
+        !self.recoveries.is_empty()
+    }
+
+    /// Gets the recoveries of unterminated tokens stopped at the specified state
+    pub fn get_recoveries(&self, state: u32) -> impl Iterator<Item = Recovery> + '_ {
+        let start = self
+            .recoveries
+            .partition_point(|recovery| recovery.state < state);
+        self.recoveries[start..]
+            .iter()
+            .take_while(move |recovery| recovery.state == state)
+            .copied()
+    }
+
     /// Gets the number of states in the automaton
     #[must_use]
     pub fn get_states_count(&self) -> usize {
@@ -218,30 +300,107 @@ pub struct TokenMatch {
     pub state: u32,
     /// Length of the matched input
     pub length: u32,
+    /// Length of the last character of the matched input
+    pub last: u32,
+}
+
+impl TokenMatch {
+    /// Gets the length of the input matched by the specified terminal of the matching state
+    /// A terminal before a lookahead does not include the last character.
+    #[must_use]
+    pub fn get_length_for(&self, terminal: MatchedTerminal) -> u32 {
+        if terminal.before_lookahead {
+            self.length - self.last
+        } else {
+            self.length
+        }
+    }
+}
+
+/// Represents a token of a recoverable terminal that is not terminated in the input
+#[derive(Debug, Copy, Clone)]
+pub struct UnterminatedToken {
+    /// The recovery for the terminal
+    pub recovery: Recovery,
+    /// Length of the token, up to its recovery point
+    pub length: u32,
 }
 
 /// Runs the lexer's DFA to match a terminal in the input ahead
 #[must_use]
 pub fn run_dfa(automaton: &Automaton, input: &Text, index: usize) -> Option<TokenMatch> {
+    run_dfa_to_stop(automaton, input, index).0
+}
+
+/// Runs the lexer's DFA to match a terminal in the input ahead
+/// and finds the unterminated tokens of recoverable terminals that are possible instead
+/// A token is unterminated when the automaton stops at its recovery point, further than the match.
+/// The unterminated tokens stop where the automaton stops.
+#[must_use]
+pub fn run_dfa_recoverable(
+    automaton: &Automaton,
+    input: &Text,
+    index: usize,
+) -> (Option<TokenMatch>, Vec<UnterminatedToken>) {
+    let (result, state, position) = run_dfa_to_stop(automaton, input, index);
+    let matched = result.as_ref().map_or(0, |m| m.length as usize);
+    if !automaton.has_recoveries() || position <= index + matched {
+        return (result, Vec::new());
+    }
+    let at_end = input.is_end(position);
+    let at_end_of_line = at_end || is_line_ending_char(input.at(position));
+    let unterminated = automaton
+        .get_recoveries(state)
+        .filter(|recovery| match recovery.point {
+            RecoveryPoint::EndOfLine => at_end_of_line,
+            RecoveryPoint::EndOfInput => at_end,
+        })
+        .map(|recovery| UnterminatedToken {
+            recovery,
+            length: (position - index) as u32,
+        })
+        .collect();
+    (result, unterminated)
+}
+
+/// Runs the lexer's DFA to match a terminal in the input ahead
+/// Returns the match, if any, and the last live state of the automaton with the position it is reached at
+fn run_dfa_to_stop(
+    automaton: &Automaton,
+    input: &Text,
+    index: usize,
+) -> (Option<TokenMatch>, u32, usize) {
     if input.is_end(index) {
-        return Some(TokenMatch {
-            state: 0,
-            length: 0,
-        });
+        return (
+            Some(TokenMatch {
+                state: 0,
+                length: 0,
+                last: 0,
+            }),
+            0,
+            index,
+        );
     }
 
     let mut result = None;
     let mut state = 0;
     let mut position = index;
+    let mut last = 0;
+    let mut live = (0, index);
     let mut input_iter = input.iter_utf16_from(index);
 
     while state != DEAD_STATE {
+        live = (state, position);
         let state_data = automaton.get_state(state);
         // Is this state a matching state ?
-        if state_data.get_terminals_count() > 0 {
+        if state_data
+            .get_first_terminal(input.is_end(position))
+            .is_some()
+        {
             result = Some(TokenMatch {
                 state,
                 length: (position - index) as u32,
+                last: last as u32,
             });
         }
         // No further transition => exit
@@ -255,9 +414,10 @@ pub fn run_dfa(automaton: &Automaton, input: &Text, index: usize) -> Option<Toke
             }
             Some((current, l)) => {
                 position += l;
+                last = l;
                 state = state_data.get_target_by(current);
             }
         }
     }
-    result
+    (result, live.0, live.1)
 }
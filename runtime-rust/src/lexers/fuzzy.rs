@@ -233,6 +233,7 @@ impl<'s, 't, 'a> FuzzyMatcher<'s, 't, 'a> {
         TokenMatch {
             state: result.match_head.as_ref().unwrap().state,
             length: result.match_length as u32,
+            last: 0,
         }
     }
 
@@ -264,6 +265,15 @@ impl<'s, 't, 'a> FuzzyMatcher<'s, 't, 'a> {
         None
     }
 
+    /// Gets whether a state matches a terminal that is not the separator
+    /// Terminals that depend on the input that follows them are not used to fix errors.
+    fn is_matching(&self, state_data: &AutomatonState) -> bool {
+        state_data
+            .get_terminals()
+            .find(|terminal| !terminal.at_end && !terminal.before_lookahead)
+            .is_some_and(|terminal| u32::from(terminal.index) != self.separator)
+    }
+
     /// Inspects a head while at the end of the input
     fn inspect_at_end(
         &self,
@@ -273,9 +283,7 @@ impl<'s, 't, 'a> FuzzyMatcher<'s, 't, 'a> {
     ) {
         let state_data = self.automaton.get_state(head.state);
         // is it a matching state
-        if state_data.get_terminals_count() > 0
-            && u32::from(state_data.get_terminal(0).index) != self.separator
-        {
+        if self.is_matching(&state_data) {
             FuzzyMatcher::on_matching_head(result, head, offset);
         }
         if head.get_distance() < self.max_distance && !state_data.is_dead_end() {
@@ -295,9 +303,7 @@ impl<'s, 't, 'a> FuzzyMatcher<'s, 't, 'a> {
     ) {
         let state_data = self.automaton.get_state(head.state);
         // is it a matching state
-        if state_data.get_terminals_count() > 0
-            && u32::from(state_data.get_terminal(0).index) != self.separator
-        {
+        if self.is_matching(&state_data) {
             FuzzyMatcher::on_matching_head(result, head, offset);
         }
         if head.get_distance() >= self.max_distance || state_data.is_dead_end() {
@@ -407,9 +413,7 @@ impl<'s, 't, 'a> FuzzyMatcher<'s, 't, 'a> {
         distance: usize,
     ) {
         let state_data = self.automaton.get_state(head.state);
-        if state_data.get_terminals_count() > 0
-            && u32::from(state_data.get_terminal(0).index) != self.separator
-        {
+        if self.is_matching(&state_data) {
             FuzzyMatcher::on_matching_insertion(result, head, offset, state, distance);
         }
         if !at_end {
@@ -17,9 +17,15 @@
 
 //! Module for lexers' implementation
 
-use super::automaton::{run_dfa, Automaton, TokenMatch};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+use super::automaton::{
+    run_dfa_recoverable, Automaton, MatchedTerminal, TokenMatch, UnterminatedToken,
+};
 use super::fuzzy::FuzzyMatcher;
-use super::{ContextProvider, LexerData, TokenKernel};
+use super::{ContextProvider, LexerData, TokenKernel, DEFAULT_CONTEXT};
 use crate::errors::{ParseErrorUnexpectedChar, ParseErrors};
 use crate::symbols::SID_DOLLAR;
 use crate::tokens::TokenRepository;
@@ -75,9 +81,10 @@ impl<'s, 't, 'a> ContextFreeLexer<'s, 't, 'a> {
     pub fn new(
         repository: TokenRepository<'s, 't, 'a>,
         errors: &'a mut ParseErrors<'s>,
-        automaton: Automaton,
+        automaton: &'a Automaton,
         separator_id: u32,
     ) -> ContextFreeLexer<'s, 't, 'a> {
+        let config = repository.text.get_config();
         ContextFreeLexer {
             data: LexerData {
                 repository,
@@ -87,6 +94,12 @@ impl<'s, 't, 'a> ContextFreeLexer<'s, 't, 'a> {
                 separator_id,
                 index: 0,
                 recovery: DEFAULT_RECOVERY_MATCHING_DISTANCE,
+                max_tokens: config.max_tokens,
+                has_exceeded_max_tokens: false,
+                retain_trivia: config.retain_trivia,
+                externals: Vec::new(),
+                aliases: Vec::new(),
+                lexing_time: None,
             },
         }
     }
@@ -114,12 +127,34 @@ impl<'s, 't, 'a> ContextFreeLexer<'s, 't, 'a> {
     fn find_tokens(&mut self) {
         let mut index = 0;
         loop {
-            let mut result = run_dfa(&self.data.automaton, self.data.repository.text, index);
+            if let Some((terminal, length)) = self.data.run_external_scanners(index) {
+                if self.data.reached_max_tokens(index) {
+                    // stop here as if the end of input was reached
+                    self.data.repository.add(1, index, 0);
+                    return;
+                }
+                self.data.repository.add(terminal, index, length);
+                index += length;
+                continue;
+            }
+            let (mut result, unterminated) =
+                run_dfa_recoverable(self.data.automaton, self.data.repository.text, index);
+            if let Some(token) = unterminated.first() {
+                // the token is not terminated, stop it at its recovery point
+                let terminal = token.recovery.index as usize;
+                let length = token.length as usize;
+                self.data.push_error_unterminated(terminal, index, length);
+                if !self.add_token(terminal, index, length) {
+                    return;
+                }
+                index += length;
+                continue;
+            }
             if result.is_none() {
                 // failed to match, retry with error handling
                 result = run_fuzzy_matcher(
                     &self.data.repository,
-                    &self.data.automaton,
+                    self.data.automaton,
                     self.data.separator_id,
                     self.data.recovery,
                     self.data.errors,
@@ -135,24 +170,44 @@ impl<'s, 't, 'a> ContextFreeLexer<'s, 't, 'a> {
                     return;
                 }
                 // matched something
-                let terminal = self
+                let at_end = self
                     .data
-                    .automaton
-                    .get_state(the_match.state)
-                    .get_terminal(0)
-                    .index as usize;
-                if self.data.repository.terminals[terminal].id != self.data.separator_id {
-                    self.data
-                        .repository
-                        .add(terminal, index, the_match.length as usize);
+                    .repository
+                    .text
+                    .is_end(index + the_match.length as usize);
+                let state_data = self.data.automaton.get_state(the_match.state);
+                let matched = state_data
+                    .get_first_terminal(at_end)
+                    .unwrap_or_else(|| state_data.get_terminal(0));
+                let terminal = matched.index as usize;
+                let length = the_match.get_length_for(matched) as usize;
+                if !self.add_token(terminal, index, length) {
+                    return;
                 }
-                index += the_match.length as usize;
+                index += length;
             } else {
                 // skip this character
                 index += self.data.repository.text.at(index).len_utf8();
             }
         }
     }
+
+    /// Adds the token for a terminal matched at the specified index in the input
+    /// Separators are added as trivia, if retained.
+    /// Returns `false` when the maximum number of tokens is reached, in which case the end of input is added instead.
+    fn add_token(&mut self, terminal: usize, index: usize, length: usize) -> bool {
+        if self.data.repository.terminals[terminal].id != self.data.separator_id {
+            if self.data.reached_max_tokens(index) {
+                // stop here as if the end of input was reached
+                self.data.repository.add(1, index, 0);
+                return false;
+            }
+            self.data.repository.add(terminal, index, length);
+        } else if self.data.retain_trivia {
+            self.data.repository.add_trivia(terminal, index, length);
+        }
+        true
+    }
 }
 
 /// Represents a context-sensitive lexer (lexing rules do not depend on the context)
@@ -168,9 +223,10 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
     pub fn new(
         repository: TokenRepository<'s, 't, 'a>,
         errors: &'a mut ParseErrors<'s>,
-        automaton: Automaton,
+        automaton: &'a Automaton,
         separator_id: u32,
     ) -> ContextSensitiveLexer<'s, 't, 'a> {
+        let config = repository.text.get_config();
         ContextSensitiveLexer {
             data: LexerData {
                 repository,
@@ -180,6 +236,12 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
                 separator_id,
                 index: 0,
                 recovery: DEFAULT_RECOVERY_MATCHING_DISTANCE,
+                max_tokens: config.max_tokens,
+                has_exceeded_max_tokens: false,
+                retain_trivia: config.retain_trivia,
+                externals: Vec::new(),
+                aliases: Vec::new(),
+                lexing_time: None,
             },
             input_index: 0,
         }
@@ -191,16 +253,38 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
             return None;
         }
         loop {
-            let mut result = run_dfa(
-                &self.data.automaton,
+            if let Some((terminal_index, length)) =
+                self.data.run_external_scanners(self.input_index)
+            {
+                return Some(self.add_token(terminal_index, length, DEFAULT_CONTEXT));
+            }
+            let (mut result, unterminated) = run_dfa_recoverable(
+                self.data.automaton,
                 self.data.repository.text,
                 self.input_index,
             );
+            if let Some(token) = self.get_unterminated_for(&unterminated, contexts) {
+                // the token is not terminated, stop it at its recovery point
+                let terminal_index = token.recovery.index as usize;
+                let length = token.length as usize;
+                self.data
+                    .push_error_unterminated(terminal_index, self.input_index, length);
+                if self.data.repository.terminals[terminal_index].id != self.data.separator_id {
+                    return Some(self.add_token(terminal_index, length, token.recovery.context));
+                }
+                if self.data.retain_trivia {
+                    self.data
+                        .repository
+                        .add_trivia(terminal_index, self.input_index, length);
+                }
+                self.input_index += length;
+                continue;
+            }
             if result.is_none() {
                 // failed to match, retry with error handling
                 result = run_fuzzy_matcher(
                     &self.data.repository,
-                    &self.data.automaton,
+                    self.data.automaton,
                     self.data.separator_id,
                     self.data.recovery,
                     self.data.errors,
@@ -219,21 +303,26 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
                     });
                 }
                 // matched something
-                let terminal_index = self.get_terminal_for(the_match.state, contexts);
+                let at_end = self
+                    .data
+                    .repository
+                    .text
+                    .is_end(self.input_index + the_match.length as usize);
+                let matched = self.get_terminal_for(the_match.state, at_end, contexts);
+                let terminal_index = matched.index;
+                let length = the_match.get_length_for(matched) as usize;
                 let terminal_id = self.data.repository.terminals[terminal_index as usize].id;
                 if terminal_id != self.data.separator_id {
-                    let token_index = self.data.repository.add(
+                    return Some(self.add_token(terminal_index as usize, length, matched.context));
+                }
+                if self.data.retain_trivia {
+                    self.data.repository.add_trivia(
                         terminal_index as usize,
                         self.input_index,
-                        the_match.length as usize,
+                        length,
                     );
-                    self.input_index += the_match.length as usize;
-                    return Some(TokenKernel {
-                        terminal_id,
-                        index: token_index as u32,
-                    });
                 }
-                self.input_index += the_match.length as usize;
+                self.input_index += length;
             } else {
                 // skip this character
                 self.input_index += self.data.repository.text.at(self.input_index).len_utf8();
@@ -241,24 +330,77 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
         }
     }
 
-    /// Gets the index of the terminal with the highest priority that is possible in the contexts
-    fn get_terminal_for(&self, state: u32, contexts: &dyn ContextProvider) -> u16 {
+    /// Adds a token for the terminal matched in a context at the current index in the input
+    /// When the maximum number of tokens is reached, the end of input is produced instead.
+    #[allow(clippy::cast_possible_truncation)]
+    fn add_token(&mut self, terminal_index: usize, length: usize, context: u16) -> TokenKernel {
+        if self.data.reached_max_tokens(self.input_index) {
+            // stop here as if the end of input was reached
+            let token_index = self.data.repository.add(1, self.input_index, 0);
+            self.data.has_run = true;
+            return TokenKernel {
+                terminal_id: SID_DOLLAR,
+                index: token_index as u32,
+            };
+        }
+        let terminal_id = self.data.repository.terminals[terminal_index].id;
+        let token_index =
+            self.data
+                .repository
+                .add_in_context(terminal_index, self.input_index, length, context);
+        self.input_index += length;
+        TokenKernel {
+            terminal_id,
+            index: token_index as u32,
+        }
+    }
+
+    /// Gets the first unterminated token whose terminal is possible in the contexts
+    fn get_unterminated_for(
+        &self,
+        unterminated: &[UnterminatedToken],
+        contexts: &dyn ContextProvider,
+    ) -> Option<UnterminatedToken> {
+        unterminated.iter().copied().find(|token| {
+            let id = self.data.repository.terminals[token.recovery.index as usize].id;
+            id == self.data.separator_id
+                || contexts
+                    .get_context_priority(
+                        self.data.repository.get_count(),
+                        token.recovery.context,
+                        id,
+                    )
+                    .is_some()
+        })
+    }
+
+    /// Gets the terminal with the highest priority that is possible in the contexts
+    /// The terminals that are only matched at the end of the input are skipped when not `at_end`.
+    fn get_terminal_for(
+        &self,
+        state: u32,
+        at_end: bool,
+        contexts: &dyn ContextProvider,
+    ) -> MatchedTerminal {
         let state_data = self.data.automaton.get_state(state);
-        let mut matched = state_data.get_terminal(0);
-        let mut result = matched.index;
-        let mut id = self.data.repository.terminals[result as usize].id;
+        let mut candidates = state_data
+            .get_terminals()
+            .filter(|terminal| terminal.is_possible(at_end));
+        let Some(mut result) = candidates.next() else {
+            return state_data.get_terminal(0);
+        };
+        let id = self.data.repository.terminals[result.index as usize].id;
         if id == self.data.separator_id {
             // the separator trumps all
             return result;
         }
         let mut priority =
-            contexts.get_context_priority(self.data.repository.get_count(), matched.context, id);
-        for i in 1..state_data.get_terminals_count() {
-            matched = state_data.get_terminal(i);
-            id = self.data.repository.terminals[matched.index as usize].id;
+            contexts.get_context_priority(self.data.repository.get_count(), result.context, id);
+        for matched in candidates {
+            let id = self.data.repository.terminals[matched.index as usize].id;
             if id == self.data.separator_id {
                 // the separator trumps all
-                return matched.index;
+                return matched;
             }
             let priority_candidate = contexts.get_context_priority(
                 self.data.repository.get_count(),
@@ -269,7 +411,7 @@ impl<'s, 't, 'a> ContextSensitiveLexer<'s, 't, 'a> {
                 continue;
             }
             if priority.is_none() || priority_candidate.unwrap() < priority.unwrap() {
-                result = matched.index;
+                result = matched;
                 priority = priority_candidate;
             }
         }
@@ -303,8 +445,29 @@ impl<'s, 't, 'a> Lexer<'s, 't, 'a> {
         }
     }
 
+    /// Starts measuring the time spent matching tokens
+    /// The generated parsers do not call this, their statistics have no timings.
+    #[cfg(feature = "std")]
+    pub fn record_lexing_time(&mut self) {
+        self.get_data_mut().lexing_time = Some(Duration::ZERO);
+    }
+
     /// Gets the next token in the input
     pub fn get_next_token(&mut self, contexts: &dyn ContextProvider) -> Option<TokenKernel> {
+        #[cfg(feature = "std")]
+        if self.get_data().lexing_time.is_some() {
+            let start = std::time::Instant::now();
+            let result = self.match_next_token(contexts);
+            if let Some(time) = self.get_data_mut().lexing_time.as_mut() {
+                *time += start.elapsed();
+            }
+            return result;
+        }
+        self.match_next_token(contexts)
+    }
+
+    /// Matches the next token in the input
+    fn match_next_token(&mut self, contexts: &dyn ContextProvider) -> Option<TokenKernel> {
         match self {
             Lexer::ContextFree(ref mut lexer) => lexer.get_next_token(),
             Lexer::ContextSensitive(ref mut lexer) => lexer.get_next_token(contexts),
@@ -21,13 +21,19 @@ pub mod automaton;
 pub mod fuzzy;
 pub mod impls;
 
-use crate::errors::ParseErrors;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::errors::{ParseErrorMaxTokensExceeded, ParseErrorUnterminatedToken, ParseErrors};
 use crate::lexers::automaton::Automaton;
 use crate::tokens::TokenRepository;
 
 /// Identifier of the default context
 pub const DEFAULT_CONTEXT: u16 = 0;
 
+/// Name of the default context
+pub const DEFAULT_CONTEXT_NAME: &str = "default";
+
 /// Provides context information to a lexer
 pub trait ContextProvider {
     /// Gets the priority of the specified context required by the specified terminal
@@ -59,6 +65,33 @@ impl ContextProvider for DefaultContextProvider {
     }
 }
 
+/// A scanner for a terminal that cannot be expressed as a regular expression,
+/// e.g. indentation-based blocks
+pub trait ExternalScanner {
+    /// Tries to match the terminal at the specified offset in the input
+    /// Returns the offset after the match, if any.
+    /// Matches of the empty string are ignored.
+    fn scan(&self, input: &str, offset: usize) -> Option<usize>;
+}
+
+impl<F> ExternalScanner for F
+where
+    F: Fn(&str, usize) -> Option<usize>,
+{
+    fn scan(&self, input: &str, offset: usize) -> Option<usize> {
+        self(input, offset)
+    }
+}
+
+/// A terminal that is matched by an external scanner
+#[derive(Copy, Clone)]
+pub struct ExternalTerminal<'a> {
+    /// The index of the terminal in the lexer's table of terminals
+    pub terminal: usize,
+    /// The scanner for the terminal
+    pub scanner: &'a dyn ExternalScanner,
+}
+
 /// Represents the kernel of a token, i.e. the identifying information of a token
 #[derive(Debug, Default, Copy, Clone)]
 pub struct TokenKernel {
@@ -75,7 +108,7 @@ pub struct LexerData<'s, 't, 'a> {
     /// The repository for errors
     pub errors: &'a mut ParseErrors<'s>,
     /// The DFA automaton for this lexer
-    pub automaton: Automaton,
+    pub automaton: &'a Automaton,
     /// Whether the lexer has run yet
     pub has_run: bool,
     /// Symbol ID of the SEPARATOR terminal
@@ -85,6 +118,71 @@ pub struct LexerData<'s, 't, 'a> {
     /// The maximum Levenshtein distance to go to for the recovery of a matching failure.
     /// A distance of 0 indicates no recovery.
     pub recovery: usize,
+    /// The maximum number of tokens to produce, if any, initially the one of the text's configuration.
+    /// The lexer stops at the end of input when it is reached.
+    pub max_tokens: Option<usize>,
+    /// Whether the lexer stopped because it reached the maximum number of tokens
+    pub has_exceeded_max_tokens: bool,
+    /// Whether to retain the separator tokens as trivia of the following tokens,
+    /// initially as in the text's configuration
+    pub retain_trivia: bool,
+    /// The terminals matched by external scanners, tried in order before the automaton
+    pub externals: Vec<ExternalTerminal<'a>>,
+    /// The display names of the terminals that have one, by symbol identifier, used in errors
+    pub aliases: Vec<(u32, &'s str)>,
+    /// The time spent matching tokens, if measured
+    /// The time is only measured with the `std` feature.
+    pub lexing_time: Option<Duration>,
+}
+
+impl LexerData<'_, '_, '_> {
+    /// Runs the external scanners at the specified index in the input
+    /// Returns the index of the matched terminal and the length of the match, if any
+    fn run_external_scanners(&self, index: usize) -> Option<(usize, usize)> {
+        if self.externals.is_empty() {
+            return None;
+        }
+        let text = self.repository.text;
+        let input = text.get_value(0, text.len());
+        self.externals.iter().find_map(|external| {
+            external
+                .scanner
+                .scan(input, index)
+                .filter(|end| *end > index && *end <= input.len())
+                .map(|end| (external.terminal, end - index))
+        })
+    }
+
+    /// Gets whether the maximum number of tokens has been reached
+    /// before producing a new one at the specified index in the input,
+    /// in which case the error is recorded
+    fn reached_max_tokens(&mut self, index: usize) -> bool {
+        match self.max_tokens {
+            Some(max) if self.repository.get_tokens_count() >= max => {
+                self.has_exceeded_max_tokens = true;
+                self.errors
+                    .push_error_max_tokens_exceeded(ParseErrorMaxTokensExceeded::new(
+                        self.repository.text.get_position_at(index),
+                        max,
+                    ));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the error for an unterminated token of a terminal at the specified index in the input
+    fn push_error_unterminated(&mut self, terminal: usize, index: usize, length: usize) {
+        let text = self.repository.text;
+        self.errors.push_error_unterminated_token(
+            ParseErrorUnterminatedToken::new(
+                text.get_position_at(index),
+                text.get_value(index, length).chars().count(),
+                self.repository.terminals[terminal],
+            )
+            .with_aliases(&self.aliases),
+        );
+    }
 }
 
 pub use impls::Lexer;
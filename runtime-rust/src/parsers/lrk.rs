@@ -22,8 +22,9 @@ use alloc::vec::Vec;
 
 use super::subtree::SubTree;
 use super::{
-    get_op_code_base, get_op_code_tree_action, read_table_u16, read_u16, ContextProvider, LRAction,
-    LRActionCode, LRColumnMap, LRContexts, LRExpected, LRProduction, Parser, Symbol, TreeAction,
+    build_semantic_error, get_op_code_base, get_op_code_tree_action, read_table_u16,
+    read_table_u32, read_u16, read_u32, ContextProvider, LRAction, LRActionCode, LRColumnMap,
+    LRContexts, LRExpected, LRProduction, Parser, ParserStorage, Symbol, TreeAction,
     LR_ACTION_CODE_ACCEPT, LR_ACTION_CODE_NONE, LR_ACTION_CODE_REDUCE, LR_ACTION_CODE_SHIFT,
     LR_OP_CODE_BASE_ADD_VIRTUAL, LR_OP_CODE_BASE_SEMANTIC_ACTION, TREE_ACTION_DROP,
     TREE_ACTION_NONE, TREE_ACTION_PROMOTE, TREE_ACTION_REPLACE_BY_CHILDREN,
@@ -32,9 +33,22 @@ use super::{
 use crate::ast::{AstImpl, TableElemRef, TableType};
 use crate::errors::ParseErrorUnexpectedToken;
 use crate::lexers::{Lexer, TokenKernel, DEFAULT_CONTEXT};
-use crate::symbols::{SemanticBody, SemanticElement, SemanticElementTrait};
+use crate::result::ParseStatistics;
+use crate::symbols::{SemanticActions, SemanticBody, SemanticElement, SemanticElementTrait};
+
+/// Marker at the start of the data for a LR(k) automaton with a compressed table
+/// The number of columns cannot reach this value for uncompressed tables.
+pub const LRK_TABLE_COMPRESSED: u16 = 0xFFFF;
 
 /// Represents the LR(k) parsing table and productions
+///
+/// The LR table is either stored as is, with a row of actions for each state,
+/// or compressed by row displacement, when the data starts with `LRK_TABLE_COMPRESSED`.
+/// In the compressed form, the rows are overlaid in a single table of entries
+/// and each state has a displacement for its row.
+/// The entry for a state and column is at the state's displacement plus the column,
+/// provided the check for this entry is the state; otherwise, the action is the default one
+/// at the first entry of the table.
 #[derive(Clone)]
 pub struct LRkAutomaton {
     /// The number of columns in the LR table
@@ -47,6 +61,10 @@ pub struct LRkAutomaton {
     contexts: Vec<LRContexts>,
     /// The LR table
     table: Vec<u16>,
+    /// The displacement of the row for each state, when the table is compressed
+    displacements: Vec<u32>,
+    /// The state owning each entry of the table, when the table is compressed
+    checks: Vec<u16>,
     /// The table of LR productions
     productions: Vec<LRProduction>,
 }
@@ -55,12 +73,14 @@ impl LRkAutomaton {
     /// Initializes a new automaton from the given binary data
     #[must_use]
     pub fn new(data: &[u8]) -> LRkAutomaton {
-        let columns_count = read_u16(data, 0) as usize;
-        let states_count = read_u16(data, 2) as usize;
-        let productions_count = read_u16(data, 4) as usize;
-        let columns_map = LRColumnMap::new(data, 6, columns_count);
+        let is_compressed = read_u16(data, 0) == LRK_TABLE_COMPRESSED;
+        let start = if is_compressed { 2 } else { 0 };
+        let columns_count = read_u16(data, start) as usize;
+        let states_count = read_u16(data, start + 2) as usize;
+        let productions_count = read_u16(data, start + 4) as usize;
+        let columns_map = LRColumnMap::new(data, start + 6, columns_count);
         let mut contexts = Vec::with_capacity(states_count);
-        let mut index = 6 + columns_count * 2;
+        let mut index = start + 6 + columns_count * 2;
         for _i in 0..states_count {
             let mut context = LRContexts::new();
             let count = read_u16(data, index);
@@ -71,8 +91,21 @@ impl LRkAutomaton {
             }
             contexts.push(context);
         }
-        let table = read_table_u16(data, index, states_count * columns_count * 2);
-        index += states_count * columns_count * 4;
+        let (table, displacements, checks) = if is_compressed {
+            let entries_count = read_u32(data, index) as usize;
+            index += 4;
+            let displacements = read_table_u32(data, index, states_count);
+            index += states_count * 4;
+            let checks = read_table_u16(data, index, entries_count);
+            index += entries_count * 2;
+            let table = read_table_u16(data, index, entries_count * 2);
+            index += entries_count * 4;
+            (table, displacements, checks)
+        } else {
+            let table = read_table_u16(data, index, states_count * columns_count * 2);
+            index += states_count * columns_count * 4;
+            (table, Vec::new(), Vec::new())
+        };
         let mut productions = Vec::with_capacity(productions_count);
         for _i in 0..productions_count {
             let production = LRProduction::new(data, &mut index);
@@ -84,10 +117,18 @@ impl LRkAutomaton {
             columns_map,
             contexts,
             table,
+            displacements,
+            checks,
             productions,
         }
     }
 
+    /// Gets whether the LR table is compressed
+    #[must_use]
+    pub fn is_compressed(&self) -> bool {
+        !self.displacements.is_empty()
+    }
+
     /// Gets the number of states in this automaton
     #[must_use]
     pub fn get_states_count(&self) -> usize {
@@ -122,12 +163,29 @@ impl LRkAutomaton {
     /// Gets the LR(k) action for the given state and column
     #[must_use]
     pub fn get_action_at(&self, state: u32, column: usize) -> LRAction {
+        let entry = if self.displacements.is_empty() {
+            state as usize * self.columns_count + column
+        } else {
+            let entry = self.displacements[state as usize] as usize + column;
+            if u32::from(self.checks[entry]) == state {
+                entry
+            } else {
+                // the default action
+                0
+            }
+        };
         LRAction {
             table: &self.table,
-            offset: (state as usize * self.columns_count + column) * 2,
+            offset: entry * 2,
         }
     }
 
+    /// Gets the number of productions
+    #[must_use]
+    pub fn get_productions_count(&self) -> usize {
+        self.productions.len()
+    }
+
     /// Gets the i-th production
     #[must_use]
     pub fn get_production(&self, index: usize) -> &LRProduction {
@@ -138,15 +196,13 @@ impl LRkAutomaton {
     #[must_use]
     pub fn get_expected<'s>(&self, state: u32, terminals: &[Symbol<'s>]) -> LRExpected<'s> {
         let mut expected = LRExpected::new();
-        let mut offset = self.columns_count * state as usize * 2;
-        for terminal in terminals {
-            let action = self.table[offset];
+        for (column, terminal) in terminals.iter().enumerate() {
+            let action = self.get_action_at(state, column).get_code();
             if action == LR_ACTION_CODE_SHIFT {
                 expected.shifts.push(*terminal);
             } else if action == LR_ACTION_CODE_REDUCE {
                 expected.reductions.push(*terminal);
             }
-            offset += 2;
         }
         expected
     }
@@ -400,15 +456,46 @@ struct LRkHead {
     identifier: u32,
 }
 
+/// The storage for the stacks of a LR(k) parser, reused across parses
+#[derive(Default)]
+pub(crate) struct LRkStorage {
+    /// The parser's stack
+    stack: Vec<LRkHead>,
+    /// The stack of semantic objects
+    sub_trees: Vec<SubTree>,
+    /// The reduction handle
+    handle: Vec<usize>,
+}
+
+impl LRkStorage {
+    /// Gets the number of items the stacks can hold without allocating
+    pub fn capacity(&self) -> usize {
+        self.stack.capacity() + self.sub_trees.capacity() + self.handle.capacity()
+    }
+}
+
+/// The semantic actions callback of a LR(k) parser, if any
+type OptionalActions<'a> = Option<&'a mut dyn SemanticActions>;
+
 struct LRkParserData<'s, 'a> {
     /// The parser's automaton
-    automaton: LRkAutomaton,
+    automaton: &'a LRkAutomaton,
     /// The parser's stack
     stack: Vec<LRkHead>,
     /// The grammar variables
     variables: &'a [Symbol<'s>],
-    /// The semantic actions
-    actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+    /// The semantic actions, if any
+    actions: OptionalActions<'a>,
+    /// The number of reductions for each production, if recorded
+    rule_counts: Option<Vec<usize>>,
+    /// The number of reductions performed so far
+    reductions: usize,
+    /// Whether to stop on the first error raised by a semantic action
+    stop_on_semantic_error: bool,
+    /// Whether the parser stopped on an error raised by a semantic action
+    stopped: bool,
+    /// Whether the unexpected token errors keep the stack of states
+    capture_stacks: bool,
 }
 
 impl<'s, 'a> ContextProvider for LRkParserData<'s, 'a> {
@@ -578,8 +665,21 @@ impl<'s, 't, 'a> LRkParserData<'s, 'a> {
                 return action.get_code();
             }
             // now reduce
+            self.reductions += 1;
+            if let Some(counts) = self.rule_counts.as_mut() {
+                counts[action.get_data() as usize] += 1;
+            }
             let production = self.automaton.get_production(action.get_data() as usize);
-            let variable = LRkParserData::reduce(production, builder, &mut self.actions);
+            let actions = self
+                .actions
+                .as_mut()
+                .map(|actions| &mut **actions as &mut dyn SemanticActions);
+            let (variable, failed) =
+                LRkParserData::reduce(production, builder, actions, kernel.index as usize);
+            if failed && self.stop_on_semantic_error {
+                self.stopped = true;
+                return LR_ACTION_CODE_NONE;
+            }
             let length = stack.len();
             stack.truncate(length - production.reduction_length);
             let action = self.automaton.get_action(
@@ -594,11 +694,14 @@ impl<'s, 't, 'a> LRkParserData<'s, 'a> {
     }
 
     /// Executes the given LR reduction
+    /// Returns the reduced variable and whether a semantic action failed
     fn reduce(
         production: &LRProduction,
         builder: &mut LRkAstBuilder<'s, 't, 'a>,
-        actions: &mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
-    ) -> Symbol<'s> {
+        mut actions: OptionalActions<'_>,
+        lookahead: usize,
+    ) -> (Symbol<'s>, bool) {
+        let mut failed = false;
         let variable = builder.variables[production.head];
         builder.reduction_prepare(
             production.head,
@@ -613,7 +716,22 @@ impl<'s, 't, 'a> LRkParserData<'s, 'a> {
                 LR_OP_CODE_BASE_SEMANTIC_ACTION => {
                     let index = production.bytecode[i] as usize;
                     i += 1;
-                    actions(index, variable, builder);
+                    if let Some(actions) = actions.as_mut() {
+                        if let Err(error) = actions.execute(index, variable, builder) {
+                            let error = build_semantic_error(
+                                error,
+                                &*builder,
+                                &builder.lexer.get_data().repository,
+                                Some(lookahead),
+                            );
+                            builder
+                                .lexer
+                                .get_data_mut()
+                                .errors
+                                .push_error_semantic(error);
+                            failed = true;
+                        }
+                    }
                 }
                 LR_OP_CODE_BASE_ADD_VIRTUAL => {
                     let index = production.bytecode[i] as usize;
@@ -626,7 +744,7 @@ impl<'s, 't, 'a> LRkParserData<'s, 'a> {
             }
         }
         builder.reduce();
-        variable
+        (variable, failed)
     }
 }
 
@@ -644,10 +762,34 @@ impl<'s, 't, 'a> LRkParser<'s, 't, 'a> {
         lexer: &'a mut Lexer<'s, 't, 'a>,
         variables: &'a [Symbol<'s>],
         virtuals: &'a [Symbol<'s>],
-        automaton: LRkAutomaton,
+        automaton: &'a LRkAutomaton,
+        ast: &'a mut AstImpl,
+        actions: &'a mut dyn SemanticActions,
+    ) -> LRkParser<'s, 't, 'a> {
+        LRkParser::new_inner(lexer, variables, virtuals, automaton, ast, Some(actions))
+    }
+
+    /// Initializes a new instance of the parser that does not execute any semantic action
+    pub fn new_without_actions(
+        lexer: &'a mut Lexer<'s, 't, 'a>,
+        variables: &'a [Symbol<'s>],
+        virtuals: &'a [Symbol<'s>],
+        automaton: &'a LRkAutomaton,
         ast: &'a mut AstImpl,
-        actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
     ) -> LRkParser<'s, 't, 'a> {
+        LRkParser::new_inner(lexer, variables, virtuals, automaton, ast, None)
+    }
+
+    /// Initializes a new instance of the parser
+    fn new_inner(
+        lexer: &'a mut Lexer<'s, 't, 'a>,
+        variables: &'a [Symbol<'s>],
+        virtuals: &'a [Symbol<'s>],
+        automaton: &'a LRkAutomaton,
+        ast: &'a mut AstImpl,
+        actions: OptionalActions<'a>,
+    ) -> LRkParser<'s, 't, 'a> {
+        let capture_stacks = lexer.get_data().repository.text.get_config().capture_stacks;
         LRkParser {
             data: LRkParserData {
                 automaton,
@@ -657,6 +799,11 @@ impl<'s, 't, 'a> LRkParser<'s, 't, 'a> {
                 }],
                 variables,
                 actions,
+                rule_counts: None,
+                reductions: 0,
+                stop_on_semantic_error: false,
+                stopped: false,
+                capture_stacks,
             },
             builder: LRkAstBuilder::<'s, 't, 'a>::new(lexer, variables, virtuals, ast),
         }
@@ -690,7 +837,7 @@ impl<'s, 't, 'a> LRkParser<'s, 't, 'a> {
                 my_expected.push(*x);
             }
         }
-        ParseErrorUnexpectedToken::new(
+        let error = ParseErrorUnexpectedToken::new(
             token.get_position().unwrap(),
             token.get_span().unwrap().length,
             token.get_value().unwrap().to_string(),
@@ -699,6 +846,17 @@ impl<'s, 't, 'a> LRkParser<'s, 't, 'a> {
             alloc::vec![state],
             my_expected,
         )
+        .with_aliases(&self.builder.lexer.get_data().aliases);
+        if self.data.capture_stacks {
+            error.with_stacks(alloc::vec![self
+                .data
+                .stack
+                .iter()
+                .map(|head| head.state)
+                .collect()])
+        } else {
+            error
+        }
     }
 }
 
@@ -721,6 +879,14 @@ impl<'s, 't, 'a> Parser for LRkParser<'s, 't, 'a> {
                         LR_ACTION_CODE_SHIFT => {
                             kernel_maybe = self.get_next_token();
                         }
+                        _ if self.data.stopped => {
+                            // stopped on an error raised by a semantic action
+                            return;
+                        }
+                        _ if self.builder.lexer.get_data().has_exceeded_max_tokens => {
+                            // the input was cut at the maximum number of tokens, the error is already recorded
+                            return;
+                        }
                         _ => {
                             // this is an error
                             let error = self.build_error(kernel);
@@ -737,4 +903,50 @@ impl<'s, 't, 'a> Parser for LRkParser<'s, 't, 'a> {
             }
         }
     }
+
+    fn record_rule_counts(&mut self) {
+        self.data.rule_counts = Some(alloc::vec![0; self.data.automaton.get_productions_count()]);
+    }
+
+    fn take_rule_counts(&mut self) -> Option<Vec<usize>> {
+        self.data.rule_counts.take()
+    }
+
+    fn set_start_state(&mut self, state: u32) {
+        self.data.stack[0].state = state;
+    }
+
+    fn get_statistics(&self) -> ParseStatistics {
+        ParseStatistics {
+            reductions: self.data.reductions,
+            lexing_time: self.builder.lexer.get_data().lexing_time,
+            ..ParseStatistics::default()
+        }
+    }
+
+    fn set_stop_on_semantic_error(&mut self, stop: bool) {
+        self.data.stop_on_semantic_error = stop;
+    }
+
+    fn use_storage(&mut self, storage: &mut ParserStorage) {
+        let Some(mut storage) = storage.lrk.take() else {
+            return;
+        };
+        // keep the bottom of the stack for the start state
+        storage.stack.clear();
+        storage.stack.push(self.data.stack[0]);
+        storage.sub_trees.clear();
+        storage.handle.clear();
+        self.data.stack = storage.stack;
+        self.builder.stack = storage.sub_trees;
+        self.builder.handle = storage.handle;
+    }
+
+    fn release_storage(&mut self, storage: &mut ParserStorage) {
+        storage.lrk = Some(LRkStorage {
+            stack: core::mem::take(&mut self.data.stack),
+            sub_trees: core::mem::take(&mut self.builder.stack),
+            handle: core::mem::take(&mut self.builder.handle),
+        });
+    }
 }
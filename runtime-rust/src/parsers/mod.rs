@@ -22,10 +22,16 @@ pub mod rnglr;
 pub mod subtree;
 
 use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
 
+use self::lrk::LRkStorage;
+use self::rnglr::{GSSStatistics, RNGLRStorage};
+use crate::errors::ParseErrorSemantic;
 use crate::lexers::ContextProvider;
-use crate::symbols::Symbol;
-use crate::utils::bin::{read_table_u16, read_u16, read_u32};
+use crate::result::ParseStatistics;
+use crate::symbols::{SemanticBody, SemanticElementTrait, SemanticError, Symbol};
+use crate::tokens::TokenRepository;
+use crate::utils::bin::{read_table_u16, read_table_u32, read_u16, read_u32};
 
 /// The maximum number of errors
 pub const MAX_ERROR_COUNT: usize = 100;
@@ -318,7 +324,113 @@ impl<'s> LRExpected<'s> {
     }
 }
 
+/// Builds the parse error for an error raised by a semantic action
+/// The error is positioned on the element of the body it refers to,
+/// or else on the first element of the body that has a position,
+/// or else on the lookahead token, if any, or the start of the input.
+pub(crate) fn build_semantic_error(
+    error: SemanticError,
+    body: &dyn SemanticBody,
+    repository: &TokenRepository<'_, '_, '_>,
+    lookahead: Option<usize>,
+) -> ParseErrorSemantic {
+    let on_element = error
+        .element
+        .filter(|&index| index < body.length())
+        .map(|index| body.get_element_at(index))
+        .and_then(|element| {
+            let position = element.get_position()?;
+            let length = element.get_value().map_or(0, |value| value.chars().count());
+            Some((position, length))
+        });
+    let on_body = || {
+        (0..body.length())
+            .find_map(|index| body.get_element_at(index).get_position())
+            .map(|position| (position, 0))
+    };
+    let on_lookahead = || match lookahead {
+        Some(index) => {
+            let token = repository.get_token(index);
+            let position = token
+                .get_position()
+                .unwrap_or_else(|| repository.text.get_position_at(0));
+            (position, 0)
+        }
+        None => (repository.text.get_position_at(0), 0),
+    };
+    let (position, length) = on_element.or_else(on_body).unwrap_or_else(on_lookahead);
+    ParseErrorSemantic::new(position, length, error.message)
+}
+
+/// The storage for the stacks of parsers, reused across successive parses
+/// It holds the stack of LR(k) parsers and the GSS and queues of RNGLR parsers.
+#[derive(Default)]
+pub struct ParserStorage {
+    /// The storage for LR(k) parsers, if any
+    pub(crate) lrk: Option<LRkStorage>,
+    /// The storage for RNGLR parsers, if any
+    pub(crate) rnglr: Option<RNGLRStorage>,
+}
+
+impl Debug for ParserStorage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParserStorage")
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+impl ParserStorage {
+    /// Gets the number of items the stored stacks can hold without allocating
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.lrk.as_ref().map_or(0, LRkStorage::capacity)
+            + self.rnglr.as_ref().map_or(0, RNGLRStorage::capacity)
+    }
+}
+
 pub trait Parser {
     /// Parses the input
     fn parse(&mut self);
+
+    /// Starts recording the number of reductions for each production of the parser's automaton
+    /// By default, the parser does not record them.
+    fn record_rule_counts(&mut self) {}
+
+    /// Takes the number of reductions for each production of the parser's automaton, if recorded
+    fn take_rule_counts(&mut self) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Starts recording statistics on the splits and merges of the GSS
+    /// Only RNGLR parsers have a GSS, the other parsers ignore this.
+    fn record_gss_statistics(&mut self) {}
+
+    /// Takes the statistics on the GSS, if recorded
+    fn take_gss_statistics(&mut self) -> Option<GSSStatistics> {
+        None
+    }
+
+    /// Gets the statistics measured while parsing:
+    /// the number of reductions, the peak of the GSS and the time spent in the lexer
+    /// By default, no statistics are measured.
+    fn get_statistics(&self) -> ParseStatistics {
+        ParseStatistics::default()
+    }
+
+    /// Sets the state of the parser's automaton to start parsing from
+    /// This selects an additional axiom of the grammar, the default state 0 being for the main axiom.
+    /// By default, the parser only supports the main axiom and ignores this.
+    fn set_start_state(&mut self, _state: u32) {}
+
+    /// Sets whether the parser stops on the first error raised by a semantic action
+    /// By default, the error is recorded and the parser continues.
+    fn set_stop_on_semantic_error(&mut self, _stop: bool) {}
+
+    /// Takes the storage for the stacks of the parser from a previous parse, if any
+    /// By default, the parser allocates its own storage and ignores this.
+    fn use_storage(&mut self, _storage: &mut ParserStorage) {}
+
+    /// Gives back the storage for the stacks of the parser, for reuse by a later parse
+    fn release_storage(&mut self, _storage: &mut ParserStorage) {}
 }
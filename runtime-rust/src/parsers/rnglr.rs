@@ -22,21 +22,24 @@ use alloc::string::ToString;
 use alloc::vec::Vec;
 
 use super::{
-    get_op_code_base, get_op_code_tree_action, read_table_u16, read_u16, read_u32, ContextProvider,
-    LRAction, LRColumnMap, LRContexts, LRExpected, LRProduction, Parser, Symbol, TreeAction,
-    LR_ACTION_CODE_ACCEPT, LR_ACTION_CODE_REDUCE, LR_ACTION_CODE_SHIFT,
-    LR_OP_CODE_BASE_ADD_NULLABLE_VARIABLE, LR_OP_CODE_BASE_ADD_VIRTUAL,
+    build_semantic_error, get_op_code_base, get_op_code_tree_action, read_table_u16, read_u16,
+    read_u32, ContextProvider, LRAction, LRColumnMap, LRContexts, LRExpected, LRProduction, Parser,
+    ParserStorage, Symbol, TreeAction, LR_ACTION_CODE_ACCEPT, LR_ACTION_CODE_REDUCE,
+    LR_ACTION_CODE_SHIFT, LR_OP_CODE_BASE_ADD_NULLABLE_VARIABLE, LR_OP_CODE_BASE_ADD_VIRTUAL,
     LR_OP_CODE_BASE_SEMANTIC_ACTION, TREE_ACTION_DROP, TREE_ACTION_PROMOTE,
     TREE_ACTION_REPLACE_BY_CHILDREN, TREE_ACTION_REPLACE_BY_EPSILON,
 };
 use crate::ast::{AstCell, AstImpl, TableElemRef, TableType};
-use crate::errors::ParseErrorUnexpectedToken;
+use crate::errors::{ParseErrorForkBudgetExceeded, ParseErrorUnexpectedToken};
 use crate::lexers::{Lexer, TokenKernel, DEFAULT_CONTEXT};
+use crate::result::ParseStatistics;
 use crate::sppf::{
     SppfImpl, SppfImplNodeRef, SppfImplNodeReplaceable, SppfImplNodeReplaceableVersion,
     SppfImplNodeVersions,
 };
-use crate::symbols::{SemanticBody, SemanticElement, SemanticElementTrait, SID_EPSILON};
+use crate::symbols::{
+    SemanticActions, SemanticBody, SemanticElement, SemanticElementTrait, SID_EPSILON,
+};
 use crate::utils::biglist::BigList;
 use crate::utils::OwnOrMut;
 
@@ -259,8 +262,6 @@ struct GSSLabel {
 /// Represents an edge in a Graph-Structured Stack
 #[derive(Debug, Default, Copy, Clone)]
 struct GSSEdge {
-    /// The index of the node from which this edge starts
-    from: u32,
     /// The index of the node to which this edge arrives to
     to: u32,
     /// The label for this edge
@@ -279,74 +280,117 @@ struct GSSGeneration {
 }
 
 /// Represents a path in a GSS
-#[derive(Debug, Clone)]
+/// The labels on the path are stored in the buffer of the `GSSPaths` that contains it.
+#[derive(Debug, Copy, Clone)]
 struct GSSPath {
     /// The final target of this path
     last_node: usize,
-    /// The generation containing the final target of this path
-    generation: usize,
-    /// The labels on this GSS path
+    /// The index of the first label of this path in the buffer of labels
+    labels: usize,
+}
+
+/// A reusable buffer of paths in a GSS, all with the same length
+/// The labels of all the paths are stored in a single flat buffer,
+/// each path owning a slot of `length` consecutive labels.
+#[derive(Debug, Default)]
+struct GSSPaths {
+    /// The paths in this buffer
+    paths: Vec<GSSPath>,
+    /// The labels of all the paths
     labels: Vec<GSSLabel>,
+    /// The length of the paths
+    length: usize,
+    /// The key for the paths currently in this buffer: the start node, the length and the GSS stamp
+    key: Option<(usize, usize, usize)>,
 }
 
-impl GSSPath {
-    /// Initializes this path
-    pub fn new(last_node: usize, generation: usize, length: usize) -> GSSPath {
-        GSSPath {
-            last_node,
-            generation,
-            labels: if length == 0 {
-                Vec::new()
-            } else {
-                Vec::with_capacity(length)
-            },
-        }
+impl GSSPaths {
+    /// Clears this buffer for paths of the given length
+    fn reset(&mut self, length: usize) {
+        self.paths.clear();
+        self.labels.clear();
+        self.length = length;
+        self.key = None;
     }
 
-    /// Initializes this path
-    pub fn new_length0(last_node: usize, generation: usize) -> GSSPath {
-        GSSPath {
-            last_node,
-            generation,
-            labels: Vec::new(),
-        }
+    /// Gets the number of paths in this buffer
+    fn len(&self) -> usize {
+        self.paths.len()
     }
 
-    /// Initializes this path
-    pub fn from(
-        previous: &GSSPath,
-        last_node: usize,
-        generation: usize,
-        label: GSSLabel,
-    ) -> GSSPath {
-        let mut result = GSSPath {
-            last_node,
-            generation,
-            labels: previous.labels.clone(),
-        };
-        result.labels.push(label);
-        result
+    /// Gets an iterator over the paths in this buffer
+    fn iter(&self) -> core::slice::Iter<'_, GSSPath> {
+        self.paths.iter()
+    }
+
+    /// Gets the labels on the given path
+    fn labels_of(&self, path: &GSSPath) -> &[GSSLabel] {
+        &self.labels[path.labels..(path.labels + self.length)]
+    }
+
+    /// Adds a new path ending at the given node
+    fn push(&mut self, last_node: usize) -> usize {
+        let labels = self.labels.len();
+        self.labels
+            .resize(labels + self.length, GSSLabel::default());
+        self.paths.push(GSSPath { last_node, labels });
+        self.paths.len() - 1
+    }
+
+    /// Adds a new path that extends the first `hop` labels of a previous one with an edge
+    fn push_extended(&mut self, previous: usize, hop: usize, edge: GSSEdge) {
+        let origin = self.paths[previous].labels;
+        let index = self.push(edge.to as usize);
+        let labels = self.paths[index].labels;
+        self.labels.copy_within(origin..(origin + hop), labels);
+        self.labels[labels + hop] = edge.label;
     }
 
-    /// Pushes the next label
-    pub fn push(&mut self, last_node: usize, generation: usize, label: GSSLabel) {
-        self.last_node = last_node;
-        self.generation = generation;
-        self.labels.push(label);
+    /// Extends the path at the given index with an edge
+    fn extend(&mut self, index: usize, hop: usize, edge: GSSEdge) {
+        let path = &mut self.paths[index];
+        path.last_node = edge.to as usize;
+        self.labels[path.labels + hop] = edge.label;
     }
 }
 
+/// Statistics on how the GSS of a RNGLR parser split and merged during a parse
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GSSStatistics {
+    /// The number of additional stacks created because a GSS node had more than one action on the lookahead
+    pub splits: usize,
+    /// The number of times a stack joined an existing GSS node
+    pub merges: usize,
+    /// The maximum number of live stacks shifting the same token, before those reaching the same state merge
+    pub max_live_stacks: usize,
+    /// The maximum number of live stacks at an input position, as the number of GSS edges in its generation
+    pub peak_stacks: usize,
+}
+
+/// Marker for the absence of an edge in the lists of edges
+const GSS_NO_EDGE: u32 = 0xFFFF_FFFF;
+
 /// Represents Graph-Structured Stacks for GLR parsers
+/// Nodes and edges are stored in flat lists indexed by their identifier.
+/// The edges starting from a node are linked together, in the order of their creation.
 #[allow(clippy::upper_case_acronyms)]
 struct GSS {
     /// The label (GLR state) on the GSS node for the given index
     node_labels: BigList<u32>,
+    /// The generation of the GSS node for the given index
+    node_generation: BigList<usize>,
+    /// The first edge starting from the GSS node for the given index
+    node_first_edge: BigList<u32>,
+    /// The last edge starting from the GSS node for the given index
+    node_last_edge: BigList<u32>,
     /// The generations of nodes in this GSS
     node_generations: BigList<GSSGeneration>,
     /// The edges in this GSS
     edges: BigList<GSSEdge>,
-    /// The generations for the edges
-    edges_generations: BigList<GSSGeneration>,
+    /// The next edge starting from the same node as the edge for the given index
+    edges_next: BigList<u32>,
+    /// The number of edges in the current generation
+    current_edges: usize,
     /// Index of the current generation
     current_generation: usize,
 }
@@ -356,13 +400,41 @@ impl GSS {
     pub fn new() -> GSS {
         GSS {
             node_labels: BigList::default(),
+            node_generation: BigList::default(),
+            node_first_edge: BigList::default(),
+            node_last_edge: BigList::default(),
             node_generations: BigList::default(),
             edges: BigList::default(),
-            edges_generations: BigList::default(),
+            edges_next: BigList::default(),
+            current_edges: 0,
             current_generation: 0,
         }
     }
 
+    /// Removes all the nodes and edges, keeping the allocated storage for reuse
+    pub fn clear(&mut self) {
+        self.node_labels.clear();
+        self.node_generation.clear();
+        self.node_first_edge.clear();
+        self.node_last_edge.clear();
+        self.node_generations.clear();
+        self.edges.clear();
+        self.edges_next.clear();
+        self.current_edges = 0;
+        self.current_generation = 0;
+    }
+
+    /// Gets the number of nodes, edges and generations this GSS can hold without allocating
+    pub fn capacity(&self) -> usize {
+        self.node_labels.capacity()
+            + self.node_generation.capacity()
+            + self.node_first_edge.capacity()
+            + self.node_last_edge.capacity()
+            + self.node_generations.capacity()
+            + self.edges.capacity()
+            + self.edges_next.capacity()
+    }
+
     /// Gets the data of the current generation
     pub fn get_current_generation(&self) -> GSSGeneration {
         self.node_generations[self.current_generation]
@@ -384,16 +456,23 @@ impl GSS {
         (data.start..(data.start + data.count)).find(|&i| self.node_labels[i] == state)
     }
 
-    /// Gets the corresponding edge, if any
-    pub fn get_edge(&self, generation: usize, from: usize, to: usize) -> Option<GSSEdge> {
-        let data = self.edges_generations[generation];
-        for i in data.start..(data.start + data.count) {
-            let edge = self.edges[i];
-            if edge.from as usize == from && edge.to as usize == to {
-                return Some(edge);
+    /// Gets the edges starting from the given node, in the order of their creation
+    fn get_edges_from(&self, node: usize) -> impl Iterator<Item = GSSEdge> + '_ {
+        let mut next = self.node_first_edge[node];
+        core::iter::from_fn(move || {
+            if next == GSS_NO_EDGE {
+                return None;
             }
-        }
-        None
+            let edge = self.edges[next as usize];
+            next = self.edges_next[next as usize];
+            Some(edge)
+        })
+    }
+
+    /// Gets the corresponding edge, if any
+    pub fn get_edge(&self, from: usize, to: usize) -> Option<GSSEdge> {
+        self.get_edges_from(from)
+            .find(|edge| edge.to as usize == to)
     }
 
     /// Opens a new generation in this GSS
@@ -402,10 +481,7 @@ impl GSS {
             start: self.node_labels.len(),
             count: 0,
         });
-        self.edges_generations.push(GSSGeneration {
-            start: self.edges.len(),
-            count: 0,
-        });
+        self.current_edges = 0;
         if self.node_generations.len() == 1 {
             // this is the first generation
             self.current_generation = 0;
@@ -418,93 +494,99 @@ impl GSS {
     /// Creates a new node in the GSS
     pub fn create_node(&mut self, state: u32) -> usize {
         let node = self.node_labels.push(state);
+        self.node_generation.push(self.current_generation);
+        self.node_first_edge.push(GSS_NO_EDGE);
+        self.node_last_edge.push(GSS_NO_EDGE);
         self.node_generations[self.current_generation].count += 1;
         node
     }
 
     /// Creates a new edge in the GSS
+    #[allow(clippy::cast_possible_truncation)]
     pub fn create_edge(&mut self, from: usize, to: usize, label: GSSLabel) {
-        self.edges.push(GSSEdge {
-            from: from as u32,
+        let edge = self.edges.push(GSSEdge {
             to: to as u32,
             label,
-        });
-        self.edges_generations[self.current_generation].count += 1;
+        }) as u32;
+        self.edges_next.push(GSS_NO_EDGE);
+        let last = self.node_last_edge[from];
+        if last == GSS_NO_EDGE {
+            self.node_first_edge[from] = edge;
+        } else {
+            self.edges_next[last as usize] = edge;
+        }
+        self.node_last_edge[from] = edge;
+        self.current_edges += 1;
     }
 
-    /// Retrieve the generation of the given node in this GSS
-    fn get_generation_of(&self, node: usize) -> usize {
-        for i in (0..=self.current_generation).rev() {
-            let data = self.node_generations[i];
-            if node >= data.start && node < data.start + data.count {
-                return i;
-            }
+    /// Gets the number of edges in the current generation
+    pub fn get_current_edges_count(&self) -> usize {
+        self.current_edges
+    }
+
+    /// Gets the states on one of the stacks ending with the given node, from the bottom to the top
+    pub fn get_stack_states(&self, node: usize) -> Vec<u32> {
+        let mut states = alloc::vec![self.node_labels[node]];
+        let mut current = node;
+        // edges go from a node to nodes in the same or previous generations
+        while states.len() <= self.node_labels.len() {
+            let Some(edge) = self.get_edges_from(current).next() else {
+                break;
+            };
+            current = edge.to as usize;
+            states.push(self.node_labels[current]);
         }
-        panic!("Node not found");
+        states.reverse();
+        states
     }
 
     /// Gets all paths in the GSS starting at the given node and with the given length
-    pub fn get_paths(&self, from: usize, length: usize) -> Vec<GSSPath> {
-        let mut paths = Vec::new();
-        if length == 0 {
-            // 0-length path, simply return a single path with the 'from' node
-            paths.push(GSSPath::new_length0(from, 0));
-            return paths;
+    /// The paths are written in the given buffer, which is left untouched
+    /// when it already contains the same paths, i.e. when the GSS could not have changed for them.
+    pub fn get_paths(&self, from: usize, length: usize, paths: &mut GSSPaths) {
+        // The edges starting from the nodes in the previous generations can no longer change
+        let stamp = if self.node_generation[from] == self.current_generation {
+            self.edges.len()
+        } else {
+            usize::MAX
+        };
+        let key = (from, length, stamp);
+        if paths.key == Some(key) {
+            return;
         }
-
+        paths.reset(length);
         // Initialize the first path
-        paths.push(GSSPath::new(from, self.get_generation_of(from), length));
+        paths.push(from);
         // For the remaining hops
-        for _i in 0..length {
+        for hop in 0..length {
             // Insertion index for the compaction process
             let mut m = 0;
             let total = paths.len();
             for p in 0..total {
                 // for all paths
-                let last = paths[p].last_node;
-                let gen_index = paths[p].generation;
+                let last = paths.paths[p].last_node;
                 // Look for new additional paths from last
-                let gen = self.edges_generations[gen_index];
                 let mut first_edge: Option<GSSEdge> = None;
-                for e in gen.start..(gen.start + gen.count) {
-                    let edge = self.edges[e];
-                    if edge.from as usize == last {
-                        match first_edge {
-                            None => {
-                                // This is the first edge
-                                first_edge = Some(edge);
-                            }
-                            Some(_x) => {
-                                // Not the first edge
-                                // Clone and extend the new path
-                                let new_path = GSSPath::from(
-                                    &paths[p],
-                                    edge.to as usize,
-                                    self.get_generation_of(edge.to as usize),
-                                    edge.label,
-                                );
-                                paths.push(new_path);
-                            }
-                        }
+                for edge in self.get_edges_from(last) {
+                    if first_edge.is_none() {
+                        // This is the first edge
+                        first_edge = Some(edge);
+                    } else {
+                        // Not the first edge
+                        // Clone and extend the new path
+                        paths.push_extended(p, hop, edge);
                     }
                 }
                 // Check whether there was at least one edge
-                match first_edge {
-                    None => {}
-                    Some(edge) => {
-                        // Continue the current path
-                        if m != p {
-                            // swap m and p paths
-                            paths.swap(m, p);
-                        }
-                        paths[m].push(
-                            edge.to as usize,
-                            self.get_generation_of(edge.to as usize),
-                            edge.label,
-                        );
-                        // goto next
-                        m += 1;
+                if let Some(edge) = first_edge {
+                    // Continue the current path
+                    if m != p {
+                        // swap m and p paths
+                        paths.paths.swap(m, p);
                     }
+                    paths.extend(m, hop, edge);
+                    // goto next
+                    m += 1;
                 }
             }
             // inspected all current paths
@@ -512,15 +594,15 @@ impl GSS {
                 // if some previous paths have been removed
                 // => compact the list if needed
                 for p in total..paths.len() {
-                    paths.swap(m, p);
+                    paths.paths.swap(m, p);
                     m += 1;
                 }
                 // truncates the paths to those inserted
                 // m is now the exact number of paths
-                paths.truncate(m);
+                paths.paths.truncate(m);
             }
         }
-        paths
+        paths.key = Some(key);
     }
 }
 
@@ -664,21 +746,28 @@ impl<'s, 't, 'a, 'l> SPPFBuilder<'s, 't, 'a, 'l> {
     }
 
     /// Prepares for the forthcoming reduction operations
-    pub fn reduction_prepare(&mut self, first: GSSLabel, path: &GSSPath, length: usize) {
-        let mut stack = Vec::new();
-        if length > 0 {
-            if length > 1 {
-                for i in 0..(length - 1) {
-                    stack.push(path.labels[length - 2 - i]);
-                }
-            }
-            stack.push(first);
-        }
-        self.reduction = Some(SPPFReduction {
-            versions: alloc::vec![SPPFReductionVersion::new(length)],
-            stack,
+    /// The buffers of the previous reduction are reused when possible.
+    pub fn reduction_prepare(&mut self, first: GSSLabel, labels: &[GSSLabel], length: usize) {
+        let reduction = self.reduction.get_or_insert_with(|| SPPFReduction {
+            versions: Vec::new(),
+            stack: Vec::new(),
             pop_count: 0,
         });
+        reduction.stack.clear();
+        if length > 0 {
+            reduction
+                .stack
+                .extend(labels[..(length - 1)].iter().rev().copied());
+            reduction.stack.push(first);
+        }
+        reduction.pop_count = 0;
+        reduction.versions.truncate(1);
+        if let Some(version) = reduction.versions.first_mut() {
+            version.nodes.clear();
+            version.actions.clear();
+        } else {
+            reduction.versions.push(SPPFReductionVersion::new(length));
+        }
     }
 
     /// Adds the specified GSS label to the reduction cache with the given tree action
@@ -1064,11 +1153,37 @@ struct RNGLRShift {
     to: usize,
 }
 
+/// The storage for the GSS and the queues of a RNGLR parser, reused across parses
+pub(crate) struct RNGLRStorage {
+    /// The GSS
+    gss: GSS,
+    /// The buffer of paths in the GSS
+    paths: GSSPaths,
+    /// The reductions queue
+    reductions: VecDeque<RNGLRReduction>,
+    /// The shifts queue
+    shifts: VecDeque<RNGLRShift>,
+}
+
+impl RNGLRStorage {
+    /// Gets the number of items the GSS and the queues can hold without allocating
+    pub fn capacity(&self) -> usize {
+        self.gss.capacity()
+            + self.paths.paths.capacity()
+            + self.paths.labels.capacity()
+            + self.reductions.capacity()
+            + self.shifts.capacity()
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
 struct RNGLRParserData<'s, 'a> {
     /// The parser's automaton
-    automaton: RNGLRAutomaton,
+    automaton: &'a RNGLRAutomaton,
     /// The GSS for this parser
     gss: GSS,
+    /// The pooled buffer of paths for the reductions
+    paths: GSSPaths,
     /// The next token
     next_token: Option<TokenKernel>,
     /// The queue of reduction operations
@@ -1078,7 +1193,27 @@ struct RNGLRParserData<'s, 'a> {
     /// The grammar variables
     variables: &'a [Symbol<'s>],
     /// The semantic actions
-    actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+    actions: &'a mut dyn SemanticActions,
+    /// The number of reductions for each production, if recorded
+    rule_counts: Option<Vec<usize>>,
+    /// The statistics on the GSS, if recorded
+    gss_statistics: Option<GSSStatistics>,
+    /// The number of reductions performed so far
+    reductions_count: usize,
+    /// The maximum number of live stacks at an input position so far
+    peak_stacks: usize,
+    /// The state of the automaton to start parsing from
+    start_state: u32,
+    /// Whether to stop on the first error raised by a semantic action
+    stop_on_semantic_error: bool,
+    /// Whether a semantic action raised an error
+    semantic_failed: bool,
+    /// The maximum number of live stacks at an input position, if any, from the configuration of the input
+    fork_budget: Option<usize>,
+    /// Whether the maximum number of live stacks was exceeded
+    fork_budget_exceeded: bool,
+    /// Whether the unexpected token errors keep the stacks of states
+    capture_stacks: bool,
 }
 
 impl<'s, 'a> ContextProvider for RNGLRParserData<'s, 'a> {
@@ -1178,10 +1313,11 @@ impl<'s, 'a> ContextProvider for RNGLRParserData<'s, 'a> {
             let distance = distances[i];
             let production = productions[i];
             i += 1;
-            let paths = self.gss.get_paths(from, 1);
-            for path in &paths {
+            let mut paths = GSSPaths::default();
+            self.gss.get_paths(from, 1, &mut paths);
+            for path in paths.iter() {
                 let last_node = path.last_node;
-                let symbol_id = path.labels[0].symbol_id;
+                let symbol_id = paths.labels_of(path)[0].symbol_id;
                 let contexts = self
                     .automaton
                     .get_contexts(self.gss.get_represented_state(last_node));
@@ -1257,11 +1393,13 @@ impl<'s, 'a> ContextProvider for RNGLRParserData<'s, 'a> {
                     queue_vstack.push(virtual_stack);
                 } else {
                     // we reach the GSS
-                    let paths = self.gss.get_paths(
+                    let mut paths = GSSPaths::default();
+                    self.gss.get_paths(
                         gss_node,
                         production.reduction_length - queue_vstack[i].len(),
+                        &mut paths,
                     );
-                    for path in &paths {
+                    for path in paths.iter() {
                         // get the target GLR state
                         let next = self.get_next_by_var(
                             self.gss.get_represented_state(path.last_node),
@@ -1303,6 +1441,12 @@ impl<'s, 'a> RNGLRParserData<'s, 'a> {
         }
     }
 
+    /// Gets whether the parser must stop on an error raised by a semantic action,
+    /// or because the maximum number of live stacks was exceeded
+    fn is_stopped(&self) -> bool {
+        (self.stop_on_semantic_error && self.semantic_failed) || self.fork_budget_exceeded
+    }
+
     /// Checks whether the specified terminal is indeed expected for a reduction
     /// This check is required because in the case of a base LALR graph,
     /// some terminals expected for reduction in the automaton are coming from other paths.
@@ -1326,8 +1470,10 @@ impl<'s, 'a> RNGLRParserData<'s, 'a> {
                     continue;
                 }
                 let production = self.automaton.get_production(action.get_data() as usize);
-                let paths = self.gss.get_paths(gss_node, production.reduction_length);
-                for path in &paths {
+                let mut paths = GSSPaths::default();
+                self.gss
+                    .get_paths(gss_node, production.reduction_length, &mut paths);
+                for path in paths.iter() {
                     // get the target GLR state
                     let next = self.get_next_by_var(
                         self.gss.get_represented_state(path.last_node),
@@ -1383,11 +1529,13 @@ impl<'s, 'a> RNGLRParserData<'s, 'a> {
                     queue_vstack.push(virtual_stack);
                 } else {
                     // we reach the GSS
-                    let paths = self.gss.get_paths(
+                    let mut paths = GSSPaths::default();
+                    self.gss.get_paths(
                         gss_node,
                         production.reduction_length - queue_vstack[i].len(),
+                        &mut paths,
                     );
-                    for path in &paths {
+                    for path in paths.iter() {
                         // get the target GLR state
                         let next = self.get_next_by_var(
                             self.gss.get_represented_state(path.last_node),
@@ -1417,12 +1565,48 @@ impl<'s, 'a> RNGLRParserData<'s, 'a> {
         None
     }
 
+    /// Records the actions found on a new GSS node, more than one splitting the stack
+    fn record_split(&mut self, actions_count: usize) {
+        if let Some(statistics) = self.gss_statistics.as_mut() {
+            statistics.splits += actions_count.saturating_sub(1);
+        }
+    }
+
+    /// Records a stack joining an existing GSS node
+    fn record_merge(&mut self) {
+        if let Some(statistics) = self.gss_statistics.as_mut() {
+            statistics.merges += 1;
+        }
+    }
+
+    /// Records the number of live stacks, as the number of scheduled shifts
+    fn record_live_stacks(&mut self) {
+        let count = self.shifts.len();
+        if let Some(statistics) = self.gss_statistics.as_mut() {
+            statistics.max_live_stacks = statistics.max_live_stacks.max(count);
+        }
+    }
+
+    /// Records the number of live stacks at the current input position, checking the fork budget
+    fn record_stacks(&mut self) {
+        let count = self.gss.get_current_edges_count();
+        self.peak_stacks = self.peak_stacks.max(count);
+        if let Some(statistics) = self.gss_statistics.as_mut() {
+            statistics.peak_stacks = statistics.peak_stacks.max(count);
+        }
+        if self.fork_budget.is_some_and(|budget| count > budget) {
+            self.fork_budget_exceeded = true;
+        }
+    }
+
     /// Executes a shift operation
     fn parse_shift(&mut self, generation: usize, label: GSSLabel, shift: RNGLRShift) {
         let w = self.gss.find_node(generation, shift.to as u32);
         if let Some(w) = w {
             // A node for the target state is already in the GSS
             self.gss.create_edge(w, shift.from, label);
+            self.record_stacks();
+            self.record_merge();
             // Look for the new reductions at this state
             let count = self
                 .automaton
@@ -1447,10 +1631,12 @@ impl<'s, 'a> RNGLRParserData<'s, 'a> {
             // Create the new corresponding node in the GSS
             let w = self.gss.create_node(shift.to as u32);
             self.gss.create_edge(w, shift.from, label);
+            self.record_stacks();
             // Look for all the reductions and shifts at this state
             let count = self
                 .automaton
                 .get_actions_count(shift.to as u32, self.get_next_token_id());
+            self.record_split(count);
             for i in 0..count {
                 let action =
                     self.automaton
@@ -1499,28 +1685,40 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         lexer: &'l mut Lexer<'s, 't, 'a>,
         variables: &'a [Symbol<'s>],
         virtuals: &'a [Symbol<'s>],
-        automaton: RNGLRAutomaton,
+        automaton: &'a RNGLRAutomaton,
         ast: &'a mut AstImpl,
-        actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+        actions: &'a mut dyn SemanticActions,
     ) -> RNGLRParser<'s, 't, 'a, 'l> {
+        let config = lexer.get_data().repository.text.get_config();
         let mut parser = RNGLRParser {
             data: RNGLRParserData {
                 automaton,
                 gss: GSS::new(),
+                paths: GSSPaths::default(),
                 next_token: None,
                 reductions: VecDeque::new(),
                 shifts: VecDeque::new(),
                 variables,
                 actions,
+                rule_counts: None,
+                gss_statistics: None,
+                reductions_count: 0,
+                peak_stacks: 0,
+                start_state: 0,
+                stop_on_semantic_error: false,
+                semantic_failed: false,
+                fork_budget: config.fork_budget,
+                fork_budget_exceeded: false,
+                capture_stacks: config.capture_stacks,
             },
             builder: SPPFBuilder::new_ast(lexer, variables, virtuals, ast),
             nullables: alloc::vec![0xFFFF_FFFF ; variables.len()],
         };
-        RNGLRParser::build_nullables(
+        parser.data.semantic_failed = RNGLRParser::build_nullables(
             &mut parser.builder,
-            &mut parser.data.actions,
+            &mut *parser.data.actions,
             &mut parser.nullables,
-            &parser.data.automaton,
+            parser.data.automaton,
             parser.data.variables,
         );
         parser
@@ -1531,41 +1729,55 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         lexer: &'l mut Lexer<'s, 't, 'a>,
         variables: &'a [Symbol<'s>],
         virtuals: &'a [Symbol<'s>],
-        automaton: RNGLRAutomaton,
+        automaton: &'a RNGLRAutomaton,
         sppf: &'a mut SppfImpl,
-        actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+        actions: &'a mut dyn SemanticActions,
     ) -> RNGLRParser<'s, 't, 'a, 'l> {
+        let config = lexer.get_data().repository.text.get_config();
         let mut parser = RNGLRParser {
             data: RNGLRParserData {
                 automaton,
                 gss: GSS::new(),
+                paths: GSSPaths::default(),
                 next_token: None,
                 reductions: VecDeque::new(),
                 shifts: VecDeque::new(),
                 variables,
                 actions,
+                rule_counts: None,
+                gss_statistics: None,
+                reductions_count: 0,
+                peak_stacks: 0,
+                start_state: 0,
+                stop_on_semantic_error: false,
+                semantic_failed: false,
+                fork_budget: config.fork_budget,
+                fork_budget_exceeded: false,
+                capture_stacks: config.capture_stacks,
             },
             builder: SPPFBuilder::new_sppf(lexer, variables, virtuals, sppf),
             nullables: alloc::vec![0xFFFF_FFFF ; variables.len()],
         };
-        RNGLRParser::build_nullables(
+        parser.data.semantic_failed = RNGLRParser::build_nullables(
             &mut parser.builder,
-            &mut parser.data.actions,
+            &mut *parser.data.actions,
             &mut parser.nullables,
-            &parser.data.automaton,
+            parser.data.automaton,
             parser.data.variables,
         );
         parser
     }
 
     /// Builds the constant sub-trees of nullable variables
+    /// Returns whether a semantic action failed
     fn build_nullables(
         builder: &mut SPPFBuilder<'s, 't, 'a, 'l>,
-        actions: &mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+        actions: &mut dyn SemanticActions,
         nullables: &mut [usize],
         automaton: &RNGLRAutomaton,
         variables: &[Symbol],
-    ) {
+    ) -> bool {
+        let mut failed = false;
         // Get the dependency table
         let mut dependencies = RNGLRParser::build_nullables_dependencies(automaton, variables);
         // Solve and build
@@ -1582,17 +1794,18 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 let can_resolve = dependencies[i].is_empty()
                     || dependencies[i].iter().all(|&d| nullables[d] != 0xFFFF_FFFF);
                 if can_resolve {
-                    let path = GSSPath::new(0, 0, 0);
-                    nullables[i] = RNGLRParser::build_sppf(
+                    let (node, node_failed) = RNGLRParser::build_sppf(
                         builder,
                         actions,
                         nullables,
                         production.unwrap(),
                         EPSILON,
-                        &path,
+                        &[],
+                        None,
                         None,
-                    )
-                    .node_id();
+                    );
+                    nullables[i] = node.node_id();
+                    failed |= node_failed;
                     dependencies[i].clear();
                     resolved += 1;
                 } else {
@@ -1606,6 +1819,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 "Failed to initialize the parser, found a cycle in the nullable variables"
             );
         }
+        failed
     }
 
     /// Builds the dependency table between nullable variables
@@ -1648,18 +1862,50 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         result
     }
 
+    /// Counts the reductions of the ε-productions inserted as nullable sub-trees by a production
+    /// These sub-trees are built once for the parser and are never reduced while parsing.
+    fn count_nullable_reductions(
+        automaton: &RNGLRAutomaton,
+        counts: &mut [usize],
+        production: usize,
+        count: usize,
+    ) {
+        let bytecode = &automaton.get_production(production).bytecode;
+        let mut i = 0;
+        while i < bytecode.len() {
+            let op_code = bytecode[i];
+            i += 1;
+            match get_op_code_base(op_code) {
+                LR_OP_CODE_BASE_SEMANTIC_ACTION | LR_OP_CODE_BASE_ADD_VIRTUAL => {
+                    i += 1;
+                }
+                LR_OP_CODE_BASE_ADD_NULLABLE_VARIABLE => {
+                    let nullable = automaton.nullables[bytecode[i] as usize] as usize;
+                    i += 1;
+                    counts[nullable] += count;
+                    RNGLRParser::count_nullable_reductions(automaton, counts, nullable, count);
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Builds the SPPF
+    /// Returns the reduced node and whether a semantic action failed
+    #[allow(clippy::too_many_arguments)]
     fn build_sppf(
         builder: &mut SPPFBuilder<'s, 't, 'a, 'l>,
-        actions: &mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
+        actions: &mut dyn SemanticActions,
         nullables: &[usize],
         production: &LRProduction,
         first: GSSLabel,
-        path: &GSSPath,
+        labels: &[GSSLabel],
         target: Option<SppfImplNodeRef>,
-    ) -> SppfImplNodeRef {
+        lookahead: Option<usize>,
+    ) -> (SppfImplNodeRef, bool) {
+        let mut failed = false;
         let variable = builder.variables[production.head];
-        builder.reduction_prepare(first, path, production.reduction_length);
+        builder.reduction_prepare(first, labels, production.reduction_length);
         let mut i = 0;
         while i < production.bytecode.len() {
             let op_code = production.bytecode[i];
@@ -1668,7 +1914,20 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 LR_OP_CODE_BASE_SEMANTIC_ACTION => {
                     let index = production.bytecode[i] as usize;
                     i += 1;
-                    actions(index, variable, builder);
+                    if let Err(error) = actions.execute(index, variable, builder) {
+                        let error = build_semantic_error(
+                            error,
+                            &*builder,
+                            &builder.lexer.get_data().repository,
+                            lookahead,
+                        );
+                        builder
+                            .lexer
+                            .get_data_mut()
+                            .errors
+                            .push_error_semantic(error);
+                        failed = true;
+                    }
                 }
                 LR_OP_CODE_BASE_ADD_VIRTUAL => {
                     let index = production.bytecode[i] as usize;
@@ -1688,7 +1947,10 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 }
             }
         }
-        builder.reduce(production.head, production.head_action, target)
+        (
+            builder.reduce(production.head, production.head_action, target),
+            failed,
+        )
     }
 
     /// Gets the next token in the kernel
@@ -1702,7 +1964,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
 
     /// Executes the reduction operations from the given GSS generation
     fn parse_reductions(&mut self, generation: usize) {
-        while !self.data.reductions.is_empty() {
+        while !self.data.reductions.is_empty() && !self.data.is_stopped() {
             let reduction = self.data.reductions.pop_front().unwrap();
             self.parse_reduction(generation, reduction);
         }
@@ -1710,28 +1972,40 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
 
     /// Executes a reduction operation for all found path
     fn parse_reduction(&mut self, generation: usize, reduction: RNGLRReduction) {
-        let paths = {
-            let production = self.data.automaton.get_production(reduction.production);
-            if production.reduction_length == 0 {
-                self.data.gss.get_paths(reduction.node, 0)
-            } else {
-                // The given GSS node is the second on the path, so start from it with length - 1
-                self.data
-                    .gss
-                    .get_paths(reduction.node, production.reduction_length - 1)
-            }
-        };
-        for path in &paths {
-            self.parse_reduction_path(generation, reduction, path);
+        // The given GSS node is the second on the path, so start from it with length - 1
+        let length = self
+            .data
+            .automaton
+            .get_production(reduction.production)
+            .reduction_length
+            .saturating_sub(1);
+        // borrow the pooled buffer of paths for the time of the reduction
+        let mut paths = core::mem::take(&mut self.data.paths);
+        self.data.gss.get_paths(reduction.node, length, &mut paths);
+        self.data.reductions_count += paths.len();
+        if let Some(counts) = self.data.rule_counts.as_mut() {
+            counts[reduction.production] += paths.len();
+            RNGLRParser::count_nullable_reductions(
+                self.data.automaton,
+                counts,
+                reduction.production,
+                paths.len(),
+            );
         }
+        for path in paths.iter() {
+            self.parse_reduction_path(generation, reduction, path.last_node, paths.labels_of(path));
+        }
+        self.data.paths = paths;
     }
 
     /// Executes a reduction operation for a given path
+    #[allow(clippy::too_many_lines)]
     fn parse_reduction_path(
         &mut self,
         generation: usize,
         reduction: RNGLRReduction,
-        path: &GSSPath,
+        last_node: usize,
+        labels: &[GSSLabel],
     ) {
         let production = self.data.automaton.get_production(reduction.production);
         // Get the rule's head
@@ -1739,33 +2013,32 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         // Get the target state by transition on the rule's head
         let to = self
             .data
-            .get_next_by_var(self.data.gss.get_represented_state(path.last_node), head.id)
+            .get_next_by_var(self.data.gss.get_represented_state(last_node), head.id)
             .unwrap();
         // Find a node for the target state in the GSS
         let w = self.data.gss.find_node(generation, to);
         // Do we have to create a GSS edge?
-        let previous_edge_label = w.and_then(|w| {
-            self.data
-                .gss
-                .get_edge(generation, w, path.last_node)
-                .map(|edge| edge.label)
-        });
+        let previous_edge_label =
+            w.and_then(|w| self.data.gss.get_edge(w, last_node).map(|edge| edge.label));
         let sppf_node =
             if self.data.automaton.nullables[production.head] as usize == reduction.production {
                 // nullable production, use the nullable node
                 SppfImplNodeRef::new_usize(self.nullables[production.head])
             } else {
-                RNGLRParser::build_sppf(
+                let (node, failed) = RNGLRParser::build_sppf(
                     &mut self.builder,
-                    &mut self.data.actions,
+                    &mut *self.data.actions,
                     &self.nullables,
                     production,
                     reduction.first,
-                    path,
+                    labels,
                     previous_edge_label
                         .as_ref()
                         .map(|previous| previous.sppf_node),
-                )
+                    self.data.next_token.map(|token| token.index as usize),
+                );
+                self.data.semantic_failed |= failed;
+                node
             };
         let label = previous_edge_label.unwrap_or(GSSLabel {
             sppf_node,
@@ -1776,7 +2049,10 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
             // A node for the target state is already in the GSS
             if previous_edge_label.is_none() {
                 // But the new edge does not exist
-                self.data.gss.create_edge(w, path.last_node, label);
+                self.data.gss.create_edge(w, last_node, label);
+                if let Some(statistics) = self.data.gss_statistics.as_mut() {
+                    statistics.merges += 1;
+                }
                 // Look for the new reductions at this state
                 if production.reduction_length != 0 {
                     let count = self
@@ -1796,7 +2072,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                             // length 0 reduction are not considered here because they already exist at this point
                             if new_production.reduction_length > 0 {
                                 self.data.reductions.push_back(RNGLRReduction {
-                                    node: path.last_node,
+                                    node: last_node,
                                     production: action.get_data() as usize,
                                     first: label,
                                 });
@@ -1808,12 +2084,13 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         } else {
             // Create the new corresponding node in the GSS
             let w = self.data.gss.create_node(to);
-            self.data.gss.create_edge(w, path.last_node, label);
+            self.data.gss.create_edge(w, last_node, label);
             // Look for all the reductions and shifts at this state
             let count = self
                 .data
                 .automaton
                 .get_actions_count(to, self.data.get_next_token_id());
+            self.data.record_split(count);
             for i in 0..count {
                 let action = self
                     .data
@@ -1837,7 +2114,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                         });
                     } else {
                         self.data.reductions.push_back(RNGLRReduction {
-                            node: path.last_node,
+                            node: last_node,
                             production: action.get_data() as usize,
                             first: label,
                         });
@@ -1845,6 +2122,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 }
             }
         }
+        self.data.record_stacks();
     }
 
     /// Executes the shift operations for the given token
@@ -1867,6 +2145,29 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
         new_gen
     }
 
+    /// Reports the error for the exceeded maximum number of live stacks, if it was
+    fn check_fork_budget(&mut self) {
+        let Some(budget) = self.data.fork_budget else {
+            return;
+        };
+        if !self.data.fork_budget_exceeded {
+            return;
+        }
+        let repository = &self.builder.lexer.get_data().repository;
+        let position = match self.data.next_token {
+            Some(kernel) => repository
+                .get_token(kernel.index as usize)
+                .get_position()
+                .unwrap_or_else(|| repository.text.get_position_at(repository.text.len())),
+            None => repository.text.get_position_at(repository.text.len()),
+        };
+        self.builder
+            .lexer
+            .get_data_mut()
+            .errors
+            .push_error_fork_budget_exceeded(ParseErrorForkBudgetExceeded::new(position, budget));
+    }
+
     /// Builds the unexpected token error
     fn build_error(&self, kernel: TokenKernel, stem: usize) -> ParseErrorUnexpectedToken<'s> {
         let token = self
@@ -1877,6 +2178,7 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
             .get_token(kernel.index as usize);
         let mut my_states = Vec::new();
         let mut my_expected = Vec::new();
+        let mut my_stacks = Vec::new();
         let generation_data = self.data.gss.get_current_generation();
         for i in 0..generation_data.count {
             let state = self
@@ -1884,6 +2186,9 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
                 .gss
                 .get_represented_state(generation_data.start + i);
             my_states.push(state);
+            if self.data.capture_stacks {
+                my_stacks.push(self.data.gss.get_stack_states(generation_data.start + i));
+            }
             let expected_on_head = self
                 .data
                 .automaton
@@ -1916,13 +2221,20 @@ impl<'s, 't, 'a, 'l> RNGLRParser<'s, 't, 'a, 'l> {
             my_states,
             my_expected,
         )
+        .with_aliases(&self.builder.lexer.get_data().aliases)
+        .with_stacks(my_stacks)
     }
 }
 
 impl<'s, 't, 'a, 'l> Parser for RNGLRParser<'s, 't, 'a, 'l> {
     fn parse(&mut self) {
+        if self.data.is_stopped() {
+            // stopped on an error raised by a semantic action for a nullable variable
+            return;
+        }
         let mut generation = self.data.gss.create_generation();
-        let state0 = self.data.gss.create_node(0);
+        let start_state = self.data.start_state;
+        let state0 = self.data.gss.create_node(start_state);
         self.get_next_token();
 
         // bootstrap the shifts and reductions queues
@@ -1930,12 +2242,13 @@ impl<'s, 't, 'a, 'l> Parser for RNGLRParser<'s, 't, 'a, 'l> {
             let count = self
                 .data
                 .automaton
-                .get_actions_count(0, self.data.get_next_token_id());
+                .get_actions_count(start_state, self.data.get_next_token_id());
+            self.data.record_split(count);
             for i in 0..count {
-                let action = self
-                    .data
-                    .automaton
-                    .get_action(0, self.data.get_next_token_id(), i);
+                let action =
+                    self.data
+                        .automaton
+                        .get_action(start_state, self.data.get_next_token_id(), i);
                 if action.get_code() == LR_ACTION_CODE_SHIFT {
                     self.data.shifts.push_back(RNGLRShift {
                         from: state0,
@@ -1957,8 +2270,17 @@ impl<'s, 't, 'a, 'l> Parser for RNGLRParser<'s, 't, 'a, 'l> {
             let stem = self.data.gss.get_generation(generation).count;
             // apply all reduction actions
             self.parse_reductions(generation);
+            if self.data.is_stopped() {
+                // stopped on an error raised by a semantic action or on the fork budget
+                self.check_fork_budget();
+                return;
+            }
             // no scheduled shift actions?
             if self.data.shifts.is_empty() {
+                if self.builder.lexer.get_data().has_exceeded_max_tokens {
+                    // the input was cut at the maximum number of tokens, the error is already recorded
+                    return;
+                }
                 // this is an error
                 let error = self.build_error(self.data.next_token.unwrap(), stem);
                 self.builder
@@ -1973,7 +2295,13 @@ impl<'s, 't, 'a, 'l> Parser for RNGLRParser<'s, 't, 'a, 'l> {
             let old_token = self.data.next_token.unwrap();
             self.get_next_token();
             // apply the scheduled shift actions
+            self.data.record_live_stacks();
             generation = self.parse_shifts(old_token);
+            if self.data.is_stopped() {
+                // stopped on the fork budget
+                self.check_fork_budget();
+                return;
+            }
         }
 
         let generation_data = self.data.gss.get_generation(generation);
@@ -1981,11 +2309,68 @@ impl<'s, 't, 'a, 'l> Parser for RNGLRParser<'s, 't, 'a, 'l> {
             let state = self.data.gss.get_represented_state(i);
             if self.data.automaton.is_accepting_state(state) {
                 // Has reduction _Axiom_ -> axiom $ . on ε
-                let paths = self.data.gss.get_paths(i, 2);
-                let root = paths[0].labels[1];
+                let mut paths = GSSPaths::default();
+                self.data.gss.get_paths(i, 2, &mut paths);
+                let root = paths.labels_of(&paths.paths[0])[1];
                 self.builder.commit_root(root.sppf_node);
             }
         }
         // At end of input but was still waiting for tokens
     }
+
+    fn record_rule_counts(&mut self) {
+        self.data.rule_counts = Some(alloc::vec![0; self.data.automaton.get_productions_count()]);
+    }
+
+    fn take_rule_counts(&mut self) -> Option<Vec<usize>> {
+        self.data.rule_counts.take()
+    }
+
+    fn record_gss_statistics(&mut self) {
+        self.data.gss_statistics = Some(GSSStatistics::default());
+    }
+
+    fn take_gss_statistics(&mut self) -> Option<GSSStatistics> {
+        self.data.gss_statistics.take()
+    }
+
+    fn get_statistics(&self) -> ParseStatistics {
+        ParseStatistics {
+            reductions: self.data.reductions_count,
+            gss_peak_stacks: Some(self.data.peak_stacks),
+            lexing_time: self.builder.lexer.get_data().lexing_time,
+            ..ParseStatistics::default()
+        }
+    }
+
+    fn set_start_state(&mut self, state: u32) {
+        self.data.start_state = state;
+    }
+
+    fn set_stop_on_semantic_error(&mut self, stop: bool) {
+        self.data.stop_on_semantic_error = stop;
+    }
+
+    fn use_storage(&mut self, storage: &mut ParserStorage) {
+        let Some(mut storage) = storage.rnglr.take() else {
+            return;
+        };
+        storage.gss.clear();
+        storage.paths.reset(0);
+        storage.reductions.clear();
+        storage.shifts.clear();
+        self.data.gss = storage.gss;
+        self.data.paths = storage.paths;
+        self.data.reductions = storage.reductions;
+        self.data.shifts = storage.shifts;
+    }
+
+    fn release_storage(&mut self, storage: &mut ParserStorage) {
+        storage.rnglr = Some(RNGLRStorage {
+            gss: core::mem::replace(&mut self.data.gss, GSS::new()),
+            paths: core::mem::take(&mut self.data.paths),
+            reductions: core::mem::take(&mut self.data.reductions),
+            shifts: core::mem::take(&mut self.data.shifts),
+        });
+    }
 }
@@ -17,15 +17,50 @@
 
 //! Module for the definition of a parse result
 
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::time::Duration;
+
+#[cfg(feature = "serde")]
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 use crate::ast::{Ast, AstImpl};
-use crate::errors::ParseErrors;
+use crate::errors::{ParseError, ParseErrors};
+use crate::parsers::rnglr::GSSStatistics;
+use crate::parsers::ParserStorage;
 use crate::sppf::{Sppf, SppfImpl};
 use crate::symbols::Symbol;
-use crate::text::Text;
+use crate::text::{ParseConfig, Text};
 use crate::tokens::{TokenRepository, TokenRepositoryImpl};
 
+/// Statistics on a parse
+/// The counts are computed from the content of the result,
+/// the others are measured by the parser while parsing.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ParseStatistics {
+    /// The number of tokens matched by the lexer, including the end of input but not the separators
+    pub tokens: usize,
+    /// The number of lexical errors, including the decoding errors of the input
+    pub lexical_errors: usize,
+    /// The number of syntactic errors
+    pub syntax_errors: usize,
+    /// The number of nodes in the produced tree, 0 on failure
+    pub nodes: usize,
+    /// The number of reductions performed by the parser
+    pub reductions: usize,
+    /// The maximum number of live stacks at an input position, only for RNGLR parsers
+    pub gss_peak_stacks: Option<usize>,
+    /// The size of the input in bytes
+    pub input_bytes: usize,
+    /// The time spent in the lexer, if measured
+    /// Timings are only measured with the `std` feature, when requested from the lexer with `record_lexing_time`.
+    /// Only the in-memory parsers of the SDK request them (`parse_with_timings`), generated parsers never do.
+    pub lexing_time: Option<Duration>,
+    /// The time spent parsing, excluding the time spent in the lexer, if measured
+    /// As for the lexing time, only the in-memory parsers of the SDK measure it.
+    pub parsing_time: Option<Duration>,
+}
+
 /// Represents the output of a parser
 pub struct ParseResult<'s, 't, 'a, T> {
     /// The table of grammar terminals
@@ -42,6 +77,14 @@ pub struct ParseResult<'s, 't, 'a, T> {
     pub tokens: TokenRepositoryImpl,
     /// The produced AST
     parse_tree: T,
+    /// The number of reductions for each production of the parser, if recorded
+    rule_counts: Option<Vec<usize>>,
+    /// The statistics on the GSS of the parser, if recorded
+    gss_statistics: Option<GSSStatistics>,
+    /// The statistics measured by the parser
+    statistics: ParseStatistics,
+    /// The storage for the stacks of the parser, reused across parses
+    parser_storage: ParserStorage,
 }
 
 impl<'s, 't, 'a, T: Default> ParseResult<'s, 't, 'a, T> {
@@ -53,14 +96,33 @@ impl<'s, 't, 'a, T: Default> ParseResult<'s, 't, 'a, T> {
         virtuals: &'a [Symbol<'s>],
         text: Text<'t>,
     ) -> ParseResult<'s, 't, 'a, T> {
+        let errors = ParseErrors::from_decoding_errors(&text);
         ParseResult {
             terminals,
             variables,
             virtuals,
             text,
-            errors: ParseErrors::default(),
+            errors,
             tokens: TokenRepositoryImpl::default(),
             parse_tree: T::default(),
+            rule_counts: None,
+            gss_statistics: None,
+            statistics: ParseStatistics::default(),
+            parser_storage: ParserStorage::default(),
+        }
+    }
+
+    /// Converts this result into a `Result`, failing on any error
+    /// The AST of a successful result can then be obtained with `get_ast`.
+    ///
+    /// # Errors
+    ///
+    /// Return the errors found in the input, if any
+    pub fn into_result(self) -> Result<Self, ParseErrors<'s>> {
+        if self.errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(self.errors)
         }
     }
 
@@ -69,6 +131,73 @@ impl<'s, 't, 'a, T: Default> ParseResult<'s, 't, 'a, T> {
     pub fn get_tokens(&self) -> TokenRepository {
         TokenRepository::new(self.terminals, &self.text, &self.tokens)
     }
+
+    /// Gets the number of reductions for each production of the parser, if they were recorded.
+    /// Productions are identified by their index in the parser's automaton.
+    /// For RNGLR parsers, the trailing nullable variables of a right-nullable reduction
+    /// are not counted on their own.
+    #[must_use]
+    pub fn rule_counts(&self) -> Option<&[usize]> {
+        self.rule_counts.as_deref()
+    }
+
+    /// Sets the number of reductions for each production of the parser
+    pub fn set_rule_counts(&mut self, rule_counts: Option<Vec<usize>>) {
+        self.rule_counts = rule_counts;
+    }
+
+    /// Gets the statistics on the splits and merges of the GSS, if they were recorded.
+    /// They are only available for RNGLR parsers.
+    #[must_use]
+    pub fn gss_statistics(&self) -> Option<GSSStatistics> {
+        self.gss_statistics
+    }
+
+    /// Sets the statistics on the GSS of the parser
+    pub fn set_gss_statistics(&mut self, gss_statistics: Option<GSSStatistics>) {
+        self.gss_statistics = gss_statistics;
+    }
+
+    /// Sets the statistics measured by the parser
+    /// The counts computed from the content of the result are ignored.
+    pub fn set_statistics(&mut self, statistics: ParseStatistics) {
+        self.statistics = statistics;
+    }
+
+    /// Takes the storage for the stacks of the parser
+    /// For a result obtained from an arena, this is the storage of the previous parses.
+    pub fn take_parser_storage(&mut self) -> ParserStorage {
+        core::mem::take(&mut self.parser_storage)
+    }
+
+    /// Sets the storage for the stacks of the parser, given back to the arena if any
+    pub fn set_parser_storage(&mut self, storage: ParserStorage) {
+        self.parser_storage = storage;
+    }
+
+    /// Gets the statistics on this result, given the number of nodes in its tree
+    fn get_statistics(&self, nodes: usize) -> ParseStatistics {
+        let lexical_errors = self
+            .errors
+            .errors
+            .iter()
+            .filter(|error| error.is_lexical())
+            .count();
+        let syntax_errors = self
+            .errors
+            .errors
+            .iter()
+            .filter(|error| error.is_syntactic())
+            .count();
+        ParseStatistics {
+            tokens: self.get_tokens().get_tokens_count(),
+            lexical_errors,
+            syntax_errors,
+            nodes,
+            input_bytes: self.text.len(),
+            ..self.statistics
+        }
+    }
 }
 
 impl<'s, 't, 'a> ParseResult<'s, 't, 'a, AstImpl> {
@@ -78,6 +207,17 @@ impl<'s, 't, 'a> ParseResult<'s, 't, 'a, AstImpl> {
         self.parse_tree.has_root()
     }
 
+    /// Gets the statistics on this parse
+    #[must_use]
+    pub fn statistics(&self) -> ParseStatistics {
+        let nodes = if self.is_success() {
+            self.get_ast().iter_preorder().count()
+        } else {
+            0
+        };
+        self.get_statistics(nodes)
+    }
+
     /// Gets the resulting AST
     #[must_use]
     pub fn get_ast<'x>(&'x self) -> Ast<'s, 't, 'x> {
@@ -113,6 +253,17 @@ impl<'s, 't, 'a> ParseResult<'s, 't, 'a, SppfImpl> {
         self.parse_tree.has_root()
     }
 
+    /// Gets the statistics on this parse
+    #[must_use]
+    pub fn statistics(&self) -> ParseStatistics {
+        let nodes = if self.is_success() {
+            self.parse_tree.nodes.len()
+        } else {
+            0
+        };
+        self.get_statistics(nodes)
+    }
+
     /// Gets the resulting AST
     #[must_use]
     pub fn get_ast<'x>(&'x self) -> Sppf<'s, 't, 'x> {
@@ -141,6 +292,7 @@ impl<'s, 't, 'a> ParseResult<'s, 't, 'a, SppfImpl> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for ParseResult<'s, 't, 'a, AstImpl> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -159,6 +311,7 @@ impl<'s, 't, 'a> Serialize for ParseResult<'s, 't, 'a, AstImpl> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for ParseResult<'s, 't, 'a, SppfImpl> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -182,3 +335,144 @@ pub type ParseResultAst = ParseResult<'static, 'static, 'static, AstImpl>;
 
 /// A parse result with a SPPF
 pub type ParseResultSppf = ParseResult<'static, 'static, 'static, SppfImpl>;
+
+/// Represents the storage of a parse tree that can be reused across parses
+pub trait ReusableTree: Default {
+    /// Removes all the nodes, keeping the allocated storage for reuse
+    fn clear(&mut self);
+}
+
+impl ReusableTree for AstImpl {
+    fn clear(&mut self) {
+        AstImpl::clear(self);
+    }
+}
+
+impl ReusableTree for SppfImpl {
+    fn clear(&mut self) {
+        SppfImpl::clear(self);
+    }
+}
+
+/// The storage for the data produced by parsers, reused across successive parses
+/// Once its capacity is large enough, the storage for the lines of the input text,
+/// the matched tokens, the errors, the parse tree and the stacks of the parser
+/// is no longer allocated for each parse.
+#[derive(Debug, Default)]
+pub struct ParseArena<'s, T> {
+    /// The storage for the starting indices of the lines in the input text
+    lines: Vec<usize>,
+    /// The storage for the errors
+    errors: Vec<ParseError<'s>>,
+    /// The storage for the matched tokens
+    tokens: Option<TokenRepositoryImpl>,
+    /// The storage for the parse tree
+    parse_tree: Option<T>,
+    /// The storage for the stacks of the parsers
+    parser_storage: ParserStorage,
+    /// The configuration for the computation of positions in the inputs
+    config: ParseConfig,
+}
+
+impl<'s, T: ReusableTree> ParseArena<'s, T> {
+    /// Creates a new empty arena
+    #[must_use]
+    pub fn new() -> ParseArena<'s, T> {
+        ParseArena {
+            lines: Vec::new(),
+            errors: Vec::new(),
+            tokens: None,
+            parse_tree: None,
+            parser_storage: ParserStorage::default(),
+            config: ParseConfig::default(),
+        }
+    }
+
+    /// Sets the configuration for the computation of positions in the inputs parsed with this arena
+    #[must_use]
+    pub fn with_config(mut self, config: ParseConfig) -> ParseArena<'s, T> {
+        self.config = config;
+        self
+    }
+
+    /// Gets the number of items the stacks of the parsers can hold without allocating
+    #[must_use]
+    pub fn parser_capacity(&self) -> usize {
+        self.parser_storage.capacity()
+    }
+
+    /// Creates a parse result for an input, using the storage of this arena
+    /// The storage is given back to the arena when the result is dropped.
+    pub fn new_result<'r, 't, 'a>(
+        &'r mut self,
+        terminals: &'a [Symbol<'s>],
+        variables: &'a [Symbol<'s>],
+        virtuals: &'a [Symbol<'s>],
+        input: &'t str,
+    ) -> ArenaParseResult<'r, 's, 't, 'a, T> {
+        let text = Text::from_str_reusing(input, core::mem::take(&mut self.lines))
+            .with_config(self.config);
+        let mut errors = core::mem::take(&mut self.errors);
+        errors.clear();
+        let mut tokens = self.tokens.take().unwrap_or_default();
+        tokens.clear();
+        let mut parse_tree = self.parse_tree.take().unwrap_or_default();
+        parse_tree.clear();
+        let result = ParseResult {
+            terminals,
+            variables,
+            virtuals,
+            text,
+            errors: ParseErrors { errors },
+            tokens,
+            parse_tree,
+            rule_counts: None,
+            gss_statistics: None,
+            statistics: ParseStatistics::default(),
+            parser_storage: core::mem::take(&mut self.parser_storage),
+        };
+        ArenaParseResult {
+            arena: self,
+            result: Some(result),
+        }
+    }
+
+    /// Takes back the storage of a parse result
+    fn recycle(&mut self, result: ParseResult<'s, '_, '_, T>) {
+        self.lines = result.text.into_lines();
+        self.errors = result.errors.errors;
+        self.tokens = Some(result.tokens);
+        self.parse_tree = Some(result.parse_tree);
+        self.parser_storage = result.parser_storage;
+    }
+}
+
+/// A parse result using the storage of an arena, given back to the arena when dropped
+pub struct ArenaParseResult<'r, 's, 't, 'a, T: ReusableTree> {
+    /// The arena that provided the storage
+    arena: &'r mut ParseArena<'s, T>,
+    /// The parse result, until it is dropped
+    result: Option<ParseResult<'s, 't, 'a, T>>,
+}
+
+impl<'s, 't, 'a, T: ReusableTree> Deref for ArenaParseResult<'_, 's, 't, 'a, T> {
+    type Target = ParseResult<'s, 't, 'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.result.as_ref().unwrap()
+    }
+}
+
+impl<T: ReusableTree> DerefMut for ArenaParseResult<'_, '_, '_, '_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.result.as_mut().unwrap()
+    }
+}
+
+impl<T: ReusableTree> Drop for ArenaParseResult<'_, '_, '_, '_, T> {
+    fn drop(&mut self) {
+        if let Some(result) = self.result.take() {
+            self.arena.recycle(result);
+        }
+    }
+}
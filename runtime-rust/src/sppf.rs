@@ -22,14 +22,14 @@ use core::fmt::{Display, Error, Formatter};
 use core::iter::FusedIterator;
 use core::ops::Index;
 
-use serde::ser::SerializeStruct;
-use serde::{Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 use crate::ast::{TableElemRef, TableType};
 use crate::parsers::TreeAction;
 use crate::symbols::{SemanticElementTrait, Symbol};
 use crate::text::{TextContext, TextPosition, TextSpan};
-use crate::tokens::{Token, TokenRepository};
+use crate::tokens::{Token, TokenRepository, Trivia};
 
 /// Represents a reference to a Shared-Packed Parse Forest node in a specific version
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
@@ -913,6 +913,12 @@ pub struct SppfImpl {
 }
 
 impl SppfImpl {
+    /// Removes all the nodes, keeping the allocated storage for reuse
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+    }
+
     /// Stores the root of this tree
     pub fn store_root(&mut self, root: SppfImplNodeRef) {
         self.root = Some(root.node_id());
@@ -1111,7 +1117,7 @@ impl<'s, 't, 'a> Sppf<'s, 't, 'a> {
     /// a token label that contains the specified index in the input text
     #[must_use]
     pub fn find_node_at_position(&'a self, position: TextPosition) -> Option<SppfNode<'s, 't, 'a>> {
-        let index = self.tokens.text.get_line_index(position.line) + position.column - 1;
+        let index = self.tokens.text.get_index_at(position);
         self.tokens
             .find_token_at(index)
             .and_then(|token| self.find_node_for(&token))
@@ -1353,6 +1359,7 @@ impl<'s, 't, 'a> SppfNode<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for SppfNode<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1401,6 +1408,7 @@ impl<'s, 't, 'a> SppfNodeVersions<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for SppfNodeVersions<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1612,6 +1620,14 @@ impl<'s, 't, 'a> SemanticElementTrait<'s, 'a> for SppfNodeVersion<'s, 't, 'a> {
             _ => None,
         }
     }
+    /// Gets the separator tokens that precede this element in the input
+    fn leading_trivia(&self) -> Vec<Trivia<'s, 'a>> {
+        let label = self.version.label;
+        match label.table_type() {
+            TableType::Token => self.sppf.tokens.get_trivia_for(label.index()),
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl<'s, 't, 'a> Display for SppfNodeVersion<'s, 't, 'a> {
@@ -1640,6 +1656,7 @@ impl<'s, 't, 'a> Display for SppfNodeVersion<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for SppfNodeVersion<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1698,6 +1715,7 @@ impl<'s, 't, 'a> IntoIterator for SppfNodeChildren<'s, 't, 'a> {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'s, 't, 'a> Serialize for SppfNodeChildren<'s, 't, 'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
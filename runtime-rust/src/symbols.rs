@@ -17,12 +17,15 @@
 
 //! Module for the definition of grammar symbols
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt::{Display, Error, Formatter};
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use crate::text::{TextContext, TextPosition, TextSpan};
-use crate::tokens::Token;
+use crate::tokens::{Token, Trivia};
 
 /// The possible types of symbol
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -43,7 +46,8 @@ pub const SID_EPSILON: u32 = 1;
 pub const SID_DOLLAR: u32 = 2;
 
 /// Represents a grammar symbol (terminal, variable or virtual)
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Symbol<'a> {
     /// The symbol's unique identifier
     pub id: u32,
@@ -58,6 +62,31 @@ impl<'a> Display for Symbol<'a> {
     }
 }
 
+/// Represents a user-defined annotation attached to a grammar symbol, e.g. `@category("operator")`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SymbolAnnotation<'a> {
+    /// The identifier of the annotated symbol
+    pub symbol_id: u32,
+    /// The annotation's key
+    pub key: &'a str,
+    /// The annotation's value
+    pub value: &'a str,
+}
+
+/// Gets the value of the annotation with the given key on a symbol, if any
+#[must_use]
+pub fn get_metadata<'a>(
+    annotations: &[SymbolAnnotation<'a>],
+    symbol_id: u32,
+    key: &str,
+) -> Option<&'a str> {
+    annotations
+        .iter()
+        .find(|annotation| annotation.symbol_id == symbol_id && annotation.key == key)
+        .map(|annotation| annotation.value)
+}
+
 /// A trait for a parsing element
 pub trait SemanticElementTrait<'s, 'a> {
     /// Gets the position in the input text of this element
@@ -79,6 +108,13 @@ pub trait SemanticElementTrait<'s, 'a> {
     /// Gets the value of this element, if any
     #[must_use]
     fn get_value(&self) -> Option<&'a str>;
+
+    /// Gets the separator tokens (whitespace, comments) that precede this element in the input
+    /// They are only retained when the lexer is configured to do so, otherwise this is empty.
+    #[must_use]
+    fn leading_trivia(&self) -> Vec<Trivia<'s, 'a>> {
+        Vec::new()
+    }
 }
 
 /// Represents an element of parsing data
@@ -138,6 +174,15 @@ impl<'s, 't, 'a> SemanticElementTrait<'s, 'a> for SemanticElement<'s, 't, 'a> {
             SemanticElement::Virtual(_symbol) => None,
         }
     }
+
+    fn leading_trivia(&self) -> Vec<Trivia<'s, 'a>> {
+        match self {
+            SemanticElement::Token(token) => token.leading_trivia(),
+            SemanticElement::Terminal(_symbol) => Vec::new(),
+            SemanticElement::Variable(_symbol) => Vec::new(),
+            SemanticElement::Virtual(_symbol) => Vec::new(),
+        }
+    }
 }
 
 impl<'s, 't, 'a> SemanticElement<'s, 't, 'a> {
@@ -151,6 +196,36 @@ impl<'s, 't, 'a> SemanticElement<'s, 't, 'a> {
             SemanticElement::Virtual(_symbol) => SymbolType::Virtual,
         }
     }
+
+    /// Gets an adapter for displaying this element, e.g. in error messages
+    /// A token is rendered with its value and position, a symbol with its name.
+    #[must_use]
+    pub fn to_display(&self) -> SemanticElementDisplay<'_, 's, 't, 'a> {
+        SemanticElementDisplay { element: self }
+    }
+}
+
+/// Adapter for displaying a semantic element
+pub struct SemanticElementDisplay<'e, 's, 't, 'a> {
+    /// The displayed element
+    element: &'e SemanticElement<'s, 't, 'a>,
+}
+
+impl Display for SemanticElementDisplay<'_, '_, '_, '_> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self.element {
+            SemanticElement::Token(token) => {
+                write!(f, "Token({:?}", token.get_value().unwrap_or_default())?;
+                if let Some(position) = token.get_position() {
+                    write!(f, " at {}:{}", position.line, position.column)?;
+                }
+                write!(f, ")")
+            }
+            SemanticElement::Terminal(symbol) => write!(f, "Terminal('{}')", symbol.name),
+            SemanticElement::Variable(symbol) => write!(f, "Variable({})", symbol.name),
+            SemanticElement::Virtual(symbol) => write!(f, "Virtual({})", symbol.name),
+        }
+    }
 }
 
 /// Represents the semantic body of a rule being reduced
@@ -162,7 +237,109 @@ pub trait SemanticBody {
     /// Gets the length of this body
     #[must_use]
     fn length(&self) -> usize;
+
+    /// Gets the span in the input text covering all the tokens in this body
+    /// Returns `None` when there is no token or when the tokens are not contiguous
+    #[must_use]
+    fn merged_span(&self) -> Option<TextSpan> {
+        let mut result: Option<TextSpan> = None;
+        for index in 0..self.length() {
+            if let Some(span) = self.get_element_at(index).get_span() {
+                result = match result {
+                    None => Some(span),
+                    Some(current) => Some(TextSpan::merge(current, span)?),
+                };
+            }
+        }
+        result
+    }
 }
 
 /// Delegate for a user-defined semantic action
 pub type SemanticAction = dyn FnMut(Symbol, &dyn SemanticBody);
+
+/// Delegate for a user-defined semantic action that may fail
+pub type FallibleSemanticAction = dyn FnMut(Symbol, &dyn SemanticBody) -> Result<(), SemanticError>;
+
+/// Represents an error raised by a semantic action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticError {
+    /// The message for this error
+    pub message: String,
+    /// The index of the element in the body of the rule that caused this error, if any
+    pub element: Option<usize>,
+}
+
+impl SemanticError {
+    /// Creates a new error for the whole body of the rule
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> SemanticError {
+        SemanticError {
+            message: message.into(),
+            element: None,
+        }
+    }
+
+    /// Creates a new error for the specified element in the body of the rule
+    #[must_use]
+    pub fn at(element: usize, message: impl Into<String>) -> SemanticError {
+        SemanticError {
+            message: message.into(),
+            element: Some(element),
+        }
+    }
+}
+
+/// The result of a semantic action, either nothing or the result of a fallible action
+pub trait SemanticActionResult {
+    /// Converts into the result of a fallible action
+    ///
+    /// # Errors
+    ///
+    /// Returns the error raised by the action, if any
+    fn into_result(self) -> Result<(), SemanticError>;
+}
+
+impl SemanticActionResult for () {
+    fn into_result(self) -> Result<(), SemanticError> {
+        Ok(())
+    }
+}
+
+impl SemanticActionResult for Result<(), SemanticError> {
+    fn into_result(self) -> Result<(), SemanticError> {
+        self
+    }
+}
+
+/// The semantic actions of a parser, dispatched by the index of the action
+/// This is implemented for closures taking the index of the action,
+/// either infallible or returning a `Result`.
+pub trait SemanticActions {
+    /// Executes the semantic action at the specified index
+    ///
+    /// # Errors
+    ///
+    /// Returns the error raised by the action, if any
+    fn execute(
+        &mut self,
+        index: usize,
+        head: Symbol,
+        body: &dyn SemanticBody,
+    ) -> Result<(), SemanticError>;
+}
+
+impl<F, R> SemanticActions for F
+where
+    F: FnMut(usize, Symbol, &dyn SemanticBody) -> R,
+    R: SemanticActionResult,
+{
+    fn execute(
+        &mut self,
+        index: usize,
+        head: Symbol,
+        body: &dyn SemanticBody,
+    ) -> Result<(), SemanticError> {
+        self(index, head, body).into_result()
+    }
+}
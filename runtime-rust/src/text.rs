@@ -25,6 +25,7 @@ use core::fmt::{Display, Error, Formatter};
 // use alloc::io::Read;
 use core::str::Chars;
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 /// `Utf16C` represents a single UTF-16 code unit.
@@ -39,7 +40,8 @@ use serde::{Deserialize, Serialize};
 pub type Utf16C = u16;
 
 /// Represents a span of text in an input as a starting index and length
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextSpan {
     /// The starting index
     pub index: usize,
@@ -62,6 +64,24 @@ impl Ord for TextSpan {
     }
 }
 
+impl TextSpan {
+    /// Merges two spans in the same input into a single span covering both
+    /// Returns `None` when the spans are neither adjacent nor overlapping
+    #[must_use]
+    pub fn merge(a: TextSpan, b: TextSpan) -> Option<TextSpan> {
+        let (first, second) = if a.index <= b.index { (a, b) } else { (b, a) };
+        let first_end = first.index + first.length;
+        if second.index > first_end {
+            return None;
+        }
+        let end = first_end.max(second.index + second.length);
+        Some(TextSpan {
+            index: first.index,
+            length: end - first.index,
+        })
+    }
+}
+
 /// Implementation of `Display` for `TextSpan`
 impl Display for TextSpan {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
@@ -70,7 +90,8 @@ impl Display for TextSpan {
 }
 
 /// Represents a position in term of line and column in a text input
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextPosition {
     /// The 1-base line number
     pub line: usize,
@@ -93,6 +114,20 @@ impl Ord for TextPosition {
     }
 }
 
+impl TextPosition {
+    /// Gets the position of an offset in a piece of text, tabs advancing to the next tab stop
+    /// Tab stops are placed every `tab_width` columns, a width of 0 or 1 counting a tab as a single column.
+    #[must_use]
+    pub fn with_tab_width(content: &str, offset: usize, tab_width: usize) -> TextPosition {
+        let lines = find_lines_in(content.char_indices());
+        let line = find_line_at(&lines, offset);
+        TextPosition {
+            line: line + 1,
+            column: get_column_in(&content[lines[line]..offset], tab_width),
+        }
+    }
+}
+
 /// Implementation of `Display` for `TextPosition`
 impl Display for TextPosition {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
@@ -122,6 +157,58 @@ pub struct TextContext<'a> {
     pub pointer: String,
 }
 
+/// Represents an invalid sequence in an original input, replaced by U+FFFD when decoding it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecodingError {
+    /// The index of the replacement character in the decoded content
+    pub index: usize,
+    /// Whether the high surrogate is missing, otherwise the low surrogate is
+    pub missing_high: bool,
+    /// The invalid UTF-16 code unit
+    pub sequence: Utf16C,
+}
+
+/// The data about the decoding of a text from an original input that is not UTF-8
+#[derive(Debug, Default, Clone)]
+struct Decoding {
+    /// The index in the content and the offset in the original input for each character,
+    /// followed by the ends of the content and the original input
+    origins: Vec<(usize, usize)>,
+    /// The invalid sequences that have been replaced
+    errors: Vec<DecodingError>,
+}
+
+/// The configuration for the parsing of a text
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseConfig {
+    /// The number of columns between two tab stops, a tab advancing to the next one
+    /// The default width of 1 counts a tab as a single column.
+    pub tab_width: usize,
+    /// The maximum number of tokens the lexer may produce for the text, if any
+    /// When reached, the lexer records an error and stops as at the end of the input.
+    pub max_tokens: Option<usize>,
+    /// The maximum number of live stacks of a RNGLR parser at an input position, if any
+    /// When exceeded, the parse is aborted with an error. The other parsers ignore it.
+    pub fork_budget: Option<usize>,
+    /// Whether the lexer retains the separator tokens as trivia of the following tokens
+    pub retain_trivia: bool,
+    /// Whether the unexpected token errors keep the stacks of states of the parser
+    /// They are not kept by default because copying them is costly on large inputs.
+    pub capture_stacks: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        ParseConfig {
+            tab_width: 1,
+            max_tokens: None,
+            fork_budget: None,
+            retain_trivia: false,
+            capture_stacks: false,
+        }
+    }
+}
+
 /// Represents the input of parser with some metadata for line endings
 /// All line numbers and column numbers are 1-based.
 /// Indices in the content are 0-based.
@@ -131,6 +218,10 @@ pub struct Text<'a> {
     content: Cow<'a, str>,
     /// Cache of the starting indices of each line within the text
     lines: Vec<usize>,
+    /// The data about the decoding of the original input, if it was not UTF-8
+    decoding: Option<Decoding>,
+    /// The configuration for the computation of positions
+    config: ParseConfig,
 }
 
 impl<'a> Text<'a> {
@@ -140,6 +231,8 @@ impl<'a> Text<'a> {
         Text {
             content: Cow::Owned(self.content.to_string()),
             lines: self.lines,
+            decoding: self.decoding,
+            config: self.config,
         }
     }
 
@@ -151,6 +244,21 @@ impl<'a> Text<'a> {
         Text {
             content: Cow::Borrowed(content),
             lines,
+            decoding: None,
+            config: ParseConfig::default(),
+        }
+    }
+
+    /// Initializes this text, reusing the storage of the line indices of a previous text
+    #[must_use]
+    pub fn from_str_reusing(content: &'a str, mut lines: Vec<usize>) -> Text<'a> {
+        lines.clear();
+        fill_lines_in(content.char_indices(), &mut lines);
+        Text {
+            content: Cow::Borrowed(content),
+            lines,
+            decoding: None,
+            config: ParseConfig::default(),
         }
     }
 
@@ -161,6 +269,8 @@ impl<'a> Text<'a> {
         Text {
             content: Cow::Owned(content),
             lines,
+            decoding: None,
+            config: ParseConfig::default(),
         }
     }
 
@@ -179,9 +289,109 @@ impl<'a> Text<'a> {
         Ok(Text {
             content: Cow::Owned(content),
             lines,
+            decoding: None,
+            config: ParseConfig::default(),
         })
     }
 
+    /// Initializes this text from UTF-16 bytes, detecting the endianness from the byte order mark, if any
+    /// Without a byte order mark, the bytes are decoded as UTF-16LE.
+    /// Invalid sequences are replaced by U+FFFD, see `get_decoding_errors`.
+    #[must_use]
+    pub fn from_utf16(bytes: &[u8]) -> Text<'static> {
+        match bytes {
+            [0xFE, 0xFF, ..] => decode_utf16(bytes, 2, u16::from_be_bytes),
+            [0xFF, 0xFE, ..] => decode_utf16(bytes, 2, u16::from_le_bytes),
+            _ => decode_utf16(bytes, 0, u16::from_le_bytes),
+        }
+    }
+
+    /// Initializes this text from UTF-16LE bytes
+    /// Invalid sequences are replaced by U+FFFD, see `get_decoding_errors`.
+    #[must_use]
+    pub fn from_utf16le(bytes: &[u8]) -> Text<'static> {
+        decode_utf16(bytes, 0, u16::from_le_bytes)
+    }
+
+    /// Initializes this text from UTF-16BE bytes
+    /// Invalid sequences are replaced by U+FFFD, see `get_decoding_errors`.
+    #[must_use]
+    pub fn from_utf16be(bytes: &[u8]) -> Text<'static> {
+        decode_utf16(bytes, 0, u16::from_be_bytes)
+    }
+
+    /// Initializes this text from Latin-1 (ISO-8859-1) bytes
+    #[must_use]
+    pub fn from_latin1(bytes: &[u8]) -> Text<'static> {
+        let mut content = String::with_capacity(bytes.len());
+        let mut origins = Vec::with_capacity(bytes.len() + 1);
+        for (offset, &byte) in bytes.iter().enumerate() {
+            origins.push((content.len(), offset));
+            content.push(char::from(byte));
+        }
+        Text::from_decoded(content, origins, bytes.len(), Vec::new())
+    }
+
+    /// Initializes this text from decoded content
+    fn from_decoded(
+        content: String,
+        mut origins: Vec<(usize, usize)>,
+        original_length: usize,
+        errors: Vec<DecodingError>,
+    ) -> Text<'static> {
+        origins.push((content.len(), original_length));
+        let lines = find_lines_in(content.char_indices());
+        Text {
+            content: Cow::Owned(content),
+            lines,
+            decoding: Some(Decoding { origins, errors }),
+            config: ParseConfig::default(),
+        }
+    }
+
+    /// Sets the configuration for the computation of positions in this text
+    #[must_use]
+    pub fn with_config(mut self, config: ParseConfig) -> Text<'a> {
+        self.config = config;
+        self
+    }
+
+    /// Gets the configuration for the computation of positions in this text
+    #[must_use]
+    pub fn get_config(&self) -> ParseConfig {
+        self.config
+    }
+
+    /// Gets the invalid sequences that have been replaced by U+FFFD when decoding the original input
+    #[must_use]
+    pub fn get_decoding_errors(&self) -> &[DecodingError] {
+        self.decoding
+            .as_ref()
+            .map(|decoding| decoding.errors.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Translates a span in this text to the span of the original bytes it was decoded from
+    /// For a text that was not decoded, the span is the same.
+    #[must_use]
+    pub fn to_original_offset(&self, span: TextSpan) -> TextSpan {
+        let Some(decoding) = self.decoding.as_ref() else {
+            return span;
+        };
+        let start = get_original_offset(&decoding.origins, span.index);
+        let end = get_original_offset(&decoding.origins, span.index + span.length);
+        TextSpan {
+            index: start,
+            length: end - start,
+        }
+    }
+
+    /// Gets the storage of the line indices for reuse
+    #[must_use]
+    pub fn into_lines(self) -> Vec<usize> {
+        self.lines
+    }
+
     /// Gets the number of lines
     #[must_use]
     pub fn get_line_count(&self) -> usize {
@@ -239,9 +449,13 @@ impl<'a> Text<'a> {
     #[must_use]
     pub fn get_index_at(&self, position: TextPosition) -> usize {
         let from_line = &self.content[self.lines[position.line - 1]..];
+        let mut column = 1;
         let in_line_offset = from_line
             .char_indices()
-            .take(position.column - 1)
+            .take_while(|&(_, c)| {
+                column = get_next_column(column, c, self.config.tab_width);
+                column <= position.column
+            })
             .last()
             .map(|(offset, c)| offset + c.len_utf8())
             .unwrap_or_default();
@@ -274,18 +488,19 @@ impl<'a> Text<'a> {
     #[must_use]
     pub fn get_position_at(&self, index: usize) -> TextPosition {
         let line = find_line_at(&self.lines, index);
-        let nb_chars = self.content[self.lines[line]..index].chars().count();
         TextPosition {
             line: line + 1,
-            column: nb_chars + 1,
+            column: get_column_in(
+                &self.content[self.lines[line]..index],
+                self.config.tab_width,
+            ),
         }
     }
 
     /// Gets the position for a starting position and a length
     #[must_use]
     pub fn get_position_for(&self, position: TextPosition, length: usize) -> TextPosition {
-        let index = self.lines[position.line - 1] + position.column - 1 + length;
-        self.get_position_at(index)
+        self.get_position_at(self.get_index_at(position) + length)
     }
 
     /// Gets the context description for the current text at the specified position
@@ -299,6 +514,10 @@ impl<'a> Text<'a> {
     pub fn get_context_for(&self, position: TextPosition, length: usize) -> TextContext {
         // gather the data for the line
         let mut line_content = self.get_line_content(position.line);
+        // the number of characters before the position on the line
+        let column = self.content[self.get_line_index(position.line)..self.get_index_at(position)]
+            .chars()
+            .count();
         // remove the line ending
         line_content = line_content.trim_end_matches(is_line_ending_char);
         // remove the heading white space
@@ -318,7 +537,7 @@ impl<'a> Text<'a> {
         }
         let in_line_offset = line_content
             .char_indices()
-            .take(position.column - 1 - removed_heading)
+            .take(column - removed_heading)
             .last()
             .map(|(offset, c)| offset + c.len_utf8())
             .unwrap_or_default();
@@ -327,7 +546,7 @@ impl<'a> Text<'a> {
             .take_while(|&(offset, _)| offset < length)
             .count()
             .max(1);
-        let pointer_blank_count = position.column - 1 - removed_heading;
+        let pointer_blank_count = column - removed_heading;
         // build the pointer
         let mut pointer = String::with_capacity(pointer_count + pointer_blank_count);
         for c in line_content.chars().take(pointer_blank_count) {
@@ -396,6 +615,53 @@ impl<'a> Iterator for Utf16Iter<'a> {
     }
 }
 
+/// Decodes UTF-16 bytes, starting at an offset, decoding code units with the specified function
+fn decode_utf16(bytes: &[u8], start: usize, decode_unit: fn([u8; 2]) -> u16) -> Text<'static> {
+    let mut content = String::with_capacity(bytes.len() / 2);
+    let mut origins = Vec::with_capacity(bytes.len() / 2 + 1);
+    let mut errors = Vec::new();
+    let chunks = bytes[start..].chunks_exact(2);
+    let remainder = chunks.remainder();
+    let units = chunks.map(|pair| decode_unit([pair[0], pair[1]]));
+    let mut offset = start;
+    for decoded in char::decode_utf16(units) {
+        origins.push((content.len(), offset));
+        match decoded {
+            Ok(c) => {
+                content.push(c);
+                offset += 2 * c.len_utf16();
+            }
+            Err(error) => {
+                let sequence = error.unpaired_surrogate();
+                errors.push(DecodingError {
+                    index: content.len(),
+                    missing_high: (0xDC00..=0xDFFF).contains(&sequence),
+                    sequence,
+                });
+                content.push(char::REPLACEMENT_CHARACTER);
+                offset += 2;
+            }
+        }
+    }
+    if let [byte] = remainder {
+        // the last code unit is truncated
+        origins.push((content.len(), offset));
+        errors.push(DecodingError {
+            index: content.len(),
+            missing_high: false,
+            sequence: Utf16C::from(*byte),
+        });
+        content.push(char::REPLACEMENT_CHARACTER);
+    }
+    Text::from_decoded(content, origins, bytes.len(), errors)
+}
+
+/// Gets the offset in the original input for an index in the decoded content
+fn get_original_offset(origins: &[(usize, usize)], index: usize) -> usize {
+    let position = origins.partition_point(|&(decoded, _)| decoded <= index);
+    origins[position.saturating_sub(1)].1
+}
+
 /// Determines whether [c1, c2] form a line ending sequence
 /// Recognized sequences are:
 /// [U+000D, U+000A] (this is Windows-style \r \n)
@@ -413,7 +679,7 @@ fn is_line_ending(c1: char, c2: char) -> bool {
 }
 
 /// Determines whether the character is part of a line ending sequence
-fn is_line_ending_char(c: char) -> bool {
+pub(crate) fn is_line_ending_char(c: char) -> bool {
     (c == '\u{000B}' || c == '\u{000C}' || c == '\u{0085}' || c == '\u{2028}' || c == '\u{2029}')
         || c == '\u{000D}'
         || c == '\u{000A}'
@@ -422,6 +688,12 @@ fn is_line_ending_char(c: char) -> bool {
 /// Finds all the lines in this content
 fn find_lines_in<T: Iterator<Item = (usize, char)>>(iterator: T) -> Vec<usize> {
     let mut result = Vec::new();
+    fill_lines_in(iterator, &mut result);
+    result
+}
+
+/// Fills the starting indices of the lines in a text
+fn fill_lines_in<T: Iterator<Item = (usize, char)>>(iterator: T, result: &mut Vec<usize>) {
     let mut c1: char;
     let mut c2: char = '\0';
     result.push(0);
@@ -436,7 +708,21 @@ fn find_lines_in<T: Iterator<Item = (usize, char)>>(iterator: T) -> Vec<usize> {
             });
         }
     }
-    result
+}
+
+/// Gets the 1-based column after a piece of text at the start of a line
+fn get_column_in(text: &str, tab_width: usize) -> usize {
+    text.chars()
+        .fold(1, |column, c| get_next_column(column, c, tab_width))
+}
+
+/// Gets the 1-based column after a character at the given column, tabs advancing to the next tab stop
+fn get_next_column(column: usize, c: char, tab_width: usize) -> usize {
+    if c == '\t' && tab_width > 1 {
+        column + tab_width - (column - 1) % tab_width
+    } else {
+        column + 1
+    }
 }
 
 /// Finds the index of the line at the given input index in the content
@@ -541,6 +827,54 @@ fn test_text_get_position_at() {
     }
 }
 
+#[test]
+fn test_text_position_with_tab_width() {
+    let content = "a\tb\n\t\tc";
+    assert_eq!(
+        TextPosition::with_tab_width(content, 2, 4),
+        TextPosition { line: 1, column: 5 }
+    );
+    assert_eq!(
+        TextPosition::with_tab_width(content, 6, 4),
+        TextPosition { line: 2, column: 9 }
+    );
+    assert_eq!(
+        TextPosition::with_tab_width(content, 6, 1),
+        TextPosition { line: 2, column: 3 }
+    );
+}
+
+#[test]
+fn test_text_get_position_at_with_tab_width() {
+    let text = Text::from_str("a\tb\n\t\tc").with_config(ParseConfig {
+        tab_width: 8,
+        ..ParseConfig::default()
+    });
+    assert_eq!(text.get_position_at(2), TextPosition { line: 1, column: 9 });
+    assert_eq!(
+        text.get_position_at(6),
+        TextPosition {
+            line: 2,
+            column: 17
+        }
+    );
+    assert_eq!(text.get_index_at(TextPosition { line: 1, column: 9 }), 2);
+    assert_eq!(
+        text.get_index_at(TextPosition {
+            line: 2,
+            column: 17
+        }),
+        6
+    );
+    assert_eq!(
+        text.get_context_for(TextPosition { line: 1, column: 9 }, 1),
+        TextContext {
+            content: "a\tb",
+            pointer: String::from(" \t^")
+        }
+    );
+}
+
 #[test]
 fn test_text_get_context_for() {
     let text = Text::from_str("नमस्ते\nЗдравствуйте");
@@ -607,3 +941,137 @@ fn test_text_get_context_for_no_leading_tab() {
         }
     );
 }
+
+#[test]
+fn test_text_span_merge() {
+    let a = TextSpan {
+        index: 2,
+        length: 3,
+    };
+    let adjacent = TextSpan {
+        index: 5,
+        length: 2,
+    };
+    let overlapping = TextSpan {
+        index: 3,
+        length: 1,
+    };
+    let apart = TextSpan {
+        index: 6,
+        length: 1,
+    };
+    assert_eq!(
+        TextSpan::merge(a, adjacent),
+        Some(TextSpan {
+            index: 2,
+            length: 5
+        })
+    );
+    assert_eq!(TextSpan::merge(adjacent, a), TextSpan::merge(a, adjacent));
+    assert_eq!(TextSpan::merge(a, overlapping), Some(a));
+    assert_eq!(TextSpan::merge(a, apart), None);
+}
+
+#[test]
+fn test_text_from_utf16() {
+    let content = "a\u{e9}\n\u{1F600}b";
+    let mut le = alloc::vec![0xFF, 0xFE];
+    le.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+    let mut be = alloc::vec![0xFE, 0xFF];
+    be.extend(content.encode_utf16().flat_map(u16::to_be_bytes));
+    for text in [
+        Text::from_utf16(&le),
+        Text::from_utf16(&be),
+        Text::from_utf16(&le[2..]),
+        Text::from_utf16le(&le[2..]),
+        Text::from_utf16be(&be[2..]),
+    ] {
+        assert_eq!(text.content, content);
+        assert_eq!(text.get_line_count(), 2);
+        assert!(text.get_decoding_errors().is_empty());
+    }
+    let text = Text::from_utf16(&le);
+    // the smiley takes 4 bytes in UTF-8 and UTF-16
+    assert_eq!(
+        text.to_original_offset(TextSpan {
+            index: 4,
+            length: 5
+        }),
+        TextSpan {
+            index: 8,
+            length: 6
+        }
+    );
+    assert_eq!(
+        text.to_original_offset(TextSpan {
+            index: 0,
+            length: text.len()
+        }),
+        TextSpan {
+            index: 2,
+            length: 12
+        }
+    );
+}
+
+#[test]
+fn test_text_from_utf16_invalid() {
+    // a lone low surrogate, a lone high surrogate and a truncated code unit
+    let bytes = [0x61, 0x00, 0x00, 0xDC, 0x00, 0xD8, 0x62, 0x00, 0x63];
+    let text = Text::from_utf16le(&bytes);
+    assert_eq!(text.content, "a\u{FFFD}\u{FFFD}b\u{FFFD}");
+    assert_eq!(
+        text.get_decoding_errors(),
+        [
+            DecodingError {
+                index: 1,
+                missing_high: true,
+                sequence: 0xDC00
+            },
+            DecodingError {
+                index: 4,
+                missing_high: false,
+                sequence: 0xD800
+            },
+            DecodingError {
+                index: 8,
+                missing_high: false,
+                sequence: 0x63
+            }
+        ]
+    );
+    assert_eq!(
+        text.to_original_offset(TextSpan {
+            index: 7,
+            length: 4
+        }),
+        TextSpan {
+            index: 6,
+            length: 3
+        }
+    );
+}
+
+#[test]
+fn test_text_from_latin1() {
+    let text = Text::from_latin1(b"caf\xE9 \xABx\xBB");
+    assert_eq!(text.content, "caf\u{e9} \u{ab}x\u{bb}");
+    assert!(text.get_decoding_errors().is_empty());
+    assert_eq!(
+        text.to_original_offset(TextSpan {
+            index: 6,
+            length: 5
+        }),
+        TextSpan {
+            index: 5,
+            length: 3
+        }
+    );
+    // texts that were not decoded are not translated
+    let text = Text::from_str("caf\u{e9}");
+    let span = TextSpan {
+        index: 3,
+        length: 2,
+    };
+    assert_eq!(text.to_original_offset(span), span);
+}
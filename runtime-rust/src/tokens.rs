@@ -17,6 +17,9 @@
 
 //! Module for the definition of lexical tokens
 
+use alloc::vec::Vec;
+
+use crate::lexers::DEFAULT_CONTEXT;
 use crate::symbols::{SemanticElementTrait, Symbol};
 use crate::text::{Text, TextContext, TextPosition, TextSpan};
 use crate::utils::biglist::BigList;
@@ -29,6 +32,8 @@ struct TokenRepositoryCell {
     terminal: usize,
     /// The span of this token
     span: TextSpan,
+    /// The identifier of the lexical context the token was matched in
+    context: u16,
 }
 
 /// Implementation data of a repository of matched tokens
@@ -36,6 +41,30 @@ struct TokenRepositoryCell {
 pub struct TokenRepositoryImpl {
     /// The token data in this content
     cells: BigList<TokenRepositoryCell>,
+    /// The retained separator tokens, with the index of the token that follows each one
+    trivia: Vec<(usize, TokenRepositoryCell)>,
+}
+
+impl TokenRepositoryImpl {
+    /// Removes all the tokens, keeping the allocated storage for reuse
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.trivia.clear();
+    }
+}
+
+/// Represents a piece of trivia, i.e. a separator token retained by the lexer
+/// that precedes a token in the input
+#[derive(Debug, Copy, Clone)]
+pub struct Trivia<'s, 'a> {
+    /// The separator terminal that was matched
+    pub symbol: Symbol<'s>,
+    /// The position in the input text of this trivia
+    pub position: TextPosition,
+    /// The span in the input text of this trivia
+    pub span: TextSpan,
+    /// The value of this trivia
+    pub value: &'a str,
 }
 
 /// The proxy structure for a repository of matched tokens
@@ -122,12 +151,54 @@ impl<'s, 't, 'a> TokenRepository<'s, 't, 'a> {
 
     /// Registers a new token in this repository
     pub fn add(&mut self, terminal: usize, index: usize, length: usize) -> usize {
+        self.add_in_context(terminal, index, length, DEFAULT_CONTEXT)
+    }
+
+    /// Registers a new token matched in the specified lexical context
+    pub fn add_in_context(
+        &mut self,
+        terminal: usize,
+        index: usize,
+        length: usize,
+        context: u16,
+    ) -> usize {
         self.data.cells.push(TokenRepositoryCell {
             terminal,
             span: TextSpan { index, length },
+            context,
         })
     }
 
+    /// Registers a separator token that precedes the next token to be added
+    pub fn add_trivia(&mut self, terminal: usize, index: usize, length: usize) {
+        let owner = self.data.cells.len();
+        self.data.trivia.push((
+            owner,
+            TokenRepositoryCell {
+                terminal,
+                span: TextSpan { index, length },
+                context: DEFAULT_CONTEXT,
+            },
+        ));
+    }
+
+    /// Gets the separator tokens retained before the i-th token
+    #[must_use]
+    pub fn get_trivia_for(&self, index: usize) -> Vec<Trivia<'s, 'a>> {
+        let trivia = &self.data.trivia;
+        let start = trivia.partition_point(|(owner, _)| *owner < index);
+        let end = trivia.partition_point(|(owner, _)| *owner <= index);
+        trivia[start..end]
+            .iter()
+            .map(|(_, cell)| Trivia {
+                symbol: self.terminals[cell.terminal],
+                position: self.text.get_position_at(cell.span.index),
+                span: cell.span,
+                value: self.text.get_value_for(cell.span),
+            })
+            .collect()
+    }
+
     /// Gets the number of tokens in this repository
     #[must_use]
     pub fn get_tokens_count(&self) -> usize {
@@ -158,30 +229,65 @@ impl<'s, 't, 'a> TokenRepository<'s, 't, 'a> {
     /// Gets the token (if any) that contains the specified index in the input text
     #[must_use]
     pub fn find_token_at(&'a self, index: usize) -> Option<Token<'s, 't, 'a>> {
-        let count = self.data.cells.len();
-        if count == 0 {
-            return None;
-        }
+        self.locate_token(index).ok().map(|index| Token {
+            repository: self,
+            index,
+        })
+    }
+
+    /// Looks for the token that contains the specified index in the input text.
+    /// Returns `Ok` with the index of the token when found,
+    /// or `Err` with the index of the first token that starts after the index in the input text.
+    pub(crate) fn locate_token(&self, index: usize) -> Result<usize, usize> {
         let mut l: usize = 0;
-        let mut r = count - 1;
-        while l <= r {
+        let mut r = self.data.cells.len();
+        while l < r {
             let m = (l + r) / 2;
-            let cell = self.data.cells[m];
-            if index < cell.span.index {
+            let span = self.data.cells[m].span;
+            if index < span.index {
                 // look on the left
-                r = m - 1;
-            } else if index < cell.span.index + cell.span.length {
+                r = m;
+            } else if index < span.index + span.length {
                 // within the token
-                return Some(Token {
-                    repository: self,
-                    index: m,
-                });
+                return Ok(m);
             } else {
                 // look on the right
                 l = m + 1;
             }
         }
-        None
+        Err(l)
+    }
+
+    /// Gets the span in the input text of the i-th token
+    pub(crate) fn get_span_of(&self, index: usize) -> TextSpan {
+        self.data.cells[index].span
+    }
+
+    /// Gets the identifier of the lexical context the i-th token was matched in
+    #[must_use]
+    pub fn get_context_of(&self, index: usize) -> u16 {
+        self.data.cells[index].context
+    }
+}
+
+impl<'a> Token<'_, '_, 'a> {
+    /// Gets the identifier of the lexical context this token was matched in
+    /// The tokens matched in the default context, or by a context-free lexer, are in context 0.
+    #[must_use]
+    pub fn context_id(&self) -> u16 {
+        self.repository.get_context_of(self.index)
+    }
+
+    /// Gets the text of this token, as a slice of the input text
+    /// This is the same as `get_value`, without having to import `SemanticElementTrait`;
+    /// no allocation is involved in either case.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&'a str> {
+        Some(
+            self.repository
+                .text
+                .get_value_for(self.repository.data.cells[self.index].span),
+        )
     }
 }
 
@@ -220,10 +326,11 @@ impl<'s, 't, 'a> SemanticElementTrait<'s, 'a> for Token<'s, 't, 'a> {
     /// Gets the value of this element, if any
     #[must_use]
     fn get_value(&self) -> Option<&'a str> {
-        Some(
-            self.repository
-                .text
-                .get_value_for(self.repository.data.cells[self.index].span),
-        )
+        self.as_str()
+    }
+
+    /// Gets the separator tokens retained before this element
+    fn leading_trivia(&self) -> Vec<Trivia<'s, 'a>> {
+        self.repository.get_trivia_for(self.index)
     }
 }
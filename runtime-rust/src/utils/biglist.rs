@@ -41,12 +41,11 @@ pub struct BigList<T> {
     cell_index: usize,
 }
 
+/// The default list is empty and only allocates its chunks on the first addition
 impl<T: Default + Copy> Default for BigList<T> {
     fn default() -> Self {
-        let mut my_chunks = Vec::with_capacity(INIT_CHUNK_COUNT);
-        my_chunks.push([T::default(); CHUNKS_SIZE]);
         BigList {
-            chunks: my_chunks,
+            chunks: Vec::new(),
             chunk_index: 0,
             cell_index: 0,
         }
@@ -81,13 +80,28 @@ impl<T> BigList<T> {
     pub fn len(&self) -> usize {
         (self.chunk_index * CHUNKS_SIZE) + self.cell_index
     }
+
+    /// Gets the number of items this list can hold without allocating new chunks
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.chunks.len() * CHUNKS_SIZE
+    }
+
+    /// Removes all the items from this list, keeping the allocated chunks for reuse
+    pub fn clear(&mut self) {
+        self.chunk_index = 0;
+        self.cell_index = 0;
+    }
 }
 
 /// Implementation of `BigList`
 impl<T: Default + Copy> BigList<T> {
     /// Adds a value at the end of the list
     pub fn push(&mut self, value: T) -> usize {
-        if self.cell_index == CHUNKS_SIZE {
+        if self.chunks.is_empty() {
+            self.chunks.reserve(INIT_CHUNK_COUNT);
+            self.chunks.push([T::default(); CHUNKS_SIZE]);
+        } else if self.cell_index == CHUNKS_SIZE {
             self.add_chunk();
         }
         self.chunks[self.chunk_index][self.cell_index] = value;
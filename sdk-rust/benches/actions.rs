@@ -0,0 +1,58 @@
+//! Micro-benchmark for LR(k) parsing with and without dispatching the reductions to semantic actions
+//! Without actions, the parser is built with `LRkParser::new_without_actions`, as in the generated parsers.
+//! Run with `cargo bench -p hime_sdk --bench actions`
+
+use std::time::Instant;
+
+use hime_redist::symbols::{SemanticBody, Symbol};
+use hime_sdk::{CompilationTask, Input};
+
+/// The number of inputs to parse
+const INPUTS: usize = 2_000;
+
+/// The grammar for the inputs
+const GRAMMAR: &str = "grammar Expr { options { Axiom = \"e\"; Separator = \"WS\"; } \
+    terminals { WS -> ' '+; ID -> [a-z]+; NUM -> [0-9]+; } \
+    rules { e -> e '+' t | e '-' t | t ; t -> t '*' f | f ; f -> '(' e ')' | ID | NUM ; } }";
+
+/// Generates the inputs
+fn generate_inputs() -> Vec<String> {
+    (0..INPUTS)
+        .map(|i| {
+            (0..20)
+                .map(|j| format!("x{} * ({i} + y) + {j}", "a".repeat(j % 5)))
+                .collect::<Vec<_>>()
+                .join(" - ")
+        })
+        .collect()
+}
+
+fn main() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let inputs = generate_inputs();
+
+    let mut no_actions = |_index: usize, _head: Symbol, _body: &dyn SemanticBody| ();
+    let start = Instant::now();
+    for input in &inputs {
+        assert!(parser
+            .parse_with_actions(input, &mut no_actions)
+            .is_success());
+    }
+    let dispatched = start.elapsed();
+
+    let start = Instant::now();
+    for input in &inputs {
+        assert!(parser.parse(input).is_success());
+    }
+    let skipped = start.elapsed();
+
+    println!("{INPUTS} inputs dispatching to empty actions: {dispatched:?}");
+    println!("{INPUTS} inputs without actions: {skipped:?}");
+}
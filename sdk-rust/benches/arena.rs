@@ -0,0 +1,50 @@
+//! Micro-benchmark for parsing many small inputs, with and without reusing a parse arena
+//! Run with `cargo bench -p hime_sdk --bench arena`
+
+use std::time::Instant;
+
+use hime_redist::result::ParseArena;
+use hime_sdk::{CompilationTask, Input};
+
+/// The number of small inputs to parse
+const INPUTS: usize = 10_000;
+
+/// The grammar for the small inputs
+const GRAMMAR: &str = "grammar Expr { options { Axiom = \"e\"; Separator = \"WS\"; } \
+    terminals { WS -> ' '+; ID -> [a-z]+; NUM -> [0-9]+; } \
+    rules { e -> e '+' t | e '-' t | t ; t -> t '*' f | f ; f -> '(' e ')' | ID | NUM ; } }";
+
+/// Generates the small inputs
+fn generate_inputs() -> Vec<String> {
+    (0..INPUTS)
+        .map(|i| format!("x{} * ({i} + y) - {}", "a".repeat(i % 5), i % 7))
+        .collect()
+}
+
+fn main() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let inputs = generate_inputs();
+
+    let start = Instant::now();
+    for input in &inputs {
+        assert!(parser.parse(input).is_success());
+    }
+    let fresh = start.elapsed();
+
+    let mut arena = ParseArena::new();
+    let start = Instant::now();
+    for input in &inputs {
+        assert!(parser.parse_in(&mut arena, input).is_success());
+    }
+    let reused = start.elapsed();
+
+    println!("{INPUTS} inputs without arena: {fresh:?}");
+    println!("{INPUTS} inputs with a reused arena: {reused:?}");
+}
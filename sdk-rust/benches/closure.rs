@@ -0,0 +1,100 @@
+//! Micro-benchmark for the closure of the LR(1) kernels of a grammar with many terminals,
+//! with the indexed closure and with the linear scan of `Item::close_to`
+//! Run with `cargo bench -p hime_sdk --bench closure`
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use hime_sdk::grammars::{Grammar, GENERATED_AXIOM};
+use hime_sdk::lr::{build_graph_lr1, closure, Item, LookaheadMode};
+use hime_sdk::{CompilationTask, Input};
+
+/// The number of keyword terminals in the generated grammar
+const TERMINALS: usize = 150;
+/// The number of iterations to measure
+const ITERATIONS: u32 = 5;
+
+/// Generates an expression grammar where many terminals may follow the expressions
+fn generate_grammar() -> String {
+    let mut terminals = String::new();
+    let mut statements = Vec::new();
+    for i in 0..TERMINALS {
+        write!(terminals, "T{i} -> 'k{i}'; ").unwrap();
+        statements.push(format!("e T{i}"));
+    }
+    format!(
+        "grammar Big {{ options {{ Axiom = \"s\"; }} terminals {{ ID -> [a-z]+; {terminals} }} rules {{ \
+         s -> stmt s | stmt ; stmt -> {} ; \
+         e -> e '+' t | e '-' t | t ; t -> t '*' f | f ; f -> '(' e ')' | ID ; }} }}",
+        statements.join(" | ")
+    )
+}
+
+/// Closes the kernels with the linear scan of `Item::close_to`
+fn close_linear(kernels: &[Vec<Item>], grammar: &Grammar) -> usize {
+    let mut count = 0;
+    for kernel in kernels {
+        let mut items = kernel.clone();
+        let mut i = 0;
+        while i < items.len() {
+            items[i]
+                .clone()
+                .close_to(grammar, &mut items, LookaheadMode::LR1);
+            i += 1;
+        }
+        count += items.len();
+    }
+    count
+}
+
+/// Closes the kernels with the indexed closure
+fn close_indexed(kernels: &[Vec<Item>], grammar: &Grammar) -> usize {
+    kernels
+        .iter()
+        .map(|kernel| closure(kernel.clone(), grammar, LookaheadMode::LR1).len())
+        .sum()
+}
+
+/// Measures the mean duration of closing the kernels
+fn measure(
+    kernels: &[Vec<Item>],
+    grammar: &Grammar,
+    close: fn(&[Vec<Item>], &Grammar) -> usize,
+) -> (usize, Duration) {
+    // warm-up
+    let count = close(kernels, grammar);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        close(kernels, grammar);
+    }
+    (count, start.elapsed() / ITERATIONS)
+}
+
+fn main() {
+    let input = generate_grammar();
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(&input)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (graph, _) = build_graph_lr1(grammar, axiom);
+    let kernels: Vec<Vec<Item>> = graph
+        .states
+        .iter()
+        .map(|state| state.kernel.items.clone())
+        .collect();
+
+    let (linear_items, linear) = measure(&kernels, grammar, close_linear);
+    let (indexed_items, indexed) = measure(&kernels, grammar, close_indexed);
+    assert_eq!(linear_items, indexed_items);
+
+    println!(
+        "LR(1) closures of {} kernels ({indexed_items} items) for {TERMINALS} terminals:",
+        kernels.len()
+    );
+    println!("  linear scan: {linear:?} per pass");
+    println!("  indexed: {indexed:?} per pass");
+}
@@ -0,0 +1,59 @@
+//! Micro-benchmark for the construction of LALR(1) graphs on a large grammar
+//! Run with `cargo bench -p hime_sdk --bench lalr`
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use hime_sdk::grammars::GENERATED_AXIOM;
+use hime_sdk::lr::build_graph_lalr1;
+use hime_sdk::{CompilationTask, Input};
+
+/// The number of keyword terminals in the generated grammar
+const TERMINALS: usize = 200;
+/// The number of iterations to measure
+const ITERATIONS: u32 = 5;
+
+/// Generates a large expression grammar with many terminals
+fn generate_grammar() -> String {
+    let mut terminals = String::new();
+    let mut statements = Vec::new();
+    for i in 0..TERMINALS {
+        write!(terminals, "T{i} -> 'k{i}'; ").unwrap();
+        statements.push(format!("T{i} e s{}", i % 10));
+    }
+    let mut optionals = String::new();
+    for i in 0..10 {
+        write!(optionals, "s{i} -> T{i} | ; ").unwrap();
+    }
+    format!(
+        "grammar Big {{ options {{ Axiom = \"e\"; }} terminals {{ ID -> [a-z]+; {terminals} }} rules {{ \
+         e -> e '+' t | e '-' t | t ; t -> t '*' f | f ; f -> '(' e ')' | stmt | ID ; \
+         stmt -> {} ; {optionals} }} }}",
+        statements.join(" | ")
+    )
+}
+
+fn main() {
+    let input = generate_grammar();
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(&input)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    // warm-up
+    let (graph, _) = build_graph_lalr1(grammar, axiom);
+    let mut total = Duration::default();
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let _ = build_graph_lalr1(grammar, axiom);
+        total += start.elapsed();
+    }
+    println!(
+        "LALR(1) graph of {} states for {TERMINALS} terminals: {:?} per build",
+        graph.states.len(),
+        total / ITERATIONS
+    );
+}
@@ -0,0 +1,38 @@
+//! Micro-benchmark for the RNGLR runtime on a highly ambiguous grammar
+//! Run with `cargo bench -p hime_sdk --bench rnglr`
+
+use std::time::{Duration, Instant};
+
+use hime_sdk::{CompilationTask, Input, ParsingMethod};
+
+/// The highly ambiguous grammar, the number of derivations grows with the Catalan numbers
+const GRAMMAR: &str =
+    "grammar Ambiguous { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e e | A ; } }";
+
+/// The lengths of the inputs to measure
+const LENGTHS: [usize; 4] = [10, 20, 40, 80];
+/// The number of iterations to measure
+const ITERATIONS: u32 = 5;
+
+fn main() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(Some(ParsingMethod::RNGLALR1), 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    for length in LENGTHS {
+        let input = "a".repeat(length);
+        // warm-up
+        assert!(parser.parse(&input).is_success());
+        let mut total = Duration::default();
+        for _ in 0..ITERATIONS {
+            let start = Instant::now();
+            let _ = parser.parse(&input);
+            total += start.elapsed();
+        }
+        println!("{length} tokens: {:?} per parse", total / ITERATIONS);
+    }
+}
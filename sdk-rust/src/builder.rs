@@ -0,0 +1,252 @@
+/*******************************************************************************
+ * Copyright (c) 2020 Association Cénotélie (cenotelie.fr)
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Lesser General Public License as
+ * published by the Free Software Foundation, either version 3
+ * of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General
+ * Public License along with this program.
+ * If not, see <http://www.gnu.org/licenses/>.
+ ******************************************************************************/
+
+//! Module for building grammars programmatically, without going through the text of a grammar
+
+use hime_redist::parsers::{TreeAction, TREE_ACTION_NONE};
+use hime_redist::text::TextPosition;
+
+use crate::errors::Error;
+use crate::finite::NFA;
+use crate::grammars::{
+    Grammar, Rule, RuleBody, RuleBodyElement, SymbolRef, TerminalReference, DEFAULT_CONTEXT_NAME,
+    OPTION_AXIOM,
+};
+use crate::loaders::{build_nfa_text, load_terminal_expression};
+use crate::InputReference;
+
+/// The input reference for the elements of a grammar built programmatically, as there is no input
+const NO_INPUT: InputReference = InputReference {
+    input_index: 0,
+    position: TextPosition { line: 0, column: 0 },
+    length: 0,
+};
+
+/// A builder of grammars from code
+/// The symbols are referred to by the `SymbolRef` returned when they are added.
+/// The errors, e.g. for duplicate names or unknown symbols, are collected along the way
+/// and reported by `finish`.
+#[derive(Debug)]
+pub struct GrammarBuilder {
+    /// The grammar being built
+    grammar: Grammar,
+    /// The errors found so far
+    errors: Vec<Error>,
+}
+
+impl GrammarBuilder {
+    /// Creates a builder for a grammar with the specified name
+    #[must_use]
+    pub fn new(name: &str) -> GrammarBuilder {
+        GrammarBuilder {
+            grammar: Grammar::new(NO_INPUT, name.to_string()),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Sets the value of a grammar option, e.g. `Separator` or `Method`
+    pub fn set_option(&mut self, name: &str, value: &str) {
+        self.grammar
+            .add_option(NO_INPUT, NO_INPUT, name.to_string(), value.to_string());
+    }
+
+    /// Sets the axiom of the grammar
+    pub fn set_axiom(&mut self, variable: SymbolRef) {
+        match self.get_variable_name(variable) {
+            Some(name) => self.set_option(OPTION_AXIOM, &name),
+            None => self.errors.push(Error::UnknownSymbolRef(variable)),
+        }
+    }
+
+    /// Adds a lexical context, if it does not exist yet, and returns its identifier
+    /// The default context always exists, with the identifier 0.
+    pub fn add_context(&mut self, name: &str) -> usize {
+        self.grammar.resolve_context(name)
+    }
+
+    /// Adds a terminal in the default context
+    /// The terminal matches an expression in the syntax of the terminal rules of grammars, e.g. `[a-z]+`,
+    /// which may refer by name to the terminals already added.
+    pub fn add_terminal(&mut self, name: &str, regex: &str) -> SymbolRef {
+        self.add_terminal_in(DEFAULT_CONTEXT_NAME, name, regex)
+    }
+
+    /// Adds a terminal in a lexical context, which is added if it does not exist yet
+    /// The terminal matches an expression in the syntax of the terminal rules of grammars, e.g. `[a-z]+`,
+    /// which may refer by name to the terminals already added.
+    pub fn add_terminal_in(&mut self, context: &str, name: &str, regex: &str) -> SymbolRef {
+        if let Some(previous) = self.check_new_name(name) {
+            return previous;
+        }
+        let (nfa, references) =
+            match load_terminal_expression(&self.grammar, regex) {
+                Ok(loaded) => loaded,
+                Err(messages) => {
+                    self.errors.extend(messages.into_iter().map(|message| {
+                        Error::InvalidTerminalExpression(name.to_string(), message)
+                    }));
+                    (NFA::new_minimal(), Vec::new())
+                }
+            };
+        let referring_id = self.add_terminal_with(context, name, nfa);
+        for referred_id in references {
+            if let Some(referred) = self.grammar.get_terminal_mut(referred_id) {
+                referred.terminal_references.push(TerminalReference {
+                    referring_id,
+                    input_ref: NO_INPUT,
+                });
+            }
+        }
+        SymbolRef::Terminal(referring_id)
+    }
+
+    /// Adds a terminal in the default context that matches exactly a piece of text
+    pub fn add_literal(&mut self, name: &str, text: &str) -> SymbolRef {
+        if let Some(previous) = self.check_new_name(name) {
+            return previous;
+        }
+        let id = self.add_terminal_with(DEFAULT_CONTEXT_NAME, name, build_nfa_text(text, false));
+        if let Some(terminal) = self.grammar.get_terminal_mut(id) {
+            terminal.value = text.to_string();
+        }
+        SymbolRef::Terminal(id)
+    }
+
+    /// Adds a variable
+    pub fn add_variable(&mut self, name: &str) -> SymbolRef {
+        if let Some(previous) = self.check_new_name(name) {
+            return previous;
+        }
+        SymbolRef::Variable(self.grammar.add_variable(name).id)
+    }
+
+    /// Adds a rule for a variable in the default context
+    /// There must be a tree action for each symbol in the body,
+    /// or none at all, in which case no tree action is applied.
+    pub fn add_rule(
+        &mut self,
+        variable: SymbolRef,
+        body: Vec<SymbolRef>,
+        tree_actions: &[TreeAction],
+    ) {
+        self.add_rule_in(DEFAULT_CONTEXT_NAME, variable, body, tree_actions);
+    }
+
+    /// Adds a rule for a variable that opens a lexical context, which is added if it does not exist yet
+    /// There must be a tree action for each symbol in the body,
+    /// or none at all, in which case no tree action is applied.
+    pub fn add_rule_in(
+        &mut self,
+        context: &str,
+        variable: SymbolRef,
+        body: Vec<SymbolRef>,
+        tree_actions: &[TreeAction],
+    ) {
+        let Some(name) = self.get_variable_name(variable) else {
+            self.errors.push(Error::UnknownSymbolRef(variable));
+            return;
+        };
+        let errors_count = self.errors.len();
+        for symbol in &body {
+            let is_defined = match *symbol {
+                SymbolRef::Terminal(id) => self.grammar.get_terminal(id).is_some(),
+                SymbolRef::Variable(id) => self.grammar.get_variable(id).is_some(),
+                _ => false,
+            };
+            if !is_defined {
+                self.errors.push(Error::UnknownSymbolRef(*symbol));
+            }
+        }
+        if !tree_actions.is_empty() && tree_actions.len() != body.len() {
+            self.errors.push(Error::RuleActionsMismatch(
+                name.clone(),
+                body.len(),
+                tree_actions.len(),
+            ));
+        }
+        if self.errors.len() > errors_count {
+            return;
+        }
+        let elements = body
+            .into_iter()
+            .enumerate()
+            .map(|(index, symbol)| {
+                let action = tree_actions.get(index).copied().unwrap_or(TREE_ACTION_NONE);
+                RuleBodyElement::new(symbol, action, None)
+            })
+            .collect();
+        let context = self.grammar.resolve_context(context);
+        if let Some(variable) = self.grammar.variables.iter_mut().find(|v| v.name == name) {
+            variable.add_rule(Rule::new(
+                variable.id,
+                TREE_ACTION_NONE,
+                NO_INPUT,
+                RuleBody::from_parts(elements),
+                context,
+            ));
+        }
+    }
+
+    /// Finishes building the grammar
+    /// The grammar is prepared, as by `Grammar::prepare`, so that it can be built.
+    ///
+    /// # Errors
+    ///
+    /// Return the errors found while building the grammar, or when preparing it
+    pub fn finish(mut self) -> Result<Grammar, Vec<Error>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        if let Err(error) = self.grammar.prepare(0) {
+            return Err(vec![error]);
+        }
+        Ok(self.grammar)
+    }
+
+    /// Checks that no symbol already has the specified name
+    /// Otherwise, the error is recorded and the previous symbol is returned.
+    fn check_new_name(&mut self, name: &str) -> Option<SymbolRef> {
+        let previous = if let Some(terminal) = self.grammar.get_terminal_for_name(name) {
+            SymbolRef::Terminal(terminal.id)
+        } else {
+            SymbolRef::Variable(self.grammar.get_variable_for_name(name)?.id)
+        };
+        self.errors
+            .push(Error::DuplicateSymbolName(name.to_string()));
+        Some(previous)
+    }
+
+    /// Adds a named terminal matched by a NFA in a lexical context and returns its identifier
+    fn add_terminal_with(&mut self, context: &str, name: &str, nfa: NFA) -> usize {
+        self.grammar.resolve_context(context);
+        let terminal =
+            self.grammar
+                .add_terminal_named(name.to_string(), NO_INPUT, nfa, context, false);
+        terminal
+            .nfa
+            .add_final_terminal(terminal.id, terminal.context);
+        terminal.id
+    }
+
+    /// Gets the name of a variable, if the symbol is one
+    fn get_variable_name(&self, symbol: SymbolRef) -> Option<String> {
+        match symbol {
+            SymbolRef::Variable(id) => self.grammar.get_variable(id).map(|v| v.name.clone()),
+            _ => None,
+        }
+    }
+}
@@ -23,8 +23,8 @@ pub mod print;
 use std::fmt::{Display, Formatter};
 use std::io;
 
-use crate::grammars::{TerminalRef, OPTION_AXIOM, OPTION_SEPARATOR};
-use crate::lr::{Conflict, ConflictKind, ContextError};
+use crate::grammars::{RuleRef, SymbolRef, TerminalRef, OPTION_AXIOM, OPTION_SEPARATOR};
+use crate::lr::{Conflict, ConflictKind, ContextError, StateLimitError};
 use crate::{InputReference, LoadedData};
 
 /// Represents an error where a token is used by cannot be produced by the lexer
@@ -45,6 +45,9 @@ pub enum Error {
     Msg(String),
     /// Parsing error
     Parsing(InputReference, String),
+    /// The lexer produced more tokens than allowed when parsing an input
+    /// (`input`, `max_token_count`)
+    MaxTokensExceeded(InputReference, usize),
     /// The target grammar was not specified
     GrammarNotSpecified,
     /// The specified grammar was not found
@@ -58,6 +61,9 @@ pub enum Error {
     /// The grammar's axiom is not defined (does not exist)
     /// (grammar_index)
     AxiomNotDefined(usize),
+    /// An additional entry point of the grammar is not defined (does not exist)
+    /// (`grammar_index`, `entry`)
+    EntryNotDefined(usize, String),
     /// The separator token specified by a grammar is not defined
     /// (grammar_index)
     SeparatorNotDefined(usize),
@@ -73,8 +79,18 @@ pub enum Error {
     TemplateRuleWrongNumberOfArgs(InputReference, usize, usize),
     /// The specifiec symbol was not found
     SymbolNotFound(InputReference, String),
+    /// A terminal refers to itself in its own definition
+    /// (`reference`, `name`)
+    RecursiveTerminalReference(InputReference, String),
     /// Invalid character span
     InvalidCharacterSpan(InputReference),
+    /// A negative lookahead is not a set of single characters
+    InvalidLookahead(InputReference),
+    /// An annotation is not followed by the declaration of a terminal or a variable
+    /// (`reference`, `key`)
+    AnnotationWithoutSymbol(InputReference, String),
+    /// The scanner of an external terminal is not the path to a Rust function
+    InvalidExternalScanner(InputReference, String),
     /// The unicode block is not known
     UnknownUnicodeBlock(InputReference, String),
     /// The unicode category is not known
@@ -85,10 +101,30 @@ pub enum Error {
     InvalidCodePoint(InputReference, u32),
     /// A terminal override a previous definition
     OverridingPreviousTerminal(InputReference, String, InputReference),
+    /// A terminal is listed in more than one priority group
+    /// (`reference`, `name`, `previous_group`)
+    TerminalInSeveralGroups(InputReference, String, InputReference),
     /// The inherited grammar cannot be found
     GrammarNotDefined(InputReference, String),
+    /// Another grammar is declared with the same name
+    DuplicateGrammar(InputReference, String, InputReference),
+    /// A terminal redefines an inherited terminal with a different pattern
+    /// (`definition`, `name`, `inherited_definition`)
+    RedefinedTerminal(InputReference, String, InputReference),
+    /// A variable redefines an inherited variable with different alternatives
+    /// (`definition`, `name`, `inherited_definition`)
+    RedefinedVariable(InputReference, String, InputReference),
+    /// Two inherited grammars define the same symbol differently
+    /// (`parent`, `name`, `parent_definition`, `previous_definition`)
+    ConflictingInheritedSymbol(InputReference, String, InputReference, InputReference),
     /// A conflict in a grammar
     LrConflict(usize, Box<Conflict>),
+    /// The number of shift/reduce conflicts in a grammar is not the expected one
+    /// (`grammar_index`, `expected`, `found`)
+    UnexpectedConflictCount(usize, usize, usize),
+    /// The LR graph of a grammar has more states than allowed
+    /// (`grammar_index`, `error`)
+    StateLimitExceeded(usize, StateLimitError),
     /// A contextual terminal is used outside of its context
     TerminalOutsideContext(usize, ContextError),
     /// A terminal is used by the parser but cannot be produced by the lexer
@@ -96,6 +132,35 @@ pub enum Error {
     /// A terminal matches the empty string
     /// (grammar_index, terminal)
     TerminalMatchesEmpty(usize, TerminalRef),
+    /// A terminal uses a negative lookahead, which is not supported by the target runtime
+    /// (`grammar_index`, `terminal`)
+    LookaheadNotSupported(usize, TerminalRef),
+    /// A terminal is matched by an external scanner, which is not supported by the target runtime
+    /// (`grammar_index`, `terminal`)
+    ExternalTerminalNotSupported(usize, TerminalRef),
+    /// A rule of the form `A -> B` where `B` is nullable conflicts with another alternative for `A`
+    /// (`grammar_index`, `rule`, `conflicting_rule`)
+    NullableUnitRule(usize, RuleRef, RuleRef),
+    /// The axiom derives itself through a rule, making the generated axiom rule ambiguous
+    /// (`grammar_index`, `rule`, `position`)
+    AxiomReferenced(usize, RuleRef, usize),
+    /// A lexical context is declared but never opened by the parser
+    /// (`grammar_index`, `context`)
+    UnusedContext(usize, usize),
+    /// A terminal is never selected by the lexer because terminals with more priority always match instead
+    TerminalShadowed(usize, UnmatchableTokenError),
+    /// A symbol added to a grammar builder has the same name as a previous one
+    /// (`name`)
+    DuplicateSymbolName(String),
+    /// A symbol given to a grammar builder is not defined in the grammar being built
+    /// (`symbol`)
+    UnknownSymbolRef(SymbolRef),
+    /// The expression for a terminal added to a grammar builder is invalid
+    /// (`name`, `message`)
+    InvalidTerminalExpression(String, String),
+    /// A rule added to a grammar builder does not have one tree action for each symbol of its body
+    /// (`variable`, `symbols`, `actions`)
+    RuleActionsMismatch(String, usize, usize),
 }
 
 impl From<io::Error> for Error {
@@ -105,11 +170,15 @@ impl From<io::Error> for Error {
 }
 
 impl Display for Error {
+    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Io(e) => e.fmt(f),
             Self::Msg(msg) => write!(f, "{msg}"),
             Self::Parsing(_input, msg) => write!(f, "{msg}"),
+            Self::MaxTokensExceeded(_input, count) => {
+                write!(f, "Maximum number of tokens exceeded ({count})")
+            }
             Self::GrammarNotSpecified => write!(f, "The target grammar was not specified"),
             Self::GrammarNotFound(name) => write!(f, "Cannot find grammar `{name}`"),
             Self::InvalidOption(_grammar_index, name, _valid) => {
@@ -119,6 +188,9 @@ impl Display for Error {
                 write!(f, "Grammar axiom has not been specified")
             }
             Error::AxiomNotDefined(_grammar_index) => write!(f, "Grammar axiom is not defined"),
+            Error::EntryNotDefined(_grammar_index, name) => {
+                write!(f, "Grammar entry point `{name}` is not defined")
+            }
             Error::SeparatorNotDefined(_grammar_index) => {
                 write!(f, "Grammar separator token is not defined",)
             }
@@ -136,9 +208,31 @@ impl Display for Error {
                 "Template expected {expected} arguments, {provided} given"
             ),
             Self::SymbolNotFound(_input, name) => write!(f, "Cannot find symbol `{name}`"),
+            Self::RecursiveTerminalReference(_input, name) => write!(
+                f,
+                "Terminal `{name}` refers to itself, terminals cannot be recursive"
+            ),
             Self::InvalidCharacterSpan(_input) => {
                 write!(f, "Invalid character span, swap left and right bounds")
             }
+            Self::InvalidLookahead(_input) => {
+                write!(
+                    f,
+                    "Invalid lookahead, only single characters can be excluded"
+                )
+            }
+            Self::AnnotationWithoutSymbol(_input, key) => {
+                write!(
+                    f,
+                    "Annotation `@{key}` is not followed by the declaration of a terminal or a variable"
+                )
+            }
+            Self::InvalidExternalScanner(_input, name) => {
+                write!(
+                    f,
+                    "Invalid external scanner `{name}`, expected the path to a Rust function"
+                )
+            }
             Self::UnknownUnicodeBlock(_input, name) => {
                 write!(f, "Unknown unicode block `{name}`")
             }
@@ -158,9 +252,33 @@ impl Display for Error {
             Self::OverridingPreviousTerminal(_input, name, _previous) => {
                 write!(f, "Overriding the previous definition of `{name}`")
             }
+            Self::TerminalInSeveralGroups(_input, name, _previous) => {
+                write!(f, "Terminal `{name}` is already in another priority group")
+            }
             Self::GrammarNotDefined(_input, name) => {
                 write!(f, "Grammar `{name}` is not defined")
             }
+            Self::DuplicateGrammar(_input, name, _other) => {
+                write!(f, "Grammar `{name}` is declared multiple times")
+            }
+            Self::RedefinedTerminal(_input, name, _previous) => {
+                write!(
+                    f,
+                    "Terminal `{name}` redefines an inherited terminal with a different pattern"
+                )
+            }
+            Self::RedefinedVariable(_input, name, _previous) => {
+                write!(
+                    f,
+                    "Variable `{name}` redefines an inherited variable with different alternatives"
+                )
+            }
+            Self::ConflictingInheritedSymbol(_input, name, _definition, _previous) => {
+                write!(
+                    f,
+                    "Symbol `{name}` is defined differently by inherited grammars"
+                )
+            }
             Self::LrConflict(_grammar_index, conflict) => {
                 write!(
                     f,
@@ -169,7 +287,11 @@ impl Display for Error {
                         ConflictKind::ShiftReduce => "Shift/Reduce",
                         ConflictKind::ReduceReduce => "Reduce/Reduce",
                     }
-                )
+                )?;
+                if conflict.entry.is_some() {
+                    write!(f, " from an entry point")?;
+                }
+                Ok(())
             }
             Self::TerminalOutsideContext(_grammar_index, _error) => {
                 write!(f, "Contextual terminal is expected outside its context")
@@ -180,6 +302,56 @@ impl Display for Error {
             Self::TerminalMatchesEmpty(_grammar_index, _terminal_ref) => {
                 write!(f, "Terminal matches empty string, which is not allowed",)
             }
+            Self::LookaheadNotSupported(_grammar_index, _terminal_ref) => {
+                write!(
+                    f,
+                    "Terminal uses a lookahead, which is only supported for Rust"
+                )
+            }
+            Self::ExternalTerminalNotSupported(_grammar_index, _terminal_ref) => {
+                write!(
+                    f,
+                    "Terminal is matched by an external scanner, which is only supported for Rust"
+                )
+            }
+            Self::NullableUnitRule(_grammar_index, _rule, _conflicting) => {
+                write!(
+                    f,
+                    "Rule may match the empty string through a nullable variable"
+                )
+            }
+            Self::AxiomReferenced(_grammar_index, _rule, _position) => {
+                write!(f, "Axiom derives itself through a rule")
+            }
+            Self::UnusedContext(_grammar_index, _context) => {
+                write!(f, "Lexical context is never opened")
+            }
+            Self::TerminalShadowed(_grammar_index, _error) => {
+                write!(
+                    f,
+                    "Terminal is always shadowed by another one and can never be matched"
+                )
+            }
+            Self::UnexpectedConflictCount(_grammar_index, expected, found) => {
+                write!(
+                    f,
+                    "Expected {expected} shift/reduce conflict(s) but found {found}"
+                )
+            }
+            Self::StateLimitExceeded(_grammar_index, error) => {
+                write!(f, "LR graph has more than {} states", error.limit)
+            }
+            Self::DuplicateSymbolName(name) => write!(f, "Symbol `{name}` is already defined"),
+            Self::UnknownSymbolRef(symbol) => {
+                write!(f, "Symbol {symbol:?} is not defined in the grammar")
+            }
+            Self::InvalidTerminalExpression(name, message) => {
+                write!(f, "Invalid expression for terminal `{name}`: {message}")
+            }
+            Self::RuleActionsMismatch(name, symbols, actions) => write!(
+                f,
+                "Rule for `{name}` has {symbols} symbol(s) but {actions} tree action(s)"
+            ),
         }
     }
 }
@@ -205,6 +377,52 @@ impl Error {
             error: self,
         }
     }
+
+    /// Gets the index of the grammar this error is about, if it is about a single grammar
+    #[must_use]
+    pub fn get_grammar_index(&self) -> Option<usize> {
+        match self {
+            Error::InvalidOption(index, _, _)
+            | Error::AxiomNotSpecified(index)
+            | Error::AxiomNotDefined(index)
+            | Error::EntryNotDefined(index, _)
+            | Error::SeparatorNotDefined(index)
+            | Error::SeparatorIsContextual(index, _)
+            | Error::SeparatorCannotBeMatched(index, _)
+            | Error::LrConflict(index, _)
+            | Error::UnexpectedConflictCount(index, _, _)
+            | Error::StateLimitExceeded(index, _)
+            | Error::TerminalOutsideContext(index, _)
+            | Error::TerminalCannotBeMatched(index, _)
+            | Error::TerminalMatchesEmpty(index, _)
+            | Error::LookaheadNotSupported(index, _)
+            | Error::ExternalTerminalNotSupported(index, _)
+            | Error::NullableUnitRule(index, _, _)
+            | Error::AxiomReferenced(index, _, _)
+            | Error::UnusedContext(index, _)
+            | Error::TerminalShadowed(index, _) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Gets whether this error reports the same problem as another one,
+    /// regardless of the LR state in which it was found
+    #[must_use]
+    pub fn is_similar(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::LrConflict(_, conflict), Error::LrConflict(_, other)) => {
+                conflict.is_similar(other)
+            }
+            (Error::TerminalOutsideContext(_, error), Error::TerminalOutsideContext(_, other)) => {
+                error.terminal == other.terminal
+            }
+            (
+                Error::TerminalCannotBeMatched(_, error),
+                Error::TerminalCannotBeMatched(_, other),
+            ) => error.terminal == other.terminal,
+            _ => false,
+        }
+    }
 }
 
 /// An error associated to its contextual data
@@ -223,6 +441,9 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
             Error::Io(err) => err.fmt(f),
             Error::Msg(msg) => write!(f, "{msg}"),
             Error::Parsing(_input, msg) => write!(f, "{msg}"),
+            Error::MaxTokensExceeded(_input, count) => {
+                write!(f, "Maximum number of tokens exceeded ({count})")
+            }
             Error::GrammarNotSpecified => write!(f, "The target grammar was not specified"),
             Error::GrammarNotFound(name) => write!(f, "Cannot find grammar `{name}`"),
             Error::InvalidOption(_grammar_index, name, _valid) => {
@@ -237,6 +458,9 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
                     .unwrap();
                 write!(f, "Grammar axiom `{}` is not defined", &option.value)
             }
+            Error::EntryNotDefined(_grammar_index, name) => {
+                write!(f, "Grammar entry point `{name}` is not defined")
+            }
             Error::SeparatorNotDefined(grammar_index) => {
                 let option = self.context.grammars[*grammar_index]
                     .get_option(OPTION_SEPARATOR)
@@ -277,9 +501,31 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
                 "Template expected {expected} arguments, {provided} given"
             ),
             Error::SymbolNotFound(_input, name) => write!(f, "Cannot find symbol `{name}`"),
+            Error::RecursiveTerminalReference(_input, name) => write!(
+                f,
+                "Terminal `{name}` refers to itself, terminals cannot be recursive"
+            ),
             Error::InvalidCharacterSpan(_input) => {
                 write!(f, "Invalid character span, swap left and right bounds")
             }
+            Error::InvalidLookahead(_input) => {
+                write!(
+                    f,
+                    "Invalid lookahead, only single characters can be excluded"
+                )
+            }
+            Error::AnnotationWithoutSymbol(_input, key) => {
+                write!(
+                    f,
+                    "Annotation `@{key}` is not followed by the declaration of a terminal or a variable"
+                )
+            }
+            Error::InvalidExternalScanner(_input, name) => {
+                write!(
+                    f,
+                    "Invalid external scanner `{name}`, expected the path to a Rust function"
+                )
+            }
             Error::UnknownUnicodeBlock(_input, name) => {
                 write!(f, "Unknown unicode block `{name}`")
             }
@@ -299,12 +545,36 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
             Error::OverridingPreviousTerminal(_input, name, _previous) => {
                 write!(f, "Overriding the previous definition of `{name}`")
             }
+            Error::TerminalInSeveralGroups(_input, name, _previous) => {
+                write!(f, "Terminal `{name}` is already in another priority group")
+            }
             Error::GrammarNotDefined(_input, name) => {
                 write!(f, "Grammar `{name}` is not defined")
             }
+            Error::DuplicateGrammar(_input, name, _other) => {
+                write!(f, "Grammar `{name}` is declared multiple times")
+            }
+            Error::RedefinedTerminal(_input, name, _previous) => {
+                write!(
+                    f,
+                    "Terminal `{name}` redefines an inherited terminal with a different pattern"
+                )
+            }
+            Error::RedefinedVariable(_input, name, _previous) => {
+                write!(
+                    f,
+                    "Variable `{name}` redefines an inherited variable with different alternatives"
+                )
+            }
+            Error::ConflictingInheritedSymbol(_input, name, _definition, _previous) => {
+                write!(
+                    f,
+                    "Symbol `{name}` is defined differently by inherited grammars"
+                )
+            }
             Error::LrConflict(grammar_index, conflict) => {
                 let grammar = &self.context.grammars[*grammar_index];
-                let terminal = grammar.get_symbol_value(conflict.lookahead.terminal.into());
+                let terminal = conflict.lookahead.terminal.name_in(grammar);
                 write!(
                     f,
                     "{} conflict, cannot decide what to do facing `{}`",
@@ -313,11 +583,42 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
                         ConflictKind::ReduceReduce => "Reduce/Reduce",
                     },
                     terminal
+                )?;
+                let contexts = conflict.get_context_names(grammar);
+                if !contexts.is_empty() {
+                    write!(
+                        f,
+                        " in context{} `{}`",
+                        if contexts.len() > 1 { "s" } else { "" },
+                        contexts.join("`, `")
+                    )?;
+                }
+                if let Some(entry) = conflict.entry {
+                    write!(
+                        f,
+                        " when parsing from entry point `{}`",
+                        grammar.get_symbol_name(SymbolRef::Variable(entry))
+                    )?;
+                }
+                Ok(())
+            }
+            Error::UnexpectedConflictCount(grammar_index, expected, found) => {
+                write!(
+                    f,
+                    "Expected {expected} shift/reduce conflict(s) in grammar `{}` but found {found}",
+                    &self.context.grammars[*grammar_index].name
+                )
+            }
+            Error::StateLimitExceeded(grammar_index, error) => {
+                write!(
+                    f,
+                    "LR graph of grammar `{}` has more than {} states with method {:?}",
+                    &self.context.grammars[*grammar_index].name, error.limit, error.method
                 )
             }
             Error::TerminalOutsideContext(grammar_index, error) => {
                 let grammar = &self.context.grammars[*grammar_index];
-                let terminal = grammar.get_symbol_value(error.terminal.into());
+                let terminal = error.terminal.name_in(grammar);
                 write!(
                     f,
                     "Contextual terminal `{terminal}` is expected outside its context"
@@ -333,6 +634,78 @@ impl<'context, 'error, 't> Display for ContextualizedError<'context, 'error, 't>
                     &terminal.name
                 )
             }
+            Error::LookaheadNotSupported(grammar_index, terminal_ref) => {
+                let terminal = self.context.grammars[*grammar_index]
+                    .get_terminal(terminal_ref.sid())
+                    .unwrap();
+                write!(
+                    f,
+                    "Terminal `{}` uses a lookahead, which is only supported for Rust",
+                    &terminal.name
+                )
+            }
+            Error::ExternalTerminalNotSupported(grammar_index, terminal_ref) => {
+                let terminal = self.context.grammars[*grammar_index]
+                    .get_terminal(terminal_ref.sid())
+                    .unwrap();
+                write!(
+                    f,
+                    "Terminal `{}` is matched by an external scanner, which is only supported for Rust",
+                    &terminal.name
+                )
+            }
+            Error::NullableUnitRule(grammar_index, rule_ref, _conflicting) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                let rule = rule_ref.get_rule_in(grammar);
+                write!(
+                    f,
+                    "Rule for `{}` may match the empty string through nullable variable `{}`",
+                    grammar.get_symbol_name(SymbolRef::Variable(rule.head)),
+                    grammar.get_symbol_name(rule.body.choices[0].elements[0].symbol)
+                )
+            }
+            Error::AxiomReferenced(grammar_index, rule_ref, _position) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                let rule = rule_ref.get_rule_in(grammar);
+                write!(
+                    f,
+                    "Axiom `{}` derives itself through a rule for `{}`",
+                    grammar.get_axiom().unwrap_or_default(),
+                    grammar.get_symbol_name(SymbolRef::Variable(rule.head))
+                )
+            }
+            Error::UnusedContext(grammar_index, context) => {
+                write!(
+                    f,
+                    "Lexical context `{}` is never opened, its terminals can never be matched",
+                    &self.context.grammars[*grammar_index].contexts[*context]
+                )
+            }
+            Error::DuplicateSymbolName(_)
+            | Error::UnknownSymbolRef(_)
+            | Error::InvalidTerminalExpression(_, _)
+            | Error::RuleActionsMismatch(_, _, _) => self.error.fmt(f),
+            Error::TerminalShadowed(grammar_index, error) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                write!(
+                    f,
+                    "Terminal `{}` can never be matched",
+                    error.terminal.name_in(grammar)
+                )?;
+                if !error.overriders.is_empty() {
+                    write!(
+                        f,
+                        ", it is always shadowed by {}",
+                        error
+                            .overriders
+                            .iter()
+                            .map(|terminal| format!("`{}`", terminal.name_in(grammar)))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )?;
+                }
+                Ok(())
+            }
         }
     }
 }
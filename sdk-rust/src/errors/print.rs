@@ -24,7 +24,7 @@ use miette::{
 };
 
 use super::{ContextualizedError, Error};
-use crate::grammars::{OPTION_AXIOM, OPTION_SEPARATOR};
+use crate::grammars::{SymbolRef, OPTION_AXIOM, OPTION_ENTRIES, OPTION_SEPARATOR};
 use crate::lr::LookaheadOrigin;
 use crate::{InputReference, LoadedInput};
 
@@ -166,17 +166,31 @@ impl<'context, 'error, 't> ContextualizedError<'context, 'error, 't> {
 
 impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error, 't> {
     fn severity(&self) -> Option<Severity> {
-        Some(Severity::Error)
+        match &self.error {
+            Error::NullableUnitRule(_grammar_index, _rule, _conflicting) => Some(Severity::Advice),
+            Error::UnusedContext(_, _)
+            | Error::AxiomReferenced(_, _, _)
+            | Error::RedefinedTerminal(_, _, _)
+            | Error::TerminalShadowed(_, _) => Some(Severity::Warning),
+            Error::RedefinedVariable(_, _, _) => Some(Severity::Advice),
+            _ => Some(Severity::Error),
+        }
     }
 
-    #[allow(clippy::match_same_arms)]
+    #[allow(clippy::match_same_arms, clippy::too_many_lines)]
     fn source_code(&self) -> Option<&dyn SourceCode> {
         match &self.error {
             Error::Io(_) => None,
             Error::Msg(_) => None,
             Error::GrammarNotSpecified => None,
             Error::GrammarNotFound(_) => None,
-            Error::Parsing(input, _) => Some(&self.context.inputs[input.input_index]),
+            Error::DuplicateSymbolName(_)
+            | Error::UnknownSymbolRef(_)
+            | Error::InvalidTerminalExpression(_, _)
+            | Error::RuleActionsMismatch(_, _, _) => None,
+            Error::Parsing(input, _) | Error::MaxTokensExceeded(input, _) => {
+                Some(&self.context.inputs[input.input_index])
+            }
             Error::InvalidOption(grammar_index, _name, _valid) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
@@ -186,6 +200,9 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
             Error::AxiomNotDefined(grammar_index) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
+            Error::EntryNotDefined(grammar_index, _name) => {
+                Some(self.get_source_code_for_grammar(*grammar_index))
+            }
             Error::SeparatorNotDefined(grammar_index) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
@@ -202,7 +219,17 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                 Some(&self.context.inputs[input.input_index])
             }
             Error::SymbolNotFound(input, _name) => Some(&self.context.inputs[input.input_index]),
+            Error::RecursiveTerminalReference(input, _name) => {
+                Some(&self.context.inputs[input.input_index])
+            }
             Error::InvalidCharacterSpan(input) => Some(&self.context.inputs[input.input_index]),
+            Error::InvalidLookahead(input) => Some(&self.context.inputs[input.input_index]),
+            Error::AnnotationWithoutSymbol(input, _key) => {
+                Some(&self.context.inputs[input.input_index])
+            }
+            Error::InvalidExternalScanner(input, _name) => {
+                Some(&self.context.inputs[input.input_index])
+            }
             Error::UnknownUnicodeBlock(input, _name) => {
                 Some(&self.context.inputs[input.input_index])
             }
@@ -216,19 +243,46 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
             Error::OverridingPreviousTerminal(input, _name, _previous) => {
                 Some(&self.context.inputs[input.input_index])
             }
+            Error::TerminalInSeveralGroups(input, _name, _previous) => {
+                Some(&self.context.inputs[input.input_index])
+            }
             Error::GrammarNotDefined(input, _name) => Some(&self.context.inputs[input.input_index]),
+            Error::DuplicateGrammar(input, _name, _other) => {
+                Some(&self.context.inputs[input.input_index])
+            }
+            Error::RedefinedTerminal(input, _, _)
+            | Error::RedefinedVariable(input, _, _)
+            | Error::ConflictingInheritedSymbol(input, _, _, _) => {
+                Some(&self.context.inputs[input.input_index])
+            }
             Error::LrConflict(grammar_index, _conflict) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
+            Error::UnexpectedConflictCount(grammar_index, _, _)
+            | Error::StateLimitExceeded(grammar_index, _) => {
+                Some(self.get_source_code_for_grammar(*grammar_index))
+            }
             Error::TerminalOutsideContext(grammar_index, _error) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
-            Error::TerminalCannotBeMatched(grammar_index, _error) => {
+            Error::TerminalCannotBeMatched(grammar_index, _error)
+            | Error::TerminalShadowed(grammar_index, _error) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
             Error::TerminalMatchesEmpty(grammar_index, _terminal_ref) => {
                 Some(self.get_source_code_for_grammar(*grammar_index))
             }
+            Error::LookaheadNotSupported(grammar_index, _terminal_ref)
+            | Error::ExternalTerminalNotSupported(grammar_index, _terminal_ref) => {
+                Some(self.get_source_code_for_grammar(*grammar_index))
+            }
+            Error::NullableUnitRule(grammar_index, _, _)
+            | Error::AxiomReferenced(grammar_index, _, _) => {
+                Some(self.get_source_code_for_grammar(*grammar_index))
+            }
+            Error::UnusedContext(grammar_index, _context) => {
+                Some(self.get_source_code_for_grammar(*grammar_index))
+            }
         }
     }
 
@@ -239,7 +293,13 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
             Error::Msg(_) => Some(self.get_single_label_no_input()),
             Error::GrammarNotSpecified => Some(self.get_single_label_no_input()),
             Error::GrammarNotFound(_) => Some(self.get_single_label_no_input()),
-            Error::Parsing(input, _) => Some(self.get_single_label_with_input(input)),
+            Error::DuplicateSymbolName(_)
+            | Error::UnknownSymbolRef(_)
+            | Error::InvalidTerminalExpression(_, _)
+            | Error::RuleActionsMismatch(_, _, _) => Some(self.get_single_label_no_input()),
+            Error::Parsing(input, _) | Error::MaxTokensExceeded(input, _) => {
+                Some(self.get_single_label_with_input(input))
+            }
             Error::InvalidOption(grammar_index, name, _valid) => {
                 let option = self.context.grammars[*grammar_index]
                     .get_option(name)
@@ -255,6 +315,12 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                     .unwrap();
                 Some(self.get_single_label_with_input(&option.value_input_ref))
             }
+            Error::EntryNotDefined(grammar_index, _name) => {
+                let option = self.context.grammars[*grammar_index]
+                    .get_option(OPTION_ENTRIES)
+                    .unwrap();
+                Some(self.get_single_label_with_input(&option.value_input_ref))
+            }
             Error::SeparatorNotDefined(grammar_index) => {
                 let option = self.context.grammars[*grammar_index]
                     .get_option(OPTION_SEPARATOR)
@@ -288,7 +354,17 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                 Some(self.get_single_label_with_input(input))
             }
             Error::SymbolNotFound(input, _name) => Some(self.get_single_label_with_input(input)),
+            Error::RecursiveTerminalReference(input, _name) => {
+                Some(self.get_single_label_with_input(input))
+            }
             Error::InvalidCharacterSpan(input) => Some(self.get_single_label_with_input(input)),
+            Error::InvalidLookahead(input) => Some(self.get_single_label_with_input(input)),
+            Error::AnnotationWithoutSymbol(input, _key) => {
+                Some(self.get_single_label_with_input(input))
+            }
+            Error::InvalidExternalScanner(input, _name) => {
+                Some(self.get_single_label_with_input(input))
+            }
             Error::UnknownUnicodeBlock(input, _name) => {
                 Some(self.get_single_label_with_input(input))
             }
@@ -309,14 +385,57 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                 ]
                 .into_iter(),
             )),
+            Error::TerminalInSeveralGroups(input, name, previous) => Some(Box::new(
+                vec![
+                    self.label_for_input(input),
+                    self.label_for_input_with_text(previous, format!("previous group of {name}")),
+                ]
+                .into_iter(),
+            )),
             Error::GrammarNotDefined(input, _name) => Some(self.get_single_label_with_input(input)),
+            Error::DuplicateGrammar(input, name, other) => Some(Box::new(
+                vec![
+                    self.label_for_input(input),
+                    self.label_for_input_with_text(other, format!("other declaration of {name}")),
+                ]
+                .into_iter(),
+            )),
+            Error::RedefinedTerminal(input, name, previous)
+            | Error::RedefinedVariable(input, name, previous) => Some(Box::new(
+                vec![
+                    self.label_for_input(input),
+                    self.label_for_input_with_text(
+                        previous,
+                        format!("inherited definition of {name}"),
+                    ),
+                ]
+                .into_iter(),
+            )),
+            Error::ConflictingInheritedSymbol(input, name, definition, previous) => Some(Box::new(
+                vec![
+                    self.label_for_input(input),
+                    self.label_for_input_with_text(
+                        definition,
+                        format!("definition of {name} in this parent"),
+                    ),
+                    self.label_for_input_with_text(
+                        previous,
+                        format!("definition of {name} in a previous parent"),
+                    ),
+                ]
+                .into_iter(),
+            )),
+            Error::UnexpectedConflictCount(grammar_index, _, _)
+            | Error::StateLimitExceeded(grammar_index, _) => {
+                Some(self.get_single_label_with_grammar(*grammar_index))
+            }
             Error::LrConflict(grammar_index, conflict) => {
                 let grammar = &self.context.grammars[*grammar_index];
                 let mut labels = Vec::new();
                 for item in &conflict.shift_items {
                     let rule = item.rule.get_rule_in(grammar);
                     let choice = &rule.body.choices[0];
-                    let value = grammar.get_symbol_value(conflict.lookahead.terminal.into());
+                    let value = conflict.lookahead.terminal.name_in(grammar);
                     let input_ref = choice.elements[item.position].input_ref.unwrap();
                     labels.push(self.label_for_input_with_text(
                         &input_ref,
@@ -327,7 +446,7 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                     let rule = item.rule.get_rule_in(grammar);
                     let choice = &rule.body.choices[0];
                     let lookahead = item.lookaheads.get(conflict.lookahead.terminal).unwrap();
-                    let value = grammar.get_symbol_value(conflict.lookahead.terminal.into());
+                    let value = conflict.lookahead.terminal.name_in(grammar);
                     if choice.elements.is_empty() {
                         // do not display this choice
                     } else if item.position >= choice.elements.len() {
@@ -377,7 +496,8 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                 }
                 Some(Box::new(labels.into_iter()))
             }
-            Error::TerminalCannotBeMatched(grammar_index, error) => {
+            Error::TerminalCannotBeMatched(grammar_index, error)
+            | Error::TerminalShadowed(grammar_index, error) => {
                 let grammar = &self.context.grammars[*grammar_index];
                 let separator = grammar.get_terminal(error.terminal.sid()).unwrap();
                 let mut labels = vec![self.label_for_input(&separator.input_ref)];
@@ -390,13 +510,61 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                 }
                 Some(Box::new(labels.into_iter()))
             }
-            Error::TerminalMatchesEmpty(grammar_index, terminal_ref) => {
+            Error::TerminalMatchesEmpty(grammar_index, terminal_ref)
+            | Error::LookaheadNotSupported(grammar_index, terminal_ref)
+            | Error::ExternalTerminalNotSupported(grammar_index, terminal_ref) => {
                 let input = &self.context.grammars[*grammar_index]
                     .get_terminal(terminal_ref.sid())
                     .unwrap()
                     .input_ref;
                 Some(self.get_single_label_with_input(input))
             }
+            Error::NullableUnitRule(grammar_index, rule_ref, conflicting_ref) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                let rule = rule_ref.get_rule_in(grammar);
+                let conflicting = conflicting_ref.get_rule_in(grammar);
+                let mut labels = vec![self.label_for_input(
+                    &rule.body.choices[0].elements[0]
+                        .input_ref
+                        .unwrap_or(rule.head_input_ref),
+                )];
+                let conflicting_input = conflicting.body.choices[0]
+                    .elements
+                    .first()
+                    .and_then(|element| element.input_ref)
+                    .unwrap_or(conflicting.head_input_ref);
+                labels.push(self.label_for_input_with_text(
+                    &conflicting_input,
+                    String::from("Conflicting alternative"),
+                ));
+                Some(Box::new(labels.into_iter()))
+            }
+            Error::AxiomReferenced(grammar_index, rule_ref, position) => {
+                let rule = rule_ref.get_rule_in(&self.context.grammars[*grammar_index]);
+                let input = rule.body.elements[*position]
+                    .input_ref
+                    .unwrap_or(rule.head_input_ref);
+                Some(self.get_single_label_with_input(&input))
+            }
+            Error::UnusedContext(grammar_index, context) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                let labels: Vec<LabeledSpan> = grammar
+                    .terminals
+                    .iter()
+                    .filter(|terminal| terminal.context == *context)
+                    .map(|terminal| {
+                        self.label_for_input_with_text(
+                            &terminal.input_ref,
+                            format!("{} can never be matched", terminal.value),
+                        )
+                    })
+                    .collect();
+                if labels.is_empty() {
+                    Some(self.get_single_label_with_input(&grammar.input_ref))
+                } else {
+                    Some(Box::new(labels.into_iter()))
+                }
+            }
         }
     }
 
@@ -419,12 +587,42 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                         conflict.phrases[0]
                             .0
                             .iter()
-                            .map(|s| grammar.get_symbol_value((*s).into()))
+                            .map(|s| s.name_in(grammar))
                             .collect::<Vec<_>>()
                             .join(" ")
                     )))
                 }
             }
+            Error::UnexpectedConflictCount(_grammar_index, expected, found) => {
+                Some(Box::new(if found > expected {
+                    "New shift/reduce conflicts were introduced, they are reported above"
+                        .to_string()
+                } else {
+                    format!("Lower the expected number of shift/reduce conflicts to {found}")
+                }))
+            }
+            Error::AxiomReferenced(_grammar_index, _rule, _position) => Some(Box::new(
+                "The axiom is wrapped in a generated rule matching it once, followed by the end of the input; \
+                 as the axiom derives itself, this rule is ambiguous",
+            )),
+            Error::StateLimitExceeded(grammar_index, error) => {
+                let grammar = &self.context.grammars[*grammar_index];
+                let heads = error
+                    .heads
+                    .iter()
+                    .take(5)
+                    .map(|(variable, count)| {
+                        format!(
+                            "`{}` ({count})",
+                            grammar.get_symbol_name(SymbolRef::Variable(*variable))
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some(Box::new(format!(
+                    "The most recently created states are mostly for the variables: {heads}"
+                )))
+            }
             Error::TerminalOutsideContext(grammar_index, error) => {
                 if error.phrases.is_empty() {
                     None
@@ -435,7 +633,7 @@ impl<'context, 'error, 't> Diagnostic for ContextualizedError<'context, 'error,
                         error.phrases[0]
                             .0
                             .iter()
-                            .map(|s| grammar.get_symbol_value((*s).into()))
+                            .map(|s| s.name_in(grammar))
                             .collect::<Vec<_>>()
                             .join(" ")
                     )))
@@ -26,13 +26,25 @@ use crate::{CharSpan, CHARSPAN_INVALID};
 /// Represents the value epsilon on NFA transitions
 pub const EPSILON: CharSpan = CHARSPAN_INVALID;
 
+/// Represents the condition on the input following a terminal for it to be matched
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum MatchCondition {
+    /// The terminal is matched whatever follows
+    #[default]
+    Always,
+    /// The terminal is only matched at the end of the input
+    AtEnd,
+    /// The terminal is matched with one trailing lookahead character that is not part of it
+    BeforeLookahead,
+}
+
 /// Represents a marker for the final state of an automaton
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum FinalItem {
     /// Represents a fake marker of a final state in an automaton
     Dummy,
-    /// A terminal symbol in a grammar
-    Terminal(usize, usize),
+    /// A terminal symbol in a grammar, with its context and the condition on what follows
+    Terminal(usize, usize, MatchCondition),
 }
 
 impl FinalItem {
@@ -43,7 +55,7 @@ impl FinalItem {
     /// Panic when the final item does not reference a terminal
     #[must_use]
     pub fn sid(self) -> usize {
-        if let FinalItem::Terminal(id, _) = self {
+        if let FinalItem::Terminal(id, _, _) = self {
             id
         } else {
             panic!("This final item does not reference a terminal")
@@ -55,7 +67,16 @@ impl FinalItem {
     pub fn priority(self) -> usize {
         match self {
             FinalItem::Dummy => 0,
-            FinalItem::Terminal(id, _) => id,
+            FinalItem::Terminal(id, _, _) => id,
+        }
+    }
+
+    /// Gets the condition on the input following this item
+    #[must_use]
+    pub fn condition(self) -> MatchCondition {
+        match self {
+            FinalItem::Dummy => MatchCondition::Always,
+            FinalItem::Terminal(_, _, condition) => condition,
         }
     }
 
@@ -68,7 +89,7 @@ impl FinalItem {
     pub fn from(terminal: TerminalRef, context: usize) -> FinalItem {
         match terminal {
             TerminalRef::Dummy => FinalItem::Dummy,
-            TerminalRef::Terminal(id) => FinalItem::Terminal(id, context),
+            TerminalRef::Terminal(id) => FinalItem::Terminal(id, context, MatchCondition::Always),
             _ => panic!("Cannot turn this terminal into a DFA final item"),
         }
     }
@@ -76,7 +97,14 @@ impl FinalItem {
 
 impl Ord for FinalItem {
     fn cmp(&self, other: &FinalItem) -> Ordering {
-        other.priority().cmp(&self.priority())
+        // the match before a lookahead is one character shorter than the others in the same state,
+        // so that it comes last whatever the priority of its terminal
+        let before_lookahead =
+            |item: &FinalItem| item.condition() == MatchCondition::BeforeLookahead;
+        before_lookahead(self)
+            .cmp(&before_lookahead(other))
+            .then_with(|| other.priority().cmp(&self.priority()))
+            .then_with(|| self.condition().cmp(&other.condition()))
     }
 }
 
@@ -90,7 +118,7 @@ impl From<FinalItem> for TerminalRef {
     fn from(final_item: FinalItem) -> Self {
         match final_item {
             FinalItem::Dummy => TerminalRef::Dummy,
-            FinalItem::Terminal(id, _) => TerminalRef::Terminal(id),
+            FinalItem::Terminal(id, _, _) => TerminalRef::Terminal(id),
         }
     }
 }
@@ -158,6 +186,28 @@ impl DFAState {
         self.items.clear();
     }
 
+    /// Gets the items that can be selected by a lexer in this state, in order of priority
+    /// In each context, the items after the first one without condition are shadowed.
+    #[must_use]
+    pub fn get_selectable_items(&self) -> Vec<FinalItem> {
+        let mut closed = Vec::new();
+        let mut result = Vec::new();
+        for item in &self.items {
+            let context = match item {
+                FinalItem::Dummy => 0,
+                FinalItem::Terminal(_, context, _) => *context,
+            };
+            if closed.contains(&context) {
+                continue;
+            }
+            if item.condition() == MatchCondition::Always {
+                closed.push(context);
+            }
+            result.push(*item);
+        }
+        result
+    }
+
     /// Gets the child state by the specified transition
     #[must_use]
     pub fn get_child_by(&self, value: CharSpan) -> Option<usize> {
@@ -390,13 +440,9 @@ impl DFA {
         expected.add(TerminalRef::Epsilon);
         expected.add(TerminalRef::Dollar);
         for state in &self.states {
-            let mut contexts = Vec::new();
-            for item in &state.items {
-                if let FinalItem::Terminal(id, context) = item {
-                    if !contexts.contains(context) {
-                        contexts.push(*context);
-                        expected.add(TerminalRef::Terminal(*id));
-                    }
+            for item in state.get_selectable_items() {
+                if let FinalItem::Terminal(id, _, _) = item {
+                    expected.add(TerminalRef::Terminal(id));
                 }
             }
         }
@@ -408,15 +454,15 @@ impl DFA {
     #[must_use]
     pub fn get_overriders(&self, terminal: TerminalRef, context: usize) -> Vec<TerminalRef> {
         let mut overriders = TerminalSet::default();
-        let terminal_final = FinalItem::from(terminal, context);
+        let is_terminal_final = |item: &FinalItem| matches!(item, FinalItem::Terminal(id, c, _) if TerminalRef::Terminal(*id) == terminal && *c == context);
         for state in &self.states {
-            if state.items.contains(&terminal_final) {
+            if state.items.iter().any(is_terminal_final) {
                 // separator is final of this state
                 for item in &state.items {
-                    if item == &terminal_final {
+                    if is_terminal_final(item) {
                         break;
                     }
-                    if let FinalItem::Terminal(id, c) = item {
+                    if let FinalItem::Terminal(id, c, MatchCondition::Always) = item {
                         if *c == context {
                             // this final item has more priority than the separator
                             overriders.add(TerminalRef::Terminal(*id));
@@ -835,18 +881,21 @@ impl NFAState {
             let (starts, ends) = bound.count_starts_ends();
 
             // end all ongoing ranges
-            for &(_tid, next) in &current_nexts {
-                transitions.push(NFATransition {
-                    value: CharSpan::new(
-                        current_start,
-                        if starts == 0 {
-                            bound.value
-                        } else {
-                            bound.value - 1
-                        },
-                    ),
-                    next,
-                });
+            // when a start immediately follows an end, the ongoing ranges are already closed
+            if starts == 0 || current_start < bound.value {
+                for &(_tid, next) in &current_nexts {
+                    transitions.push(NFATransition {
+                        value: CharSpan::new(
+                            current_start,
+                            if starts == 0 {
+                                bound.value
+                            } else {
+                                bound.value - 1
+                            },
+                        ),
+                        next,
+                    });
+                }
             }
             let ongoings = current_nexts
                 .iter()
@@ -1245,6 +1294,48 @@ mod tests_nfa_normalize {
             ]
         )
     }
+
+    #[test]
+    fn test_overlap_adjacent_end_start() {
+        let mut state = NFAState::new(0);
+        state.add_transition(CharSpan::new(0, 20), 1);
+        state.add_transition(CharSpan::new(5, 10), 2);
+        state.add_transition(CharSpan::new(11, 15), 3);
+        let mut map = Vec::new();
+        state.fill_bounds_map(&mut map);
+        let bounds = map.iter().map(|b| b.value).collect::<Vec<_>>();
+        assert_eq!(bounds, vec![0, 5, 10, 11, 15, 20]);
+        state.normalize(&map);
+        assert_eq!(
+            state.transitions,
+            vec![
+                NFATransition {
+                    next: 1,
+                    value: CharSpan::new(0, 4)
+                },
+                NFATransition {
+                    next: 1,
+                    value: CharSpan::new(5, 10)
+                },
+                NFATransition {
+                    next: 2,
+                    value: CharSpan::new(5, 10)
+                },
+                NFATransition {
+                    next: 1,
+                    value: CharSpan::new(11, 15)
+                },
+                NFATransition {
+                    next: 3,
+                    value: CharSpan::new(11, 15)
+                },
+                NFATransition {
+                    next: 1,
+                    value: CharSpan::new(16, 20)
+                },
+            ]
+        );
+    }
 }
 
 /// Represents a Non-deterministic Finite Automaton
@@ -1256,6 +1347,8 @@ pub struct NFA {
     pub entry: usize,
     /// The exit state for this automaton
     pub exit: usize,
+    /// The state reached after the negative lookahead, when the automaton ends with one
+    pub lookahead: Option<usize>,
 }
 
 impl NFA {
@@ -1266,6 +1359,7 @@ impl NFA {
             states: vec![NFAState::new(0), NFAState::new(1)],
             entry: 0,
             exit: 1,
+            lookahead: None,
         }
     }
 
@@ -1436,6 +1530,7 @@ impl NFA {
                 .collect(),
             entry: 0,
             exit: std::usize::MAX,
+            lookahead: None,
         }
     }
 
@@ -1479,6 +1574,85 @@ impl NFA {
                 .collect(),
             entry: self.entry,
             exit: self.exit,
+            lookahead: None,
+        }
+    }
+
+    /// Makes this automaton insensitive to the case of ASCII letters
+    /// The transitions on letters are duplicated for the letters in the other case.
+    #[must_use]
+    pub fn into_case_insensitive(self) -> NFA {
+        let mut result = self;
+        for state in &mut result.states {
+            let mut added = Vec::new();
+            for transition in &state.transitions {
+                for (from, to) in [
+                    (u16::from(b'a'), u16::from(b'A')),
+                    (u16::from(b'A'), u16::from(b'a')),
+                ] {
+                    let begin = transition.value.begin.max(from);
+                    let end = transition.value.end.min(from + 25);
+                    if begin <= end {
+                        added.push(NFATransition {
+                            value: CharSpan::new(begin - from + to, end - from + to),
+                            next: transition.next,
+                        });
+                    }
+                }
+            }
+            state.transitions.extend(added);
+        }
+        result
+    }
+
+    /// Ends this automaton with a negative lookahead on a single character
+    /// The lookahead matches any UTF-16 code unit that is not in the excluded spans.
+    #[must_use]
+    pub fn into_negative_lookahead(self, excluded: &[CharSpan]) -> NFA {
+        let mut result = self;
+        let target = result.add_state().id;
+        let mut begin = 0_u32;
+        let mut excluded = excluded.to_vec();
+        excluded.sort_by_key(|span| span.begin);
+        for span in excluded {
+            if u32::from(span.begin) > begin {
+                result.add_transition(
+                    result.exit,
+                    CharSpan::new(begin as u16, span.begin - 1),
+                    target,
+                );
+            }
+            begin = begin.max(u32::from(span.end) + 1);
+        }
+        if begin <= 0xFFFF {
+            result.add_transition(result.exit, CharSpan::new(begin as u16, 0xFFFF), target);
+        }
+        result.lookahead = Some(target);
+        result
+    }
+
+    /// Marks the final states of this automaton for the specified terminal
+    /// With a negative lookahead, the terminal is matched either at the end of the input,
+    /// or before a lookahead character that is allowed.
+    pub fn add_final_terminal(&mut self, id: usize, context: usize) {
+        match self.lookahead {
+            None => self.states[self.exit].add_item(FinalItem::Terminal(
+                id,
+                context,
+                MatchCondition::Always,
+            )),
+            Some(lookahead) => {
+                self.states[self.exit].add_item(FinalItem::Terminal(
+                    id,
+                    context,
+                    MatchCondition::AtEnd,
+                ));
+                self.states[lookahead].add_item(FinalItem::Terminal(
+                    id,
+                    context,
+                    MatchCondition::BeforeLookahead,
+                ));
+            }
         }
     }
 
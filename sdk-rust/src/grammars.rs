@@ -18,17 +18,19 @@
 //! Library for grammars
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter, Write};
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
+use hime_redist::lexers::automaton::RecoveryPoint;
 use hime_redist::parsers::{TreeAction, TREE_ACTION_DROP, TREE_ACTION_NONE, TREE_ACTION_PROMOTE};
 
 use crate::errors::{Error, UnmatchableTokenError};
 use crate::finite::{FinalItem, DFA, EPSILON, NFA};
-use crate::lr::Graph;
+use crate::lr::{Graph, DEFAULT_MAX_STATES};
+use crate::output::get_lexer_data_size;
 use crate::sdk::InMemoryParser;
-use crate::{InputReference, ParsingMethod};
+use crate::{CompilationProgress, InputReference, ParsingMethod};
 
 /// Represents a symbol in a grammar
 pub trait Symbol {
@@ -51,6 +53,46 @@ pub struct TerminalReference {
     pub input_ref: InputReference,
 }
 
+/// A definition of a symbol encountered while assembling a grammar and its parents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolDefinition {
+    /// The name of the grammar that holds the definition
+    pub grammar: String,
+    /// The input reference for the definition
+    pub input_ref: InputReference,
+    /// The canonical text of the definition, used to compare definitions
+    pub content: String,
+}
+
+/// A user-defined annotation on a terminal or a variable, e.g. `@category("operator")`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// The input reference for the annotation
+    pub input_ref: InputReference,
+    /// The annotation's key
+    pub key: String,
+    /// The annotation's value
+    pub value: String,
+}
+
+/// A reference to a parent grammar in the inheritance clause of a grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarReference {
+    /// The input reference for the parent's name in the clause
+    pub input_ref: InputReference,
+    /// The parent grammar's name
+    pub name: String,
+}
+
+/// Adds an annotation to a symbol's annotations, replacing any previous one with the same key
+fn set_annotation(annotations: &mut Vec<Annotation>, annotation: Annotation) {
+    if let Some(previous) = annotations.iter_mut().find(|a| a.key == annotation.key) {
+        *previous = annotation;
+    } else {
+        annotations.push(annotation);
+    }
+}
+
 /// Represents a terminal symbol in a grammar
 #[derive(Debug, Clone)]
 pub struct Terminal {
@@ -70,8 +112,20 @@ pub struct Terminal {
     pub is_anonymous: bool,
     /// Whether the terminal is a fragment
     pub is_fragment: bool,
+    /// The path to the Rust function that matches this terminal, for an external terminal
+    pub external_scanner: Option<String>,
+    /// The index of the priority group of this terminal in `Grammar::terminal_groups`, if any
+    pub group: Option<usize>,
+    /// The point at which the unterminated tokens of this terminal stop, if it is recoverable
+    pub recovery: Option<RecoveryPoint>,
+    /// The name displayed for this terminal in parse errors, if any
+    pub alias: Option<String>,
+    /// The user-defined annotations on this terminal
+    pub annotations: Vec<Annotation>,
     /// The references to this terminal by others
     pub terminal_references: Vec<TerminalReference>,
+    /// All the definitions encountered for this terminal, the last one being in effect
+    pub definitions: Vec<SymbolDefinition>,
 }
 
 impl Terminal {
@@ -80,6 +134,17 @@ impl Terminal {
     pub fn priority(&self) -> usize {
         self.id
     }
+
+    /// Gets the name displayed for this terminal in parse errors, i.e. its alias if it has one
+    #[must_use]
+    pub fn get_display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Adds an annotation to this terminal, replacing any previous one with the same key
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        set_annotation(&mut self.annotations, annotation);
+    }
 }
 
 impl Symbol for Terminal {
@@ -97,6 +162,8 @@ impl Symbol for Terminal {
     fn get_description(&self) -> String {
         if self.is_anonymous {
             format!("Inline terminal `{}`", &self.value)
+        } else if let Some(alias) = &self.alias {
+            format!("Terminal `{alias}` (alias of `{}`)", self.name)
         } else {
             format!(
                 "Terminal {}`{}`",
@@ -127,6 +194,17 @@ impl PartialOrd for Terminal {
     }
 }
 
+/// Represents a group of terminals that share the same lexical priority
+/// The priority of the group is the one of its terminal declared last.
+/// Between the terminals of a group, the longest match wins as usual.
+#[derive(Debug, Clone)]
+pub struct TerminalGroup {
+    /// The input reference for the declaration of the group
+    pub input_ref: InputReference,
+    /// The identifiers of the terminals in this group
+    pub terminals: Vec<usize>,
+}
+
 /// Represents a reference to a terminal-like
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum TerminalRef {
@@ -159,11 +237,33 @@ impl TerminalRef {
     pub fn priority(self) -> usize {
         self.sid()
     }
+
+    /// Gets the name of this terminal for display in the specified grammar
+    /// Inline terminals are named by their value, as written in the grammar.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the terminal is not in the grammar
+    #[must_use]
+    pub fn name_in(self, grammar: &Grammar) -> String {
+        match self {
+            TerminalRef::Dummy => String::from("#"),
+            TerminalRef::Epsilon => String::from("ε"),
+            TerminalRef::Dollar => String::from("$"),
+            TerminalRef::NullTerminal => String::from("∅"),
+            TerminalRef::Terminal(id) => grammar.get_terminal(id).unwrap().value.clone(),
+        }
+    }
 }
 
 impl Ord for TerminalRef {
     fn cmp(&self, other: &TerminalRef) -> Ordering {
-        self.priority().cmp(&other.priority())
+        match (self, other) {
+            // both markers share the same priority, keep the order total
+            (TerminalRef::Dummy, TerminalRef::NullTerminal) => Ordering::Less,
+            (TerminalRef::NullTerminal, TerminalRef::Dummy) => Ordering::Greater,
+            _ => self.priority().cmp(&other.priority()),
+        }
     }
 }
 
@@ -174,22 +274,12 @@ impl PartialOrd for TerminalRef {
 }
 
 /// Represents a set of unique terminals (sorted by ID)
-#[derive(Debug, Clone, Default, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct TerminalSet {
-    /// The backing content
+    /// The backing content, kept sorted
     pub content: Vec<TerminalRef>,
 }
 
-impl PartialEq for TerminalSet {
-    fn eq(&self, other: &TerminalSet) -> bool {
-        self.content.len() == other.content.len()
-            && self
-                .content
-                .iter()
-                .all(|t_ref| other.content.contains(t_ref))
-    }
-}
-
 impl TerminalSet {
     /// Creates a set with a single element
     #[must_use]
@@ -199,43 +289,89 @@ impl TerminalSet {
         }
     }
 
-    /// Gets the number of states in this automaton
+    /// Gets the number of terminals in this set
     #[must_use]
     pub fn len(&self) -> usize {
         self.content.len()
     }
 
-    /// Gets whether the NFA has no state
+    /// Gets whether this set is empty
     #[must_use]
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
 
-    /// Adds a new terminal
-    fn do_add(&mut self, item: TerminalRef) -> bool {
-        if self.content.contains(&item) {
-            false
-        } else {
-            self.content.push(item);
-            true
-        }
+    /// Gets whether this set contains the specified terminal
+    #[must_use]
+    pub fn contains(&self, item: TerminalRef) -> bool {
+        self.content.binary_search(&item).is_ok()
+    }
+
+    /// Gets an iterator over the terminals in this set
+    pub fn iter(&self) -> std::slice::Iter<'_, TerminalRef> {
+        self.content.iter()
     }
 
     /// Adds a new terminal
     pub fn add(&mut self, item: TerminalRef) -> bool {
-        let modified = self.do_add(item);
-        self.content.sort();
-        modified
+        match self.content.binary_search(&item) {
+            Ok(_) => false,
+            Err(index) => {
+                self.content.insert(index, item);
+                true
+            }
+        }
     }
 
-    /// Adds new terminals
+    /// Adds the terminals of another set to this one,
+    /// returns whether this set has been modified
+    pub fn union_with(&mut self, others: &TerminalSet) -> bool {
+        if others.content.iter().all(|item| self.contains(*item)) {
+            return false;
+        }
+        let mut result = Vec::with_capacity(self.content.len() + others.content.len());
+        let mut others = others.content.iter().copied().peekable();
+        for &item in &self.content {
+            while let Some(other) = others.next_if(|other| *other < item) {
+                result.push(other);
+            }
+            others.next_if_eq(&item);
+            result.push(item);
+        }
+        result.extend(others);
+        self.content = result;
+        true
+    }
+
+    /// Adds new terminals, same as `union_with`
     pub fn add_others(&mut self, others: &TerminalSet) -> bool {
-        let mut modified = false;
-        for item in &others.content {
-            modified |= self.do_add(*item);
+        self.union_with(others)
+    }
+
+    /// Gets the terminals that are in both this set and the other one
+    #[must_use]
+    pub fn intersect(&self, other: &TerminalSet) -> TerminalSet {
+        TerminalSet {
+            content: self
+                .content
+                .iter()
+                .copied()
+                .filter(|item| other.contains(*item))
+                .collect(),
+        }
+    }
+
+    /// Gets the terminals that are in this set but not in the other one
+    #[must_use]
+    pub fn difference(&self, other: &TerminalSet) -> TerminalSet {
+        TerminalSet {
+            content: self
+                .content
+                .iter()
+                .copied()
+                .filter(|item| !other.contains(*item))
+                .collect(),
         }
-        self.content.sort();
-        modified
     }
 
     /// Removes all items from this collection
@@ -249,6 +385,15 @@ impl TerminalSet {
     }
 }
 
+impl<'a> IntoIterator for &'a TerminalSet {
+    type Item = &'a TerminalRef;
+    type IntoIter = std::slice::Iter<'a, TerminalRef>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.content.iter()
+    }
+}
+
 /// Represents a virtual symbol in a grammar
 #[derive(Debug, Clone)]
 pub struct Virtual {
@@ -348,6 +493,10 @@ pub struct Variable {
     pub firsts: TerminalSet,
     /// The FOLLOWERS set for this variable
     pub followers: TerminalSet,
+    /// All the definitions encountered for this variable
+    pub definitions: Vec<SymbolDefinition>,
+    /// The user-defined annotations on this variable
+    pub annotations: Vec<Annotation>,
 }
 
 impl Variable {
@@ -361,9 +510,23 @@ impl Variable {
             rules: Vec::new(),
             firsts: TerminalSet::default(),
             followers: TerminalSet::default(),
+            definitions: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Adds a definition for this variable, unless it is already known
+    pub fn add_definition(&mut self, definition: SymbolDefinition) {
+        if !self.definitions.contains(&definition) {
+            self.definitions.push(definition);
         }
     }
 
+    /// Adds an annotation to this variable, replacing any previous one with the same key
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        set_annotation(&mut self.annotations, annotation);
+    }
+
     /// Adds the given rule for this variable as a unique element
     pub fn add_rule(&mut self, rule: Rule) {
         if !self.rules.contains(&rule) {
@@ -382,11 +545,11 @@ impl Variable {
     pub fn compute_firsts(&mut self, firsts_for_var: &mut HashMap<usize, TerminalSet>) -> bool {
         let mut modified = false;
         for rule in &mut self.rules {
-            modified |= self.firsts.add_others(&rule.body.firsts);
+            modified |= self.firsts.union_with(&rule.body.firsts);
             modified |= firsts_for_var
                 .entry(self.id)
                 .or_default()
-                .add_others(&rule.body.firsts);
+                .union_with(&rule.body.firsts);
             modified |= rule.body.compute_firsts(firsts_for_var);
         }
         modified
@@ -605,15 +768,10 @@ impl RuleChoice {
             SymbolRef::Variable(id) => {
                 let mut modified = false;
                 if let Some(var_firsts) = firsts_for_var.get(&id) {
-                    for first in &var_firsts.content {
-                        match *first {
-                            TerminalRef::Epsilon => {
-                                modified |= self.firsts.add_others(next);
-                            }
-                            _ => {
-                                modified |= self.firsts.add(*first);
-                            }
-                        }
+                    let epsilon = TerminalSet::single(TerminalRef::Epsilon);
+                    modified |= self.firsts.union_with(&var_firsts.difference(&epsilon));
+                    if var_firsts.contains(TerminalRef::Epsilon) {
+                        modified |= self.firsts.union_with(next);
                     }
                 }
                 modified
@@ -792,7 +950,7 @@ impl RuleBody {
                 modified |= self.choices[i].compute_firsts(&next_firsts, firsts_for_var);
             }
         }
-        modified |= self.firsts.add_others(&self.choices[0].firsts);
+        modified |= self.firsts.union_with(&self.choices[0].firsts);
         modified
     }
 
@@ -815,7 +973,7 @@ impl RuleBody {
             }
             // Create a new empty choice
             self.choices.push(RuleChoice::default());
-            self.firsts.add_others(&self.choices[0].firsts);
+            self.firsts.union_with(&self.choices[0].firsts);
         }
     }
 
@@ -825,11 +983,11 @@ impl RuleBody {
         for (i, choice) in self.choices.iter().enumerate().take(self.choices.len() - 1) {
             if let SymbolRef::Variable(id) = choice.elements[0].symbol {
                 // add the FIRSTS set of the next choice to the variable followers except ε
-                for first in &self.choices[i + 1].firsts.content {
-                    if *first != TerminalRef::Epsilon {
-                        followers.entry(id).or_default().add(*first);
-                    }
-                }
+                let epsilon = TerminalSet::single(TerminalRef::Epsilon);
+                followers
+                    .entry(id)
+                    .or_default()
+                    .union_with(&self.choices[i + 1].firsts.difference(&epsilon));
             }
         }
     }
@@ -846,13 +1004,9 @@ impl RuleBody {
             if let SymbolRef::Variable(id) = choice.elements[0].symbol {
                 // if the next choice FIRSTS set contains ε
                 // add the FOLLOWERS of the head variable to the FOLLOWERS of the found variable
-                if self.choices[i + 1]
-                    .firsts
-                    .content
-                    .contains(&TerminalRef::Epsilon)
-                {
+                if self.choices[i + 1].firsts.contains(TerminalRef::Epsilon) {
                     let head_followers = followers.get(&head).cloned().unwrap_or_default();
-                    modified |= followers.entry(id).or_default().add_others(&head_followers);
+                    modified |= followers.entry(id).or_default().union_with(&head_followers);
                 }
             }
         }
@@ -906,6 +1060,20 @@ impl Rule {
             context,
         }
     }
+
+    /// Gets the FIRSTS set of the remainder of this rule's body after the dot at the specified position
+    /// The position counts the body's symbols, excluding virtual and action symbols.
+    /// For a dot at the end of the body, the set only contains ε.
+    ///
+    /// # Panics
+    ///
+    /// Raise a panic when the choices of this rule's body have not been computed,
+    /// i.e. when the grammar has not been prepared.
+    #[must_use]
+    pub fn firsts_after(&self, position: usize) -> &TerminalSet {
+        let choices = &self.body.choices;
+        &choices[position.min(choices.len() - 1)].firsts
+    }
 }
 
 impl PartialEq for Rule {
@@ -917,7 +1085,7 @@ impl PartialEq for Rule {
 impl Eq for Rule {}
 
 /// A reference to a grammar rule
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct RuleRef {
     /// The identifier of the variable
     pub variable: usize,
@@ -1162,6 +1330,10 @@ pub const PREFIX_GENERATED_VARIABLE: &str = "__V";
 pub const GENERATED_AXIOM: &str = "__VAxiom";
 /// Name of the grammar option specifying the grammar's axiom variable
 pub const OPTION_AXIOM: &str = "Axiom";
+/// Name of the grammar option specifying additional entry point variables, separated by commas
+pub const OPTION_ENTRIES: &str = "Entries";
+/// Name of the grammar option specifying whether symbol identifiers are derived from the symbols' names
+pub const OPTION_STABLE_IDS: &str = "StableIds";
 /// Name of the grammar option specifying the grammar's separator terminal
 pub const OPTION_SEPARATOR: &str = "Separator";
 /// The output path for compilation artifacts
@@ -1179,6 +1351,10 @@ pub const OPTION_ACCESS_MODIFIER: &str = "Modifier";
 /// The name of the default lexical context
 pub const DEFAULT_CONTEXT_NAME: &str = "__default";
 
+/// The upper bound (excluded) of the symbol identifiers derived from the symbols' names
+/// The value `0xFFFF` is reserved by the runtime
+const STABLE_SID_END: usize = 0xFFFF;
+
 /// The counter for the generation of unique names across multiple grammars
 static NEXT_UNIQUE_SID: AtomicUsize = AtomicUsize::new(0);
 
@@ -1188,6 +1364,27 @@ fn generate_unique_id() -> String {
     format!("{value:0X}")
 }
 
+/// Computes a hash of the specified key (FNV-1a) within `[0, range)`
+fn stable_hash(key: &str, range: usize) -> usize {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash % range as u64) as usize
+}
+
+/// Remaps the grammar symbols referenced by a template rule symbol
+fn remap_template_symbol(symbol: &mut TemplateRuleSymbol, remap: &dyn Fn(SymbolRef) -> SymbolRef) {
+    match symbol {
+        TemplateRuleSymbol::Parameter(_) => {}
+        TemplateRuleSymbol::Symbol(inner) => *inner = remap(*inner),
+        TemplateRuleSymbol::Template(template_ref) => {
+            for argument in &mut template_ref.arguments {
+                remap_template_symbol(argument, remap);
+            }
+        }
+    }
+}
+
 /// An option for the grammar
 #[derive(Debug, Clone)]
 pub struct GrammarOption {
@@ -1206,6 +1403,8 @@ pub struct Grammar {
     pub input_ref: InputReference,
     /// The grammar's name
     pub name: String,
+    /// The references to the parent grammars in the inheritance clause
+    pub parents: Vec<GrammarReference>,
     /// The next unique symbol identifier for this grammar
     pub next_sid: usize,
     /// The grammar's options
@@ -1214,6 +1413,12 @@ pub struct Grammar {
     pub contexts: Vec<String>,
     /// The grammar's terminals
     pub terminals: Vec<Terminal>,
+    /// The groups of terminals that share the same lexical priority
+    pub terminal_groups: Vec<TerminalGroup>,
+    /// The terminals to synchronize on during panic-mode error recovery, as declared with `%recover`
+    /// This is metadata exported to the generated code for the callers implementing a recovery,
+    /// the parsers of the runtime do not use it.
+    pub sync_terminals: Vec<TerminalRef>,
     /// The grammar's variables
     pub variables: Vec<Variable>,
     /// The grammar's virtual symbols
@@ -1222,6 +1427,126 @@ pub struct Grammar {
     pub actions: Vec<Action>,
     /// The template rules
     pub template_rules: Vec<TemplateRule>,
+    /// The stable identifiers of the symbols, by key, when the identifiers are stable
+    /// They are those recorded by the previous generations of the grammar, if any,
+    /// completed with the identifiers of the new symbols when the grammar is prepared.
+    pub stable_ids: HashMap<String, usize>,
+}
+
+/// The maximum number of sentential forms explored when enumerating the sentences of a grammar
+const MAX_SENTENTIAL_FORMS: usize = 100_000;
+
+/// Lazy enumerator of the sentences of a grammar
+struct SentenceEnumerator<'g> {
+    /// The grammar
+    grammar: &'g Grammar,
+    /// The maximum length of the sentences
+    max_len: usize,
+    /// The maximum number of symbols in the sentential forms to explore
+    max_form_len: usize,
+    /// The minimal length of the sentences derived from each productive variable
+    min_lengths: HashMap<usize, usize>,
+    /// The sentential forms to explore
+    queue: VecDeque<Vec<SymbolRef>>,
+    /// The sentential forms already explored
+    visited: HashSet<Vec<SymbolRef>>,
+    /// The sentences already yielded
+    yielded: HashSet<Vec<TerminalRef>>,
+}
+
+impl SentenceEnumerator<'_> {
+    /// Pushes a sentential form to explore, if it can still yield a sentence within the bounds
+    fn push(&mut self, form: Vec<SymbolRef>) {
+        if self.visited.len() >= MAX_SENTENTIAL_FORMS {
+            return;
+        }
+        // variables that can derive the empty string could be expanded indefinitely
+        if form.len() > self.max_form_len {
+            return;
+        }
+        let mut min_length = 0;
+        for symbol in &form {
+            min_length += match symbol {
+                SymbolRef::Variable(sid) => match self.min_lengths.get(sid) {
+                    Some(length) => *length,
+                    None => return, // non-productive variable
+                },
+                _ => 1,
+            };
+        }
+        if min_length <= self.max_len && !self.visited.contains(&form) {
+            self.visited.insert(form.clone());
+            self.queue.push_back(form);
+        }
+    }
+}
+
+impl Iterator for SentenceEnumerator<'_> {
+    type Item = Vec<TerminalRef>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(form) = self.queue.pop_front() {
+            let leftmost = form
+                .iter()
+                .enumerate()
+                .find_map(|(index, symbol)| match symbol {
+                    SymbolRef::Variable(sid) => Some((index, *sid)),
+                    _ => None,
+                });
+            let Some((index, sid)) = leftmost else {
+                // only terminals remain
+                let sentence: Vec<TerminalRef> = form
+                    .iter()
+                    .filter_map(|symbol| match symbol {
+                        SymbolRef::Terminal(sid) => Some(TerminalRef::Terminal(*sid)),
+                        _ => None,
+                    })
+                    .collect();
+                if self.yielded.insert(sentence.clone()) {
+                    return Some(sentence);
+                }
+                continue;
+            };
+            let Some(variable) = self.grammar.get_variable(sid) else {
+                continue;
+            };
+            for rule in &variable.rules {
+                let mut expanded = form[..index].to_vec();
+                expanded.extend(
+                    rule.body
+                        .elements
+                        .iter()
+                        .map(|element| element.symbol)
+                        .filter(|symbol| {
+                            matches!(symbol, SymbolRef::Terminal(_) | SymbolRef::Variable(_))
+                        }),
+                );
+                expanded.extend_from_slice(&form[index + 1..]);
+                self.push(expanded);
+            }
+        }
+        None
+    }
+}
+
+/// Feeds the formatted definition of a grammar to a 64-bit FNV-1a hash
+/// Unlike `DefaultHasher`, the hash does not depend on the process nor on the version of Rust.
+struct FingerprintWriter(u64);
+
+impl FingerprintWriter {
+    /// The offset basis of the 64-bit FNV-1a hash
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    /// The prime of the 64-bit FNV-1a hash
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl std::fmt::Write for FingerprintWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for byte in s.bytes() {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FingerprintWriter::PRIME);
+        }
+        Ok(())
+    }
 }
 
 /// Represents the build data for a grammar
@@ -1229,14 +1554,56 @@ pub struct Grammar {
 pub struct BuildData {
     /// The DFA
     pub dfa: DFA,
+    /// The statistics about the compaction of the DFA
+    pub lexer_statistics: LexerStatistics,
     /// The expected terminals
     pub expected: TerminalSet,
     /// The separator terminal
     pub separator: Option<TerminalRef>,
     /// The parsing method
     pub method: ParsingMethod,
-    /// The LR graph
+    /// The identifier of the generated axiom
+    pub axiom: usize,
+    /// The LR graph, for the axiom and all the additional entry points
     pub graph: Graph,
+    /// The data for the additional entry points
+    pub entries: Vec<EntryData>,
+}
+
+/// Represents the statistics about the size of the lexer's automaton
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LexerStatistics {
+    /// The number of states in the DFA before its compaction
+    pub states_before: usize,
+    /// The number of states in the DFA after its compaction
+    pub states_after: usize,
+    /// The size in bytes of the serialized DFA before its compaction
+    pub bytes_before: usize,
+    /// The size in bytes of the serialized DFA after its compaction
+    pub bytes_after: usize,
+}
+
+impl Display for LexerStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "lexer: {} -> {} states, {} -> {} bytes",
+            self.states_before, self.states_after, self.bytes_before, self.bytes_after
+        )
+    }
+}
+
+/// Represents the build data for an additional entry point of a grammar
+#[derive(Debug, Clone)]
+pub struct EntryData {
+    /// The name of the entry point variable
+    pub name: String,
+    /// The identifier of the entry point variable
+    pub variable: usize,
+    /// The identifier of the generated axiom for this entry point
+    pub axiom: usize,
+    /// The initial state for this entry point in the LR graph
+    pub state: usize,
 }
 
 impl Grammar {
@@ -1246,14 +1613,18 @@ impl Grammar {
         Grammar {
             input_ref,
             name,
+            parents: Vec::new(),
             next_sid: 3,
             options: HashMap::new(),
             contexts: vec![DEFAULT_CONTEXT_NAME.to_string()],
             terminals: Vec::new(),
+            terminal_groups: Vec::new(),
+            sync_terminals: Vec::new(),
             variables: Vec::new(),
             virtuals: Vec::new(),
             actions: Vec::new(),
             template_rules: Vec::new(),
+            stable_ids: HashMap::new(),
         }
     }
 
@@ -1282,12 +1653,81 @@ impl Grammar {
         );
     }
 
+    /// Adds an additional entry point, as declared by a `%entry_point` directive
+    /// The entry point is appended to the value of the `Entries` option, which is created when missing.
+    pub fn add_entry(&mut self, input_ref: InputReference, name: &str) {
+        if self.get_entries().contains(&name) {
+            return;
+        }
+        match self.options.get_mut(OPTION_ENTRIES) {
+            Some(option) if !option.value.trim().is_empty() => {
+                option.value.push_str(", ");
+                option.value.push_str(name);
+            }
+            _ => self.add_option(
+                input_ref,
+                input_ref,
+                OPTION_ENTRIES.to_string(),
+                name.to_string(),
+            ),
+        }
+    }
+
     /// Gets an option
     #[must_use]
     pub fn get_option(&self, name: &str) -> Option<&GrammarOption> {
         self.options.get(name)
     }
 
+    /// Gets the value of an option
+    #[must_use]
+    pub fn get_option_value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|option| option.value.as_str())
+    }
+
+    /// Gets the values of all the options declared in this grammar, by name
+    #[must_use]
+    pub fn get_options(&self) -> HashMap<&str, &str> {
+        self.options
+            .iter()
+            .map(|(name, option)| (name.as_str(), option.value.as_str()))
+            .collect()
+    }
+
+    /// Gets the name of the axiom variable declared in this grammar's options, if any
+    #[must_use]
+    pub fn get_axiom(&self) -> Option<&str> {
+        self.get_option_value(OPTION_AXIOM)
+    }
+
+    /// Gets whether the symbol identifiers are derived from the symbols' names
+    #[must_use]
+    pub fn has_stable_ids(&self) -> bool {
+        self.get_option(OPTION_STABLE_IDS)
+            .is_some_and(|option| option.value == "true")
+    }
+
+    /// Gets the names of the additional entry point variables
+    #[must_use]
+    pub fn get_entries(&self) -> Vec<&str> {
+        self.get_option(OPTION_ENTRIES)
+            .map(|option| {
+                option
+                    .value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets the name of the generated axiom for an entry point
+    #[must_use]
+    pub fn get_entry_axiom_name(entry: &str) -> String {
+        format!("{GENERATED_AXIOM}_{entry}")
+    }
+
     /// Gets the symbol with the given name in this grammar
     #[must_use]
     pub fn get_symbol(&self, name: &str) -> Option<SymbolRef> {
@@ -1362,6 +1802,25 @@ impl Grammar {
         }
     }
 
+    /// Gets the FIRSTS set of the specified symbol,
+    /// i.e. the terminals that can start a phrase derived from it, including ε when it is nullable.
+    /// This grammar is expected to have been prepared beforehand.
+    #[must_use]
+    pub fn first_set(&self, symbol: SymbolRef) -> TerminalSet {
+        match symbol {
+            SymbolRef::Dummy | SymbolRef::NullTerminal => TerminalSet::default(),
+            SymbolRef::Epsilon | SymbolRef::Virtual(_) | SymbolRef::Action(_) => {
+                TerminalSet::single(TerminalRef::Epsilon)
+            }
+            SymbolRef::Dollar => TerminalSet::single(TerminalRef::Dollar),
+            SymbolRef::Terminal(id) => TerminalSet::single(TerminalRef::Terminal(id)),
+            SymbolRef::Variable(id) => self
+                .get_variable(id)
+                .map(|variable| variable.firsts.clone())
+                .unwrap_or_default(),
+        }
+    }
+
     /// Resolves the specified lexical context name for this grammar
     pub fn resolve_context(&mut self, name: &str) -> usize {
         if let Some(index) = self.contexts.iter().position(|c| name == c) {
@@ -1424,12 +1883,50 @@ impl Grammar {
             context,
             is_anonymous,
             is_fragment,
+            external_scanner: None,
+            group: None,
+            recovery: None,
+            alias: None,
+            annotations: Vec::new(),
             terminal_references: Vec::new(),
+            definitions: Vec::new(),
         };
         self.terminals.push(terminal);
         &mut self.terminals[index]
     }
 
+    /// Adds a group of terminals that share the same lexical priority
+    /// Returns the index of the group
+    pub fn add_terminal_group(
+        &mut self,
+        input_ref: InputReference,
+        terminals: Vec<usize>,
+    ) -> usize {
+        let index = self.terminal_groups.len();
+        for terminal in self
+            .terminals
+            .iter_mut()
+            .filter(|t| terminals.contains(&t.id))
+        {
+            terminal.group = Some(index);
+        }
+        self.terminal_groups.push(TerminalGroup {
+            input_ref,
+            terminals,
+        });
+        index
+    }
+
+    /// Adds terminals to synchronize on during panic-mode error recovery
+    /// The terminals are merged with those already declared.
+    pub fn add_sync_terminals(&mut self, terminals: &[TerminalRef]) {
+        for terminal in terminals {
+            if !self.sync_terminals.contains(terminal) {
+                self.sync_terminals.push(*terminal);
+            }
+        }
+    }
+
     /// Gets the terminal with the specified identifier
     #[must_use]
     pub fn get_terminal(&self, sid: usize) -> Option<&Terminal> {
@@ -1447,6 +1944,32 @@ impl Grammar {
         self.terminals.iter().find(|t| t.name == name)
     }
 
+    /// Overrides the named terminal inherited from a parent grammar with a new definition.
+    /// The terminal keeps its identifier, and thus its priority.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the terminal or the specified context do not exist in the grammar
+    pub fn override_terminal_named(
+        &mut self,
+        name: &str,
+        input_ref: InputReference,
+        mut nfa: NFA,
+        context: &str,
+        is_fragment: bool,
+    ) -> &mut Terminal {
+        let context = self.contexts.iter().position(|c| c == context).unwrap();
+        let terminal = self.terminals.iter_mut().find(|t| t.name == name).unwrap();
+        nfa.add_final_terminal(terminal.id, context);
+        terminal.input_ref = input_ref;
+        terminal.nfa = nfa;
+        terminal.context = context;
+        terminal.is_fragment = is_fragment;
+        terminal.external_scanner = None;
+        terminal.recovery = None;
+        terminal
+    }
+
     /// Gets the terminal with the given name
     #[must_use]
     pub fn get_terminal_for_value(&self, value: &str) -> Option<&Terminal> {
@@ -1507,11 +2030,20 @@ impl Grammar {
 
     /// Inherit the specified variable
     fn inherit_variable(&mut self, other: &Variable) {
-        if self.variables.iter().all(|v| v.name != other.name) {
+        let index = if let Some(index) = self.variables.iter().position(|v| v.name == other.name) {
+            index
+        } else {
             // no variable with the same name
             let sid = self.next_sid + other.id - 3;
             self.variables
                 .push(Variable::new(sid, other.name.clone(), None));
+            self.variables.len() - 1
+        };
+        for definition in &other.definitions {
+            self.variables[index].add_definition(definition.clone());
+        }
+        for annotation in &other.annotations {
+            self.variables[index].add_annotation(annotation.clone());
         }
     }
 
@@ -1752,9 +2284,8 @@ impl Grammar {
                 let sid = self.next_sid + terminal.id - 3;
                 let context = self.resolve_context(&other.contexts[terminal.context]);
                 let mut nfa = terminal.nfa.clone_no_finals();
-                nfa.states[nfa.exit]
-                    .items
-                    .push(FinalItem::Terminal(sid, context));
+                nfa.lookahead = terminal.nfa.lookahead;
+                nfa.add_final_terminal(sid, context);
                 self.terminals.push(Terminal {
                     id: sid,
                     name: terminal.name.clone(),
@@ -1764,10 +2295,43 @@ impl Grammar {
                     context,
                     is_fragment: terminal.is_fragment,
                     is_anonymous: terminal.is_anonymous,
+                    external_scanner: terminal.external_scanner.clone(),
+                    group: None,
+                    recovery: terminal.recovery,
+                    alias: terminal.alias.clone(),
+                    annotations: terminal.annotations.clone(),
                     terminal_references: Vec::new(),
+                    definitions: terminal.definitions.clone(),
                 });
             }
         }
+        for group in &other.terminal_groups {
+            let terminals: Vec<usize> = group
+                .terminals
+                .iter()
+                .map(|id| self.next_sid + id - 3)
+                .filter(|sid| self.terminals.iter().any(|t| t.id == *sid))
+                .collect();
+            if !terminals.is_empty() {
+                self.add_terminal_group(group.input_ref, terminals);
+            }
+        }
+        let sync_terminals: Vec<TerminalRef> = other
+            .sync_terminals
+            .iter()
+            .filter_map(|terminal| match terminal {
+                // the terminal may be redefined in this grammar, match it by name
+                TerminalRef::Terminal(id) => {
+                    let inherited = other.get_terminal(*id)?;
+                    self.terminals
+                        .iter()
+                        .find(|t| t.name == inherited.name)
+                        .map(|t| TerminalRef::Terminal(t.id))
+                }
+                _ => Some(*terminal),
+            })
+            .collect();
+        self.add_sync_terminals(&sync_terminals);
     }
 
     /// Inherits the virtuals from the parent grammar
@@ -1992,17 +2556,213 @@ impl Grammar {
         let mut dfa = DFA::from_nfa(nfa).minimize();
         dfa.repack_transitions();
         dfa.prune();
+        self.sort_dfa_items(&mut dfa);
         dfa
     }
 
+    /// Sorts the final items in the states of a DFA by priority, when the priority is not given by the identifiers
+    fn sort_dfa_items(&self, dfa: &mut DFA) {
+        if self.has_stable_ids() || !self.terminal_groups.is_empty() {
+            // the identifiers no longer follow the declaration order that defines the priority,
+            // or the priority of grouped terminals is the one of their group
+            let ranks = self.get_terminal_ranks();
+            for state in &mut dfa.states {
+                state.items.sort_by_key(|item| {
+                    std::cmp::Reverse(match item {
+                        FinalItem::Dummy => (0, 0),
+                        FinalItem::Terminal(sid, _, _) => {
+                            ranks.get(sid).copied().unwrap_or_default()
+                        }
+                    })
+                });
+            }
+        }
+    }
+
+    /// Gets the lexical rank of each terminal, by identifier
+    /// The rank is the position of the terminal in the declaration order, or the one of the last terminal of its group,
+    /// followed by the position of the terminal itself to keep the order total within a group.
+    fn get_terminal_ranks(&self) -> HashMap<usize, (usize, usize)> {
+        let positions: HashMap<usize, usize> = self
+            .terminals
+            .iter()
+            .enumerate()
+            .map(|(position, terminal)| (terminal.id, position + 1))
+            .collect();
+        self.terminals
+            .iter()
+            .map(|terminal| {
+                let position = positions[&terminal.id];
+                let rank = terminal.group.map_or(position, |group| {
+                    self.terminal_groups[group]
+                        .terminals
+                        .iter()
+                        .filter_map(|id| positions.get(id).copied())
+                        .max()
+                        .unwrap_or(position)
+                });
+                (terminal.id, (rank, position))
+            })
+            .collect()
+    }
+
+    /// Compacts the DFA for the lexer, once the LR graph is known
+    /// In each state, the matched terminals that the lexer can never select at runtime are removed,
+    /// i.e. the terminals shadowed in their context, by the separator,
+    /// or in a context that is never opened or where they are never expected.
+    /// The states that only differed by these terminals are then merged.
+    #[must_use]
+    pub fn compact_dfa(&self, dfa: &DFA, graph: &Graph, separator: Option<TerminalRef>) -> DFA {
+        let unused_contexts = graph.unused_contexts(self);
+        let expected = graph.expected_terminals();
+        let mut result = dfa.clone();
+        if result.states.is_empty() {
+            // a grammar without terminals has an empty automaton once pruned
+            return result;
+        }
+        for state in &mut result.states {
+            let mut items = Vec::new();
+            // the items shadowed by a terminal with more priority in the same context are dropped
+            for item in state.get_selectable_items() {
+                let terminal = TerminalRef::from(item);
+                let context = self.get_terminal_context(terminal);
+                if self.contexts.len() > 1 && Some(terminal) == separator {
+                    // the separator trumps all
+                    items = vec![item];
+                    break;
+                }
+                // the first terminal is always kept because it is the fallback of the lexer
+                if items.is_empty()
+                    || context == 0
+                    || (!unused_contexts.contains(&context) && expected.contains(terminal))
+                {
+                    items.push(item);
+                }
+            }
+            state.items = items;
+        }
+        // merged states may repack their transitions, which in turn allows more merges
+        loop {
+            let mut next = result.minimize();
+            next.repack_transitions();
+            if next.len() == result.len() {
+                break;
+            }
+            result = next;
+        }
+        self.sort_dfa_items(&mut result);
+        result
+    }
+
+    /// Extracts the sub-grammar reachable from a variable into a standalone grammar
+    /// The result only keeps the variables transitively used by the rules of `from`,
+    /// the terminals, virtuals and actions they reference, and the separator terminal, if any.
+    /// The symbols are renumbered in the order of their original identifiers,
+    /// so that the relative priorities of the terminals are preserved.
+    /// The variable `from` becomes the axiom of the result and its FIRSTS and FOLLOWERS sets are recomputed.
+    #[must_use]
+    pub fn reachable_subset(&self, from: usize) -> Grammar {
+        let mut kept = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(sid) = stack.pop() {
+            let Some(variable) = self.get_variable(sid) else {
+                continue;
+            };
+            if !kept.insert(sid) {
+                continue;
+            }
+            for element in variable.rules.iter().flat_map(|rule| &rule.body.elements) {
+                match element.symbol {
+                    SymbolRef::Variable(id) => stack.push(id),
+                    SymbolRef::Terminal(id) | SymbolRef::Virtual(id) | SymbolRef::Action(id) => {
+                        kept.insert(id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(separator) = self
+            .get_option_value(OPTION_SEPARATOR)
+            .and_then(|name| self.get_terminal_for_name(name))
+        {
+            kept.insert(separator.id);
+        }
+
+        let mut result = self.clone();
+        result
+            .terminals
+            .retain(|terminal| kept.contains(&terminal.id));
+        for terminal in &mut result.terminals {
+            terminal
+                .terminal_references
+                .retain(|reference| kept.contains(&reference.referring_id));
+        }
+        for group in &mut result.terminal_groups {
+            group.terminals.retain(|sid| kept.contains(sid));
+        }
+        result.sync_terminals.retain(|terminal| match terminal {
+            TerminalRef::Terminal(sid) => kept.contains(sid),
+            _ => true,
+        });
+        result
+            .variables
+            .retain(|variable| kept.contains(&variable.id));
+        for variable in &mut result.variables {
+            variable.generated_for = variable.generated_for.filter(|sid| kept.contains(sid));
+            variable.firsts = TerminalSet::default();
+            variable.followers = TerminalSet::default();
+            for rule in &mut variable.rules {
+                rule.body.firsts = TerminalSet::default();
+                rule.body.choices.clear();
+            }
+        }
+        result.virtuals.retain(|symbol| kept.contains(&symbol.id));
+        result.actions.retain(|symbol| kept.contains(&symbol.id));
+        // the instances of the templates are already in the variables
+        result.template_rules.clear();
+
+        if let Some(variable) = self.get_variable(from) {
+            let axiom = result
+                .options
+                .entry(OPTION_AXIOM.to_string())
+                .or_insert_with(|| GrammarOption {
+                    name_input_ref: self.input_ref,
+                    value_input_ref: self.input_ref,
+                    value: String::new(),
+                });
+            axiom.value.clone_from(&variable.name);
+        }
+        result.options.remove(OPTION_ENTRIES);
+
+        let mut sids = kept.into_iter().collect::<Vec<_>>();
+        sids.sort_unstable();
+        let mapping = sids
+            .into_iter()
+            .enumerate()
+            .map(|(index, sid)| (sid, index + 3))
+            .collect::<HashMap<_, _>>();
+        result.next_sid = 3;
+        result.remap_ids(&mapping);
+        for variable in &mut result.variables {
+            variable.compute_choices();
+        }
+        result.compute_firsts();
+        result.compute_followers();
+        result
+    }
+
     /// Prepares this grammar for code and data generation
     /// This methods inserts a new grammar rule as its axiom and computes the FIRSTS and FOLLOWERS sets
+    /// Preparing a grammar that is already prepared does not change it.
     ///
     /// # Errors
     ///
     /// Return an error when the axiom is not properly defined
     pub fn prepare(&mut self, grammar_index: usize) -> Result<(), Error> {
         self.add_real_axiom(grammar_index)?;
+        if self.has_stable_ids() {
+            self.assign_stable_ids();
+        }
         for variable in &mut self.variables {
             variable.compute_choices();
         }
@@ -2011,8 +2771,191 @@ impl Grammar {
         Ok(())
     }
 
+    /// Reads the stable identifiers recorded by a previous generation, one `identifier key` per line
+    /// The lines that are not well-formed are ignored.
+    pub fn read_stable_ids(&mut self, content: &str) {
+        for line in content.lines() {
+            let Some((sid, key)) = line.split_once(' ') else {
+                continue;
+            };
+            if let Ok(sid) = sid.parse::<usize>() {
+                if (3..STABLE_SID_END).contains(&sid) {
+                    self.stable_ids.insert(key.to_string(), sid);
+                }
+            }
+        }
+    }
+
+    /// Writes the stable identifiers, one `identifier key` per line, in the order of the identifiers
+    #[must_use]
+    pub fn write_stable_ids(&self) -> String {
+        let mut entries: Vec<(usize, &str)> = self
+            .stable_ids
+            .iter()
+            .map(|(key, sid)| (*sid, key.as_str()))
+            .collect();
+        entries.sort_unstable();
+        let mut result = String::new();
+        for (sid, key) in entries {
+            result.push_str(&sid.to_string());
+            result.push(' ');
+            result.push_str(key);
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Re-assigns the identifiers of all symbols from a hash of their names
+    /// The symbols keep the identifiers in `stable_ids`, recorded by previous generations.
+    /// The new symbols get the hash of their key, or on collision, the next free identifier in the order of the keys.
+    /// The identifiers of the symbols that no longer exist remain reserved, so that they are never reused.
+    fn assign_stable_ids(&mut self) {
+        // keep the terminals in declaration order, which defines their priority
+        self.terminals.sort_by_key(|t| t.id);
+        let mut keys = Vec::new();
+        for terminal in &self.terminals {
+            if terminal.is_anonymous {
+                keys.push((format!("inline {}", terminal.value), terminal.id));
+            } else {
+                keys.push((format!("terminal {}", terminal.name), terminal.id));
+            }
+        }
+        let mut variable_keys = HashMap::new();
+        for variable in self.variables.iter().filter(|v| v.generated_for.is_none()) {
+            variable_keys.insert(variable.id, format!("variable {}", variable.name));
+        }
+        // generated variables are named after their rank within the variable they are generated for
+        let mut ranks = HashMap::new();
+        for variable in &self.variables {
+            if let Some(parent) = variable.generated_for {
+                let rank = ranks.entry(parent).or_insert(0);
+                *rank += 1;
+                let parent_key = variable_keys
+                    .get(&parent)
+                    .cloned()
+                    .unwrap_or_else(|| format!("variable {parent}"));
+                variable_keys.insert(variable.id, format!("{parent_key}/{rank}"));
+            }
+        }
+        keys.extend(variable_keys.into_iter().map(|(sid, key)| (key, sid)));
+        for symbol in &self.virtuals {
+            keys.push((format!("virtual {}", symbol.name), symbol.id));
+        }
+        for symbol in &self.actions {
+            keys.push((format!("action {}", symbol.name), symbol.id));
+        }
+        keys.sort();
+
+        let mut used: HashSet<usize> = self.stable_ids.values().copied().collect();
+        let mut mapping = HashMap::new();
+        let mut new_keys = Vec::new();
+        for (key, sid) in keys {
+            match self.stable_ids.get(&key) {
+                Some(new_sid) => {
+                    mapping.insert(sid, *new_sid);
+                }
+                None => new_keys.push((key, sid)),
+            }
+        }
+        for (key, sid) in new_keys {
+            let mut new_sid = 3 + stable_hash(&key, STABLE_SID_END - 3);
+            while !used.insert(new_sid) {
+                new_sid = if new_sid + 1 == STABLE_SID_END {
+                    3
+                } else {
+                    new_sid + 1
+                };
+            }
+            self.stable_ids.insert(key, new_sid);
+            mapping.insert(sid, new_sid);
+        }
+        self.remap_ids(&mapping);
+    }
+
+    /// Remaps the identifiers of all symbols
+    /// The identifiers that are not in the mapping are kept as is.
+    fn remap_ids(&mut self, mapping: &HashMap<usize, usize>) {
+        let remap = |sid: usize| mapping.get(&sid).copied().unwrap_or(sid);
+        let remap_symbol = |symbol: SymbolRef| match symbol {
+            SymbolRef::Terminal(sid) => SymbolRef::Terminal(remap(sid)),
+            SymbolRef::Variable(sid) => SymbolRef::Variable(remap(sid)),
+            SymbolRef::Virtual(sid) => SymbolRef::Virtual(remap(sid)),
+            SymbolRef::Action(sid) => SymbolRef::Action(remap(sid)),
+            _ => symbol,
+        };
+        for terminal in &mut self.terminals {
+            terminal.id = remap(terminal.id);
+            for state in &mut terminal.nfa.states {
+                for item in &mut state.items {
+                    if let FinalItem::Terminal(sid, context, condition) = *item {
+                        *item = FinalItem::Terminal(remap(sid), context, condition);
+                    }
+                }
+            }
+            for reference in &mut terminal.terminal_references {
+                reference.referring_id = remap(reference.referring_id);
+            }
+        }
+        for variable in &mut self.variables {
+            let old_sid = variable.id;
+            variable.id = remap(old_sid);
+            if variable.name == format!("{PREFIX_GENERATED_VARIABLE}{old_sid}") {
+                variable.name = format!("{PREFIX_GENERATED_VARIABLE}{}", variable.id);
+            }
+            variable.generated_for = variable.generated_for.map(remap);
+            for rule in &mut variable.rules {
+                rule.head = remap(rule.head);
+                for element in &mut rule.body.elements {
+                    element.symbol = remap_symbol(element.symbol);
+                }
+            }
+        }
+        for terminal in &mut self.sync_terminals {
+            if let TerminalRef::Terminal(sid) = terminal {
+                *sid = remap(*sid);
+            }
+        }
+        for group in &mut self.terminal_groups {
+            for sid in &mut group.terminals {
+                *sid = remap(*sid);
+            }
+        }
+        for symbol in &mut self.virtuals {
+            symbol.id = remap(symbol.id);
+        }
+        for symbol in &mut self.actions {
+            symbol.id = remap(symbol.id);
+        }
+        for template_rule in &mut self.template_rules {
+            for instance in &mut template_rule.instances {
+                instance.head = remap(instance.head);
+                for argument in &mut instance.arguments {
+                    *argument = remap_symbol(*argument);
+                }
+            }
+            for body in &mut template_rule.bodies {
+                for element in &mut body.elements {
+                    remap_template_symbol(&mut element.symbol, &remap_symbol);
+                }
+            }
+        }
+        self.next_sid = self
+            .terminals
+            .iter()
+            .map(|terminal| terminal.id)
+            .chain(self.variables.iter().map(|variable| variable.id))
+            .chain(self.virtuals.iter().map(|symbol| symbol.id))
+            .chain(self.actions.iter().map(|symbol| symbol.id))
+            .max()
+            .map_or(self.next_sid, |sid| sid + 1);
+    }
+
     /// Adds the real axiom to this grammar
     fn add_real_axiom(&mut self, grammar_index: usize) -> Result<(), Error> {
+        if self.get_variable_for_name(GENERATED_AXIOM).is_some() {
+            // the grammar is already prepared
+            return Ok(());
+        }
         let axiom_option = self
             .options
             .get(OPTION_AXIOM)
@@ -2024,19 +2967,42 @@ impl Grammar {
             .ok_or(Error::AxiomNotDefined(grammar_index))?
             .id;
         let input_ref = axiom_option.value_input_ref;
+        // Resolve the additional entry points before modifying the grammar
+        let mut entries = Vec::new();
+        if let Some(entries_option) = self.get_option(OPTION_ENTRIES) {
+            for entry in self.get_entries() {
+                let entry_id = self
+                    .get_variable_for_name(entry)
+                    .ok_or_else(|| Error::EntryNotDefined(grammar_index, entry.to_string()))?
+                    .id;
+                entries.push((
+                    Grammar::get_entry_axiom_name(entry),
+                    entry_id,
+                    entries_option.value_input_ref,
+                ));
+            }
+        }
         // Create the real axiom rule variable and rule
-        let real_axiom = self.add_variable(GENERATED_AXIOM);
+        self.add_generated_axiom(GENERATED_AXIOM, axiom_id, input_ref);
+        for (name, entry_id, input_ref) in entries {
+            self.add_generated_axiom(&name, entry_id, input_ref);
+        }
+        Ok(())
+    }
+
+    /// Adds a generated axiom variable and its rule for the specified target variable
+    fn add_generated_axiom(&mut self, name: &str, target: usize, input_ref: InputReference) {
+        let real_axiom = self.add_variable(name);
         real_axiom.rules.push(Rule::new(
             real_axiom.id,
             TREE_ACTION_NONE,
             input_ref,
             RuleBody::from_parts(vec![
-                RuleBodyElement::new(SymbolRef::Variable(axiom_id), TREE_ACTION_PROMOTE, None),
+                RuleBodyElement::new(SymbolRef::Variable(target), TREE_ACTION_PROMOTE, None),
                 RuleBodyElement::new(SymbolRef::Dollar, TREE_ACTION_DROP, None),
             ]),
             0,
         ));
-        Ok(())
     }
 
     /// Computes the FIRSTS sets for this grammar
@@ -2073,15 +3039,448 @@ impl Grammar {
         }
     }
 
+    /// Finds the rules of the form `A -> B` where `B` is nullable
+    /// and `A` has another alternative that conflicts with it,
+    /// i.e. that can also derive the empty string or start with a terminal in the FIRSTS of `B`.
+    /// This grammar is expected to have been prepared beforehand.
+    #[must_use]
+    pub fn find_nullable_unit_rules(&self, grammar_index: usize) -> Vec<Error> {
+        let mut warnings = Vec::new();
+        for variable in &self.variables {
+            for (index, rule) in variable.rules.iter().enumerate() {
+                let Some(choice) = rule.body.choices.first() else {
+                    continue;
+                };
+                if choice.elements.len() != 1 {
+                    continue;
+                }
+                let SymbolRef::Variable(sid) = choice.elements[0].symbol else {
+                    continue;
+                };
+                let Some(target) = self.get_variable(sid) else {
+                    continue;
+                };
+                if !target.firsts.contains(TerminalRef::Epsilon) {
+                    continue;
+                }
+                let conflicting = variable
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .find(|(other, candidate)| {
+                        *other != index
+                            && candidate.body.firsts.iter().any(|terminal| {
+                                *terminal == TerminalRef::Epsilon
+                                    || target.firsts.contains(*terminal)
+                            })
+                    });
+                if let Some((other, _)) = conflicting {
+                    warnings.push(Error::NullableUnitRule(
+                        grammar_index,
+                        RuleRef::new(variable.id, index),
+                        RuleRef::new(variable.id, other),
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Finds the uses of the axiom in the body of the rules through which the axiom derives itself
+    /// These are the references to the axiom in a rule whose other elements are all nullable,
+    /// for a variable that the axiom also derives with only nullable elements around it.
+    /// The axiom is then wrapped in a generated rule that is ambiguous,
+    /// as the input matches an unbounded number of nested axioms.
+    /// This grammar is expected to have been prepared beforehand.
+    #[must_use]
+    pub fn find_axiom_references(&self, grammar_index: usize) -> Vec<Error> {
+        let mut warnings = Vec::new();
+        let Some(axiom) = self
+            .get_axiom()
+            .and_then(|name| self.get_variable_for_name(name))
+        else {
+            return warnings;
+        };
+        let is_nullable = |symbol: SymbolRef| match symbol {
+            SymbolRef::Variable(sid) => self
+                .get_variable(sid)
+                .is_some_and(|variable| variable.firsts.contains(TerminalRef::Epsilon)),
+            SymbolRef::Virtual(_) | SymbolRef::Action(_) | SymbolRef::Epsilon => true,
+            _ => false,
+        };
+        // the variables that the axiom derives with only nullable elements around them
+        let mut derived = vec![axiom.id];
+        let mut queue = VecDeque::from([axiom.id]);
+        while let Some(current) = queue.pop_front() {
+            let Some(variable) = self.get_variable(current) else {
+                continue;
+            };
+            for (index, rule) in variable.rules.iter().enumerate() {
+                let elements = &rule.body.elements;
+                for (position, element) in elements.iter().enumerate() {
+                    let SymbolRef::Variable(sid) = element.symbol else {
+                        continue;
+                    };
+                    let is_alone = elements
+                        .iter()
+                        .enumerate()
+                        .all(|(other, element)| other == position || is_nullable(element.symbol));
+                    if !is_alone {
+                        continue;
+                    }
+                    if sid == axiom.id {
+                        warnings.push(Error::AxiomReferenced(
+                            grammar_index,
+                            RuleRef::new(variable.id, index),
+                            position,
+                        ));
+                    } else if !derived.contains(&sid) {
+                        derived.push(sid);
+                        queue.push_back(sid);
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Finds the terminals and variables of this grammar that redefine a symbol inherited from a parent grammar,
+    /// with a different definition.
+    /// Redefined terminals replace the inherited definition,
+    /// while redefined variables add alternatives to the inherited ones.
+    #[must_use]
+    pub fn find_redefined_symbols(&self) -> Vec<Error> {
+        let mut warnings = Vec::new();
+        for terminal in &self.terminals {
+            if let Some((current, previous)) = self.find_redefinition(&terminal.definitions) {
+                warnings.push(Error::RedefinedTerminal(
+                    current.input_ref,
+                    terminal.name.clone(),
+                    previous.input_ref,
+                ));
+            }
+        }
+        for variable in &self.variables {
+            if let Some((current, previous)) = self.find_redefinition(&variable.definitions) {
+                warnings.push(Error::RedefinedVariable(
+                    current.input_ref,
+                    variable.name.clone(),
+                    previous.input_ref,
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Finds the last definition in this grammar that differs from the last inherited one
+    fn find_redefinition<'d>(
+        &self,
+        definitions: &'d [SymbolDefinition],
+    ) -> Option<(&'d SymbolDefinition, &'d SymbolDefinition)> {
+        let previous = definitions
+            .iter()
+            .rev()
+            .find(|definition| definition.grammar != self.name)?;
+        let current = definitions
+            .iter()
+            .rev()
+            .find(|definition| definition.grammar == self.name)?;
+        (current.content != previous.content).then_some((current, previous))
+    }
+
+    /// Finds the symbols of a parent grammar that conflict with the ones already inherited from other parents,
+    /// i.e. that are defined differently by both parents.
+    /// This is expected to be called before inheriting from the parent.
+    #[must_use]
+    pub fn find_inheritance_conflicts(
+        &self,
+        input_ref: InputReference,
+        parent: &Grammar,
+    ) -> Vec<Error> {
+        let mut errors = Vec::new();
+        let mut check = |name: &str, mine: &[SymbolDefinition], theirs: &[SymbolDefinition]| {
+            if let (Some(mine), Some(theirs)) = (mine.last(), theirs.last()) {
+                if mine.input_ref != theirs.input_ref && mine.content != theirs.content {
+                    errors.push(Error::ConflictingInheritedSymbol(
+                        input_ref,
+                        name.to_string(),
+                        theirs.input_ref,
+                        mine.input_ref,
+                    ));
+                }
+            }
+        };
+        for terminal in &parent.terminals {
+            if let Some(mine) = self.get_terminal_for_name(&terminal.name) {
+                check(&terminal.name, &mine.definitions, &terminal.definitions);
+            }
+        }
+        for variable in &parent.variables {
+            if let Some(mine) = self.get_variable_for_name(&variable.name) {
+                check(&variable.name, &mine.definitions, &variable.definitions);
+            }
+        }
+        errors
+    }
+
+    /// Finds the lexical contexts that are declared but never opened by the parser,
+    /// neither from the axiom nor from any additional entry point
+    #[must_use]
+    pub fn find_unused_contexts(&self, grammar_index: usize, data: &BuildData) -> Vec<Error> {
+        data.graph
+            .unused_contexts(self)
+            .into_iter()
+            .map(|context| Error::UnusedContext(grammar_index, context))
+            .collect()
+    }
+
+    /// Finds the terminals that the lexer never selects,
+    /// i.e. whenever they match, a terminal with more priority always matches as well
+    #[must_use]
+    pub fn find_shadowed_terminals(&self, grammar_index: usize, data: &BuildData) -> Vec<Error> {
+        let shadowed: Vec<&Terminal> = self
+            .terminals
+            .iter()
+            .filter(|terminal| {
+                !terminal.is_fragment
+                    && terminal.external_scanner.is_none()
+                    && !data.expected.contains(TerminalRef::Terminal(terminal.id))
+            })
+            .collect();
+        if shadowed.is_empty() {
+            return Vec::new();
+        }
+        // the compacted DFA no longer has the shadowed terminals, look into the original one
+        let dfa = self.build_dfa();
+        shadowed
+            .into_iter()
+            .map(|terminal| {
+                let terminal_ref = TerminalRef::Terminal(terminal.id);
+                Error::TerminalShadowed(
+                    grammar_index,
+                    UnmatchableTokenError {
+                        terminal: terminal_ref,
+                        overriders: dfa.get_overriders(terminal_ref, terminal.context),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Gets the shortest sentence of terminals that can be derived from the axiom
+    /// Return `None` when the axiom is not defined or cannot derive any sentence
+    #[must_use]
+    pub fn shortest_sentence(&self) -> Option<Vec<TerminalRef>> {
+        let axiom = self.get_variable_for_name(&self.get_option(OPTION_AXIOM)?.value)?;
+        self.get_shortest_sentences().remove(&axiom.id)
+    }
+
+    /// Gets the shortest sentence that can be derived from each productive variable
+    fn get_shortest_sentences(&self) -> HashMap<usize, Vec<TerminalRef>> {
+        let mut shortest: HashMap<usize, Vec<TerminalRef>> = HashMap::new();
+        let mut modified = true;
+        while modified {
+            modified = false;
+            for variable in &self.variables {
+                for rule in &variable.rules {
+                    let Some(sentence) = Self::shortest_sentence_for(&rule.body, &shortest) else {
+                        continue;
+                    };
+                    let better = shortest
+                        .get(&variable.id)
+                        .is_none_or(|previous| sentence.len() < previous.len());
+                    if better {
+                        shortest.insert(variable.id, sentence);
+                        modified = true;
+                    }
+                }
+            }
+        }
+        shortest
+    }
+
+    /// Lazily enumerates the sentences of at most `max_len` terminals that can be derived from the axiom
+    ///
+    /// The sentences are produced by expanding the leftmost variable of sentential forms, breadth-first.
+    /// They are therefore yielded in the order of the number of derivation steps required to produce them,
+    /// not by length. Each sentence is yielded once.
+    /// To avoid a combinatorial blowup, the enumeration stops after exploring a bounded number of sentential forms,
+    /// so that not all sentences may be yielded for large grammars.
+    /// Nothing is yielded when the axiom is not defined.
+    pub fn enumerate_sentences(
+        &self,
+        max_len: usize,
+    ) -> impl Iterator<Item = Vec<TerminalRef>> + '_ {
+        // In a smallest derivation tree of a sentence, a variable cannot repeat on a path
+        // without the yield shrinking, so that the paths have at most (max_len + 1) * |V| variables.
+        // The symbols of a leftmost sentential form are the terminals of the sentence
+        // and the pending siblings along the current path.
+        let max_body_len = self
+            .variables
+            .iter()
+            .flat_map(|variable| &variable.rules)
+            .map(|rule| {
+                rule.body
+                    .elements
+                    .iter()
+                    .filter(|element| {
+                        matches!(
+                            element.symbol,
+                            SymbolRef::Terminal(_) | SymbolRef::Variable(_)
+                        )
+                    })
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        let max_form_len =
+            max_len + max_body_len.saturating_sub(1) * (max_len + 1) * self.variables.len().max(1);
+        let mut enumerator = SentenceEnumerator {
+            grammar: self,
+            max_len,
+            max_form_len,
+            min_lengths: self
+                .get_shortest_sentences()
+                .into_iter()
+                .map(|(sid, sentence)| (sid, sentence.len()))
+                .collect(),
+            queue: VecDeque::new(),
+            visited: HashSet::new(),
+            yielded: HashSet::new(),
+        };
+        if let Some(axiom) = self
+            .get_option(OPTION_AXIOM)
+            .and_then(|option| self.get_variable_for_name(&option.value))
+        {
+            enumerator.push(vec![SymbolRef::Variable(axiom.id)]);
+        }
+        enumerator
+    }
+
+    /// Gets the shortest sentence for a rule body, given the currently known shortest sentences for variables
+    fn shortest_sentence_for(
+        body: &RuleBody,
+        shortest: &HashMap<usize, Vec<TerminalRef>>,
+    ) -> Option<Vec<TerminalRef>> {
+        let mut sentence = Vec::new();
+        for element in &body.elements {
+            match element.symbol {
+                SymbolRef::Terminal(sid) => sentence.push(TerminalRef::Terminal(sid)),
+                SymbolRef::Variable(sid) => sentence.extend_from_slice(shortest.get(&sid)?),
+                _ => {}
+            }
+        }
+        Some(sentence)
+    }
+
+    /// Gets a fingerprint of the definition of this grammar, before it is prepared for a build
+    /// with the specified parsing method, if any, overriding the one in the grammar's options.
+    /// Grammars with the same fingerprint produce the same build data.
+    /// The fingerprint is only stable for the same version of this crate.
+    #[must_use]
+    pub fn fingerprint(&self, parsing_method: Option<ParsingMethod>) -> u64 {
+        let mut hasher = FingerprintWriter(FingerprintWriter::OFFSET_BASIS);
+        let mut options = self.options.iter().collect::<Vec<_>>();
+        options.sort_by(|left, right| left.0.cmp(right.0));
+        let mut stable_ids = self.stable_ids.iter().collect::<Vec<_>>();
+        stable_ids.sort();
+        // the names of anonymous terminals are generated from a counter shared by all grammars
+        let terminals = self
+            .terminals
+            .iter()
+            .map(|terminal| {
+                let mut terminal = terminal.clone();
+                if terminal.is_anonymous {
+                    terminal.name.clear();
+                }
+                terminal
+            })
+            .collect::<Vec<_>>();
+        let _ = write!(
+            hasher,
+            "{}|{parsing_method:?}|{}|{}|{options:?}|{stable_ids:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+            crate::CRATE_VERSION,
+            self.name,
+            self.next_sid,
+            self.contexts,
+            terminals,
+            self.terminal_groups,
+            self.sync_terminals,
+            self.variables,
+            self.virtuals,
+            self.actions,
+            self.template_rules
+        );
+        hasher.0
+    }
+
     /// Build data for this grammar
     ///
     /// # Errors
     ///
     /// Return the errors produced when building the grammar
+    ///
+    /// # Panics
+    ///
+    /// A panic is raised when the generated axioms cannot be found
+    /// after the grammar has been prepared (which should not happen).
     pub fn build(
         &mut self,
         parsing_method: Option<ParsingMethod>,
         grammar_index: usize,
+    ) -> Result<BuildData, Vec<Error>> {
+        self.build_with_progress(parsing_method, grammar_index, &|_| ())
+    }
+
+    /// Build data for this grammar, notifying the progress callback along the way
+    ///
+    /// # Errors
+    ///
+    /// Return the errors produced when building the grammar
+    ///
+    /// # Panics
+    ///
+    /// A panic is raised when the generated axioms cannot be found
+    /// after the grammar has been prepared (which should not happen).
+    pub fn build_with_progress(
+        &mut self,
+        parsing_method: Option<ParsingMethod>,
+        grammar_index: usize,
+        progress: &dyn Fn(CompilationProgress),
+    ) -> Result<BuildData, Vec<Error>> {
+        self.build_from_graph(
+            parsing_method,
+            grammar_index,
+            None,
+            None,
+            DEFAULT_MAX_STATES,
+            progress,
+        )
+    }
+
+    /// Build data for this grammar, reusing the LR graph of a previous build, if any
+    /// The reused graph must have been built for the same grammar and parsing method,
+    /// e.g. for a grammar with the same fingerprint, this is not checked.
+    /// When `expected_conflicts` is specified, the build accepts exactly this number of shift/reduce conflicts,
+    /// which are resolved in favor of the shift, and fails for any other number.
+    /// The build fails as soon as the LR graph has more than `max_states` states.
+    ///
+    /// # Errors
+    ///
+    /// Return the errors produced when building the grammar
+    ///
+    /// # Panics
+    ///
+    /// A panic is raised when the generated axioms cannot be found
+    /// after the grammar has been prepared (which should not happen).
+    pub fn build_from_graph(
+        &mut self,
+        parsing_method: Option<ParsingMethod>,
+        grammar_index: usize,
+        graph: Option<Graph>,
+        expected_conflicts: Option<usize>,
+        max_states: usize,
+        progress: &dyn Fn(CompilationProgress),
     ) -> Result<BuildData, Vec<Error>> {
         if let Err(error) = self.prepare(grammar_index) {
             return Err(vec![error]);
@@ -2097,7 +3496,13 @@ impl Grammar {
                 .collect());
         }
         // Build the data for the lexer
-        let expected = dfa.get_expected();
+        let mut expected = dfa.get_expected();
+        // external terminals are not matched by the automaton
+        for terminal in &self.terminals {
+            if terminal.external_scanner.is_some() {
+                expected.add(TerminalRef::Terminal(terminal.id));
+            }
+        }
         let separator = match self.get_separator(grammar_index, &expected, &dfa) {
             Ok(separator) => separator,
             Err(error) => return Err(vec![error]),
@@ -2107,13 +3512,53 @@ impl Grammar {
             Err(error) => return Err(vec![error]),
         };
         // Build the data for the parser
-        let graph = crate::lr::build_graph(self, grammar_index, &expected, &dfa, method)?;
+        let axiom = self.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+        let entries: Vec<EntryData> = self
+            .get_entries()
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| EntryData {
+                name: entry.to_string(),
+                variable: self.get_variable_for_name(entry).unwrap().id,
+                axiom: self
+                    .get_variable_for_name(&Grammar::get_entry_axiom_name(entry))
+                    .unwrap()
+                    .id,
+                state: index + 1,
+            })
+            .collect();
+        let graph = match graph {
+            Some(graph) => graph,
+            None => crate::lr::build_graph(
+                self,
+                grammar_index,
+                &expected,
+                &dfa,
+                method,
+                axiom,
+                &entries,
+                expected_conflicts,
+                max_states,
+                progress,
+            )?,
+        };
+        // Compact the DFA now that the contexts and expected terminals are known
+        let compacted = self.compact_dfa(&dfa, &graph, separator);
+        let lexer_statistics = LexerStatistics {
+            states_before: dfa.len(),
+            states_after: compacted.len(),
+            bytes_before: get_lexer_data_size(&dfa),
+            bytes_after: get_lexer_data_size(&compacted),
+        };
         Ok(BuildData {
-            dfa,
+            dfa: compacted,
+            lexer_statistics,
             expected,
             separator,
             method,
+            axiom,
             graph,
+            entries,
         })
     }
 
@@ -2135,7 +3580,7 @@ impl Grammar {
         if terminal.context != 0 {
             return Err(Error::SeparatorIsContextual(grammar_index, terminal_ref));
         }
-        if expected.content.contains(&terminal_ref) {
+        if expected.contains(terminal_ref) {
             // the terminal is produced by the lexer => ok
             return Ok(Some(terminal_ref));
         }
@@ -2158,27 +3603,42 @@ impl Grammar {
     ) -> Result<ParsingMethod, Error> {
         match parsing_method {
             Some(method) => Ok(method),
-            None => match self.get_option(OPTION_METHOD) {
-                Some(option) => match option.value.as_ref() {
-                    "lr0" => Ok(ParsingMethod::LR0),
-                    "lr1" => Ok(ParsingMethod::LR1),
-                    "lalr1" => Ok(ParsingMethod::LALR1),
-                    "rnglr1" => Ok(ParsingMethod::RNGLR1),
-                    "rnglalr1" => Ok(ParsingMethod::RNGLALR1),
-                    _ => Err(Error::InvalidOption(
-                        grammar_index,
-                        OPTION_METHOD.to_string(),
-                        vec![
-                            String::from("lr0"),
-                            String::from("lr1"),
-                            String::from("lalr1"),
-                            String::from("rnglr1"),
-                            String::from("rnglalr1"),
-                        ],
-                    )),
-                },
-                None => Ok(ParsingMethod::LALR1),
-            },
+            None => Ok(self
+                .get_method(grammar_index)?
+                .unwrap_or(ParsingMethod::LALR1)),
+        }
+    }
+
+    /// Gets the parsing method declared in this grammar's options, if any
+    ///
+    /// Valid values for the method are: `lr0`, `lr1`, `lalr1`, `rnglr1`, `rnglalr1` and `ielr1`.
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the value for the method is not an expected one
+    pub fn get_method(&self, grammar_index: usize) -> Result<Option<ParsingMethod>, Error> {
+        let Some(option) = self.get_option(OPTION_METHOD) else {
+            return Ok(None);
+        };
+        match option.value.as_ref() {
+            "lr0" => Ok(Some(ParsingMethod::LR0)),
+            "lr1" => Ok(Some(ParsingMethod::LR1)),
+            "lalr1" => Ok(Some(ParsingMethod::LALR1)),
+            "rnglr1" => Ok(Some(ParsingMethod::RNGLR1)),
+            "rnglalr1" => Ok(Some(ParsingMethod::RNGLALR1)),
+            "ielr1" => Ok(Some(ParsingMethod::IELR1)),
+            _ => Err(Error::InvalidOption(
+                grammar_index,
+                OPTION_METHOD.to_string(),
+                vec![
+                    String::from("lr0"),
+                    String::from("lr1"),
+                    String::from("lalr1"),
+                    String::from("rnglr1"),
+                    String::from("rnglalr1"),
+                    String::from("ielr1"),
+                ],
+            )),
         }
     }
 
@@ -2197,6 +3657,9 @@ impl Display for Grammar {
         for variable in &self.variables {
             for rule in &variable.rules {
                 write!(f, "{} ->", variable.name)?;
+                if rule.body.choices[0].elements.is_empty() {
+                    write!(f, " ε")?;
+                }
                 for element in &rule.body.choices[0].elements {
                     write!(f, " {}", self.get_symbol_value(element.symbol))?;
                 }
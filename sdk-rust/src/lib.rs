@@ -21,6 +21,7 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::cast_possible_truncation, clippy::module_name_repetitions)]
 
+pub mod builder;
 pub mod errors;
 pub mod finite;
 pub mod grammars;
@@ -31,9 +32,12 @@ pub mod sdk;
 pub mod unicode;
 
 use std::cmp::Ordering;
-use std::fmt::Debug;
+use std::fmt::{Debug, Formatter};
 use std::fs;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
 
 use grammars::BuildData;
 use hime_redist::ast::AstNode;
@@ -44,6 +48,7 @@ use crate::grammars::{
     Grammar, OPTION_ACCESS_MODIFIER, OPTION_MODE, OPTION_NAMESPACE, OPTION_OUTPUT_PATH,
     OPTION_RUNTIME,
 };
+use crate::lr::Graph;
 use crate::sdk::InMemoryParser;
 
 /// The version of this program
@@ -240,6 +245,8 @@ pub enum Input<'a> {
     FileName(String),
     /// Raw input
     Raw(&'a str),
+    /// A gzip-compressed file, decompressed when opened
+    GzipFile(PathBuf),
 }
 
 impl<'a> Input<'a> {
@@ -249,6 +256,17 @@ impl<'a> Input<'a> {
         match self {
             Input::FileName(file_name) => file_name.clone(),
             Input::Raw(_) => String::from("raw input"),
+            Input::GzipFile(path) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Gets the path of the file behind this input, if any
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Input::FileName(file_name) => Some(Path::new(file_name)),
+            Input::Raw(_) => None,
+            Input::GzipFile(path) => Some(path),
         }
     }
 
@@ -261,6 +279,7 @@ impl<'a> Input<'a> {
         match self {
             Input::FileName(file_name) => Ok(Box::new(fs::File::open(file_name)?)),
             Input::Raw(text) => Ok(Box::new(text.as_bytes())),
+            Input::GzipFile(path) => Ok(Box::new(GzDecoder::new(fs::File::open(path)?))),
         }
     }
 }
@@ -272,6 +291,20 @@ pub struct LoadedInput<'t> {
     pub name: String,
     /// The input's content (full text)
     pub content: Text<'t>,
+    /// The encoding declared at the top of the input, e.g. with `% encoding UTF-8`, if any
+    /// Inputs are always decoded as UTF-8.
+    pub encoding: Option<String>,
+    /// The other inputs included by this one, e.g. with `%include "common.gram";`
+    pub includes: Vec<InputInclude>,
+}
+
+/// An include statement in an input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputInclude {
+    /// The input reference for the included path
+    pub input_ref: InputReference,
+    /// The included path, as written, relative to the including input
+    pub path: String,
 }
 
 /// Transforms into an owned static version of the data
@@ -279,6 +312,8 @@ fn loaded_input_into_static(input: LoadedInput<'_>) -> LoadedInput<'static> {
     LoadedInput {
         name: input.name,
         content: input.content.into_static(),
+        encoding: input.encoding,
+        includes: input.includes,
     }
 }
 
@@ -290,6 +325,29 @@ impl<'t> LoadedInput<'t> {
     }
 }
 
+/// Resolves the includes of loaded inputs relative to the files of the including inputs
+/// Returns the canonical paths of the included files and the errors for the files that cannot be found.
+/// The includes of inputs that are not files, e.g. raw inputs, are left to the caller.
+fn resolve_includes(inputs: &[Input], loaded: &[LoadedInput]) -> (Vec<PathBuf>, Vec<Error>) {
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    for (input, loaded) in inputs.iter().zip(loaded) {
+        let Some(directory) = input.path().and_then(Path::parent) else {
+            continue;
+        };
+        for include in &loaded.includes {
+            match directory.join(&include.path).canonicalize() {
+                Ok(path) if path.is_file() => paths.push(path),
+                _ => errors.push(Error::Parsing(
+                    include.input_ref,
+                    format!("Cannot find included file `{}`", include.path),
+                )),
+            }
+        }
+    }
+    (paths, errors)
+}
+
 /// The data resulting of loading inputs
 #[derive(Debug, Default, Clone)]
 pub struct LoadedData<'t> {
@@ -317,6 +375,39 @@ impl<'t> LoadedData<'t> {
     pub fn into_static(self) -> LoadedData<'static> {
         loaded_data_into_static(self)
     }
+
+    /// Finds a loaded grammar by its name, returning its index and the grammar
+    #[must_use]
+    pub fn find_grammar(&self, name: &str) -> Option<(usize, &Grammar)> {
+        self.grammars
+            .iter()
+            .enumerate()
+            .find(|(_, grammar)| grammar.name == name)
+    }
+
+    /// Finds the grammars that are declared with the same name as another one,
+    /// in which case inheritance cannot be resolved reliably.
+    /// An error is produced for each duplicate declaration.
+    #[must_use]
+    pub fn find_duplicate_grammars(&self) -> Vec<Error> {
+        self.grammars
+            .iter()
+            .enumerate()
+            .filter_map(|(index, grammar)| {
+                self.grammars
+                    .iter()
+                    .enumerate()
+                    .find(|(other, candidate)| *other != index && candidate.name == grammar.name)
+                    .map(|(_, other)| {
+                        Error::DuplicateGrammar(
+                            grammar.input_ref,
+                            grammar.name.clone(),
+                            other.input_ref,
+                        )
+                    })
+            })
+            .collect()
+    }
 }
 
 /// Reference to an input
@@ -386,6 +477,9 @@ pub enum ParsingMethod {
     RNGLR1,
     /// The RNGLR parsing method based on a LALR(1) graph
     RNGLALR1,
+    /// The IELR(1) parsing method, as powerful as LR(1) with nearly as few states as LALR(1)
+    /// The graph is the LALR(1) graph in which only the states with conflicts due to the LALR(1) merge are split.
+    IELR1,
 }
 
 impl ParsingMethod {
@@ -399,7 +493,10 @@ impl ParsingMethod {
     #[must_use]
     pub fn is_rnglr(self) -> bool {
         match self {
-            ParsingMethod::LR0 | ParsingMethod::LR1 | ParsingMethod::LALR1 => false,
+            ParsingMethod::LR0
+            | ParsingMethod::LR1
+            | ParsingMethod::LALR1
+            | ParsingMethod::IELR1 => false,
             ParsingMethod::RNGLR1 | ParsingMethod::RNGLALR1 => true,
         }
     }
@@ -447,8 +544,21 @@ pub enum Modifier {
     Internal,
 }
 
+/// Represents the progress of a compilation task
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CompilationProgress {
+    /// Loading the inputs (`current`, `total`)
+    LoadingGrammar(usize, usize),
+    /// Building the LR graph of a grammar
+    BuildingLRGraph,
+    /// Building the reductions of the LR graph of a grammar
+    BuildingReductions,
+    /// Generating the artifacts for a grammar
+    Generating,
+}
+
 /// Represents a compilation task for the generation of lexers and parsers from grammars
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct CompilationTask<'a> {
     /// The inputs
     pub inputs: Vec<Input<'a>>,
@@ -470,6 +580,22 @@ pub struct CompilationTask<'a> {
     pub method: Option<ParsingMethod>,
     /// Whether to print debug data when building a grammar
     pub print_debug_data: Option<bool>,
+    /// The maximum number of lookaheads printed for an item in the debug data, defaults to 8
+    pub debug_max_lookaheads: Option<usize>,
+    /// Whether to print all the lookaheads of the items in the debug data
+    pub debug_verbose: Option<bool>,
+    /// The exact number of shift/reduce conflicts that are expected in the grammars, if any, like `%expect` in yacc
+    /// When there are exactly this many, they are resolved in favor of the shift instead of failing the build.
+    /// Reduce/reduce conflicts are always reported.
+    pub expected_conflicts: Option<usize>,
+    /// The maximum number of states in the LR graphs, defaults to 100 000
+    /// The build of a grammar fails as soon as its graph exceeds it, e.g. for an accidentally ambiguous grammar.
+    pub max_states: Option<usize>,
+    /// The maximum number of tokens the lexer may produce for a grammar input
+    pub max_token_count: Option<usize>,
+    /// The maximum number of tokens the lexers of the generated in-memory parsers may produce for an input
+    /// It is set in the configuration of the parsers for their inputs given as strings.
+    pub parser_max_token_count: Option<usize>,
     /// Java-only, the path to the local maven repository to use
     pub java_maven_repository: Option<String>,
     /// Rust-only, indicates whether standard library exclusive features are enabled
@@ -479,6 +605,43 @@ pub struct CompilationTask<'a> {
     pub rust_suppress_module_doc: Option<bool>,
     /// Rust-only, indicates whether to compress automata binary files
     pub rust_compress_automata: Option<bool>,
+    /// Rust-only, indicates whether to emit the semantic actions plumbing in the generated code
+    /// (by default, only when the grammar declares semantic actions)
+    pub rust_emit_actions: Option<bool>,
+    /// The callback to notify of the progress of the compilation
+    pub progress: Option<Box<dyn Fn(CompilationProgress) + 'a>>,
+}
+
+impl Debug for CompilationTask<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompilationTask")
+            .field("inputs", &self.inputs)
+            .field("grammar_name", &self.grammar_name)
+            .field("mode", &self.mode)
+            .field("output_target", &self.output_target)
+            .field(
+                "output_target_runtime_path",
+                &self.output_target_runtime_path,
+            )
+            .field("output_path", &self.output_path)
+            .field("output_namespace", &self.output_namespace)
+            .field("output_modifier", &self.output_modifier)
+            .field("method", &self.method)
+            .field("print_debug_data", &self.print_debug_data)
+            .field("debug_max_lookaheads", &self.debug_max_lookaheads)
+            .field("debug_verbose", &self.debug_verbose)
+            .field("expected_conflicts", &self.expected_conflicts)
+            .field("max_states", &self.max_states)
+            .field("max_token_count", &self.max_token_count)
+            .field("parser_max_token_count", &self.parser_max_token_count)
+            .field("java_maven_repository", &self.java_maven_repository)
+            .field("rust_use_std", &self.rust_use_std)
+            .field("rust_suppress_module_doc", &self.rust_suppress_module_doc)
+            .field("rust_compress_automata", &self.rust_compress_automata)
+            .field("rust_emit_actions", &self.rust_emit_actions)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
 }
 
 impl<'a> CompilationTask<'a> {
@@ -597,6 +760,17 @@ impl<'a> CompilationTask<'a> {
         }
     }
 
+    /// Gets the options for rendering the items of LR graphs in the debug data
+    #[must_use]
+    pub fn get_debug_report_options(&self) -> lr::ReportOptions {
+        lr::ReportOptions {
+            max_lookaheads: self
+                .debug_max_lookaheads
+                .unwrap_or(lr::DEFAULT_MAX_REPORTED_LOOKAHEADS),
+            verbose: self.debug_verbose.unwrap_or_default(),
+        }
+    }
+
     /// Rust-only, gets whether standard library exclusive features are enabled
     #[must_use]
     pub fn get_rust_use_std(&self) -> bool {
@@ -616,12 +790,30 @@ impl<'a> CompilationTask<'a> {
         self.rust_compress_automata.unwrap_or(false)
     }
 
+    /// Rust-only, gets whether to emit the semantic actions plumbing in the generated code for the grammar
+    #[must_use]
+    pub fn get_rust_emit_actions(&self, grammar: &Grammar) -> bool {
+        self.rust_emit_actions
+            .unwrap_or(!grammar.actions.is_empty())
+    }
+
     /// Executes this task
     ///
     /// # Errors
     ///
     /// Outputs all the errors produced while loading and compiling, if any
     pub fn execute(&self) -> Result<LoadedData<'a>, Errors<'a>> {
+        self.execute_with_warnings().map(|(data, _warnings)| data)
+    }
+
+    /// Executes this task, also returning the warnings found when building the grammars:
+    /// the uses of the axiom within rules, the nullable unit rules, the redefined inherited symbols,
+    /// the unused lexical contexts and the terminals that are always shadowed by others
+    ///
+    /// # Errors
+    ///
+    /// Outputs all the errors produced while loading and compiling, if any
+    pub fn execute_with_warnings(&self) -> Result<(LoadedData<'a>, Vec<Error>), Errors<'a>> {
         let mut data = self.load()?;
         // select the grammars to build
         match &self.grammar_name {
@@ -638,6 +830,20 @@ impl<'a> CompilationTask<'a> {
             Ok(d) => d,
             Err(errors) => return Err(Errors::from(data, errors)),
         };
+        let warnings: Vec<Error> = data
+            .grammars
+            .iter()
+            .zip(all_data.iter())
+            .enumerate()
+            .flat_map(|(index, (grammar, data))| {
+                let mut warnings = grammar.find_axiom_references(index);
+                warnings.append(&mut grammar.find_nullable_unit_rules(index));
+                warnings.append(&mut grammar.find_redefined_symbols());
+                warnings.append(&mut grammar.find_unused_contexts(index, data));
+                warnings.append(&mut grammar.find_shadowed_terminals(index, data));
+                warnings
+            })
+            .collect();
         if self.print_debug_data.unwrap_or_default() {
             for (grammar, data) in data.grammars.iter().zip(all_data.iter()) {
                 println!("================ {}", &grammar.name);
@@ -646,8 +852,13 @@ impl<'a> CompilationTask<'a> {
                 let graph = lr::GraphWithGrammar {
                     grammar,
                     graph: &data.graph,
+                    options: self.get_debug_report_options(),
                 };
                 println!("{graph}");
+                println!(
+                    "================ {}, {}",
+                    &grammar.name, data.lexer_statistics
+                );
             }
         }
         if let Err(errors) = self.execute_grammar_artifacts(&data.grammars, &all_data) {
@@ -659,20 +870,102 @@ impl<'a> CompilationTask<'a> {
         self.execute_output_assembly(&data.grammars, Runtime::Java, &mut errors);
         self.execute_output_assembly(&data.grammars, Runtime::Rust, &mut errors);
         if errors.is_empty() {
-            Ok(data)
+            Ok((data, warnings))
         } else {
             Err(Errors::from(data, errors))
         }
     }
 
     /// Loads the data for this task
+    /// The files included by the inputs that are files are loaded as well, relative to the including file.
     ///
     /// # Errors
     ///
     /// Outputs all the errors obtained while loading the inputs, if any
     pub fn load(&self) -> Result<LoadedData<'a>, Errors<'a>> {
-        let inputs = loaders::open_all(&self.inputs)?;
-        loaders::load(inputs)
+        let mut inputs = self.inputs.clone();
+        // the files already loaded, so that each file is loaded once even when includes form a cycle
+        let mut loaded: Vec<PathBuf> = inputs
+            .iter()
+            .filter_map(|input| input.path()?.canonicalize().ok())
+            .collect();
+        loop {
+            let result = loaders::open_all(&inputs).and_then(|opened| {
+                loaders::load_with_progress(opened, self.max_token_count, &|current, total| {
+                    self.report_progress(CompilationProgress::LoadingGrammar(current, total));
+                })
+            });
+            let data = match &result {
+                Ok(data) => data,
+                Err(errors) => &errors.context,
+            };
+            let (included, missing) = resolve_includes(&inputs, &data.inputs);
+            let mut added = false;
+            for path in included {
+                if !loaded.contains(&path) {
+                    inputs.push(Input::FileName(path.to_string_lossy().into_owned()));
+                    loaded.push(path);
+                    added = true;
+                }
+            }
+            if added {
+                // load again with the included files
+                continue;
+            }
+            return match result {
+                Ok(data) if missing.is_empty() => Ok(data),
+                Ok(data) => Err(Errors::from(data, missing)),
+                Err(mut errors) => {
+                    errors.errors.extend(missing);
+                    Err(errors)
+                }
+            };
+        }
+    }
+
+    /// Notifies the progress callback, if any, of the progress of this task
+    pub fn report_progress(&self, progress: CompilationProgress) {
+        if let Some(callback) = self.progress.as_ref() {
+            callback(progress);
+        }
+    }
+
+    /// Builds a grammar while reporting the progress of this task
+    ///
+    /// # Errors
+    ///
+    /// Return the errors produced when building the grammar
+    pub fn build_grammar(
+        &self,
+        grammar: &mut Grammar,
+        grammar_index: usize,
+    ) -> Result<BuildData, Vec<Error>> {
+        self.build_grammar_from_graph(grammar, grammar_index, None)
+    }
+
+    /// Builds a grammar while reporting the progress of this task,
+    /// reusing the LR graph of a previous build, if any
+    /// The reused graph must have been built for a grammar with the same fingerprint.
+    ///
+    /// # Errors
+    ///
+    /// Return the errors produced when building the grammar
+    pub fn build_grammar_from_graph(
+        &self,
+        grammar: &mut Grammar,
+        grammar_index: usize,
+        graph: Option<Graph>,
+    ) -> Result<BuildData, Vec<Error>> {
+        grammar.build_from_graph(
+            self.method,
+            grammar_index,
+            graph,
+            self.expected_conflicts,
+            self.max_states.unwrap_or(lr::DEFAULT_MAX_STATES),
+            &|progress| {
+                self.report_progress(progress);
+            },
+        )
     }
 
     /// Generates the in-memory parser for a grammar
@@ -685,8 +978,11 @@ impl<'a> CompilationTask<'a> {
         grammar: &'g mut Grammar,
         grammar_index: usize,
     ) -> Result<InMemoryParser<'g>, Vec<Error>> {
-        let data = grammar.build(self.method, grammar_index)?;
-        output::build_in_memory_grammar(grammar, &data)
+        let data = self.build_grammar(grammar, grammar_index)?;
+        self.report_progress(CompilationProgress::Generating);
+        let mut parser = output::build_in_memory_grammar(grammar, &data)?;
+        parser.config.max_tokens = self.parser_max_token_count;
+        Ok(parser)
     }
 
     /// Build the specified grammars
@@ -698,7 +994,14 @@ impl<'a> CompilationTask<'a> {
         let mut results = Vec::new();
         // prepare the grammars
         for (index, grammar) in grammars.iter_mut().enumerate() {
-            match grammar.build(self.method, index) {
+            if grammar.has_stable_ids() {
+                // recover the identifiers assigned by the previous generation, if any
+                let path = output::get_stable_ids_path(self, grammar);
+                if let Ok(content) = fs::read_to_string(path) {
+                    grammar.read_stable_ids(&content);
+                }
+            }
+            match self.build_grammar(grammar, index) {
                 Ok(data) => {
                     results.push(data);
                 }
@@ -723,6 +1026,7 @@ impl<'a> CompilationTask<'a> {
         let mut errors = Vec::new();
         // prepare the grammars
         for (index, (grammar, data)) in grammars.iter().zip(data.iter()).enumerate() {
+            self.report_progress(CompilationProgress::Generating);
             if let Err(mut errs) = output::output_grammar_artifacts(self, grammar, index, data) {
                 errors.append(&mut errs);
             }
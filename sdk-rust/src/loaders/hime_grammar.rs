@@ -12,8 +12,10 @@ use hime_redist::lexers::Lexer;
 use hime_redist::parsers::lrk::{LRkAutomaton, LRkParser};
 use hime_redist::parsers::Parser;
 use hime_redist::result::{ParseResult, ParseResultAst};
-use hime_redist::symbols::{SemanticBody, SemanticElementTrait, Symbol};
-use hime_redist::text::Text;
+use hime_redist::symbols::{
+    get_metadata, SemanticBody, SemanticElementTrait, Symbol, SymbolAnnotation,
+};
+use hime_redist::text::{ParseConfig, Text};
 use hime_redist::tokens::TokenRepository;
 
 /// Static resource for the serialized lexer automaton
@@ -63,10 +65,33 @@ pub const ID_TERMINAL_BLOCK_TERMINALS: u32 = 0x001C;
 pub const ID_TERMINAL_BLOCK_RULES: u32 = 0x001D;
 /// The unique identifier for terminal `BLOCK_CONTEXT`
 pub const ID_TERMINAL_BLOCK_CONTEXT: u32 = 0x001E;
+/// The unique identifier for terminal EMPTY
+pub const ID_TERMINAL_EMPTY: u32 = 0x001F;
+/// The unique identifier for terminal `EXTERNAL_TERMINAL`
+pub const ID_TERMINAL_EXTERNAL_TERMINAL: u32 = 0x0020;
+/// The unique identifier for terminal `PRIORITY_GROUP`
+pub const ID_TERMINAL_PRIORITY_GROUP: u32 = 0x0021;
+/// The unique identifier for terminal `RECOVER_AT_EOL`
+pub const ID_TERMINAL_RECOVER_AT_EOL: u32 = 0x0022;
+/// The unique identifier for terminal `RECOVER_AT_EOF`
+pub const ID_TERMINAL_RECOVER_AT_EOF: u32 = 0x0023;
+/// The unique identifier for terminal `CASE_INSENSITIVE`
+pub const ID_TERMINAL_CASE_INSENSITIVE: u32 = 0x0024;
+/// The unique identifier for terminal ALIAS
+pub const ID_TERMINAL_ALIAS: u32 = 0x0025;
+/// The unique identifier for terminal `INCLUDE`
+pub const ID_TERMINAL_INCLUDE: u32 = 0x0026;
+/// The unique identifier for terminal `RECOVER`
+pub const ID_TERMINAL_RECOVER: u32 = 0x0027;
+/// The unique identifier for terminal `ENTRY_POINT`
+pub const ID_TERMINAL_ENTRY_POINT: u32 = 0x0028;
 
 /// The unique identifier for the default context
 pub const CONTEXT_DEFAULT: u16 = 0;
 
+/// The names of the lexical contexts, indexed by their identifier
+pub const CONTEXTS: &[&str] = &["default"];
+
 /// The collection of terminals matched by this lexer
 /// The terminals are in an order consistent with the automaton,
 /// so that terminal indices in the automaton can be used to retrieve the terminals in this table
@@ -168,361 +193,487 @@ pub const TERMINALS: &[Symbol] = &[
         name: "BLOCK_CONTEXT",
     },
     Symbol {
-        id: 0x0043,
+        id: 0x001F,
+        name: "EMPTY",
+    },
+    Symbol {
+        id: 0x0020,
+        name: "EXTERNAL_TERMINAL",
+    },
+    Symbol {
+        id: 0x0021,
+        name: "PRIORITY_GROUP",
+    },
+    Symbol {
+        id: 0x0022,
+        name: "RECOVER_AT_EOL",
+    },
+    Symbol {
+        id: 0x0023,
+        name: "RECOVER_AT_EOF",
+    },
+    Symbol {
+        id: 0x0024,
+        name: "CASE_INSENSITIVE",
+    },
+    Symbol {
+        id: 0x0025,
+        name: "ALIAS",
+    },
+    Symbol {
+        id: 0x0026,
+        name: "INCLUDE",
+    },
+    Symbol {
+        id: 0x0027,
+        name: "RECOVER",
+    },
+    Symbol {
+        id: 0x0028,
+        name: "ENTRY_POINT",
+    },
+    Symbol {
+        id: 0x0056,
         name: "=",
     },
     Symbol {
-        id: 0x0044,
+        id: 0x0057,
         name: ";",
     },
     Symbol {
-        id: 0x0045,
+        id: 0x0058,
         name: "(",
     },
     Symbol {
-        id: 0x0046,
+        id: 0x0059,
         name: ")",
     },
     Symbol {
-        id: 0x0048,
+        id: 0x005B,
         name: "{",
     },
     Symbol {
-        id: 0x0049,
+        id: 0x005C,
         name: ",",
     },
     Symbol {
-        id: 0x004A,
+        id: 0x005D,
         name: "}",
     },
     Symbol {
-        id: 0x004F,
+        id: 0x0062,
+        name: "(?!",
+    },
+    Symbol {
+        id: 0x0063,
         name: "->",
     },
     Symbol {
-        id: 0x0050,
+        id: 0x0065,
         name: "fragment",
     },
     Symbol {
-        id: 0x0052,
+        id: 0x0068,
         name: "@",
     },
     Symbol {
-        id: 0x0053,
+        id: 0x0069,
         name: "<",
     },
     Symbol {
-        id: 0x0055,
+        id: 0x006B,
         name: ">",
     },
     Symbol {
-        id: 0x0056,
+        id: 0x006C,
         name: "#",
     },
     Symbol {
-        id: 0x005E,
+        id: 0x0075,
         name: ":",
     },
     Symbol {
-        id: 0x0060,
+        id: 0x0077,
         name: "grammar",
     },
 ];
 
+/// Gets the lexer's automaton, deserialized on first use
+fn get_lexer_automaton() -> &'static Automaton {
+    static AUTOMATON: std::sync::OnceLock<Automaton> = std::sync::OnceLock::new();
+    AUTOMATON.get_or_init(|| Automaton::new(LEXER_AUTOMATON))
+}
+
 /// Creates a new lexer
 fn new_lexer<'a: 'b, 'b, 'c>(
     repository: TokenRepository<'a, 'b, 'c>,
     errors: &'c mut ParseErrors<'a>,
+    automaton: &'c Automaton,
 ) -> Lexer<'a, 'b, 'c> {
-    let automaton = Automaton::new(LEXER_AUTOMATON);
     Lexer::ContextFree(ContextFreeLexer::new(repository, errors, automaton, 0x0007))
 }
 
 /// Static resource for the serialized parser automaton
 const PARSER_AUTOMATON: &[u8] = include_bytes!("hime_grammar_parser.bin");
 
+/// Gets the parser's automaton, deserialized on first use
+fn get_parser_automaton() -> &'static LRkAutomaton {
+    static AUTOMATON: std::sync::OnceLock<LRkAutomaton> = std::sync::OnceLock::new();
+    AUTOMATON.get_or_init(|| LRkAutomaton::new(PARSER_AUTOMATON))
+}
+
 /// The unique identifier for variable option
-pub const ID_VARIABLE_OPTION: u32 = 0x001F;
+pub const ID_VARIABLE_OPTION: u32 = 0x0029;
 /// The unique identifier for variable `terminal_def_atom`
-pub const ID_VARIABLE_TERMINAL_DEF_ATOM: u32 = 0x0020;
+pub const ID_VARIABLE_TERMINAL_DEF_ATOM: u32 = 0x002A;
 /// The unique identifier for variable `terminal_def_element`
-pub const ID_VARIABLE_TERMINAL_DEF_ELEMENT: u32 = 0x0021;
+pub const ID_VARIABLE_TERMINAL_DEF_ELEMENT: u32 = 0x002B;
 /// The unique identifier for variable `terminal_def_cardinalilty`
-pub const ID_VARIABLE_TERMINAL_DEF_CARDINALILTY: u32 = 0x0022;
+pub const ID_VARIABLE_TERMINAL_DEF_CARDINALILTY: u32 = 0x002C;
 /// The unique identifier for variable `terminal_def_repetition`
-pub const ID_VARIABLE_TERMINAL_DEF_REPETITION: u32 = 0x0023;
+pub const ID_VARIABLE_TERMINAL_DEF_REPETITION: u32 = 0x002D;
 /// The unique identifier for variable `terminal_def_fragment`
-pub const ID_VARIABLE_TERMINAL_DEF_FRAGMENT: u32 = 0x0024;
+pub const ID_VARIABLE_TERMINAL_DEF_FRAGMENT: u32 = 0x002E;
 /// The unique identifier for variable `terminal_def_restrict`
-pub const ID_VARIABLE_TERMINAL_DEF_RESTRICT: u32 = 0x0025;
+pub const ID_VARIABLE_TERMINAL_DEF_RESTRICT: u32 = 0x002F;
 /// The unique identifier for variable `terminal_definition`
-pub const ID_VARIABLE_TERMINAL_DEFINITION: u32 = 0x0026;
+pub const ID_VARIABLE_TERMINAL_DEFINITION: u32 = 0x0030;
+/// The unique identifier for variable `terminal_def_lookahead`
+pub const ID_VARIABLE_TERMINAL_DEF_LOOKAHEAD: u32 = 0x0031;
+/// The unique identifier for variable `terminal_modifier`
+pub const ID_VARIABLE_TERMINAL_MODIFIER: u32 = 0x0032;
 /// The unique identifier for variable `terminal_rule`
-pub const ID_VARIABLE_TERMINAL_RULE: u32 = 0x0027;
+pub const ID_VARIABLE_TERMINAL_RULE: u32 = 0x0033;
 /// The unique identifier for variable `terminal_fragment`
-pub const ID_VARIABLE_TERMINAL_FRAGMENT: u32 = 0x0028;
+pub const ID_VARIABLE_TERMINAL_FRAGMENT: u32 = 0x0034;
 /// The unique identifier for variable `terminal_context`
-pub const ID_VARIABLE_TERMINAL_CONTEXT: u32 = 0x0029;
+pub const ID_VARIABLE_TERMINAL_CONTEXT: u32 = 0x0035;
+/// The unique identifier for variable `terminal_external`
+pub const ID_VARIABLE_TERMINAL_EXTERNAL: u32 = 0x0036;
+/// The unique identifier for variable `terminal_group`
+pub const ID_VARIABLE_TERMINAL_GROUP: u32 = 0x0037;
+/// The unique identifier for variable `terminal_alias`
+pub const ID_VARIABLE_TERMINAL_ALIAS: u32 = 0x0038;
 /// The unique identifier for variable `terminal_item`
-pub const ID_VARIABLE_TERMINAL_ITEM: u32 = 0x002A;
+pub const ID_VARIABLE_TERMINAL_ITEM: u32 = 0x0039;
+/// The unique identifier for variable `symbol_annotation`
+pub const ID_VARIABLE_SYMBOL_ANNOTATION: u32 = 0x003A;
 /// The unique identifier for variable `rule_sym_action`
-pub const ID_VARIABLE_RULE_SYM_ACTION: u32 = 0x002B;
+pub const ID_VARIABLE_RULE_SYM_ACTION: u32 = 0x003B;
 /// The unique identifier for variable `rule_sym_virtual`
-pub const ID_VARIABLE_RULE_SYM_VIRTUAL: u32 = 0x002C;
+pub const ID_VARIABLE_RULE_SYM_VIRTUAL: u32 = 0x003C;
 /// The unique identifier for variable `rule_sym_ref_params`
-pub const ID_VARIABLE_RULE_SYM_REF_PARAMS: u32 = 0x002D;
+pub const ID_VARIABLE_RULE_SYM_REF_PARAMS: u32 = 0x003D;
 /// The unique identifier for variable `rule_sym_ref_template`
-pub const ID_VARIABLE_RULE_SYM_REF_TEMPLATE: u32 = 0x002E;
+pub const ID_VARIABLE_RULE_SYM_REF_TEMPLATE: u32 = 0x003E;
 /// The unique identifier for variable `rule_sym_ref_simple`
-pub const ID_VARIABLE_RULE_SYM_REF_SIMPLE: u32 = 0x002F;
+pub const ID_VARIABLE_RULE_SYM_REF_SIMPLE: u32 = 0x003F;
 /// The unique identifier for variable `rule_def_atom`
-pub const ID_VARIABLE_RULE_DEF_ATOM: u32 = 0x0030;
+pub const ID_VARIABLE_RULE_DEF_ATOM: u32 = 0x0040;
 /// The unique identifier for variable `rule_def_context`
-pub const ID_VARIABLE_RULE_DEF_CONTEXT: u32 = 0x0031;
+pub const ID_VARIABLE_RULE_DEF_CONTEXT: u32 = 0x0041;
 /// The unique identifier for variable `rule_def_sub`
-pub const ID_VARIABLE_RULE_DEF_SUB: u32 = 0x0032;
+pub const ID_VARIABLE_RULE_DEF_SUB: u32 = 0x0042;
 /// The unique identifier for variable `rule_def_element`
-pub const ID_VARIABLE_RULE_DEF_ELEMENT: u32 = 0x0033;
+pub const ID_VARIABLE_RULE_DEF_ELEMENT: u32 = 0x0043;
 /// The unique identifier for variable `rule_def_tree_action`
-pub const ID_VARIABLE_RULE_DEF_TREE_ACTION: u32 = 0x0034;
+pub const ID_VARIABLE_RULE_DEF_TREE_ACTION: u32 = 0x0044;
 /// The unique identifier for variable `rule_def_repetition`
-pub const ID_VARIABLE_RULE_DEF_REPETITION: u32 = 0x0035;
+pub const ID_VARIABLE_RULE_DEF_REPETITION: u32 = 0x0045;
 /// The unique identifier for variable `rule_def_fragment`
-pub const ID_VARIABLE_RULE_DEF_FRAGMENT: u32 = 0x0036;
+pub const ID_VARIABLE_RULE_DEF_FRAGMENT: u32 = 0x0046;
 /// The unique identifier for variable `rule_def_choice`
-pub const ID_VARIABLE_RULE_DEF_CHOICE: u32 = 0x0037;
+pub const ID_VARIABLE_RULE_DEF_CHOICE: u32 = 0x0047;
 /// The unique identifier for variable `rule_definition`
-pub const ID_VARIABLE_RULE_DEFINITION: u32 = 0x0038;
+pub const ID_VARIABLE_RULE_DEFINITION: u32 = 0x0048;
 /// The unique identifier for variable `rule_template_params`
-pub const ID_VARIABLE_RULE_TEMPLATE_PARAMS: u32 = 0x0039;
+pub const ID_VARIABLE_RULE_TEMPLATE_PARAMS: u32 = 0x0049;
 /// The unique identifier for variable `cf_rule_template`
-pub const ID_VARIABLE_CF_RULE_TEMPLATE: u32 = 0x003A;
+pub const ID_VARIABLE_CF_RULE_TEMPLATE: u32 = 0x004A;
 /// The unique identifier for variable `cf_rule_simple`
-pub const ID_VARIABLE_CF_RULE_SIMPLE: u32 = 0x003B;
+pub const ID_VARIABLE_CF_RULE_SIMPLE: u32 = 0x004B;
+/// The unique identifier for variable `recover_set`
+pub const ID_VARIABLE_RECOVER_SET: u32 = 0x004C;
+/// The unique identifier for variable `entry_point`
+pub const ID_VARIABLE_ENTRY_POINT: u32 = 0x004D;
 /// The unique identifier for variable `cf_rule`
-pub const ID_VARIABLE_CF_RULE: u32 = 0x003C;
+pub const ID_VARIABLE_CF_RULE: u32 = 0x004E;
 /// The unique identifier for variable `grammar_options`
-pub const ID_VARIABLE_GRAMMAR_OPTIONS: u32 = 0x003D;
+pub const ID_VARIABLE_GRAMMAR_OPTIONS: u32 = 0x004F;
 /// The unique identifier for variable `grammar_terminals`
-pub const ID_VARIABLE_GRAMMAR_TERMINALS: u32 = 0x003E;
+pub const ID_VARIABLE_GRAMMAR_TERMINALS: u32 = 0x0050;
 /// The unique identifier for variable `grammar_cf_rules`
-pub const ID_VARIABLE_GRAMMAR_CF_RULES: u32 = 0x003F;
+pub const ID_VARIABLE_GRAMMAR_CF_RULES: u32 = 0x0051;
 /// The unique identifier for variable `grammar_parency`
-pub const ID_VARIABLE_GRAMMAR_PARENCY: u32 = 0x0040;
+pub const ID_VARIABLE_GRAMMAR_PARENCY: u32 = 0x0052;
 /// The unique identifier for variable `cf_grammar`
-pub const ID_VARIABLE_CF_GRAMMAR: u32 = 0x0041;
+pub const ID_VARIABLE_CF_GRAMMAR: u32 = 0x0053;
+/// The unique identifier for variable `include_statement`
+pub const ID_VARIABLE_INCLUDE_STATEMENT: u32 = 0x0054;
 /// The unique identifier for variable file
-pub const ID_VARIABLE_FILE: u32 = 0x0042;
+pub const ID_VARIABLE_FILE: u32 = 0x0055;
 
 /// The unique identifier for virtual range
-pub const ID_VIRTUAL_RANGE: u32 = 0x0047;
+pub const ID_VIRTUAL_RANGE: u32 = 0x005A;
 /// The unique identifier for virtual concat
-pub const ID_VIRTUAL_CONCAT: u32 = 0x004B;
+pub const ID_VIRTUAL_CONCAT: u32 = 0x005E;
 /// The unique identifier for virtual emptypart
-pub const ID_VIRTUAL_EMPTYPART: u32 = 0x0058;
+pub const ID_VIRTUAL_EMPTYPART: u32 = 0x006E;
 
 /// The collection of variables matched by this parser
 /// The variables are in an order consistent with the automaton,
 /// so that variable indices in the automaton can be used to retrieve the variables in this table
 pub const VARIABLES: &[Symbol] = &[
     Symbol {
-        id: 0x001F,
+        id: 0x0029,
         name: "option",
     },
     Symbol {
-        id: 0x0020,
+        id: 0x002A,
         name: "terminal_def_atom",
     },
     Symbol {
-        id: 0x0021,
+        id: 0x002B,
         name: "terminal_def_element",
     },
     Symbol {
-        id: 0x0022,
+        id: 0x002C,
         name: "terminal_def_cardinalilty",
     },
     Symbol {
-        id: 0x0023,
+        id: 0x002D,
         name: "terminal_def_repetition",
     },
     Symbol {
-        id: 0x0024,
+        id: 0x002E,
         name: "terminal_def_fragment",
     },
     Symbol {
-        id: 0x0025,
+        id: 0x002F,
         name: "terminal_def_restrict",
     },
     Symbol {
-        id: 0x0026,
+        id: 0x0030,
         name: "terminal_definition",
     },
     Symbol {
-        id: 0x0027,
+        id: 0x0031,
+        name: "terminal_def_lookahead",
+    },
+    Symbol {
+        id: 0x0032,
+        name: "terminal_modifier",
+    },
+    Symbol {
+        id: 0x0033,
         name: "terminal_rule",
     },
     Symbol {
-        id: 0x0028,
+        id: 0x0034,
         name: "terminal_fragment",
     },
     Symbol {
-        id: 0x0029,
+        id: 0x0035,
         name: "terminal_context",
     },
     Symbol {
-        id: 0x002A,
+        id: 0x0036,
+        name: "terminal_external",
+    },
+    Symbol {
+        id: 0x0037,
+        name: "terminal_group",
+    },
+    Symbol {
+        id: 0x0038,
+        name: "terminal_alias",
+    },
+    Symbol {
+        id: 0x0039,
         name: "terminal_item",
     },
     Symbol {
-        id: 0x002B,
+        id: 0x003A,
+        name: "symbol_annotation",
+    },
+    Symbol {
+        id: 0x003B,
         name: "rule_sym_action",
     },
     Symbol {
-        id: 0x002C,
+        id: 0x003C,
         name: "rule_sym_virtual",
     },
     Symbol {
-        id: 0x002D,
+        id: 0x003D,
         name: "rule_sym_ref_params",
     },
     Symbol {
-        id: 0x002E,
+        id: 0x003E,
         name: "rule_sym_ref_template",
     },
     Symbol {
-        id: 0x002F,
+        id: 0x003F,
         name: "rule_sym_ref_simple",
     },
     Symbol {
-        id: 0x0030,
+        id: 0x0040,
         name: "rule_def_atom",
     },
     Symbol {
-        id: 0x0031,
+        id: 0x0041,
         name: "rule_def_context",
     },
     Symbol {
-        id: 0x0032,
+        id: 0x0042,
         name: "rule_def_sub",
     },
     Symbol {
-        id: 0x0033,
+        id: 0x0043,
         name: "rule_def_element",
     },
     Symbol {
-        id: 0x0034,
+        id: 0x0044,
         name: "rule_def_tree_action",
     },
     Symbol {
-        id: 0x0035,
+        id: 0x0045,
         name: "rule_def_repetition",
     },
     Symbol {
-        id: 0x0036,
+        id: 0x0046,
         name: "rule_def_fragment",
     },
     Symbol {
-        id: 0x0037,
+        id: 0x0047,
         name: "rule_def_choice",
     },
     Symbol {
-        id: 0x0038,
+        id: 0x0048,
         name: "rule_definition",
     },
     Symbol {
-        id: 0x0039,
+        id: 0x0049,
         name: "rule_template_params",
     },
     Symbol {
-        id: 0x003A,
+        id: 0x004A,
         name: "cf_rule_template",
     },
     Symbol {
-        id: 0x003B,
+        id: 0x004B,
         name: "cf_rule_simple",
     },
     Symbol {
-        id: 0x003C,
+        id: 0x004C,
+        name: "recover_set",
+    },
+    Symbol {
+        id: 0x004D,
+        name: "entry_point",
+    },
+    Symbol {
+        id: 0x004E,
         name: "cf_rule",
     },
     Symbol {
-        id: 0x003D,
+        id: 0x004F,
         name: "grammar_options",
     },
     Symbol {
-        id: 0x003E,
+        id: 0x0050,
         name: "grammar_terminals",
     },
     Symbol {
-        id: 0x003F,
+        id: 0x0051,
         name: "grammar_cf_rules",
     },
     Symbol {
-        id: 0x0040,
+        id: 0x0052,
         name: "grammar_parency",
     },
     Symbol {
-        id: 0x0041,
+        id: 0x0053,
         name: "cf_grammar",
     },
     Symbol {
-        id: 0x0042,
+        id: 0x0054,
+        name: "include_statement",
+    },
+    Symbol {
+        id: 0x0055,
         name: "file",
     },
     Symbol {
-        id: 0x004C,
-        name: "__V76",
+        id: 0x005F,
+        name: "__V95",
     },
     Symbol {
-        id: 0x004D,
-        name: "__V77",
+        id: 0x0060,
+        name: "__V96",
     },
     Symbol {
-        id: 0x004E,
-        name: "__V78",
+        id: 0x0061,
+        name: "__V97",
     },
     Symbol {
-        id: 0x0051,
-        name: "__V81",
+        id: 0x0064,
+        name: "__V100",
     },
     Symbol {
-        id: 0x0054,
-        name: "__V84",
+        id: 0x0066,
+        name: "__V102",
     },
     Symbol {
-        id: 0x0057,
-        name: "__V87",
+        id: 0x0067,
+        name: "__V103",
     },
     Symbol {
-        id: 0x0059,
-        name: "__V89",
+        id: 0x006A,
+        name: "__V106",
     },
     Symbol {
-        id: 0x005A,
-        name: "__V90",
+        id: 0x006D,
+        name: "__V109",
     },
     Symbol {
-        id: 0x005B,
-        name: "__V91",
+        id: 0x006F,
+        name: "__V111",
     },
     Symbol {
-        id: 0x005C,
-        name: "__V92",
+        id: 0x0070,
+        name: "__V112",
     },
     Symbol {
-        id: 0x005D,
-        name: "__V93",
+        id: 0x0071,
+        name: "__V113",
     },
     Symbol {
-        id: 0x005F,
-        name: "__V95",
+        id: 0x0072,
+        name: "__V114",
     },
     Symbol {
-        id: 0x0061,
-        name: "__V97",
+        id: 0x0073,
+        name: "__V115",
     },
     Symbol {
-        id: 0x0062,
+        id: 0x0074,
+        name: "__V116",
+    },
+    Symbol {
+        id: 0x0076,
+        name: "__V118",
+    },
+    Symbol {
+        id: 0x0078,
+        name: "__V120",
+    },
+    Symbol {
+        id: 0x0079,
+        name: "__V121",
+    },
+    Symbol {
+        id: 0x007A,
         name: "__VAxiom",
     },
 ];
@@ -532,19 +683,28 @@ pub const VARIABLES: &[Symbol] = &[
 /// so that virtual indices in the automaton can be used to retrieve the virtuals in this table
 pub const VIRTUALS: &[Symbol] = &[
     Symbol {
-        id: 0x0047,
+        id: 0x005A,
         name: "range",
     },
     Symbol {
-        id: 0x004B,
+        id: 0x005E,
         name: "concat",
     },
     Symbol {
-        id: 0x0058,
+        id: 0x006E,
         name: "emptypart",
     },
 ];
 
+/// The user-defined annotations on the terminals and variables
+pub const ANNOTATIONS: &[SymbolAnnotation] = &[];
+
+/// Gets the value of the annotation with the specified key on a terminal or a variable, if any
+#[must_use]
+pub fn metadata(symbol_id: u32, key: &str) -> Option<&'static str> {
+    get_metadata(ANNOTATIONS, symbol_id, key)
+}
+
 /// Parses the specified string with this parser
 #[must_use]
 pub fn parse_str(input: &str) -> ParseResult<'static, '_, 'static, AstImpl> {
@@ -559,6 +719,13 @@ pub fn parse_string(input: String) -> ParseResultAst {
     parse_text(text)
 }
 
+/// Parses the specified string with this parser and a configuration
+#[must_use]
+pub fn parse_string_with_config(input: String, config: ParseConfig) -> ParseResultAst {
+    let text = Text::from_string(input).with_config(config);
+    parse_text(text)
+}
+
 /// Parses the specified stream of UTF-8 with this parser
 ///
 /// # Errors
@@ -585,8 +752,8 @@ fn parse_text_with<'s, 't, 'a>(
     let mut result = ParseResult::<AstImpl>::new(terminals, variables, virtuals, text);
     {
         let data = result.get_parsing_data();
-        let mut lexer = new_lexer(data.0, data.1);
-        let automaton = LRkAutomaton::new(PARSER_AUTOMATON);
+        let mut lexer = new_lexer(data.0, data.1, get_lexer_automaton());
+        let automaton = get_parser_automaton();
         let mut parser = LRkParser::new(
             &mut lexer,
             variables,
@@ -624,6 +791,7 @@ pub trait Visitor {
     fn on_terminal_block_terminals(&self, _node: &AstNode) {}
     fn on_terminal_block_rules(&self, _node: &AstNode) {}
     fn on_terminal_block_context(&self, _node: &AstNode) {}
+    fn on_terminal_empty(&self, _node: &AstNode) {}
     fn on_variable_option(&self, _node: &AstNode) {}
     fn on_variable_terminal_def_atom(&self, _node: &AstNode) {}
     fn on_variable_terminal_def_element(&self, _node: &AstNode) {}
@@ -701,45 +869,46 @@ pub fn visit_ast_node(node: AstNode, visitor: &dyn Visitor) {
         0x001C => visitor.on_terminal_block_terminals(&node),
         0x001D => visitor.on_terminal_block_rules(&node),
         0x001E => visitor.on_terminal_block_context(&node),
-        0x001F => visitor.on_variable_option(&node),
-        0x0020 => visitor.on_variable_terminal_def_atom(&node),
-        0x0021 => visitor.on_variable_terminal_def_element(&node),
-        0x0022 => visitor.on_variable_terminal_def_cardinalilty(&node),
-        0x0023 => visitor.on_variable_terminal_def_repetition(&node),
-        0x0024 => visitor.on_variable_terminal_def_fragment(&node),
-        0x0025 => visitor.on_variable_terminal_def_restrict(&node),
-        0x0026 => visitor.on_variable_terminal_definition(&node),
-        0x0027 => visitor.on_variable_terminal_rule(&node),
-        0x0028 => visitor.on_variable_terminal_fragment(&node),
-        0x0029 => visitor.on_variable_terminal_context(&node),
-        0x002A => visitor.on_variable_terminal_item(&node),
-        0x002B => visitor.on_variable_rule_sym_action(&node),
-        0x002C => visitor.on_variable_rule_sym_virtual(&node),
-        0x002D => visitor.on_variable_rule_sym_ref_params(&node),
-        0x002E => visitor.on_variable_rule_sym_ref_template(&node),
-        0x002F => visitor.on_variable_rule_sym_ref_simple(&node),
-        0x0030 => visitor.on_variable_rule_def_atom(&node),
-        0x0031 => visitor.on_variable_rule_def_context(&node),
-        0x0032 => visitor.on_variable_rule_def_sub(&node),
-        0x0033 => visitor.on_variable_rule_def_element(&node),
-        0x0034 => visitor.on_variable_rule_def_tree_action(&node),
-        0x0035 => visitor.on_variable_rule_def_repetition(&node),
-        0x0036 => visitor.on_variable_rule_def_fragment(&node),
-        0x0037 => visitor.on_variable_rule_def_choice(&node),
-        0x0038 => visitor.on_variable_rule_definition(&node),
-        0x0039 => visitor.on_variable_rule_template_params(&node),
-        0x003A => visitor.on_variable_cf_rule_template(&node),
-        0x003B => visitor.on_variable_cf_rule_simple(&node),
-        0x003C => visitor.on_variable_cf_rule(&node),
-        0x003D => visitor.on_variable_grammar_options(&node),
-        0x003E => visitor.on_variable_grammar_terminals(&node),
-        0x003F => visitor.on_variable_grammar_cf_rules(&node),
-        0x0040 => visitor.on_variable_grammar_parency(&node),
-        0x0041 => visitor.on_variable_cf_grammar(&node),
-        0x0042 => visitor.on_variable_file(&node),
-        0x0047 => visitor.on_virtual_range(&node),
-        0x004B => visitor.on_virtual_concat(&node),
-        0x0058 => visitor.on_virtual_emptypart(&node),
+        0x001F => visitor.on_terminal_empty(&node),
+        0x0020 => visitor.on_variable_option(&node),
+        0x0021 => visitor.on_variable_terminal_def_atom(&node),
+        0x0022 => visitor.on_variable_terminal_def_element(&node),
+        0x0023 => visitor.on_variable_terminal_def_cardinalilty(&node),
+        0x0024 => visitor.on_variable_terminal_def_repetition(&node),
+        0x0025 => visitor.on_variable_terminal_def_fragment(&node),
+        0x0026 => visitor.on_variable_terminal_def_restrict(&node),
+        0x0027 => visitor.on_variable_terminal_definition(&node),
+        0x0028 => visitor.on_variable_terminal_rule(&node),
+        0x0029 => visitor.on_variable_terminal_fragment(&node),
+        0x002A => visitor.on_variable_terminal_context(&node),
+        0x002B => visitor.on_variable_terminal_item(&node),
+        0x002C => visitor.on_variable_rule_sym_action(&node),
+        0x002D => visitor.on_variable_rule_sym_virtual(&node),
+        0x002E => visitor.on_variable_rule_sym_ref_params(&node),
+        0x002F => visitor.on_variable_rule_sym_ref_template(&node),
+        0x0030 => visitor.on_variable_rule_sym_ref_simple(&node),
+        0x0031 => visitor.on_variable_rule_def_atom(&node),
+        0x0032 => visitor.on_variable_rule_def_context(&node),
+        0x0033 => visitor.on_variable_rule_def_sub(&node),
+        0x0034 => visitor.on_variable_rule_def_element(&node),
+        0x0035 => visitor.on_variable_rule_def_tree_action(&node),
+        0x0036 => visitor.on_variable_rule_def_repetition(&node),
+        0x0037 => visitor.on_variable_rule_def_fragment(&node),
+        0x0038 => visitor.on_variable_rule_def_choice(&node),
+        0x0039 => visitor.on_variable_rule_definition(&node),
+        0x003A => visitor.on_variable_rule_template_params(&node),
+        0x003B => visitor.on_variable_cf_rule_template(&node),
+        0x003C => visitor.on_variable_cf_rule_simple(&node),
+        0x003D => visitor.on_variable_cf_rule(&node),
+        0x003E => visitor.on_variable_grammar_options(&node),
+        0x003F => visitor.on_variable_grammar_terminals(&node),
+        0x0040 => visitor.on_variable_grammar_cf_rules(&node),
+        0x0041 => visitor.on_variable_grammar_parency(&node),
+        0x0042 => visitor.on_variable_cf_grammar(&node),
+        0x0043 => visitor.on_variable_file(&node),
+        0x0048 => visitor.on_virtual_range(&node),
+        0x004C => visitor.on_virtual_concat(&node),
+        0x0059 => visitor.on_virtual_emptypart(&node),
         _ => (),
     };
 }
@@ -23,7 +23,8 @@ use std::borrow::Cow;
 use std::io::{self, Read};
 
 use hime_redist::ast::{Ast, AstImpl, AstNode};
-use hime_redist::errors::ParseErrorDataTrait;
+use hime_redist::errors::{ParseError, ParseErrorDataTrait};
+use hime_redist::lexers::automaton::RecoveryPoint;
 use hime_redist::lexers::DEFAULT_CONTEXT;
 use hime_redist::parsers::{
     TREE_ACTION_DROP, TREE_ACTION_NONE, TREE_ACTION_PROMOTE, TREE_ACTION_REPLACE_BY_CHILDREN,
@@ -31,15 +32,19 @@ use hime_redist::parsers::{
 };
 use hime_redist::result::{ParseResult, ParseResultAst};
 use hime_redist::symbols::SemanticElementTrait;
+use hime_redist::text::ParseConfig;
 
 use crate::errors::{Error, Errors};
-use crate::finite::{FinalItem, NFA};
+use crate::finite::{FinalItem, DFA, NFA};
 use crate::grammars::{
-    BodySet, Grammar, Rule, RuleBody, SymbolRef, TemplateRuleBody, TemplateRuleParam,
-    TemplateRuleRef, TemplateRuleSymbol, TerminalReference, DEFAULT_CONTEXT_NAME,
+    Annotation, BodySet, Grammar, GrammarReference, Rule, RuleBody, SymbolDefinition, SymbolRef,
+    TemplateRuleBody, TemplateRuleParam, TemplateRuleRef, TemplateRuleSymbol, TerminalRef,
+    TerminalReference, DEFAULT_CONTEXT_NAME,
 };
 use crate::unicode::{Span, BLOCKS, CATEGORIES};
-use crate::{CharSpan, Input, InputReference, LoadedData, LoadedInput, CHARSPAN_INVALID};
+use crate::{
+    CharSpan, Input, InputInclude, InputReference, LoadedData, LoadedInput, CHARSPAN_INVALID,
+};
 
 /// Represents a generalised input for a loader
 pub struct LoadInput<'a>(String, Box<dyn Read + 'a>);
@@ -65,9 +70,12 @@ pub fn open_all<'t>(inputs: &[Input<'t>]) -> Result<Vec<LoadInput<'t>>, Errors<'
     }
 }
 
+/// The name of an input, with the encoding it declares, if any
+type InputName = (String, Option<String>);
+
 /// Build the loaded data structure
 fn build_loaded_data<'t>(
-    names: Vec<String>,
+    names: Vec<InputName>,
     parse_results: Vec<ParseResult<'_, 't, '_, AstImpl>>,
     grammars: Vec<Grammar>,
 ) -> LoadedData<'t> {
@@ -75,9 +83,11 @@ fn build_loaded_data<'t>(
         inputs: names
             .into_iter()
             .zip(parse_results)
-            .map(|(name, result)| LoadedInput {
+            .map(|((name, encoding), result)| LoadedInput {
                 name,
                 content: result.text,
+                encoding,
+                includes: Vec::new(),
             })
             .collect(),
         grammars,
@@ -99,22 +109,44 @@ pub fn load_inputs<'t>(inputs: &[Input<'t>]) -> Result<LoadedData<'t>, Errors<'t
 ///
 /// Return the parsing and loading errors if any
 pub fn load(inputs: Vec<LoadInput>) -> Result<LoadedData, Errors> {
+    load_with_progress(inputs, None, &|_, _| ())
+}
+
+/// Loads all inputs into grammars,
+/// notifying the progress callback (`current`, `total`) before each input is parsed
+/// When specified, the lexer stops with an error on an input producing more than `max_tokens` tokens
+///
+/// # Errors
+///
+/// Return the parsing and loading errors if any
+pub fn load_with_progress<'t>(
+    inputs: Vec<LoadInput<'t>>,
+    max_tokens: Option<usize>,
+    progress: &dyn Fn(usize, usize),
+) -> Result<LoadedData<'t>, Errors<'t>> {
     // parse
-    let (names, results) = parse_inputs(inputs)?;
+    let (names, results) = parse_inputs(inputs, max_tokens, progress)?;
     // extract grammar roots
     let asts: Vec<Ast> = results
         .iter()
         .map(hime_redist::result::ParseResult::<AstImpl>::get_ast)
         .collect();
     let doc_roots: Vec<AstNode> = asts.iter().map(hime_redist::ast::Ast::get_root).collect();
-    let roots: Vec<(usize, AstNode)> = doc_roots
+    let (includes, roots): (Vec<_>, Vec<_>) = doc_roots
         .iter()
         .enumerate()
         .flat_map(|(index, &doc_root)| doc_root.into_iter().map(move |root| (index, root)))
+        .partition(|(_, root)| root.get_symbol().id == hime_grammar::ID_VARIABLE_INCLUDE_STATEMENT);
+    let includes: Vec<(usize, InputInclude)> = includes
+        .into_iter()
+        .map(|(input_index, node)| (input_index, load_include(input_index, node)))
         .collect();
     // get the grammars
     let (grammars, errors) = do_load_grammars(&roots);
-    let data = build_loaded_data(names, results, grammars);
+    let mut data = build_loaded_data(names, results, grammars);
+    for (input_index, include) in includes {
+        data.inputs[input_index].includes.push(include);
+    }
     if errors.is_empty() {
         Ok(data)
     } else {
@@ -122,6 +154,16 @@ pub fn load(inputs: Vec<LoadInput>) -> Result<LoadedData, Errors> {
     }
 }
 
+/// Loads an include statement
+fn load_include(input_index: usize, node: AstNode) -> InputInclude {
+    let path_node = node.child(0);
+    let value = path_node.get_value().unwrap();
+    InputInclude {
+        input_ref: InputReference::from(input_index, &path_node),
+        path: replace_escapees(&value[1..(value.len() - 1)]).into_owned(),
+    }
+}
+
 /// Loads grammars from AST roots
 ///
 /// # Errors
@@ -186,29 +228,65 @@ fn resolve_inheritance<'s, 't, 'a>(
     }
 }
 
-/// Parses the specified input stream
+/// The byte order mark that may start an UTF-8 input
+const UTF8_BOM: char = '\u{FEFF}';
+
+/// The name that refers to the end of the input in the sets of terminals for error recovery
+const RECOVER_END_OF_INPUT: &str = "EOF";
+
+/// Reads the content of an input stream
+/// A leading byte order mark is removed.
+/// An encoding declaration on the first line, e.g. `% encoding UTF-8`, is returned
+/// and replaced by blanks, so that the positions in the content are unchanged.
+fn read_input_stream(content: Box<dyn Read + '_>) -> io::Result<(String, Option<String>)> {
+    let mut text = String::new();
+    io::BufReader::new(content).read_to_string(&mut text)?;
+    if text.starts_with(UTF8_BOM) {
+        text.drain(..UTF8_BOM.len_utf8());
+    }
+    let first_line = text.lines().next().unwrap_or_default();
+    let encoding = first_line.strip_prefix('%').and_then(|directive| {
+        let mut parts = directive.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("encoding"), Some(name), None) => Some(name.to_string()),
+            _ => None,
+        }
+    });
+    if encoding.is_some() {
+        let blank = first_line.chars().map(|_| ' ').collect::<String>();
+        text.replace_range(..first_line.len(), &blank);
+    }
+    Ok((text, encoding))
+}
+
+/// Parses the content of an input
 #[allow(clippy::result_large_err)]
-fn parse_input_stream<'a>(
-    content: Box<dyn Read + 'a>,
+fn parse_input_content(
+    content: String,
     input_index: usize,
+    max_tokens: Option<usize>,
 ) -> Result<ParseResultAst, (Option<ParseResultAst>, Vec<Error>)> {
-    let mut reader = io::BufReader::new(content);
-    let result =
-        hime_grammar::parse_utf8_stream(&mut reader).map_err(|e| (None, vec![Error::Io(e)]))?;
+    let config = ParseConfig {
+        max_tokens,
+        ..ParseConfig::default()
+    };
+    let result = hime_grammar::parse_string_with_config(content, config);
     let errors: Vec<Error> = result
         .errors
         .errors
         .iter()
         .map(|error| {
-            let position = error.get_position();
-            Error::Parsing(
-                InputReference {
-                    input_index,
-                    position,
-                    length: error.get_length(),
-                },
-                error.to_string(),
-            )
+            let input_ref = InputReference {
+                input_index,
+                position: error.get_position(),
+                length: error.get_length(),
+            };
+            match error {
+                ParseError::MaxTokensExceeded(error) => {
+                    Error::MaxTokensExceeded(input_ref, error.get_count())
+                }
+                error => Error::Parsing(input_ref, error.to_string()),
+            }
         })
         .collect();
     if errors.is_empty() {
@@ -219,14 +297,29 @@ fn parse_input_stream<'a>(
 }
 
 /// Parses all inputs
-fn parse_inputs(inputs: Vec<LoadInput>) -> Result<(Vec<String>, Vec<ParseResultAst>), Errors> {
+fn parse_inputs<'t>(
+    inputs: Vec<LoadInput<'t>>,
+    max_tokens: Option<usize>,
+    progress: &dyn Fn(usize, usize),
+) -> Result<(Vec<InputName>, Vec<ParseResultAst>), Errors<'t>> {
     let mut names = Vec::new();
     let mut results = Vec::new();
     let mut has_errors = false;
     let mut errors = Vec::new();
+    let total = inputs.len();
     for (index, input) in inputs.into_iter().enumerate() {
-        names.push(input.0);
-        match parse_input_stream(input.1, index) {
+        progress(index + 1, total);
+        let parsed = match read_input_stream(input.1) {
+            Ok((content, encoding)) => {
+                names.push((input.0, encoding));
+                parse_input_content(content, index, max_tokens)
+            }
+            Err(error) => {
+                names.push((input.0, None));
+                Err((None, vec![Error::Io(error)]))
+            }
+        };
+        match parsed {
             Ok(result) => {
                 results.push(result);
             }
@@ -272,16 +365,25 @@ impl<'s, 't, 'a> Loader<'s, 't, 'a> {
     ) -> Loader<'s, 't, 'a> {
         let input_ref = InputReference::from(input_index, &root.child(0));
         let name = root.child(0).get_value().unwrap();
-        let inherited = root
+        let inherited: Vec<&'a str> = root
             .child(1)
             .into_iter()
             .map(|node| node.get_value().unwrap())
             .collect();
+        let mut grammar = Grammar::new(input_ref, name.to_string());
+        grammar.parents = root
+            .child(1)
+            .into_iter()
+            .map(|node| GrammarReference {
+                input_ref: InputReference::from(input_index, &node),
+                name: node.get_value().unwrap().to_string(),
+            })
+            .collect();
         let mut loader = Loader {
             input_index,
             root,
             inherited,
-            grammar: Grammar::new(input_ref, name.to_string()),
+            grammar,
             case_insensitive: false,
         };
         if loader.is_solved() {
@@ -316,8 +418,18 @@ impl<'s, 't, 'a> Loader<'s, 't, 'a> {
     fn load(&mut self, completed: &[Loader], errors: &mut Vec<Error>) -> bool {
         let mut modified = false;
         let grammar = &mut self.grammar;
+        let (input_index, root) = (self.input_index, self.root);
         self.inherited.retain(|parent| {
             if let Some(loader) = completed.iter().find(|l| &l.grammar.name == parent) {
+                let node = root
+                    .child(1)
+                    .into_iter()
+                    .find(|node| node.get_value() == Some(*parent))
+                    .unwrap();
+                errors.append(&mut grammar.find_inheritance_conflicts(
+                    InputReference::from(input_index, &node),
+                    &loader.grammar,
+                ));
                 grammar.inherit(&loader.grammar);
                 modified = true;
                 false
@@ -387,8 +499,20 @@ fn load_terminals(
     grammar: &mut Grammar,
     node: AstNode,
 ) {
+    // the annotations waiting for the next declared terminal
+    let mut annotations = Vec::new();
     for child in node {
         let id = child.get_symbol().id;
+        if id == hime_grammar::ID_VARIABLE_SYMBOL_ANNOTATION {
+            annotations.push(load_annotation(input_index, child));
+            continue;
+        }
+        let declares_terminal = id == hime_grammar::ID_VARIABLE_TERMINAL_FRAGMENT
+            || id == hime_grammar::ID_VARIABLE_TERMINAL_RULE
+            || id == hime_grammar::ID_VARIABLE_TERMINAL_EXTERNAL;
+        if !declares_terminal {
+            report_dangling_annotations(errors, &mut annotations);
+        }
         if id == hime_grammar::ID_TERMINAL_BLOCK_CONTEXT {
             load_terminal_rule_context(input_index, errors, grammar, child);
         } else if id == hime_grammar::ID_VARIABLE_TERMINAL_FRAGMENT {
@@ -409,10 +533,24 @@ fn load_terminals(
                 DEFAULT_CONTEXT_NAME,
                 false,
             );
+        } else if id == hime_grammar::ID_VARIABLE_TERMINAL_EXTERNAL {
+            load_terminal_external(input_index, errors, grammar, child);
+        } else if id == hime_grammar::ID_VARIABLE_TERMINAL_GROUP {
+            load_terminal_group(input_index, errors, grammar, child);
+        } else if id == hime_grammar::ID_VARIABLE_TERMINAL_ALIAS {
+            load_terminal_alias(input_index, errors, grammar, child);
         } else {
             panic!("Unrecognized symbol: {}", node.get_symbol().name);
         }
+        if declares_terminal {
+            annotate_terminal(
+                grammar,
+                child.child(0).get_value().unwrap(),
+                &mut annotations,
+            );
+        }
     }
+    report_dangling_annotations(errors, &mut annotations);
 }
 
 /// Loads the terminal context in the given AST
@@ -424,11 +562,53 @@ fn load_terminal_rule_context(
 ) {
     let name = node.child(0).get_value().unwrap();
     grammar.resolve_context(name);
+    let mut annotations = Vec::new();
     for child in node.into_iter().skip(1) {
+        if child.get_symbol().id == hime_grammar::ID_VARIABLE_SYMBOL_ANNOTATION {
+            annotations.push(load_annotation(input_index, child));
+            continue;
+        }
         load_terminal_rule(input_index, errors, grammar, child, name, false);
+        annotate_terminal(
+            grammar,
+            child.child(0).get_value().unwrap(),
+            &mut annotations,
+        );
+    }
+    report_dangling_annotations(errors, &mut annotations);
+}
+
+/// Loads the annotation in the given AST
+fn load_annotation(input_index: usize, node: AstNode) -> Annotation {
+    let value = node.child(1).get_value().unwrap();
+    Annotation {
+        input_ref: InputReference::from(input_index, &node.child(0)),
+        key: node.child(0).get_value().unwrap().to_string(),
+        value: replace_escapees(&value[1..(value.len() - 1)]).into_owned(),
     }
 }
 
+/// Attaches the pending annotations to the terminal that has just been declared
+/// The annotations are dropped when the declaration failed.
+fn annotate_terminal(grammar: &mut Grammar, name: &str, annotations: &mut Vec<Annotation>) {
+    let Some(terminal) = grammar.terminals.iter_mut().find(|t| t.name == name) else {
+        annotations.clear();
+        return;
+    };
+    for annotation in annotations.drain(..) {
+        terminal.add_annotation(annotation);
+    }
+}
+
+/// Reports the pending annotations that are not followed by the declaration of a symbol
+fn report_dangling_annotations(errors: &mut Vec<Error>, annotations: &mut Vec<Annotation>) {
+    errors.extend(
+        annotations
+            .drain(..)
+            .map(|annotation| Error::AnnotationWithoutSymbol(annotation.input_ref, annotation.key)),
+    );
+}
+
 /// Loads the terminal rule in the given AST
 fn load_terminal_rule(
     input_index: usize,
@@ -440,25 +620,75 @@ fn load_terminal_rule(
 ) {
     let node_name = node.child(0);
     let name = node_name.get_value().unwrap();
-    if let Some(previous) = grammar.get_terminal_for_name(name) {
-        errors.push(Error::OverridingPreviousTerminal(
-            InputReference::from(input_index, &node_name),
-            name.to_string(),
-            previous.input_ref,
-        ));
+    let input_ref = InputReference::from(input_index, &node_name);
+    let Some(is_inherited) = check_terminal_override(errors, grammar, name, input_ref) else {
         return;
+    };
+    let mut definition = SymbolDefinition {
+        grammar: grammar.name.clone(),
+        input_ref,
+        content: get_definition_content(node.child(1)),
+    };
+    let mut recovery = None;
+    let mut case_insensitive = false;
+    let mut node_lookahead = None;
+    for child in node.into_iter().skip(2) {
+        definition.content.push_str(&get_definition_content(child));
+        match child.get_symbol().id {
+            hime_grammar::ID_TERMINAL_RECOVER_AT_EOL => recovery = Some(RecoveryPoint::EndOfLine),
+            hime_grammar::ID_TERMINAL_RECOVER_AT_EOF => {
+                recovery = Some(RecoveryPoint::EndOfInput);
+            }
+            hime_grammar::ID_TERMINAL_CASE_INSENSITIVE => case_insensitive = true,
+            _ => node_lookahead = Some(child),
+        }
     }
+    let errors_count = errors.len();
     let mut references = Vec::new();
-    let nfa = load_nfa(input_index, errors, &mut references, grammar, node.child(1));
-    let terminal = grammar.add_terminal_named(
-        name.to_string(),
-        InputReference::from(input_index, &node_name),
-        nfa,
-        context,
-        is_fragment,
-    );
-    terminal.nfa.states[terminal.nfa.exit]
-        .add_item(FinalItem::Terminal(terminal.id, terminal.context));
+    let mut nfa = load_nfa(input_index, errors, &mut references, grammar, node.child(1));
+    if case_insensitive {
+        nfa = nfa.into_case_insensitive();
+    }
+    if let Some(node_lookahead) = node_lookahead {
+        let mut lookahead = load_nfa(
+            input_index,
+            errors,
+            &mut references,
+            grammar,
+            node_lookahead.child(0),
+        );
+        if case_insensitive {
+            lookahead = lookahead.into_case_insensitive();
+        }
+        match get_lookahead_spans(lookahead) {
+            Some(excluded) => nfa = nfa.into_negative_lookahead(&excluded),
+            None => errors.push(Error::InvalidLookahead(InputReference::from(
+                input_index,
+                &node_lookahead,
+            ))),
+        }
+    }
+    // the terminal is not defined yet while loading its expression,
+    // so that a reference to itself is reported as a recursion instead of a missing symbol
+    for error in &mut errors[errors_count..] {
+        if let Error::SymbolNotFound(reference, referred) = error {
+            if referred == name {
+                *error = Error::RecursiveTerminalReference(*reference, name.to_string());
+            }
+        }
+    }
+    let terminal = if is_inherited {
+        grammar.override_terminal_named(name, input_ref, nfa, context, is_fragment)
+    } else {
+        let terminal =
+            grammar.add_terminal_named(name.to_string(), input_ref, nfa, context, is_fragment);
+        terminal
+            .nfa
+            .add_final_terminal(terminal.id, terminal.context);
+        terminal
+    };
+    terminal.recovery = recovery;
+    terminal.definitions.push(definition);
     let referring_id = terminal.id;
     for (referred_id, input_ref) in references {
         if let Some(referred) = grammar.get_terminal_mut(referred_id) {
@@ -470,6 +700,211 @@ fn load_terminal_rule(
     }
 }
 
+/// Checks whether a terminal being defined overrides one inherited from a parent grammar
+/// Returns `None` when the terminal is already defined in this grammar, which is an error.
+fn check_terminal_override(
+    errors: &mut Vec<Error>,
+    grammar: &Grammar,
+    name: &str,
+    input_ref: InputReference,
+) -> Option<bool> {
+    match grammar.get_terminal_for_name(name) {
+        None => Some(false),
+        Some(previous)
+            if previous
+                .definitions
+                .last()
+                .is_some_and(|definition| definition.grammar != grammar.name) =>
+        {
+            // the terminal is inherited from a parent grammar => override it
+            Some(true)
+        }
+        Some(previous) => {
+            errors.push(Error::OverridingPreviousTerminal(
+                input_ref,
+                name.to_string(),
+                previous.input_ref,
+            ));
+            None
+        }
+    }
+}
+
+/// Loads the external terminal in the given AST
+fn load_terminal_external(
+    input_index: usize,
+    errors: &mut Vec<Error>,
+    grammar: &mut Grammar,
+    node: AstNode,
+) {
+    let node_name = node.child(0);
+    let name = node_name.get_value().unwrap();
+    let input_ref = InputReference::from(input_index, &node_name);
+    let value = node.child(1).get_value().unwrap();
+    let function = replace_escapees(&value[1..(value.len() - 1)]).into_owned();
+    if !is_rust_path(&function) {
+        errors.push(Error::InvalidExternalScanner(
+            InputReference::from(input_index, &node.child(1)),
+            function,
+        ));
+        return;
+    }
+    let Some(is_inherited) = check_terminal_override(errors, grammar, name, input_ref) else {
+        return;
+    };
+    let definition = SymbolDefinition {
+        grammar: grammar.name.clone(),
+        input_ref,
+        content: format!("%external_terminal \"{function}\""),
+    };
+    // the automaton never matches the terminal, only its scanner does
+    let nfa = NFA::new_minimal();
+    let terminal = if is_inherited {
+        grammar.override_terminal_named(name, input_ref, nfa, DEFAULT_CONTEXT_NAME, false)
+    } else {
+        let terminal = grammar.add_terminal_named(
+            name.to_string(),
+            input_ref,
+            nfa,
+            DEFAULT_CONTEXT_NAME,
+            false,
+        );
+        terminal
+            .nfa
+            .add_final_terminal(terminal.id, terminal.context);
+        terminal
+    };
+    terminal.definitions.push(definition);
+    terminal.external_scanner = Some(function);
+}
+
+/// Loads the priority group of terminals in the given AST
+fn load_terminal_group(
+    input_index: usize,
+    errors: &mut Vec<Error>,
+    grammar: &mut Grammar,
+    node: AstNode,
+) {
+    let mut terminals = Vec::new();
+    for child in node {
+        let name = child.get_value().unwrap();
+        let input_ref = InputReference::from(input_index, &child);
+        let Some(terminal) = grammar.get_terminal_for_name(name) else {
+            errors.push(Error::SymbolNotFound(input_ref, name.to_string()));
+            continue;
+        };
+        if let Some(group) = terminal.group {
+            errors.push(Error::TerminalInSeveralGroups(
+                input_ref,
+                name.to_string(),
+                grammar.terminal_groups[group].input_ref,
+            ));
+            continue;
+        }
+        if !terminals.contains(&terminal.id) {
+            terminals.push(terminal.id);
+        }
+    }
+    grammar.add_terminal_group(InputReference::from(input_index, &node), terminals);
+}
+
+/// Loads the alias of a terminal in the given AST
+fn load_terminal_alias(
+    input_index: usize,
+    errors: &mut Vec<Error>,
+    grammar: &mut Grammar,
+    node: AstNode,
+) {
+    let node_name = node.child(0);
+    let name = node_name.get_value().unwrap();
+    let value = node.child(1).get_value().unwrap();
+    let alias = replace_escapees(&value[1..(value.len() - 1)]).into_owned();
+    let Some(terminal) = grammar.terminals.iter_mut().find(|t| t.name == name) else {
+        errors.push(Error::SymbolNotFound(
+            InputReference::from(input_index, &node_name),
+            name.to_string(),
+        ));
+        return;
+    };
+    terminal.alias = Some(alias);
+}
+
+/// Gets whether a name is the path to a Rust item, e.g. `crate::scanners::indent`
+fn is_rust_path(name: &str) -> bool {
+    name.split("::").all(|segment| {
+        let mut chars = segment.chars();
+        chars
+            .next()
+            .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+            && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+            && segment != "_"
+    })
+}
+
+/// Gets the characters excluded by a negative lookahead
+/// The lookahead must only match single characters, otherwise `None` is returned.
+fn get_lookahead_spans(mut nfa: NFA) -> Option<Vec<CharSpan>> {
+    nfa.states[nfa.exit].add_item(FinalItem::Dummy);
+    let dfa = DFA::from_nfa(nfa);
+    let is_leaf =
+        |state: usize| dfa.states[state].is_final() && dfa.states[state].transitions.is_empty();
+    if dfa.states[0].is_final() {
+        return None;
+    }
+    let mut spans = Vec::new();
+    for (span, next) in &dfa.states[0].transitions {
+        if is_leaf(*next) {
+            spans.push(*span);
+            continue;
+        }
+        // a character outside plane 0 is only accepted when all of them are,
+        // so that the lookahead can be checked on the leading surrogate
+        let intermediate = &dfa.states[*next];
+        let all_low_surrogates = intermediate
+            .transitions
+            .iter()
+            .all(|(low, target)| low.begin >= 0xDC00 && low.end <= 0xDFFF && is_leaf(*target));
+        let count: u32 = intermediate
+            .transitions
+            .keys()
+            .map(|low| u32::from(low.end - low.begin) + 1)
+            .sum();
+        if span.begin < 0xD800
+            || span.end > 0xDBFF
+            || intermediate.is_final()
+            || !all_low_surrogates
+            || count != 0x400
+        {
+            return None;
+        }
+        spans.push(*span);
+    }
+    Some(spans)
+}
+
+/// Gets the canonical text of a definition in the AST, used to compare definitions
+fn get_definition_content(node: AstNode) -> String {
+    if node.get_symbol().id == hime_grammar::ID_TERMINAL_EMPTY {
+        // `%empty` is the explicit form of an empty part
+        return String::from("emptypart");
+    }
+    let mut content = node
+        .get_value()
+        .unwrap_or(node.get_symbol().name)
+        .to_string();
+    if node.children_count() > 0 {
+        content.push('(');
+        for (index, child) in node.into_iter().enumerate() {
+            if index > 0 {
+                content.push(',');
+            }
+            content.push_str(&get_definition_content(child));
+        }
+        content.push(')');
+    }
+    content
+}
+
 /// Builds the NFA represented by the AST node
 fn load_nfa(
     input_index: usize,
@@ -540,6 +975,49 @@ fn load_nfa(
     }
 }
 
+/// Loads the NFA for a terminal expression in the syntax of the terminal rules of grammars, e.g. `[a-z]+`
+/// The expression may refer by name to the terminals already in the grammar.
+/// Returns the NFA with the identifiers of the referenced terminals,
+/// or the messages for the errors in the expression.
+pub(crate) fn load_terminal_expression(
+    grammar: &Grammar,
+    expression: &str,
+) -> Result<(NFA, Vec<usize>), Vec<String>> {
+    let result = hime_grammar::parse_string(format!(
+        "grammar Expression {{ options {{ }} terminals {{ EXPRESSION -> {expression}; }} rules {{ }} }}"
+    ));
+    if let Some(error) = result.errors.errors.first() {
+        // the following errors are only caused by the recovery from the first one
+        return Err(vec![error.to_string()]);
+    }
+    let ast = result.get_ast();
+    let roots: Vec<AstNode> = ast.get_root().into_iter().collect();
+    let rules: Vec<AstNode> = roots
+        .iter()
+        .flat_map(|root| root.into_iter())
+        .filter(|node| node.get_symbol().id == hime_grammar::ID_TERMINAL_BLOCK_TERMINALS)
+        .flat_map(IntoIterator::into_iter)
+        .collect();
+    // the expression must not escape from the terminal rule
+    let [rule] = rules[..] else {
+        return Err(vec![format!("Invalid terminal expression `{expression}`")]);
+    };
+    if roots.len() != 1
+        || rule.get_symbol().id != hime_grammar::ID_VARIABLE_TERMINAL_RULE
+        || rule.children_count() != 2
+    {
+        return Err(vec![format!("Invalid terminal expression `{expression}`")]);
+    }
+    let mut errors = Vec::new();
+    let mut references = Vec::new();
+    let nfa = load_nfa(0, &mut errors, &mut references, grammar, rule.child(1));
+    if errors.is_empty() {
+        Ok((nfa, references.into_iter().map(|(id, _)| id).collect()))
+    } else {
+        Err(errors.iter().map(ToString::to_string).collect())
+    }
+}
+
 /// Builds a NFA from a piece of text
 fn load_nfa_simple_text(node: &AstNode) -> NFA {
     // build the raw piece of text
@@ -551,8 +1029,11 @@ fn load_nfa_simple_text(node: &AstNode) -> NFA {
     } else {
         &value[1..(value.len() - 1)]
     });
+    build_nfa_text(&value, insensitive)
+}
 
-    // build the result
+/// Builds a NFA matching a piece of text, optionally ignoring the case of ASCII letters
+pub(crate) fn build_nfa_text(value: &str, insensitive: bool) -> NFA {
     let mut nfa = NFA::new_minimal();
     let mut buffer = [0; 2];
     nfa.exit = nfa.entry;
@@ -795,12 +1276,19 @@ fn add_unicode_span_to_nfa(nfa: &mut NFA, span: Span) {
 /// Loads the rules block of a grammar
 fn load_rules(input_index: usize, errors: &mut Vec<Error>, grammar: &mut Grammar, node: AstNode) {
     // load new variables for the rule's head
+    let mut annotations = Vec::new();
     for child in node {
         let id = child.get_symbol().id;
-        if id == hime_grammar::ID_VARIABLE_CF_RULE_SIMPLE {
+        if id == hime_grammar::ID_VARIABLE_SYMBOL_ANNOTATION {
+            annotations.push(load_annotation(input_index, child));
+        } else if id == hime_grammar::ID_VARIABLE_CF_RULE_SIMPLE {
             let name = child.child(0).get_value().unwrap();
-            grammar.add_variable(name);
+            let variable = grammar.add_variable(name);
+            for annotation in annotations.drain(..) {
+                variable.add_annotation(annotation);
+            }
         } else if id == hime_grammar::ID_VARIABLE_CF_RULE_TEMPLATE {
+            report_dangling_annotations(errors, &mut annotations);
             let name = child.child(0).get_value().unwrap();
             let arguments: Vec<TemplateRuleParam> = child
                 .child(1)
@@ -815,10 +1303,20 @@ fn load_rules(input_index: usize, errors: &mut Vec<Error>, grammar: &mut Grammar
                 InputReference::from(input_index, &node.child(0)),
                 arguments,
             );
+        } else if id == hime_grammar::ID_VARIABLE_RECOVER_SET {
+            report_dangling_annotations(errors, &mut annotations);
+        } else if id == hime_grammar::ID_VARIABLE_ENTRY_POINT {
+            report_dangling_annotations(errors, &mut annotations);
+            let name = child.child(0);
+            grammar.add_entry(
+                InputReference::from(input_index, &name),
+                name.get_value().unwrap(),
+            );
         } else {
             panic!("Unrecognized symbol: {}", node.get_symbol().name);
         }
     }
+    report_dangling_annotations(errors, &mut annotations);
     // load template rules
     for child in node {
         let id = child.get_symbol().id;
@@ -833,6 +1331,48 @@ fn load_rules(input_index: usize, errors: &mut Vec<Error>, grammar: &mut Grammar
             load_simple_rule(input_index, errors, grammar, child);
         }
     }
+    // load the terminals for error recovery, once all inline terminals are defined
+    for child in node {
+        let id = child.get_symbol().id;
+        if id == hime_grammar::ID_VARIABLE_RECOVER_SET {
+            load_recover_set(input_index, errors, grammar, child);
+        }
+    }
+}
+
+/// Loads a set of terminals to synchronize on during error recovery
+/// The terminals are referred to by name or by value, `EOF` refers to the end of the input unless it is a terminal.
+fn load_recover_set(
+    input_index: usize,
+    errors: &mut Vec<Error>,
+    grammar: &mut Grammar,
+    node: AstNode,
+) {
+    let mut terminals = Vec::new();
+    for child in node {
+        let value = child.get_value().unwrap();
+        let terminal = if child.get_symbol().id == hime_grammar::ID_TERMINAL_LITERAL_TEXT {
+            let start = if value.starts_with('~') { 2 } else { 1 };
+            let value = replace_escapees(&value[start..(value.len() - 1)]);
+            grammar
+                .get_terminal_for_value(&value)
+                .map(|terminal| TerminalRef::Terminal(terminal.id))
+        } else if let Some(terminal) = grammar.get_terminal_for_name(value) {
+            Some(TerminalRef::Terminal(terminal.id))
+        } else if value == RECOVER_END_OF_INPUT {
+            Some(TerminalRef::Dollar)
+        } else {
+            None
+        };
+        match terminal {
+            Some(terminal) => terminals.push(terminal),
+            None => errors.push(Error::SymbolNotFound(
+                InputReference::from(input_index, &child),
+                value.to_string(),
+            )),
+        }
+    }
+    grammar.add_sync_terminals(&terminals);
 }
 
 /// Loads the syntactic rule in the given AST
@@ -844,9 +1384,15 @@ fn load_simple_rule(
 ) {
     let name = node.child(0).get_value().unwrap();
     let head_sid = grammar.add_variable(name).id;
+    let definition = SymbolDefinition {
+        grammar: grammar.name.clone(),
+        input_ref: InputReference::from(input_index, &node.child(0)),
+        content: get_definition_content(node.child(1)),
+    };
     let definitions =
         load_simple_rule_definitions(input_index, errors, grammar, head_sid, node.child(1));
     let variable = grammar.add_variable(name);
+    variable.add_definition(definition);
     for body in definitions.bodies {
         variable.add_rule(Rule::new(
             variable.id,
@@ -894,7 +1440,9 @@ fn load_simple_rule_definitions(
         hime_grammar::ID_VIRTUAL_CONCAT => {
             load_simple_rule_concat(input_index, errors, grammar, head_sid, node)
         }
-        hime_grammar::ID_VIRTUAL_EMPTYPART => load_simple_rule_empty_part(),
+        hime_grammar::ID_VIRTUAL_EMPTYPART | hime_grammar::ID_TERMINAL_EMPTY => {
+            load_simple_rule_empty_part()
+        }
         _ => load_simple_rule_atomic(input_index, errors, grammar, node),
     }
 }
@@ -1279,8 +1827,9 @@ fn load_simple_rule_atomic_inline_text(
                 InputReference::from(input_index, &node),
                 nfa,
             );
-            terminal.nfa.states[terminal.nfa.exit]
-                .add_item(FinalItem::Terminal(terminal.id, terminal.context));
+            terminal
+                .nfa
+                .add_final_terminal(terminal.id, terminal.context);
             terminal.id
         }
         Some(terminal) => terminal.id,
@@ -1349,7 +1898,9 @@ fn load_template_rule_definitions(
         hime_grammar::ID_VIRTUAL_CONCAT => {
             load_template_rule_concat(input_index, errors, grammar, parameters, node)
         }
-        hime_grammar::ID_VIRTUAL_EMPTYPART => load_template_rule_empty_part(),
+        hime_grammar::ID_VIRTUAL_EMPTYPART | hime_grammar::ID_TERMINAL_EMPTY => {
+            load_template_rule_empty_part()
+        }
         _ => load_template_rule_atomic(input_index, errors, grammar, parameters, node),
     }
 }
@@ -1779,8 +2330,9 @@ fn load_template_rule_atomic_inline_text(
                 InputReference::from(input_index, &node),
                 nfa,
             );
-            terminal.nfa.states[terminal.nfa.exit]
-                .add_item(FinalItem::Terminal(terminal.id, terminal.context));
+            terminal
+                .nfa
+                .add_final_terminal(terminal.id, terminal.context);
             terminal.id
         }
         Some(terminal) => terminal.id,
@@ -1810,6 +2362,8 @@ fn get_char_value(value: &[char], i: usize) -> (char, usize) {
         'r' => ('\r', 2),       // carriage return
         't' => ('\t', 2),       // horizontal tab
         'v' => (11 as char, 2), // vertical tab
+        'x' => get_hexa_char_value(value, i, 2),
+        'U' => get_hexa_char_value(value, i, 8),
         'u' => {
             let mut l = 0;
             while i + 2 + l < value.len() {
@@ -1833,6 +2387,24 @@ fn get_char_value(value: &[char], i: usize) -> (char, usize) {
     }
 }
 
+/// Gets the value of a fixed-length hexadecimal escape sequence (`\\xNN` or `\\UNNNNNNNN`) at the given index
+fn get_hexa_char_value(value: &[char], i: usize, length: usize) -> (char, usize) {
+    let digits = value[(i + 2)..]
+        .iter()
+        .take(length)
+        .take_while(|c| c.is_ascii_hexdigit())
+        .count();
+    if digits == 0 {
+        return (value[i + 1], 2);
+    }
+    let char_hexa: String = value[(i + 2)..(i + 2 + digits)].iter().collect();
+    let char_value = u32::from_str_radix(&char_hexa, 16).unwrap();
+    (
+        std::char::from_u32(char_value).unwrap_or(std::char::REPLACEMENT_CHARACTER),
+        digits + 2,
+    )
+}
+
 /// Replaces the escape sequences in the given piece of text by their value
 #[must_use]
 pub fn replace_escapees(value: &str) -> Cow<str> {
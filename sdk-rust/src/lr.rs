@@ -17,18 +17,22 @@
 
 //! Module for LR automata
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::Instant;
 
 use hime_redist::parsers::{LRActionCode, LR_ACTION_CODE_REDUCE, LR_ACTION_CODE_SHIFT};
 
 use crate::errors::{Error, UnmatchableTokenError};
 use crate::finite::DFA;
 use crate::grammars::{
-    Grammar, RuleChoice, RuleChoiceRef, RuleRef, SymbolRef, Terminal, TerminalRef, TerminalSet,
-    GENERATED_AXIOM,
+    EntryData, Grammar, RuleChoice, RuleChoiceRef, RuleRef, SymbolRef, Terminal, TerminalRef,
+    TerminalSet,
 };
-use crate::ParsingMethod;
+use crate::output::helper::{write_u32, write_u8};
+use crate::{CompilationProgress, ParsingMethod};
 
 /// The lookahead mode for LR items
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -82,75 +86,139 @@ impl Lookahead {
 
 /// A set of lookahead in a LR graph
 #[derive(Debug, Clone, Default, Eq)]
-pub struct Lookaheads(Vec<Lookahead>);
+pub struct Lookaheads {
+    /// The lookaheads, in insertion order
+    content: Vec<Lookahead>,
+    /// The terminals with the index of their lookahead in `content`, sorted by terminal
+    index: Vec<(TerminalRef, usize)>,
+}
 
 impl PartialEq for Lookaheads {
     fn eq(&self, other: &Lookaheads) -> bool {
-        self.0.iter().all(|lookahead| other.0.contains(lookahead))
+        self.content
+            .iter()
+            .all(|lookahead| other.contains(lookahead.terminal))
     }
 }
 
 impl Lookaheads {
+    /// Finds the position of the specified terminal in the index
+    fn find(&self, terminal: TerminalRef) -> Result<usize, usize> {
+        self.index
+            .binary_search_by(|(candidate, _)| candidate.cmp(&terminal))
+    }
+
     /// Gets the lookahead with the specified terminal
     #[must_use]
     pub fn get(&self, terminal: TerminalRef) -> Option<&Lookahead> {
-        self.0
-            .iter()
-            .find(|lookahead| lookahead.terminal == terminal)
+        self.find(terminal)
+            .ok()
+            .map(|position| &self.content[self.index[position].1])
+    }
+
+    /// Gets an iterator over the lookaheads, in insertion order
+    pub fn iter(&self) -> std::slice::Iter<'_, Lookahead> {
+        self.content.iter()
+    }
+
+    /// Gets an iterator over the lookaheads, sorted by terminal
+    pub fn iter_sorted(&self) -> impl Iterator<Item = &Lookahead> + '_ {
+        self.index.iter().map(|&(_, index)| &self.content[index])
+    }
+
+    /// Gets the number of lookaheads
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.content.len()
     }
 
-    /// Adds a new lookahead
-    fn add(&mut self, lookahead: Lookahead) {
-        if let Some(previous) = self.0.iter_mut().find(|candidate| candidate == &&lookahead) {
-            for origin in lookahead.origins {
-                if !previous.origins.contains(&origin) {
-                    previous.origins.push(origin);
+    /// Gets whether there is no lookahead
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Adds a new lookahead, returns whether its terminal was not already present
+    fn add(&mut self, lookahead: &Lookahead) -> bool {
+        match self.find(lookahead.terminal) {
+            Ok(position) => {
+                let previous = &mut self.content[self.index[position].1];
+                if previous.origins == lookahead.origins {
+                    return false;
+                }
+                for origin in &lookahead.origins {
+                    if !previous.origins.contains(origin) {
+                        previous.origins.push(*origin);
+                    }
                 }
+                false
+            }
+            Err(position) => {
+                self.index
+                    .insert(position, (lookahead.terminal, self.content.len()));
+                self.content.push(lookahead.clone());
+                true
             }
-        } else {
-            self.0.push(lookahead);
         }
     }
 
-    /// Adds new terminals
-    pub fn add_others(&mut self, others: &Lookaheads) {
-        for other in &others.0 {
-            self.add(other.clone());
+    /// Adds new terminals, returns whether new terminals were added
+    pub fn add_others(&mut self, others: &Lookaheads) -> bool {
+        let mut modified = false;
+        for other in &others.content {
+            modified |= self.add(other);
         }
+        modified
     }
 
     /// Gets whether the specified terminal is present as a lookahead
     #[must_use]
     pub fn contains(&self, terminal: TerminalRef) -> bool {
-        self.0
-            .iter()
-            .any(|candidate| candidate.terminal == terminal)
+        self.find(terminal).is_ok()
     }
 
     /// Removes the specified terminal
     pub fn remove(&mut self, terminal: TerminalRef) {
-        self.0.retain(|candidate| candidate.terminal != terminal);
+        if let Ok(position) = self.find(terminal) {
+            let (_, removed) = self.index.remove(position);
+            self.content.remove(removed);
+            for (_, index) in &mut self.index {
+                if *index > removed {
+                    *index -= 1;
+                }
+            }
+        }
     }
 
     /// Builds this lookahead sets from a set of FIRSTS
     #[must_use]
     pub fn from_firsts(firsts: &TerminalSet, choice: RuleChoiceRef) -> Lookaheads {
-        Lookaheads(
-            firsts
-                .content
-                .iter()
-                .map(|terminal| Lookahead {
-                    terminal: *terminal,
-                    origins: vec![LookaheadOrigin::FirstOf(choice)],
-                })
-                .collect(),
-        )
+        let mut result = Lookaheads::default();
+        for terminal in firsts {
+            result.add(&Lookahead {
+                terminal: *terminal,
+                origins: vec![LookaheadOrigin::FirstOf(choice)],
+            });
+        }
+        result
     }
 
     /// Builds this set from a single lookahead
     #[must_use]
     pub fn from_single(lookahead: Lookahead) -> Lookaheads {
-        Lookaheads(vec![lookahead])
+        Lookaheads {
+            index: vec![(lookahead.terminal, 0)],
+            content: vec![lookahead],
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Lookaheads {
+    type Item = &'a Lookahead;
+    type IntoIter = std::slice::Iter<'a, Lookahead>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.content.iter()
     }
 }
 
@@ -177,6 +245,20 @@ impl Item {
         }
     }
 
+    /// Gets whether this item accepts the input,
+    /// i.e. it completes the rule of a generated axiom, ending with `$`, on the end of input
+    /// The item has no lookahead when the lookaheads are not computed (LR(0)).
+    #[must_use]
+    pub fn is_accepting(&self, grammar: &Grammar) -> bool {
+        let elements = &self.rule.get_rule_in(grammar).body.choices[0].elements;
+        self.position >= elements.len()
+            && elements
+                .last()
+                .is_some_and(|element| element.symbol == SymbolRef::Dollar)
+            && (self.lookaheads.iter().next().is_none()
+                || self.lookaheads.contains(TerminalRef::Epsilon))
+    }
+
     /// Gets the symbol following the dot in this item
     #[must_use]
     pub fn get_next_symbol(&self, grammar: &Grammar) -> Option<SymbolRef> {
@@ -188,14 +270,15 @@ impl Item {
         }
     }
 
-    /// Gets rule choice following the dot in this item
+    /// Gets the rule choice for the remainder of the rule after the symbol following the dot,
+    /// i.e. `beta` for an item of the form `[Var -> alpha . next beta]`
     #[must_use]
     pub fn get_next_choice<'g>(&self, grammar: &'g Grammar) -> Option<&'g RuleChoice> {
         let rule = self.rule.get_rule_in(grammar);
         if self.position >= rule.body.choices[0].elements.len() {
             None
         } else {
-            Some(&rule.body.choices[self.position + 1])
+            rule.body.choices.get(self.position + 1)
         }
     }
 
@@ -233,6 +316,34 @@ impl Item {
     /// A panic is raised when the symbols in the rule cannot be found
     /// in their respective grammar (which should not happen).
     pub fn close_to(&self, grammar: &Grammar, closure: &mut Vec<Item>, mode: LookaheadMode) {
+        let mut candidates = Vec::new();
+        self.get_closure_candidates(grammar, mode, &mut candidates);
+        for candidate in candidates {
+            match mode {
+                LookaheadMode::LR0 | LookaheadMode::LR1 => {
+                    if !closure.contains(&candidate) {
+                        closure.push(candidate);
+                    }
+                }
+                LookaheadMode::LALR1 => {
+                    if let Some(other) = closure.iter_mut().find(|item| item.same_base(&candidate))
+                    {
+                        other.lookaheads.add_others(&candidate.lookaheads);
+                    } else {
+                        closure.push(candidate);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gets the items derived from this one in a closure, possibly with duplicates
+    fn get_closure_candidates(
+        &self,
+        grammar: &Grammar,
+        mode: LookaheadMode,
+        candidates: &mut Vec<Item>,
+    ) {
         if let Some(SymbolRef::Variable(sid)) = self.get_next_symbol(grammar) {
             // Here the item is of the form [Var -> alpha . next beta]
             // next is a variable
@@ -240,7 +351,9 @@ impl Item {
             // Firsts will contains symbols that may follow Next
             // Firsts will therefore be the lookahead for child items
             let mut firsts = Lookaheads::from_firsts(
-                &self.get_next_choice(grammar).unwrap().firsts,
+                self.rule
+                    .get_rule_in(grammar)
+                    .firsts_after(self.position + 1),
                 RuleChoiceRef {
                     rule: self.rule,
                     position: self.position + 1,
@@ -257,42 +370,25 @@ impl Item {
             // For each rule that has Next as a head variable :
             for index in 0..variable.rules.len() {
                 match mode {
-                    LookaheadMode::LR0 => {
-                        let candidate = Item {
-                            rule: RuleRef::new(sid, index),
-                            position: 0,
-                            lookaheads: Lookaheads::default(),
-                        };
-                        if !closure.contains(&candidate) {
-                            closure.push(candidate);
-                        }
-                    }
+                    LookaheadMode::LR0 => candidates.push(Item {
+                        rule: RuleRef::new(sid, index),
+                        position: 0,
+                        lookaheads: Lookaheads::default(),
+                    }),
                     LookaheadMode::LR1 => {
-                        for lookahead in firsts.clone().0 {
-                            let candidate = Item {
+                        for lookahead in firsts.iter().cloned() {
+                            candidates.push(Item {
                                 rule: RuleRef::new(sid, index),
                                 position: 0,
                                 lookaheads: Lookaheads::from_single(lookahead),
-                            };
-                            if !closure.contains(&candidate) {
-                                closure.push(candidate);
-                            }
-                        }
-                    }
-                    LookaheadMode::LALR1 => {
-                        let candidate = Item {
-                            rule: RuleRef::new(sid, index),
-                            position: 0,
-                            lookaheads: firsts.clone(),
-                        };
-                        if let Some(other) =
-                            closure.iter_mut().find(|item| item.same_base(&candidate))
-                        {
-                            other.lookaheads.add_others(&candidate.lookaheads);
-                        } else {
-                            closure.push(candidate);
+                            });
                         }
                     }
+                    LookaheadMode::LALR1 => candidates.push(Item {
+                        rule: RuleRef::new(sid, index),
+                        position: 0,
+                        lookaheads: firsts.clone(),
+                    }),
                 }
             }
         }
@@ -304,6 +400,11 @@ impl Item {
         self.rule == other.rule && self.position == other.position
     }
 
+    /// Gets the base of this item, i.e. its rule and position, as a sortable key
+    fn get_base(&self) -> (usize, usize, usize) {
+        (self.rule.variable, self.rule.index, self.position)
+    }
+
     /// If this item uses a rule with a generated head,
     /// recursively get the parent rule
     ///
@@ -340,13 +441,21 @@ impl Item {
     ///
     /// Return an error when formatting fails
     #[allow(clippy::missing_panics_doc)]
-    pub fn format(&self, f: &mut Formatter, grammar: &Grammar) -> std::fmt::Result {
+    pub fn format(
+        &self,
+        f: &mut Formatter,
+        grammar: &Grammar,
+        options: ReportOptions,
+    ) -> std::fmt::Result {
         let rule = self.rule.get_rule_in(grammar);
         write!(
             f,
             "{} ->",
             grammar.get_symbol_name(SymbolRef::Variable(rule.head))
         )?;
+        if rule.body.choices[0].elements.is_empty() {
+            write!(f, " ε")?;
+        }
         for (index, element) in rule.body.choices[0].elements.iter().enumerate() {
             if index == self.position {
                 write!(f, " •")?;
@@ -357,42 +466,202 @@ impl Item {
             write!(f, " •")?;
         }
         write!(f, "  ❰")?;
-        for (index, lookahead) in self.lookaheads.0.iter().enumerate() {
+        let count = options.get_displayed_lookaheads(self.lookaheads.len());
+        for (index, lookahead) in self.lookaheads.iter_sorted().take(count).enumerate() {
             if index > 0 {
                 write!(f, " ")?;
             }
-            write!(f, "{}", grammar.get_symbol_value(lookahead.terminal.into()))?;
+            write!(f, "{}", lookahead.terminal.name_in(grammar))?;
+        }
+        if count < self.lookaheads.len() {
+            write!(f, " … ({} more)", self.lookaheads.len() - count)?;
         }
         writeln!(f, "❱")?;
         Ok(())
     }
 }
 
+/// The default maximum number of lookaheads rendered for an item in the reports
+pub const DEFAULT_MAX_REPORTED_LOOKAHEADS: usize = 8;
+
+/// The options for rendering the items of LR graphs in textual reports
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReportOptions {
+    /// The maximum number of lookaheads rendered for an item, the others being elided with their count
+    pub max_lookaheads: usize,
+    /// Whether to render all the lookaheads, regardless of the maximum
+    pub verbose: bool,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            max_lookaheads: DEFAULT_MAX_REPORTED_LOOKAHEADS,
+            verbose: false,
+        }
+    }
+}
+
+impl ReportOptions {
+    /// Gets the number of lookaheads to render out of the given count
+    #[must_use]
+    pub fn get_displayed_lookaheads(&self, count: usize) -> usize {
+        if self.verbose {
+            count
+        } else {
+            count.min(self.max_lookaheads)
+        }
+    }
+}
+
 /// The graph along with the associated grammar
 pub struct GraphWithGrammar<'a> {
     /// The grammar
     pub grammar: &'a Grammar,
     /// The built LR graph
     pub graph: &'a Graph,
+    /// The options for rendering the items
+    pub options: ReportOptions,
 }
 
 impl<'a> Display for GraphWithGrammar<'a> {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        self.graph.format(f, self.grammar)
+        self.graph.format(f, self.grammar, self.options)
+    }
+}
+
+/// Gets the closure of a set of items
+/// The initial items come first, followed by the items derived from them.
+/// This is equivalent to closing each item in turn with `Item::close_to`,
+/// but the items already in the closure are found through an index instead of a linear scan.
+#[must_use]
+pub fn closure(items: Vec<Item>, grammar: &Grammar, mode: LookaheadMode) -> Vec<Item> {
+    let mut builder = ClosureBuilder::new(mode);
+    for item in items {
+        builder.push(item);
+    }
+    let mut candidates = Vec::new();
+    let mut i = 0;
+    while i < builder.items.len() {
+        builder.items[i].get_closure_candidates(grammar, mode, &mut candidates);
+        for candidate in candidates.drain(..) {
+            builder.add(candidate);
+        }
+        i += 1;
+    }
+    builder.items
+}
+
+/// A closure being built, with an index of its items
+struct ClosureBuilder {
+    /// The lookahead mode
+    mode: LookaheadMode,
+    /// The items in the closure
+    items: Vec<Item>,
+    /// The interned sets of lookahead terminals, sorted, with their handle
+    /// The empty set is always interned first, with the handle `0`.
+    lookaheads: HashMap<Vec<TerminalRef>, usize>,
+    /// The bases of the items, with the handle of their lookahead terminals (LR(0) and LR(1))
+    keys: HashSet<(RuleRef, usize, usize)>,
+    /// The index of the first item for each base (LALR(1))
+    bases: HashMap<(RuleRef, usize), usize>,
+}
+
+impl ClosureBuilder {
+    /// Creates an empty closure
+    fn new(mode: LookaheadMode) -> ClosureBuilder {
+        ClosureBuilder {
+            mode,
+            items: Vec::new(),
+            lookaheads: HashMap::from([(Vec::new(), 0)]),
+            keys: HashSet::new(),
+            bases: HashMap::new(),
+        }
+    }
+
+    /// Gets the handle of the set of terminals of some lookaheads
+    fn intern(&mut self, lookaheads: &Lookaheads) -> usize {
+        let terminals: Vec<TerminalRef> = lookaheads
+            .index
+            .iter()
+            .map(|&(terminal, _)| terminal)
+            .collect();
+        let next = self.lookaheads.len();
+        *self.lookaheads.entry(terminals).or_insert(next)
+    }
+
+    /// Pushes an item at the end of the closure, without looking for an existing one
+    fn push(&mut self, item: Item) {
+        match self.mode {
+            LookaheadMode::LR0 | LookaheadMode::LR1 => {
+                let handle = self.intern(&item.lookaheads);
+                self.keys.insert((item.rule, item.position, handle));
+            }
+            LookaheadMode::LALR1 => {
+                self.bases
+                    .entry((item.rule, item.position))
+                    .or_insert(self.items.len());
+            }
+        }
+        self.items.push(item);
+    }
+
+    /// Adds an item derived in the closure
+    /// As with `Item::close_to`, a LR(0) or LR(1) item is dropped when an item with the same base
+    /// has a subset of its lookaheads. The derived items having at most one lookahead in these modes,
+    /// the subsets are the empty set and the item's own set.
+    /// A LALR(1) item is merged into the first item with the same base.
+    fn add(&mut self, item: Item) {
+        match self.mode {
+            LookaheadMode::LR0 | LookaheadMode::LR1 => {
+                let handle = self.intern(&item.lookaheads);
+                if !self.keys.contains(&(item.rule, item.position, 0))
+                    && !self.keys.contains(&(item.rule, item.position, handle))
+                {
+                    self.push(item);
+                }
+            }
+            LookaheadMode::LALR1 => {
+                if let Some(&index) = self.bases.get(&(item.rule, item.position)) {
+                    self.items[index].lookaheads.add_others(&item.lookaheads);
+                } else {
+                    self.push(item);
+                }
+            }
+        }
     }
 }
 
 /// Represents the kernel of a LR state
 #[derive(Debug, Clone, Eq, Default)]
 pub struct StateKernel {
-    /// The items in this kernel
+    /// The items in this kernel, sorted by rule and position (see `add_item`)
     pub items: Vec<Item>,
 }
 
 impl PartialEq for StateKernel {
     fn eq(&self, other: &StateKernel) -> bool {
+        // the items with the same base are at the same positions in both kernels
         self.items.len() == other.items.len()
-            && self.items.iter().all(|item| other.items.contains(item))
+            && self
+                .items
+                .iter()
+                .zip(&other.items)
+                .all(|(left, right)| left.same_base(right))
+            && self
+                .items
+                .iter()
+                .zip(&other.items)
+                .all(|(left, right)| left == right || other.get_items_like(left).contains(left))
+    }
+}
+
+impl Hash for StateKernel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // only the bases, the lookaheads do not matter for equality
+        for item in &self.items {
+            item.get_base().hash(state);
+        }
     }
 }
 
@@ -400,12 +669,7 @@ impl StateKernel {
     /// Gets the closure of this kernel
     #[must_use]
     pub fn into_state(self, grammar: &Grammar, mode: LookaheadMode) -> State {
-        let mut items = self.items.clone();
-        let mut i = 0;
-        while i < items.len() {
-            items[i].clone().close_to(grammar, &mut items, mode);
-            i += 1;
-        }
+        let items = closure(self.items.clone(), grammar, mode);
         State {
             kernel: self,
             items,
@@ -415,11 +679,54 @@ impl StateKernel {
         }
     }
 
-    /// Adds an item to the kernel
+    /// Adds an item to the kernel, if it is not already there
+    /// The items are kept sorted by rule and position, so that the kernels can be compared linearly.
     pub fn add_item(&mut self, item: Item) {
-        if !self.items.contains(&item) {
-            self.items.push(item);
+        let similar = self.get_items_like(&item);
+        if !similar.contains(&item) {
+            let end = self
+                .items
+                .partition_point(|candidate| candidate.get_base() <= item.get_base());
+            self.items.insert(end, item);
+        }
+    }
+
+    /// Gets the items in this kernel with the same base as the specified one
+    fn get_items_like(&self, item: &Item) -> &[Item] {
+        let base = item.get_base();
+        let start = self
+            .items
+            .partition_point(|candidate| candidate.get_base() < base);
+        let end = self
+            .items
+            .partition_point(|candidate| candidate.get_base() <= base);
+        &self.items[start..end]
+    }
+
+    /// Gets the sorted bases (rule and position) of the items in this kernel
+    fn get_bases(&self) -> Vec<(usize, usize, usize)> {
+        let mut bases: Vec<(usize, usize, usize)> = self.items.iter().map(Item::get_base).collect();
+        bases.sort_unstable();
+        bases.dedup();
+        bases
+    }
+
+    /// Formats the items of this kernel
+    ///
+    /// # Errors
+    ///
+    /// Return an error when formatting fails
+    pub fn format(
+        &self,
+        f: &mut Formatter,
+        grammar: &Grammar,
+        options: ReportOptions,
+    ) -> std::fmt::Result {
+        for item in &self.items {
+            write!(f, "    ")?;
+            item.format(f, grammar, options)?;
         }
+        Ok(())
     }
 }
 
@@ -434,6 +741,35 @@ pub struct Reduction {
     pub length: usize,
 }
 
+impl Reduction {
+    /// Gets whether this is a right-nullable reduction,
+    /// i.e. a RNGLR reduction that does not pop all the symbols of the rule
+    #[must_use]
+    pub fn is_right_nullable(&self, grammar: &Grammar) -> bool {
+        self.length < self.rule.get_rule_in(grammar).body.choices[0].len()
+    }
+
+    /// Gets the nullable variables after the reduced part of the rule
+    /// These are the variables the parser inserts with their epsilon derivation when reducing
+    #[must_use]
+    pub fn get_nullable_suffix(&self, grammar: &Grammar) -> Vec<usize> {
+        self.rule
+            .get_rule_in(grammar)
+            .body
+            .elements
+            .iter()
+            .filter(|element| {
+                !matches!(element.symbol, SymbolRef::Virtual(_) | SymbolRef::Action(_))
+            })
+            .skip(self.length)
+            .filter_map(|element| match element.symbol {
+                SymbolRef::Variable(id) => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 /// Represents a LR state
 #[derive(Debug, Clone)]
 pub struct State {
@@ -450,6 +786,91 @@ pub struct State {
 }
 
 impl State {
+    /// Gets the conflicts raised by this state, given its identifier in the graph
+    pub fn get_conflicts<'c>(
+        &self,
+        state_id: usize,
+        conflicts: &'c Conflicts,
+    ) -> impl Iterator<Item = &'c Conflict> + 'c {
+        conflicts.in_state(state_id)
+    }
+
+    /// Gets whether this state raises at least one conflict, given its identifier in the graph
+    #[must_use]
+    pub fn has_conflict(&self, state_id: usize, conflicts: &Conflicts) -> bool {
+        conflicts.has_conflict_in(state_id)
+    }
+
+    /// Gets whether this state accepts the input, i.e. one of its items accepts it
+    #[must_use]
+    pub fn is_accepting(&self, grammar: &Grammar) -> bool {
+        self.items.iter().any(|item| item.is_accepting(grammar))
+    }
+
+    /// Gets whether this state has an item for a rule at a position
+    #[must_use]
+    pub fn has_item(&self, rule: RuleRef, position: usize) -> bool {
+        self.item_for(rule, position).is_some()
+    }
+
+    /// Gets the first item of this state for a rule at a position
+    /// With lookaheads, there may be several such items that only differ by their lookaheads.
+    #[must_use]
+    pub fn item_for(&self, rule: RuleRef, position: usize) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|item| item.rule == rule && item.position == position)
+    }
+
+    /// Gets the lexical contexts within which each item of this state is, given those of the kernel items
+    /// An item is within the context of its rule and, in the closure, within those of the items it derives from.
+    fn get_item_contexts(
+        &self,
+        kernel: &HashMap<(RuleRef, usize), Vec<usize>>,
+        grammar: &Grammar,
+    ) -> Vec<Vec<usize>> {
+        let mut result: Vec<Vec<usize>> = self
+            .items
+            .iter()
+            .map(|item| {
+                let mut contexts = kernel
+                    .get(&(item.rule, item.position))
+                    .cloned()
+                    .unwrap_or_default();
+                let context = item.rule.get_rule_in(grammar).context;
+                if context != 0 && !contexts.contains(&context) {
+                    contexts.push(context);
+                }
+                contexts
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, item) in self.items.iter().enumerate() {
+                let Some(SymbolRef::Variable(variable)) = item.get_next_symbol(grammar) else {
+                    continue;
+                };
+                if result[index].is_empty() {
+                    continue;
+                }
+                let carried = result[index].clone();
+                for (derived, child) in self.items.iter().enumerate() {
+                    if child.position != 0 || child.rule.variable != variable {
+                        continue;
+                    }
+                    for &context in &carried {
+                        if !result[derived].contains(&context) {
+                            result[derived].push(context);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Builds reductions for this state
     pub fn build_reductions_lr0(&mut self, id: usize, grammar: &Grammar) -> Conflicts {
         let mut conflicts = Conflicts::default();
@@ -458,8 +879,12 @@ impl State {
             if item.get_action(grammar) != LR_ACTION_CODE_REDUCE {
                 continue;
             }
-            if !self.children.is_empty() {
-                // shift/reduce conflict
+            if self
+                .children
+                .keys()
+                .any(|symbol| !matches!(symbol, SymbolRef::Variable(_)))
+            {
+                // shift/reduce conflict, the transitions on variables are only gotos after reductions
                 conflicts.raise_shift_reduce(
                     self,
                     id,
@@ -497,7 +922,7 @@ impl State {
             if item.get_action(grammar) != LR_ACTION_CODE_REDUCE {
                 continue;
             }
-            for lookahead in &item.lookaheads.0 {
+            for lookahead in &item.lookaheads {
                 let symbol_ref: SymbolRef = lookahead.terminal.into();
                 if self.children.contains_key(&symbol_ref) {
                     // There is already a shift action for the lookahead => conflict
@@ -528,6 +953,7 @@ impl State {
                 }
             }
         }
+        conflicts.compute_overlaps(self);
         conflicts
     }
 
@@ -538,15 +964,14 @@ impl State {
         for (index, item) in self.items.iter().enumerate() {
             let rule = item.rule.get_rule_in(grammar);
             if item.get_action(grammar) == LR_ACTION_CODE_SHIFT
-                && !rule.body.choices[item.position]
-                    .firsts
-                    .content
-                    .contains(&TerminalRef::Epsilon)
+                && !rule
+                    .firsts_after(item.position)
+                    .contains(TerminalRef::Epsilon)
             {
                 // item is shift action and is not nullable after the dot
                 continue;
             }
-            for lookahead in &item.lookaheads.0 {
+            for lookahead in &item.lookaheads {
                 let symbol_ref: SymbolRef = lookahead.terminal.into();
                 if self.children.contains_key(&symbol_ref) {
                     // There is already a shift action for the lookahead => conflict
@@ -575,6 +1000,7 @@ impl State {
                 });
             }
         }
+        conflicts.compute_overlaps(self);
         conflicts
     }
 
@@ -586,6 +1012,50 @@ impl State {
             .find(|reduction| reduction.lookahead.terminal == terminal)
     }
 
+    /// Gets the items of this state in dependency order
+    /// The kernel items come first, then each closure item follows
+    /// the item it was derived from, depth-first.
+    #[must_use]
+    pub fn items_in_dependency_order(&self, grammar: &Grammar) -> Vec<&Item> {
+        let mut visited = vec![false; self.items.len()];
+        let mut result = Vec::with_capacity(self.items.len());
+        let mut roots = Vec::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if self.kernel.items.contains(item) {
+                visited[index] = true;
+                result.push(item);
+                roots.push(index);
+            }
+        }
+        let mut stack = Vec::new();
+        for root in roots {
+            stack.push(root);
+            while let Some(current) = stack.pop() {
+                if current != root {
+                    result.push(&self.items[current]);
+                }
+                let Some(SymbolRef::Variable(sid)) = self.items[current].get_next_symbol(grammar)
+                else {
+                    continue;
+                };
+                // push the derived items in reverse so that they are visited in order
+                for (index, item) in self.items.iter().enumerate().rev() {
+                    if !visited[index] && item.position == 0 && item.rule.variable == sid {
+                        visited[index] = true;
+                        stack.push(index);
+                    }
+                }
+            }
+        }
+        // items that are not reachable through derivations (should not happen)
+        for (index, item) in self.items.iter().enumerate() {
+            if !visited[index] {
+                result.push(item);
+            }
+        }
+        result
+    }
+
     /// Formats this state
     ///
     /// # Errors
@@ -596,6 +1066,7 @@ impl State {
         f: &mut Formatter,
         state_index: usize,
         grammar: &Grammar,
+        options: ReportOptions,
     ) -> std::fmt::Result {
         writeln!(f, "state {state_index} {{")?;
         writeln!(f, "  transitions {{")?;
@@ -613,7 +1084,7 @@ impl State {
             writeln!(
                 f,
                 "    on {} reduce a {}",
-                grammar.get_symbol_value(reduction.lookahead.terminal.into()),
+                reduction.lookahead.terminal.name_in(grammar),
                 grammar.get_symbol_value(SymbolRef::Variable(
                     reduction.rule.get_rule_in(grammar).head
                 ))
@@ -623,7 +1094,7 @@ impl State {
         writeln!(f, "  items {{")?;
         for item in &self.items {
             write!(f, "    ")?;
-            item.format(f, grammar)?;
+            item.format(f, grammar, options)?;
         }
         writeln!(f, "  }}")?;
         writeln!(f, "}}")?;
@@ -631,6 +1102,33 @@ impl State {
     }
 }
 
+/// The default maximum number of states in a LR graph
+pub const DEFAULT_MAX_STATES: usize = 100_000;
+
+/// The number of the most recently created states that are summarized when a graph has too many states
+const RECENT_STATES_SUMMARIZED: usize = 100;
+
+/// The error for a LR graph that has more states than allowed
+#[derive(Debug, Clone)]
+pub struct StateLimitError {
+    /// The maximum number of states
+    pub limit: usize,
+    /// The parsing method the graph was built for
+    pub method: ParsingMethod,
+    /// The variables heading the kernel items of the most recently created states,
+    /// with the number of these states they appear in, the most frequent first
+    pub heads: Vec<(usize, usize)>,
+}
+
+/// Escapes a value for a cell in the CSV format, by quoting it when required
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 /// Represents a LR graph
 #[derive(Debug, Clone, Default)]
 pub struct Graph {
@@ -642,18 +1140,88 @@ impl Graph {
     /// Initializes a graph from the given state
     #[must_use]
     pub fn from(state: State, grammar: &Grammar, mode: LookaheadMode) -> Graph {
-        let mut graph = Graph::default();
-        graph.states.push(state);
+        Graph::from_states(vec![state], grammar, mode)
+    }
+
+    /// Initializes a graph from the given initial states, one for each axiom
+    /// The initial states keep their index in the graph
+    #[must_use]
+    pub fn from_states(states: Vec<State>, grammar: &Grammar, mode: LookaheadMode) -> Graph {
+        // the construction is not bounded
+        Graph::build_from_states(states, grammar, mode, None, usize::MAX).unwrap_or_default()
+    }
+
+    /// Initializes a graph from the given initial states, one for each axiom,
+    /// recording the time spent on building it in the profile
+    /// The initial states keep their index in the graph
+    #[must_use]
+    pub fn from_states_profiled(
+        states: Vec<State>,
+        grammar: &Grammar,
+        mode: LookaheadMode,
+        profile: &mut GraphBuildProfile,
+    ) -> Graph {
+        Graph::build_from_states(states, grammar, mode, Some(profile), usize::MAX)
+            .unwrap_or_default()
+    }
+
+    /// Initializes a graph from the given initial states, recording the time spent in the profile, if any
+    /// The construction stops as soon as the graph has more than `max_states` states,
+    /// the partial graph is then returned as an error.
+    fn build_from_states(
+        states: Vec<State>,
+        grammar: &Grammar,
+        mode: LookaheadMode,
+        mut profile: Option<&mut GraphBuildProfile>,
+        max_states: usize,
+    ) -> Result<Graph, Graph> {
+        let mut graph = Graph { states };
         let mut i = 0;
         while i < graph.states.len() {
-            graph.build_at_state(grammar, i, mode);
+            graph.build_at_state(grammar, i, mode, profile.as_deref_mut());
+            if graph.states.len() > max_states {
+                return Err(graph);
+            }
             i += 1;
         }
-        graph
+        if let Some(profile) = profile {
+            profile.state_count = graph.states.len();
+        }
+        Ok(graph)
+    }
+
+    /// Gets the variables heading the kernel items of the most recently created states
+    /// with the number of these states they appear in, the most frequent first
+    #[must_use]
+    pub fn get_recent_kernel_heads(&self) -> Vec<(usize, usize)> {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        let start = self.states.len().saturating_sub(RECENT_STATES_SUMMARIZED);
+        for state in &self.states[start..] {
+            let mut heads: Vec<usize> = state
+                .kernel
+                .items
+                .iter()
+                .map(|item| item.rule.variable)
+                .collect();
+            heads.sort_unstable();
+            heads.dedup();
+            for head in heads {
+                *counts.entry(head).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(usize, usize)> = counts.into_iter().collect();
+        counts.sort_unstable_by(|(v1, c1), (v2, c2)| c2.cmp(c1).then(v1.cmp(v2)));
+        counts
     }
 
     /// Build this graph at the given state
-    fn build_at_state(&mut self, grammar: &Grammar, state_id: usize, mode: LookaheadMode) {
+    fn build_at_state(
+        &mut self,
+        grammar: &Grammar,
+        state_id: usize,
+        mode: LookaheadMode,
+        mut profile: Option<&mut GraphBuildProfile>,
+    ) {
         // Shift dictionnary for the current set
         let mut shifts: HashMap<SymbolRef, StateKernel> = HashMap::new();
         // Build the children kernels from the shift actions
@@ -666,9 +1234,17 @@ impl Graph {
         let mut shifts: Vec<(SymbolRef, StateKernel)> = shifts.into_iter().collect();
         shifts.sort_by_key(|(s, _)| *s);
         for (next, kernel) in shifts {
-            let child_index = match self.get_state_for(&kernel) {
-                Some(child_index) => child_index,
-                None => self.add_state(kernel.into_state(grammar, mode)),
+            let existing = timed(profile.as_deref_mut().map(|p| &mut p.dedup_time_ns), || {
+                self.get_state_for(&kernel)
+            });
+            let child_index = if let Some(child_index) = existing {
+                child_index
+            } else {
+                let state = timed(
+                    profile.as_deref_mut().map(|p| &mut p.closure_time_ns),
+                    || kernel.into_state(grammar, mode),
+                );
+                self.add_state(state)
             };
             self.states[state_id].children.insert(next, child_index);
         }
@@ -680,7 +1256,7 @@ impl Graph {
                 match item.get_next_symbol(grammar) {
                     Some(SymbolRef::Variable(sid)) => {
                         let variable = &grammar.get_variable(sid).unwrap();
-                        opening_terminals.add_others(&variable.firsts);
+                        opening_terminals.union_with(&variable.firsts);
                     }
                     Some(SymbolRef::Epsilon) => {
                         opening_terminals.add(TerminalRef::Epsilon);
@@ -749,31 +1325,1009 @@ impl Graph {
         conflicts
     }
 
+    /// Gets an iterator over the states of this graph with their identifiers
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &State)> {
+        self.states.iter().enumerate()
+    }
+
     /// Gets the inverse graph
     #[must_use]
     pub fn inverse(&self) -> InverseGraph {
         InverseGraph::from(self)
     }
 
-    /// Formats this graph
-    ///
-    /// # Errors
-    ///
-    /// Return an error when formatting fails
-    pub fn format(&self, f: &mut Formatter, grammar: &Grammar) -> std::fmt::Result {
-        for (index, state) in self.states.iter().enumerate() {
-            state.format(f, index, grammar)?;
+    /// Gets the lexical contexts, other than the default one, that may be open in each state, indexed by state
+    /// An item is within the context of its rule and within the contexts of the item it derives from,
+    /// either in the closure of a state or through a transition, until the rule of the context is reduced.
+    /// A context opened by a transition remains open in the states reached from there,
+    /// which over-approximates the contexts actually open since reductions are not followed.
+    #[must_use]
+    pub fn get_reachable_contexts(&self, grammar: &Grammar) -> Vec<Vec<usize>> {
+        let mut result = vec![Vec::new(); self.states.len()];
+        // the contexts of the kernel items of each state, by rule and position
+        let mut kernels: Vec<HashMap<(RuleRef, usize), Vec<usize>>> =
+            vec![HashMap::new(); self.states.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (from, state) in self.states.iter().enumerate() {
+                let item_contexts = state.get_item_contexts(&kernels[from], grammar);
+                for (&symbol, &to) in &state.children {
+                    let mut carried = result[from].clone();
+                    for (item, contexts) in state.items.iter().zip(&item_contexts) {
+                        if item.get_next_symbol(grammar) != Some(symbol) {
+                            continue;
+                        }
+                        let kernel = kernels[to]
+                            .entry((item.rule, item.position + 1))
+                            .or_default();
+                        for &context in contexts {
+                            if !kernel.contains(&context) {
+                                kernel.push(context);
+                                changed = true;
+                            }
+                        }
+                        carried.extend_from_slice(contexts);
+                    }
+                    if let SymbolRef::Terminal(id) = symbol {
+                        if let Some(contexts) =
+                            state.opening_contexts.get(&TerminalRef::Terminal(id))
+                        {
+                            carried.extend_from_slice(contexts);
+                        }
+                    }
+                    for context in carried {
+                        if let Err(index) = result[to].binary_search(&context) {
+                            result[to].insert(index, context);
+                            changed = true;
+                        }
+                    }
+                }
+            }
         }
-        Ok(())
+        result
     }
-}
-
-/// An inverse LR graph
-#[derive(Debug, Clone, Default)]
-pub struct InverseGraph(HashMap<usize, HashMap<SymbolRef, Vec<usize>>>);
 
-/// Queue element for exploring paths in the LR graph
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    /// Builds the incoming transitions of each state, indexed by state
+    /// Each transition is given as the symbol and the state it comes from, ordered by origin state.
+    #[must_use]
+    pub fn build_predecessors(&self) -> Vec<Vec<(SymbolRef, usize)>> {
+        let mut predecessors = vec![Vec::new(); self.states.len()];
+        for (from, state) in self.states.iter().enumerate() {
+            for (&symbol, &to) in &state.children {
+                predecessors[to].push((symbol, from));
+            }
+        }
+        for incoming in &mut predecessors {
+            incoming.sort_unstable_by_key(|&(symbol, from)| (from, symbol));
+        }
+        predecessors
+    }
+
+    /// Gets short phrases that can be accepted from the specified stack of states up to the end of the input, shortest first
+    /// The stack goes from the bottom to the top, the phrases do not include the final `$`.
+    #[must_use]
+    pub fn get_completions_from(
+        &self,
+        stack: &[usize],
+        grammar: &Grammar,
+        max: usize,
+    ) -> Vec<Phrase> {
+        let mut results: Vec<Phrase> = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(Completion {
+            stack: stack.to_vec(),
+            lookahead: None,
+            phrase: Phrase::default(),
+        });
+        let mut budget = COMPLETIONS_BUDGET;
+        while let Some(current) = queue.pop_front() {
+            if results.len() >= max || budget == 0 {
+                break;
+            }
+            budget -= 1;
+            if !visited.insert((
+                current.stack.clone(),
+                current.lookahead,
+                current.phrase.0.clone(),
+            )) {
+                continue;
+            }
+            let top = &self.states[current.stack[current.stack.len() - 1]];
+            // reductions do not make the phrase longer, explore them first
+            for reduction in &top.reductions {
+                let terminal = reduction.lookahead.terminal;
+                if terminal == TerminalRef::Epsilon {
+                    // accepting after `$`, the phrase is already complete
+                    continue;
+                }
+                let lookahead = if terminal == TerminalRef::NullTerminal {
+                    current.lookahead
+                } else if current
+                    .lookahead
+                    .is_none_or(|expected| expected == terminal)
+                {
+                    Some(terminal)
+                } else {
+                    continue;
+                };
+                let head = SymbolRef::Variable(reduction.rule.get_rule_in(grammar).head);
+                if reduction.length >= current.stack.len() {
+                    // the bottom of the stack cannot be popped
+                    continue;
+                }
+                let mut stack = current.stack[..(current.stack.len() - reduction.length)].to_vec();
+                if let Some(&next) = self.states[stack[stack.len() - 1]].children.get(&head) {
+                    stack.push(next);
+                    queue.push_front(Completion {
+                        stack,
+                        lookahead,
+                        phrase: current.phrase.clone(),
+                    });
+                }
+            }
+            let mut shifts: Vec<(TerminalRef, usize)> = top
+                .children
+                .iter()
+                .filter_map(|(symbol, &next)| match *symbol {
+                    SymbolRef::Dollar => Some((TerminalRef::Dollar, next)),
+                    SymbolRef::Terminal(id) => Some((TerminalRef::Terminal(id), next)),
+                    _ => None,
+                })
+                .filter(|(terminal, _)| {
+                    current
+                        .lookahead
+                        .is_none_or(|expected| expected == *terminal)
+                })
+                .collect();
+            shifts.sort_by_key(|(terminal, _)| terminal.sid());
+            for (terminal, next) in shifts {
+                if terminal == TerminalRef::Dollar {
+                    if !results.contains(&current.phrase) {
+                        results.push(current.phrase.clone());
+                    }
+                    continue;
+                }
+                let mut stack = current.stack.clone();
+                stack.push(next);
+                let mut phrase = current.phrase.clone();
+                phrase.append(terminal);
+                queue.push_back(Completion {
+                    stack,
+                    lookahead: None,
+                    phrase,
+                });
+            }
+        }
+        results.truncate(max);
+        results
+    }
+
+    /// Gets the phrases of at most `max_len` terminals that have more than one derivation, shortest first
+    /// All the runs of the parser from the initial state are simulated, following all the reductions of each state.
+    /// A phrase is ambiguous when more than one run accepts it.
+    /// Right-nullable reductions are not followed because the complete reduction of the same rule
+    /// is also reached through the reductions of the nullable variables, with the same derivation.
+    #[must_use]
+    pub fn find_ambiguities(&self, grammar: &Grammar, max_len: usize) -> Vec<Phrase> {
+        let mut accepted: HashMap<Vec<TerminalRef>, usize> = HashMap::new();
+        let mut pending = vec![Completion {
+            stack: vec![0],
+            lookahead: None,
+            phrase: Phrase::default(),
+        }];
+        let mut budget = AMBIGUITIES_BUDGET;
+        while let Some(current) = pending.pop() {
+            if budget == 0 {
+                break;
+            }
+            budget -= 1;
+            let top = &self.states[current.stack[current.stack.len() - 1]];
+            let mut reductions: Vec<&Reduction> = Vec::new();
+            for reduction in &top.reductions {
+                if !reduction.is_right_nullable(grammar)
+                    && !reductions.iter().any(|other| {
+                        other.rule == reduction.rule
+                            && other.lookahead.terminal == reduction.lookahead.terminal
+                    })
+                {
+                    reductions.push(reduction);
+                }
+            }
+            for reduction in reductions {
+                let terminal = reduction.lookahead.terminal;
+                if terminal == TerminalRef::Epsilon {
+                    continue;
+                }
+                let lookahead = if terminal == TerminalRef::NullTerminal {
+                    current.lookahead
+                } else if current
+                    .lookahead
+                    .is_none_or(|expected| expected == terminal)
+                {
+                    Some(terminal)
+                } else {
+                    continue;
+                };
+                if reduction.length >= current.stack.len() {
+                    continue;
+                }
+                let head = SymbolRef::Variable(reduction.rule.get_rule_in(grammar).head);
+                let mut stack = current.stack[..(current.stack.len() - reduction.length)].to_vec();
+                if let Some(&next) = self.states[stack[stack.len() - 1]].children.get(&head) {
+                    stack.push(next);
+                    pending.push(Completion {
+                        stack,
+                        lookahead,
+                        phrase: current.phrase.clone(),
+                    });
+                }
+            }
+            for (symbol, &next) in &top.children {
+                let terminal = match *symbol {
+                    SymbolRef::Dollar => TerminalRef::Dollar,
+                    SymbolRef::Terminal(id) => TerminalRef::Terminal(id),
+                    _ => continue,
+                };
+                if current
+                    .lookahead
+                    .is_some_and(|expected| expected != terminal)
+                {
+                    continue;
+                }
+                if terminal == TerminalRef::Dollar {
+                    *accepted.entry(current.phrase.0.clone()).or_default() += 1;
+                } else if current.phrase.len() < max_len {
+                    let mut stack = current.stack.clone();
+                    stack.push(next);
+                    let mut phrase = current.phrase.clone();
+                    phrase.append(terminal);
+                    pending.push(Completion {
+                        stack,
+                        lookahead: None,
+                        phrase,
+                    });
+                }
+            }
+        }
+        let mut results: Vec<Phrase> = accepted
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(terminals, _)| Phrase(terminals))
+            .collect();
+        results.sort_by_cached_key(|phrase| {
+            (
+                phrase.len(),
+                phrase
+                    .0
+                    .iter()
+                    .map(|terminal| terminal.sid())
+                    .collect::<Vec<_>>(),
+            )
+        });
+        results
+    }
+
+    /// Formats this graph
+    ///
+    /// # Errors
+    ///
+    /// Return an error when formatting fails
+    pub fn format(
+        &self,
+        f: &mut Formatter,
+        grammar: &Grammar,
+        options: ReportOptions,
+    ) -> std::fmt::Result {
+        for (index, state) in self.states.iter().enumerate() {
+            state.format(f, index, grammar, options)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the parsing table of this graph in the CSV format, with a row for each state
+    /// The columns are for `$`, then for the expected terminals and the grammar's variables.
+    /// A cell contains `sN` for a shift or a goto to the state `N`, `rK` for a reduction
+    /// with the rule `K` and `acc` when the input is accepted, separated by spaces when there are several.
+    /// The rules are numbered in the order of the variables and then of their rules, as in the parser tables.
+    /// An empty cell is an error.
+    #[must_use]
+    pub fn to_csv(&self, grammar: &Grammar) -> String {
+        let rules: HashMap<RuleRef, usize> = grammar
+            .variables
+            .iter()
+            .flat_map(|variable| {
+                (0..variable.rules.len()).map(|index| RuleRef::new(variable.id, index))
+            })
+            .enumerate()
+            .map(|(index, rule)| (rule, index))
+            .collect();
+        let is_accepting = |rule: RuleRef| {
+            rule.get_rule_in(grammar).body.choices[0]
+                .elements
+                .last()
+                .is_some_and(|element| element.symbol == SymbolRef::Dollar)
+        };
+        let mut terminals = vec![TerminalRef::Dollar];
+        terminals.extend(
+            self.expected_terminals()
+                .content
+                .into_iter()
+                .filter(|terminal| matches!(terminal, TerminalRef::Terminal(_))),
+        );
+        let mut result = String::from("state");
+        for terminal in &terminals {
+            result.push(',');
+            result.push_str(&escape_csv(grammar.get_symbol_value((*terminal).into())));
+        }
+        for variable in &grammar.variables {
+            result.push(',');
+            result.push_str(&escape_csv(&variable.name));
+        }
+        result.push('\n');
+        for (index, state) in self.states.iter().enumerate() {
+            result.push_str(&index.to_string());
+            for &terminal in &terminals {
+                let mut actions = Vec::new();
+                if let Some(next) = state.children.get(&terminal.into()) {
+                    actions.push(format!("s{next}"));
+                }
+                for reduction in &state.reductions {
+                    if is_accepting(reduction.rule) {
+                        if terminal == TerminalRef::Dollar {
+                            actions.push(String::from("acc"));
+                        }
+                    } else if reduction.lookahead.terminal == terminal
+                        || reduction.lookahead.terminal == TerminalRef::NullTerminal
+                    {
+                        actions.push(format!("r{}", rules[&reduction.rule]));
+                    }
+                }
+                result.push(',');
+                result.push_str(&actions.join(" "));
+            }
+            for variable in &grammar.variables {
+                result.push(',');
+                if let Some(next) = state.children.get(&SymbolRef::Variable(variable.id)) {
+                    result.push('s');
+                    result.push_str(&next.to_string());
+                }
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Gets whether the specified state accepts the input
+    #[must_use]
+    pub fn is_accepting(&self, state: usize, grammar: &Grammar) -> bool {
+        self.states[state].is_accepting(grammar)
+    }
+
+    /// Gets the lexical contexts declared in the grammar that are never opened in this graph
+    #[must_use]
+    pub fn unused_contexts(&self, grammar: &Grammar) -> Vec<usize> {
+        // the default context is always available
+        (1..grammar.contexts.len())
+            .filter(|context| {
+                self.states.iter().all(|state| {
+                    state
+                        .opening_contexts
+                        .values()
+                        .all(|contexts| !contexts.contains(context))
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the terminals that are expected (shifted or used as lookahead) in at least one state
+    #[must_use]
+    pub fn expected_terminals(&self) -> TerminalSet {
+        let mut expected = TerminalSet::default();
+        for state in &self.states {
+            for symbol in state.children.keys() {
+                if let SymbolRef::Terminal(id) = symbol {
+                    expected.add(TerminalRef::Terminal(*id));
+                }
+            }
+            for reduction in &state.reductions {
+                expected.add(reduction.lookahead.terminal);
+            }
+        }
+        expected
+    }
+
+    /// Finds the reductions that can never be applied, with the state they are in
+    /// A reduction is dead when none of the states reached after it, going back through the predecessors
+    /// and then to the goto on the rule's head, can shift its lookahead or apply another live reduction on it.
+    /// The reductions without lookahead (LR(0)) and the reductions of the generated axioms that accept the input
+    /// are always considered live.
+    #[must_use]
+    pub fn find_dead_reductions(&self, grammar: &Grammar) -> Vec<(usize, Reduction)> {
+        let predecessors = self.build_predecessors();
+        // the states reached after each reduction
+        let targets: Vec<Vec<Vec<usize>>> = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(state_id, state)| {
+                state
+                    .reductions
+                    .iter()
+                    .map(|reduction| {
+                        let mut origins = vec![state_id];
+                        for _ in 0..reduction.length {
+                            origins = origins
+                                .iter()
+                                .flat_map(|&origin| {
+                                    predecessors[origin].iter().map(|&(_, from)| from)
+                                })
+                                .collect();
+                            origins.sort_unstable();
+                            origins.dedup();
+                        }
+                        let head = SymbolRef::Variable(reduction.rule.get_rule_in(grammar).head);
+                        origins
+                            .iter()
+                            .filter_map(|origin| self.states[*origin].children.get(&head).copied())
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut live: Vec<Vec<bool>> = self
+            .states
+            .iter()
+            .map(|state| {
+                state
+                    .reductions
+                    .iter()
+                    .map(|reduction| {
+                        reduction.lookahead.terminal == TerminalRef::NullTerminal
+                            || reduction.rule.get_rule_in(grammar).body.choices[0]
+                                .elements
+                                .last()
+                                .is_some_and(|element| element.symbol == SymbolRef::Dollar)
+                    })
+                    .collect()
+            })
+            .collect();
+        let can_act = |live: &[Vec<bool>], state_id: usize, terminal: TerminalRef| {
+            let state = &self.states[state_id];
+            state.children.contains_key(&terminal.into())
+                || state
+                    .reductions
+                    .iter()
+                    .zip(&live[state_id])
+                    .any(|(reduction, &live)| {
+                        live && (reduction.lookahead.terminal == terminal
+                            || reduction.lookahead.terminal == TerminalRef::NullTerminal)
+                    })
+        };
+        let mut modified = true;
+        while modified {
+            modified = false;
+            for (state_id, state) in self.states.iter().enumerate() {
+                for (index, reduction) in state.reductions.iter().enumerate() {
+                    if !live[state_id][index]
+                        && targets[state_id][index]
+                            .iter()
+                            .any(|&target| can_act(&live, target, reduction.lookahead.terminal))
+                    {
+                        live[state_id][index] = true;
+                        modified = true;
+                    }
+                }
+            }
+        }
+        self.states
+            .iter()
+            .enumerate()
+            .flat_map(|(state_id, state)| {
+                state
+                    .reductions
+                    .iter()
+                    .zip(&live[state_id])
+                    .filter(|(_, &live)| !live)
+                    .map(move |(reduction, _)| (state_id, reduction.clone()))
+            })
+            .collect()
+    }
+
+    /// Finds pairs of distinct states whose item sets are the same up to a consistent renaming of the variables
+    /// The item sets are compared once their variables are relabeled greedily (see `get_relabeled_items`).
+    /// The states in a pair are always equivalent, but this is not an exhaustive search:
+    /// two equivalent states are missed when the greedy relabeling of their item sets differs.
+    /// The pairs are sorted, the smallest state coming first in each pair.
+    #[must_use]
+    pub fn find_renamed_state_pairs(&self, grammar: &Grammar) -> Vec<(usize, usize)> {
+        let mut classes: HashMap<Vec<RelabeledItem>, Vec<usize>> = HashMap::new();
+        for (index, state) in self.states.iter().enumerate() {
+            classes
+                .entry(get_relabeled_items(state, grammar))
+                .or_default()
+                .push(index);
+        }
+        let mut result = Vec::new();
+        for states in classes.values() {
+            for (rank, &left) in states.iter().enumerate() {
+                for &right in &states[rank + 1..] {
+                    result.push((left, right));
+                }
+            }
+        }
+        result.sort_unstable();
+        result
+    }
+
+    /// Computes the structural differences between this graph and another one built for the same grammar
+    /// States are matched when their kernels have the same items, regardless of the lookaheads
+    #[must_use]
+    pub fn diff(&self, other: &Graph) -> GraphDiff {
+        let mut other_states = HashMap::new();
+        for (index, state) in other.states.iter().enumerate().rev() {
+            other_states.insert(state.kernel.get_bases(), index);
+        }
+        let matched: Vec<Option<usize>> = self
+            .states
+            .iter()
+            .map(|state| other_states.get(&state.kernel.get_bases()).copied())
+            .collect();
+        let mut diff = GraphDiff::default();
+        let mut found = vec![false; other.states.len()];
+        for (index, state) in self.states.iter().enumerate() {
+            let Some(other_index) = matched[index] else {
+                diff.removed_states.push((index, state.kernel.clone()));
+                continue;
+            };
+            found[other_index] = true;
+            let other_state = &other.states[other_index];
+            // compare the reductions
+            let reductions: Vec<(TerminalRef, RuleRef)> = state
+                .reductions
+                .iter()
+                .map(|r| (r.lookahead.terminal, r.rule))
+                .collect();
+            let other_reductions: Vec<(TerminalRef, RuleRef)> = other_state
+                .reductions
+                .iter()
+                .map(|r| (r.lookahead.terminal, r.rule))
+                .collect();
+            let removed: Vec<(TerminalRef, RuleRef)> = reductions
+                .iter()
+                .filter(|r| !other_reductions.contains(r))
+                .copied()
+                .collect();
+            let added: Vec<(TerminalRef, RuleRef)> = other_reductions
+                .iter()
+                .filter(|r| !reductions.contains(r))
+                .copied()
+                .collect();
+            if !removed.is_empty() || !added.is_empty() {
+                diff.changed_reductions.push(StateChanges {
+                    state: index,
+                    other_state: other_index,
+                    kernel: state.kernel.clone(),
+                    removed,
+                    added,
+                });
+            }
+            // compare the transitions, the targets must be equivalent states
+            let mut removed: Vec<(SymbolRef, usize)> = state
+                .children
+                .iter()
+                .filter(|(symbol, &target)| {
+                    other_state.children.get(symbol).copied() != matched[target]
+                })
+                .map(|(&symbol, &target)| (symbol, target))
+                .collect();
+            let mut added: Vec<(SymbolRef, usize)> = other_state
+                .children
+                .iter()
+                .filter(|(symbol, &target)| {
+                    state
+                        .children
+                        .get(symbol)
+                        .is_none_or(|&original| matched[original] != Some(target))
+                })
+                .map(|(&symbol, &target)| (symbol, target))
+                .collect();
+            if !removed.is_empty() || !added.is_empty() {
+                removed.sort_unstable();
+                added.sort_unstable();
+                diff.changed_transitions.push(StateChanges {
+                    state: index,
+                    other_state: other_index,
+                    kernel: state.kernel.clone(),
+                    removed,
+                    added,
+                });
+            }
+        }
+        for (index, state) in other.states.iter().enumerate() {
+            if !found[index] {
+                diff.new_states.push((index, state.kernel.clone()));
+            }
+        }
+        diff
+    }
+}
+
+impl IntoIterator for Graph {
+    type Item = State;
+    type IntoIter = std::vec::IntoIter<State>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.states.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Graph {
+    type Item = (usize, &'a State);
+    type IntoIter = std::iter::Enumerate<std::slice::Iter<'a, State>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.states.iter().enumerate()
+    }
+}
+
+impl Graph {
+    /// Writes this graph in a binary form, to be loaded back with `read_from`
+    /// The graph is only meaningful for the grammar it was built from.
+    ///
+    /// # Errors
+    ///
+    /// Return an `std::io::Error` when the writer fails
+    pub fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_usize(writer, self.states.len())?;
+        for state in &self.states {
+            write_items(writer, &state.kernel.items)?;
+            write_items(writer, &state.items)?;
+            write_usize(writer, state.children.len())?;
+            for (symbol, child) in &state.children {
+                write_symbol_ref(writer, *symbol)?;
+                write_usize(writer, *child)?;
+            }
+            write_usize(writer, state.opening_contexts.len())?;
+            for (terminal, contexts) in &state.opening_contexts {
+                write_terminal_ref(writer, *terminal)?;
+                write_usize(writer, contexts.len())?;
+                for context in contexts {
+                    write_usize(writer, *context)?;
+                }
+            }
+            write_usize(writer, state.reductions.len())?;
+            for reduction in &state.reductions {
+                write_lookahead(writer, &reduction.lookahead)?;
+                write_rule_ref(writer, reduction.rule)?;
+                write_usize(writer, reduction.length)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a graph written by `write_to`
+    ///
+    /// # Errors
+    ///
+    /// Return an `std::io::Error` when the reader fails or the data is not a graph
+    pub fn read_from(reader: &mut dyn Read) -> io::Result<Graph> {
+        let count = read_usize(reader)?;
+        let mut states = Vec::new();
+        for _ in 0..count {
+            let kernel = StateKernel {
+                items: read_items(reader)?,
+            };
+            let items = read_items(reader)?;
+            let mut children = HashMap::new();
+            for _ in 0..read_usize(reader)? {
+                let symbol = read_symbol_ref(reader)?;
+                children.insert(symbol, read_usize(reader)?);
+            }
+            let mut opening_contexts = HashMap::new();
+            for _ in 0..read_usize(reader)? {
+                let terminal = read_terminal_ref(reader)?;
+                let contexts = (0..read_usize(reader)?)
+                    .map(|_| read_usize(reader))
+                    .collect::<io::Result<Vec<_>>>()?;
+                opening_contexts.insert(terminal, contexts);
+            }
+            let mut reductions = Vec::new();
+            for _ in 0..read_usize(reader)? {
+                reductions.push(Reduction {
+                    lookahead: read_lookahead(reader)?,
+                    rule: read_rule_ref(reader)?,
+                    length: read_usize(reader)?,
+                });
+            }
+            states.push(State {
+                kernel,
+                items,
+                children,
+                opening_contexts,
+                reductions,
+            });
+        }
+        Ok(Graph { states })
+    }
+}
+
+/// Writes an unsigned integer in the binary form of a graph
+fn write_usize(writer: &mut dyn Write, value: usize) -> io::Result<()> {
+    write_u32(writer, value as u32)
+}
+
+/// Reads an unsigned integer in the binary form of a graph
+fn read_usize(reader: &mut dyn Read) -> io::Result<usize> {
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer) as usize)
+}
+
+/// Gets the error for invalid data in the binary form of a graph
+fn invalid_graph_data(what: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid {what} in graph data"),
+    )
+}
+
+/// Writes a reference to a terminal in the binary form of a graph
+fn write_terminal_ref(writer: &mut dyn Write, terminal: TerminalRef) -> io::Result<()> {
+    write_symbol_ref(writer, terminal.into())
+}
+
+/// Reads a reference to a terminal in the binary form of a graph
+fn read_terminal_ref(reader: &mut dyn Read) -> io::Result<TerminalRef> {
+    match read_symbol_ref(reader)? {
+        SymbolRef::Dummy => Ok(TerminalRef::Dummy),
+        SymbolRef::Epsilon => Ok(TerminalRef::Epsilon),
+        SymbolRef::Dollar => Ok(TerminalRef::Dollar),
+        SymbolRef::NullTerminal => Ok(TerminalRef::NullTerminal),
+        SymbolRef::Terminal(id) => Ok(TerminalRef::Terminal(id)),
+        _ => Err(invalid_graph_data("terminal")),
+    }
+}
+
+/// Writes a reference to a symbol in the binary form of a graph
+fn write_symbol_ref(writer: &mut dyn Write, symbol: SymbolRef) -> io::Result<()> {
+    let (tag, id) = match symbol {
+        SymbolRef::Dummy => (0, 0),
+        SymbolRef::Epsilon => (1, 0),
+        SymbolRef::Dollar => (2, 0),
+        SymbolRef::NullTerminal => (3, 0),
+        SymbolRef::Terminal(id) => (4, id),
+        SymbolRef::Variable(id) => (5, id),
+        SymbolRef::Virtual(id) => (6, id),
+        SymbolRef::Action(id) => (7, id),
+    };
+    write_u8(writer, tag)?;
+    write_usize(writer, id)
+}
+
+/// Reads a reference to a symbol in the binary form of a graph
+fn read_symbol_ref(reader: &mut dyn Read) -> io::Result<SymbolRef> {
+    let mut tag = [0; 1];
+    reader.read_exact(&mut tag)?;
+    let id = read_usize(reader)?;
+    match tag[0] {
+        0 => Ok(SymbolRef::Dummy),
+        1 => Ok(SymbolRef::Epsilon),
+        2 => Ok(SymbolRef::Dollar),
+        3 => Ok(SymbolRef::NullTerminal),
+        4 => Ok(SymbolRef::Terminal(id)),
+        5 => Ok(SymbolRef::Variable(id)),
+        6 => Ok(SymbolRef::Virtual(id)),
+        7 => Ok(SymbolRef::Action(id)),
+        _ => Err(invalid_graph_data("symbol")),
+    }
+}
+
+/// Writes a reference to a rule in the binary form of a graph
+fn write_rule_ref(writer: &mut dyn Write, rule: RuleRef) -> io::Result<()> {
+    write_usize(writer, rule.variable)?;
+    write_usize(writer, rule.index)
+}
+
+/// Reads a reference to a rule in the binary form of a graph
+fn read_rule_ref(reader: &mut dyn Read) -> io::Result<RuleRef> {
+    let variable = read_usize(reader)?;
+    Ok(RuleRef::new(variable, read_usize(reader)?))
+}
+
+/// Writes a lookahead in the binary form of a graph
+fn write_lookahead(writer: &mut dyn Write, lookahead: &Lookahead) -> io::Result<()> {
+    write_terminal_ref(writer, lookahead.terminal)?;
+    write_usize(writer, lookahead.origins.len())?;
+    for LookaheadOrigin::FirstOf(choice) in &lookahead.origins {
+        write_rule_ref(writer, choice.rule)?;
+        write_usize(writer, choice.position)?;
+    }
+    Ok(())
+}
+
+/// Reads a lookahead in the binary form of a graph
+fn read_lookahead(reader: &mut dyn Read) -> io::Result<Lookahead> {
+    let terminal = read_terminal_ref(reader)?;
+    let mut origins = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        let rule = read_rule_ref(reader)?;
+        origins.push(LookaheadOrigin::FirstOf(RuleChoiceRef {
+            rule,
+            position: read_usize(reader)?,
+        }));
+    }
+    Ok(Lookahead { terminal, origins })
+}
+
+/// Writes LR items in the binary form of a graph
+fn write_items(writer: &mut dyn Write, items: &[Item]) -> io::Result<()> {
+    write_usize(writer, items.len())?;
+    for item in items {
+        write_rule_ref(writer, item.rule)?;
+        write_usize(writer, item.position)?;
+        write_usize(writer, item.lookaheads.len())?;
+        for lookahead in &item.lookaheads {
+            write_lookahead(writer, lookahead)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads LR items in the binary form of a graph
+fn read_items(reader: &mut dyn Read) -> io::Result<Vec<Item>> {
+    let mut items = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        let rule = read_rule_ref(reader)?;
+        let position = read_usize(reader)?;
+        let mut lookaheads = Lookaheads::default();
+        for _ in 0..read_usize(reader)? {
+            lookaheads.add(&read_lookahead(reader)?);
+        }
+        items.push(Item {
+            rule,
+            position,
+            lookaheads,
+        });
+    }
+    Ok(items)
+}
+
+/// The changes of a LR state between two graphs
+#[derive(Debug, Clone)]
+pub struct StateChanges<T> {
+    /// The identifier of the state in the original graph
+    pub state: usize,
+    /// The identifier of the equivalent state in the other graph
+    pub other_state: usize,
+    /// The kernel of the state in the original graph
+    pub kernel: StateKernel,
+    /// The elements only found in the original graph
+    pub removed: Vec<T>,
+    /// The elements only found in the other graph
+    pub added: Vec<T>,
+}
+
+/// The structural differences between two LR graphs
+#[derive(Debug, Clone, Default)]
+pub struct GraphDiff {
+    /// The states (identifier and kernel) of the other graph that have no equivalent in the original graph
+    pub new_states: Vec<(usize, StateKernel)>,
+    /// The states (identifier and kernel) of the original graph that have no equivalent in the other graph
+    pub removed_states: Vec<(usize, StateKernel)>,
+    /// The equivalent states with different reductions, as (lookahead, rule)
+    pub changed_reductions: Vec<StateChanges<(TerminalRef, RuleRef)>>,
+    /// The equivalent states with different transitions, as (symbol, target state)
+    pub changed_transitions: Vec<StateChanges<(SymbolRef, usize)>>,
+}
+
+impl GraphDiff {
+    /// Gets whether the two graphs are structurally equivalent
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.new_states.is_empty()
+            && self.removed_states.is_empty()
+            && self.changed_reductions.is_empty()
+            && self.changed_transitions.is_empty()
+    }
+
+    /// Gets a human-readable description of the differences
+    #[must_use]
+    pub fn to_string(&self, grammar: &Grammar) -> String {
+        GraphDiffWithGrammar {
+            grammar,
+            diff: self,
+            options: ReportOptions::default(),
+        }
+        .to_string()
+    }
+
+    /// Formats the differences
+    ///
+    /// # Errors
+    ///
+    /// Return an error when formatting fails
+    pub fn format(
+        &self,
+        f: &mut Formatter,
+        grammar: &Grammar,
+        options: ReportOptions,
+    ) -> std::fmt::Result {
+        for (index, kernel) in &self.new_states {
+            writeln!(f, "+ state {index}")?;
+            kernel.format(f, grammar, options)?;
+        }
+        for (index, kernel) in &self.removed_states {
+            writeln!(f, "- state {index}")?;
+            kernel.format(f, grammar, options)?;
+        }
+        for changes in &self.changed_reductions {
+            writeln!(
+                f,
+                "~ state {} -> {}: reductions",
+                changes.state, changes.other_state
+            )?;
+            changes.kernel.format(f, grammar, options)?;
+            for (prefix, reductions) in [("-", &changes.removed), ("+", &changes.added)] {
+                for (lookahead, rule) in reductions {
+                    let rule = rule.get_rule_in(grammar);
+                    write!(
+                        f,
+                        "  {prefix} on {} reduce {} ->",
+                        lookahead.name_in(grammar),
+                        grammar.get_symbol_name(SymbolRef::Variable(rule.head))
+                    )?;
+                    for element in &rule.body.choices[0].elements {
+                        write!(f, " {}", grammar.get_symbol_value(element.symbol))?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        for changes in &self.changed_transitions {
+            writeln!(
+                f,
+                "~ state {} -> {}: transitions",
+                changes.state, changes.other_state
+            )?;
+            changes.kernel.format(f, grammar, options)?;
+            for (prefix, transitions) in [("-", &changes.removed), ("+", &changes.added)] {
+                for (symbol, target) in transitions {
+                    writeln!(
+                        f,
+                        "  {prefix} on {} goto {target}",
+                        grammar.get_symbol_value(*symbol)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The differences between two graphs along with the associated grammar
+pub struct GraphDiffWithGrammar<'a> {
+    /// The grammar
+    pub grammar: &'a Grammar,
+    /// The differences between the graphs
+    pub diff: &'a GraphDiff,
+    /// The options for rendering the items
+    pub options: ReportOptions,
+}
+
+impl Display for GraphDiffWithGrammar<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        self.diff.format(f, self.grammar, self.options)
+    }
+}
+
+/// An inverse LR graph
+#[derive(Debug, Clone, Default)]
+pub struct InverseGraph(HashMap<usize, HashMap<SymbolRef, Vec<usize>>>);
+
+/// Queue element for exploring paths in the LR graph
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 struct PNode {
     /// The associated LR state
     state: usize,
@@ -849,10 +2403,16 @@ impl InverseGraph {
     /// Gets all the paths from state 0 to the specified one
     #[must_use]
     pub fn get_paths_to(&self, target: usize) -> Vec<Path> {
-        if target == 0 {
-            // for the first state, a single path that is empty
+        self.get_paths_from(0, target)
+    }
+
+    /// Gets all the paths from an initial state to the specified one
+    #[must_use]
+    pub fn get_paths_from(&self, initial: usize, target: usize) -> Vec<Path> {
+        if target == initial {
+            // for the initial state, a single path that is empty
             return vec![Path(vec![PathElem {
-                state: 0,
+                state: initial,
                 transition: None,
             }])];
         }
@@ -872,7 +2432,7 @@ impl InverseGraph {
                         visited_with.push(*symbol);
                         let index = elements.len();
                         elements.push(PNode::new(*previous, Some(*symbol), Some(current)));
-                        if *previous == 0 {
+                        if *previous == initial {
                             goals.push(index);
                         } else {
                             queue.push(index);
@@ -902,13 +2462,35 @@ impl InverseGraph {
     /// Gets possible inputs that allows for reaching the specified state from state 0
     #[must_use]
     pub fn get_inputs_for(&self, state: usize, grammar: &Grammar) -> Vec<Phrase> {
-        self.get_paths_to(state)
+        self.get_inputs_from(0, state, grammar)
+    }
+
+    /// Gets possible inputs that allows for reaching the specified state from an initial state
+    #[must_use]
+    pub fn get_inputs_from(&self, initial: usize, state: usize, grammar: &Grammar) -> Vec<Phrase> {
+        self.get_paths_from(initial, state)
             .into_iter()
             .map(|path| path.get_phrase(grammar))
             .collect()
     }
 }
 
+/// The maximum number of parser configurations explored when looking for completions
+const COMPLETIONS_BUDGET: usize = 10_000;
+
+/// The maximum number of parser configurations explored when looking for ambiguities
+const AMBIGUITIES_BUDGET: usize = 100_000;
+
+/// A configuration of the parser when looking for completions, or ambiguities
+struct Completion {
+    /// The stack of states, with the top last
+    stack: Vec<usize>,
+    /// The terminal a previous reduction was made on, which must be shifted next
+    lookahead: Option<TerminalRef>,
+    /// The terminals shifted so far
+    phrase: Phrase,
+}
+
 /// Represents a phrase that can be produced by grammar.
 /// It is essentially a list of terminals
 #[derive(Debug, Default, Clone, Eq)]
@@ -921,11 +2503,32 @@ impl PartialEq for Phrase {
 }
 
 impl Phrase {
+    /// Gets the number of terminals in this phrase
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Gets whether this phrase is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Appends a terminal to this phrase
     pub fn append(&mut self, terminal: TerminalRef) {
         self.0.push(terminal);
     }
 
+    /// Gets a displayable form of this phrase, using the names of the terminals in the grammar
+    #[must_use]
+    pub fn display<'g>(&'g self, grammar: &'g Grammar) -> impl Display + 'g {
+        PhraseWithGrammar {
+            grammar,
+            phrase: self,
+        }
+    }
+
     /// Builds the input by decomposing the given variable
     /// This methods recursively triggers the production of encoutered variables to arrive to the terminal symbols.
     /// The methods also tries do not go into an infinite loop by keeping track of the rule definitions that are currently used.
@@ -933,7 +2536,7 @@ impl Phrase {
     fn build_input(&mut self, grammar: &Grammar, variable: usize, stack: &mut Vec<RuleRef>) {
         let variable = grammar.get_variable(variable).unwrap();
         // if the variable to decompose is nullable (epsilon is in the FIRSTS state), stop here
-        if variable.firsts.content.contains(&TerminalRef::Epsilon) {
+        if variable.firsts.contains(TerminalRef::Epsilon) {
             return;
         }
         let rule_index = (0..(variable.rules.len()))
@@ -977,6 +2580,26 @@ impl Phrase {
     }
 }
 
+/// A phrase along with the associated grammar
+struct PhraseWithGrammar<'a> {
+    /// The grammar
+    grammar: &'a Grammar,
+    /// The phrase to display
+    phrase: &'a Phrase,
+}
+
+impl Display for PhraseWithGrammar<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for (index, terminal) in self.phrase.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", terminal.name_in(self.grammar))?;
+        }
+        Ok(())
+    }
+}
+
 /// The kinds of LR conflicts
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ConflictKind {
@@ -986,6 +2609,93 @@ pub enum ConflictKind {
     ReduceReduce,
 }
 
+/// A symbol in an item set where the variables are relabeled
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum RelabeledSymbol {
+    /// A variable, with its label if it has already been assigned one
+    Variable(Option<usize>),
+    /// Any other symbol, by its kind and identifier
+    Other(u8, usize),
+}
+
+impl RelabeledSymbol {
+    /// Gets the relabeled symbol for a symbol, given the labels assigned to the variables
+    fn from(symbol: SymbolRef, labels: &HashMap<usize, usize>) -> RelabeledSymbol {
+        match symbol {
+            SymbolRef::Variable(id) => RelabeledSymbol::Variable(labels.get(&id).copied()),
+            SymbolRef::Terminal(id) => RelabeledSymbol::Other(0, id),
+            SymbolRef::Virtual(id) => RelabeledSymbol::Other(1, id),
+            SymbolRef::Action(id) => RelabeledSymbol::Other(2, id),
+            SymbolRef::Epsilon => RelabeledSymbol::Other(3, 0),
+            SymbolRef::Dollar => RelabeledSymbol::Other(4, 0),
+            SymbolRef::Dummy => RelabeledSymbol::Other(5, 0),
+            SymbolRef::NullTerminal => RelabeledSymbol::Other(6, 0),
+        }
+    }
+}
+
+/// An item in an item set where the variables are relabeled
+/// (`position`, `head` and `body` symbols, `lookaheads`)
+type RelabeledItem = (usize, Vec<RelabeledSymbol>, Vec<RelabeledSymbol>);
+
+/// Gets the item set of a state where the variables are relabeled greedily
+/// The items are taken one at a time, choosing each time the one with the smallest encoding
+/// given the labels assigned so far, the variables it uses then being labeled in order.
+/// Two item sets with the same relabeling only differ by a renaming of the variables.
+/// The converse does not hold, this is not a canonical form:
+/// when items with the same encoding would label the variables differently,
+/// the first one is taken, so that two such item sets may be relabeled differently.
+fn get_relabeled_items(state: &State, grammar: &Grammar) -> Vec<RelabeledItem> {
+    let no_labels = HashMap::new();
+    let items: Vec<(usize, Vec<SymbolRef>, Vec<RelabeledSymbol>)> = state
+        .items
+        .iter()
+        .map(|item| {
+            let rule = item.rule.get_rule_in(grammar);
+            let mut symbols = vec![SymbolRef::Variable(rule.head)];
+            symbols.extend(rule.body.choices[0].elements.iter().map(|e| e.symbol));
+            let mut lookaheads: Vec<RelabeledSymbol> = item
+                .lookaheads
+                .iter()
+                .map(|lookahead| RelabeledSymbol::from(lookahead.terminal.into(), &no_labels))
+                .collect();
+            lookaheads.sort_unstable();
+            lookaheads.dedup();
+            (item.position, symbols, lookaheads)
+        })
+        .collect();
+    let encode = |index: usize, labels: &HashMap<usize, usize>| -> RelabeledItem {
+        let (position, symbols, lookaheads) = &items[index];
+        (
+            *position,
+            symbols
+                .iter()
+                .map(|symbol| RelabeledSymbol::from(*symbol, labels))
+                .collect(),
+            lookaheads.clone(),
+        )
+    };
+    let mut labels = HashMap::new();
+    let mut remaining: Vec<usize> = (0..items.len()).collect();
+    let mut result = Vec::with_capacity(items.len());
+    while let Some((rank, _)) = remaining
+        .iter()
+        .enumerate()
+        .map(|(rank, index)| (rank, encode(*index, &labels)))
+        .min_by(|(_, left), (_, right)| left.cmp(right))
+    {
+        let index = remaining.remove(rank);
+        for symbol in &items[index].1 {
+            if let SymbolRef::Variable(id) = symbol {
+                let next = labels.len();
+                labels.entry(*id).or_insert(next);
+            }
+        }
+        result.push(encode(index, &labels));
+    }
+    result
+}
+
 /// A conflict between items
 #[derive(Debug, Clone, Eq)]
 pub struct Conflict {
@@ -999,26 +2709,153 @@ pub struct Conflict {
     pub reduce_items: Vec<Item>,
     /// The terminal that poses the conflict
     pub lookahead: Lookahead,
+    /// All the terminals that collide for the involved items,
+    /// i.e. the lookaheads of the reduce items that can also be shifted (shift/reduce),
+    /// or that are shared by two reduce items (reduce/reduce).
+    /// This is empty for LR(0) conflicts, as the items have no lookahead.
+    pub overlap: TerminalSet,
     /// Example phrases for the conflict
     pub phrases: Vec<Phrase>,
+    /// The entry point variable for which the conflict occurs, if not the grammar's axiom
+    pub entry: Option<usize>,
+    /// The lexical contexts, other than the default one, in which the state may encounter the lookahead
+    pub contexts: Vec<usize>,
+}
+
+impl PartialEq for Conflict {
+    fn eq(&self, other: &Conflict) -> bool {
+        self.state == other.state
+            && self.kind == other.kind
+            && self.lookahead == other.lookahead
+            && same_items(&self.shift_items, &other.shift_items)
+            && same_items(&self.reduce_items, &other.reduce_items)
+    }
 }
 
-impl PartialEq for Conflict {
-    fn eq(&self, other: &Conflict) -> bool {
-        self.state == other.state && self.kind == other.kind && self.lookahead == other.lookahead
+/// Gets whether two sets of items have the same base items, regardless of their order
+fn same_items(left: &[Item], right: &[Item]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .all(|item| right.iter().any(|other| other.same_base(item)))
+}
+
+impl Conflict {
+    /// Gets whether this conflict involves the same items as another one,
+    /// regardless of the state in which they occur
+    #[must_use]
+    pub fn is_similar(&self, other: &Conflict) -> bool {
+        self.kind == other.kind
+            && self.lookahead == other.lookahead
+            && same_items(&self.reduce_items, &other.reduce_items)
+    }
+
+    /// Gets the names of the lexical contexts in which the lookahead may be encountered
+    #[must_use]
+    pub fn get_context_names<'g>(&self, grammar: &'g Grammar) -> Vec<&'g str> {
+        self.contexts
+            .iter()
+            .map(|&context| grammar.contexts[context].as_str())
+            .collect()
     }
 }
 
 /// A set of conflicts
 #[derive(Debug, Default, Clone)]
-pub struct Conflicts(Vec<Conflict>);
+pub struct Conflicts {
+    /// The conflicts, in the order they were raised
+    content: Vec<Conflict>,
+    /// The indices of the conflicts raised by each state
+    states: HashMap<usize, Vec<usize>>,
+}
 
 impl Conflicts {
-    /// Find a similar conflict already regsitered
-    fn find_similar(&mut self, kind: ConflictKind, lookahead: &Lookahead) -> Option<&mut Conflict> {
-        self.0
-            .iter_mut()
-            .find(|c| c.kind == kind && &c.lookahead == lookahead)
+    /// Gets whether there is no conflict
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Gets the number of conflicts
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Gets an iterator over the conflicts
+    pub fn iter(&self) -> impl Iterator<Item = &Conflict> {
+        self.content.iter()
+    }
+
+    /// Gets an iterator over the shift/reduce conflicts
+    pub fn shift_reduce(&self) -> impl Iterator<Item = &Conflict> {
+        self.content
+            .iter()
+            .filter(|conflict| conflict.kind == ConflictKind::ShiftReduce)
+    }
+
+    /// Gets an iterator over the reduce/reduce conflicts
+    pub fn reduce_reduce(&self) -> impl Iterator<Item = &Conflict> {
+        self.content
+            .iter()
+            .filter(|conflict| conflict.kind == ConflictKind::ReduceReduce)
+    }
+
+    /// Gets the number of conflicts of a kind
+    #[must_use]
+    pub fn count_of_kind(&self, kind: ConflictKind) -> usize {
+        self.content
+            .iter()
+            .filter(|conflict| conflict.kind == kind)
+            .count()
+    }
+
+    /// Groups the conflicts by the state that raises them
+    #[must_use]
+    pub fn by_state(&self) -> HashMap<usize, Vec<&Conflict>> {
+        let mut result: HashMap<usize, Vec<&Conflict>> = HashMap::new();
+        for conflict in &self.content {
+            result.entry(conflict.state).or_default().push(conflict);
+        }
+        result
+    }
+
+    /// Gets an iterator over the conflicts raised by a state
+    pub fn in_state(&self, state_id: usize) -> impl Iterator<Item = &Conflict> {
+        self.states
+            .get(&state_id)
+            .into_iter()
+            .flatten()
+            .map(|index| &self.content[*index])
+    }
+
+    /// Gets whether a state raises at least one conflict
+    #[must_use]
+    pub fn has_conflict_in(&self, state_id: usize) -> bool {
+        self.states.contains_key(&state_id)
+    }
+
+    /// Registers a new conflict
+    fn push(&mut self, conflict: Conflict) {
+        self.states
+            .entry(conflict.state)
+            .or_default()
+            .push(self.content.len());
+        self.content.push(conflict);
+    }
+
+    /// Find a conflict already registered in the same state for the same kind and lookahead
+    fn find_similar(
+        &mut self,
+        state_id: usize,
+        kind: ConflictKind,
+        lookahead: &Lookahead,
+    ) -> Option<&mut Conflict> {
+        let index = *self.states.get(&state_id)?.iter().find(|index| {
+            let conflict = &self.content[**index];
+            conflict.kind == kind && &conflict.lookahead == lookahead
+        })?;
+        Some(&mut self.content[index])
     }
 
     /// Raise a shift/reduce conflict
@@ -1031,29 +2868,28 @@ impl Conflicts {
         lookahead: Lookahead,
     ) {
         // look for previous conflict
-        if let Some(previous) = self.find_similar(ConflictKind::ShiftReduce, &lookahead) {
-            previous.reduce_items.push(reducing);
+        if let Some(previous) = self.find_similar(state_id, ConflictKind::ShiftReduce, &lookahead) {
+            push_item(&mut previous.reduce_items, reducing);
             return;
         }
         // No previous conflict was found
         let next_symbol = Some(lookahead.terminal.into());
         let mut shift_items = Vec::new();
         for item in &state.items {
-            if item.get_next_symbol(grammar) == next_symbol
-                && shift_items
-                    .iter()
-                    .all(|candidate: &Item| !candidate.same_base(item))
-            {
-                shift_items.push(item.clone());
+            if item.get_next_symbol(grammar) == next_symbol {
+                push_item(&mut shift_items, item.clone());
             }
         }
-        self.0.push(Conflict {
+        self.push(Conflict {
             state: state_id,
             kind: ConflictKind::ShiftReduce,
             shift_items,
             reduce_items: vec![reducing],
             lookahead,
+            overlap: TerminalSet::default(),
             phrases: Vec::new(),
+            entry: None,
+            contexts: Vec::new(),
         });
     }
 
@@ -1066,42 +2902,113 @@ impl Conflicts {
         lookahead: Lookahead,
     ) {
         // look for previous conflict
-        if let Some(previous) = self.find_similar(ConflictKind::ReduceReduce, &lookahead) {
-            previous.reduce_items.push(reducing);
+        if let Some(conflict) = self.find_similar(state_id, ConflictKind::ReduceReduce, &lookahead)
+        {
+            push_item(&mut conflict.reduce_items, previous);
+            push_item(&mut conflict.reduce_items, reducing);
             return;
         }
         // No previous conflict was found
-        self.0.push(Conflict {
+        self.push(Conflict {
             state: state_id,
             kind: ConflictKind::ReduceReduce,
             shift_items: Vec::new(),
             reduce_items: vec![previous, reducing],
             lookahead,
+            overlap: TerminalSet::default(),
             phrases: Vec::new(),
+            entry: None,
+            contexts: Vec::new(),
         });
     }
 
+    /// Computes the overlap of the conflicts raised in a state
+    /// The lookaheads of a reduce item are gathered from all the state's items with the same base,
+    /// as LR(1) items only have a single lookahead each.
+    fn compute_overlaps(&mut self, state: &State) {
+        for conflict in &mut self.content {
+            let lookaheads: Vec<TerminalSet> = conflict
+                .reduce_items
+                .iter()
+                .map(|reducing| {
+                    let mut result = TerminalSet::default();
+                    for item in state.items.iter().filter(|item| item.same_base(reducing)) {
+                        for lookahead in &item.lookaheads {
+                            result.add(lookahead.terminal);
+                        }
+                    }
+                    result
+                })
+                .collect();
+            conflict.overlap.clear();
+            match conflict.kind {
+                ConflictKind::ShiftReduce => {
+                    // the lookaheads of the reduce items that can also be shifted
+                    for terminals in &lookaheads {
+                        for terminal in terminals {
+                            if state.children.contains_key(&(*terminal).into()) {
+                                conflict.overlap.add(*terminal);
+                            }
+                        }
+                    }
+                }
+                ConflictKind::ReduceReduce => {
+                    // the lookaheads shared by at least two reduce items
+                    for (index, terminals) in lookaheads.iter().enumerate() {
+                        for other in &lookaheads[(index + 1)..] {
+                            conflict.overlap.union_with(&terminals.intersect(other));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     /// Aggregate other conflicts into this collection
     pub fn aggregate(&mut self, other: Conflicts) {
-        for conflict in other.0 {
-            if let Some(previous) = self.find_similar(conflict.kind, &conflict.lookahead) {
+        for conflict in other.content {
+            if let Some(previous) =
+                self.find_similar(conflict.state, conflict.kind, &conflict.lookahead)
+            {
+                previous.overlap.union_with(&conflict.overlap);
                 for item in conflict.shift_items {
-                    if previous.shift_items.iter().all(|i| i != &item) {
-                        previous.shift_items.push(item);
-                    }
+                    push_item(&mut previous.shift_items, item);
                 }
                 for item in conflict.reduce_items {
-                    if previous.reduce_items.iter().all(|i| i != &item) {
-                        previous.reduce_items.push(item);
-                    }
+                    push_item(&mut previous.reduce_items, item);
                 }
             } else {
-                self.0.push(conflict);
+                self.push(conflict);
             }
         }
     }
 }
 
+impl IntoIterator for Conflicts {
+    type Item = Conflict;
+    type IntoIter = std::vec::IntoIter<Conflict>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.content.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Conflicts {
+    type Item = &'a Conflict;
+    type IntoIter = std::slice::Iter<'a, Conflict>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.content.iter()
+    }
+}
+
+/// Adds an item to those of a conflict, unless an item with the same base is already present
+fn push_item(items: &mut Vec<Item>, item: Item) {
+    if items.iter().all(|candidate| !candidate.same_base(&item)) {
+        items.push(item);
+    }
+}
+
 /// Represents an error where a contextual terminal is expected but its context cannot be available at this point
 #[derive(Debug, Clone, Eq)]
 pub struct ContextError {
@@ -1123,69 +3030,97 @@ impl PartialEq for ContextError {
     }
 }
 
-/// Gets the LR(0) graph
-fn get_graph_lr0(grammar: &Grammar) -> Graph {
+/// Gets the initial states for the specified generated axioms
+fn get_initial_states(
+    grammar: &Grammar,
+    axioms: &[usize],
+    mode: LookaheadMode,
+    mut profile: Option<&mut GraphBuildProfile>,
+) -> Vec<State> {
+    axioms
+        .iter()
+        .map(|&axiom| {
+            // the end of input follows the axiom, as for the initial LALR(1) kernels
+            let lookaheads = match mode {
+                LookaheadMode::LR0 => Lookaheads::default(),
+                _ => Lookaheads::from_single(Lookahead::from(TerminalRef::Epsilon)),
+            };
+            let item = Item {
+                rule: RuleRef::new(axiom, 0),
+                position: 0,
+                lookaheads,
+            };
+            let kernel = StateKernel { items: vec![item] };
+            timed(
+                profile.as_deref_mut().map(|p| &mut p.closure_time_ns),
+                || kernel.into_state(grammar, mode),
+            )
+        })
+        .collect()
+}
+
+/// Gets the LR(0) graph starting from the specified generated axioms
+/// The partial graph is returned as an error when it has more than `max_states` states
+fn get_graph_lr0(
+    grammar: &Grammar,
+    axioms: &[usize],
+    mut profile: Option<&mut GraphBuildProfile>,
+    max_states: usize,
+) -> Result<Graph, Graph> {
     // Create the base LR(0) graph
-    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap();
-    let item = Item {
-        rule: RuleRef::new(axiom.id, 0),
-        position: 0,
-        lookaheads: Lookaheads::default(),
-    };
-    let kernel = StateKernel { items: vec![item] };
-    let state0 = kernel.into_state(grammar, LookaheadMode::LR0);
-    Graph::from(state0, grammar, LookaheadMode::LR0)
+    let states = get_initial_states(grammar, axioms, LookaheadMode::LR0, profile.as_deref_mut());
+    Graph::build_from_states(states, grammar, LookaheadMode::LR0, profile, max_states)
 }
 
 /// Builds a LR(0) graph
 #[must_use]
-pub fn build_graph_lr0(grammar: &Grammar) -> (Graph, Conflicts) {
-    let mut graph = get_graph_lr0(grammar);
+pub fn build_graph_lr0(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_lr0(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
     let conflicts = graph.build_reductions_lr0(grammar);
     (graph, conflicts)
 }
 
-/// Gets the LR(1) graph
-fn get_graph_lr1(grammar: &Grammar) -> Graph {
-    // Create the base LR(0) graph
-    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap();
-    let item = Item {
-        rule: RuleRef::new(axiom.id, 0),
-        position: 0,
-        lookaheads: Lookaheads::default(),
-    };
-    let kernel = StateKernel { items: vec![item] };
-    let state0 = kernel.into_state(grammar, LookaheadMode::LR1);
-    Graph::from(state0, grammar, LookaheadMode::LR1)
+/// Gets the LR(1) graph starting from the specified generated axioms
+/// The partial graph is returned as an error when it has more than `max_states` states
+fn get_graph_lr1(
+    grammar: &Grammar,
+    axioms: &[usize],
+    mut profile: Option<&mut GraphBuildProfile>,
+    max_states: usize,
+) -> Result<Graph, Graph> {
+    let states = get_initial_states(grammar, axioms, LookaheadMode::LR1, profile.as_deref_mut());
+    Graph::build_from_states(states, grammar, LookaheadMode::LR1, profile, max_states)
 }
 
 /// Builds a LR(1) graph
 #[must_use]
-pub fn build_graph_lr1(grammar: &Grammar) -> (Graph, Conflicts) {
-    let mut graph = get_graph_lr1(grammar);
+pub fn build_graph_lr1(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_lr1(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
     let conflicts = graph.build_reductions_lr1(grammar);
     (graph, conflicts)
 }
 
 /// Builds a RNGLR(1) graph
 #[must_use]
-pub fn build_graph_rnglr1(grammar: &Grammar) -> (Graph, Conflicts) {
-    let mut graph = get_graph_lr1(grammar);
+pub fn build_graph_rnglr1(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_lr1(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
     let conflicts = graph.build_reductions_rnglr1(grammar);
     (graph, conflicts)
 }
 
 /// Builds the kernels for a LALR(1) graph
-fn build_graph_lalr1_kernels(graph0: &Graph) -> Vec<StateKernel> {
+fn build_graph_lalr1_kernels(graph0: &Graph, initials: usize) -> Vec<StateKernel> {
     // copy kernel without the lookaheads
     let mut kernels: Vec<StateKernel> = graph0
         .states
         .iter()
         .map(|state| state.kernel.clone())
         .collect();
-    // set epsilon as lookahead on all items in kernel 0
-    for item in &mut kernels[0].items {
-        item.lookaheads.add(Lookahead::from(TerminalRef::Epsilon));
+    // set epsilon as lookahead on all items in the initial kernels
+    for kernel in &mut kernels[..initials] {
+        for item in &mut kernel.items {
+            item.lookaheads.add(&Lookahead::from(TerminalRef::Epsilon));
+        }
     }
     kernels
 }
@@ -1199,13 +3134,24 @@ struct Propagation {
     to_item: usize,
 }
 
-/// Builds the propagation table for a LALR(1) graph
+/// Lookaheads spontaneously generated by a state for an item in the kernel of one of its children
+#[derive(Debug, Clone)]
+struct Generation {
+    from_state: usize,
+    to_state: usize,
+    to_item: usize,
+    lookaheads: Lookaheads,
+}
+
+/// Builds the propagation table for a LALR(1) graph, with the spontaneously generated lookaheads
 fn build_graph_lalr1_propagation_table(
     graph0: &Graph,
     grammar: &Grammar,
-    kernels: &mut [StateKernel],
-) -> Vec<Propagation> {
+    kernels: &[StateKernel],
+    mut profile: Option<&mut GraphBuildProfile>,
+) -> (Vec<Propagation>, Vec<Generation>) {
     let mut propagation = Vec::new();
+    let mut generations = Vec::new();
     for i in 0..kernels.len() {
         // For each LALR(1) item in the kernel
         // Only the kernel needs to be examined as the other items will be discovered and treated
@@ -1220,16 +3166,17 @@ fn build_graph_lalr1_propagation_table(
             // Item here is of the form [A -> alpha . beta]
             // Create the corresponding dummy item : [A -> alpha . beta, dummy]
             // This item is used to detect lookahead propagation
-            let dummy_state = StateKernel {
-                items: vec![Item {
-                    rule: kernels[i].items[item_id].rule,
-                    position: kernels[i].items[item_id].position,
-                    lookaheads: Lookaheads::from_single(Lookahead::from(TerminalRef::Dummy)),
-                }],
-            }
-            .into_state(grammar, LookaheadMode::LR1);
+            let dummy_item = Item {
+                rule: kernels[i].items[item_id].rule,
+                position: kernels[i].items[item_id].position,
+                lookaheads: Lookaheads::from_single(Lookahead::from(TerminalRef::Dummy)),
+            };
+            let dummy_items = timed(
+                profile.as_deref_mut().map(|p| &mut p.closure_time_ns),
+                || closure(vec![dummy_item], grammar, LookaheadMode::LR1),
+            );
             // For each item in the closure of the dummy item
-            for dummy_item in &dummy_state.items {
+            for dummy_item in &dummy_items {
                 if let Some(next_symbol) = dummy_item.get_next_symbol(grammar) {
                     // not a reduction
                     let dummy_child = dummy_item.get_child();
@@ -1251,49 +3198,86 @@ fn build_graph_lalr1_propagation_table(
                         });
                     } else {
                         // => Spontaneous generation of lookaheads
-                        kernels[child_state].items[child_item]
-                            .lookaheads
-                            .add_others(&dummy_item.lookaheads);
+                        generations.push(Generation {
+                            from_state: i,
+                            to_state: child_state,
+                            to_item: child_item,
+                            lookaheads: dummy_item.lookaheads.clone(),
+                        });
                     }
                 }
             }
         }
     }
-    propagation
+    (propagation, generations)
+}
+
+/// Adds the spontaneously generated lookaheads to the kernels of a LALR(1) graph
+fn build_graph_lalr1_generate(kernels: &mut [StateKernel], generations: &[Generation]) {
+    for generation in generations {
+        kernels[generation.to_state].items[generation.to_item]
+            .lookaheads
+            .add_others(&generation.lookaheads);
+    }
+}
+
+/// Gets the source and target items of a propagation,
+/// the target is `None` when the propagation is from an item to itself
+fn get_propagation_ends<'k>(
+    kernels: &'k mut [StateKernel],
+    propagation: &Propagation,
+) -> (&'k Item, Option<&'k mut Item>) {
+    let (from_state, from_item) = (propagation.from_state, propagation.from_item);
+    let (to_state, to_item) = (propagation.to_state, propagation.to_item);
+    if from_state != to_state {
+        let (left, right) = kernels.split_at_mut(from_state.max(to_state));
+        let (from, to) = if from_state < to_state {
+            (&left[from_state], &mut right[0])
+        } else {
+            (&right[0], &mut left[to_state])
+        };
+        return (&from.items[from_item], Some(&mut to.items[to_item]));
+    }
+    let items = &mut kernels[from_state].items;
+    if from_item == to_item {
+        return (&items[from_item], None);
+    }
+    let (left, right) = items.split_at_mut(from_item.max(to_item));
+    if from_item < to_item {
+        (&left[from_item], Some(&mut right[0]))
+    } else {
+        (&right[0], Some(&mut left[to_item]))
+    }
 }
 
 /// Executes the propagation for a LALR(1) graph
 fn build_graph_lalr1_propagate(kernels: &mut [StateKernel], table: &[Propagation]) {
-    let mut modifications = 1;
-    while modifications != 0 {
-        modifications = 0;
+    let mut modified = true;
+    while modified {
+        modified = false;
         for propagation in table {
-            let before = kernels[propagation.to_state].items[propagation.to_item]
-                .lookaheads
-                .0
-                .len();
-            let others = kernels[propagation.from_state].items[propagation.from_item]
-                .lookaheads
-                .clone();
-            kernels[propagation.to_state].items[propagation.to_item]
-                .lookaheads
-                .add_others(&others);
-            let after = kernels[propagation.to_state].items[propagation.to_item]
-                .lookaheads
-                .0
-                .len();
-            modifications += after - before;
+            let (from, to) = get_propagation_ends(kernels, propagation);
+            if let Some(to) = to {
+                modified |= to.lookaheads.add_others(&from.lookaheads);
+            }
         }
     }
 }
 
 /// Builds the complete LALR(1) graph
-fn build_graph_lalr1_graph(kernels: Vec<StateKernel>, graph0: &Graph, grammar: &Grammar) -> Graph {
+fn build_graph_lalr1_graph(
+    kernels: Vec<StateKernel>,
+    graph0: &Graph,
+    grammar: &Grammar,
+    profile: Option<&mut GraphBuildProfile>,
+) -> Graph {
     // Build states
-    let mut states: Vec<State> = kernels
-        .into_iter()
-        .map(|kernel| kernel.into_state(grammar, LookaheadMode::LALR1))
-        .collect();
+    let mut states: Vec<State> = timed(profile.map(|p| &mut p.closure_time_ns), || {
+        kernels
+            .into_iter()
+            .map(|kernel| kernel.into_state(grammar, LookaheadMode::LALR1))
+            .collect()
+    });
     // Link for each LALR(1) set
     for (state0, state1) in graph0.states.iter().zip(states.iter_mut()) {
         state1.children = state0.children.clone();
@@ -1302,36 +3286,503 @@ fn build_graph_lalr1_graph(kernels: Vec<StateKernel>, graph0: &Graph, grammar: &
     Graph { states }
 }
 
-/// Gets the LALR(1) graph
-fn get_graph_lalr1(grammar: &Grammar) -> Graph {
-    let graph0 = get_graph_lr0(grammar);
-    let mut kernels = build_graph_lalr1_kernels(&graph0);
-    let propagation = build_graph_lalr1_propagation_table(&graph0, grammar, &mut kernels);
+/// Gets the LALR(1) graph starting from the specified generated axioms
+/// The partial graph is returned as an error when it has more than `max_states` states
+fn get_graph_lalr1(
+    grammar: &Grammar,
+    axioms: &[usize],
+    mut profile: Option<&mut GraphBuildProfile>,
+    max_states: usize,
+) -> Result<Graph, Graph> {
+    let graph0 = get_graph_lr0(grammar, axioms, profile.as_deref_mut(), max_states)?;
+    let mut kernels = build_graph_lalr1_kernels(&graph0, axioms.len());
+    let (propagation, generations) =
+        build_graph_lalr1_propagation_table(&graph0, grammar, &kernels, profile.as_deref_mut());
+    build_graph_lalr1_generate(&mut kernels, &generations);
     build_graph_lalr1_propagate(&mut kernels, &propagation);
-    build_graph_lalr1_graph(kernels, &graph0, grammar)
+    Ok(build_graph_lalr1_graph(kernels, &graph0, grammar, profile))
 }
 
 /// Builds a LALR(1) graph
 #[must_use]
-pub fn build_graph_lalr1(grammar: &Grammar) -> (Graph, Conflicts) {
-    let mut graph = get_graph_lalr1(grammar);
+pub fn build_graph_lalr1(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_lalr1(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
     let conflicts = graph.build_reductions_lr1(grammar);
     (graph, conflicts)
 }
 
 /// Builds a RNGLALR(1) graph
 #[must_use]
-pub fn build_graph_rnglalr1(grammar: &Grammar) -> (Graph, Conflicts) {
-    let mut graph = get_graph_lalr1(grammar);
+pub fn build_graph_rnglalr1(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_lalr1(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
     let conflicts = graph.build_reductions_rnglr1(grammar);
     (graph, conflicts)
 }
 
+/// The actions of a state on the terminals it reduces on,
+/// a shift being represented by `None` and a reduction by its rule
+type StateActions = HashMap<TerminalRef, Vec<Option<RuleRef>>>;
+
+/// Gets the actions of a state on the terminals it reduces on
+fn get_ielr1_actions(state: &State, grammar: &Grammar) -> StateActions {
+    let mut actions = StateActions::new();
+    for item in &state.items {
+        if item.get_action(grammar) != LR_ACTION_CODE_REDUCE {
+            continue;
+        }
+        for lookahead in &item.lookaheads {
+            let entry = actions.entry(lookahead.terminal).or_default();
+            if entry.is_empty() && state.children.contains_key(&lookahead.terminal.into()) {
+                entry.push(None);
+            }
+            if !entry.contains(&Some(item.rule)) {
+                entry.push(Some(item.rule));
+            }
+        }
+    }
+    actions
+}
+
+/// Gets whether a state can be merged with others that have the same core
+/// A merge is possible when it raises no conflict other than the ones already raised by the merged states.
+fn can_merge_ielr1(members: &[usize], candidate: usize, actions: &[StateActions]) -> bool {
+    let states = || members.iter().copied().chain(Some(candidate));
+    let mut merged = StateActions::new();
+    for state in states() {
+        for (terminal, state_actions) in &actions[state] {
+            let entry = merged.entry(*terminal).or_default();
+            for action in state_actions {
+                if !entry.contains(action) {
+                    entry.push(*action);
+                }
+            }
+        }
+    }
+    merged.iter().all(|(terminal, merged_actions)| {
+        merged_actions.len() <= 1
+            || states().any(|state| {
+                actions[state]
+                    .get(terminal)
+                    .is_some_and(|own| own.len() == merged_actions.len())
+            })
+    })
+}
+
+/// Splits the blocks of states so that merging the states of a block raises no new conflict
+/// Returns the new block of each state.
+fn split_ielr1_inadequate_blocks(blocks: &[usize], actions: &[StateActions]) -> Vec<usize> {
+    // the members of the new blocks and, for each old block, the new ones it is split into
+    let mut members: Vec<Vec<usize>> = Vec::new();
+    let mut splits: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut result = vec![0; blocks.len()];
+    for (state, block) in blocks.iter().enumerate() {
+        let candidates = splits.entry(*block).or_default();
+        let target = candidates
+            .iter()
+            .copied()
+            .find(|&candidate| can_merge_ielr1(&members[candidate], state, actions));
+        let target = target.unwrap_or_else(|| {
+            members.push(Vec::new());
+            candidates.push(members.len() - 1);
+            members.len() - 1
+        });
+        members[target].push(state);
+        result[state] = target;
+    }
+    result
+}
+
+/// Splits the blocks of states so that the states of a block have transitions to the same blocks
+/// Returns the new block of each state.
+fn split_ielr1_transitions(graph: &Graph, blocks: &[usize]) -> Vec<usize> {
+    let mut signatures: HashMap<(usize, Vec<(SymbolRef, usize)>), usize> = HashMap::new();
+    graph
+        .states
+        .iter()
+        .zip(blocks)
+        .map(|(state, block)| {
+            let mut children: Vec<(SymbolRef, usize)> = state
+                .children
+                .iter()
+                .map(|(symbol, child)| (*symbol, blocks[*child]))
+                .collect();
+            children.sort_unstable();
+            let next = signatures.len();
+            *signatures.entry((*block, children)).or_insert(next)
+        })
+        .collect()
+}
+
+/// The lookaheads given by a LR(0) state to the kernel items of one of its children
+#[derive(Debug, Default)]
+struct TransitionLookaheads<'g> {
+    /// The propagations from the kernel items of the parent, as (parent item, child item)
+    propagated: Vec<(usize, usize)>,
+    /// The lookaheads generated by the parent
+    generated: Vec<&'g Generation>,
+}
+
+/// The lookaheads given along the transitions of a LR(0) graph, by parent and child state
+type Transitions<'g> = HashMap<(usize, usize), TransitionLookaheads<'g>>;
+
+/// Indexes the propagations and generations of lookaheads by parent and child state
+fn get_ielr1_transitions<'g>(
+    propagation: &[Propagation],
+    generations: &'g [Generation],
+) -> Transitions<'g> {
+    let mut transitions = Transitions::new();
+    for edge in propagation {
+        transitions
+            .entry((edge.from_state, edge.to_state))
+            .or_default()
+            .propagated
+            .push((edge.from_item, edge.to_item));
+    }
+    for generation in generations {
+        transitions
+            .entry((generation.from_state, generation.to_state))
+            .or_default()
+            .generated
+            .push(generation);
+    }
+    transitions
+}
+
+/// Gets, for each kernel item of the LR(0) states, the lookaheads that contribute to a conflict
+/// A lookahead contributes to the conflicts of an inadequate LALR(1) state on it.
+/// All the kernel items of the state are concerned, as they give their lookaheads to the closure items.
+/// A lookahead also contributes when the item propagates it to another item it contributes for.
+fn get_ielr1_contributions(
+    graph0: &Graph,
+    propagation: &[Propagation],
+    conflicts: &Conflicts,
+) -> Vec<Vec<TerminalSet>> {
+    let mut contributions: Vec<Vec<TerminalSet>> = graph0
+        .states
+        .iter()
+        .map(|state| vec![TerminalSet::default(); state.kernel.items.len()])
+        .collect();
+    for conflict in conflicts {
+        for item in &mut contributions[conflict.state] {
+            item.add(conflict.lookahead.terminal);
+        }
+    }
+    let mut modified = true;
+    while modified {
+        modified = false;
+        for edge in propagation {
+            let terminals = contributions[edge.to_state][edge.to_item].clone();
+            modified |= contributions[edge.from_state][edge.from_item].union_with(&terminals);
+        }
+    }
+    contributions
+}
+
+/// Splits the isocores of the LR(0) graph, i.e. the states with the same kernel items,
+/// whose lookaheads that contribute to conflicts differ
+/// The lookaheads are generated by the parent of a state or propagated from it,
+/// so that the split states are built from the initial ones as for a LR(1) graph,
+/// but only the contributing lookaheads are kept.
+/// Returns the LR(0) state and the children of each split state, the initial states coming first.
+fn split_ielr1_isocores(
+    graph0: &Graph,
+    initials: &[StateKernel],
+    transitions: &Transitions,
+    contributions: &[Vec<TerminalSet>],
+) -> Vec<(usize, HashMap<SymbolRef, usize>)> {
+    let contributing = |core: usize, item: usize, lookaheads: &Lookaheads| TerminalSet {
+        content: lookaheads
+            .iter_sorted()
+            .map(|lookahead| lookahead.terminal)
+            .filter(|terminal| contributions[core][item].contains(*terminal))
+            .collect(),
+    };
+    let mut keys: Vec<(usize, Vec<TerminalSet>)> = initials
+        .iter()
+        .enumerate()
+        .map(|(core, kernel)| {
+            let lookaheads = kernel
+                .items
+                .iter()
+                .enumerate()
+                .map(|(item, kernel_item)| contributing(core, item, &kernel_item.lookaheads))
+                .collect();
+            (core, lookaheads)
+        })
+        .collect();
+    let mut index: HashMap<(usize, Vec<TerminalSet>), usize> = keys
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(split, key)| (key, split))
+        .collect();
+    let mut result = Vec::new();
+    while result.len() < keys.len() {
+        let (core, lookaheads) = keys[result.len()].clone();
+        let mut children = HashMap::new();
+        for (symbol, child) in &graph0.states[core].children {
+            let mut child_lookaheads =
+                vec![TerminalSet::default(); graph0.states[*child].kernel.items.len()];
+            if let Some(transition) = transitions.get(&(core, *child)) {
+                for generation in &transition.generated {
+                    let item = generation.to_item;
+                    child_lookaheads[item].union_with(&contributing(
+                        *child,
+                        item,
+                        &generation.lookaheads,
+                    ));
+                }
+                for (from, to) in &transition.propagated {
+                    child_lookaheads[*to]
+                        .union_with(&lookaheads[*from].intersect(&contributions[*child][*to]));
+                }
+            }
+            let key = (*child, child_lookaheads);
+            let target = *index.entry(key.clone()).or_insert_with(|| {
+                keys.push(key);
+                keys.len() - 1
+            });
+            children.insert(*symbol, target);
+        }
+        result.push((core, children));
+    }
+    result
+}
+
+/// Builds the graph of the split states, with their complete lookaheads
+/// The lookaheads are generated and propagated as for a LALR(1) graph,
+/// but along the transitions between the split states.
+fn build_ielr1_split_graph(
+    graph0: &Graph,
+    split: &[(usize, HashMap<SymbolRef, usize>)],
+    initials: &[StateKernel],
+    transitions: &Transitions,
+    grammar: &Grammar,
+    profile: Option<&mut GraphBuildProfile>,
+) -> Graph {
+    let mut kernels: Vec<StateKernel> = split
+        .iter()
+        .map(|(core, _)| {
+            initials
+                .get(*core)
+                .unwrap_or(&graph0.states[*core].kernel)
+                .clone()
+        })
+        .collect();
+    let mut table = Vec::new();
+    for (from_state, (core, children)) in split.iter().enumerate() {
+        for (symbol, to_state) in children {
+            let child = graph0.states[*core].children[symbol];
+            let Some(transition) = transitions.get(&(*core, child)) else {
+                continue;
+            };
+            for generation in &transition.generated {
+                kernels[*to_state].items[generation.to_item]
+                    .lookaheads
+                    .add_others(&generation.lookaheads);
+            }
+            for (from_item, to_item) in &transition.propagated {
+                table.push(Propagation {
+                    from_state,
+                    from_item: *from_item,
+                    to_state: *to_state,
+                    to_item: *to_item,
+                });
+            }
+        }
+    }
+    build_graph_lalr1_propagate(&mut kernels, &table);
+    let mut states: Vec<State> = timed(profile.map(|p| &mut p.closure_time_ns), || {
+        kernels
+            .into_iter()
+            .map(|kernel| kernel.into_state(grammar, LookaheadMode::LALR1))
+            .collect()
+    });
+    for (state, (core, children)) in states.iter_mut().zip(split) {
+        state.children.clone_from(children);
+        state
+            .opening_contexts
+            .clone_from(&graph0.states[*core].opening_contexts);
+    }
+    Graph { states }
+}
+
+/// Merges back the split states of the same isocore whose merge raises no new conflict
+/// The blocks of merged states are refined so that the transitions remain deterministic.
+fn merge_ielr1_compatible_states(
+    split: &Graph,
+    cores: Vec<usize>,
+    grammar: &Grammar,
+    profile: Option<&mut GraphBuildProfile>,
+) -> Graph {
+    let actions: Vec<StateActions> = split
+        .states
+        .iter()
+        .map(|state| get_ielr1_actions(state, grammar))
+        .collect();
+    let mut blocks = cores;
+    let mut count = 0;
+    loop {
+        blocks = split_ielr1_inadequate_blocks(&blocks, &actions);
+        blocks = split_ielr1_transitions(split, &blocks);
+        let new_count = blocks.iter().max().map_or(0, |max| max + 1);
+        if new_count == count {
+            break;
+        }
+        count = new_count;
+    }
+    // merge the states of each block, the initial states keep their index
+    let mut kernels = vec![StateKernel::default(); count];
+    let mut representatives = vec![None; count];
+    for (index, (state, block)) in split.states.iter().zip(&blocks).enumerate() {
+        representatives[*block].get_or_insert(index);
+        let kernel = &mut kernels[*block];
+        for item in &state.kernel.items {
+            if let Some(existing) = kernel.items.iter_mut().find(|i| i.same_base(item)) {
+                existing.lookaheads.add_others(&item.lookaheads);
+            } else {
+                kernel.add_item(item.clone());
+            }
+        }
+    }
+    let mut states: Vec<State> = timed(profile.map(|p| &mut p.closure_time_ns), || {
+        kernels
+            .into_iter()
+            .map(|kernel| kernel.into_state(grammar, LookaheadMode::LALR1))
+            .collect()
+    });
+    for (state, representative) in states.iter_mut().zip(representatives) {
+        let representative = &split.states[representative.unwrap()];
+        state.children = representative
+            .children
+            .iter()
+            .map(|(symbol, child)| (*symbol, blocks[*child]))
+            .collect();
+        state
+            .opening_contexts
+            .clone_from(&representative.opening_contexts);
+    }
+    Graph { states }
+}
+
+/// Gets the IELR(1) graph starting from the specified generated axioms
+/// The LALR(1) graph is built first. When it has inadequate states, i.e. states with conflicts,
+/// the lookaheads that contribute to these conflicts are traced back along the propagations of the LALR(1) construction.
+/// The isocores whose contributing lookaheads differ are split, as in the LR(1) graph,
+/// so that the split states have the conflicts of the LR(1) graph. The others are kept merged.
+/// The split states that can be merged back without raising a new conflict are finally merged.
+/// The graph is returned as an error when it has more than `max_states` states.
+fn get_graph_ielr1(
+    grammar: &Grammar,
+    axioms: &[usize],
+    mut profile: Option<&mut GraphBuildProfile>,
+    max_states: usize,
+) -> Result<Graph, Graph> {
+    let graph0 = get_graph_lr0(grammar, axioms, profile.as_deref_mut(), max_states)?;
+    let mut kernels = build_graph_lalr1_kernels(&graph0, axioms.len());
+    let (propagation, generations) =
+        build_graph_lalr1_propagation_table(&graph0, grammar, &kernels, profile.as_deref_mut());
+    let initials = kernels[..axioms.len()].to_vec();
+    build_graph_lalr1_generate(&mut kernels, &generations);
+    build_graph_lalr1_propagate(&mut kernels, &propagation);
+    let lalr1 = build_graph_lalr1_graph(kernels, &graph0, grammar, profile.as_deref_mut());
+    let conflicts = lalr1.clone().build_reductions_lr1(grammar);
+    let graph = if conflicts.is_empty() {
+        // no state is inadequate
+        lalr1
+    } else {
+        let contributions = get_ielr1_contributions(&graph0, &propagation, &conflicts);
+        let transitions = get_ielr1_transitions(&propagation, &generations);
+        let split = split_ielr1_isocores(&graph0, &initials, &transitions, &contributions);
+        let cores = split.iter().map(|(core, _)| *core).collect();
+        let split = build_ielr1_split_graph(
+            &graph0,
+            &split,
+            &initials,
+            &transitions,
+            grammar,
+            profile.as_deref_mut(),
+        );
+        merge_ielr1_compatible_states(&split, cores, grammar, profile)
+    };
+    if graph.states.len() > max_states {
+        return Err(graph);
+    }
+    Ok(graph)
+}
+
+/// Builds an IELR(1) graph
+/// The graph has the states of the LALR(1) graph, except for the states that raise conflicts
+/// only because of the LALR(1) merge, which are split as in the LR(1) graph.
+#[must_use]
+pub fn build_graph_ielr1(grammar: &Grammar, axiom: usize) -> (Graph, Conflicts) {
+    let mut graph = get_graph_ielr1(grammar, &[axiom], None, usize::MAX).unwrap_or_default();
+    let conflicts = graph.build_reductions_lr1(grammar);
+    (graph, conflicts)
+}
+
+/// The time spent in the phases of the construction of a LR graph
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GraphBuildProfile {
+    /// The time spent on computing the closures of the states, in nanoseconds
+    pub closure_time_ns: u64,
+    /// The time spent on looking for existing states with the same kernel, in nanoseconds
+    pub dedup_time_ns: u64,
+    /// The time spent on building the reductions, in nanoseconds
+    pub reduction_time_ns: u64,
+    /// The number of states in the graph
+    pub state_count: usize,
+}
+
+/// Executes an action, adding the time it took to the counter, if any
+fn timed<T>(counter: Option<&mut u64>, action: impl FnOnce() -> T) -> T {
+    let Some(counter) = counter else {
+        return action();
+    };
+    let start = Instant::now();
+    let result = action();
+    let elapsed = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+    *counter = counter.saturating_add(elapsed);
+    result
+}
+
+/// Builds the graph for a parsing method, recording the time spent in each phase of the construction
+#[must_use]
+pub fn build_graph_profiled(
+    grammar: &Grammar,
+    axiom: usize,
+    method: ParsingMethod,
+) -> (Graph, Conflicts, GraphBuildProfile) {
+    let mut profile = GraphBuildProfile::default();
+    let mut graph = match method {
+        ParsingMethod::LR0 => get_graph_lr0(grammar, &[axiom], Some(&mut profile), usize::MAX),
+        ParsingMethod::LR1 | ParsingMethod::RNGLR1 => {
+            get_graph_lr1(grammar, &[axiom], Some(&mut profile), usize::MAX)
+        }
+        ParsingMethod::LALR1 | ParsingMethod::RNGLALR1 => {
+            get_graph_lalr1(grammar, &[axiom], Some(&mut profile), usize::MAX)
+        }
+        ParsingMethod::IELR1 => get_graph_ielr1(grammar, &[axiom], Some(&mut profile), usize::MAX),
+    }
+    .unwrap_or_default();
+    let conflicts = timed(Some(&mut profile.reduction_time_ns), || match method {
+        ParsingMethod::LR0 => graph.build_reductions_lr0(grammar),
+        ParsingMethod::LR1 | ParsingMethod::LALR1 | ParsingMethod::IELR1 => {
+            graph.build_reductions_lr1(grammar)
+        }
+        ParsingMethod::RNGLR1 | ParsingMethod::RNGLALR1 => graph.build_reductions_rnglr1(grammar),
+    });
+    profile.state_count = graph.states.len();
+    (graph, conflicts, profile)
+}
+
 /// Find the potential context errors in the graph
+/// The initial states of the graph are the first `initials` ones
 fn find_context_errors(
     graph: &Graph,
     inverse: &InverseGraph,
     grammar: &Grammar,
+    initials: usize,
 ) -> Vec<ContextError> {
     let mut errors = Vec::new();
     for (from_state, state) in graph.states.iter().enumerate() {
@@ -1346,6 +3797,7 @@ fn find_context_errors(
                     graph,
                     inverse,
                     grammar,
+                    initials,
                     &mut errors,
                     from_state,
                     *to_state,
@@ -1358,16 +3810,20 @@ fn find_context_errors(
 }
 
 /// Find the potential context errors in the graph at a state
+#[allow(clippy::too_many_arguments)]
 fn find_context_errors_in(
     graph: &Graph,
     inverse: &InverseGraph,
     grammar: &Grammar,
+    initials: usize,
     errors: &mut Vec<ContextError>,
     from_state: usize,
     to_state: usize,
     terminal: &Terminal,
 ) {
-    let mut paths = inverse.get_paths_to(from_state);
+    let mut paths: Vec<Path> = (0..initials)
+        .flat_map(|initial| inverse.get_paths_from(initial, from_state))
+        .collect();
     for path in &mut paths {
         path.0.push(PathElem {
             state: to_state,
@@ -1440,7 +3896,7 @@ fn find_unmatchable_tokens(
                 if let SymbolRef::Terminal(id) = element.symbol {
                     // looking at a terminal
                     let terminal_ref = TerminalRef::Terminal(id);
-                    if !expected.content.contains(&terminal_ref) {
+                    if !expected.contains(terminal_ref) {
                         // the terminal is not expected
                         unexpected.add(terminal_ref);
                     }
@@ -1451,37 +3907,111 @@ fn find_unmatchable_tokens(
     unexpected
 }
 
-/// Build the specified grammar
+/// Build the specified grammar, starting from the specified generated axiom
+/// and from the generated axioms of the additional entry points.
+/// The graph has one initial state for each, the one for the axiom being state 0,
+/// followed by the ones for the entry points (see `EntryData::state`).
+/// The progress callback is notified when starting to build the graph and its reductions
 ///
 /// # Errors
 ///
-/// Returns LR conflict as errors for LR(k) parsers
+/// Returns LR conflict as errors for LR(k) parsers,
+/// except for the shift/reduce conflicts when there are exactly `expected_conflicts` of them.
+/// Returns an error as soon as the graph has more than `max_states` states.
+#[allow(clippy::too_many_arguments)]
 pub fn build_graph(
     grammar: &Grammar,
     grammar_index: usize,
     expected: &TerminalSet,
     dfa: &DFA,
     method: ParsingMethod,
+    axiom: usize,
+    entries: &[EntryData],
+    expected_conflicts: Option<usize>,
+    max_states: usize,
+    progress: &dyn Fn(CompilationProgress),
 ) -> Result<Graph, Vec<Error>> {
-    let (graph, conflicts) = match method {
-        ParsingMethod::LR0 => build_graph_lr0(grammar),
-        ParsingMethod::LR1 => build_graph_lr1(grammar),
-        ParsingMethod::LALR1 => build_graph_lalr1(grammar),
-        ParsingMethod::RNGLR1 => build_graph_rnglr1(grammar),
-        ParsingMethod::RNGLALR1 => build_graph_rnglalr1(grammar),
+    progress(CompilationProgress::BuildingLRGraph);
+    let mut axioms = vec![axiom];
+    axioms.extend(entries.iter().map(|entry| entry.axiom));
+    let graph = match method {
+        ParsingMethod::LR0 => get_graph_lr0(grammar, &axioms, None, max_states),
+        ParsingMethod::LR1 | ParsingMethod::RNGLR1 => {
+            get_graph_lr1(grammar, &axioms, None, max_states)
+        }
+        ParsingMethod::LALR1 | ParsingMethod::RNGLALR1 => {
+            get_graph_lalr1(grammar, &axioms, None, max_states)
+        }
+        ParsingMethod::IELR1 => get_graph_ielr1(grammar, &axioms, None, max_states),
+    };
+    let mut graph = match graph {
+        Ok(graph) => graph,
+        Err(partial) => {
+            return Err(vec![Error::StateLimitExceeded(
+                grammar_index,
+                StateLimitError {
+                    limit: max_states,
+                    method,
+                    heads: partial.get_recent_kernel_heads(),
+                },
+            )]);
+        }
+    };
+    progress(CompilationProgress::BuildingReductions);
+    let conflicts = match method {
+        ParsingMethod::LR0 => graph.build_reductions_lr0(grammar),
+        ParsingMethod::LR1 | ParsingMethod::LALR1 | ParsingMethod::IELR1 => {
+            graph.build_reductions_lr1(grammar)
+        }
+        ParsingMethod::RNGLR1 | ParsingMethod::RNGLALR1 => graph.build_reductions_rnglr1(grammar),
     };
     let inverse = graph.inverse();
     let mut errors = Vec::new();
     if method.raise_conflict() {
-        for mut conflict in conflicts.0 {
-            conflict.phrases = inverse.get_inputs_for(conflict.state, grammar);
+        let reachable_contexts = graph.get_reachable_contexts(grammar);
+        // the shift/reduce conflicts are accepted when there are exactly as many as expected,
+        // they are then resolved in favor of the shift
+        let shift_reduce = conflicts.count_of_kind(ConflictKind::ShiftReduce);
+        if let Some(expected) = expected_conflicts {
+            if expected != shift_reduce {
+                errors.push(Error::UnexpectedConflictCount(
+                    grammar_index,
+                    expected,
+                    shift_reduce,
+                ));
+            }
+        }
+        let accept_shift_reduce = expected_conflicts == Some(shift_reduce);
+        for mut conflict in conflicts {
+            if accept_shift_reduce && conflict.kind == ConflictKind::ShiftReduce {
+                continue;
+            }
+            // attribute the conflict to the first axiom that reaches its state
+            let (initial, phrases) = (0..axioms.len())
+                .find_map(|initial| {
+                    let phrases = inverse.get_inputs_from(initial, conflict.state, grammar);
+                    (!phrases.is_empty()).then_some((initial, phrases))
+                })
+                .unwrap_or_default();
+            conflict.entry = initial.checked_sub(1).map(|index| entries[index].variable);
+            conflict.phrases = phrases;
             for phrase in &mut conflict.phrases {
                 phrase.append(conflict.lookahead.terminal);
             }
+            let mut contexts = reachable_contexts[conflict.state].clone();
+            if let Some(opened) = graph.states[conflict.state]
+                .opening_contexts
+                .get(&conflict.lookahead.terminal)
+            {
+                contexts.extend_from_slice(opened);
+            }
+            contexts.sort_unstable();
+            contexts.dedup();
+            conflict.contexts = contexts;
             errors.push(Error::LrConflict(grammar_index, Box::new(conflict)));
         }
     }
-    for error in find_context_errors(&graph, &inverse, grammar) {
+    for error in find_context_errors(&graph, &inverse, grammar, axioms.len()) {
         errors.push(Error::TerminalOutsideContext(grammar_index, error));
     }
     for unexpected in find_unmatchable_tokens(&graph, grammar, expected).content {
@@ -1499,3 +4029,33 @@ pub fn build_graph(
     }
     Err(errors)
 }
+
+#[test]
+fn test_raise_reduce_reduce_dedupes_items() {
+    let item = |variable: usize| Item {
+        rule: RuleRef::new(variable, 0),
+        position: 1,
+        lookaheads: Lookaheads::default(),
+    };
+    let lookahead = Lookahead::from(TerminalRef::Dollar);
+    let mut conflicts = Conflicts::default();
+    conflicts.raise_reduce_reduce(0, item(3), item(4), lookahead.clone());
+    conflicts.raise_reduce_reduce(0, item(3), item(5), lookahead.clone());
+    conflicts.raise_reduce_reduce(0, item(4), item(5), lookahead);
+    assert_eq!(conflicts.len(), 1);
+    let variables: Vec<usize> = conflicts.content[0]
+        .reduce_items
+        .iter()
+        .map(|item| item.rule.variable)
+        .collect();
+    assert_eq!(variables, [3, 4, 5]);
+    // aggregating the same conflict does not duplicate its items
+    conflicts.aggregate(conflicts.clone());
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts.content[0].reduce_items.len(), 3);
+    // the same conflict in another state is kept apart
+    let mut other = Conflicts::default();
+    other.raise_reduce_reduce(1, item(3), item(4), Lookahead::from(TerminalRef::Dollar));
+    conflicts.aggregate(other);
+    assert_eq!(conflicts.len(), 2);
+}
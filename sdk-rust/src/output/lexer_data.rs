@@ -17,15 +17,18 @@
 
 //! Module for writing lexer automaton
 
+use std::cmp::Reverse;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::PathBuf;
 
-use hime_redist::lexers::automaton::DEAD_STATE;
+use hime_redist::lexers::automaton::{
+    Recovery, DEAD_STATE, TERMINAL_AT_END, TERMINAL_BEFORE_LOOKAHEAD,
+};
 
 use crate::errors::Error;
-use crate::finite::{DFAState, DFA};
-use crate::grammars::{Grammar, TerminalRef, TerminalSet};
+use crate::finite::{DFAState, MatchCondition, DFA};
+use crate::grammars::{Grammar, Terminal, TerminalRef, TerminalSet};
 use crate::output::helper::{write_u16, write_u32};
 use crate::CharSpan;
 
@@ -61,22 +64,7 @@ pub fn write_lexer_data(
     for state in &dfa.states {
         write_u32(writer, offset)?;
         // adds the length required by this state
-        offset += 3 + 256; // header + transitions for [0-255] characters
-        let mut current_contexts = Vec::new();
-        for item in &state.items {
-            let context = grammar.get_terminal_context((*item).into());
-            if !current_contexts.contains(&context) {
-                current_contexts.push(context);
-                // context information
-                offset += 2;
-            }
-        }
-        for transition in state.transitions.keys() {
-            if transition.end >= 256 {
-                // transition outside the [0-255] range
-                offset += 3;
-            }
-        }
+        offset += get_state_length(state) as u32;
     }
     // write each state
     for state in &dfa.states {
@@ -85,6 +73,90 @@ pub fn write_lexer_data(
     Ok(())
 }
 
+/// Gets the recoveries of the unterminated tokens of the recoverable terminals for a DFA
+/// A terminal is recovered from all the states from which one of its final states can be reached.
+/// For the same state, the recoveries are ordered by decreasing priority of the terminals.
+#[must_use]
+pub fn get_lexer_recoveries(grammar: &Grammar, dfa: &DFA, expected: &TerminalSet) -> Vec<Recovery> {
+    let mut predecessors = vec![Vec::new(); dfa.len()];
+    for (index, state) in dfa.states.iter().enumerate() {
+        for next in state.transitions.values() {
+            predecessors[*next].push(index);
+        }
+    }
+    let mut terminals: Vec<&Terminal> = grammar
+        .terminals
+        .iter()
+        .filter(|terminal| terminal.recovery.is_some() && !terminal.is_fragment)
+        .collect();
+    terminals.sort_by_key(|terminal| Reverse(terminal.priority()));
+    let mut recoveries = Vec::new();
+    for terminal in terminals {
+        let terminal_ref = TerminalRef::Terminal(terminal.id);
+        let (Some(point), Some(index)) = (
+            terminal.recovery,
+            expected.content.iter().position(|t| t == &terminal_ref),
+        ) else {
+            continue;
+        };
+        let mut queue: Vec<usize> = dfa
+            .states
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| state.items.iter().any(|item| item.sid() == terminal.id))
+            .map(|(index, _)| index)
+            .collect();
+        let mut reaching = vec![false; dfa.len()];
+        for index in &queue {
+            reaching[*index] = true;
+        }
+        while let Some(current) = queue.pop() {
+            for previous in &predecessors[current] {
+                if !reaching[*previous] {
+                    reaching[*previous] = true;
+                    queue.push(*previous);
+                }
+            }
+        }
+        // the initial state is never stopped at after the start of a token
+        recoveries.extend(
+            reaching
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(_, reached)| **reached)
+                .map(|(state, _)| Recovery {
+                    state: state as u32,
+                    context: terminal.context as u16,
+                    index: index as u16,
+                    point,
+                }),
+        );
+    }
+    recoveries.sort_by_key(|recovery| recovery.state);
+    recoveries
+}
+
+/// Gets the size in bytes of the lexer's data for a DFA
+pub fn get_lexer_data_size(dfa: &DFA) -> usize {
+    // number of states and offsets as u32, states as u16
+    4 + 4 * dfa.len() + 2 * dfa.states.iter().map(get_state_length).sum::<usize>()
+}
+
+/// Gets the length (in u16) of the data for a DFA state
+fn get_state_length(state: &DFAState) -> usize {
+    let mut length = 3 + 256; // header + transitions for [0-255] characters
+                              // context information for each matched terminal
+    length += 2 * state.get_selectable_items().len();
+    for transition in state.transitions.keys() {
+        if transition.end >= 256 {
+            // transition outside the [0-255] range
+            length += 3;
+        }
+    }
+    length
+}
+
 /// Writes the lexer's data
 fn write_lexer_data_state(
     writer: &mut dyn Write,
@@ -113,21 +185,21 @@ fn write_lexer_data_state(
     }
     let mut contexts = Vec::new();
     let mut matched = Vec::new();
-    for item in &state.items {
+    // the terminals with the most priority for each context, up to the first one without condition
+    for item in state.get_selectable_items() {
         let terminal = grammar.get_terminal(item.sid()).unwrap();
         let terminal_ref = TerminalRef::Terminal(terminal.id);
-        if !contexts.contains(&terminal.context) {
-            // this is the first time this context is found in the current DFA state
-            // this is the terminal with the most priority for this context
-            contexts.push(terminal.context);
-            matched.push(
-                expected
-                    .content
-                    .iter()
-                    .position(|t| t == &terminal_ref)
-                    .unwrap(),
-            );
-        }
+        let index = expected
+            .content
+            .iter()
+            .position(|t| t == &terminal_ref)
+            .unwrap() as u16;
+        contexts.push(terminal.context);
+        matched.push(match item.condition() {
+            MatchCondition::Always => index,
+            MatchCondition::AtEnd => index | TERMINAL_AT_END,
+            MatchCondition::BeforeLookahead => index | TERMINAL_BEFORE_LOOKAHEAD,
+        });
     }
 
     // write the number of matched terminals
@@ -139,7 +211,7 @@ fn write_lexer_data_state(
     // write the matched terminals
     for (context, index) in contexts.into_iter().zip(matched.into_iter()) {
         write_u16(writer, context as u16)?;
-        write_u16(writer, index as u16)?;
+        write_u16(writer, index)?;
     }
     // write the cached transitions
     for value in &cache {
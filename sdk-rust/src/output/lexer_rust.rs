@@ -21,6 +21,8 @@ use std::fs::File;
 use std::io::{self, Write};
 use std::path::PathBuf;
 
+use hime_redist::lexers::automaton::Recovery;
+
 use crate::errors::Error;
 use crate::grammars::{Grammar, TerminalRef, TerminalSet, PREFIX_GENERATED_TERMINAL};
 use crate::output::get_lexer_bin_name_rust;
@@ -43,6 +45,8 @@ pub fn write(
     with_std: bool,
     suppress_module_doc: bool,
     compress_automata: bool,
+    emit_actions: bool,
+    recoveries: &[Recovery],
 ) -> Result<(), Error> {
     let mut final_path = PathBuf::new();
     if let Some(path) = path {
@@ -63,6 +67,26 @@ pub fn write(
         None => 0xFFFF,
         Some(terminal_ref) => terminal_ref.sid(),
     };
+    // the scanners of the external terminals, with the terminals' indices
+    let externals: Vec<(usize, &str)> = expected
+        .content
+        .iter()
+        .enumerate()
+        .skip(2)
+        .filter_map(|(index, terminal_ref)| {
+            let terminal = grammar.get_terminal(terminal_ref.sid())?;
+            Some((index, terminal.external_scanner.as_deref()?))
+        })
+        .collect();
+    // the display names of the terminals that have one
+    let aliases: Vec<(usize, &str)> = expected
+        .content
+        .iter()
+        .filter_map(|terminal_ref| {
+            let terminal = grammar.get_terminal(terminal_ref.sid())?;
+            Some((terminal.id, terminal.alias.as_deref()?))
+        })
+        .collect();
 
     if !suppress_module_doc {
         writeln!(
@@ -81,9 +105,23 @@ pub fn write(
 
     writeln!(writer, "use hime_redist::ast::{{AstImpl, AstNode}};")?;
     writeln!(writer, "use hime_redist::errors::ParseErrors;")?;
-    writeln!(writer, "use hime_redist::lexers::automaton::Automaton;")?;
+    if recoveries.is_empty() {
+        writeln!(writer, "use hime_redist::lexers::automaton::Automaton;")?;
+    } else {
+        writeln!(
+            writer,
+            "use hime_redist::lexers::automaton::{{Automaton, Recovery, RecoveryPoint}};"
+        )?;
+    }
     writeln!(writer, "use hime_redist::lexers::impls::{base_lexer}Lexer;")?;
-    writeln!(writer, "use hime_redist::lexers::Lexer;")?;
+    if externals.is_empty() {
+        writeln!(writer, "use hime_redist::lexers::Lexer;")?;
+    } else {
+        writeln!(
+            writer,
+            "use hime_redist::lexers::{{ExternalTerminal, Lexer}};"
+        )?;
+    }
     if is_rnglr {
         writeln!(writer, "use hime_redist::parsers::rnglr::RNGLRAutomaton;")?;
         writeln!(writer, "use hime_redist::parsers::rnglr::RNGLRParser;")?;
@@ -94,16 +132,20 @@ pub fn write(
     writeln!(writer, "use hime_redist::parsers::Parser;")?;
     writeln!(
         writer,
-        "use hime_redist::result::{{ParseResult, ParseResultAst{}}};",
+        "use hime_redist::result::{{ArenaParseResult, ParseArena, ParseResult, ParseResultAst{}}};",
         if is_rnglr { ", ParseResultSppf" } else { "" }
     )?;
     if is_rnglr {
         writeln!(writer, "use hime_redist::sppf::SppfImpl;")?;
     }
-    writeln!(writer, "use hime_redist::symbols::SemanticBody;")?;
+    if emit_actions || is_rnglr {
+        writeln!(writer, "use hime_redist::symbols::SemanticBody;")?;
+    }
     writeln!(writer, "use hime_redist::symbols::SemanticElementTrait;")?;
     writeln!(writer, "use hime_redist::symbols::Symbol;")?;
-    writeln!(writer, "use hime_redist::text::Text;")?;
+    writeln!(writer, "use hime_redist::symbols::SymbolAnnotation;")?;
+    writeln!(writer, "use hime_redist::symbols::get_metadata;")?;
+    writeln!(writer, "use hime_redist::text::{{ParseConfig, Text}};")?;
     writeln!(writer, "use hime_redist::tokens::TokenRepository;")?;
     writeln!(writer)?;
 
@@ -124,6 +166,23 @@ pub fn write(
     }
     writeln!(writer)?;
 
+    if !recoveries.is_empty() {
+        writeln!(
+            writer,
+            "/// The recoveries of the unterminated tokens of the recoverable terminals"
+        )?;
+        writeln!(writer, "const LEXER_RECOVERIES: &[Recovery] = &[")?;
+        for recovery in recoveries {
+            writeln!(
+                writer,
+                "    Recovery {{ state: 0x{:04X}, context: 0x{:04X}, index: 0x{:04X}, point: RecoveryPoint::{:?} }},",
+                recovery.state, recovery.context, recovery.index, recovery.point
+            )?;
+        }
+        writeln!(writer, "];")?;
+        writeln!(writer)?;
+    }
+
     for terminal_ref in expected.content.iter().skip(2) {
         let terminal = grammar.get_terminal(terminal_ref.sid()).unwrap();
         if terminal.name.starts_with(PREFIX_GENERATED_TERMINAL) {
@@ -156,6 +215,30 @@ pub fn write(
     }
     writeln!(writer)?;
 
+    writeln!(
+        writer,
+        "/// The names of the lexical contexts, indexed by their identifier"
+    )?;
+    write!(writer, "pub const CONTEXTS: &[&str] = &[\"default\"")?;
+    for context in grammar.contexts.iter().skip(1) {
+        write!(writer, ", \"{}\"", context.replace('"', "\\\""))?;
+    }
+    writeln!(writer, "];")?;
+    writeln!(writer)?;
+
+    if !aliases.is_empty() {
+        writeln!(
+            writer,
+            "/// The names displayed in errors for the terminals that have one, by symbol identifier"
+        )?;
+        writeln!(writer, "pub const TERMINAL_ALIASES: &[(u32, &str)] = &[")?;
+        for (id, alias) in &aliases {
+            writeln!(writer, "    (0x{id:04X}, {alias:?}),")?;
+        }
+        writeln!(writer, "];")?;
+        writeln!(writer)?;
+    }
+
     writeln!(
         writer,
         "/// The collection of terminals matched by this lexer"
@@ -190,20 +273,73 @@ pub fn write(
     writeln!(writer, "];")?;
     writeln!(writer)?;
 
+    let automaton = format!(
+        "Automaton::new(LEXER_AUTOMATON{}){}",
+        if compress_automata { ".as_ref()" } else { "" },
+        if recoveries.is_empty() {
+            ""
+        } else {
+            ".with_recoveries(LEXER_RECOVERIES)"
+        }
+    );
+    if with_std {
+        writeln!(
+            writer,
+            "/// Gets the lexer's automaton, deserialized on first use"
+        )?;
+        writeln!(writer, "fn get_lexer_automaton() -> &'static Automaton {{")?;
+        writeln!(
+            writer,
+            "    static AUTOMATON: std::sync::OnceLock<Automaton> = std::sync::OnceLock::new();"
+        )?;
+        writeln!(writer, "    AUTOMATON.get_or_init(|| {automaton})")?;
+    } else {
+        writeln!(writer, "/// Deserializes the lexer's automaton")?;
+        writeln!(writer, "fn get_lexer_automaton() -> Automaton {{")?;
+        writeln!(writer, "    {automaton}")?;
+    }
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
     writeln!(writer, "/// Creates a new lexer")?;
     writeln!(writer, "fn new_lexer<'a: 'b, 'b, 'c>(")?;
     writeln!(writer, "    repository: TokenRepository<'a, 'b, 'c>,")?;
-    writeln!(writer, "    errors: &'c mut ParseErrors<'a>")?;
+    writeln!(writer, "    errors: &'c mut ParseErrors<'a>,")?;
+    writeln!(writer, "    automaton: &'c Automaton")?;
     writeln!(writer, ") -> Lexer<'a, 'b, 'c> {{")?;
+    if externals.is_empty() && aliases.is_empty() {
+        writeln!(
+            writer,
+            "    Lexer::{base_lexer}({base_lexer}Lexer::new(repository, errors, automaton, 0x{separator:04X}))"
+        )?;
+        writeln!(writer, "}}")?;
+        writeln!(writer)?;
+        return Ok(());
+    }
     writeln!(
         writer,
-        "    let automaton = Automaton::new(LEXER_AUTOMATON{});",
-        if compress_automata { ".as_ref()" } else { "" }
-    )?;
-    writeln!(
-        writer,
-        "    Lexer::{base_lexer}({base_lexer}Lexer::new(repository, errors, automaton, 0x{separator:04X}))"
+        "    let mut lexer = Lexer::{base_lexer}({base_lexer}Lexer::new(repository, errors, automaton, 0x{separator:04X}));"
     )?;
+    if !externals.is_empty() {
+        writeln!(
+            writer,
+            "    lexer.get_data_mut().externals = {}vec![",
+            if with_std { "" } else { "alloc::" }
+        )?;
+        for (index, function) in externals {
+            writeln!(writer, "        ExternalTerminal {{")?;
+            writeln!(writer, "            terminal: {index},")?;
+            writeln!(writer, "            scanner: &{function}")?;
+            writeln!(writer, "        }},")?;
+        }
+        writeln!(writer, "    ];")?;
+    }
+    if !aliases.is_empty() {
+        writeln!(
+            writer,
+            "    lexer.get_data_mut().aliases = TERMINAL_ALIASES.to_vec();"
+        )?;
+    }
+    writeln!(writer, "    lexer")?;
     writeln!(writer, "}}")?;
     writeln!(writer)?;
     Ok(())
@@ -36,15 +36,18 @@ use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
 use hime_redist::lexers::automaton::Automaton;
+use hime_redist::lexers::DEFAULT_CONTEXT_NAME;
 use hime_redist::parsers::lrk::LRkAutomaton;
 use hime_redist::parsers::rnglr::RNGLRAutomaton;
 use hime_redist::symbols::Symbol;
+use hime_redist::text::ParseConfig;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
 use crate::errors::Error;
-use crate::grammars::{BuildData, Grammar};
-use crate::sdk::{InMemoryParser, ParserAutomaton};
+use crate::finite::DFA;
+use crate::grammars::{BuildData, Grammar, TerminalRef, OPTION_AXIOM};
+use crate::sdk::{InMemoryExternalTerminal, InMemoryParser, ParserAutomaton};
 use crate::{CompilationTask, ParsingMethod, Runtime};
 
 /// Output artifacts for a grammar
@@ -81,6 +84,30 @@ pub fn output_grammar_artifacts(
         Ok(modifier) => modifier,
         Err(error) => return Err(vec![error]),
     };
+    if runtime != Runtime::Rust {
+        // only the Rust runtime reads the lookahead conditions on terminals
+        // and calls external scanners
+        let errors: Vec<Error> = grammar
+            .terminals
+            .iter()
+            .filter_map(|terminal| {
+                let terminal_ref = TerminalRef::Terminal(terminal.id);
+                if terminal.external_scanner.is_some() {
+                    Some(Error::ExternalTerminalNotSupported(
+                        grammar_index,
+                        terminal_ref,
+                    ))
+                } else if terminal.nfa.lookahead.is_some() {
+                    Some(Error::LookaheadNotSupported(grammar_index, terminal_ref))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+    }
 
     // write data
     let output_path = task.get_output_path_for(grammar);
@@ -93,28 +120,20 @@ pub fn output_grammar_artifacts(
     ) {
         return Err(vec![error]);
     }
-    if let Err(error) = match data.method {
-        ParsingMethod::LR0 | ParsingMethod::LR1 | ParsingMethod::LALR1 => {
-            parser_data::write_parser_lrk_data_file(
-                output_path.as_ref(),
-                get_parser_bin_name(grammar, runtime),
-                grammar,
-                &data.expected,
-                &data.graph,
-            )
-        }
-        ParsingMethod::RNGLR1 | ParsingMethod::RNGLALR1 => {
-            parser_data::write_parser_rnglr_data_file(
-                output_path.as_ref(),
-                get_parser_bin_name(grammar, runtime),
-                grammar,
-                &data.expected,
-                &data.graph,
-            )
-        }
-    } {
+    if let Err(error) = write_parser_data_file(
+        output_path.as_ref(),
+        get_parser_bin_name(grammar, runtime),
+        grammar,
+        data,
+        runtime,
+    ) {
         return Err(vec![error]);
     }
+    if grammar.has_stable_ids() {
+        if let Err(error) = write_stable_ids_file(task, grammar) {
+            return Err(vec![error]);
+        }
+    }
     // write code
     match runtime {
         Runtime::Net => {
@@ -169,6 +188,7 @@ pub fn output_grammar_artifacts(
             let with_std = task.get_rust_use_std();
             let suppress_module_doc = task.get_rust_suppress_module_doc();
             let compress_automata = task.get_rust_compress_automata();
+            let emit_actions = task.get_rust_emit_actions(grammar);
             if let Err(error) = lexer_rust::write(
                 output_path.as_ref(),
                 format!("{}.rs", helper::to_snake_case(&grammar.name)),
@@ -179,6 +199,8 @@ pub fn output_grammar_artifacts(
                 with_std,
                 suppress_module_doc,
                 compress_automata,
+                emit_actions,
+                &lexer_data::get_lexer_recoveries(grammar, &data.dfa, &data.expected),
             ) {
                 return Err(vec![error]);
             }
@@ -192,6 +214,8 @@ pub fn output_grammar_artifacts(
                 mode.output_assembly(),
                 with_std,
                 compress_automata,
+                emit_actions,
+                &data.entries,
             ) {
                 return Err(vec![error]);
             }
@@ -200,6 +224,39 @@ pub fn output_grammar_artifacts(
     Ok(())
 }
 
+/// Writes the parser automaton for a grammar
+fn write_parser_data_file(
+    path: Option<&String>,
+    file_name: String,
+    grammar: &Grammar,
+    data: &BuildData,
+    runtime: Runtime,
+) -> Result<(), Error> {
+    match data.method {
+        ParsingMethod::LR0 | ParsingMethod::LR1 | ParsingMethod::LALR1 | ParsingMethod::IELR1 => {
+            // only the Rust runtime reads compressed LR(k) tables
+            parser_data::write_parser_lrk_data_file(
+                path,
+                file_name,
+                grammar,
+                &data.expected,
+                &data.graph,
+                runtime == Runtime::Rust,
+            )
+        }
+        ParsingMethod::RNGLR1 | ParsingMethod::RNGLALR1 => {
+            parser_data::write_parser_rnglr_data_file(
+                path,
+                file_name,
+                grammar,
+                &data.expected,
+                &data.graph,
+                data.axiom,
+            )
+        }
+    }
+}
+
 /// Builds the in-memory parser for a grammar
 ///
 /// # Errors
@@ -220,12 +277,16 @@ pub fn build_in_memory_grammar<'a>(
             name: "$",
         },
     ];
+    let mut aliases = Vec::new();
     for terminal_ref in data.expected.content.iter().skip(2) {
         if let Some(terminal) = grammar.get_terminal(terminal_ref.sid()) {
             terminals.push(Symbol {
                 id: terminal.id as u32,
                 name: &terminal.value,
             });
+            if let Some(alias) = &terminal.alias {
+                aliases.push((terminal.id as u32, alias.as_str()));
+            }
         }
     }
     let variables: Vec<Symbol<'a>> = grammar
@@ -247,49 +308,94 @@ pub fn build_in_memory_grammar<'a>(
 
     // build automata
     let mut lexer_automaton = Vec::new();
-    if let Err(error) =
-        lexer_data::write_lexer_data(&mut lexer_automaton, grammar, &data.dfa, &data.expected)
-    {
-        return Err(vec![error]);
-    }
-    let mut parser_automaton = Vec::new();
-    if let Err(error) = if data.method.is_rnglr() {
-        parser_data::write_parser_lrk_data(
-            &mut parser_automaton,
-            grammar,
-            &data.expected,
-            &data.graph,
-        )
-    } else {
-        parser_data::write_parser_rnglr_data(
-            &mut parser_automaton,
-            grammar,
-            &data.expected,
-            &data.graph,
-        )
-    } {
-        return Err(vec![error]);
-    }
+    lexer_data::write_lexer_data(&mut lexer_automaton, grammar, &data.dfa, &data.expected)
+        .map_err(|error| vec![error])?;
+    let parser_automaton = build_in_memory_automaton(grammar, data).map_err(|error| vec![error])?;
+    // the external terminals, to be bound to their scanners
+    let external_terminals = data
+        .expected
+        .content
+        .iter()
+        .enumerate()
+        .skip(2)
+        .filter_map(|(index, terminal_ref)| {
+            let terminal = grammar.get_terminal(terminal_ref.sid())?;
+            Some(InMemoryExternalTerminal {
+                function: terminal.external_scanner.clone()?,
+                terminal: index,
+                scanner: None,
+            })
+        })
+        .collect();
+
+    // the axiom starts from state 0, the additional entry points from their own initial state
+    let mut starts: Vec<(String, u32)> = grammar
+        .get_option(OPTION_AXIOM)
+        .map(|option| (option.value.clone(), 0))
+        .into_iter()
+        .collect();
+    starts.extend(
+        data.entries
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.state as u32)),
+    );
 
     Ok(InMemoryParser {
         name: &grammar.name,
         terminals,
+        aliases,
+        annotations: parser_data::get_symbol_annotations(grammar, &data.expected),
         variables,
         virtuals,
         separator: match data.separator {
             None => 0xFFFF,
             Some(terminal_ref) => terminal_ref.sid() as u32,
         },
-        lexer_automaton: Automaton::new(&lexer_automaton),
+        lexer_automaton: Automaton::new(&lexer_automaton).with_recoveries(
+            &lexer_data::get_lexer_recoveries(grammar, &data.dfa, &data.expected),
+        ),
         lexer_is_context_sensitive: grammar.contexts.len() > 1,
-        parser_automaton: if data.method.is_rnglr() {
-            ParserAutomaton::Rnglr(RNGLRAutomaton::new(&parser_automaton))
-        } else {
-            ParserAutomaton::Lrk(LRkAutomaton::new(&parser_automaton))
-        },
+        contexts: std::iter::once(DEFAULT_CONTEXT_NAME.to_string())
+            .chain(grammar.contexts.iter().skip(1).cloned())
+            .collect(),
+        parser_automaton,
+        starts,
+        productions: parser_data::get_production_rules(grammar, data.method.is_rnglr()),
+        stop_on_semantic_error: false,
+        config: ParseConfig::default(),
+        external_terminals,
     })
 }
 
+/// Builds the in-memory parser automaton for a grammar
+fn build_in_memory_automaton(
+    grammar: &Grammar,
+    data: &BuildData,
+) -> Result<ParserAutomaton, Error> {
+    let mut parser_automaton = Vec::new();
+    if data.method.is_rnglr() {
+        parser_data::write_parser_rnglr_data(
+            &mut parser_automaton,
+            grammar,
+            &data.expected,
+            &data.graph,
+            data.axiom,
+        )?;
+        Ok(ParserAutomaton::Rnglr(RNGLRAutomaton::new(
+            &parser_automaton,
+        )))
+    } else {
+        parser_data::write_parser_lrk_data(
+            &mut parser_automaton,
+            grammar,
+            &data.expected,
+            &data.graph,
+            true,
+        )?;
+        Ok(ParserAutomaton::Lrk(LRkAutomaton::new(&parser_automaton)))
+    }
+}
+
 /// Gets the list of sources to produce for a grammar
 ///
 /// # Errors
@@ -366,6 +472,22 @@ pub fn build_assembly(
     }
 }
 
+/// Gets the path to the file recording the stable identifiers of a grammar's symbols
+#[must_use]
+pub fn get_stable_ids_path(task: &CompilationTask, grammar: &Grammar) -> PathBuf {
+    build_file(
+        task.get_output_path_for(grammar).as_ref(),
+        format!("{}.ids", helper::to_snake_case(&grammar.name)),
+    )
+}
+
+/// Writes the file recording the stable identifiers of a grammar's symbols
+fn write_stable_ids_file(task: &CompilationTask, grammar: &Grammar) -> Result<(), Error> {
+    let mut file = File::create(get_stable_ids_path(task, grammar))?;
+    file.write_all(grammar.write_stable_ids().as_bytes())?;
+    Ok(())
+}
+
 /// Gets the name of the file for the lexer automaton
 fn get_lexer_bin_name(grammar: &Grammar, runtime: Runtime) -> String {
     match runtime {
@@ -414,6 +536,12 @@ fn get_parser_bin_name_rust(grammar: &Grammar) -> String {
     format!("{}_parser.bin", helper::to_snake_case(&grammar.name))
 }
 
+/// Gets the size in bytes of the serialized data for a lexer's automaton
+#[must_use]
+pub fn get_lexer_data_size(dfa: &DFA) -> usize {
+    lexer_data::get_lexer_data_size(dfa)
+}
+
 /// Creates a temp folder
 ///
 /// # Panics
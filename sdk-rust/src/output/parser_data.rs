@@ -22,19 +22,23 @@ use std::fs::File;
 use std::io::{self, Write};
 use std::path::PathBuf;
 
+use hime_redist::parsers::lrk::LRK_TABLE_COMPRESSED;
 use hime_redist::parsers::{
     LR_ACTION_CODE_ACCEPT, LR_ACTION_CODE_NONE, LR_ACTION_CODE_REDUCE, LR_ACTION_CODE_SHIFT,
     LR_OP_CODE_BASE_ADD_NULLABLE_VARIABLE, LR_OP_CODE_BASE_ADD_VIRTUAL, LR_OP_CODE_BASE_POP_STACK,
     LR_OP_CODE_BASE_SEMANTIC_ACTION,
 };
+use hime_redist::symbols::SymbolAnnotation;
 
 use crate::errors::Error;
-use crate::grammars::{
-    Grammar, Rule, RuleRef, SymbolRef, TerminalRef, TerminalSet, GENERATED_AXIOM,
-};
+use crate::grammars::{Grammar, Rule, RuleRef, SymbolRef, TerminalRef, TerminalSet};
 use crate::lr::{Graph, State};
 use crate::output::helper::{write_u16, write_u32, write_u8};
 
+/// The maximum ratio (in percents) of the size of a compressed LR(k) table to the uncompressed one
+/// for the compressed one to be used
+const LRK_COMPRESSION_THRESHOLD: usize = 75;
+
 /// Writes the data for a LR(k) parser
 pub fn write_parser_lrk_data_file(
     path: Option<&String>,
@@ -42,6 +46,7 @@ pub fn write_parser_lrk_data_file(
     grammar: &Grammar,
     expected: &TerminalSet,
     graph: &Graph,
+    allow_compression: bool,
 ) -> Result<(), Error> {
     let mut final_path = PathBuf::new();
     if let Some(path) = path {
@@ -50,21 +55,36 @@ pub fn write_parser_lrk_data_file(
     final_path.push(file_name);
     let file = File::create(final_path)?;
     let mut writer = io::BufWriter::new(file);
-    write_parser_lrk_data(&mut writer, grammar, expected, graph)
+    write_parser_lrk_data(&mut writer, grammar, expected, graph, allow_compression)
 }
 
 /// Writes the data for a LR(k) parser
+/// When allowed, the LR table is compressed if this sufficiently reduces its size.
 pub fn write_parser_lrk_data(
     writer: &mut dyn Write,
     grammar: &Grammar,
     expected: &TerminalSet,
     graph: &Graph,
+    allow_compression: bool,
 ) -> Result<(), Error> {
-    let mut rules = Vec::new();
-    for variable in &grammar.variables {
-        for i in 0..variable.rules.len() {
-            rules.push(RuleRef::new(variable.id, i));
-        }
+    let rules = get_lrk_productions(grammar);
+    let rows: Vec<Vec<(u16, u16)>> = graph
+        .states
+        .iter()
+        .map(|state| get_parser_lrk_data_row(grammar, expected, &rules, state))
+        .collect();
+    let compressed = if allow_compression {
+        let compressed = LRkCompressedTable::from_rows(&rows);
+        let columns_count = expected.len() + grammar.variables.len();
+        let uncompressed_size = rows.len() * columns_count * 4;
+        (compressed.size() * 100 <= uncompressed_size * LRK_COMPRESSION_THRESHOLD)
+            .then_some(compressed)
+    } else {
+        None
+    };
+
+    if compressed.is_some() {
+        write_u16(writer, LRK_TABLE_COMPRESSED)?;
     }
     // number of columns
     write_u16(writer, (expected.len() + grammar.variables.len()) as u16)?;
@@ -77,8 +97,15 @@ pub fn write_parser_lrk_data(
     write_parser_opening_contexts(writer, graph)?;
 
     // write the LR table
-    for state in &graph.states {
-        write_parser_lrk_data_state(writer, grammar, expected, &rules, state)?;
+    if let Some(compressed) = compressed {
+        compressed.write(writer)?;
+    } else {
+        for row in &rows {
+            for (code, data) in row {
+                write_u16(writer, *code)?;
+                write_u16(writer, *data)?;
+            }
+        }
     }
     // write production rules
     for variable in &grammar.variables {
@@ -89,6 +116,149 @@ pub fn write_parser_lrk_data(
     Ok(())
 }
 
+/// A LR(k) table compressed by row displacement
+struct LRkCompressedTable {
+    /// The displacement of the row for each state
+    displacements: Vec<u32>,
+    /// The state owning each entry
+    checks: Vec<u16>,
+    /// The action for each entry
+    entries: Vec<(u16, u16)>,
+}
+
+impl LRkCompressedTable {
+    /// Compresses the rows of a LR(k) table
+    /// The rows are placed from the most to the least populated,
+    /// each at the first displacement where its actions do not collide with those already placed.
+    /// The first entry is reserved for the default action (none).
+    fn from_rows(rows: &[Vec<(u16, u16)>]) -> LRkCompressedTable {
+        let columns_count = rows.first().map_or(0, Vec::len);
+        let actions: Vec<Vec<usize>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, (code, _))| *code != LR_ACTION_CODE_NONE)
+                    .map(|(column, _)| column)
+                    .collect()
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        order.sort_by_key(|state| std::cmp::Reverse(actions[*state].len()));
+        let mut displacements = vec![0; rows.len()];
+        let mut used = vec![true];
+        for state in order {
+            if actions[state].is_empty() {
+                // all actions are the default one
+                continue;
+            }
+            // past the used entries, there can be no collision
+            let displacement = (1..=used.len())
+                .find(|displacement| {
+                    actions[state]
+                        .iter()
+                        .all(|column| !used.get(displacement + column).copied().unwrap_or(false))
+                })
+                .unwrap();
+            for column in &actions[state] {
+                let entry = displacement + column;
+                if entry >= used.len() {
+                    used.resize(entry + 1, false);
+                }
+                used[entry] = true;
+            }
+            displacements[state] = displacement;
+        }
+        // all the columns must be addressable for all the states
+        let size = displacements.iter().max().copied().unwrap_or_default() + columns_count;
+        let mut checks = vec![LRK_TABLE_COMPRESSED; size.max(1)];
+        let mut entries = vec![(LR_ACTION_CODE_NONE, LR_ACTION_CODE_NONE); size.max(1)];
+        for (state, columns) in actions.iter().enumerate() {
+            for column in columns {
+                let entry = displacements[state] + column;
+                checks[entry] = state as u16;
+                entries[entry] = rows[state][*column];
+            }
+        }
+        LRkCompressedTable {
+            displacements: displacements.into_iter().map(|d| d as u32).collect(),
+            checks,
+            entries,
+        }
+    }
+
+    /// Gets the size in bytes of the compressed table, including the additional marker
+    fn size(&self) -> usize {
+        2 + 4 + self.displacements.len() * 4 + self.checks.len() * 2 + self.entries.len() * 4
+    }
+
+    /// Writes the compressed table
+    fn write(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        write_u32(writer, self.entries.len() as u32)?;
+        for displacement in &self.displacements {
+            write_u32(writer, *displacement)?;
+        }
+        for check in &self.checks {
+            write_u16(writer, *check)?;
+        }
+        for (code, data) in &self.entries {
+            write_u16(writer, *code)?;
+            write_u16(writer, *data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the grammar rule for each production of a LR(k) parser
+fn get_lrk_productions(grammar: &Grammar) -> Vec<RuleRef> {
+    let mut rules = Vec::new();
+    for variable in &grammar.variables {
+        for i in 0..variable.rules.len() {
+            rules.push(RuleRef::new(variable.id, i));
+        }
+    }
+    rules
+}
+
+/// Gets the grammar rule for each production of the parser automaton
+pub fn get_production_rules(grammar: &Grammar, is_rnglr: bool) -> Vec<RuleRef> {
+    if is_rnglr {
+        get_rnglr_productions(grammar)
+            .0
+            .into_iter()
+            .map(|(rule_ref, _)| rule_ref)
+            .collect()
+    } else {
+        get_lrk_productions(grammar)
+    }
+}
+
+/// Gets the user-defined annotations on the expected terminals and the variables of a grammar
+pub fn get_symbol_annotations<'a>(
+    grammar: &'a Grammar,
+    expected: &TerminalSet,
+) -> Vec<SymbolAnnotation<'a>> {
+    let terminals = expected
+        .content
+        .iter()
+        .filter_map(|terminal_ref| grammar.get_terminal(terminal_ref.sid()))
+        .map(|terminal| (terminal.id, &terminal.annotations));
+    let variables = grammar
+        .variables
+        .iter()
+        .map(|variable| (variable.id, &variable.annotations));
+    terminals
+        .chain(variables)
+        .flat_map(|(id, annotations)| {
+            annotations.iter().map(move |annotation| SymbolAnnotation {
+                symbol_id: id as u32,
+                key: &annotation.key,
+                value: &annotation.value,
+            })
+        })
+        .collect()
+}
+
 /// Writes the column headers for a parser data
 fn write_parser_column_headers(
     writer: &mut dyn Write,
@@ -125,59 +295,49 @@ fn write_parser_opening_contexts(writer: &mut dyn Write, graph: &Graph) -> Resul
     Ok(())
 }
 
-/// Generates the parser's binary data for the provided LR state
-fn write_parser_lrk_data_state(
-    writer: &mut dyn Write,
+/// Gets the actions in the LR table for the provided LR state
+fn get_parser_lrk_data_row(
     grammar: &Grammar,
     expected: &TerminalSet,
     rules: &[RuleRef],
     state: &State,
-) -> Result<(), Error> {
-    // write action on epsilon
+) -> Vec<(u16, u16)> {
+    let mut row = Vec::with_capacity(expected.len() + grammar.variables.len());
+    // action on epsilon
     if state.get_reduction_for(TerminalRef::Epsilon).is_some()
         || state.get_reduction_for(TerminalRef::NullTerminal).is_some()
     {
-        write_u16(writer, LR_ACTION_CODE_ACCEPT)?;
+        row.push((LR_ACTION_CODE_ACCEPT, LR_ACTION_CODE_NONE));
     } else {
-        write_u16(writer, LR_ACTION_CODE_NONE)?;
+        row.push((LR_ACTION_CODE_NONE, LR_ACTION_CODE_NONE));
     }
-    write_u16(writer, LR_ACTION_CODE_NONE)?;
-    // write actions for terminals
+    // actions for terminals
     for terminal in expected.content.iter().skip(1) {
         let terminal = *terminal;
         if let Some(next) = state.children.get(&terminal.into()) {
-            write_u16(writer, LR_ACTION_CODE_SHIFT)?;
-            write_u16(writer, *next as u16)?;
-        } else if let Some(reduction) = state.get_reduction_for(terminal) {
-            let index = rules
-                .iter()
-                .position(|rule| rule == &reduction.rule)
-                .unwrap();
-            write_u16(writer, LR_ACTION_CODE_REDUCE)?;
-            write_u16(writer, index as u16)?;
-        } else if let Some(reduction) = state.get_reduction_for(TerminalRef::NullTerminal) {
+            row.push((LR_ACTION_CODE_SHIFT, *next as u16));
+        } else if let Some(reduction) = state
+            .get_reduction_for(terminal)
+            .or_else(|| state.get_reduction_for(TerminalRef::NullTerminal))
+        {
             let index = rules
                 .iter()
                 .position(|rule| rule == &reduction.rule)
                 .unwrap();
-            write_u16(writer, LR_ACTION_CODE_REDUCE)?;
-            write_u16(writer, index as u16)?;
+            row.push((LR_ACTION_CODE_REDUCE, index as u16));
         } else {
-            write_u16(writer, LR_ACTION_CODE_NONE)?;
-            write_u16(writer, LR_ACTION_CODE_NONE)?;
+            row.push((LR_ACTION_CODE_NONE, LR_ACTION_CODE_NONE));
         }
     }
-    // write actions for terminals
+    // actions for variables
     for variable in &grammar.variables {
         if let Some(next) = state.children.get(&SymbolRef::Variable(variable.id)) {
-            write_u16(writer, LR_ACTION_CODE_SHIFT)?;
-            write_u16(writer, *next as u16)?;
+            row.push((LR_ACTION_CODE_SHIFT, *next as u16));
         } else {
-            write_u16(writer, LR_ACTION_CODE_NONE)?;
-            write_u16(writer, LR_ACTION_CODE_NONE)?;
+            row.push((LR_ACTION_CODE_NONE, LR_ACTION_CODE_NONE));
         }
     }
-    Ok(())
+    row
 }
 
 /// Generates the parser's binary representation of a rule production
@@ -239,6 +399,7 @@ pub fn write_parser_rnglr_data_file(
     grammar: &Grammar,
     expected: &TerminalSet,
     graph: &Graph,
+    axiom: usize,
 ) -> Result<(), Error> {
     let mut final_path = PathBuf::new();
     if let Some(path) = path {
@@ -247,16 +408,13 @@ pub fn write_parser_rnglr_data_file(
     final_path.push(file_name);
     let file = File::create(final_path)?;
     let mut writer = io::BufWriter::new(file);
-    write_parser_rnglr_data(&mut writer, grammar, expected, graph)
+    write_parser_rnglr_data(&mut writer, grammar, expected, graph, axiom)
 }
 
-/// Writes the data for a RNGLR parser
-pub fn write_parser_rnglr_data(
-    writer: &mut dyn Write,
-    grammar: &Grammar,
-    expected: &TerminalSet,
-    graph: &Graph,
-) -> Result<(), Error> {
+/// Gets the productions of a RNGLR parser, as the grammar rule and length of each production,
+/// along with the index of the nullable production for each variable
+#[allow(clippy::cast_possible_truncation)]
+fn get_rnglr_productions(grammar: &Grammar) -> (Vec<(RuleRef, usize)>, Vec<u16>) {
     // complete list of rules, including new ones for the right-nullable parts
     let mut rules = Vec::new();
     // index of the nullable rule for the variable with the same index
@@ -271,18 +429,14 @@ pub fn write_parser_rnglr_data(
             ));
             // Look for right-nullable choices
             for i in 1..rule.body.choices[0].len() {
-                if rule.body.choices[i]
-                    .firsts
-                    .content
-                    .contains(&TerminalRef::Epsilon)
-                {
+                if rule.firsts_after(i).contains(TerminalRef::Epsilon) {
                     temp.push((RuleRef::new(variable.id, rule_index), i));
                 }
             }
         }
         let mut null_index: u16 = 0xFFFF;
         // nullable variable?
-        if variable.firsts.content.contains(&TerminalRef::Epsilon) {
+        if variable.firsts.contains(TerminalRef::Epsilon) {
             // look for a nullable rule
             if let Some((index, _)) = temp.iter().enumerate().find(|(_, (_, l))| *l == 0) {
                 // Found a 0-length reduction rule => perfect
@@ -294,8 +448,7 @@ pub fn write_parser_rnglr_data(
                     .find(|(rule_ref, _)| {
                         variable.rules[rule_ref.index].body.choices[0]
                             .firsts
-                            .content
-                            .contains(&TerminalRef::Epsilon)
+                            .contains(TerminalRef::Epsilon)
                     })
                     .copied()
                     .unwrap();
@@ -307,6 +460,19 @@ pub fn write_parser_rnglr_data(
         nullables.push(null_index);
         rules.append(&mut temp);
     }
+    (rules, nullables)
+}
+
+/// Writes the data for a RNGLR parser
+/// The axiom is the identifier of the generated axiom variable the graph starts from
+pub fn write_parser_rnglr_data(
+    writer: &mut dyn Write,
+    grammar: &Grammar,
+    expected: &TerminalSet,
+    graph: &Graph,
+    axiom: usize,
+) -> Result<(), Error> {
+    let (rules, nullables) = get_rnglr_productions(grammar);
 
     let mut total: u32 = 0;
     let mut offsets: Vec<u32> = Vec::new(); // for each state, the offset in the action table
@@ -324,7 +490,7 @@ pub fn write_parser_rnglr_data(
     let axiom_index = grammar
         .variables
         .iter()
-        .position(|variable| variable.name == GENERATED_AXIOM)
+        .position(|variable| variable.id == axiom)
         .unwrap();
 
     // index of the axiom variable
@@ -525,3 +691,58 @@ fn write_parser_rnglr_data_rule(
     }
     Ok(())
 }
+
+#[test]
+fn test_lrk_compressed_table() {
+    use hime_redist::parsers::lrk::LRkAutomaton;
+
+    use crate::{CompilationTask, Input};
+
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(include_str!("../loaders/HimeGrammar.gram"))],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let mut uncompressed = Vec::new();
+    write_parser_lrk_data(
+        &mut uncompressed,
+        grammar,
+        &build.expected,
+        &build.graph,
+        false,
+    )
+    .unwrap();
+    let mut compressed = Vec::new();
+    write_parser_lrk_data(
+        &mut compressed,
+        grammar,
+        &build.expected,
+        &build.graph,
+        true,
+    )
+    .unwrap();
+    // for the 179 states of the grammar, the data goes from 66001 bytes to 14157 bytes
+    assert!(compressed.len() * 4 < uncompressed.len());
+    let uncompressed = LRkAutomaton::new(&uncompressed);
+    let compressed = LRkAutomaton::new(&compressed);
+    assert!(!uncompressed.is_compressed());
+    assert!(compressed.is_compressed());
+    assert_eq!(
+        compressed.get_states_count(),
+        uncompressed.get_states_count()
+    );
+    assert_eq!(
+        compressed.get_columns_count(),
+        uncompressed.get_columns_count()
+    );
+    for state in 0..uncompressed.get_states_count() as u32 {
+        for column in 0..uncompressed.get_columns_count() {
+            let expected = uncompressed.get_action_at(state, column);
+            let action = compressed.get_action_at(state, column);
+            assert_eq!(action.get_code(), expected.get_code());
+            assert_eq!(action.get_data(), expected.get_data());
+        }
+    }
+}
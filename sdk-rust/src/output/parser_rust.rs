@@ -22,13 +22,17 @@ use std::io::{self, Write};
 use std::path::PathBuf;
 
 use crate::errors::Error;
-use crate::grammars::{Grammar, TerminalSet, PREFIX_GENERATED_TERMINAL, PREFIX_GENERATED_VARIABLE};
+use crate::grammars::{
+    EntryData, Grammar, TerminalSet, OPTION_AXIOM, PREFIX_GENERATED_TERMINAL,
+    PREFIX_GENERATED_VARIABLE,
+};
 use crate::output::get_parser_bin_name_rust;
 use crate::output::helper::{to_snake_case, to_upper_case};
+use crate::output::parser_data::get_symbol_annotations;
 use crate::ParsingMethod;
 
 /// Generates code for the specified file
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
 pub fn write(
     path: Option<&String>,
     file_name: String,
@@ -39,6 +43,8 @@ pub fn write(
     output_assembly: bool,
     with_std: bool,
     compress_automata: bool,
+    emit_actions: bool,
+    entries: &[EntryData],
 ) -> Result<(), Error> {
     let mut final_path = PathBuf::new();
     if let Some(path) = path {
@@ -74,46 +80,118 @@ pub fn write(
         )?;
     }
     writeln!(writer)?;
+    write_code_parser_automaton(&mut writer, automaton_type, with_std, compress_automata)?;
 
     write_code_symbols(&mut writer, grammar)?;
     write_code_variables(&mut writer, grammar)?;
     write_code_virtuals(&mut writer, grammar)?;
-    write_code_actions(&mut writer, grammar)?;
-    write_code_constructors(
-        &mut writer,
-        grammar,
-        output_assembly,
-        nmespace,
-        automaton_type,
-        parser_type,
-        parser_ctor,
-        "AstImpl",
-        "ParseResultAst",
-        "",
-        with_std,
-        compress_automata,
-    )?;
-    if method.is_rnglr() {
-        writeln!(writer)?;
+    write_code_annotations(&mut writer, grammar, expected)?;
+    write_code_sync_terminals(&mut writer, grammar)?;
+    if emit_actions {
+        write_code_actions(&mut writer, grammar)?;
+    }
+    // the parse functions for the axiom, then for each additional entry point
+    let mut starts = vec![(0, String::new())];
+    for entry in entries {
+        starts.push((entry.state, format!("_{}", to_snake_case(&entry.name))));
+    }
+    for (index, (start_state, entry_suffix)) in starts.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
         write_code_constructors(
             &mut writer,
             grammar,
             output_assembly,
             nmespace,
-            automaton_type,
+            *start_state,
             parser_type,
-            "new_with_sppf",
-            "SppfImpl",
-            "ParseResultSppf",
-            "_to_sppf",
+            parser_ctor,
+            "AstImpl",
+            "ParseResultAst",
+            entry_suffix,
             with_std,
-            compress_automata,
+            emit_actions,
         )?;
+        if method.is_rnglr() {
+            writeln!(writer)?;
+            write_code_constructors(
+                &mut writer,
+                grammar,
+                output_assembly,
+                nmespace,
+                *start_state,
+                parser_type,
+                "new_with_sppf",
+                "SppfImpl",
+                "ParseResultSppf",
+                &format!("{entry_suffix}_to_sppf"),
+                with_std,
+                emit_actions,
+            )?;
+        }
+    }
+    if !entries.is_empty() {
+        write_code_parse_as(&mut writer, grammar, entries)?;
     }
     write_code_visitor(&mut writer, grammar, expected)?;
     Ok(())
 }
 
+/// Generates the selector of the parse function for the axiom and the additional entry points
+fn write_code_parse_as(
+    writer: &mut dyn Write,
+    grammar: &Grammar,
+    entries: &[EntryData],
+) -> Result<(), Error> {
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Selects the parse function for the specified entry point, either the axiom or an additional entry point"
+    )?;
+    writeln!(
+        writer,
+        "/// Returns `None` when the entry point is neither the axiom, nor an additional entry point"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn select_parser(entry_point: &str) -> Option<fn(&str) -> ParseResult<'static, '_, 'static, AstImpl>> {{"
+    )?;
+    writeln!(writer, "    match entry_point {{")?;
+    if let Some(axiom) = grammar.get_option(OPTION_AXIOM) {
+        writeln!(writer, "        {:?} => Some(parse_str),", axiom.value)?;
+    }
+    for entry in entries {
+        writeln!(
+            writer,
+            "        {:?} => Some(parse_str_{}),",
+            entry.name,
+            to_snake_case(&entry.name)
+        )?;
+    }
+    writeln!(writer, "        _ => None,")?;
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Parses the specified string with this parser, as the specified start symbol"
+    )?;
+    writeln!(
+        writer,
+        "/// Returns `None` when the start symbol is neither the axiom, nor an additional entry point"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn parse_str_as<'t>(start: &str, input: &'t str) -> Option<ParseResult<'static, 't, 'static, AstImpl>> {{"
+    )?;
+    writeln!(writer, "    select_parser(start).map(|parse| parse(input))")?;
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
 /// Generates the code for the symbols
 fn write_code_symbols(writer: &mut dyn Write, grammar: &Grammar) -> Result<(), Error> {
     for variable in grammar
@@ -205,11 +283,65 @@ fn write_code_virtuals(writer: &mut dyn Write, grammar: &Grammar) -> Result<(),
     Ok(())
 }
 
+/// Generates the code for the user-defined annotations on the symbols
+fn write_code_annotations(
+    writer: &mut dyn Write,
+    grammar: &Grammar,
+    expected: &TerminalSet,
+) -> Result<(), Error> {
+    writeln!(
+        writer,
+        "/// The user-defined annotations on the terminals and variables"
+    )?;
+    writeln!(writer, "pub const ANNOTATIONS: &[SymbolAnnotation] = &[")?;
+    for annotation in get_symbol_annotations(grammar, expected) {
+        writeln!(writer, "    SymbolAnnotation {{")?;
+        writeln!(writer, "        symbol_id: 0x{:04X},", annotation.symbol_id)?;
+        writeln!(writer, "        key: {:?},", annotation.key)?;
+        writeln!(writer, "        value: {:?}", annotation.value)?;
+        writeln!(writer, "    }},")?;
+    }
+    writeln!(writer, "];")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Gets the value of the annotation with the specified key on a terminal or a variable, if any"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn metadata(symbol_id: u32, key: &str) -> Option<&'static str> {{"
+    )?;
+    writeln!(writer, "    get_metadata(ANNOTATIONS, symbol_id, key)")?;
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Generates the code for the terminals to synchronize on during error recovery
+fn write_code_sync_terminals(writer: &mut dyn Write, grammar: &Grammar) -> Result<(), Error> {
+    writeln!(
+        writer,
+        "/// The identifiers of the terminals to synchronize on during panic-mode error recovery"
+    )?;
+    writeln!(
+        writer,
+        "/// This is metadata for the callers implementing a recovery, the parser does not use it."
+    )?;
+    write!(writer, "pub const SYNC_TERMINALS: &[u32] = &[")?;
+    for (index, terminal) in grammar.sync_terminals.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ", ")?;
+        }
+        write!(writer, "0x{:04X}", terminal.sid())?;
+    }
+    writeln!(writer, "];")?;
+    writeln!(writer)?;
+    Ok(())
+}
+
 /// Generates the code for the semantic actions
 fn write_code_actions(writer: &mut dyn Write, grammar: &Grammar) -> Result<(), Error> {
-    if grammar.actions.is_empty() {
-        return Ok(());
-    }
     writeln!(
         writer,
         "/// Represents a set of semantic actions in this parser"
@@ -234,23 +366,64 @@ fn write_code_actions(writer: &mut dyn Write, grammar: &Grammar) -> Result<(), E
     Ok(())
 }
 
+/// Generates the accessor to the parser's automaton
+/// With std, the automaton is deserialized on first use and shared by all the parses.
+fn write_code_parser_automaton(
+    writer: &mut dyn Write,
+    automaton_type: &str,
+    with_std: bool,
+    compress_automata: bool,
+) -> Result<(), Error> {
+    let automaton = format!(
+        "{automaton_type}::new(PARSER_AUTOMATON{})",
+        if compress_automata { ".as_ref()" } else { "" }
+    );
+    if with_std {
+        writeln!(
+            writer,
+            "/// Gets the parser's automaton, deserialized on first use"
+        )?;
+        writeln!(
+            writer,
+            "fn get_parser_automaton() -> &'static {automaton_type} {{"
+        )?;
+        writeln!(
+            writer,
+            "    static AUTOMATON: std::sync::OnceLock<{automaton_type}> = std::sync::OnceLock::new();"
+        )?;
+        writeln!(writer, "    AUTOMATON.get_or_init(|| {automaton})")?;
+    } else {
+        writeln!(writer, "/// Deserializes the parser's automaton")?;
+        writeln!(writer, "fn get_parser_automaton() -> {automaton_type} {{")?;
+        writeln!(writer, "    {automaton}")?;
+    }
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    Ok(())
+}
+
 /// Generates the code for the constructors
-#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
+#[allow(
+    clippy::too_many_lines,
+    clippy::too_many_arguments,
+    clippy::fn_params_excessive_bools
+)]
 fn write_code_constructors(
     writer: &mut dyn Write,
     grammar: &Grammar,
     output_assembly: bool,
     nmespace: &str,
-    automaton_type: &str,
+    start_state: usize,
     parser_type: &str,
     parser_ctor: &str,
     tree_type: &str,
     parse_result_type: &str,
     fn_suffix: &str,
     with_std: bool,
-    compress_automata: bool,
+    has_actions: bool,
 ) -> Result<(), Error> {
-    let has_actions = !grammar.actions.is_empty();
+    // without semantic actions, the LR(k) parser can skip the dispatcher altogether
+    let with_dispatcher = has_actions || parser_type != "LRkParser";
     writeln!(writer, "/// Parses the specified string with this parser")?;
     if output_assembly {
         writeln!(writer, "#[no_mangle]")?;
@@ -294,6 +467,92 @@ fn write_code_constructors(
         writeln!(writer, "}}")?;
     }
 
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Parses the specified string with this parser, computing the positions with a configuration"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn parse_str{fn_suffix}_with_config(input: &str, config: ParseConfig) -> ParseResult<'static, '_, 'static, {tree_type}> {{"
+    )?;
+    writeln!(
+        writer,
+        "    let text = Text::from_str(input).with_config(config);"
+    )?;
+    writeln!(
+        writer,
+        "    parse_text{fn_suffix}(text{})",
+        if has_actions {
+            ", &mut NoActions {}"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(writer, "}}")?;
+    if has_actions {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "/// Parses the specified string with this parser and semantic actions, computing the positions with a configuration"
+        )?;
+        writeln!(
+            writer,
+            "pub fn parse_str{fn_suffix}_with_config_and_actions<'t>(input: &'t str, config: ParseConfig, actions: &mut dyn Actions) -> ParseResult<'static, 't, 'static, {tree_type}> {{"
+        )?;
+        writeln!(
+            writer,
+            "    let text = Text::from_str(input).with_config(config);"
+        )?;
+        writeln!(writer, "    parse_text{fn_suffix}(text, actions)")?;
+        writeln!(writer, "}}")?;
+    }
+
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Parses the specified string with this parser, using the storage of an arena"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn parse_str{fn_suffix}_in<'r, 't>(arena: &'r mut ParseArena<'static, {tree_type}>, input: &'t str) -> ArenaParseResult<'r, 'static, 't, 'static, {tree_type}> {{"
+    )?;
+    writeln!(
+        writer,
+        "    let mut result = arena.new_result(TERMINALS, VARIABLES, VIRTUALS, input);"
+    )?;
+    writeln!(
+        writer,
+        "    parse_into{fn_suffix}(&mut result{});",
+        if has_actions {
+            ", &mut NoActions {}"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(writer, "    result")?;
+    writeln!(writer, "}}")?;
+    if has_actions {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "/// Parses the specified string with this parser, using the storage of an arena"
+        )?;
+        writeln!(
+            writer,
+            "pub fn parse_str{fn_suffix}_in_with<'r, 't>(arena: &'r mut ParseArena<'static, {tree_type}>, input: &'t str, actions: &mut dyn Actions) -> ArenaParseResult<'r, 'static, 't, 'static, {tree_type}> {{"
+        )?;
+        writeln!(
+            writer,
+            "    let mut result = arena.new_result(TERMINALS, VARIABLES, VIRTUALS, input);"
+        )?;
+        writeln!(writer, "    parse_into{fn_suffix}(&mut result, actions);")?;
+        writeln!(writer, "    result")?;
+        writeln!(writer, "}}")?;
+    }
+
     writeln!(writer)?;
     writeln!(writer, "/// Parses the specified string with this parser")?;
     if output_assembly {
@@ -396,10 +655,14 @@ fn write_code_constructors(
     }
 
     writeln!(writer)?;
-    writeln!(writer, "/// Parses the specified text with this parser")?;
     writeln!(
         writer,
-        "fn parse_text{fn_suffix}<'t>(text: Text<'t>{}) -> ParseResult<'static, 't, 'static, {tree_type}> {{",
+        "/// Parses the specified text with this parser, e.g. a text decoded from UTF-16 or Latin-1"
+    )?;
+    writeln!(writer, "#[must_use]")?;
+    writeln!(
+        writer,
+        "pub fn parse_text{fn_suffix}<'t>(text: Text<'t>{}) -> ParseResult<'static, 't, 'static, {tree_type}> {{",
         if has_actions {
             ", actions: &mut dyn Actions"
         } else {
@@ -423,6 +686,33 @@ fn write_code_constructors(
         writeln!(writer, "    actions: &mut dyn Actions")?;
     }
     writeln!(writer, ") -> ParseResult<'s, 't, 'a, {tree_type}> {{")?;
+    writeln!(
+        writer,
+        "    let mut result = ParseResult::<{tree_type}>::new(terminals, variables, virtuals, text);"
+    )?;
+    writeln!(
+        writer,
+        "    parse_into{fn_suffix}(&mut result{});",
+        if has_actions { ", actions" } else { "" }
+    )?;
+    writeln!(writer, "    result")?;
+    writeln!(writer, "}}")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "/// Parses the input of a parse result with this parser"
+    )?;
+    writeln!(
+        writer,
+        "fn parse_into{fn_suffix}(result: &mut ParseResult<'_, '_, '_, {tree_type}>{}) {{",
+        if has_actions {
+            ", actions: &mut dyn Actions"
+        } else {
+            ""
+        }
+    )?;
+    writeln!(writer, "    let variables = result.variables;")?;
+    writeln!(writer, "    let virtuals = result.virtuals;")?;
     if has_actions {
         writeln!(writer, "    let mut my_actions = |index: usize, head: Symbol, body: &dyn SemanticBody| match index {{")?;
         for (index, action) in grammar.actions.iter().enumerate() {
@@ -436,28 +726,50 @@ fn write_code_constructors(
         writeln!(writer, "        _ => ()")?;
         writeln!(writer, "    }};")?;
         writeln!(writer)?;
-    } else {
+    } else if with_dispatcher {
         writeln!(writer, "    let mut my_actions = |_index: usize, _head: Symbol, _body: &dyn SemanticBody| {{}};")?;
     }
     writeln!(
         writer,
-        "    let mut result = ParseResult::<{tree_type}>::new(terminals, variables, virtuals, text);"
+        "    let mut storage = result.take_parser_storage();"
     )?;
-    writeln!(writer, "    {{")?;
+    writeln!(writer, "    let statistics = {{")?;
     writeln!(writer, "        let data = result.get_parsing_data();")?;
-    writeln!(writer, "        let mut lexer = new_lexer(data.0, data.1);")?;
+    // with std, the automata are deserialized once and shared by all the parses
+    let borrow = if with_std { "" } else { "&" };
     writeln!(
         writer,
-        "        let automaton = {automaton_type}::new(PARSER_AUTOMATON{});",
-        if compress_automata { ".as_ref()" } else { "" }
+        "        let lexer_automaton = {borrow}get_lexer_automaton();"
+    )?;
+    writeln!(
+        writer,
+        "        let mut lexer = new_lexer(data.0, data.1, lexer_automaton);"
     )?;
     writeln!(
         writer,
-        "        let mut parser = {parser_type}::{parser_ctor}(&mut lexer, variables, virtuals, automaton, data.2, &mut my_actions);"
+        "        let automaton = {borrow}get_parser_automaton();"
     )?;
+    if with_dispatcher {
+        writeln!(
+            writer,
+            "        let mut parser = {parser_type}::{parser_ctor}(&mut lexer, variables, virtuals, automaton, data.2, &mut my_actions);"
+        )?;
+    } else {
+        writeln!(
+            writer,
+            "        let mut parser = {parser_type}::new_without_actions(&mut lexer, variables, virtuals, automaton, data.2);"
+        )?;
+    }
+    writeln!(writer, "        parser.use_storage(&mut storage);")?;
+    if start_state != 0 {
+        writeln!(writer, "        parser.set_start_state({start_state});")?;
+    }
     writeln!(writer, "        parser.parse();")?;
-    writeln!(writer, "    }}")?;
-    writeln!(writer, "    result")?;
+    writeln!(writer, "        parser.release_storage(&mut storage);")?;
+    writeln!(writer, "        parser.get_statistics()")?;
+    writeln!(writer, "    }};")?;
+    writeln!(writer, "    result.set_parser_storage(storage);")?;
+    writeln!(writer, "    result.set_statistics(statistics);")?;
     writeln!(writer, "}}")?;
     Ok(())
 }
@@ -17,19 +17,26 @@
 
 //! Module for SDK utilities
 
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+
 use hime_redist::ast::AstImpl;
-use hime_redist::errors::ParseErrors;
+use hime_redist::errors::{ParseError, ParseErrors};
 use hime_redist::lexers::automaton::Automaton;
 use hime_redist::lexers::impls::{ContextFreeLexer, ContextSensitiveLexer};
-use hime_redist::lexers::Lexer;
+use hime_redist::lexers::{DefaultContextProvider, ExternalScanner, ExternalTerminal, Lexer};
 use hime_redist::parsers::lrk::{LRkAutomaton, LRkParser};
-use hime_redist::parsers::rnglr::{RNGLRAutomaton, RNGLRParser};
-use hime_redist::parsers::Parser;
-use hime_redist::result::ParseResult;
-use hime_redist::symbols::{SemanticBody, Symbol};
-use hime_redist::text::Text;
+use hime_redist::parsers::rnglr::{GSSStatistics, RNGLRAutomaton, RNGLRParser};
+use hime_redist::parsers::{Parser, ParserStorage};
+use hime_redist::result::{ArenaParseResult, ParseArena, ParseResult, ParseStatistics};
+use hime_redist::symbols::{get_metadata, SemanticActions, SemanticBody, Symbol, SymbolAnnotation};
+use hime_redist::text::{ParseConfig, Text};
 use hime_redist::tokens::TokenRepository;
 
+use crate::grammars::{Grammar, RuleRef};
+use crate::lr::{Graph, Phrase};
+
 /// The automaton for a parser
 #[derive(Clone)]
 pub enum ParserAutomaton {
@@ -39,6 +46,28 @@ pub enum ParserAutomaton {
     Rnglr(RNGLRAutomaton),
 }
 
+/// What an in-memory parser records while parsing, in addition to the parse tree
+#[derive(Debug, Default, Copy, Clone)]
+struct Recording {
+    /// Whether to record the number of reductions for each production
+    rule_counts: bool,
+    /// Whether to record statistics on the GSS
+    gss_statistics: bool,
+    /// Whether to measure the time spent lexing and parsing
+    timings: bool,
+}
+
+/// Represents a terminal that is matched by an external scanner in an in-memory parser
+#[derive(Clone)]
+pub struct InMemoryExternalTerminal {
+    /// The path to the Rust function that matches the terminal in generated parsers
+    pub function: String,
+    /// The index of the terminal in the table of terminals
+    pub terminal: usize,
+    /// The scanner bound to the terminal, if any
+    pub scanner: Option<Rc<dyn ExternalScanner>>,
+}
+
 /// Represents complete data for a parser
 #[derive(Clone)]
 pub struct InMemoryParser<'s> {
@@ -46,6 +75,10 @@ pub struct InMemoryParser<'s> {
     pub name: &'s str,
     /// The expected terminals
     pub terminals: Vec<Symbol<'s>>,
+    /// The display names of the terminals that have one, by symbol identifier, used in errors
+    pub aliases: Vec<(u32, &'s str)>,
+    /// The user-defined annotations on the terminals and variables
+    pub annotations: Vec<SymbolAnnotation<'s>>,
     /// The variables
     pub variables: Vec<Symbol<'s>>,
     /// The virtuals
@@ -56,52 +89,317 @@ pub struct InMemoryParser<'s> {
     pub lexer_automaton: Automaton,
     /// Whether the lexer is context-sensitive
     pub lexer_is_context_sensitive: bool,
+    /// The names of the lexical contexts, indexed by their identifier
+    pub contexts: Vec<String>,
     /// The parser's automaton
     pub parser_automaton: ParserAutomaton,
+    /// The initial state in the parser's automaton for the axiom and each additional entry point
+    /// (`variable_name`, `state`)
+    pub starts: Vec<(String, u32)>,
+    /// The grammar rule for each production of the parser's automata
+    pub productions: Vec<RuleRef>,
+    /// Whether the parser stops on the first error raised by a semantic action
+    /// By default, the error is recorded and the parser continues.
+    pub stop_on_semantic_error: bool,
+    /// The configuration for the parsing of the inputs given as strings, e.g. the tab width or the fork budget
+    /// The inputs given as texts and those parsed in an arena keep their own configuration.
+    pub config: ParseConfig,
+    /// The terminals matched by external scanners
+    /// The terminals without a bound scanner are never matched.
+    pub external_terminals: Vec<InMemoryExternalTerminal>,
 }
 
 impl<'s> InMemoryParser<'s> {
+    /// Gets the name of the lexical context with the specified identifier, if any
+    #[must_use]
+    pub fn get_context_name(&self, id: u16) -> Option<&str> {
+        self.contexts.get(id as usize).map(String::as_str)
+    }
+
+    /// Gets the value of the annotation with the specified key on a terminal or a variable, if any
+    #[must_use]
+    pub fn metadata(&self, symbol_id: u32, key: &str) -> Option<&'s str> {
+        get_metadata(&self.annotations, symbol_id, key)
+    }
+
+    /// Binds a scanner to the external terminals declared with the specified Rust function
+    /// Returns whether a terminal was found for the function.
+    pub fn bind_external_scanner<S>(&mut self, function: &str, scanner: S) -> bool
+    where
+        S: ExternalScanner + 'static,
+    {
+        let scanner: Rc<dyn ExternalScanner> = Rc::new(scanner);
+        let mut found = false;
+        for external in &mut self.external_terminals {
+            if external.function == function {
+                external.scanner = Some(scanner.clone());
+                found = true;
+            }
+        }
+        found
+    }
+
     /// Parses an input parser
     #[must_use]
     pub fn parse<'a, 't>(&'a self, input: &'t str) -> ParseResult<'s, 't, 'a, AstImpl> {
-        let text = Text::from_str(input);
-        let mut result =
-            ParseResult::<AstImpl>::new(&self.terminals, &self.variables, &self.virtuals, text);
-        let mut my_actions = |_index: usize, _head: Symbol, _body: &dyn SemanticBody| ();
+        self.parse_with(0, input, Recording::default())
+    }
+
+    /// Parses an input text, e.g. a text decoded from UTF-16 or Latin-1
+    /// The invalid sequences replaced when decoding the text are reported as errors.
+    #[must_use]
+    pub fn parse_text<'a, 't>(&'a self, text: Text<'t>) -> ParseResult<'s, 't, 'a, AstImpl> {
+        self.parse_text_with(0, text, Recording::default())
+    }
+
+    /// Parses an input parser, using the storage of an arena
+    /// Reusing the same arena across parses avoids allocating the storage for each parse.
+    /// The positions in the input are computed with the configuration of the arena.
+    #[must_use]
+    pub fn parse_in<'r, 'a, 't>(
+        &'a self,
+        arena: &'r mut ParseArena<'s, AstImpl>,
+        input: &'t str,
+    ) -> ArenaParseResult<'r, 's, 't, 'a, AstImpl> {
+        let mut result = arena.new_result(&self.terminals, &self.variables, &self.virtuals, input);
+        self.parse_into(0, &mut result, None, Recording::default());
+        result
+    }
+
+    /// Parses an input parser and executes the semantic actions of the grammar
+    /// The actions are given by their index in the grammar.
+    /// The errors raised by the actions are reported as parse errors.
+    #[must_use]
+    pub fn parse_with_actions<'a, 't>(
+        &'a self,
+        input: &'t str,
+        actions: &mut dyn SemanticActions,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let mut result = ParseResult::<AstImpl>::new(
+            &self.terminals,
+            &self.variables,
+            &self.virtuals,
+            Text::from_str(input).with_config(self.config),
+        );
+        self.parse_into(0, &mut result, Some(actions), Recording::default());
+        result
+    }
+
+    /// Parses an input parser and records the number of reductions for each production
+    /// The grammar rule for each production is given by `productions`
+    #[must_use]
+    pub fn parse_with_rule_counts<'a, 't>(
+        &'a self,
+        input: &'t str,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let recording = Recording {
+            rule_counts: true,
+            ..Recording::default()
+        };
+        self.parse_with(0, input, recording)
+    }
+
+    /// Parses an input parser and records statistics on the splits and merges of the GSS
+    /// The statistics are only recorded for RNGLR parsers.
+    #[must_use]
+    pub fn parse_with_gss_statistics<'a, 't>(
+        &'a self,
+        input: &'t str,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let recording = Recording {
+            gss_statistics: true,
+            ..Recording::default()
+        };
+        self.parse_with(0, input, recording)
+    }
+
+    /// Parses an input parser and measures the time spent lexing and parsing
+    /// The timings are reported in the statistics of the result.
+    /// They are specific to in-memory parsers, generated parsers do not measure them.
+    #[must_use]
+    pub fn parse_with_timings<'a, 't>(
+        &'a self,
+        input: &'t str,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let recording = Recording {
+            timings: true,
+            ..Recording::default()
+        };
+        self.parse_with(0, input, recording)
+    }
+
+    /// Runs only the lexer on an input, without parsing it
+    /// The matched tokens and the lexical errors are in the result, which has no parse tree.
+    /// As there is no parser to drive a context-sensitive lexer,
+    /// all the lexical contexts are active, the default one having the least priority.
+    #[must_use]
+    pub fn tokenize<'a, 't>(&'a self, input: &'t str) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let mut result = ParseResult::<AstImpl>::new(
+            &self.terminals,
+            &self.variables,
+            &self.virtuals,
+            Text::from_str(input).with_config(self.config),
+        );
         {
             let data = result.get_parsing_data();
             let mut lexer = self.new_lexer(data.0, data.1);
-            self.do_parse(&mut lexer, data.2, &mut my_actions);
+            let contexts = DefaultContextProvider {};
+            while lexer.get_next_token(&contexts).is_some() {}
         }
         result
     }
 
+    /// Gets the number of reductions for each grammar rule,
+    /// from the number of reductions for each production of the parser's automata
+    #[must_use]
+    pub fn get_rule_counts(&self, counts: &[usize]) -> HashMap<RuleRef, usize> {
+        let mut result = HashMap::new();
+        for (rule_ref, count) in self.productions.iter().zip(counts) {
+            *result.entry(*rule_ref).or_insert(0) += count;
+        }
+        result
+    }
+
+    /// Parses an input as the specified start symbol,
+    /// either the grammar's axiom or an additional entry point
+    /// Returns `None` when the start symbol is neither
+    /// This is the in-memory counterpart of `select_parser` in the generated Rust parsers.
+    #[must_use]
+    pub fn parse_as<'a, 't>(
+        &'a self,
+        start: &str,
+        input: &'t str,
+    ) -> Option<ParseResult<'s, 't, 'a, AstImpl>> {
+        self.starts
+            .iter()
+            .find(|(name, _)| name == start)
+            .map(|&(_, state)| self.parse_with(state, input, Recording::default()))
+    }
+
+    /// Parses an input starting from the specified state of the parser automaton
+    fn parse_with<'a, 't>(
+        &'a self,
+        start_state: u32,
+        input: &'t str,
+        recording: Recording,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        self.parse_text_with(
+            start_state,
+            Text::from_str(input).with_config(self.config),
+            recording,
+        )
+    }
+
+    /// Parses an input text starting from the specified state of the parser automaton
+    fn parse_text_with<'a, 't>(
+        &'a self,
+        start_state: u32,
+        text: Text<'t>,
+        recording: Recording,
+    ) -> ParseResult<'s, 't, 'a, AstImpl> {
+        let mut result =
+            ParseResult::<AstImpl>::new(&self.terminals, &self.variables, &self.virtuals, text);
+        self.parse_into(start_state, &mut result, None, recording);
+        result
+    }
+
+    /// Parses an input in a result
+    fn parse_into<'a>(
+        &'a self,
+        start_state: u32,
+        result: &mut ParseResult<'s, '_, 'a, AstImpl>,
+        actions: Option<&mut dyn SemanticActions>,
+        recording: Recording,
+    ) {
+        let mut no_actions = |_index: usize, _head: Symbol, _body: &dyn SemanticBody| ();
+        // shorten the lifetime of the actions to the one of the parser
+        let actions = actions.map(|actions| actions as &mut dyn SemanticActions);
+        let mut storage = result.take_parser_storage();
+        let (rule_counts, gss_statistics, statistics) = {
+            let data = result.get_parsing_data();
+            let mut lexer = self.new_lexer(data.0, data.1);
+            if recording.timings {
+                lexer.record_lexing_time();
+            }
+            self.do_parse(
+                start_state,
+                &mut lexer,
+                data.2,
+                actions,
+                &mut no_actions,
+                &mut storage,
+                recording,
+            )
+        };
+        result.set_parser_storage(storage);
+        result.set_rule_counts(rule_counts);
+        result.set_gss_statistics(gss_statistics);
+        result.set_statistics(statistics);
+    }
+
     /// Execute the parser
+    /// Without semantic actions, the LR(k) parser does not dispatch the reductions to them,
+    /// whereas the RNGLR parser dispatches them to `no_actions`.
+    #[allow(clippy::too_many_arguments)]
     fn do_parse<'a, 't>(
         &'a self,
+        start_state: u32,
         lexer: &'a mut Lexer<'s, 't, 'a>,
         ast: &'a mut AstImpl,
-        actions: &'a mut dyn FnMut(usize, Symbol, &dyn SemanticBody),
-    ) {
+        actions: Option<&'a mut dyn SemanticActions>,
+        no_actions: &'a mut dyn SemanticActions,
+        storage: &mut ParserStorage,
+        recording: Recording,
+    ) -> (Option<Vec<usize>>, Option<GSSStatistics>, ParseStatistics) {
         let mut parser: Box<dyn Parser> = match &self.parser_automaton {
-            ParserAutomaton::Lrk(automaton) => Box::new(LRkParser::new(
-                lexer,
-                &self.variables,
-                &self.virtuals,
-                automaton.clone(),
-                ast,
-                actions,
-            )),
+            ParserAutomaton::Lrk(automaton) => Box::new(match actions {
+                Some(actions) => LRkParser::new(
+                    lexer,
+                    &self.variables,
+                    &self.virtuals,
+                    automaton,
+                    ast,
+                    actions,
+                ),
+                None => LRkParser::new_without_actions(
+                    lexer,
+                    &self.variables,
+                    &self.virtuals,
+                    automaton,
+                    ast,
+                ),
+            }),
             ParserAutomaton::Rnglr(automaton) => Box::new(RNGLRParser::new_with_ast(
                 lexer,
                 &self.variables,
                 &self.virtuals,
-                automaton.clone(),
+                automaton,
                 ast,
-                actions,
+                actions.unwrap_or(no_actions),
             )),
         };
+        parser.use_storage(storage);
+        parser.set_start_state(start_state);
+        parser.set_stop_on_semantic_error(self.stop_on_semantic_error);
+        if recording.rule_counts {
+            parser.record_rule_counts();
+        }
+        if recording.gss_statistics {
+            parser.record_gss_statistics();
+        }
+        let start = Instant::now();
         parser.parse();
+        let elapsed = start.elapsed();
+        let mut statistics = parser.get_statistics();
+        if let Some(lexing_time) = statistics.lexing_time {
+            statistics.parsing_time = Some(elapsed.saturating_sub(lexing_time));
+        }
+        parser.release_storage(storage);
+        (
+            parser.take_rule_counts(),
+            parser.take_gss_statistics(),
+            statistics,
+        )
     }
 
     /// Creates a new lexer
@@ -110,20 +408,61 @@ impl<'s> InMemoryParser<'s> {
         repository: TokenRepository<'s, 't, 'a>,
         errors: &'a mut ParseErrors<'s>,
     ) -> Lexer<'s, 't, 'a> {
-        if self.lexer_is_context_sensitive {
-            Lexer::ContextFree(ContextFreeLexer::new(
+        let mut lexer = if self.lexer_is_context_sensitive {
+            Lexer::ContextSensitive(ContextSensitiveLexer::new(
                 repository,
                 errors,
-                self.lexer_automaton.clone(),
+                &self.lexer_automaton,
                 self.separator,
             ))
         } else {
-            Lexer::ContextSensitive(ContextSensitiveLexer::new(
+            Lexer::ContextFree(ContextFreeLexer::new(
                 repository,
                 errors,
-                self.lexer_automaton.clone(),
+                &self.lexer_automaton,
                 self.separator,
             ))
+        };
+        lexer.get_data_mut().aliases.clone_from(&self.aliases);
+        lexer.get_data_mut().externals = self
+            .external_terminals
+            .iter()
+            .filter_map(|external| {
+                Some(ExternalTerminal {
+                    terminal: external.terminal,
+                    scanner: external.scanner.as_deref()?,
+                })
+            })
+            .collect();
+        lexer
+    }
+}
+
+/// Suggestions of valid input for parse errors
+pub trait ParseErrorSuggestions {
+    /// Gets up to `max` short phrases that the parser could have accepted instead,
+    /// from the error's position up to the end of the input, shortest first
+    /// The graph must be the one the parser was built from,
+    /// and the parser must capture its stacks (see `ParseConfig::capture_stacks`).
+    fn suggestions(&self, grammar: &Grammar, graph: &Graph, max: usize) -> Vec<Phrase>;
+}
+
+impl ParseErrorSuggestions for ParseError<'_> {
+    fn suggestions(&self, grammar: &Grammar, graph: &Graph, max: usize) -> Vec<Phrase> {
+        let ParseError::UnexpectedToken(error) = self else {
+            return Vec::new();
+        };
+        let mut results: Vec<Phrase> = Vec::new();
+        for stack in error.get_stacks() {
+            let stack: Vec<usize> = stack.iter().map(|state| *state as usize).collect();
+            for phrase in graph.get_completions_from(&stack, grammar, max) {
+                if !results.contains(&phrase) {
+                    results.push(phrase);
+                }
+            }
         }
+        results.sort_by_key(Phrase::len);
+        results.truncate(max);
+        results
     }
 }
@@ -0,0 +1,72 @@
+//! Tests on the allocations of repeated parses
+//! These tests are in their own binary because they count the allocations of the whole process.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hime_redist::result::ParseArena;
+use hime_sdk::{CompilationTask, Input};
+
+/// An allocator that counts the allocated bytes
+struct CountingAllocator;
+
+/// The number of bytes allocated so far
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Gets the number of bytes allocated by an action
+fn allocated_by<T>(action: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let result = action();
+    (result, ALLOCATED.load(Ordering::Relaxed) - before)
+}
+
+#[test]
+fn test_repeated_parses_reuse_automata() {
+    // many keywords for automata much larger than the input
+    let keywords: Vec<String> = (0..100).map(|i| format!("K{i} -> 'keyword{i}';")).collect();
+    let alternatives: Vec<String> = (0..100).map(|i| format!("K{i}")).collect();
+    let content = format!(
+        "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ {} }} rules {{ e -> e k | k ; k -> {} ; }} }}",
+        keywords.join(" "),
+        alternatives.join(" | ")
+    );
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(&content)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let (_, automata) = allocated_by(|| {
+        (
+            parser.lexer_automaton.clone(),
+            parser.parser_automaton.clone(),
+        )
+    });
+    let mut arena = ParseArena::new();
+    let mut parse =
+        || allocated_by(|| parser.parse_in(&mut arena, "keyword1keyword2").is_success());
+    assert!(parse().0);
+    let (success, first) = parse();
+    assert!(success);
+    let (success, second) = parse();
+    assert!(success);
+    // the repeated parses allocate the same and do not deserialize nor copy the automata
+    assert_eq!(first, second);
+    assert!(second < automata, "{second} bytes allocated per parse");
+}
@@ -0,0 +1,2523 @@
+use std::cell::RefCell;
+
+use hime_redist::ast::{AstImpl, AstNode};
+use hime_redist::errors::{ParseError, ParseErrorDataTrait};
+use hime_redist::parsers::{TREE_ACTION_DROP, TREE_ACTION_NONE};
+use hime_redist::result::{ParseArena, ParseResult};
+use hime_redist::symbols::{SemanticBody, SemanticElementTrait, SemanticError, Symbol};
+use hime_redist::text::{ParseConfig, Text, TextPosition, TextSpan};
+use hime_sdk::builder::GrammarBuilder;
+use hime_sdk::errors::Error;
+use hime_sdk::grammars::{RuleRef, SymbolRef, TerminalRef, TerminalSet};
+use hime_sdk::sdk::ParseErrorSuggestions;
+use hime_sdk::{CompilationProgress, CompilationTask, Input, Mode, ParsingMethod, Runtime};
+
+#[test]
+fn test_find_nullable_unit_rules() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> b | ; b -> A* ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let warnings = grammar.find_nullable_unit_rules(0);
+    assert_eq!(warnings.len(), 1);
+    let Error::NullableUnitRule(0, rule, conflicting) = &warnings[0] else {
+        panic!("expected a nullable unit rule");
+    };
+    let e = grammar.get_variable_for_name("e").unwrap().id;
+    assert_eq!(rule.variable, e);
+    assert_eq!(conflicting.variable, e);
+    assert_ne!(rule.index, conflicting.index);
+}
+
+#[test]
+fn test_find_axiom_references() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> x ; x -> A | '(' e ')' | o e ; o -> '(' | ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let warnings = grammar.find_axiom_references(0);
+    // `e` derives itself through `x -> o e`, but not through `x -> '(' e ')'`
+    assert_eq!(warnings.len(), 1);
+    let Error::AxiomReferenced(0, rule, 1) = &warnings[0] else {
+        panic!("expected a reference to the axiom");
+    };
+    assert_eq!(
+        *rule,
+        RuleRef::new(grammar.get_variable_for_name("x").unwrap().id, 2)
+    );
+}
+
+#[test]
+fn test_shortest_sentence() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e B | x ; x -> A A x | B ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let Some(SymbolRef::Terminal(b)) = grammar.get_symbol("B") else {
+        panic!("terminal B not found");
+    };
+    assert_eq!(
+        grammar.shortest_sentence(),
+        Some(vec![TerminalRef::Terminal(b)])
+    );
+}
+
+#[test]
+fn test_shortest_sentence_non_productive() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A e ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    assert_eq!(data.grammars[0].shortest_sentence(), None);
+}
+
+#[test]
+fn test_entry_points() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Entries = \"x\"; } terminals { A -> 'a'; } rules { e -> x ';' ; x -> A '+' A | A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    assert_eq!(build.entries.len(), 1);
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser.parse("a+a;").is_success());
+    assert!(!parser.parse("a+a").is_success());
+    assert!(parser.parse_as("x", "a+a").unwrap().is_success());
+    assert!(!parser.parse_as("x", "a+a;").unwrap().is_success());
+    assert!(parser.parse_as("y", "a").is_none());
+}
+
+#[test]
+fn test_entry_point_directives() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"file\"; Entries = \"expr\"; } terminals { A -> 'a'; } rules { file -> stmt+ ; stmt -> expr ';' ; expr -> A ('+' A)* ; %entry_point stmt; %entry_point expr; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    // the directives add to the option, without duplicating the entry points it already declares
+    assert_eq!(grammar.get_entries(), ["expr", "stmt"]);
+    let build = grammar.build(None, 0).unwrap();
+    assert_eq!(build.entries.len(), 2);
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser.parse_as("file", "a;a+a;").unwrap().is_success());
+    assert!(parser.parse_as("stmt", "a+a;").unwrap().is_success());
+    assert!(!parser.parse_as("stmt", "a+a").unwrap().is_success());
+    assert!(parser.parse_as("expr", "a+a").unwrap().is_success());
+    assert!(!parser.parse_as("expr", "a;").unwrap().is_success());
+
+    // entry points must be variables
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; %entry_point x; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let errors = data.grammars[0].build(None, 0).unwrap_err();
+    assert!(matches!(&errors[..], [Error::EntryNotDefined(0, name)] if name == "x"));
+}
+
+#[test]
+fn test_parse_as() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"file\"; Entries = \"stmt\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ file -> stmt+ ; stmt -> A ';' ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        // a single graph, with an initial state for the axiom and one for the entry point
+        assert_eq!(build.entries[0].state, 1);
+        let parser = grammar.get_in_memory(&build).unwrap();
+        // the same input, parsed from the axiom and from the entry point
+        let as_file = parser.parse_as("file", "a;").unwrap();
+        assert!(as_file.errors.errors.is_empty());
+        assert_eq!(as_file.get_ast().get_root().get_symbol().name, "file");
+        let as_stmt = parser.parse_as("stmt", "a;").unwrap();
+        assert!(as_stmt.errors.errors.is_empty());
+        assert_eq!(as_stmt.get_ast().get_root().get_symbol().name, "stmt");
+        assert!(parser
+            .parse_as("file", "a;a;")
+            .unwrap()
+            .errors
+            .errors
+            .is_empty());
+        assert!(!parser
+            .parse_as("stmt", "a;a;")
+            .unwrap()
+            .errors
+            .errors
+            .is_empty());
+        assert!(parser.parse_as("other", "a;").is_none());
+    }
+}
+
+#[test]
+fn test_parse_empty_input() {
+    for method in ["lr0", "lr1", "lalr1", "ielr1", "rnglr1", "rnglalr1"] {
+        for rules in [
+            "file -> ;",
+            "file -> entries ; entries -> entries entry | ; entry -> ID '=' ID ;",
+        ] {
+            let content = format!(
+                "grammar Test {{ options {{ Axiom = \"file\"; Method = \"{method}\"; }} terminals {{ ID -> [a-z]+; }} rules {{ {rules} }} }}"
+            );
+            let task = CompilationTask {
+                inputs: vec![Input::Raw(&content)],
+                ..CompilationTask::default()
+            };
+            let mut data = task.load().unwrap();
+            let grammar = &mut data.grammars[0];
+            if method == "lr0" && rules != "file -> ;" {
+                // the reduction of the empty list genuinely conflicts with the shift of its first entry
+                let errors = grammar.build(None, 0).unwrap_err();
+                assert!(matches!(errors[..], [Error::LrConflict(_, _)]));
+                continue;
+            }
+            let build = grammar.build(None, 0).unwrap();
+            let parser = grammar.get_in_memory(&build).unwrap();
+            let result = parser.parse("");
+            assert!(result.errors.errors.is_empty(), "{method}: {rules}");
+            let ast = result.get_ast();
+            let root = ast.get_root();
+            assert_eq!(root.get_symbol().name, "file", "{method}: {rules}");
+            if rules == "file -> ;" {
+                assert_eq!(root.children().len(), 0, "{method}: {rules}");
+            }
+            // non-empty inputs are still parsed when the grammar allows them
+            assert_eq!(
+                parser.parse("a = b").is_success(),
+                rules != "file -> ;",
+                "{method}: {rules}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_entry_points_conflicts() {
+    // with LR(1), the state after `A` from the entry point is not merged with the one from the axiom
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Entries = \"r\"; Method = \"lr1\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> p B | q C ; r -> p | q ; p -> A ; q -> A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let errors = grammar.build(None, 0).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    let Error::LrConflict(0, conflict) = &errors[0] else {
+        panic!("expected a conflict");
+    };
+    let r = grammar.get_variable_for_name("r").unwrap().id;
+    assert_eq!(conflict.entry, Some(r));
+}
+
+#[test]
+fn test_entry_points_not_defined() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Entries = \"e, y\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let errors = data.grammars[0].build(None, 0).unwrap_err();
+    assert!(matches!(&errors[0], Error::EntryNotDefined(0, name) if name == "y"));
+}
+
+#[test]
+fn test_enumerate_sentences() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e B | x ; x -> x x | ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let Some(SymbolRef::Terminal(a)) = grammar.get_symbol("A") else {
+        panic!("terminal A not found");
+    };
+    let Some(SymbolRef::Terminal(b)) = grammar.get_symbol("B") else {
+        panic!("terminal B not found");
+    };
+    let (a, b) = (TerminalRef::Terminal(a), TerminalRef::Terminal(b));
+    let sentences: Vec<Vec<TerminalRef>> = grammar.enumerate_sentences(4).collect();
+    assert_eq!(sentences, vec![vec![], vec![a, b], vec![a, a, b, b]]);
+}
+
+#[test]
+fn test_enumerate_sentences_nullable_variables() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> x x x x x A ; x -> ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let Some(SymbolRef::Terminal(a)) = grammar.get_symbol("A") else {
+        panic!("terminal A not found");
+    };
+    let sentences: Vec<Vec<TerminalRef>> = grammar.enumerate_sentences(1).collect();
+    assert_eq!(sentences, vec![vec![TerminalRef::Terminal(a)]]);
+}
+
+#[test]
+fn test_stable_ids_unchanged_by_new_terminal() {
+    let load = |terminals: &str| {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; StableIds = \"true\"; }} terminals {{ {terminals} }} rules {{ e -> A (x ',')* B ; x -> C | 'c' ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let mut grammar = data.grammars.remove(0);
+        grammar.build(None, 0).unwrap();
+        grammar
+    };
+    let before = load("A -> 'a'; B -> 'b'; C -> 'x';");
+    let after = load("A -> 'a'; N -> 'n'; B -> 'b'; C -> 'x';");
+    assert!(after.get_terminal_for_name("N").is_some());
+    for terminal in &before.terminals {
+        assert_eq!(
+            after.get_terminal_for_value(&terminal.value).unwrap().id,
+            terminal.id
+        );
+    }
+    for variable in &before.variables {
+        assert_eq!(
+            after.get_variable_for_name(&variable.name).unwrap().id,
+            variable.id
+        );
+    }
+}
+
+#[test]
+fn test_stable_ids_kept_on_hash_collision() {
+    // the identifier a key hashes to, as assigned by the grammar
+    let hash = |key: &str| {
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        3 + hash % 0xFFFC
+    };
+    // a new terminal that collides with Z and is placed before it
+    let target = hash("terminal Z");
+    let colliding = (0..)
+        .map(|i| format!("A{i}"))
+        .find(|name| hash(&format!("terminal {name}")) == target)
+        .unwrap();
+    let load = |terminals: &str| {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; StableIds = \"true\"; }} terminals {{ {terminals} }} rules {{ e -> Z ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        data.grammars.remove(0)
+    };
+    let mut before = load("Z -> 'z';");
+    before.build(None, 0).unwrap();
+    let z = before.get_terminal_for_name("Z").unwrap().id;
+    assert_eq!(z as u64, target);
+
+    // without the previous identifiers, the new terminal takes the identifier of Z
+    let mut fresh = load(&format!("Z -> 'z'; {colliding} -> 'y';"));
+    fresh.build(None, 0).unwrap();
+    assert_ne!(fresh.get_terminal_for_name("Z").unwrap().id, z);
+
+    // with them, Z keeps its identifier
+    let mut after = load(&format!("Z -> 'z'; {colliding} -> 'y';"));
+    after.read_stable_ids(&before.write_stable_ids());
+    after.build(None, 0).unwrap();
+    assert_eq!(after.get_terminal_for_name("Z").unwrap().id, z);
+    assert_ne!(after.get_terminal_for_name(&colliding).unwrap().id, z);
+    for variable in &before.variables {
+        assert_eq!(
+            after.get_variable_for_name(&variable.name).unwrap().id,
+            variable.id
+        );
+    }
+}
+
+#[test]
+fn test_stable_ids_persisted_between_generations() {
+    let folder = hime_sdk::output::temporary_folder();
+    std::fs::create_dir_all(&folder).unwrap();
+    let generate = |terminals: &str| {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; StableIds = \"true\"; }} terminals {{ {terminals} }} rules {{ e -> A B ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            mode: Some(Mode::Sources),
+            output_target: Some(Runtime::Rust),
+            output_path: Some(folder.to_str().unwrap().to_string()),
+            ..CompilationTask::default()
+        };
+        task.execute().unwrap();
+        std::fs::read_to_string(folder.join("test.ids")).unwrap()
+    };
+    let before = generate("A -> 'a'; B -> 'b';");
+    let after = generate("A -> 'a'; N -> 'n'; B -> 'b';");
+    for line in before.lines() {
+        assert!(after.lines().any(|other| other == line));
+    }
+    assert!(after.lines().any(|line| line.ends_with(" terminal N")));
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_stable_ids_keep_lexical_priority() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; StableIds = \"true\"; } terminals { WS -> ' '+; ID -> [a-z]+; } rules { e -> 'if' ID ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser.parse("if x").is_success());
+    assert!(!parser.parse("x if").is_success());
+}
+
+#[test]
+fn test_compact_dfa_disjoint_contexts() {
+    // the reserved words are never expected in their contexts, only `ID` can be matched
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { context first { RESERVED_1 -> 'alpha' | 'beta' | 'gamma'; } context second { RESERVED_2 -> 'delta' | 'epsilon' | 'zeta'; } WS -> ' '+; ID -> [a-z]+; } rules { e -> ID #first { ID } #second { ID } ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let statistics = build.lexer_statistics;
+    assert_eq!(statistics.states_after, 3);
+    assert!(statistics.states_after < statistics.states_before);
+    assert!(statistics.bytes_after * 5 < statistics.bytes_before);
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser.parse("alpha delta zeta").is_success());
+    assert!(!parser.parse("alpha delta").is_success());
+}
+
+#[test]
+fn test_execute_with_warnings() {
+    let folder = hime_sdk::output::temporary_folder();
+    std::fs::create_dir_all(&folder).unwrap();
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Base { options { Axiom = \"e\"; Method = \"rnglalr1\"; } terminals { B -> 'if'; A -> [a-z]+; context dead { C -> 'c'; } } rules { e -> b | A | '(' e ')' ; b -> A* ; } }",
+            ),
+            Input::Raw("grammar Derived : Base { options { } terminals { A -> 'x'; } rules { } }"),
+        ],
+        mode: Some(Mode::Sources),
+        output_target: Some(Runtime::Rust),
+        output_path: Some(folder.to_str().unwrap().to_string()),
+        ..CompilationTask::default()
+    };
+    let (_, warnings) = task.execute_with_warnings().unwrap();
+    std::fs::remove_dir_all(&folder).unwrap();
+    let count = |predicate: &dyn Fn(&Error) -> bool| {
+        warnings.iter().filter(|warning| predicate(warning)).count()
+    };
+    // the nested reference to the axiom does not make it derive itself
+    assert_eq!(count(&|w| matches!(w, Error::AxiomReferenced(_, _, _))), 0);
+    assert_eq!(count(&|w| matches!(w, Error::NullableUnitRule(_, _, _))), 2);
+    assert_eq!(count(&|w| matches!(w, Error::UnusedContext(_, _))), 2);
+    assert_eq!(count(&|w| matches!(w, Error::TerminalShadowed(0, _))), 1);
+    assert_eq!(
+        count(&|w| matches!(w, Error::RedefinedTerminal(_, name, _) if name == "A")),
+        1
+    );
+}
+
+#[test]
+fn test_find_unused_contexts() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; context used { B -> 'b'; } context dead { C -> 'c'; } } rules { e -> A #used { B } ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let dead = grammar.contexts.iter().position(|c| c == "dead").unwrap();
+    assert_eq!(build.graph.unused_contexts(grammar), vec![dead]);
+    let warnings = grammar.find_unused_contexts(0, &build);
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(warnings[0], Error::UnusedContext(0, context) if context == dead));
+}
+
+#[test]
+fn test_rule_counts() {
+    for method in ["lalr1", "rnglalr1"] {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; B -> 'b'; C -> 'c'; }} rules {{ e -> x! y ; x -> A | B ; y -> C | ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        assert!(parser.parse("ac").rule_counts().is_none());
+        let result = parser.parse_with_rule_counts("ac");
+        assert!(result.is_success());
+        let counts = parser.get_rule_counts(result.rule_counts().unwrap());
+        let count_for = |name: &str, index: usize| {
+            let variable = grammar.get_variable_for_name(name).unwrap().id;
+            counts
+                .get(&RuleRef::new(variable, index))
+                .copied()
+                .unwrap_or_default()
+        };
+        // the dropped variable is still reduced
+        assert_eq!(count_for("x", 0), 1);
+        assert_eq!(count_for("x", 1), 0);
+        assert_eq!(count_for("y", 0), 1);
+        assert_eq!(count_for("e", 0), 1);
+    }
+}
+
+#[test]
+fn test_rule_counts_nullable_sub_trees() {
+    let parse = |method: &str, input: &str| {
+        let grammar = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; B -> 'b'; C -> 'c'; D -> 'd'; }} rules {{ e -> A y z ; y -> B | ; z -> w v ; w -> C | ; v -> D | ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&grammar)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        let result = parser.parse_with_rule_counts(input);
+        assert!(result.is_success());
+        let counts = parser.get_rule_counts(result.rule_counts().unwrap());
+        ["e", "y", "z", "w", "v"]
+            .iter()
+            .map(|name| {
+                let variable = grammar.get_variable_for_name(name).unwrap().id;
+                (0..2)
+                    .map(|index| {
+                        counts
+                            .get(&RuleRef::new(variable, index))
+                            .copied()
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    // the right-nullable part y z is inserted as a nullable sub-tree
+    assert_eq!(
+        parse("lalr1", "a"),
+        vec![vec![1, 0], vec![0, 1], vec![1, 0], vec![0, 1], vec![0, 1]]
+    );
+    // the RNGLR parser reduces each way to derive the empty tail,
+    // counting the ε-productions in the nullable sub-trees each of them inserts
+    assert_eq!(
+        parse("rnglalr1", "a"),
+        vec![vec![3, 0], vec![0, 2], vec![5, 0], vec![0, 4], vec![0, 5]]
+    );
+    // the same rules are covered
+    for input in ["a", "ab", "ac", "ad", "abcd"] {
+        let expected = parse("lalr1", input);
+        let counts = parse("rnglalr1", input);
+        for (expected, counts) in expected.iter().zip(counts.iter()) {
+            for (expected, count) in expected.iter().zip(counts.iter()) {
+                assert_eq!(*expected > 0, *count > 0, "{input}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_statistics() {
+    for method in ["lalr1", "rnglalr1"] {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; Separator = \"WS\"; }} terminals {{ WS -> ' '; A -> 'a'; B -> 'b'; C -> 'c'; }} rules {{ e -> x* y ; x -> A | B ; y -> C | ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        let result = parser.parse_with_rule_counts("a b a c");
+        assert!(result.is_success());
+        let statistics = result.statistics();
+        // 4 tokens and the end of input, without the separators
+        assert_eq!(statistics.tokens, 5);
+        assert_eq!(statistics.input_bytes, 7);
+        assert_eq!(statistics.lexical_errors, 0);
+        assert_eq!(statistics.syntax_errors, 0);
+        assert!(statistics.nodes >= 1);
+        let rules_used = result
+            .rule_counts()
+            .unwrap()
+            .iter()
+            .filter(|&&count| count > 0)
+            .count();
+        assert!(statistics.reductions >= rules_used);
+        assert_eq!(statistics.gss_peak_stacks.is_some(), method == "rnglalr1");
+        assert!(statistics.lexing_time.is_none());
+        assert!(statistics.parsing_time.is_none());
+        // the timings are only measured when requested
+        let statistics = parser.parse_with_timings("a b a c").statistics();
+        assert!(statistics.lexing_time.is_some());
+        assert!(statistics.parsing_time.is_some());
+        // errors are counted by kind
+        let result = parser.parse("a%c a");
+        let statistics = result.statistics();
+        assert!(!result.is_success());
+        assert_eq!(statistics.nodes, 0);
+        assert_eq!(statistics.lexical_errors, 1);
+        assert_eq!(statistics.syntax_errors, 1);
+    }
+}
+
+#[test]
+fn test_grammar_fingerprint() {
+    let fingerprint_of = |content: &str| {
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(content)],
+            ..CompilationTask::default()
+        };
+        task.load().unwrap().grammars[0].fingerprint(None)
+    };
+    let content = "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A '+' e | A ; } }";
+    // the names generated for the anonymous terminals do not matter
+    assert_eq!(fingerprint_of(content), fingerprint_of(content));
+    assert_ne!(
+        fingerprint_of(content),
+        fingerprint_of(&content.replace("'+'", "'-'"))
+    );
+    // the parsing method of the build matters
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        ..CompilationTask::default()
+    };
+    let grammar = &task.load().unwrap().grammars[0];
+    assert_ne!(
+        grammar.fingerprint(Some(ParsingMethod::LALR1)),
+        grammar.fingerprint(Some(ParsingMethod::RNGLALR1))
+    );
+    assert_eq!(grammar.fingerprint(None), fingerprint_of(content));
+}
+
+#[test]
+fn test_compilation_progress() {
+    let events = RefCell::new(Vec::new());
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        )],
+        progress: Some(Box::new(|progress| events.borrow_mut().push(progress))),
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    task.generate_in_memory(&mut data.grammars[0], 0).unwrap();
+    drop(task);
+    assert_eq!(
+        events.into_inner(),
+        vec![
+            CompilationProgress::LoadingGrammar(1, 1),
+            CompilationProgress::BuildingLRGraph,
+            CompilationProgress::BuildingReductions,
+            CompilationProgress::Generating,
+        ]
+    );
+}
+
+#[test]
+fn test_terminal_set_operations() {
+    let set_of = |ids: &[usize]| {
+        let mut set = TerminalSet::default();
+        for id in ids.iter().rev() {
+            set.add(TerminalRef::Terminal(*id));
+        }
+        set
+    };
+    let mut left = set_of(&[3, 5, 7]);
+    let right = set_of(&[4, 5, 8]);
+    assert_eq!(left.content, set_of(&[7, 5, 3]).content);
+    assert!(left.contains(TerminalRef::Terminal(5)));
+    assert!(!left.contains(TerminalRef::Terminal(4)));
+    assert_eq!(left.intersect(&right), set_of(&[5]));
+    assert_eq!(left.difference(&right), set_of(&[3, 7]));
+    assert!(left.union_with(&right));
+    assert!(!left.union_with(&right));
+    assert_eq!(left, set_of(&[3, 4, 5, 7, 8]));
+    assert_eq!(
+        left.iter()
+            .map(|terminal| terminal.sid())
+            .collect::<Vec<_>>(),
+        [3, 4, 5, 7, 8]
+    );
+    let mut markers = TerminalSet::single(TerminalRef::NullTerminal);
+    assert!(markers.add(TerminalRef::Dummy));
+    assert!(!markers.add(TerminalRef::NullTerminal));
+    assert_eq!(markers.len(), 2);
+}
+
+#[test]
+fn test_terminal_name_in() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A '+' A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let Some(SymbolRef::Terminal(a)) = grammar.get_symbol("A") else {
+        panic!("terminal A not found");
+    };
+    let plus = grammar.terminals.iter().find(|t| t.id != a).unwrap().id;
+    assert_eq!(TerminalRef::Terminal(a).name_in(grammar), "A");
+    assert_eq!(TerminalRef::Terminal(plus).name_in(grammar), "+");
+    assert_eq!(TerminalRef::Epsilon.name_in(grammar), "ε");
+    assert_eq!(TerminalRef::Dollar.name_in(grammar), "$");
+    assert_eq!(TerminalRef::Dummy.name_in(grammar), "#");
+    assert_eq!(TerminalRef::NullTerminal.name_in(grammar), "∅");
+}
+
+#[test]
+fn test_hexadecimal_escapes() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            r#"grammar Test { options { Axiom = "e"; } terminals { NUL -> '\x00'; LOW -> [\x61-\x63]; SMILE -> '\U0001F600'; E -> '\u00e9'; } rules { e -> NUL LOW '\x41' SMILE E ; } }"#,
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    assert!(grammar.get_terminal_for_value("A").is_some());
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser.parse("\0bA\u{1F600}\u{e9}").errors.errors.is_empty());
+    assert!(!parser.parse("\0dA\u{1F600}\u{e9}").errors.errors.is_empty());
+    assert!(!parser
+        .parse("x00bA\u{1F600}\u{e9}")
+        .errors
+        .errors
+        .is_empty());
+}
+
+#[test]
+fn test_rnglr_right_nullable_reduction() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Method = \"rnglr1\"; } terminals { B -> 'b'; } rules { e -> x ; x -> B c ; c -> ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    // reducing `x` after `B` leaves `c` to its epsilon derivation
+    let Some(SymbolRef::Variable(c)) = grammar.get_symbol("c") else {
+        panic!("variable c not found");
+    };
+    let reduction = build
+        .graph
+        .states
+        .iter()
+        .flat_map(|state| state.reductions.iter())
+        .find(|reduction| reduction.is_right_nullable(grammar))
+        .unwrap();
+    assert_eq!(reduction.length, 1);
+    assert_eq!(reduction.get_nullable_suffix(grammar), [c]);
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("b");
+    assert!(result.errors.errors.is_empty());
+    let ast = result.get_ast();
+    let x = ast.get_root().child(0);
+    assert_eq!(x.get_symbol().name, "x");
+    let names: Vec<&str> = x
+        .children()
+        .iter()
+        .map(|node| node.get_symbol().name)
+        .collect();
+    assert_eq!(names, ["B", "c"]);
+    assert_eq!(x.child(1).children_count(), 0);
+}
+
+#[test]
+fn test_in_memory_max_token_count() {
+    // without and with lexical contexts, for both kinds of lexers
+    for terminals in ["A -> 'a';", "A -> 'a'; context inner { B -> 'b'; }"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ {terminals} }} rules {{ e -> A* ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            parser_max_token_count: Some(3),
+            ..CompilationTask::default()
+        };
+        // the limit for the generated parser does not apply to the grammar inputs
+        let mut data = task.load().unwrap();
+        let parser = task.generate_in_memory(&mut data.grammars[0], 0).unwrap();
+        assert!(parser.parse("aaa").errors.errors.is_empty());
+        let result = parser.parse("aaaaa");
+        let Some(ParseError::MaxTokensExceeded(error)) = result.errors.errors.first() else {
+            panic!("expected the maximum number of tokens to be exceeded");
+        };
+        assert_eq!(error.get_count(), 3);
+        assert_eq!(error.get_position().column, 4);
+        // the lexer stopped after the third token and the parse was aborted
+        assert_eq!(result.get_tokens().get_tokens_count(), 4);
+        assert_eq!(result.errors.errors.len(), 1);
+    }
+}
+
+#[test]
+fn test_in_memory_max_token_count_aborts() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ e -> A A A A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            parser_max_token_count: Some(3),
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let parser = task.generate_in_memory(&mut data.grammars[0], 0).unwrap();
+        let result = parser.parse("aaaa");
+        // the parse stops at the limit, without an error for the missing fourth token
+        assert_eq!(result.errors.errors.len(), 1);
+        assert!(matches!(
+            result.errors.errors[0],
+            ParseError::MaxTokensExceeded(_)
+        ));
+    }
+}
+
+/// Base grammar of the inheritance chain
+const INHERITANCE_BASE: &str =
+    "grammar Base { options { Axiom = \"e\"; } terminals { ID -> [a-z]+; } rules { e -> ID; } }";
+/// Grammar redefining both the terminal and the variable of its parent
+const INHERITANCE_DERIVED: &str =
+    "grammar Derived : Base { options { } terminals { ID -> [a-z0-9]+; } rules { e -> ID ID; } }";
+
+#[test]
+fn test_find_redefined_symbols() {
+    // chain Base <- Derived <- Leaf, where Leaf only adds an alternative to `e`
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(INHERITANCE_BASE),
+            Input::Raw(INHERITANCE_DERIVED),
+            Input::Raw("grammar Leaf : Derived { options { } rules { e -> ID ID ID; } }"),
+        ],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let (base, derived, leaf) = (&data.grammars[0], &data.grammars[1], &data.grammars[2]);
+    assert!(base.find_redefined_symbols().is_empty());
+
+    let warnings = derived.find_redefined_symbols();
+    assert_eq!(warnings.len(), 2);
+    let Error::RedefinedTerminal(input, name, previous) = &warnings[0] else {
+        panic!("expected a redefined terminal");
+    };
+    assert_eq!(name, "ID");
+    assert_eq!(input.input_index, 1);
+    assert_eq!(previous.input_index, 0);
+    let Error::RedefinedVariable(input, name, previous) = &warnings[1] else {
+        panic!("expected a redefined variable");
+    };
+    assert_eq!(name, "e");
+    assert_eq!(input.input_index, 1);
+    assert_eq!(previous.input_index, 0);
+    // the derived definition of the terminal is in effect
+    let terminal = derived.get_terminal_for_name("ID").unwrap();
+    assert_eq!(terminal.input_ref.input_index, 1);
+    assert_eq!(terminal.definitions.len(), 2);
+
+    // the leaf grammar does not report the redefinitions of its parent again
+    let warnings = leaf.find_redefined_symbols();
+    assert_eq!(warnings.len(), 1);
+    let Error::RedefinedVariable(input, name, previous) = &warnings[0] else {
+        panic!("expected a redefined variable");
+    };
+    assert_eq!(name, "e");
+    assert_eq!(input.input_index, 2);
+    assert_eq!(previous.input_index, 1);
+    let terminal = leaf.get_terminal_for_name("ID").unwrap();
+    assert_eq!(terminal.input_ref.input_index, 1);
+    assert_eq!(
+        leaf.get_variable_for_name("e").unwrap().definitions.len(),
+        3
+    );
+}
+
+#[test]
+fn test_conflicting_inherited_symbols() {
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(INHERITANCE_BASE),
+            Input::Raw(INHERITANCE_DERIVED),
+            Input::Raw("grammar Merged : Base, Derived { options { } rules { } }"),
+        ],
+        ..CompilationTask::default()
+    };
+    let errors = task.load().unwrap_err();
+    let conflicts: Vec<(&str, usize, usize)> = errors
+        .errors
+        .iter()
+        .filter_map(|error| match error {
+            Error::ConflictingInheritedSymbol(input, name, definition, previous) => {
+                assert_eq!(input.input_index, 2);
+                Some((name.as_str(), definition.input_index, previous.input_index))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(conflicts, [("ID", 1, 0), ("e", 1, 0)]);
+
+    // inheriting twice the same definitions is not a conflict
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(INHERITANCE_BASE),
+            Input::Raw(
+                "grammar Other : Base { options { } terminals { NUM -> [0-9]+; } rules { } }",
+            ),
+            Input::Raw("grammar Merged : Base, Other { options { } rules { } }"),
+        ],
+        ..CompilationTask::default()
+    };
+    assert!(task.load().is_ok());
+}
+
+#[test]
+fn test_explicit_empty_rule() {
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Explicit { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> A x list<C> ; x -> %empty | B ; list<y> -> %empty | list<y> y ; } }",
+            ),
+            Input::Raw(
+                "grammar Implicit { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> A x list<C> ; x -> | B ; list<y> -> | list<y> y ; } }",
+            ),
+        ],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    for (index, grammar) in data.grammars.iter_mut().enumerate() {
+        let build = grammar.build(None, index).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        for input in ["a", "ab", "acc", "abcc"] {
+            assert!(parser.parse(input).errors.errors.is_empty());
+        }
+    }
+    // both forms produce the same rules, where empty bodies are shown as ε
+    let explicit = data.grammars[0].to_string();
+    assert_eq!(explicit, data.grammars[1].to_string());
+    assert!(explicit.contains("x -> ε\n"));
+}
+
+#[test]
+fn test_generated_parser_without_actions() {
+    let folder = hime_sdk::output::temporary_folder();
+    std::fs::create_dir_all(&folder).unwrap();
+    let generate = |rules: &str, method: &str| {
+        let input = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ {rules} }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&input)],
+            mode: Some(Mode::Sources),
+            output_target: Some(Runtime::Rust),
+            output_path: Some(folder.to_str().unwrap().to_string()),
+            ..CompilationTask::default()
+        };
+        task.execute().unwrap();
+        std::fs::read_to_string(folder.join("test.rs")).unwrap()
+    };
+    // without semantic actions, the LR(k) parser does not dispatch the reductions
+    let code = generate("e -> e A | A ;", "lalr1");
+    assert!(code.contains(
+        "LRkParser::new_without_actions(&mut lexer, variables, virtuals, automaton, data.2)"
+    ));
+    assert!(!code.contains("my_actions"));
+    assert!(!code.contains("pub trait Actions"));
+    // with semantic actions, they are dispatched through the callback
+    let code = generate("e -> e A @act | A ;", "lalr1");
+    assert!(code.contains(
+        "LRkParser::new(&mut lexer, variables, virtuals, automaton, data.2, &mut my_actions)"
+    ));
+    assert!(!code.contains("new_without_actions"));
+    // the RNGLR parser always dispatches the reductions
+    let code = generate("e -> e A | A ;", "rnglalr1");
+    assert!(code.contains("&mut my_actions"));
+    assert!(!code.contains("new_without_actions"));
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn test_generated_parser_caches_automata() {
+    let folder = hime_sdk::output::temporary_folder();
+    std::fs::create_dir_all(&folder).unwrap();
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e A | A ; } }",
+        )],
+        mode: Some(Mode::Sources),
+        output_target: Some(Runtime::Rust),
+        output_path: Some(folder.to_str().unwrap().to_string()),
+        ..CompilationTask::default()
+    };
+    task.execute().unwrap();
+    let code = std::fs::read_to_string(folder.join("test.rs")).unwrap();
+    std::fs::remove_dir_all(&folder).unwrap();
+    // the automata are deserialized on the first parse only
+    assert!(code.contains(
+        "static AUTOMATON: std::sync::OnceLock<Automaton> = std::sync::OnceLock::new();"
+    ));
+    assert!(code.contains(
+        "static AUTOMATON: std::sync::OnceLock<LRkAutomaton> = std::sync::OnceLock::new();"
+    ));
+    assert!(code.contains("let automaton = get_parser_automaton();"));
+    assert!(!code.contains("LRkAutomaton::new(PARSER_AUTOMATON);"));
+}
+
+#[test]
+fn test_parse_without_actions() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ e -> e '+' t @add | t ; t -> '(' e ')' | A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        for input in ["(a+a)+a", "a+(a", "a++a"] {
+            let mut count = 0;
+            let mut actions = |_index: usize, _head: Symbol, _body: &dyn SemanticBody| count += 1;
+            let with = parser.parse_with_actions(input, &mut actions);
+            let without = parser.parse(input);
+            assert_eq!(with.is_success(), without.is_success());
+            assert_eq!(with.errors.errors.len(), without.errors.errors.len());
+            if with.is_success() {
+                assert_eq!(
+                    with.get_ast().get_root().to_string(),
+                    without.get_ast().get_root().to_string()
+                );
+            }
+            drop(with);
+            assert_eq!(count > 0, input == "(a+a)+a");
+        }
+    }
+}
+
+#[test]
+fn test_parse_arena_reuse() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e '+' A | A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let mut arena = ParseArena::new();
+    for input in ["a+a+a", "a+", "a", "a+a+a"] {
+        let expected = parser.parse(input);
+        let result = parser.parse_in(&mut arena, input);
+        assert_eq!(result.is_success(), expected.is_success());
+        assert_eq!(result.errors.errors.len(), expected.errors.errors.len());
+        assert_eq!(
+            result.get_tokens().get_count(),
+            expected.get_tokens().get_count()
+        );
+        assert_eq!(result.text.get_line_count(), expected.text.get_line_count());
+        if expected.is_success() {
+            assert_eq!(
+                result.get_ast().get_root().to_string(),
+                expected.get_ast().get_root().to_string()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_parse_arena_keeps_parser_storage() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ e -> e '+' t | t ; t -> '(' e ')' | A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        let input = "((a+a)+(a+(a+a)))+a+(a+a)";
+        let mut arena = ParseArena::new();
+        assert_eq!(arena.parser_capacity(), 0);
+        assert!(parser.parse_in(&mut arena, input).is_success());
+        let capacity = arena.parser_capacity();
+        assert!(capacity > 0, "no parser storage kept for {method}");
+        for _ in 0..3 {
+            assert!(parser.parse_in(&mut arena, input).is_success());
+            assert_eq!(
+                arena.parser_capacity(),
+                capacity,
+                "storage grew for {method}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_parse_config_tab_width() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> (' ' | '\\t' | '\\n')+; A -> 'a'; B -> 'b'; } rules { e -> A+ ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let mut parser = grammar.get_in_memory(&build).unwrap();
+    let config = ParseConfig {
+        tab_width: 4,
+        ..ParseConfig::default()
+    };
+    parser.config = config;
+    let input = "a\t\ta\n\tb";
+    let check = |result: &ParseResult<AstImpl>| {
+        let tokens = result.get_tokens();
+        assert_eq!(
+            tokens.get_token(1).get_position(),
+            Some(TextPosition { line: 1, column: 9 })
+        );
+        assert_eq!(result.errors.errors.len(), 1);
+        assert_eq!(
+            result.errors.errors[0].get_position(),
+            TextPosition { line: 2, column: 5 }
+        );
+    };
+    check(&parser.parse(input));
+    check(&parser.parse_with_rule_counts(input));
+    check(&parser.parse_in(&mut ParseArena::new().with_config(config), input));
+    // the lexer alone computes the same positions
+    let tokens = parser.tokenize(input);
+    assert_eq!(
+        tokens.get_tokens().get_token(2).get_position(),
+        Some(TextPosition { line: 2, column: 5 })
+    );
+    // without the configuration, a tab is a single column
+    parser.config = ParseConfig::default();
+    assert_eq!(
+        parser.parse(input).get_tokens().get_token(1).get_position(),
+        Some(TextPosition { line: 1, column: 4 })
+    );
+}
+
+#[test]
+fn test_gss_statistics() {
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Deterministic { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e '+' A | A ; } }",
+            ),
+            Input::Raw(
+                "grammar Ambiguous { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e '+' e | A ; } }",
+            ),
+        ],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let mut statistics = Vec::new();
+    for (index, grammar) in data.grammars.iter_mut().enumerate() {
+        let build = grammar.build(Some(ParsingMethod::RNGLALR1), index).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        assert!(parser.parse("a+a+a+a").gss_statistics().is_none());
+        let result = parser.parse_with_gss_statistics("a+a+a+a");
+        assert!(result.is_success());
+        statistics.push(result.gss_statistics().unwrap());
+    }
+    assert_eq!(statistics[0].splits, 0);
+    assert_eq!(statistics[0].merges, 0);
+    assert_eq!(statistics[0].max_live_stacks, 1);
+    assert!(statistics[1].splits > 0);
+    assert!(statistics[1].merges > 0);
+    assert!(statistics[1].max_live_stacks > 1);
+}
+
+#[test]
+fn test_fork_budget() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Ambiguous { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e e | A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(Some(ParsingMethod::RNGLALR1), 0).unwrap();
+    let mut parser = grammar.get_in_memory(&build).unwrap();
+    let input = "aaaaaaaaaaaa";
+    let result = parser.parse_with_gss_statistics(input);
+    assert!(result.is_success());
+    let peak = result.gss_statistics().unwrap().peak_stacks;
+    assert!(peak > 4);
+    // a budget at the peak is not exceeded
+    parser.config.fork_budget = Some(peak);
+    assert!(parser.parse(input).is_success());
+    // a smaller budget aborts the parse
+    parser.config.fork_budget = Some(4);
+    let result = parser.parse(input);
+    assert!(!result.is_success());
+    assert_eq!(result.errors.errors.len(), 1);
+    let ParseError::ForkBudgetExceeded(error) = &result.errors.errors[0] else {
+        panic!("expected the fork budget to be exceeded");
+    };
+    assert_eq!(error.get_budget(), 4);
+    assert!(error.get_position().column > 1);
+}
+
+#[test]
+fn test_negative_lookahead() {
+    // without and with lexical contexts, for both kinds of lexers
+    for contexts in ["", "context inner { X -> 'x'; }"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ LT -> '<' (?! '=' | '>'); EQ -> '='; ID -> [a-z]+; SMILE -> '\\U0001F600'; {contexts} }} rules {{ e -> (LT | EQ | ID | SMILE)* ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        for (input, expected) in [
+            ("<", vec!["<"]),
+            ("<a", vec!["<", "a"]),
+            ("a<b=c", vec!["a", "<", "b", "=", "c"]),
+            ("<\u{1F600}", vec!["<", "\u{1F600}"]),
+        ] {
+            let result = parser.parse(input);
+            let values: Vec<String> = result
+                .get_tokens()
+                .iter()
+                .filter(|token| token.get_symbol().name != "$")
+                .filter_map(|token| token.get_value().map(ToString::to_string))
+                .collect();
+            assert_eq!(values, expected, "for input {input:?}");
+        }
+        assert!(!parser.parse("<=").errors.errors.is_empty());
+        assert!(!parser.parse("a<>").errors.errors.is_empty());
+    }
+}
+
+#[test]
+fn test_negative_lookahead_longest_match() {
+    // the longer terminal declared before the one with a lookahead still wins
+    for contexts in ["", "context inner { X -> 'x'; }"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ LTA -> '<a'; LT -> '<' (?! '='); ID -> [a-z]+; {contexts} }} rules {{ e -> (LTA | LT | ID)* ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let parser = grammar.get_in_memory(&build).unwrap();
+        for (input, expected) in [
+            ("<a", vec!["LTA"]),
+            ("<", vec!["LT"]),
+            ("<b", vec!["LT", "ID"]),
+            ("<ab", vec!["LTA", "ID"]),
+        ] {
+            let result = parser.parse(input);
+            assert!(result.errors.errors.is_empty(), "for input {input:?}");
+            let tokens = result.get_tokens();
+            let names: Vec<&str> = tokens
+                .iter()
+                .map(|token| token.get_symbol().name)
+                .filter(|name| *name != "$")
+                .collect();
+            assert_eq!(names, expected, "for input {input:?}");
+        }
+    }
+}
+
+#[test]
+fn test_invalid_negative_lookahead() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { LT -> '<' (?! '=='); } rules { e -> LT ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let Err(errors) = task.load() else {
+        panic!("expected the lookahead to be rejected");
+    };
+    assert!(matches!(errors.errors[0], Error::InvalidLookahead(_)));
+}
+
+#[test]
+fn test_external_terminal() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; A -> 'a'; %external_terminal NUM \"crate::scan_number\" } rules { e -> A NUM A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let mut parser = grammar.get_in_memory(&build).unwrap();
+
+    // without a scanner, the terminal is never matched
+    assert!(!parser.parse("a 42 a").is_success());
+    assert!(!parser.bind_external_scanner("crate::scan_other", |_: &str, _| None));
+    assert!(
+        parser.bind_external_scanner("crate::scan_number", |input: &str, offset: usize| {
+            let length = input[offset..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            (length > 0).then_some(offset + length)
+        })
+    );
+    let result = parser.parse("a 42 a");
+    assert!(result.is_success());
+    let tokens = result.get_tokens();
+    let token = tokens.get_token(1);
+    assert_eq!(token.get_symbol().name, "NUM");
+    assert_eq!(token.get_value(), Some("42"));
+    assert!(!parser.parse("a a").is_success());
+}
+
+#[test]
+fn test_invalid_external_scanner() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { %external_terminal NUM \"scan number\" } rules { e -> NUM ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let Err(errors) = task.load() else {
+        panic!("expected the scanner to be rejected");
+    };
+    assert!(matches!(
+        &errors.errors[0],
+        Error::InvalidExternalScanner(_, name) if name == "scan number"
+    ));
+}
+
+#[test]
+fn test_ast_iterators() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> x B x ; x -> A A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("aabaa");
+    assert!(result.errors.errors.is_empty());
+    let ast = result.get_ast();
+    let names = |nodes: &mut dyn Iterator<Item = hime_redist::ast::AstNode>| {
+        nodes
+            .map(|node| node.get_symbol().name.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(names(&mut ast.iter_preorder()), "e x A A B x A A");
+    assert_eq!(names(&mut ast.iter_postorder()), "A A x B A A x e");
+    let x = grammar.get_variable_for_name("x").unwrap().id;
+    let nodes: Vec<_> = ast.iter_symbol(x as u32).collect();
+    assert_eq!(nodes.len(), 2);
+    assert!(nodes[0] == ast.get_root().child(0));
+    assert!(nodes[1] == ast.get_root().child(2));
+    let leaf = nodes[1].child(1);
+    assert_eq!(names(&mut leaf.ancestors()), "x e");
+    assert_eq!(names(&mut ast.get_root().descendants()), "x A A B x A A");
+}
+
+#[test]
+fn test_ast_node_at() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; A -> 'a'; B -> 'b'; } rules { e -> x B x ; x -> A A | ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    // tokens at 0, 2 and 5
+    let result = parser.parse("a a  b");
+    assert!(result.errors.errors.is_empty());
+    let ast = result.get_ast();
+    let root = ast.get_root();
+    let name = |node: Option<hime_redist::ast::AstNode>| {
+        node.map(|node| node.get_symbol().name.to_string())
+    };
+    // exactly at the start of a token
+    assert!(ast.node_at_index(0) == Some(root.child(0).child(0)));
+    assert!(ast.node_at_index(2) == Some(root.child(0).child(1)));
+    assert!(ast.node_at_index(5) == Some(root.child(1)));
+    assert!(ast.node_at(TextPosition { line: 1, column: 6 }) == Some(root.child(1)));
+    // between two tokens
+    assert!(ast.node_at_index(1) == Some(root.child(0)));
+    assert!(ast.node_at_index(3) == Some(root));
+    assert_eq!(name(ast.node_at_index(4)), Some(String::from("e")));
+    // past the end of the input, the second x is empty and never returned
+    assert!(ast.node_at_index(6).is_none());
+    assert!(ast.node_at_index(100).is_none());
+    assert!(ast.node_at(TextPosition { line: 1, column: 7 }).is_none());
+    assert!(ast.node_at(TextPosition { line: 2, column: 1 }).is_none());
+
+    let range = |index: usize, length: usize| {
+        ast.nodes_in_range(TextSpan { index, length })
+            .into_iter()
+            .map(|node| node.get_symbol().name.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(range(0, 6), "e");
+    assert_eq!(range(0, 3), "x");
+    assert_eq!(range(0, 2), "A");
+    assert_eq!(range(1, 5), "A B");
+    assert_eq!(range(3, 2), "");
+    assert_eq!(range(6, 2), "");
+}
+
+#[test]
+fn test_parse_decoded_text() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; STRING -> '\"' [^\"]* '\"'; } rules { e -> x* ; x -> ID | STRING ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    // gets the value and original span of the tokens, except the end of input
+    let tokens = |text: Text| {
+        let result = parser.parse_text(text);
+        let errors = result
+            .errors
+            .errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let tokens = result
+            .get_tokens()
+            .iter()
+            .filter(|token| token.get_symbol().name != "$")
+            .map(|token| {
+                let span = result.text.to_original_offset(token.get_span().unwrap());
+                (
+                    token.get_value().unwrap().to_string(),
+                    span.index,
+                    span.length,
+                )
+            })
+            .collect::<Vec<_>>();
+        (tokens, errors)
+    };
+
+    // Latin-1 with high-bit characters inside string literals
+    let (values, errors) = tokens(Text::from_latin1(b"a \"caf\xE9\" \"\xAB\xBB\""));
+    assert!(errors.is_empty());
+    assert_eq!(
+        values,
+        [
+            (String::from("a"), 0, 1),
+            (String::from("\"caf\u{e9}\""), 2, 6),
+            (String::from("\"\u{ab}\u{bb}\""), 9, 4),
+        ]
+    );
+
+    // UTF-16LE with a byte order mark
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(
+        "ab \"\u{e9}t\u{e9}\""
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes),
+    );
+    let (values, errors) = tokens(Text::from_utf16(&bytes));
+    assert!(errors.is_empty());
+    assert_eq!(
+        values,
+        [
+            (String::from("ab"), 2, 4),
+            (String::from("\"\u{e9}t\u{e9}\""), 8, 10),
+        ]
+    );
+
+    // UTF-16BE with a lone surrogate in a string literal, reported without failing the parse
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend("ab \"".encode_utf16().flat_map(u16::to_be_bytes));
+    bytes.extend([0xD8, 0x00, 0x00, 0x22]);
+    let (values, errors) = tokens(Text::from_utf16(&bytes));
+    assert_eq!(errors, ["Incorrect encoding sequence: [0xD800 <missing>]"]);
+    assert_eq!(
+        values,
+        [
+            (String::from("ab"), 2, 4),
+            (String::from("\"\u{FFFD}\""), 8, 6),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_error_render() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> (' ' | '\\n')+; A -> 'a'; B -> 'b'; } rules { e -> A B ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+
+    let result = parser.parse("\n  x a a");
+    let text = Text::from_str("\n  x a a");
+    let errors = result.into_result().err().unwrap();
+    assert_eq!(errors.errors.len(), 3);
+    let error = &errors.errors[2];
+    assert_eq!(error.get_position(), TextPosition { line: 2, column: 7 });
+    assert_eq!(error.get_length(), 1);
+    assert_eq!(error.get_excerpt(&text, 0), "a");
+    assert_eq!(error.get_excerpt(&text, 2), "a a");
+    assert_eq!(error.get_excerpt(&text, 100), "  x a a");
+    assert_eq!(
+        error.render(&text),
+        "Unexpected token \"a\"; expected: B\n --> (2, 7)\n  |\n2 | x a a\n  |     ^\n"
+    );
+    let message = errors.to_string();
+    assert!(message.starts_with("@(2, 3) Unexpected character 'x' (U+78)\n"));
+    assert!(message.ends_with("\n@(2, 7) Unexpected token \"a\"; expected: B"));
+    // the errors can be used with the error handling of the standard library
+    let boxed: Box<dyn std::error::Error> = Box::new(errors.clone());
+    assert_eq!(boxed.to_string(), errors.to_string());
+
+    let result = parser.parse("a b").into_result();
+    assert!(result.is_ok_and(|result| result.get_ast().get_root().children_count() == 2));
+}
+
+#[test]
+fn test_leading_trivia() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Separator = \"SEP\"; ParserType = \"{method}\"; }} terminals {{ SEP -> (' ' | '\\n' | '#' [a-z]*)+; A -> 'a'; B -> 'b'; }} rules {{ e -> A B ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let mut parser = grammar.get_in_memory(&build).unwrap();
+        let input = " #doc\na  b ";
+
+        // by default, the separators are dropped
+        let result = parser.parse(input);
+        assert!(result.is_success());
+        assert!(result
+            .get_tokens()
+            .iter()
+            .all(|token| token.leading_trivia().is_empty()));
+
+        parser.config.retain_trivia = true;
+        let result = parser.parse(input);
+        assert!(result.is_success());
+        let tokens = result.get_tokens();
+        let trivia: Vec<Vec<&str>> = tokens
+            .iter()
+            .map(|token| {
+                token
+                    .leading_trivia()
+                    .iter()
+                    .map(|trivia| trivia.value)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(trivia, vec![vec![" #doc\n"], vec!["  "], vec![" "]]);
+        let leading = tokens.get_token(1).leading_trivia();
+        assert_eq!(leading[0].symbol.name, "SEP");
+        assert_eq!(leading[0].position, TextPosition { line: 2, column: 2 });
+        assert_eq!(
+            leading[0].span,
+            TextSpan {
+                index: 7,
+                length: 2
+            }
+        );
+        // the trivia of a node is the one of its first token
+        let ast = result.get_ast();
+        assert_eq!(ast.get_root().leading_trivia()[0].value, " #doc\n");
+    }
+}
+
+#[test]
+fn test_parse_error_suggestions() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ LP -> '('; RP -> ')'; A -> 'a'; }} rules {{ e -> LP e RP | A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let mut parser = grammar.get_in_memory(&build).unwrap();
+        // the stacks are only captured on demand
+        let result = parser.parse("((a");
+        assert!(result.errors.errors[0]
+            .suggestions(grammar, &build.graph, 1)
+            .is_empty());
+        parser.config.capture_stacks = true;
+        let suggestions = |input: &str, max: usize| {
+            let result = parser.parse(input);
+            result.errors.errors[0]
+                .suggestions(grammar, &build.graph, max)
+                .iter()
+                .map(|phrase| phrase.display(grammar).to_string())
+                .collect::<Vec<_>>()
+        };
+        // the completions depend on the whole stack, not only the current state
+        assert_eq!(suggestions("((a", 1), ["RP RP"]);
+        assert_eq!(suggestions("(a", 1), ["RP"]);
+        assert_eq!(suggestions("(()", 2), ["A RP RP", "LP A RP RP RP"]);
+        assert!(suggestions("(()", 0).is_empty());
+    }
+}
+
+#[test]
+fn test_semantic_action_errors() {
+    for method in ["lalr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Separator = \"SEP\"; ParserType = \"{method}\"; }} terminals {{ SEP -> ' '+; N -> [0-9]+; }} rules {{ e -> i i i ; i -> N @check ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let build = grammar.build(None, 0).unwrap();
+        let mut parser = grammar.get_in_memory(&build).unwrap();
+        let input = "3 42 5";
+        let mut calls = 0;
+        let mut check = |_index: usize, _head: Symbol, body: &dyn SemanticBody| {
+            calls += 1;
+            let value = body.get_element_at(0).get_value().unwrap().to_string();
+            if value.len() > 1 {
+                Err(SemanticError::at(0, format!("{value} is too large")))
+            } else {
+                Ok(())
+            }
+        };
+
+        // by default, the error is recorded and the parser continues
+        let result = parser.parse_with_actions(input, &mut check);
+        assert_eq!(result.errors.errors.len(), 1);
+        let Some(ParseError::Semantic(error)) = result.errors.errors.first() else {
+            panic!("expected a semantic error");
+        };
+        assert_eq!(error.get_message(), "42 is too large");
+        assert_eq!(error.get_position(), TextPosition { line: 1, column: 3 });
+        assert_eq!(error.get_length(), 2);
+        assert_eq!(calls, 3);
+
+        parser.stop_on_semantic_error = true;
+        calls = 0;
+        let mut check = |_index: usize, _head: Symbol, body: &dyn SemanticBody| {
+            calls += 1;
+            if body.get_element_at(0).get_value().unwrap().len() > 1 {
+                Err(SemanticError::new("too large"))
+            } else {
+                Ok(())
+            }
+        };
+        let result = parser.parse_with_actions(input, &mut check);
+        assert_eq!(result.errors.errors.len(), 1);
+        // an error for the whole body is positioned on its first element
+        assert_eq!(
+            result.errors.errors[0].get_position(),
+            TextPosition { line: 1, column: 3 }
+        );
+        assert_eq!(calls, 2);
+
+        // infallible actions are still accepted
+        let mut calls = 0;
+        let result = parser.parse_with_actions(
+            input,
+            &mut |_index: usize, _head: Symbol, _body: &dyn SemanticBody| {
+                calls += 1;
+            },
+        );
+        assert!(result.is_success());
+        assert_eq!(calls, 3);
+    }
+}
+
+#[test]
+fn test_token_context_ids() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; context outer { KW_LET -> 'let'; } context inner { KW_IN -> 'in'; } } rules { e -> ID #outer { KW_LET ID #inner { KW_IN ID } } ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("x let y in z");
+    assert!(result.is_success());
+    let tokens = result.get_tokens();
+    let contexts: Vec<&str> = tokens
+        .iter()
+        .map(|token| parser.get_context_name(token.context_id()).unwrap())
+        .collect();
+    // the tokens of terminals in the default context stay in the default context,
+    // even when an enclosing context is opened
+    assert_eq!(
+        contexts,
+        vec!["default", "outer", "default", "inner", "default", "default"]
+    );
+    assert_eq!(tokens.get_token(1).context_id(), 1);
+    assert_eq!(tokens.get_token(3).context_id(), 2);
+    assert_eq!(parser.get_context_name(3), None);
+}
+
+#[test]
+fn test_tokenize() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; context outer { KW_LET -> 'let'; } } rules { e -> ID #outer { KW_LET ID } ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let parser = task.generate_in_memory(grammar, 0).unwrap();
+    // the input is not parsed, only tokenized
+    let result = parser.tokenize("let x ! let");
+    assert!(!result.is_success());
+    let tokens = result.get_tokens();
+    let symbols: Vec<&str> = tokens.iter().map(|token| token.get_symbol().name).collect();
+    assert_eq!(symbols, ["KW_LET", "ID", "KW_LET", "$"]);
+    assert_eq!(tokens.get_token(0).context_id(), 1);
+    assert_eq!(result.errors.errors.len(), 1);
+    assert!(matches!(
+        result.errors.errors[0],
+        ParseError::UnexpectedChar(_)
+    ));
+}
+
+#[test]
+fn test_token_as_str() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; } rules { e -> ID+ ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let parser = task.generate_in_memory(grammar, 0).unwrap();
+    let result = parser.parse("let x");
+    assert!(result.is_success());
+    let tokens = result.get_tokens();
+    let values: Vec<Option<&str>> = tokens.iter().map(|token| token.as_str()).collect();
+    assert_eq!(values, [Some("let"), Some("x"), Some("")]);
+    // the same as the value of the token
+    for token in tokens.iter() {
+        assert_eq!(token.as_str(), token.get_value());
+    }
+}
+
+#[test]
+fn test_terminal_priority_groups() {
+    let content = |terminals: &str| {
+        format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Separator = \"WS\"; }} terminals {{ {terminals} }} rules {{ e -> (KW_IF | KW_ELSE | ID)+ ; }} }}"
+        )
+    };
+    let terminals = "WS -> ' '+; KW_IF -> 'if'; ID -> [a-z]+; KW_ELSE -> 'else';";
+    // `ID` is declared after `KW_IF` and overrides it
+    let ungrouped = content(terminals);
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(&ungrouped)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let errors = data.grammars[0].build(None, 0).err().unwrap();
+    assert!(matches!(&errors[0], Error::TerminalCannotBeMatched(0, _)));
+
+    // the group has the priority of `KW_ELSE`, the longest match still wins
+    let grouped = content(&format!("{terminals} %priority_group {{ KW_IF, KW_ELSE }}"));
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(&grouped)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    assert_eq!(grammar.terminal_groups.len(), 1);
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("if else iffy");
+    assert!(result.is_success());
+    let tokens = result.get_tokens();
+    let names: Vec<&str> = tokens.iter().map(|token| token.get_symbol().name).collect();
+    assert_eq!(names, vec!["KW_IF", "KW_ELSE", "ID", "$"]);
+
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; %priority_group { A, B } %priority_group { B, C } } rules { e -> A B ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let errors = task.load().err().unwrap();
+    assert_eq!(errors.errors.len(), 2);
+    assert!(matches!(&errors.errors[0], Error::TerminalInSeveralGroups(_, name, _) if name == "B"));
+    assert!(matches!(&errors.errors[1], Error::SymbolNotFound(_, name) if name == "C"));
+}
+
+#[test]
+fn test_lexical_errors_in_multiline_terminals() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> [ \\t\\r\\n]+; ID -> [a-z]+; COMMENT -> '/*' ([^*] | '*'+ [^*/])* '*'+ '/'; STRING -> '\"' ([^\"\\\\] | '\\\\' [nt\"])* '\"'; } rules { e -> item* ; item -> ID | STRING | COMMENT ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let tokens_of = |result: &ParseResult<AstImpl>| {
+        result
+            .get_tokens()
+            .iter()
+            .map(|token| {
+                (
+                    token.get_value().unwrap_or_default().to_string(),
+                    token.get_position().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // an invalid escape on the second line of a string
+    let result = parser.parse("a \"x\ny \\q z\" b");
+    assert_eq!(result.errors.errors.len(), 1);
+    assert!(matches!(
+        result.errors.errors[0],
+        ParseError::UnexpectedChar(_)
+    ));
+    assert_eq!(
+        result.errors.errors[0].get_position(),
+        TextPosition { line: 2, column: 4 }
+    );
+    assert_eq!(
+        tokens_of(&result),
+        vec![
+            (String::from("a"), TextPosition { line: 1, column: 1 }),
+            (
+                String::from("\"x\ny \\q z\""),
+                TextPosition { line: 1, column: 3 }
+            ),
+            (String::from("b"), TextPosition { line: 2, column: 9 }),
+            (
+                String::new(),
+                TextPosition {
+                    line: 2,
+                    column: 10
+                }
+            ),
+        ]
+    );
+
+    // a stray character after a comment
+    let result = parser.parse("a /* x\n y */ ` b");
+    assert_eq!(
+        result.errors.errors[0].get_position(),
+        TextPosition { line: 2, column: 7 }
+    );
+    assert_eq!(
+        tokens_of(&result)[1],
+        (
+            String::from("/* x\n y */"),
+            TextPosition { line: 1, column: 3 }
+        )
+    );
+
+    // an unterminated comment is fixed across its lines,
+    // the errors are within the comment rather than at the end of the input
+    let result = parser.parse("a /* x\n y * b\nc");
+    assert!(!result.errors.errors.is_empty());
+    assert!(result
+        .errors
+        .errors
+        .iter()
+        .all(|error| matches!(error, ParseError::UnexpectedChar(_))));
+    assert_eq!(
+        result.errors.errors[0].get_position(),
+        TextPosition { line: 2, column: 7 }
+    );
+    assert_eq!(
+        tokens_of(&result)[1],
+        (
+            String::from("/* x\n y * b\nc"),
+            TextPosition { line: 1, column: 3 }
+        )
+    );
+}
+
+#[test]
+fn test_unterminated_token_recovery() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> [ \\r\\n]+; ID -> [a-z]+; STRING -> '\"' [^\"\\r\\n]* '\"' %recover_at_eol; } rules { e -> stmt+ ; stmt -> ID '=' STRING ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("a = \"x\"\nb = \"unterminated\nc = \"z\"\n");
+    // the unterminated string is cut at the end of its line and parsing continues
+    assert_eq!(result.errors.errors.len(), 1);
+    let Some(ParseError::UnterminatedToken(error)) = result.errors.errors.first() else {
+        panic!("expected an unterminated token error");
+    };
+    assert_eq!(error.get_position(), TextPosition { line: 2, column: 5 });
+    assert_eq!(error.get_length(), 13);
+    assert_eq!(error.get_terminal().name, "STRING");
+    let tokens = result.get_tokens();
+    let values: Vec<&str> = tokens
+        .iter()
+        .filter_map(|token| token.get_value())
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            "a",
+            "=",
+            "\"x\"",
+            "b",
+            "=",
+            "\"unterminated",
+            "c",
+            "=",
+            "\"z\"",
+            ""
+        ]
+    );
+    let ast = result.get_ast();
+    assert_eq!(ast.get_root().children_count(), 3);
+}
+
+#[test]
+fn test_semantic_element_display() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"SEP\"; } terminals { SEP -> ' '+; N -> [0-9]+; } rules { e -> x '+' N \"v\" @show ; x -> N ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let mut elements = Vec::new();
+    let mut show = |_index: usize, _head: Symbol, body: &dyn SemanticBody| {
+        for i in 0..body.length() {
+            elements.push(body.get_element_at(i).to_display().to_string());
+        }
+    };
+    let result = parser.parse_with_actions("1 + 2", &mut show);
+    assert!(result.is_success());
+    assert_eq!(
+        elements,
+        vec![
+            "Variable(x)",
+            "Token(\"+\" at 1:3)",
+            "Token(\"2\" at 1:5)",
+            "Virtual(v)"
+        ]
+    );
+}
+
+#[test]
+fn test_case_insensitive_terminals() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; SELECT -> 'select' %case_insensitive; FROM -> 'from' (?! [a-z]) %case_insensitive; } rules { e -> SELECT ID FROM ID ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    for input in ["select a from b", "SELECT a FROM b", "Select a fRoM b"] {
+        let result = parser.parse(input);
+        assert!(result.is_success(), "{input}: {}", result.errors);
+        let tokens = result.get_tokens();
+        // the tokens of case-insensitive terminals keep the declared name
+        let names: Vec<&str> = tokens.iter().map(|token| token.get_symbol().name).collect();
+        assert_eq!(names, vec!["SELECT", "ID", "FROM", "ID", "$"]);
+    }
+    // identifiers stay case-sensitive
+    assert!(!parser.parse("select A from b").is_success());
+    // the lookahead is also case-insensitive
+    assert!(!parser.parse("select a fromB b").is_success());
+}
+
+#[test]
+fn test_terminal_aliases() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; ID -> [a-z]+; T_LCURLY -> '{'; T_RCURLY -> '}'; %alias T_LCURLY \"{\"; %alias T_RCURLY \"}\"; } rules { e -> T_LCURLY ID* T_RCURLY ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let terminal = grammar.get_terminal_for_name("T_LCURLY").unwrap();
+    assert_eq!(terminal.alias.as_deref(), Some("{"));
+    assert_eq!(terminal.get_display_name(), "{");
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    // aliases do not change the matching
+    assert!(parser.parse("{ a b }").is_success());
+    let result = parser.parse("a }");
+    let Some(ParseError::UnexpectedToken(error)) = result.errors.errors.first() else {
+        panic!("expected an unexpected token error");
+    };
+    assert_eq!(error.to_string(), "Unexpected token \"a\"; expected: {");
+    assert_eq!(error.get_expected()[0].name, "T_LCURLY");
+}
+
+#[test]
+fn test_symbol_annotations() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { @category(\"operator\") @my_tool(\"a \\\"b\\\"\") PLUS -> '+'; ID -> [a-z]+; context Inner { @category(\"name\") NAME -> 'n'; } } rules { @node_kind(\"BinaryExpr\") e -> ID PLUS ID ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let plus = grammar.get_terminal_for_name("PLUS").unwrap().id as u32;
+    let id = grammar.get_terminal_for_name("ID").unwrap().id as u32;
+    let name = grammar.get_terminal_for_name("NAME").unwrap();
+    assert_eq!(name.annotations[0].value, "name");
+    let e = grammar.get_variable_for_name("e").unwrap().id as u32;
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert_eq!(parser.metadata(plus, "category"), Some("operator"));
+    // unknown annotations are preserved verbatim
+    assert_eq!(parser.metadata(plus, "my_tool"), Some("a \"b\""));
+    assert_eq!(parser.metadata(e, "node_kind"), Some("BinaryExpr"));
+    assert_eq!(parser.metadata(id, "category"), None);
+    assert_eq!(parser.metadata(e, "category"), None);
+}
+
+#[test]
+fn test_annotation_without_symbol() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { T -> 't'; @category(\"operator\") } rules { e -> T ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let Err(errors) = task.load() else {
+        panic!("expected the annotation to be rejected");
+    };
+    assert!(matches!(
+        errors.errors[0],
+        Error::AnnotationWithoutSymbol(_, ref key) if key == "category"
+    ));
+}
+
+#[test]
+fn test_reachable_subset() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> A x | B y ; x -> C? y ; y -> B | ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let x = grammar.get_variable_for_name("x").unwrap().id;
+    let mut subset = grammar.reachable_subset(x);
+    assert_eq!(subset.get_option_value("Axiom"), Some("x"));
+    // the referenced terminals and the separator are kept, in their original order
+    let terminals: Vec<&str> = subset.terminals.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(terminals, ["WS", "B", "C"]);
+    assert!(subset.get_variable_for_name("e").is_none());
+    assert!(subset.get_variable_for_name("y").is_some());
+    // the symbols are renumbered consistently
+    let mut sids: Vec<usize> = subset
+        .terminals
+        .iter()
+        .map(|t| t.id)
+        .chain(subset.variables.iter().map(|v| v.id))
+        .collect();
+    sids.sort_unstable();
+    assert_eq!(sids, (3..3 + sids.len()).collect::<Vec<_>>());
+    assert_eq!(subset.next_sid, 3 + sids.len());
+    // the firsts are recomputed for the subset
+    let b = subset.get_terminal_for_name("B").unwrap().id;
+    let c = subset.get_terminal_for_name("C").unwrap().id;
+    let x = subset.get_variable_for_name("x").unwrap();
+    assert!(x.firsts.contains(TerminalRef::Terminal(b)));
+    assert!(x.firsts.contains(TerminalRef::Terminal(c)));
+    assert!(x.firsts.contains(TerminalRef::Epsilon));
+    // the subset is a standalone grammar
+    let build = subset.build(None, 0).unwrap();
+    let parser = subset.get_in_memory(&build).unwrap();
+    assert!(parser.parse("c b").is_success());
+    assert!(!parser.parse("b c").is_success());
+}
+
+#[test]
+fn test_grammar_builder() {
+    let mut builder = GrammarBuilder::new("Test");
+    let ws = builder.add_terminal("WS", "' '+");
+    builder.add_terminal("DIGIT", "[0-9]");
+    let number = builder.add_terminal("NUMBER", "DIGIT+");
+    let plus = builder.add_literal("PLUS", "+");
+    let sum = builder.add_variable("sum");
+    builder.add_rule(sum, vec![number], &[]);
+    builder.add_rule(
+        sum,
+        vec![sum, plus, number],
+        &[TREE_ACTION_NONE, TREE_ACTION_DROP, TREE_ACTION_NONE],
+    );
+    builder.set_axiom(sum);
+    builder.set_option("Separator", "WS");
+    let mut grammar = builder.finish().unwrap();
+    assert!(matches!(ws, SymbolRef::Terminal(_)));
+    let digit = grammar.get_terminal_for_name("DIGIT").unwrap();
+    assert_eq!(digit.terminal_references.len(), 1);
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    let result = parser.parse("1 + 23 + 4");
+    assert!(result.is_success());
+    let ast = result.get_ast();
+    let root = ast.get_root();
+    assert_eq!(root.get_symbol().name, "sum");
+    assert_eq!(root.children().len(), 2);
+    assert!(!parser.parse("1 +").is_success());
+}
+
+#[test]
+fn test_grammar_builder_errors() {
+    let mut builder = GrammarBuilder::new("Test");
+    let a = builder.add_literal("A", "a");
+    let e = builder.add_variable("e");
+    builder.add_variable("A");
+    builder.add_terminal("B", "[a-");
+    builder.add_rule(a, vec![e], &[]);
+    builder.add_rule(e, vec![SymbolRef::Variable(42)], &[]);
+    builder.add_rule(e, vec![a, a], &[TREE_ACTION_DROP]);
+    builder.set_axiom(e);
+    let errors = builder.finish().unwrap_err();
+    assert_eq!(errors.len(), 5);
+    assert!(matches!(&errors[0], Error::DuplicateSymbolName(name) if name == "A"));
+    assert!(matches!(&errors[1], Error::InvalidTerminalExpression(name, _) if name == "B"));
+    assert!(matches!(errors[2], Error::UnknownSymbolRef(symbol) if symbol == a));
+    assert!(matches!(
+        errors[3],
+        Error::UnknownSymbolRef(SymbolRef::Variable(42))
+    ));
+    assert!(matches!(&errors[4], Error::RuleActionsMismatch(name, 2, 1) if name == "e"));
+}
+
+#[test]
+fn test_recover_sets() {
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Test { options { Axiom = \"s\"; } terminals { ID -> [a-z]+; } rules { %recover { ';', EOF } s -> (e ';')* ; e -> ID | '{' s '}' ; %recover { '}', ';' } } }",
+            ),
+            Input::Raw("grammar Derived : Test { options { } terminals { ID -> [a-zA-Z]+; } rules { %recover { ID } } }"),
+        ],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let semicolon = grammar.get_terminal_for_value(";").unwrap().id;
+    let brace = grammar.get_terminal_for_value("}").unwrap().id;
+    // the sets are merged
+    assert_eq!(
+        grammar.sync_terminals,
+        [
+            TerminalRef::Terminal(semicolon),
+            TerminalRef::Dollar,
+            TerminalRef::Terminal(brace)
+        ]
+    );
+    // the inherited sets are merged with the ones of the derived grammar
+    let derived = &data.grammars[1];
+    let semicolon = derived.get_terminal_for_value(";").unwrap().id;
+    let brace = derived.get_terminal_for_value("}").unwrap().id;
+    let id = derived.get_terminal_for_name("ID").unwrap().id;
+    assert_eq!(
+        derived.sync_terminals,
+        [
+            TerminalRef::Terminal(semicolon),
+            TerminalRef::Dollar,
+            TerminalRef::Terminal(brace),
+            TerminalRef::Terminal(id)
+        ]
+    );
+
+    // the inherited terminals are matched by name, not by value
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Test { options { Axiom = \"s\"; } terminals { SEMI -> ';'; ID -> [a-z]+; } rules { s -> (ID SEMI)* ; %recover { SEMI } } }",
+            ),
+            Input::Raw("grammar Derived : Test { options { } terminals { } rules { t -> 'SEMI' ; %recover { ID } } }"),
+        ],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let derived = &data.grammars[1];
+    let semicolon = derived.get_terminal_for_name("SEMI").unwrap();
+    assert_eq!(semicolon.value, "SEMI");
+    let id = derived.get_terminal_for_name("ID").unwrap().id;
+    assert_eq!(
+        derived.sync_terminals,
+        [
+            TerminalRef::Terminal(semicolon.id),
+            TerminalRef::Terminal(id)
+        ]
+    );
+
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { ID -> [a-z]+; } rules { e -> ID ; %recover { ';', NUM } } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let errors = task.load().unwrap_err();
+    let names: Vec<&str> = errors
+        .errors
+        .iter()
+        .filter_map(|error| match error {
+            Error::SymbolNotFound(_, name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["';'", "NUM"]);
+}
+
+#[test]
+fn test_find_shadowed_terminals() {
+    let build = |terminals: &str| {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ {terminals} }} rules {{ e -> ID ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut grammar = task.load().unwrap().grammars.remove(0);
+        let data = grammar.build(None, 0).unwrap();
+        let warnings = grammar.find_shadowed_terminals(0, &data);
+        (grammar, warnings)
+    };
+    // the keyword is declared before the catch-all identifier, which has more priority
+    let (grammar, warnings) = build("IF -> 'if'; ID -> [a-z]+;");
+    assert_eq!(warnings.len(), 1);
+    let Error::TerminalShadowed(0, error) = &warnings[0] else {
+        panic!("expected a shadowed terminal");
+    };
+    let if_ref = TerminalRef::Terminal(grammar.get_terminal_for_name("IF").unwrap().id);
+    let id_ref = TerminalRef::Terminal(grammar.get_terminal_for_name("ID").unwrap().id);
+    assert_eq!(error.terminal, if_ref);
+    assert_eq!(error.overriders, [id_ref]);
+
+    // the keyword is declared last
+    let (_, warnings) = build("ID -> [a-z]+; IF -> 'if';");
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_terminal_fragments() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Separator = \"WS\"; } terminals { WS -> ' '+; fragment HEX_DIGIT -> [0-9a-fA-F]; HEX -> '0x' HEX_DIGIT+; COLOR -> '#' HEX_DIGIT HEX_DIGIT HEX_DIGIT; CODE -> 'U+' HEX_DIGIT+; } rules { e -> (HEX | COLOR | CODE)* ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    assert!(parser
+        .terminals
+        .iter()
+        .all(|symbol| symbol.name != "HEX_DIGIT"));
+    for name in ["HEX", "COLOR", "CODE"] {
+        assert!(parser.terminals.iter().any(|symbol| symbol.name == name));
+    }
+    let result = parser.parse("0x1f #aBc U+00e9");
+    assert!(result.errors.errors.is_empty());
+    let tokens = result.get_tokens();
+    let names: Vec<&str> = tokens.iter().map(|token| token.get_symbol().name).collect();
+    assert!(!names.contains(&"HEX_DIGIT"));
+    assert_eq!(names, ["HEX", "COLOR", "CODE", "$"]);
+    // a lone digit is not a token on its own
+    assert!(!parser.parse("0x1f 0").errors.errors.is_empty());
+
+    // terminals cannot refer to themselves, the error points at the recursive reference
+    for (terminals, reference) in [
+        ("fragment F -> 'x' F?; A -> F;", "F?"),
+        ("fragment F -> 'x'; A -> F ('+' A)?;", "A)"),
+    ] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ {terminals} }} rules {{ e -> A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let errors = task.load().unwrap_err();
+        let [Error::RecursiveTerminalReference(input, _)] = &errors.errors[..] else {
+            panic!("expected a recursive reference in {terminals}");
+        };
+        assert_eq!(input.position.column, content.find(reference).unwrap() + 1);
+    }
+}
+
+#[test]
+fn test_grammar_options() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Method = \"rnglalr1\"; Namespace = \"test\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let (index, grammar) = data.find_grammar("Test").unwrap();
+    assert_eq!(index, 0);
+    assert!(data.find_grammar("Other").is_none());
+    assert_eq!(grammar.get_axiom(), Some("e"));
+    assert_eq!(
+        grammar.get_method(index).unwrap(),
+        Some(ParsingMethod::RNGLALR1)
+    );
+    assert_eq!(grammar.get_option_value("Namespace"), Some("test"));
+    let options = grammar.get_options();
+    assert_eq!(options.len(), 3);
+    assert_eq!(options.get("Method"), Some(&"rnglalr1"));
+}
+
+#[test]
+fn test_grammar_invalid_method() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; Method = \"ll1\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    assert!(matches!(
+        data.grammars[0].get_method(0),
+        Err(Error::InvalidOption(0, ref name, _)) if name == "Method"
+    ));
+}
+
+/// Ambiguous grammars for the golden outputs of the RNGLR runtime
+const RNGLR_GOLDEN_GRAMMARS: [&str; 3] = [
+    "grammar G0 { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e e | A ; } }",
+    "grammar G1 { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> e '+' e | e '*' e | A ; } }",
+    "grammar G2 { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> s+ ; s -> s '+'^ s | A | '(' s* ')'! ; } }",
+];
+
+/// A golden output of the RNGLR runtime for an input of the ambiguous grammars:
+/// (grammar, input, AST, number of reductions for each production, GSS splits, merges and live stacks)
+type RnglrGoldenOutput = (
+    usize,
+    &'static str,
+    &'static str,
+    &'static [usize],
+    [usize; 3],
+);
+
+/// The golden outputs of the RNGLR runtime for inputs of the ambiguous grammars
+const RNGLR_GOLDEN_OUTPUTS: [RnglrGoldenOutput; 6] = [
+    (
+        0,
+        "aaaa",
+        "e(e(e(e(A) e(A)) e(A)) e(A))",
+        &[11, 6, 0],
+        [2, 8, 2],
+    ),
+    (
+        0,
+        "aaaaaa",
+        "e(e(e(e(e(e(A) e(A)) e(A)) e(A)) e(A)) e(A))",
+        &[45, 10, 0],
+        [4, 24, 2],
+    ),
+    (
+        1,
+        "a+a*a+a",
+        "e(e(e(e(A) + e(A)) * e(A)) + e(A))",
+        &[6, 4, 4, 0],
+        [3, 4, 3],
+    ),
+    (
+        1,
+        "a*a+a*a+a",
+        "e(e(e(e(e(A) * e(A)) + e(A)) * e(A)) + e(A))",
+        &[10, 10, 5, 0],
+        [5, 8, 3],
+    ),
+    (
+        2,
+        "a+a+a a",
+        "e(+(+(s(A) s(A)) s(A)) s(A))",
+        &[1, 4, 4, 0, 0, 1, 1, 0, 0, 0],
+        [1, 2, 2],
+    ),
+    (
+        2,
+        "(a a+a)+a",
+        "e(+(s(( s(A) +(s(A) s(A))) s(A)))",
+        &[1, 2, 4, 0, 1, 1, 0, 1, 1, 0],
+        [0, 0, 1],
+    ),
+];
+
+/// Writes an AST as a s-expression
+fn write_sexpr(node: AstNode, output: &mut String) {
+    output.push_str(node.get_symbol().name);
+    if node.children_count() > 0 {
+        output.push('(');
+        for (index, child) in node.children().iter().enumerate() {
+            if index > 0 {
+                output.push(' ');
+            }
+            write_sexpr(child, output);
+        }
+        output.push(')');
+    }
+}
+
+#[test]
+fn test_rnglr_golden_outputs() {
+    let task = CompilationTask {
+        inputs: RNGLR_GOLDEN_GRAMMARS
+            .iter()
+            .map(|g| Input::Raw(g))
+            .collect(),
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let mut parsers = Vec::new();
+    let mut builds = Vec::new();
+    for (index, grammar) in data.grammars.iter_mut().enumerate() {
+        builds.push(grammar.build(Some(ParsingMethod::RNGLALR1), index).unwrap());
+    }
+    for (grammar, build) in data.grammars.iter().zip(&builds) {
+        parsers.push(grammar.get_in_memory(build).unwrap());
+    }
+    for (grammar, input, ast, counts, statistics) in RNGLR_GOLDEN_OUTPUTS {
+        let result = parsers[grammar].parse_with_rule_counts(input);
+        assert!(result.is_success());
+        let mut sexpr = String::new();
+        write_sexpr(result.get_ast().get_root(), &mut sexpr);
+        assert_eq!(sexpr, ast, "{input}");
+        assert_eq!(result.rule_counts().unwrap(), counts, "{input}");
+        let result = parsers[grammar].parse_with_gss_statistics(input);
+        let actual = result.gss_statistics().unwrap();
+        assert_eq!(
+            [actual.splits, actual.merges, actual.max_live_stacks],
+            statistics,
+            "{input}"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hime_sdk::errors::Error;
+use hime_sdk::{CompilationTask, Input};
+
+#[test]
+fn test_load_gzip_file() {
+    let content = "grammar Test\n{\n  options { Axiom = \"e\"; }\n  terminals { A -> 'a'; }\n  rules { e -> A; }\n}\n";
+    let path = std::env::temp_dir().join(format!("hime_test_gzip_{}.gram.gz", std::process::id()));
+    let mut encoder = GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        Compression::default(),
+    );
+    encoder.write_all(content.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    let task = CompilationTask {
+        inputs: vec![Input::GzipFile(path.clone())],
+        ..CompilationTask::default()
+    };
+    let data = task.load();
+    std::fs::remove_file(&path).unwrap();
+    let data = data.unwrap();
+    assert_eq!(data.inputs[0].content.len(), content.len());
+    let terminal = data.grammars[0].get_terminal_for_name("A").unwrap();
+    assert_eq!(terminal.input_ref.position.line, 4);
+}
+
+#[test]
+fn test_load_max_token_count() {
+    let content =
+        "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A; } }";
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        max_token_count: Some(8),
+        ..CompilationTask::default()
+    };
+    let errors = task.load().unwrap_err();
+    assert_eq!(errors.errors.len(), 1);
+    let Error::MaxTokensExceeded(input, count) = &errors.errors[0] else {
+        panic!("expected the maximum number of tokens to be exceeded");
+    };
+    assert_eq!(*count, 8);
+    // the 9th token is the `;` after the axiom option
+    assert_eq!(input.position.column, 37);
+
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        max_token_count: Some(100),
+        ..CompilationTask::default()
+    };
+    assert!(task.load().is_ok());
+}
+
+#[test]
+fn test_load_bom_and_encoding() {
+    let content = "\u{FEFF}grammar Test\n{\n  options { Axiom = \"e\"; }\n  terminals { A -> 'a'; }\n  rules { e -> A; }\n}\n";
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    assert_eq!(data.inputs[0].encoding, None);
+    let terminal = data.grammars[0].get_terminal_for_name("A").unwrap();
+    assert_eq!(terminal.input_ref.position.line, 4);
+
+    let content = "\u{FEFF}% encoding UTF-8\ngrammar Test\n{\n  options { Axiom = \"e\"; }\n  terminals { A -> 'a'; }\n  rules { e -> A; }\n}\n";
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    assert_eq!(data.inputs[0].encoding.as_deref(), Some("UTF-8"));
+    let terminal = data.grammars[0].get_terminal_for_name("A").unwrap();
+    assert_eq!(terminal.input_ref.position.line, 5);
+    assert_eq!(terminal.input_ref.position.column, 15);
+}
+
+#[test]
+fn test_load_includes() {
+    let content = "%include \"common/base.gram\";\n%include \"other\\u0020file.gram\";\ngrammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A; } }";
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(content)],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let includes = &data.inputs[0].includes;
+    assert_eq!(includes.len(), 2);
+    assert_eq!(includes[0].path, "common/base.gram");
+    assert_eq!(includes[0].input_ref.position.line, 1);
+    assert_eq!(includes[0].input_ref.position.column, 10);
+    assert_eq!(includes[1].path, "other file.gram");
+    assert_eq!(data.grammars.len(), 1);
+}
+
+#[test]
+fn test_load_included_files() {
+    let root = std::env::temp_dir().join(format!("hime_test_load_includes_{}", std::process::id()));
+    std::fs::create_dir_all(root.join("a")).unwrap();
+    std::fs::create_dir_all(root.join("common")).unwrap();
+    // the base includes the main file back, which is only loaded once
+    std::fs::write(
+        root.join("common").join("base.gram"),
+        "%include \"../a/main.gram\";\ngrammar Base { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+    )
+    .unwrap();
+    let main = root.join("a").join("main.gram");
+    std::fs::write(
+        &main,
+        "%include \"../common/base.gram\";\ngrammar Main : Base { options { Axiom = \"f\"; } rules { f -> e A ; } }",
+    )
+    .unwrap();
+    let task = CompilationTask {
+        inputs: vec![Input::FileName(main.to_string_lossy().into_owned())],
+        ..CompilationTask::default()
+    };
+    let data = task.load();
+    std::fs::remove_dir_all(&root).unwrap();
+    let data = data.unwrap();
+    assert_eq!(data.inputs.len(), 2);
+    assert!(data.inputs[1].name.ends_with("base.gram"));
+    let names: Vec<&str> = data.grammars.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"Main") && names.contains(&"Base"));
+}
+
+#[test]
+fn test_load_missing_included_file() {
+    let root = std::env::temp_dir().join(format!("hime_test_load_missing_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let main = root.join("main.gram");
+    std::fs::write(
+        &main,
+        "%include \"missing.gram\";\ngrammar Main { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> A ; } }",
+    )
+    .unwrap();
+    let task = CompilationTask {
+        inputs: vec![Input::FileName(main.to_string_lossy().into_owned())],
+        ..CompilationTask::default()
+    };
+    let errors = task.load().unwrap_err();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(errors.errors.len(), 1);
+    assert!(
+        matches!(&errors.errors[0], Error::Parsing(input, message) if input.position.line == 1 && message.contains("missing.gram"))
+    );
+}
@@ -0,0 +1,905 @@
+use hime_sdk::errors::Error;
+use hime_sdk::grammars::{Grammar, RuleRef, SymbolRef, TerminalRef, GENERATED_AXIOM};
+use hime_sdk::lr::{
+    build_graph_ielr1, build_graph_lalr1, build_graph_lr0, build_graph_lr1, build_graph_profiled,
+    build_graph_rnglalr1, build_graph_rnglr1, closure, Conflict, ConflictKind, Conflicts, Graph,
+    GraphWithGrammar, Item, Lookahead, LookaheadMode, Lookaheads, Phrase, ReportOptions, State,
+    StateKernel,
+};
+use hime_sdk::{CompilationTask, Input, ParsingMethod};
+
+#[test]
+fn test_phrase_display() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let data = task.load().unwrap();
+    let grammar = &data.grammars[0];
+    let Some(SymbolRef::Terminal(a)) = grammar.get_symbol("A") else {
+        panic!("terminal A not found");
+    };
+    let Some(SymbolRef::Terminal(b)) = grammar.get_symbol("B") else {
+        panic!("terminal B not found");
+    };
+    let mut phrase = Phrase::default();
+    assert!(phrase.is_empty());
+    phrase.append(TerminalRef::Terminal(a));
+    phrase.append(TerminalRef::Terminal(b));
+    phrase.append(TerminalRef::Dollar);
+    assert_eq!(phrase.len(), 3);
+    assert_eq!(phrase.display(grammar).to_string(), "A B $");
+}
+
+#[test]
+fn test_report_max_lookaheads() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; D -> 'd'; E -> 'e'; F -> 'f'; G -> 'g'; H -> 'h'; I -> 'i'; J -> 'j'; X -> 'x'; } rules { e -> x (A | B | C | D | E | F | G | H | I | J); x -> X; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(Some(ParsingMethod::LALR1), 0).unwrap().graph;
+    let report = |options: ReportOptions| {
+        let report = GraphWithGrammar {
+            grammar,
+            graph: &graph,
+            options,
+        }
+        .to_string();
+        report
+            .lines()
+            .find(|line| line.contains("x -> X •"))
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+    assert_eq!(
+        report(ReportOptions::default()),
+        "x -> X •  ❰A B C D E F G H … (2 more)❱"
+    );
+    assert_eq!(
+        report(ReportOptions {
+            max_lookaheads: 3,
+            verbose: false
+        }),
+        "x -> X •  ❰A B C … (7 more)❱"
+    );
+    assert_eq!(
+        report(ReportOptions {
+            max_lookaheads: 3,
+            verbose: true
+        }),
+        "x -> X •  ❰A B C D E F G H I J❱"
+    );
+}
+
+#[test]
+fn test_items_in_dependency_order() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> x; x -> z A | w B; z -> A; w -> B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(None, 0).unwrap().graph;
+    let state = &graph.states[0];
+    let items = state.items_in_dependency_order(grammar);
+    assert_eq!(items.len(), state.items.len());
+    assert_eq!(items[0], &state.kernel.items[0]);
+    let names: Vec<&str> = items
+        .iter()
+        .map(|item| grammar.get_symbol_name(SymbolRef::Variable(item.rule.variable)))
+        .collect();
+    assert_eq!(names[1..], ["e", "x", "z", "x", "w"]);
+}
+
+#[test]
+fn test_graph_diff() {
+    let task = CompilationTask {
+        inputs: vec![
+            Input::Raw(
+                "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; } rules { e -> x ';' ; x -> A '+' A | A ; } }",
+            ),
+            Input::Raw(
+                "grammar TestEntries { options { Axiom = \"e\"; Entries = \"x\"; } terminals { A -> 'a'; } rules { e -> x ';' ; x -> A '+' A | A ; } }",
+            ),
+        ],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let build = data.grammars[0].build(None, 0).unwrap();
+    assert!(build.graph.diff(&build.graph).is_empty());
+
+    let grammar = &mut data.grammars[1];
+    let build_entries = grammar.build(None, 1).unwrap();
+    let diff = build.graph.diff(&build_entries.graph);
+    // the initial state for the entry point, and the states reached from it
+    assert!(!diff.new_states.is_empty());
+    assert!(diff.removed_states.is_empty());
+    // the states after `A` also reduce on `$` when parsing from the entry point
+    assert!(!diff.changed_reductions.is_empty());
+    let text = diff.to_string(grammar);
+    assert!(text.contains("+ state 1\n    __VAxiom_x -> • x $"));
+}
+
+#[test]
+fn test_graph_iter() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e | B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let graph = data.grammars[0].build(None, 0).unwrap().graph;
+    let count = graph.states.len();
+    let mut ids = Vec::new();
+    for (id, state) in &graph {
+        assert!(std::ptr::eq(state, &graph.states[id]));
+        ids.push(id);
+    }
+    assert_eq!(ids, (0..count).collect::<Vec<_>>());
+    assert_eq!(graph.iter().count(), count);
+    let kernels: Vec<StateKernel> = graph.states.iter().map(|s| s.kernel.clone()).collect();
+    let owned: Vec<State> = graph.into_iter().collect();
+    assert_eq!(owned.len(), count);
+    assert!(owned
+        .iter()
+        .zip(&kernels)
+        .all(|(state, kernel)| &state.kernel == kernel));
+}
+
+#[test]
+fn test_graph_to_csv() {
+    let table = |method: &str| {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; }} rules {{ e -> e ',' A | A ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let graph = grammar.build(None, 0).unwrap().graph;
+        graph.to_csv(grammar)
+    };
+    // the anonymous terminal `,` is quoted
+    assert_eq!(
+        table("lalr1"),
+        "state,$,A,\",\",e,__VAxiom\n\
+         0,,s1,,s2,\n\
+         1,r1,,r1,,\n\
+         2,s3,,s4,,\n\
+         3,acc,,,,\n\
+         4,,s5,,,\n\
+         5,r0,,r0,,\n"
+    );
+    // without lookaheads, the reductions are for all the terminals
+    assert!(table("lr0").contains("\n1,r1,r1,r1,,\n"));
+}
+
+#[test]
+fn test_find_dead_reductions() {
+    for method in ["lr1", "lalr1", "ielr1", "rnglr1", "rnglalr1"] {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"s\"; Entries = \"e\"; Method = \"{method}\"; }} terminals {{ A -> 'a'; B -> 'b'; }} rules {{ s -> e ';' | B o ; e -> e ',' A | A ; o -> A | ; }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let mut graph = grammar.build(None, 0).unwrap().graph;
+        assert!(graph.find_dead_reductions(grammar).is_empty(), "{method}");
+        // `e -> A` cannot be followed by `B`
+        let a = grammar.get_symbol("A").unwrap();
+        let state = graph.states[0].children[&a];
+        let mut reduction = graph.states[state].reductions[0].clone();
+        reduction.lookahead = Lookahead::from(match grammar.get_symbol("B") {
+            Some(SymbolRef::Terminal(id)) => TerminalRef::Terminal(id),
+            _ => panic!("terminal B not found"),
+        });
+        graph.states[state].reductions.push(reduction.clone());
+        let count = graph.states[state].reductions.len();
+        let dead = graph.find_dead_reductions(grammar);
+        assert_eq!(dead, [(state, reduction)], "{method}");
+        assert_eq!(graph.states[state].reductions.len(), count);
+    }
+}
+
+#[test]
+fn test_build_graph_profiled() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(FIRSTS_GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    type Builder = fn(&Grammar, usize) -> (Graph, Conflicts);
+    let builders: [(ParsingMethod, Builder); 6] = [
+        (ParsingMethod::LR0, build_graph_lr0),
+        (ParsingMethod::LR1, build_graph_lr1),
+        (ParsingMethod::LALR1, build_graph_lalr1),
+        (ParsingMethod::RNGLR1, build_graph_rnglr1),
+        (ParsingMethod::RNGLALR1, build_graph_rnglalr1),
+        (ParsingMethod::IELR1, build_graph_ielr1),
+    ];
+    for (method, builder) in builders {
+        let (graph, conflicts, profile) = build_graph_profiled(grammar, axiom, method);
+        let (expected_graph, expected_conflicts) = builder(grammar, axiom);
+        // profiling does not change the result
+        assert!(graph.diff(&expected_graph).is_empty());
+        assert_eq!(conflicts.len(), expected_conflicts.len());
+        assert_eq!(profile.state_count, graph.states.len());
+        assert!(profile.closure_time_ns > 0);
+        assert!(profile.dedup_time_ns > 0);
+        assert!(profile.reduction_time_ns > 0);
+    }
+}
+
+#[test]
+fn test_graph_write_read() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(FIRSTS_GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (graph, _) = build_graph_rnglalr1(grammar, axiom);
+    let mut buffer = Vec::new();
+    graph.write_to(&mut buffer).unwrap();
+    let read = Graph::read_from(&mut buffer.as_slice()).unwrap();
+    assert!(graph.diff(&read).is_empty());
+    for (state, read_state) in graph.states.iter().zip(&read.states) {
+        assert_eq!(state.items, read_state.items);
+        assert_eq!(state.opening_contexts, read_state.opening_contexts);
+        assert_eq!(state.reductions, read_state.reductions);
+    }
+    // truncated data is rejected
+    assert!(Graph::read_from(&mut &buffer[..buffer.len() - 1]).is_err());
+}
+
+#[test]
+fn test_build_graph_ielr1() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { } rules { e -> 'a' x 'c' | 'a' y 'd' | 'b' y 'c' | 'b' x 'd' | z 'p' | 'q' z 'r' ; x -> 'e' ; y -> 'e' ; z -> 'y' 'w' ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (lalr1, lalr1_conflicts) = build_graph_lalr1(grammar, axiom);
+    let (lr1, lr1_conflicts) = build_graph_lr1(grammar, axiom);
+    let (ielr1, ielr1_conflicts) = build_graph_ielr1(grammar, axiom);
+    // the reduce/reduce conflict on `x -> e` and `y -> e` comes from the LALR(1) merge
+    assert!(!lalr1_conflicts.is_empty());
+    assert!(lr1_conflicts.is_empty());
+    assert!(ielr1_conflicts.is_empty());
+    // only the inadequate state is split, the states after `y` are still merged
+    assert_eq!(ielr1.states.len(), lalr1.states.len() + 1);
+    assert!(ielr1.states.len() < lr1.states.len());
+
+    let build = grammar.build(Some(ParsingMethod::IELR1), 0).unwrap();
+    let parser = grammar.get_in_memory(&build).unwrap();
+    for input in ["aec", "aed", "bec", "bed", "ywp", "qywr"] {
+        assert!(parser.parse(input).is_success(), "{input}");
+    }
+    assert!(!parser.parse("ywr").is_success());
+}
+
+#[test]
+fn test_build_graph_ielr1_max_states() {
+    let build = |max_states: usize| {
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(
+                "grammar Test { options { Axiom = \"s\"; } terminals { } rules { s -> 'a' e 'c' | 'a' f 'd' | 'b' f 'c' | 'b' e 'd' | 'x' l 'c' | 'y' l 'd' ; e -> 'e' ; f -> 'e' ; l -> 'p' 'q' 'r' 's' 't' 'u' ; } }",
+            )],
+            method: Some(ParsingMethod::IELR1),
+            max_states: Some(max_states),
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        task.build_grammar(&mut data.grammars[0], 0)
+            .map(|build| build.graph.states.len())
+    };
+    // the limit applies to the IELR(1) graph, not to the larger intermediate graphs
+    assert_eq!(build(27).unwrap(), 27);
+    let errors = build(26).unwrap_err();
+    assert!(matches!(errors[0], Error::StateLimitExceeded(0, _)));
+}
+
+#[test]
+fn test_find_renamed_state_pairs() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { } rules { e -> 'a' x | 'a' 'a' y ; x -> 'c' x | 'd' ; y -> 'c' y | 'd' ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (graph, _conflicts) = build_graph_lalr1(grammar, axiom);
+    let heads = |state: usize| -> Vec<&str> {
+        let mut heads: Vec<&str> = graph.states[state]
+            .kernel
+            .items
+            .iter()
+            .map(|item| {
+                grammar.get_symbol_name(SymbolRef::Variable(item.rule.get_rule_in(grammar).head))
+            })
+            .collect();
+        heads.dedup();
+        heads
+    };
+    let pairs = graph.find_renamed_state_pairs(grammar);
+    // the states after `c`, `d` and `c x` are the same as the ones for `y`, renaming `x` into `y`
+    assert_eq!(pairs.len(), 3);
+    for (left, right) in pairs {
+        assert!(left < right);
+        let mut heads = [heads(left), heads(right)];
+        heads.sort();
+        assert_eq!(heads, [vec!["x"], vec!["y"]]);
+    }
+}
+
+/// Grammar with rules of length 0, 1 and 3
+const FIRSTS_GRAMMAR: &str = "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; D -> 'd'; } rules { e -> a b c ; a -> A | ; b -> B ; c -> C | D ; } }";
+
+#[test]
+fn test_build_predecessors() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e | B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(None, 0).unwrap().graph;
+    let predecessors = graph.build_predecessors();
+    assert_eq!(predecessors.len(), graph.states.len());
+    assert!(predecessors[0].is_empty());
+    // every transition appears exactly once, as incoming to its target
+    for (from, state) in graph.states.iter().enumerate() {
+        for (&symbol, &to) in &state.children {
+            assert_eq!(
+                predecessors[to]
+                    .iter()
+                    .filter(|&&incoming| incoming == (symbol, from))
+                    .count(),
+                1
+            );
+        }
+    }
+    let count: usize = graph.states.iter().map(|state| state.children.len()).sum();
+    assert_eq!(predecessors.iter().map(Vec::len).sum::<usize>(), count);
+    // the state after `A` is reached from the initial state and from itself
+    let a = grammar.get_symbol("A").unwrap();
+    let after_a = graph.states[0].children[&a];
+    assert_eq!(predecessors[after_a], vec![(a, 0), (a, after_a)]);
+}
+
+#[test]
+fn test_accepting_states() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e | B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(None, 0).unwrap().graph;
+    // the only accepting state is the one reducing the generated axiom after `$`
+    let accepting: Vec<usize> = (0..graph.states.len())
+        .filter(|&state| graph.is_accepting(state, grammar))
+        .collect();
+    assert_eq!(accepting.len(), 1);
+    let state = &graph.states[accepting[0]];
+    assert!(state.get_reduction_for(TerminalRef::Epsilon).is_some());
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    assert!(state.items.iter().any(|item| item.rule.variable == axiom));
+}
+
+#[test]
+fn test_state_items_lookup() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; } rules { e -> A e | B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(None, 0).unwrap().graph;
+    // the initial state starts the axiom rule and closes on the rules for `e`
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let e = grammar.get_variable_for_name("e").unwrap().id;
+    let initial = &graph.states[0];
+    assert!(initial.has_item(RuleRef::new(axiom, 0), 0));
+    assert!(initial.has_item(RuleRef::new(e, 0), 0));
+    assert!(!initial.has_item(RuleRef::new(e, 0), 1));
+    let item = initial.item_for(RuleRef::new(e, 1), 0).unwrap();
+    assert_eq!(item.rule, RuleRef::new(e, 1));
+    assert!(initial.item_for(RuleRef::new(e, 2), 0).is_none());
+}
+
+/// Gets the names of the terminals in a set
+fn get_names<'g>(grammar: &'g Grammar, terminals: &[TerminalRef]) -> Vec<&'g str> {
+    terminals
+        .iter()
+        .map(|terminal| grammar.get_symbol_name((*terminal).into()))
+        .collect()
+}
+
+/// Gets the lookaheads of the item for a rule at a position in a state
+fn get_lookaheads<'g>(
+    grammar: &'g Grammar,
+    state: &State,
+    variable: &str,
+    index: usize,
+    position: usize,
+) -> Vec<&'g str> {
+    let rule = RuleRef::new(grammar.get_variable_for_name(variable).unwrap().id, index);
+    let item = state
+        .items
+        .iter()
+        .find(|item| item.rule == rule && item.position == position)
+        .unwrap();
+    let mut terminals: Vec<TerminalRef> = item
+        .lookaheads
+        .iter()
+        .map(|lookahead| lookahead.terminal)
+        .collect();
+    terminals.sort();
+    get_names(grammar, &terminals)
+}
+
+#[test]
+fn test_rule_firsts_after() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(FIRSTS_GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let firsts_after = |variable: &str, index: usize, position: usize| {
+        let rule = RuleRef::new(grammar.get_variable_for_name(variable).unwrap().id, index)
+            .get_rule_in(grammar);
+        get_names(grammar, &rule.firsts_after(position).content)
+    };
+    // rule of length 3
+    assert_eq!(firsts_after("e", 0, 0), ["A", "B"]);
+    assert_eq!(firsts_after("e", 0, 1), ["B"]);
+    assert_eq!(firsts_after("e", 0, 2), ["C", "D"]);
+    assert_eq!(firsts_after("e", 0, 3), ["ε"]);
+    assert_eq!(firsts_after("e", 0, 10), ["ε"]);
+    // rule of length 1
+    assert_eq!(firsts_after("a", 0, 0), ["A"]);
+    assert_eq!(firsts_after("a", 0, 1), ["ε"]);
+    // rule of length 0
+    assert_eq!(firsts_after("a", 1, 0), ["ε"]);
+}
+
+#[test]
+fn test_closure_lookaheads() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(FIRSTS_GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let graph = grammar.build(None, 0).unwrap().graph;
+    let grammar = &data.grammars[0];
+    let state0 = &graph.states[0];
+    assert_eq!(get_lookaheads(grammar, state0, "e", 0, 0), ["$"]);
+    // rules of length 1 and 0 are followed by FIRSTS(b c)
+    assert_eq!(get_lookaheads(grammar, state0, "a", 0, 0), ["B"]);
+    assert_eq!(get_lookaheads(grammar, state0, "a", 1, 0), ["B"]);
+    let a = grammar.get_variable_for_name("a").unwrap().id;
+    let state1 = &graph.states[state0.children[&SymbolRef::Variable(a)]];
+    assert_eq!(get_lookaheads(grammar, state1, "e", 0, 1), ["$"]);
+    assert_eq!(get_lookaheads(grammar, state1, "b", 0, 0), ["C", "D"]);
+    let b = grammar.get_variable_for_name("b").unwrap().id;
+    let state2 = &graph.states[state1.children[&SymbolRef::Variable(b)]];
+    assert_eq!(get_lookaheads(grammar, state2, "c", 0, 0), ["$"]);
+    assert_eq!(get_lookaheads(grammar, state2, "c", 1, 0), ["$"]);
+}
+
+#[test]
+fn test_closure() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(FIRSTS_GRAMMAR)],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let e = grammar.get_variable_for_name("e").unwrap().id;
+    let describe = |items: &[Item]| {
+        items
+            .iter()
+            .map(|item| {
+                let mut terminals: Vec<TerminalRef> = item
+                    .lookaheads
+                    .iter()
+                    .map(|lookahead| lookahead.terminal)
+                    .collect();
+                terminals.sort();
+                format!(
+                    "{}.{} {}",
+                    grammar.get_symbol_name(SymbolRef::Variable(item.rule.variable)),
+                    item.position,
+                    get_names(grammar, &terminals).join(",")
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let kernel = |position: usize| {
+        vec![Item {
+            rule: RuleRef::new(e, 0),
+            position,
+            lookaheads: Lookaheads::from_single(Lookahead::from(TerminalRef::Dollar)),
+        }]
+    };
+    assert_eq!(
+        describe(&closure(kernel(0), grammar, LookaheadMode::LR1)),
+        ["e.0 $", "a.0 B", "a.0 B"]
+    );
+    assert_eq!(
+        describe(&closure(kernel(1), grammar, LookaheadMode::LR1)),
+        ["e.1 $", "b.0 C", "b.0 D"]
+    );
+    assert_eq!(
+        describe(&closure(kernel(2), grammar, LookaheadMode::LR0)),
+        ["e.2 $", "c.0 ", "c.0 "]
+    );
+    assert_eq!(
+        describe(&closure(kernel(3), grammar, LookaheadMode::LR1)),
+        ["e.3 $"]
+    );
+}
+
+#[test]
+fn test_closure_same_as_close_to() {
+    // expressions with a nullable suffix, so that lookaheads flow from items to their closure
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { ID -> [a-z]+; } rules { e -> e '+' t | t ; t -> t '*' f o | f o ; f -> '(' e ')' | ID ; o -> '!' | ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let describe = |items: &[Item]| {
+        items
+            .iter()
+            .map(|item| {
+                let lookaheads: Vec<_> = item
+                    .lookaheads
+                    .iter()
+                    .map(|lookahead| (lookahead.terminal, lookahead.origins.clone()))
+                    .collect();
+                (item.rule, item.position, lookaheads)
+            })
+            .collect::<Vec<_>>()
+    };
+    let (lr1, _) = build_graph_lr1(grammar, axiom);
+    let (lalr1, _) = build_graph_lalr1(grammar, axiom);
+    for (graph, mode) in [
+        (&lr1, LookaheadMode::LR0),
+        (&lr1, LookaheadMode::LR1),
+        (&lalr1, LookaheadMode::LALR1),
+    ] {
+        for state in &graph.states {
+            let mut expected = state.kernel.items.clone();
+            let mut i = 0;
+            while i < expected.len() {
+                expected[i].clone().close_to(grammar, &mut expected, mode);
+                i += 1;
+            }
+            let items = closure(state.kernel.items.clone(), grammar, mode);
+            assert_eq!(describe(&items), describe(&expected));
+        }
+    }
+}
+
+#[test]
+fn test_conflicts_filter_and_group() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> x B | y B | z C | A C C; x -> A; y -> A; z -> A; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (graph, conflicts) = build_graph_lr1(grammar, axiom);
+    assert!(!conflicts.is_empty());
+    assert_eq!(conflicts.len(), 2);
+    assert_eq!(conflicts.iter().count(), 2);
+    assert_eq!(conflicts.shift_reduce().count(), 1);
+    assert_eq!(conflicts.reduce_reduce().count(), 1);
+    assert_eq!(conflicts.count_of_kind(ConflictKind::ShiftReduce), 1);
+    assert_eq!(conflicts.count_of_kind(ConflictKind::ReduceReduce), 1);
+    // both conflicts are raised in the state after `A`
+    let by_state = conflicts.by_state();
+    assert_eq!(by_state.len(), 1);
+    assert_eq!(by_state.values().next().unwrap().len(), 2);
+    // the same conflicts are found from the state
+    let conflicting = *by_state.keys().next().unwrap();
+    for (index, state) in graph.states.iter().enumerate() {
+        assert_eq!(state.has_conflict(index, &conflicts), index == conflicting);
+    }
+    let state = &graph.states[conflicting];
+    assert_eq!(state.get_conflicts(conflicting, &conflicts).count(), 2);
+    assert!(state
+        .get_conflicts(conflicting, &conflicts)
+        .all(|conflict| conflict.state == conflicting));
+}
+
+#[test]
+fn test_expected_conflicts() {
+    let dangling_else = "grammar Test { options { Axiom = \"s\"; Separator = \"WS\"; } terminals { WS -> ' '+; } rules { s -> 'if' s | 'if' s 'else' s | 'x' ; } }";
+    let build = |grammar: &str, expected_conflicts: Option<usize>| {
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(grammar)],
+            expected_conflicts,
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        task.build_grammar(&mut data.grammars[0], 0)
+    };
+    // the conflict is reported by default
+    let errors = build(dangling_else, None).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], Error::LrConflict(0, _)));
+    // the expected conflict is resolved in favor of the shift
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(dangling_else)],
+        expected_conflicts: Some(1),
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let parser = task.generate_in_memory(&mut data.grammars[0], 0).unwrap();
+    let result = parser.parse("if if x else x");
+    assert!(result.is_success());
+    let ast = result.get_ast();
+    let inner = ast.get_root().children().at(1);
+    assert_eq!(inner.children().len(), 4);
+    // any other number of conflicts fails the build
+    for expected in [0, 2] {
+        let errors = build(dangling_else, Some(expected)).unwrap_err();
+        assert!(errors.iter().any(
+            |error| matches!(error, Error::UnexpectedConflictCount(0, e, 1) if *e == expected)
+        ));
+        assert!(errors
+            .iter()
+            .any(|error| matches!(error, Error::LrConflict(0, _))));
+    }
+    // reduce/reduce conflicts are always reported
+    let errors = build(
+        "grammar Test { options { Axiom = \"e\"; } terminals { } rules { e -> x 'b' | y 'b' ; x -> 'a' ; y -> 'a' ; } }",
+        Some(0),
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 1);
+    let Error::LrConflict(0, conflict) = &errors[0] else {
+        panic!("expected a conflict");
+    };
+    assert_eq!(conflict.kind, ConflictKind::ReduceReduce);
+}
+
+#[test]
+fn test_conflicts_kept_apart_across_states() {
+    // both states after `A` and after `C A` have a shift/reduce conflict on `B`
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; } rules { e -> x B | C y B; x -> A | A B; y -> A | A B; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (graph, conflicts) = build_graph_lr1(grammar, axiom);
+    assert_eq!(conflicts.shift_reduce().count(), 2);
+    let conflicts: Vec<&Conflict> = conflicts.iter().collect();
+    assert_ne!(conflicts[0].state, conflicts[1].state);
+    assert_ne!(conflicts[0], conflicts[1]);
+    for conflict in conflicts {
+        // each conflict only holds the items of its own state
+        assert_eq!(conflict.shift_items.len(), 1);
+        assert_eq!(conflict.reduce_items.len(), 1);
+        let state = &graph.states[conflict.state];
+        assert!(state.items.contains(&conflict.reduce_items[0]));
+        assert!(state.items.contains(&conflict.shift_items[0]));
+    }
+}
+
+#[test]
+fn test_conflict_overlap() {
+    // `x -> A •` reduces on `B`, `C` and `D`, but only `B` and `C` can also be shifted
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } terminals { A -> 'a'; B -> 'b'; C -> 'c'; D -> 'd'; } rules { e -> x B | x C | x D | A B | A C; x -> A; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    grammar.prepare(0).unwrap();
+    let axiom = grammar.get_variable_for_name(GENERATED_AXIOM).unwrap().id;
+    let (_, conflicts) = build_graph_lr1(grammar, axiom);
+    let terminal = |name: &str| match grammar.get_symbol(name) {
+        Some(SymbolRef::Terminal(id)) => TerminalRef::Terminal(id),
+        _ => panic!("terminal {name} not found"),
+    };
+    assert_eq!(conflicts.shift_reduce().count(), 2);
+    for conflict in &conflicts {
+        assert_eq!(conflict.overlap.len(), 2);
+        assert!(conflict.overlap.contains(terminal("B")));
+        assert!(conflict.overlap.contains(terminal("C")));
+        assert!(!conflict.overlap.contains(terminal("D")));
+    }
+}
+
+#[test]
+fn test_conflict_contexts() {
+    let conflicts = |rules: &str| {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; }} terminals {{ A -> 'a'; R -> ')'; context interp {{ X -> 'x'; }} }} rules {{ {rules} }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let errors = data.grammars[0].build(None, 0).unwrap_err();
+        errors
+            .iter()
+            .filter(|error| matches!(error, Error::LrConflict(_, _)))
+            .map(|error| error.with_context(&data).to_string())
+            .collect::<Vec<_>>()
+    };
+    // the conflict on `R` happens within the context, even though `R` is also used outside of it
+    assert_eq!(
+        conflicts("e -> R | A #interp { s } ; s -> x R | y R ; x -> X ; y -> X ;"),
+        ["Reduce/Reduce conflict, cannot decide what to do facing `R` in context `interp`"]
+    );
+    // the context is carried through the closure of `s` and the goto on `w`
+    assert_eq!(
+        conflicts("e -> R | A #interp { s } ; s -> w p R | w q R ; w -> X ; p -> ; q -> ;"),
+        ["Reduce/Reduce conflict, cannot decide what to do facing `R` in context `interp`"]
+    );
+    assert_eq!(
+        conflicts("e -> R | A #interp { s } ; s -> X p R | X q R ; p -> ; q -> ;"),
+        ["Reduce/Reduce conflict, cannot decide what to do facing `R` in context `interp`"]
+    );
+    assert_eq!(
+        conflicts("e -> R | A s ; s -> x R | y R ; x -> X ; y -> X ;"),
+        ["Reduce/Reduce conflict, cannot decide what to do facing `R`"]
+    );
+}
+
+#[test]
+fn test_find_ambiguities() {
+    let ambiguities = |rules: &str, max_len: usize| {
+        let content = format!(
+            "grammar Test {{ options {{ Axiom = \"e\"; Method = \"rnglr1\"; }} terminals {{ A -> 'a'; P -> '+'; }} rules {{ {rules} }} }}"
+        );
+        let task = CompilationTask {
+            inputs: vec![Input::Raw(&content)],
+            ..CompilationTask::default()
+        };
+        let mut data = task.load().unwrap();
+        let grammar = &mut data.grammars[0];
+        let graph = grammar.build(None, 0).unwrap().graph;
+        graph
+            .find_ambiguities(grammar, max_len)
+            .iter()
+            .map(|phrase| phrase.display(grammar).to_string())
+            .collect::<Vec<_>>()
+    };
+    // deliberately ambiguous
+    assert_eq!(ambiguities("e -> e P e | A ;", 5), ["A P A P A"]);
+    assert_eq!(ambiguities("e -> e P e | A ;", 4), Vec::<String>::new());
+    assert_eq!(ambiguities("e -> x | y ; x -> A ; y -> A ;", 3), ["A"]);
+    // unambiguous, including with nullable variables at the end of rules
+    assert!(ambiguities("e -> e P A | A ;", 7).is_empty());
+    assert!(ambiguities("e -> A x ; x -> P x | ;", 4).is_empty());
+    // ambiguous through nullable variables
+    assert_eq!(ambiguities("e -> A x x ; x -> P | ;", 4), ["A P"]);
+}
+
+#[test]
+fn test_max_states() {
+    let grammar = "grammar Test { options { Axiom = \"e\"; } terminals { } rules { e -> e x | x ; x -> 'a' y | 'b' y ; y -> 'c' | 'd' e 'f' ; } }";
+    for method in [
+        ParsingMethod::LR1,
+        ParsingMethod::LALR1,
+        ParsingMethod::IELR1,
+    ] {
+        let build = |max_states: Option<usize>| {
+            let task = CompilationTask {
+                inputs: vec![Input::Raw(grammar)],
+                method: Some(method),
+                max_states,
+                ..CompilationTask::default()
+            };
+            let mut data = task.load().unwrap();
+            let result = task.build_grammar(&mut data.grammars[0], 0);
+            (data.grammars.remove(0), result)
+        };
+        let (_, result) = build(None);
+        let count = result.unwrap().graph.states.len();
+        // the limit does not affect a graph that does not exceed it
+        let (_, result) = build(Some(count));
+        assert_eq!(result.unwrap().graph.states.len(), count);
+        let (grammar, result) = build(Some(3));
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let Error::StateLimitExceeded(0, error) = &errors[0] else {
+            panic!("expected the state limit to be exceeded");
+        };
+        assert_eq!(error.limit, 3);
+        assert_eq!(error.method, method);
+        assert!(!error.heads.is_empty());
+        assert!(error.heads.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(error
+            .heads
+            .iter()
+            .all(|(variable, _)| grammar.get_variable(*variable).is_some()));
+    }
+}
+
+#[test]
+fn test_state_kernel_sorted_items() {
+    let item = |variable: usize, index: usize, position: usize| Item {
+        rule: RuleRef::new(variable, index),
+        position,
+        lookaheads: Lookaheads::default(),
+    };
+    let mut first = StateKernel::default();
+    let mut second = StateKernel::default();
+    for (variable, index, position) in [(5, 1, 2), (3, 0, 1), (5, 0, 1), (3, 0, 0)] {
+        first.add_item(item(variable, index, position));
+    }
+    for (variable, index, position) in [(3, 0, 0), (5, 0, 1), (5, 1, 2), (3, 0, 1), (5, 0, 1)] {
+        second.add_item(item(variable, index, position));
+    }
+    // the items are sorted by rule and position, without duplicates
+    let bases: Vec<(usize, usize, usize)> = first
+        .items
+        .iter()
+        .map(|item| (item.rule.variable, item.rule.index, item.position))
+        .collect();
+    assert_eq!(bases, [(3, 0, 0), (3, 0, 1), (5, 0, 1), (5, 1, 2)]);
+    assert_eq!(first, second);
+    second.add_item(item(4, 0, 0));
+    assert_ne!(first, second);
+}
@@ -1,4 +1,5 @@
 use hime_sdk::output::helper::{get_namespace_java, get_namespace_net, get_namespace_rust};
+use hime_sdk::{CompilationTask, Input};
 
 /// [Github issue #79](https://github.com/cenotelie/hime/issues/79)
 #[test]
@@ -10,3 +11,18 @@ fn test_namespace_transformation() {
     assert_eq!(get_namespace_rust("a.b.c"), String::from("a::b::c"));
     assert_eq!(get_namespace_rust("a::b::c"), String::from("a::b::c"));
 }
+
+/// A grammar without terminals has an empty lexer automaton
+#[test]
+fn test_grammar_without_terminals() {
+    let task = CompilationTask {
+        inputs: vec![Input::Raw(
+            "grammar Test { options { Axiom = \"e\"; } rules { e -> ; } }",
+        )],
+        ..CompilationTask::default()
+    };
+    let mut data = task.load().unwrap();
+    let grammar = &mut data.grammars[0];
+    let build = grammar.build(None, 0).unwrap();
+    assert_eq!(build.dfa.len(), 0);
+}
@@ -74,6 +74,8 @@ pub fn parse_fixtures(fixtures: &[FixtureDef]) -> Result<Fixtures, Errors> {
                     .map(|(r, FixtureDef(name, _))| LoadedInput {
                         name: (*name).to_string(),
                         content: r.text,
+                        encoding: None,
+                        includes: Vec::new(),
                     })
                     .collect(),
                 grammars: Vec::new(),
@@ -241,6 +241,8 @@ impl Fixtures {
             .map(|fixture| LoadedInput {
                 name: fixture.name.clone(),
                 content: fixture.content.text.clone(),
+                encoding: None,
+                includes: Vec::new(),
             })
             .collect()
     }
@@ -273,12 +273,18 @@ pub const TERMINALS: &[Symbol] = &[
     },
 ];
 
+/// Gets the lexer's automaton, deserialized on first use
+fn get_lexer_automaton() -> &'static Automaton {
+    static AUTOMATON: std::sync::OnceLock<Automaton> = std::sync::OnceLock::new();
+    AUTOMATON.get_or_init(|| Automaton::new(LEXER_AUTOMATON))
+}
+
 /// Creates a new lexer
 fn new_lexer<'a: 'b, 'b, 'c>(
     repository: TokenRepository<'a, 'b, 'c>,
     errors: &'c mut ParseErrors<'a>,
+    automaton: &'c Automaton,
 ) -> Lexer<'a, 'b, 'c> {
-    let automaton = Automaton::new(LEXER_AUTOMATON);
     Lexer::ContextSensitive(ContextSensitiveLexer::new(
         repository, errors, automaton, 0x0007,
     ))
@@ -287,6 +293,12 @@ fn new_lexer<'a: 'b, 'b, 'c>(
 /// Static resource for the serialized parser automaton
 const PARSER_AUTOMATON: &[u8] = include_bytes!("expected_tree_parser.bin");
 
+/// Gets the parser's automaton, deserialized on first use
+fn get_parser_automaton() -> &'static LRkAutomaton {
+    static AUTOMATON: std::sync::OnceLock<LRkAutomaton> = std::sync::OnceLock::new();
+    AUTOMATON.get_or_init(|| LRkAutomaton::new(PARSER_AUTOMATON))
+}
+
 /// The unique identifier for variable option
 pub const ID_VARIABLE_OPTION: u32 = 0x001F;
 /// The unique identifier for variable `terminal_def_atom`
@@ -701,8 +713,8 @@ fn parse_text_with<'s, 't, 'a>(
     let mut result = ParseResult::<AstImpl>::new(terminals, variables, virtuals, text);
     {
         let data = result.get_parsing_data();
-        let mut lexer = new_lexer(data.0, data.1);
-        let automaton = LRkAutomaton::new(PARSER_AUTOMATON);
+        let mut lexer = new_lexer(data.0, data.1, get_lexer_automaton());
+        let automaton = get_parser_automaton();
         let mut parser = LRkParser::new(
             &mut lexer,
             variables,